@@ -0,0 +1,100 @@
+/* The C API of whitebox-tools. See src/ffi.rs for the ownership of the strings that are returned. */
+
+#ifndef WHITEBOX_TOOLS_H
+#define WHITEBOX_TOOLS_H
+
+/* Generated with cbindgen:0.29.2 */
+
+/* This file is generated by cbindgen from src/ffi.rs; do not edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ The result of running a tool.
+ */
+typedef enum WbtStatus {
+  WBT_STATUS_OK = 0,
+  /*
+   The tool failed, e.g. because a file could not be read.
+   */
+  WBT_STATUS_ERROR = 1,
+  /*
+   The tool name or the arguments are not valid, e.g. a required argument is missing.
+   */
+  WBT_STATUS_INVALID_ARGUMENT = 2,
+  /*
+   The tool or an input file does not exist.
+   */
+  WBT_STATUS_NOT_FOUND = 3,
+  /*
+   The tool was cancelled by the progress callback.
+   */
+  WBT_STATUS_CANCELLED = 4,
+} WbtStatus;
+
+/*
+ Receives the progress of a tool: a label for the step of the tool, e.g. "Performing
+ analysis", the percentage of the step that is complete and the user data given to
+ wbt_run_tool. The tool is cancelled if it returns a non-zero value.
+ */
+typedef int (*WbtProgressCallback)(const char *label, int progress, void *user_data);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Runs a tool, e.g. "elev_percentile", with its arguments, e.g. "-i=DEM.dep", "-o=out.dep"
+ and "--filter=25". File names that are not paths are in the working directory, which may
+ be null. The progress of the tool is given to the callback, if it is not null, and is not
+ printed; the tool runs in non-verbose mode. The message of an error is retrieved with
+ wbt_last_error.
+ */
+enum WbtStatus wbt_run_tool(const char *tool_name,
+                            const char *const *args,
+                            size_t num_args,
+                            const char *working_directory,
+                            WbtProgressCallback callback,
+                            void *user_data);
+
+/*
+ Returns the message of the last error of the calling thread, or an empty string. It is
+ valid until the next call to the API on the thread.
+ */
+const char *wbt_last_error(void);
+
+/*
+ Returns the help of a tool, i.e. its description, parameters and example usage, or null
+ if the tool does not exist.
+ */
+char *wbt_tool_help(const char *tool_name);
+
+/*
+ Returns a JSON description of a tool and its parameters, or null if the tool does not exist.
+ */
+char *wbt_tool_parameters(const char *tool_name);
+
+/*
+ Returns a JSON description of all of the tools and their parameters.
+ */
+char *wbt_list_tools(void);
+
+/*
+ Frees a string that was returned by the API. Null is ignored.
+ */
+void wbt_free_string(char *s);
+
+/*
+ Returns the version of the library, e.g. "0.1.1".
+ */
+const char *wbt_version(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* WHITEBOX_TOOLS_H */
@@ -0,0 +1,327 @@
+/*
+The C API, which is exported by the cdylib (libwhitebox_tools.so, libwhitebox_tools.dylib or
+whitebox_tools.dll) and declared in include/whitebox_tools.h. The header is generated from this
+file with cbindgen, i.e. 'cbindgen --config cbindgen.toml --output include/whitebox_tools.h'
+run in the crate directory, and is regenerated whenever the API changes.
+
+Strings are UTF-8 and null terminated. The strings returned by wbt_tool_help,
+wbt_tool_parameters and wbt_list_tools are owned by the caller and are freed with
+wbt_free_string. The functions may be called from several threads at once; the progress
+callback of a tool and its last error are those of the thread that it was run on.
+*/
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::io::{Error, ErrorKind};
+use std::os::raw::{c_char, c_int, c_void};
+use std::panic;
+use std::ptr;
+use crate::tools::ToolManager;
+use crate::tools::progress::set_progress_handler;
+
+/// The result of running a tool.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WbtStatus {
+    Ok = 0,
+    /// The tool failed, e.g. because a file could not be read.
+    Error = 1,
+    /// The tool name or the arguments are not valid, e.g. a required argument is missing.
+    InvalidArgument = 2,
+    /// The tool or an input file does not exist.
+    NotFound = 3,
+    /// The tool was cancelled by the progress callback.
+    Cancelled = 4,
+}
+
+/// Receives the progress of a tool: a label for the step of the tool, e.g. "Performing
+/// analysis", the percentage of the step that is complete and the user data given to
+/// wbt_run_tool. The tool is cancelled if it returns a non-zero value.
+pub type WbtProgressCallback = Option<extern "C" fn(label: *const c_char, progress: c_int, user_data: *mut c_void) -> c_int>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace("\0", "")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = message);
+}
+
+fn get_status(err: &Error) -> WbtStatus {
+    match err.kind() {
+        ErrorKind::Interrupted => WbtStatus::Cancelled,
+        ErrorKind::InvalidInput => WbtStatus::InvalidArgument,
+        ErrorKind::NotFound => WbtStatus::NotFound,
+        _ => WbtStatus::Error,
+    }
+}
+
+/// Returns the string of a pointer given to the API, or an error if it is null or not UTF-8.
+unsafe fn to_string(s: *const c_char, name: &str) -> Result<String, Error> {
+    if s.is_null() {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("The {} is null.", name)));
+    }
+    match CStr::from_ptr(s).to_str() {
+        Ok(s) => Ok(s.to_string()),
+        Err(_) => Err(Error::new(ErrorKind::InvalidInput, format!("The {} is not UTF-8.", name))),
+    }
+}
+
+/// Returns a string that is owned by the caller, or null with the last error set.
+fn to_owned_c_string(result: Result<String, Error>) -> *mut c_char {
+    match result {
+        Ok(s) => match CString::new(s) {
+            Ok(s) => s.into_raw(),
+            Err(_) => {
+                set_last_error("The string contains a null character.");
+                ptr::null_mut()
+            },
+        },
+        Err(err) => {
+            set_last_error(&err.to_string());
+            ptr::null_mut()
+        },
+    }
+}
+
+/// Runs a tool, e.g. "elev_percentile", with its arguments, e.g. "-i=DEM.dep", "-o=out.dep"
+/// and "--filter=25". File names that are not paths are in the working directory, which may
+/// be null. The progress of the tool is given to the callback, if it is not null, and is not
+/// printed; the tool runs in non-verbose mode. The message of an error is retrieved with
+/// wbt_last_error.
+///
+/// # Safety
+///
+/// `tool_name` and `working_directory` must be null or nul-terminated strings, and `args`
+/// must point to `num_args` nul-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn wbt_run_tool(tool_name: *const c_char, args: *const *const c_char, num_args: usize,
+    working_directory: *const c_char, callback: WbtProgressCallback, user_data: *mut c_void) -> WbtStatus {
+
+    let tool_name = match to_string(tool_name, "tool name") {
+        Ok(s) => s,
+        Err(err) => { set_last_error(&err.to_string()); return get_status(&err); },
+    };
+    let working_directory = if working_directory.is_null() {
+        String::new()
+    } else {
+        match to_string(working_directory, "working directory") {
+            Ok(s) => s,
+            Err(err) => { set_last_error(&err.to_string()); return get_status(&err); },
+        }
+    };
+    if args.is_null() && num_args > 0 {
+        set_last_error("The arguments are null.");
+        return WbtStatus::InvalidArgument;
+    }
+    let mut tool_args = Vec::with_capacity(num_args);
+    for i in 0..num_args {
+        match to_string(*args.add(i), "argument") {
+            Ok(s) => tool_args.push(s),
+            Err(err) => { set_last_error(&err.to_string()); return get_status(&err); },
+        }
+    }
+
+    if let Some(callback) = callback {
+        let user_data = user_data as usize;
+        set_progress_handler(Some(Box::new(move |label: &str, progress: usize| {
+            let label = CString::new(label).unwrap_or_default();
+            callback(label.as_ptr(), progress as c_int, user_data as *mut c_void) == 0
+        })));
+    }
+    let tm = ToolManager { working_dir: working_directory, verbose: false };
+    // a panic must not unwind into the caller
+    let result = panic::catch_unwind(|| tm.run_tool(tool_name, tool_args));
+    set_progress_handler(None);
+
+    match result {
+        Ok(Ok(())) => {
+            set_last_error("");
+            WbtStatus::Ok
+        },
+        Ok(Err(err)) => {
+            set_last_error(&err.to_string());
+            get_status(&err)
+        },
+        Err(_) => {
+            set_last_error("The tool failed unexpectedly.");
+            WbtStatus::Error
+        },
+    }
+}
+
+/// Returns the message of the last error of the calling thread, or an empty string. It is
+/// valid until the next call to the API on the thread.
+#[no_mangle]
+pub extern "C" fn wbt_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| e.borrow().as_ptr())
+}
+
+/// Returns the help of a tool, i.e. its description, parameters and example usage, or null
+/// if the tool does not exist.
+///
+/// # Safety
+///
+/// `tool_name` must be null or a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn wbt_tool_help(tool_name: *const c_char) -> *mut c_char {
+    to_owned_c_string(to_string(tool_name, "tool name").and_then(|name| ToolManager::default().get_tool_help(&name)))
+}
+
+/// Returns a JSON description of a tool and its parameters, or null if the tool does not exist.
+///
+/// # Safety
+///
+/// `tool_name` must be null or a nul-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn wbt_tool_parameters(tool_name: *const c_char) -> *mut c_char {
+    to_owned_c_string(to_string(tool_name, "tool name").and_then(|name| ToolManager::default().get_tool_parameters_json(&name)))
+}
+
+/// Returns a JSON description of all of the tools and their parameters.
+#[no_mangle]
+pub extern "C" fn wbt_list_tools() -> *mut c_char {
+    to_owned_c_string(Ok(ToolManager::default().get_tools_json()))
+}
+
+/// Frees a string that was returned by the API. Null is ignored.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by the API that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn wbt_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Returns the version of the library, e.g. "0.1.1".
+#[no_mangle]
+pub extern "C" fn wbt_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+    use std::fs;
+    use std::os::raw::{c_char, c_int, c_void};
+    use std::ptr;
+    use crate::io_utils::temp_file_name;
+    use crate::raster::*;
+    use super::*;
+
+    fn last_error() -> String {
+        unsafe { CStr::from_ptr(wbt_last_error()).to_str().unwrap().to_string() }
+    }
+
+    fn create_dem(file_name: &str) {
+        let mut configs = RasterConfigs::default();
+        configs.rows = 20;
+        configs.columns = 20;
+        configs.north = 20.0;
+        configs.east = 20.0;
+        configs.resolution_x = 1.0;
+        configs.resolution_y = 1.0;
+        configs.nodata = -9999.0;
+        configs.data_type = DataType::F32;
+        let mut dem = Raster::initialize_using_config(file_name, &configs);
+        for row in 0..20 {
+            for col in 0..20 {
+                dem.set_value(row, col, (row * col % 7) as f64);
+            }
+        }
+        dem.write().unwrap();
+    }
+
+    fn remove_raster(file_name: &str) {
+        let _ = fs::remove_file(file_name);
+        let _ = fs::remove_file(file_name.replace(".dep", ".tas"));
+    }
+
+    /// Runs elev_percentile on a DEM, which is created and removed, and returns the status.
+    fn run_elev_percentile(name: &str, callback: WbtProgressCallback, user_data: *mut c_void) -> WbtStatus {
+        let input_file = temp_file_name(&format!("{}_dem.dep", name));
+        let output_file = temp_file_name(&format!("{}_out.dep", name));
+        create_dem(&input_file);
+        let args: Vec<CString> = vec![format!("-i={}", input_file), format!("-o={}", output_file), "--filter=3".to_string()]
+            .into_iter().map(|a| CString::new(a).unwrap()).collect();
+        let arg_ptrs: Vec<*const c_char> = args.iter().map(|a| a.as_ptr()).collect();
+        let tool_name = CString::new("elev_percentile").unwrap();
+        let status = unsafe { wbt_run_tool(tool_name.as_ptr(), arg_ptrs.as_ptr(), arg_ptrs.len(), ptr::null(), callback, user_data) };
+        remove_raster(&input_file);
+        remove_raster(&output_file);
+        status
+    }
+
+    extern "C" fn count_progress(label: *const c_char, progress: c_int, user_data: *mut c_void) -> c_int {
+        assert!(!unsafe { CStr::from_ptr(label) }.to_bytes().is_empty());
+        assert!((0..=100).contains(&progress));
+        unsafe { *(user_data as *mut usize) += 1; }
+        0
+    }
+
+    extern "C" fn cancel(_label: *const c_char, _progress: c_int, _user_data: *mut c_void) -> c_int {
+        1
+    }
+
+    #[test]
+    fn progress_is_given_to_the_callback() {
+        let mut count = 0usize;
+        let status = run_elev_percentile("progress", Some(count_progress), &mut count as *mut usize as *mut c_void);
+        assert_eq!(status, WbtStatus::Ok, "{}", last_error());
+        assert!(count > 0);
+        assert_eq!(last_error(), "");
+    }
+
+    #[test]
+    fn callback_cancels_the_tool() {
+        let status = run_elev_percentile("cancel", Some(cancel), ptr::null_mut());
+        assert_eq!(status, WbtStatus::Cancelled);
+        assert_eq!(last_error(), "The tool was cancelled.");
+    }
+
+    #[test]
+    fn invalid_tool_names_and_arguments() {
+        unsafe {
+            assert_eq!(wbt_run_tool(ptr::null(), ptr::null(), 0, ptr::null(), None, ptr::null_mut()), WbtStatus::InvalidArgument);
+            assert_eq!(last_error(), "The tool name is null.");
+
+            let tool_name = CString::new("no_such_tool").unwrap();
+            assert_eq!(wbt_run_tool(tool_name.as_ptr(), ptr::null(), 0, ptr::null(), None, ptr::null_mut()), WbtStatus::NotFound);
+            assert!(last_error().contains("no_such_tool"));
+
+            let tool_name = CString::new("elev_percentile").unwrap();
+            assert_eq!(wbt_run_tool(tool_name.as_ptr(), ptr::null(), 1, ptr::null(), None, ptr::null_mut()), WbtStatus::InvalidArgument);
+            assert_eq!(last_error(), "The arguments are null.");
+            assert_eq!(wbt_run_tool(tool_name.as_ptr(), ptr::null(), 0, ptr::null(), None, ptr::null_mut()), WbtStatus::InvalidArgument);
+        }
+    }
+
+    #[test]
+    fn strings_returned_by_the_api() {
+        unsafe {
+            let tool_name = CString::new("elev_percentile").unwrap();
+            let json = wbt_tool_parameters(tool_name.as_ptr());
+            assert!(!json.is_null());
+            assert!(CStr::from_ptr(json).to_str().unwrap().contains("--filter"));
+            wbt_free_string(json);
+
+            let help = wbt_tool_help(tool_name.as_ptr());
+            assert!(!help.is_null());
+            wbt_free_string(help);
+
+            let tools = wbt_list_tools();
+            assert!(CStr::from_ptr(tools).to_str().unwrap().contains("elev_percentile"));
+            wbt_free_string(tools);
+
+            let tool_name = CString::new("no_such_tool").unwrap();
+            assert!(wbt_tool_parameters(tool_name.as_ptr()).is_null());
+            assert!(last_error().contains("no_such_tool"));
+            wbt_free_string(ptr::null_mut());
+
+            assert_eq!(CStr::from_ptr(wbt_version()).to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+        }
+    }
+}
@@ -1,5 +1,7 @@
 // extern crate byteorder;
 
+use std::convert::Infallible;
+use std::str::FromStr;
 use byteorder::{ByteOrder, LittleEndian, BigEndian};
 
 pub struct ByteOrderReader {
@@ -10,7 +12,7 @@ pub struct ByteOrderReader {
 
 impl ByteOrderReader {
     pub fn new(buffer: Vec<u8>, byte_order: Endianness) -> ByteOrderReader {
-        ByteOrderReader { buffer: buffer, byte_order: byte_order, pos: 0usize }
+        ByteOrderReader { buffer, byte_order, pos: 0usize }
     }
 
     pub fn seek(&mut self, position: usize) {
@@ -21,6 +23,10 @@ impl ByteOrderReader {
         self.buffer.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty()
+    }
+
     pub fn read_u8(&mut self) -> u8 {
         // There's really no need for endian issues when reading single bytes.
         let val = self.buffer[self.pos];
@@ -116,23 +122,23 @@ impl ByteOrderReader {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub enum Endianness {
+    #[default]
     LittleEndian,
     BigEndian,
 }
 
-impl Default for Endianness {
-    fn default() -> Endianness { Endianness::LittleEndian }
-}
 
-impl Endianness {
-    pub fn from_str<'a>(val: &'a str) -> Endianness {
+impl FromStr for Endianness {
+    type Err = Infallible;
+
+    fn from_str(val: &str) -> Result<Endianness, Infallible> {
         let val_lc: &str = &val.to_lowercase();
         if val_lc.contains("lsb") || val_lc.contains("little") || val_lc.contains("intel") {
-            return Endianness::LittleEndian;
+            Ok(Endianness::LittleEndian)
         } else {
-            return Endianness::BigEndian;
+            Ok(Endianness::BigEndian)
         }
     }
 }
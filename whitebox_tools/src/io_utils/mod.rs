@@ -1,3 +1,37 @@
 // extern crate byteorder;
 
 pub mod byte_order_reader;
+
+#[cfg(test)]
+use std::env;
+use std::io::{self, ErrorKind, Read};
+#[cfg(test)]
+use std::process;
+#[cfg(test)]
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Reads until the buffer is full or the end of the reader is reached, returning the number
+/// of bytes read. Unlike `read_exact`, reaching the end is not an error, which suits data
+/// that are read in chunks of a fixed size, of which the last may be partial.
+pub fn read_fully<R: Read>(reader: &mut R, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buffer.len() {
+        match reader.read(&mut buffer[n..]) {
+            Ok(0) => break,
+            Ok(k) => n += k,
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(n)
+}
+
+/// Returns a path in the temporary directory for a file written by a test. The process id
+/// and a counter are prefixed to the name, so that concurrent tests never share a file,
+/// while the extension, which determines the file format, is kept.
+#[cfg(test)]
+pub fn temp_file_name(name: &str) -> String {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::SeqCst);
+    env::temp_dir().join(format!("whitebox_{}_{}_{}", process::id(), n, name)).to_string_lossy().to_string()
+}
@@ -0,0 +1,373 @@
+use std::fmt;
+use byteorder::{ByteOrder, LittleEndian};
+use crate::lidar::vlr::Vlr;
+
+/// The length of an extra bytes descriptor within the extra bytes VLR.
+const DESCRIPTOR_LENGTH: usize = 192;
+
+/// The data type of an extra bytes attribute.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtraBytesDataType {
+    /// Bytes that are not described any further; the value is the number of bytes.
+    Undocumented(u8),
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+    /// One of the two- and three-element array types (codes 11-30) that are deprecated
+    /// in LAS 1.4 R13; the value is the type code. Their values cannot be accessed.
+    Deprecated(u8),
+}
+
+impl ExtraBytesDataType {
+    /// Returns the data type with a type code. The options field of the descriptor holds
+    /// the number of bytes of an undocumented (code 0) attribute.
+    pub fn from_code(code: u8, options: u8) -> Option<ExtraBytesDataType> {
+        let t = match code {
+            0 => ExtraBytesDataType::Undocumented(options),
+            1 => ExtraBytesDataType::U8,
+            2 => ExtraBytesDataType::I8,
+            3 => ExtraBytesDataType::U16,
+            4 => ExtraBytesDataType::I16,
+            5 => ExtraBytesDataType::U32,
+            6 => ExtraBytesDataType::I32,
+            7 => ExtraBytesDataType::U64,
+            8 => ExtraBytesDataType::I64,
+            9 => ExtraBytesDataType::F32,
+            10 => ExtraBytesDataType::F64,
+            11..=30 => ExtraBytesDataType::Deprecated(code),
+            _ => return None,
+        };
+        Some(t)
+    }
+
+    pub fn code(&self) -> u8 {
+        match *self {
+            ExtraBytesDataType::Undocumented(_) => 0,
+            ExtraBytesDataType::U8 => 1,
+            ExtraBytesDataType::I8 => 2,
+            ExtraBytesDataType::U16 => 3,
+            ExtraBytesDataType::I16 => 4,
+            ExtraBytesDataType::U32 => 5,
+            ExtraBytesDataType::I32 => 6,
+            ExtraBytesDataType::U64 => 7,
+            ExtraBytesDataType::I64 => 8,
+            ExtraBytesDataType::F32 => 9,
+            ExtraBytesDataType::F64 => 10,
+            ExtraBytesDataType::Deprecated(code) => code,
+        }
+    }
+
+    /// Returns the number of bytes that the attribute occupies in each point record.
+    pub fn size(&self) -> usize {
+        match *self {
+            ExtraBytesDataType::Undocumented(n) => n as usize,
+            ExtraBytesDataType::U8 | ExtraBytesDataType::I8 => 1,
+            ExtraBytesDataType::U16 | ExtraBytesDataType::I16 => 2,
+            ExtraBytesDataType::U32 | ExtraBytesDataType::I32 | ExtraBytesDataType::F32 => 4,
+            ExtraBytesDataType::U64 | ExtraBytesDataType::I64 | ExtraBytesDataType::F64 => 8,
+            ExtraBytesDataType::Deprecated(code) => {
+                // codes 11-20 are two-element arrays and 21-30 three-element arrays of
+                // the types 1-10
+                let element = ExtraBytesDataType::from_code((code - 11) % 10 + 1, 0).unwrap();
+                let count = if code <= 20 { 2 } else { 3 };
+                element.size() * count
+            },
+        }
+    }
+
+    fn is_signed(&self) -> bool {
+        matches!(*self, ExtraBytesDataType::I8 | ExtraBytesDataType::I16 | ExtraBytesDataType::I32 | ExtraBytesDataType::I64)
+    }
+
+    fn is_float(&self) -> bool {
+        matches!(*self, ExtraBytesDataType::F32 | ExtraBytesDataType::F64)
+    }
+
+    /// Decodes a value of this type, or returns `None` if the type has no accessible values.
+    pub fn decode(&self, bytes: &[u8]) -> Option<ExtraBytesValue> {
+        let v = match *self {
+            ExtraBytesDataType::U8 => ExtraBytesValue::U8(bytes[0]),
+            ExtraBytesDataType::I8 => ExtraBytesValue::I8(bytes[0] as i8),
+            ExtraBytesDataType::U16 => ExtraBytesValue::U16(LittleEndian::read_u16(bytes)),
+            ExtraBytesDataType::I16 => ExtraBytesValue::I16(LittleEndian::read_i16(bytes)),
+            ExtraBytesDataType::U32 => ExtraBytesValue::U32(LittleEndian::read_u32(bytes)),
+            ExtraBytesDataType::I32 => ExtraBytesValue::I32(LittleEndian::read_i32(bytes)),
+            ExtraBytesDataType::U64 => ExtraBytesValue::U64(LittleEndian::read_u64(bytes)),
+            ExtraBytesDataType::I64 => ExtraBytesValue::I64(LittleEndian::read_i64(bytes)),
+            ExtraBytesDataType::F32 => ExtraBytesValue::F32(LittleEndian::read_f32(bytes)),
+            ExtraBytesDataType::F64 => ExtraBytesValue::F64(LittleEndian::read_f64(bytes)),
+            _ => return None,
+        };
+        Some(v)
+    }
+
+    /// Converts a number to a value of this type, rounding it if the type is an integer
+    /// type, or returns `None` if the type has no accessible values.
+    pub fn value_from_f64(&self, value: f64) -> Option<ExtraBytesValue> {
+        let r = value.round();
+        let v = match *self {
+            ExtraBytesDataType::U8 => ExtraBytesValue::U8(r as u8),
+            ExtraBytesDataType::I8 => ExtraBytesValue::I8(r as i8),
+            ExtraBytesDataType::U16 => ExtraBytesValue::U16(r as u16),
+            ExtraBytesDataType::I16 => ExtraBytesValue::I16(r as i16),
+            ExtraBytesDataType::U32 => ExtraBytesValue::U32(r as u32),
+            ExtraBytesDataType::I32 => ExtraBytesValue::I32(r as i32),
+            ExtraBytesDataType::U64 => ExtraBytesValue::U64(r as u64),
+            ExtraBytesDataType::I64 => ExtraBytesValue::I64(r as i64),
+            ExtraBytesDataType::F32 => ExtraBytesValue::F32(value as f32),
+            ExtraBytesDataType::F64 => ExtraBytesValue::F64(value),
+            _ => return None,
+        };
+        Some(v)
+    }
+}
+
+impl fmt::Display for ExtraBytesDataType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ExtraBytesDataType::Undocumented(n) => write!(f, "{} undocumented bytes", n),
+            ExtraBytesDataType::U8 => write!(f, "u8"),
+            ExtraBytesDataType::I8 => write!(f, "i8"),
+            ExtraBytesDataType::U16 => write!(f, "u16"),
+            ExtraBytesDataType::I16 => write!(f, "i16"),
+            ExtraBytesDataType::U32 => write!(f, "u32"),
+            ExtraBytesDataType::I32 => write!(f, "i32"),
+            ExtraBytesDataType::U64 => write!(f, "u64"),
+            ExtraBytesDataType::I64 => write!(f, "i64"),
+            ExtraBytesDataType::F32 => write!(f, "f32"),
+            ExtraBytesDataType::F64 => write!(f, "f64"),
+            ExtraBytesDataType::Deprecated(code) => write!(f, "deprecated array type {}", code),
+        }
+    }
+}
+
+/// The stored value of an extra bytes attribute of a point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtraBytesValue {
+    U8(u8),
+    I8(i8),
+    U16(u16),
+    I16(i16),
+    U32(u32),
+    I32(i32),
+    U64(u64),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+}
+
+impl ExtraBytesValue {
+    pub fn data_type(&self) -> ExtraBytesDataType {
+        match *self {
+            ExtraBytesValue::U8(_) => ExtraBytesDataType::U8,
+            ExtraBytesValue::I8(_) => ExtraBytesDataType::I8,
+            ExtraBytesValue::U16(_) => ExtraBytesDataType::U16,
+            ExtraBytesValue::I16(_) => ExtraBytesDataType::I16,
+            ExtraBytesValue::U32(_) => ExtraBytesDataType::U32,
+            ExtraBytesValue::I32(_) => ExtraBytesDataType::I32,
+            ExtraBytesValue::U64(_) => ExtraBytesDataType::U64,
+            ExtraBytesValue::I64(_) => ExtraBytesDataType::I64,
+            ExtraBytesValue::F32(_) => ExtraBytesDataType::F32,
+            ExtraBytesValue::F64(_) => ExtraBytesDataType::F64,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match *self {
+            ExtraBytesValue::U8(v) => v as f64,
+            ExtraBytesValue::I8(v) => v as f64,
+            ExtraBytesValue::U16(v) => v as f64,
+            ExtraBytesValue::I16(v) => v as f64,
+            ExtraBytesValue::U32(v) => v as f64,
+            ExtraBytesValue::I32(v) => v as f64,
+            ExtraBytesValue::U64(v) => v as f64,
+            ExtraBytesValue::I64(v) => v as f64,
+            ExtraBytesValue::F32(v) => v as f64,
+            ExtraBytesValue::F64(v) => v,
+        }
+    }
+
+    /// Writes the value into `bytes`, which must be at least as long as the value's type.
+    pub fn encode(&self, bytes: &mut [u8]) {
+        match *self {
+            ExtraBytesValue::U8(v) => bytes[0] = v,
+            ExtraBytesValue::I8(v) => bytes[0] = v as u8,
+            ExtraBytesValue::U16(v) => LittleEndian::write_u16(bytes, v),
+            ExtraBytesValue::I16(v) => LittleEndian::write_i16(bytes, v),
+            ExtraBytesValue::U32(v) => LittleEndian::write_u32(bytes, v),
+            ExtraBytesValue::I32(v) => LittleEndian::write_i32(bytes, v),
+            ExtraBytesValue::U64(v) => LittleEndian::write_u64(bytes, v),
+            ExtraBytesValue::I64(v) => LittleEndian::write_i64(bytes, v),
+            ExtraBytesValue::F32(v) => LittleEndian::write_f32(bytes, v),
+            ExtraBytesValue::F64(v) => LittleEndian::write_f64(bytes, v),
+        }
+    }
+}
+
+/// Describes an extra bytes attribute, i.e. a named value that is stored in each point
+/// record after the fields of the point format, as listed in the extra bytes VLR
+/// (LASF_Spec, record 4). The no-data, minimum and maximum values are stored values,
+/// i.e. before the scale and offset are applied.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExtraBytesDescriptor {
+    pub data_type: ExtraBytesDataType,
+    pub name: String,
+    pub description: String,
+    pub no_data: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub scale: Option<f64>,
+    pub offset: Option<f64>,
+}
+
+impl ExtraBytesDescriptor {
+    /// Creates a descriptor; names are limited to 32 bytes and must be unique within a file.
+    pub fn new<'a>(name: &'a str, data_type: ExtraBytesDataType, description: &'a str) -> ExtraBytesDescriptor {
+        ExtraBytesDescriptor {
+            data_type,
+            name: name.to_string(),
+            description: description.to_string(),
+            no_data: None,
+            min: None,
+            max: None,
+            scale: None,
+            offset: None,
+        }
+    }
+
+    /// Returns the number of bytes that the attribute occupies in each point record.
+    pub fn size(&self) -> usize {
+        self.data_type.size()
+    }
+
+    /// Converts a stored value into the attribute's units by applying its scale and offset.
+    pub fn apply_scale_and_offset(&self, value: f64) -> f64 {
+        value * self.scale.unwrap_or(1f64) + self.offset.unwrap_or(0f64)
+    }
+
+    /// Converts a value in the attribute's units to a stored value, i.e. the inverse of
+    /// `apply_scale_and_offset`.
+    pub fn remove_scale_and_offset(&self, value: f64) -> f64 {
+        (value - self.offset.unwrap_or(0f64)) / self.scale.unwrap_or(1f64)
+    }
+
+    /// Decodes a 192-byte descriptor of the extra bytes VLR; `None` if the type is unknown.
+    pub fn from_bytes(bytes: &[u8]) -> Option<ExtraBytesDescriptor> {
+        let options = bytes[3];
+        let data_type = ExtraBytesDataType::from_code(bytes[2], options)?;
+        let field = |offset: usize, bit: u8| -> Option<f64> {
+            if let ExtraBytesDataType::Undocumented(_) = data_type {
+                // the options hold the size rather than the flags
+                return None;
+            }
+            if options & bit == 0 {
+                return None;
+            }
+            let b = &bytes[offset..offset + 8];
+            Some(if data_type.is_float() || bit >= 8 {
+                LittleEndian::read_f64(b)
+            } else if data_type.is_signed() {
+                LittleEndian::read_i64(b) as f64
+            } else {
+                LittleEndian::read_u64(b) as f64
+            })
+        };
+        Some(ExtraBytesDescriptor {
+            data_type,
+            name: fixed_string(&bytes[4..36]),
+            description: fixed_string(&bytes[160..192]),
+            no_data: field(40, 0b0_0001),
+            min: field(64, 0b0_0010),
+            max: field(88, 0b0_0100),
+            scale: field(112, 0b0_1000),
+            offset: field(136, 0b1_0000),
+        })
+    }
+
+    /// Encodes the descriptor as a 192-byte descriptor of the extra bytes VLR.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; DESCRIPTOR_LENGTH];
+        bytes[2] = self.data_type.code();
+        let mut options = 0u8;
+        if let ExtraBytesDataType::Undocumented(n) = self.data_type {
+            options = n;
+        } else {
+            let fields = [(self.no_data, 40, 0b0_0001u8), (self.min, 64, 0b0_0010u8), (self.max, 88, 0b0_0100u8),
+                          (self.scale, 112, 0b0_1000u8), (self.offset, 136, 0b1_0000u8)];
+            for &(value, offset, bit) in &fields {
+                if let Some(v) = value {
+                    options |= bit;
+                    let b = &mut bytes[offset..offset + 8];
+                    if self.data_type.is_float() || bit >= 8 {
+                        LittleEndian::write_f64(b, v);
+                    } else if self.data_type.is_signed() {
+                        LittleEndian::write_i64(b, v as i64);
+                    } else {
+                        LittleEndian::write_u64(b, v as u64);
+                    }
+                }
+            }
+        }
+        bytes[3] = options;
+        copy_fixed_string(&self.name, &mut bytes[4..36]);
+        copy_fixed_string(&self.description, &mut bytes[160..192]);
+        bytes
+    }
+}
+
+impl fmt::Display for ExtraBytesDescriptor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut s = format!("{} ({})", self.name, self.data_type);
+        if let Some(v) = self.scale { s = s + &format!(", scale: {}", v); }
+        if let Some(v) = self.offset { s = s + &format!(", offset: {}", v); }
+        if let Some(v) = self.no_data { s = s + &format!(", no data: {}", v); }
+        if !self.description.is_empty() { s = s + &format!(", {}", self.description); }
+        write!(f, "{}", s)
+    }
+}
+
+/// Returns the descriptors held by an extra bytes VLR or EVLR. Descriptors of unknown
+/// types end the list, since the layout of the attributes that follow them is unknown.
+pub fn descriptors_from_vlr(vlr: &Vlr) -> Vec<ExtraBytesDescriptor> {
+    let mut descriptors = vec![];
+    for bytes in vlr.binary_data.chunks(DESCRIPTOR_LENGTH) {
+        if bytes.len() < DESCRIPTOR_LENGTH {
+            break;
+        }
+        match ExtraBytesDescriptor::from_bytes(bytes) {
+            Some(d) => descriptors.push(d),
+            None => break,
+        }
+    }
+    descriptors
+}
+
+/// Creates the extra bytes VLR that describes a list of attributes.
+pub fn extra_bytes_vlr(descriptors: &[ExtraBytesDescriptor]) -> Vlr {
+    let mut vlr = Vlr::default();
+    vlr.user_id = "LASF_Spec".to_string();
+    vlr.record_id = 4;
+    vlr.description = "Extra bytes".to_string();
+    for d in descriptors {
+        vlr.binary_data.extend(d.to_bytes());
+    }
+    vlr.record_length_after_header = vlr.binary_data.len() as u16;
+    vlr
+}
+
+fn fixed_string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_matches(char::from(0)).trim().to_string()
+}
+
+fn copy_fixed_string(s: &str, bytes: &mut [u8]) {
+    let b = s.as_bytes();
+    let len = if b.len() < bytes.len() { b.len() } else { bytes.len() };
+    bytes[0..len].copy_from_slice(&b[0..len]);
+}
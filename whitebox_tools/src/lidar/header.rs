@@ -1,5 +1,5 @@
 use std::fmt;
-use lidar::las::GlobalEncodingField;
+use crate::lidar::las::GlobalEncodingField;
 
 #[derive(Default, Clone, Debug)]
 pub struct LasHeader {
@@ -22,8 +22,8 @@ pub struct LasHeader {
     pub number_of_vlrs: u32,
     pub point_format: u8,
     pub point_record_length: u16,
-    pub number_of_points: u32,
-    pub number_of_points_by_return: [u32; 5],
+    pub number_of_points: u64,
+    pub number_of_points_by_return: [u64; 15],
     pub x_scale_factor: f64,
     pub y_scale_factor: f64,
     pub z_scale_factor: f64,
@@ -37,6 +37,8 @@ pub struct LasHeader {
     pub max_z: f64,
     pub min_z: f64,
     pub waveform_data_start: u64,
+    pub start_of_first_evlr: u64,
+    pub number_of_evlrs: u32,
 }
 
 impl fmt::Display for LasHeader {
@@ -60,9 +62,11 @@ impl fmt::Display for LasHeader {
         s = s + &format!("\nPoint Format: {}", self.point_format);
         s = s + &format!("\nPoint Record Length: {}", self.point_record_length);
         s = s + &format!("\nNum. of Points: {}", self.number_of_points);
-        s = s + &"\nNumber of Points by Return: [";
-        for i in 0..self.number_of_points_by_return.len() {
-            if i < self.number_of_points_by_return.len()-1 {
+        s += "\nNumber of Points by Return: [";
+        // Versions prior to LAS 1.4 only record the first five returns.
+        let num_returns = if self.version_major == 1 && self.version_minor < 4 { 5 } else { 15 };
+        for i in 0..num_returns {
+            if i < num_returns-1 {
                 s = s + &format!("{}, ", self.number_of_points_by_return[i]);
             } else {
                 s = s + &format!("{}]", self.number_of_points_by_return[i]);
@@ -83,6 +87,10 @@ impl fmt::Display for LasHeader {
         s = s + &format!("\nMin Z: {}", self.min_z);
 
         s = s + &format!("\nWaveform Data Start: {}", self.waveform_data_start);
+        if self.version_major == 1 && self.version_minor >= 4 {
+            s = s + &format!("\nStart of First EVLR: {}", self.start_of_first_evlr);
+            s = s + &format!("\nNumber of EVLRs: {}", self.number_of_evlrs);
+        }
 
         write!(f, "{}", s)
     }
@@ -5,16 +5,22 @@ use std::io::prelude::*;
 use std::io::{Error, ErrorKind};
 use std::fmt;
 use std::f64;
-use std::io::BufWriter;
-use std::fs::File;
+use std::io::{BufReader, BufWriter, SeekFrom};
 use std::fs;
-use std::mem;
+use std::fs::File;
+use std::cmp;
 use std::str;
-use lidar::header::LasHeader;
-use lidar::point_data::{ ClassificationBitField, PointBitField, PointData, RgbData, WaveformPacket };
-use lidar::vlr::Vlr;
-use raster::geotiff::geokeys::GeoKeys;
+use crate::lidar::extra_bytes::{ descriptors_from_vlr, extra_bytes_vlr, ExtraBytesDataType, ExtraBytesDescriptor, ExtraBytesValue };
+use crate::lidar::header::LasHeader;
+use crate::lidar::las_index::{ index_file_name, point_in_polygon, LasIndex };
+use crate::lidar::las_reader::LasReader;
+use crate::lidar::point_data::{ ClassificationBitField, PointBitField, PointData, RgbData, WaveformPacket };
+use crate::lidar::vlr::Vlr;
+use crate::raster::geotiff::geokeys::GeoKeys;
+use crate::spatial_ref_system::SpatialReference;
 use std::ops::Index;
+use byteorder::{ByteOrder, LittleEndian};
+use laz::{LasZipCompressor, LasZipDecompressor, LazItemRecordBuilder, LazVlr};
 
 #[derive(Default, Clone)]
 pub struct LasFile {
@@ -22,22 +28,30 @@ pub struct LasFile {
     file_mode: String,
     pub header: LasHeader,
     pub vlr_data: Vec<Vlr>,
+    pub evlr_data: Vec<Vlr>,
     point_data: Vec<PointData>,
     // point_buffer_size: usize,
     gps_data: Vec<f64>,
     rgb_data: Vec<RgbData>,
+    nir_data: Vec<u16>,
     waveform_data: Vec<WaveformPacket>,
     pub geokeys: GeoKeys,
     // starting_point: usize,
     header_is_set: bool,
     pub use_point_intensity: bool,
     pub use_point_userdata: bool,
+    offsets_from_data: bool,
+    spatial_index: Option<LasIndex>,
+    spatial_reference: SpatialReference,
+    extra_bytes: Vec<ExtraBytesDescriptor>,
+    // the extra bytes of each point, in point order
+    extra_byte_data: Vec<u8>,
 }
 
 impl Index<usize> for LasFile {
     type Output = PointData;
 
-    fn index<'a>(&'a self, _index: usize) -> &'a PointData {
+    fn index(&self, _index: usize) -> &PointData {
         &self.point_data[_index]
     }
 }
@@ -48,7 +62,7 @@ impl LasFile {
         let mut lf = LasFile { file_name: file_name.to_string(), ..Default::default() };
         lf.file_mode = file_mode.to_lowercase();
         if lf.file_mode == "r" || lf.file_mode == "rh"  {
-            try!(lf.read());
+            lf.read()?;
         } else {
             lf.file_mode = "w".to_string();
         }
@@ -74,23 +88,46 @@ impl LasFile {
 
         output.add_header(input.header.clone());
 
-        // Copy the VLRs
+        // Copy the VLRs. The extra bytes VLR is written from the descriptors of the extra
+        // bytes, which are copied so that the extra bytes of the input's points can be
+        // retained, e.g. using add_point_record_with_extra_bytes.
+        output.extra_bytes = input.extra_bytes.clone();
         for i in 0..(input.header.number_of_vlrs as usize) {
-            output.add_vlr(input.vlr_data[i].clone());
+            if !input.vlr_data[i].is_extra_bytes() {
+                output.add_vlr(input.vlr_data[i].clone());
+            }
+        }
+
+        // Copy the EVLRs, including any waveform data
+        for evlr in input.evlr_data.iter().filter(|v| !v.is_extra_bytes()) {
+            output.add_evlr(evlr.clone());
         }
 
+        output.geokeys = input.geokeys.clone();
+        output.spatial_reference = input.spatial_reference.clone();
+
         output
     }
 
+    /// Sets the header of a file that is being created. The version, scale factors, offsets,
+    /// system identifier and generating software of the header are retained, so a file that
+    /// is initialized using another file shares its quantization of the point coordinates.
+    /// A header without scale factors (i.e. zero) has them, and the offsets, derived from
+    /// the data bounds when the file is written.
     pub fn add_header(&mut self, header: LasHeader) {
         if self.file_mode == "r" { return; }
         self.header = header;
 
         self.header.number_of_vlrs = 0;
         self.header.number_of_points = 0;
+        self.header.number_of_evlrs = 0;
+        self.header.start_of_first_evlr = 0;
+        self.header.waveform_data_start = 0;
 
-        self.header.version_major = 1;
-        self.header.version_minor = 3;
+        if self.header.version_major != 1 {
+            self.header.version_major = 1;
+            self.header.version_minor = 3;
+        }
     		// These must be set by the data
         self.header.min_x = f64::INFINITY;
         self.header.max_x = f64::NEG_INFINITY;
@@ -99,17 +136,55 @@ impl LasFile {
         self.header.min_z = f64::INFINITY;
         self.header.max_z = f64::NEG_INFINITY;
 
-		self.header.system_id = "LiDAR Tools by John Lindsay     ".to_string();
-		self.header.generating_software = "LiDAR Tools                     ".to_string();
-		self.header.number_of_points_by_return = [0, 0, 0, 0, 0];
-
-		self.header.x_scale_factor = 0.0001;
-		self.header.y_scale_factor = 0.0001;
-		self.header.z_scale_factor = 0.0001;
+		self.header.number_of_points_by_return = [0u64; 15];
 
         self.header_is_set = true;
     }
 
+    /// Returns the coordinate reference system of the points, as described by the file's
+    /// OGC WKT record or, failing that, its GeoKeys.
+    pub fn get_spatial_reference(&self) -> SpatialReference {
+        self.spatial_reference.clone()
+    }
+
+    /// Sets the coordinate reference system of the points. Any existing CRS records
+    /// (GeoKeys and WKT) are removed; records describing the new CRS are added when the
+    /// file is written.
+    pub fn set_spatial_reference(&mut self, srs: &SpatialReference) {
+        let num_vlrs = self.vlr_data.len();
+        self.vlr_data.retain(|v| !v.is_crs_record());
+        self.header.number_of_vlrs -= (num_vlrs - self.vlr_data.len()) as u32;
+        let num_evlrs = self.evlr_data.len();
+        self.evlr_data.retain(|v| !v.is_crs_record());
+        self.header.number_of_evlrs -= (num_evlrs - self.evlr_data.len()) as u32;
+        self.geokeys = Default::default();
+        self.spatial_reference = srs.clone();
+    }
+
+    /// Sets the scale factors used to store the point coordinates of the output file.
+    pub fn set_scale_factors(&mut self, x_scale_factor: f64, y_scale_factor: f64, z_scale_factor: f64) {
+        self.header.x_scale_factor = x_scale_factor;
+        self.header.y_scale_factor = y_scale_factor;
+        self.header.z_scale_factor = z_scale_factor;
+    }
+
+    /// Sets the offsets used to store the point coordinates of the output file. These are
+    /// used as they are, rather than derived from the data bounds.
+    pub fn set_offsets(&mut self, x_offset: f64, y_offset: f64, z_offset: f64) {
+        self.header.x_offset = x_offset;
+        self.header.y_offset = y_offset;
+        self.header.z_offset = z_offset;
+        self.offsets_from_data = false;
+    }
+
+    /// Derives the offsets of the output file from the bounds of its points when it is
+    /// written, rather than retaining those of the header. This is needed when the points
+    /// have been moved far enough from the input's extent, e.g. by reprojection, that their
+    /// coordinates may no longer fit in the 32-bit integers of the point records.
+    pub fn use_offsets_from_data(&mut self) {
+        self.offsets_from_data = true;
+    }
+
     pub fn add_vlr(&mut self, vlr: Vlr) {
         if self.file_mode == "r" { return; }
         // the header must be set before you can add VLRs
@@ -120,85 +195,200 @@ impl LasFile {
         self.header.number_of_vlrs += 1;
     }
 
+    /// Adds an extended VLR, which is written after the point records. The length of
+    /// the record is taken from its binary data, which may exceed 65,535 bytes.
+    pub fn add_evlr(&mut self, evlr: Vlr) {
+        if self.file_mode == "r" { return; }
+        if !self.header_is_set {
+            panic!("The header of a LAS file must be added before any EVLRs. Please see add_header().");
+        }
+        self.evlr_data.push(evlr);
+        self.header.number_of_evlrs += 1;
+    }
+
+    /// Returns the descriptions of the extra bytes attributes of the points.
+    pub fn get_extra_bytes_descriptors(&self) -> &[ExtraBytesDescriptor] {
+        &self.extra_bytes
+    }
+
+    /// Adds an extra bytes attribute to the points of a file that is being created. The
+    /// attribute's values are zero until they are set, e.g. using `set_extra_f64`. It is
+    /// described in the extra bytes VLR when the file is written.
+    pub fn add_extra_bytes_descriptor(&mut self, descriptor: ExtraBytesDescriptor) -> Result<(), Error> {
+        if self.file_mode == "r" {
+            return Err(Error::other("The file was opened in read-only mode"));
+        }
+        if descriptor.name.is_empty() || descriptor.name.len() > 32 {
+            return Err(Error::new(ErrorKind::InvalidInput, "The name of an extra bytes attribute must be 1 to 32 characters long."));
+        }
+        if self.extra_bytes.iter().any(|d| d.name == descriptor.name) {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("The file already has an extra bytes attribute named '{}'.", descriptor.name)));
+        }
+
+        // make room for the attribute in the extra bytes of any existing points
+        let old_length = self.extra_bytes_length();
+        let size = descriptor.size();
+        let num_points = self.point_data.len();
+        if size > 0 && num_points > 0 {
+            let mut data = Vec::with_capacity(num_points * (old_length + size));
+            for i in 0..num_points {
+                data.extend_from_slice(&self.extra_byte_data[i * old_length..(i + 1) * old_length]);
+                data.extend(vec![0u8; size]);
+            }
+            self.extra_byte_data = data;
+        }
+        self.extra_bytes.push(descriptor);
+        Ok(())
+    }
+
+    /// Returns the stored value of an extra bytes attribute of a point, without applying
+    /// the attribute's scale and offset.
+    pub fn get_extra_value(&self, index: usize, name: &str) -> Result<ExtraBytesValue, Error> {
+        let (i, start) = self.find_extra_bytes(index, name)?;
+        let d = &self.extra_bytes[i];
+        match d.data_type.decode(&self.extra_byte_data[start..start + d.size()]) {
+            Some(v) => Ok(v),
+            None => Err(Error::new(ErrorKind::InvalidData, format!("The values of the extra bytes attribute '{}' ({}) cannot be accessed.", name, d.data_type))),
+        }
+    }
+
+    /// Sets the stored value of an extra bytes attribute of a point. The value must be of
+    /// the attribute's data type.
+    pub fn set_extra_value(&mut self, index: usize, name: &str, value: ExtraBytesValue) -> Result<(), Error> {
+        let (i, start) = self.find_extra_bytes(index, name)?;
+        let data_type = self.extra_bytes[i].data_type;
+        if value.data_type() != data_type {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("The extra bytes attribute '{}' holds {} rather than {} values.", name, data_type, value.data_type())));
+        }
+        value.encode(&mut self.extra_byte_data[start..start + data_type.size()]);
+        Ok(())
+    }
+
+    /// Returns the value of an extra bytes attribute of a point, with the attribute's
+    /// scale and offset applied.
+    pub fn get_extra_f64(&self, index: usize, name: &str) -> Result<f64, Error> {
+        let value = self.get_extra_value(index, name)?.to_f64();
+        let (i, _) = self.find_extra_bytes(index, name)?;
+        Ok(self.extra_bytes[i].apply_scale_and_offset(value))
+    }
+
+    /// Sets the value of an extra bytes attribute of a point. The attribute's scale and
+    /// offset are removed and the value is rounded if it is stored as an integer.
+    pub fn set_extra_f64(&mut self, index: usize, name: &str, value: f64) -> Result<(), Error> {
+        let (i, start) = self.find_extra_bytes(index, name)?;
+        let d = &self.extra_bytes[i];
+        match d.data_type.value_from_f64(d.remove_scale_and_offset(value)) {
+            Some(v) => v.encode(&mut self.extra_byte_data[start..start + d.size()]),
+            None => return Err(Error::new(ErrorKind::InvalidInput, format!("The values of the extra bytes attribute '{}' ({}) cannot be set.", name, d.data_type))),
+        }
+        Ok(())
+    }
+
+    /// Returns the extra bytes of a point, laid out as described by the descriptors.
+    pub fn get_extra_bytes(&self, index: usize) -> &[u8] {
+        let length = self.extra_bytes_length();
+        &self.extra_byte_data[index * length..(index + 1) * length]
+    }
+
+    fn extra_bytes_length(&self) -> usize {
+        self.extra_bytes.iter().map(|d| d.size()).sum()
+    }
+
+    /// Finds an extra bytes attribute by name, returning its index among the descriptors
+    /// and the position of a point's value in `extra_byte_data`.
+    fn find_extra_bytes(&self, index: usize, name: &str) -> Result<(usize, usize), Error> {
+        if index >= self.point_data.len() {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("Point index {} is out of range; the file contains {} points.", index, self.point_data.len())));
+        }
+        let mut offset = 0usize;
+        for (i, d) in self.extra_bytes.iter().enumerate() {
+            if d.name == name {
+                return Ok((i, index * self.extra_bytes_length() + offset));
+            }
+            offset += d.size();
+        }
+        Err(Error::new(ErrorKind::NotFound, format!("The file does not have an extra bytes attribute named '{}'.", name)))
+    }
+
     pub fn add_point_record(&mut self, point: LidarPointRecord) {
         if self.file_mode == "r" { return; }
         if !self.header_is_set {
             panic!("The header of a LAS file must be added before any point records. Please see add_header().");
         }
-        let mut which_return = 0_usize;
-        let x: f64;
-        let y: f64;
-        let z: f64;
-        match point {
-            LidarPointRecord::PointRecord0 { point_data }  => {
-                self.point_data.push(point_data);
-                x = point_data.x;
-                y = point_data.y;
-                z = point_data.z;
-                which_return = point_data.bit_field.return_number() as usize;
-            },
-            LidarPointRecord::PointRecord1 { point_data, gps_data } => {
-                self.point_data.push(point_data);
-                x = point_data.x;
-                y = point_data.y;
-                z = point_data.z;
-                which_return = point_data.bit_field.return_number() as usize;
-                self.gps_data.push(gps_data);
-            },
-            LidarPointRecord::PointRecord2 { point_data, rgb_data } => {
-                self.point_data.push(point_data);
-                x = point_data.x;
-                y = point_data.y;
-                z = point_data.z;
-                which_return = point_data.bit_field.return_number() as usize;
-                self.rgb_data.push(rgb_data);
-            },
-            LidarPointRecord::PointRecord3 { point_data, gps_data, rgb_data } => {
-                self.point_data.push(point_data);
-                x = point_data.x;
-                y = point_data.y;
-                z = point_data.z;
-                which_return = point_data.bit_field.return_number() as usize;
-                self.gps_data.push(gps_data);
-                self.rgb_data.push(rgb_data);
-            },
+        let point_data = self.store_point_record(point);
+        update_header_summary(&mut self.header, &point_data);
+        let length = self.extra_bytes_length();
+        if length > 0 {
+            let new_length = self.extra_byte_data.len() + length;
+            self.extra_byte_data.resize(new_length, 0u8);
         }
+    }
 
-        if x < self.header.min_x { self.header.min_x = x; }
-        if x > self.header.max_x { self.header.max_x = x; }
-        if y < self.header.min_y { self.header.min_y = y; }
-        if y > self.header.max_y { self.header.max_y = y; }
-        if z < self.header.min_z { self.header.min_z = z; }
-        if z > self.header.max_z { self.header.max_z = z; }
-
-        self.header.number_of_points += 1;
-        if which_return == 0 { which_return = 1; }
-        self.header.number_of_points_by_return[which_return-1] += 1;
+    /// Adds a point record along with its extra bytes, which are laid out as described by
+    /// the extra bytes descriptors of the file, e.g. those of a point of the file that this
+    /// one is initialized using. Any bytes beyond the extra bytes of the file are ignored
+    /// and any that are missing are zero.
+    pub fn add_point_record_with_extra_bytes(&mut self, point: LidarPointRecord, extra_bytes: &[u8]) {
+        if self.file_mode == "r" { return; }
+        self.add_point_record(point);
+        let length = self.extra_bytes_length();
+        let n = cmp::min(length, extra_bytes.len());
+        let start = self.extra_byte_data.len() - length;
+        self.extra_byte_data[start..start + n].copy_from_slice(&extra_bytes[..n]);
     }
 
     pub fn get_record(&self, index: usize) -> LidarPointRecord {
-        let lpr: LidarPointRecord;
         match self.header.point_format {
             0 => {
-                lpr = LidarPointRecord::PointRecord0 { point_data: self.point_data[index] };
+                LidarPointRecord::PointRecord0 { point_data: self.point_data[index] }
             },
             1 => {
-                lpr = LidarPointRecord::PointRecord1 { point_data: self.point_data[index],
-                    gps_data: self.gps_data[index] };
+                LidarPointRecord::PointRecord1 { point_data: self.point_data[index],
+                    gps_data: self.gps_data[index] }
             },
             2 => {
-                lpr = LidarPointRecord::PointRecord2 { point_data: self.point_data[index],
-                    rgb_data: self.rgb_data[index] };
+                LidarPointRecord::PointRecord2 { point_data: self.point_data[index],
+                    rgb_data: self.rgb_data[index] }
             },
             3 => {
-                lpr = LidarPointRecord::PointRecord3 { point_data: self.point_data[index],
-                    gps_data: self.gps_data[index], rgb_data: self.rgb_data[index] };
+                LidarPointRecord::PointRecord3 { point_data: self.point_data[index],
+                    gps_data: self.gps_data[index], rgb_data: self.rgb_data[index] }
+            },
+            4 => {
+                LidarPointRecord::PointRecord4 { point_data: self.point_data[index],
+                    gps_data: self.gps_data[index], wave_packet: self.waveform_data[index] }
+            },
+            5 => {
+                LidarPointRecord::PointRecord5 { point_data: self.point_data[index],
+                    gps_data: self.gps_data[index], rgb_data: self.rgb_data[index],
+                    wave_packet: self.waveform_data[index] }
+            },
+            6 => {
+                LidarPointRecord::PointRecord6 { point_data: self.point_data[index],
+                    gps_data: self.gps_data[index] }
+            },
+            7 => {
+                LidarPointRecord::PointRecord7 { point_data: self.point_data[index],
+                    gps_data: self.gps_data[index], rgb_data: self.rgb_data[index] }
+            },
+            8 => {
+                LidarPointRecord::PointRecord8 { point_data: self.point_data[index],
+                    gps_data: self.gps_data[index], rgb_data: self.rgb_data[index],
+                    nir_data: self.nir_data[index] }
+            },
+            9 => {
+                LidarPointRecord::PointRecord9 { point_data: self.point_data[index],
+                    gps_data: self.gps_data[index], wave_packet: self.waveform_data[index] }
+            },
+            10 => {
+                LidarPointRecord::PointRecord10 { point_data: self.point_data[index],
+                    gps_data: self.gps_data[index], rgb_data: self.rgb_data[index],
+                    nir_data: self.nir_data[index], wave_packet: self.waveform_data[index] }
             },
             _ => {
                 panic!("Unsupported point format");
             },
         }
-        lpr
     }
 
     pub fn get_point_info(&self, index: usize) -> PointData {
@@ -207,32 +397,116 @@ impl LasFile {
 
     pub fn get_rgb(&self, index: usize) -> Result<RgbData, Error> {
         if self.rgb_data.len() >= index {
-            return Ok(self.rgb_data[index]);
+            Ok(self.rgb_data[index])
         } else {
-            return Err(Error::new(ErrorKind::NotFound, "RGB value not found, possibly because the file point format does not include colour data."));
+            Err(Error::new(ErrorKind::NotFound, "RGB value not found, possibly because the file point format does not include colour data."))
         }
     }
 
     pub fn get_gps_time(&self, index: usize) -> Result<f64, Error> {
         if self.gps_data.len() >= index {
-            return Ok(self.gps_data[index]);
+            Ok(self.gps_data[index])
+        } else {
+            Err(Error::new(ErrorKind::NotFound, "GPS time value not found, possibly because the file point format does not include GPS data."))
+        }
+    }
+
+    pub fn get_nir(&self, index: usize) -> Result<u16, Error> {
+        if index < self.nir_data.len() {
+            Ok(self.nir_data[index])
         } else {
-            return Err(Error::new(ErrorKind::NotFound, "GPS time value not found, possibly because the file point format does not include GPS data."));
+            Err(Error::new(ErrorKind::NotFound, "NIR value not found, possibly because the file point format does not include NIR data."))
         }
     }
 
     pub fn read(&mut self) -> Result<(), Error> {
+        let mut f = File::open(&self.file_name)?;
+        let laszip_vlr = self.read_header_and_vlrs(&mut f)?;
 
-        // // See if the file exists. If not, raise error.
-        // fs::metadata(&self.file_name)
+        // The point records may end with extra bytes, following the fields of the point format.
+        let standard_length = standard_record_length(self.header.point_format, self.use_point_intensity, self.use_point_userdata) as usize;
+        let rec_length = self.header.point_record_length as usize;
+        let num_extra_bytes = rec_length.saturating_sub(standard_length);
 
-        let mut f = File::open(&self.file_name)?;
-        let metadata = fs::metadata(&self.file_name)?;
-        let file_size: usize = metadata.len() as usize;
-        let mut buffer = vec![0; file_size];
+        if self.file_mode == "rh" { // file_mode = "rh" does not read points, only the header.
+            self.extra_bytes = extra_bytes_descriptors(&self.vlr_data, &[], num_extra_bytes);
+            return Ok(());
+        }
+
+        self.read_evlrs(&mut f)?;
+        self.extra_bytes = extra_bytes_descriptors(&self.vlr_data, &self.evlr_data, num_extra_bytes);
+
+        /////////////////////////
+        // Read the point data //
+        /////////////////////////
+        check_point_format(self.header.point_format)?;
+        if rec_length < standard_length {
+            return Err(Error::new(ErrorKind::InvalidData, format!("The point record length of {} bytes is shorter than the {} bytes of point format {}.",
+                rec_length, standard_length, self.header.point_format)));
+        }
+        let num_points = self.header.number_of_points;
+        f.seek(SeekFrom::Start(self.header.offset_to_points as u64))?;
+        match laszip_vlr {
+            Some(vlr) => {
+                // The compressed length of the points is unknown, so they are decompressed in
+                // chunks, rather than into a buffer sized by the point count of the header.
+                let mut decompressor = laszip_decompressor(BufReader::new(f), &vlr, &self.header)?;
+                let mut buffer = vec![];
+                let mut num_read = 0u64;
+                while num_read < num_points {
+                    let chunk_size = cmp::min(num_points - num_read, 1_000_000) as usize;
+                    buffer.resize(chunk_size * rec_length, 0u8);
+                    decompressor.decompress_many(&mut buffer)?;
+                    self.store_point_records(&buffer, standard_length);
+                    num_read += chunk_size as u64;
+                }
+            },
+            None => {
+                let data_length = num_points.checked_mul(rec_length as u64);
+                let file_size = f.metadata()?.len();
+                match data_length {
+                    Some(len) if (self.header.offset_to_points as u64).saturating_add(len) <= file_size => {
+                        let mut buffer = vec![0u8; len as usize];
+                        f.read_exact(&mut buffer)?;
+                        self.store_point_records(&buffer, standard_length);
+                    },
+                    _ => {
+                        return Err(Error::new(ErrorKind::InvalidData, "The file is shorter than the number of point records stated in the header."));
+                    },
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Decodes and stores the point records of a buffer, along with their extra bytes.
+    fn store_point_records(&mut self, buffer: &[u8], standard_length: usize) {
+        let rec_length = self.header.point_record_length as usize;
+        for record in buffer.chunks(rec_length) {
+            let point = decode_point_record(record, &self.header, self.use_point_intensity, self.use_point_userdata);
+            self.store_point_record(point);
+            if rec_length > standard_length {
+                self.extra_byte_data.extend_from_slice(&record[standard_length..]);
+            }
+        }
+    }
 
-        // read the file's bytes into a buffer
-        f.read(&mut buffer)?;
+    /// Reads the file header and the VLRs, leaving the point records unread. LAZ files flag
+    /// compression using the two high bits of the point format and describe the compressor
+    /// in the LASzip VLR. Point records are always presented decompressed, so the LASzip VLR
+    /// is not retained in `vlr_data`; it is returned instead, or `None` for LAS files.
+    pub(crate) fn read_header_and_vlrs(&mut self, f: &mut File) -> Result<Option<Vlr>, Error> {
+        let file_size = f.metadata()?.len() as usize;
+
+        // The header is at most 375 bytes (LAS 1.4). The remainder of the file head,
+        // i.e. the VLRs, is read once the offset to the point records is known.
+        let mut buffer = vec![0u8; cmp::min(file_size, 375)];
+        f.seek(SeekFrom::Start(0))?;
+        f.read_exact(&mut buffer)?;
+        if buffer.len() < 227 {
+            return Err(Error::new(ErrorKind::InvalidData, "Either the file is formatted incorrectly or it is an unsupported LAS version."));
+        }
 
         self.header.project_id_used = true;
         self.header.version_major = buffer[24];
@@ -243,256 +517,348 @@ impl LasFile {
             self.header.version_minor = buffer[9];
             if self.header.version_major < 1 || self.header.version_major > 2 || self.header.version_minor > 5 {
                 // There's something very wrong. Throw an error.
-                return Err(Error::new(ErrorKind::Other, "Either the file is formatted incorrectly or it is an unsupported LAS version."));
+                return Err(Error::other("Either the file is formatted incorrectly or it is an unsupported LAS version."));
             }
             self.header.project_id_used = false;
         }
-        unsafe {
+        let header_length = standard_header_size(self.header.version_minor) as usize
+            - if self.header.project_id_used { 0 } else { 16 };
+        if buffer.len() < header_length {
+            return Err(Error::new(ErrorKind::InvalidData, format!("The file is shorter than the {} byte header of a LAS {}.{} file.",
+                header_length, self.header.version_major, self.header.version_minor)));
+        }
 
-            //////////////////////////
-            // Read the File Header //
-            //////////////////////////
-            let mut offset: usize = 0;
-            self.header.file_signature = String::from_utf8_lossy(&buffer[offset..offset+4]).to_string();
-            if self.header.file_signature != "LASF" {
-                return Err(Error::new(ErrorKind::Other, "Either the file is formatted incorrectly or it is an unsupported LAS version."));
-            }
+        //////////////////////////
+        // Read the File Header //
+        //////////////////////////
+        let mut offset: usize = 0;
+        self.header.file_signature = String::from_utf8_lossy(&buffer[offset..offset+4]).to_string();
+        if self.header.file_signature != "LASF" {
+            return Err(Error::other("Either the file is formatted incorrectly or it is an unsupported LAS version."));
+        }
+        offset += 4;
+        self.header.file_source_id = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
+        offset += 2;
+        let ge_val = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
+        self.header.global_encoding = GlobalEncodingField { value: ge_val};
+        offset += 2;
+        if self.header.project_id_used {
+            self.header.project_id1 = u32::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3]]);
             offset += 4;
-            self.header.file_source_id = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-            offset += 2;
-            let ge_val = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-            self.header.global_encoding = GlobalEncodingField { value: ge_val};
+            self.header.project_id2 = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
             offset += 2;
-            if self.header.project_id_used {
-                self.header.project_id1 = mem::transmute::<[u8; 4], u32>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3]]);
-                offset += 4;
-                self.header.project_id2 = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                offset += 2;
-                self.header.project_id3 = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                offset += 2;
-                for i in 0..8 {
-                    self.header.project_id4[i] = buffer[offset + i];
-                }
-                offset += 8;
-            }
-            // The version major and minor are read earlier.
-            // Two bytes that must be added to the offset here.
-            offset += 2;
-            //self.header.project_id4 = String::from_utf8_lossy(&buffer[16..24]).trim().to_string();
-            self.header.system_id = String::from_utf8_lossy(&buffer[offset..offset+32]).trim().to_string();
-            offset += 32;
-            self.header.generating_software = String::from_utf8_lossy(&buffer[offset..offset+32]).trim().to_string();
-            offset += 32;
-            // self.header.system_id = String::from_utf8_lossy(&buffer[26..58]).trim().to_string();
-            // self.header.generating_software = String::from_utf8_lossy(&buffer[58..90]).trim().to_string();
-            self.header.file_creation_day = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-            offset += 2;
-            self.header.file_creation_year = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-            offset += 2;
-            self.header.header_size = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
+            self.header.project_id3 = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
             offset += 2;
-            self.header.offset_to_points = mem::transmute::<[u8; 4], u32>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3]]);
-            offset += 4;
-            self.header.number_of_vlrs = mem::transmute::<[u8; 4], u32>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3]]);
-            offset += 4;
-            self.header.point_format = buffer[offset];
-            offset += 1;
-            self.header.point_record_length = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-            offset += 2;
-            self.header.number_of_points = mem::transmute::<[u8; 4], u32>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3]]);
-            offset += 4;
-
-            // let mut num_returns = 5;
-            // if self.header.version_major == 1_u8 && self.header.version_minor > 3_u8 {
-            //     num_returns = 7;
-            // }
-            // offset = 111;
-            for i in 0..5 {
-                self.header.number_of_points_by_return[i] = mem::transmute::<[u8; 4], u32>([buffer[offset + i * 4], buffer[offset + i * 4 + 1], buffer[offset + i * 4 + 2], buffer[offset + i * 4 + 3]]);
-                // self.header.number_of_points_by_return.push(mem::transmute::<[u8; 4], u32>([buffer[offset + i * 4], buffer[offset + i * 4 + 1], buffer[offset + i * 4 + 2], buffer[offset + i * 4 + 3]]));
-            }
-            offset += 5 * 4;
-            self.header.x_scale_factor = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
-            offset += 8;
-            self.header.y_scale_factor = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+            self.header.project_id4.copy_from_slice(&buffer[offset..offset + 8]);
             offset += 8;
-            self.header.z_scale_factor = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
-            offset += 8;
-            self.header.x_offset = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
-            offset += 8;
-            self.header.y_offset = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
-            offset += 8;
-            self.header.z_offset = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
-            offset += 8;
-            self.header.max_x = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
-            offset += 8;
-            self.header.min_x = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
-            offset += 8;
-            self.header.max_y = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        }
+        // The version major and minor are read earlier.
+        // Two bytes that must be added to the offset here.
+        offset += 2;
+        //self.header.project_id4 = String::from_utf8_lossy(&buffer[16..24]).trim().to_string();
+        self.header.system_id = String::from_utf8_lossy(&buffer[offset..offset+32]).trim_matches(char::from(0)).trim().to_string();
+        offset += 32;
+        self.header.generating_software = String::from_utf8_lossy(&buffer[offset..offset+32]).trim_matches(char::from(0)).trim().to_string();
+        offset += 32;
+        // self.header.system_id = String::from_utf8_lossy(&buffer[26..58]).trim().to_string();
+        // self.header.generating_software = String::from_utf8_lossy(&buffer[58..90]).trim().to_string();
+        self.header.file_creation_day = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
+        offset += 2;
+        self.header.file_creation_year = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
+        offset += 2;
+        self.header.header_size = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
+        offset += 2;
+        if (self.header.header_size as usize) < header_length {
+            return Err(Error::new(ErrorKind::InvalidData, format!("The header size of {} bytes is less than the {} bytes of a LAS {}.{} header.",
+                self.header.header_size, header_length, self.header.version_major, self.header.version_minor)));
+        }
+        self.header.offset_to_points = u32::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3]]);
+        offset += 4;
+        self.header.number_of_vlrs = u32::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3]]);
+        offset += 4;
+        self.header.point_format = buffer[offset];
+        offset += 1;
+        self.header.point_record_length = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
+        offset += 2;
+        self.header.number_of_points = u32::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3]]) as u64;
+        offset += 4;
+
+        // let mut num_returns = 5;
+        // if self.header.version_major == 1_u8 && self.header.version_minor > 3_u8 {
+        //     num_returns = 7;
+        // }
+        // offset = 111;
+        for i in 0..5 {
+            self.header.number_of_points_by_return[i] = u32::from_le_bytes([buffer[offset + i * 4], buffer[offset + i * 4 + 1], buffer[offset + i * 4 + 2], buffer[offset + i * 4 + 3]]) as u64;
+            // self.header.number_of_points_by_return.push(mem::transmute::<[u8; 4], u32>([buffer[offset + i * 4], buffer[offset + i * 4 + 1], buffer[offset + i * 4 + 2], buffer[offset + i * 4 + 3]]));
+        }
+        offset += 5 * 4;
+        self.header.x_scale_factor = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.y_scale_factor = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.z_scale_factor = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.x_offset = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.y_offset = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.z_offset = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.max_x = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.min_x = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.max_y = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.min_y = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.max_z = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        offset += 8;
+        self.header.min_z = f64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+
+        offset += 8;
+
+        if self.header.version_major == 1 && self.header.version_minor >= 3 {
+            self.header.waveform_data_start = u64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
             offset += 8;
-            self.header.min_y = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+        }
+
+        if self.header.version_major == 1 && self.header.version_minor >= 4 {
+            self.header.start_of_first_evlr = u64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
             offset += 8;
-            self.header.max_z = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
+            self.header.number_of_evlrs = u32::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3]]);
+            offset += 4;
+            // The 64-bit point counts supersede the legacy 32-bit fields, which are zero for point formats 6-10.
+            self.header.number_of_points = u64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
             offset += 8;
-            self.header.min_z = mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
-
-            if self.header.version_major == 1 && self.header.version_minor == 3 {
+            for i in 0..15 {
+                self.header.number_of_points_by_return[i] = u64::from_le_bytes([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
                 offset += 8;
-                self.header.waveform_data_start = mem::transmute::<[u8; 8], u64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]);
             }
+        }
 
-            ///////////////////////
-            // Read the VLR data //
-            ///////////////////////
-            offset = self.header.header_size as usize;
-            //self.vlr_data = vec![Vlr{0'u16, "".to_string(), 0'u16, 0'u16, "".to_string()}; self.header.number_of_vlrs as usize];
-            for _ in 0..self.header.number_of_vlrs {
-                let mut vlr: Vlr = Default::default();
-                vlr.reserved = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                offset += 2;
-                vlr.user_id = String::from_utf8_lossy(&buffer[offset..offset+16]).trim().to_string();
-                offset += 16;
-                vlr.record_id = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                offset += 2;
-                vlr.record_length_after_header = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                offset += 2;
-                vlr.description = String::from_utf8_lossy(&buffer[offset..offset+32]).trim().to_string();
-                offset += 32;
-                // get the byte data
-                for i in 0..vlr.record_length_after_header {
-                    vlr.binary_data.push(buffer[offset + i as usize]);
-                }
-                offset += vlr.record_length_after_header as usize;
-
-                if vlr.record_id == 34_735 {
-                    self.geokeys.add_key_directory(&vlr.binary_data);
-                } else if vlr.record_id == 34_736 {
-                    self.geokeys.add_double_params(&vlr.binary_data);
-                } else if vlr.record_id == 34_737 {
-                    self.geokeys.add_ascii_params(&vlr.binary_data);
-                }
-                self.vlr_data.push(vlr);
+        if self.header.offset_to_points as usize > file_size {
+            return Err(Error::new(ErrorKind::InvalidData, "The offset to the point records lies beyond the end of the file."));
+        }
+        if self.header.offset_to_points as usize > buffer.len() {
+            let start = buffer.len();
+            buffer.resize(self.header.offset_to_points as usize, 0u8);
+            f.read_exact(&mut buffer[start..])?;
+        }
+
+        ///////////////////////
+        // Read the VLR data //
+        ///////////////////////
+        offset = self.header.header_size as usize;
+        //self.vlr_data = vec![Vlr{0'u16, "".to_string(), 0'u16, 0'u16, "".to_string()}; self.header.number_of_vlrs as usize];
+        for _ in 0..self.header.number_of_vlrs {
+            let mut vlr: Vlr = Default::default();
+            if offset + 54 > buffer.len() {
+                return Err(Error::new(ErrorKind::InvalidData, "The VLRs extend beyond the start of the point records."));
+            }
+            vlr.reserved = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
+            offset += 2;
+            vlr.user_id = String::from_utf8_lossy(&buffer[offset..offset+16]).trim().to_string();
+            offset += 16;
+            vlr.record_id = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
+            offset += 2;
+            vlr.record_length_after_header = u16::from_le_bytes([buffer[offset], buffer[offset+1]]);
+            offset += 2;
+            vlr.description = String::from_utf8_lossy(&buffer[offset..offset+32]).trim().to_string();
+            offset += 32;
+            if offset + vlr.record_length_after_header as usize > buffer.len() {
+                return Err(Error::new(ErrorKind::InvalidData, "The VLRs extend beyond the start of the point records."));
+            }
+            // get the byte data
+            for i in 0..vlr.record_length_after_header {
+                vlr.binary_data.push(buffer[offset + i as usize]);
             }
+            offset += vlr.record_length_after_header as usize;
+
+            if vlr.record_id == 34_735 {
+                self.geokeys.add_key_directory(&vlr.binary_data);
+            } else if vlr.record_id == 34_736 {
+                self.geokeys.add_double_params(&vlr.binary_data);
+            } else if vlr.record_id == 34_737 {
+                self.geokeys.add_ascii_params(&vlr.binary_data);
+            }
+            self.vlr_data.push(vlr);
+        }
 
-            if self.file_mode != "rh" { // file_mode = "rh" does not read points, only the header.
-                /////////////////////////
-                // Read the point data //
-                /////////////////////////
-                // self.starting_point = 0;
-                // let _ = self.read_points().unwrap();
-
-                // Intensity and userdata are both optional. Figure out if they need to be read.
-                // The only way to do this is to compare the point record length by point format
-                let rec_lengths = [ [20_u16, 18_u16, 19_u16, 17_u16],
-                                    [28_u16, 26_u16, 27_u16, 25_u16],
-                                    [26_u16, 24_u16, 25_u16, 23_u16],
-                                    [34_u16, 32_u16, 33_u16, 31_u16] ];
-
-                if self.header.point_record_length == rec_lengths[self.header.point_format as usize][0] {
-                    self.use_point_intensity = true;
-                    self.use_point_userdata = true;
-                } else if self.header.point_record_length == rec_lengths[self.header.point_format as usize][1] {
-                    self.use_point_intensity = false;
-                    self.use_point_userdata = true;
-                } else if self.header.point_record_length == rec_lengths[self.header.point_format as usize][2] {
-                    self.use_point_intensity = true;
-                    self.use_point_userdata = false;
-                } else if self.header.point_record_length == rec_lengths[self.header.point_format as usize][3] {
-                    self.use_point_intensity = false;
-                    self.use_point_userdata = false;
+        self.spatial_reference = spatial_reference_from_vlrs(&self.vlr_data, &self.geokeys);
+
+        let is_compressed = (self.header.point_format & 0b1100_0000u8) != 0;
+        self.header.point_format &= 0b0011_1111u8;
+        let laszip_vlr = match self.vlr_data.iter().position(|v| v.record_id == 22_204 && v.user_id.starts_with("laszip encoded")) {
+            Some(i) => {
+                self.header.number_of_vlrs -= 1;
+                Some(self.vlr_data.remove(i))
+            },
+            None => {
+                if is_compressed {
+                    return Err(Error::new(ErrorKind::InvalidData, "The file is flagged as compressed (LAZ) but does not contain a LASzip VLR."));
                 }
+                None
+            },
+        };
+
+        // Intensity and userdata are both optional. Figure out if they need to be read.
+        // The only way to do this is to compare the point record length by point format
+        let rec_lengths = [ [20_u16, 18_u16, 19_u16, 17_u16],
+                            [28_u16, 26_u16, 27_u16, 25_u16],
+                            [26_u16, 24_u16, 25_u16, 23_u16],
+                            [34_u16, 32_u16, 33_u16, 31_u16] ];
 
+        // Records that are longer than any of these carry extra bytes after the standard fields.
+        self.use_point_intensity = true;
+        self.use_point_userdata = true;
+        if self.header.point_format > 3 {
+            // Intensity and userdata are always present in the other point formats.
+        } else if self.header.point_record_length == rec_lengths[self.header.point_format as usize][0] {
+            self.use_point_intensity = true;
+            self.use_point_userdata = true;
+        } else if self.header.point_record_length == rec_lengths[self.header.point_format as usize][1] {
+            self.use_point_intensity = false;
+            self.use_point_userdata = true;
+        } else if self.header.point_record_length == rec_lengths[self.header.point_format as usize][2] {
+            self.use_point_intensity = true;
+            self.use_point_userdata = false;
+        } else if self.header.point_record_length == rec_lengths[self.header.point_format as usize][3] {
+            self.use_point_intensity = false;
+            self.use_point_userdata = false;
+        }
 
-                for i in 0..self.header.number_of_points {
-                    offset = (self.header.offset_to_points + (i as u32) * (self.header.point_record_length as u32)) as usize;
-                    let mut p: PointData = Default::default();
-                    p.x = ((mem::transmute::<[u8; 4], i32>([buffer[offset], buffer[offset+1], buffer[offset+2],
-                        buffer[offset+3]])) as f64) * self.header.x_scale_factor + self.header.x_offset;
-                    offset += 4;
-                    p.y = ((mem::transmute::<[u8; 4], i32>([buffer[offset], buffer[offset+1], buffer[offset+2],
-                        buffer[offset+3]])) as f64) * self.header.y_scale_factor + self.header.y_offset;
-                    offset += 4;
-                    p.z = ((mem::transmute::<[u8; 4], i32>([buffer[offset], buffer[offset+1], buffer[offset+2],
-                        buffer[offset+3]])) as f64) * self.header.z_scale_factor + self.header.z_offset;
-                    offset += 4;
-                    if self.use_point_intensity {
-                        p.intensity = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                        offset += 2;
-                    }
-                    p.bit_field =  PointBitField { value: buffer[offset] };
-                    offset += 1;
-                    p.class_bit_field = ClassificationBitField { value: buffer[offset] };
-                    offset += 1;
-                    p.scan_angle = mem::transmute::<[u8; 1], i8>([buffer[offset]]);
-                    offset += 1;
-                    if self.use_point_userdata {
-                        p.user_data = buffer[offset];
-                        offset += 1;
-                    }
-                    p.point_source_id = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                    self.point_data.push(p);
-                }
+        Ok(laszip_vlr)
+    }
 
-                if self.header.point_format == 1 {
-                    // read the GPS data
-                    for i in 0..self.header.number_of_points {
-                        offset = (self.header.offset_to_points + 20u32 + (i as u32) * (self.header.point_record_length as u32)) as usize;
-                        self.gps_data.push(mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]));
-                    }
-                } else if self.header.point_format == 2 {
-                    // read the RGB data
-                    for i in 0..self.header.number_of_points {
-                        offset = (self.header.offset_to_points + 20u32 + (i as u32) * (self.header.point_record_length as u32)) as usize;
-                        let mut rgb: RgbData = Default::default();
-                        rgb.red = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                        offset += 2;
-                        rgb.green = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                        offset += 2;
-                        rgb.blue = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                        self.rgb_data.push(rgb);
-                        // let r: u16 = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                        // offset += 2;
-                        // let g: u16 = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                        // offset += 2;
-                        // let b: u16 = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                        // self.rgb_data.push(RgbData { red: r, green: g, blue: b });
-                    }
-                } else if self.header.point_format == 3 {
-                    for i in 0..self.header.number_of_points {
-                        // read the GPS data
-                        offset = (self.header.offset_to_points + 20u32 + (i as u32) * (self.header.point_record_length as u32)) as usize;
-                        self.gps_data.push(mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1], buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5], buffer[offset+6], buffer[offset+7]]));
-                        offset += 8;
-                        // read the RGB data
-                        let mut rgb: RgbData = Default::default();
-                        rgb.red = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                        offset += 2;
-                        rgb.green = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                        offset += 2;
-                        rgb.blue = mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]]);
-                        self.rgb_data.push(rgb);
-                    }
-                } else if self.header.point_format == 4 {
+    /// Reads the extended VLRs, which follow the point records. A LAS 1.3 file may contain
+    /// a single EVLR, the waveform data packet record, which is located by the header's
+    /// waveform data start rather than by the EVLR fields of the LAS 1.4 header.
+    pub(crate) fn read_evlrs(&mut self, f: &mut File) -> Result<(), Error> {
+        let (start, count) = if self.header.version_major == 1 && self.header.version_minor >= 4 {
+            (self.header.start_of_first_evlr, self.header.number_of_evlrs)
+        } else if self.header.version_major == 1 && self.header.version_minor == 3 && self.header.waveform_data_start > 0 {
+            (self.header.waveform_data_start, 1u32)
+        } else {
+            (0u64, 0u32)
+        };
+        if count == 0 {
+            return Ok(());
+        }
 
-                }
+        let file_size = f.metadata()?.len();
+        let mut offset = start;
+        let mut evlr_header = [0u8; 60];
+        for _ in 0..count {
+            if offset + 60 > file_size {
+                return Err(Error::new(ErrorKind::InvalidData, "The EVLRs extend beyond the end of the file."));
             }
-
+            f.seek(SeekFrom::Start(offset))?;
+            f.read_exact(&mut evlr_header)?;
+            let mut evlr: Vlr = Default::default();
+            evlr.reserved = LittleEndian::read_u16(&evlr_header[0..2]);
+            evlr.user_id = String::from_utf8_lossy(&evlr_header[2..18]).trim_matches(char::from(0)).trim().to_string();
+            evlr.record_id = LittleEndian::read_u16(&evlr_header[18..20]);
+            let record_length = LittleEndian::read_u64(&evlr_header[20..28]);
+            evlr.description = String::from_utf8_lossy(&evlr_header[28..60]).trim_matches(char::from(0)).trim().to_string();
+            if offset + 60 + record_length > file_size {
+                return Err(Error::new(ErrorKind::InvalidData, "The EVLRs extend beyond the end of the file."));
+            }
+            // The 16-bit VLR length field cannot hold the length of larger EVLRs; the
+            // length of the binary data is authoritative.
+            evlr.record_length_after_header = cmp::min(record_length, u16::MAX as u64) as u16;
+            evlr.binary_data = vec![0u8; record_length as usize];
+            f.read_exact(&mut evlr.binary_data)?;
+            self.evlr_data.push(evlr);
+            offset += 60 + record_length;
         }
 
+        // LAS 1.4 files may store the WKT in an EVLR
+        if let Some(evlr) = self.evlr_data.iter().find(|v| v.is_wkt()) {
+            let mut srs = SpatialReference::from_wkt(&String::from_utf8_lossy(&evlr.binary_data));
+            if srs.epsg_code == 0 {
+                srs.epsg_code = self.spatial_reference.epsg_code;
+            }
+            self.spatial_reference = srs;
+        }
         Ok(())
     }
 
+    /// Adds a point record to the in-memory point data and returns its `PointData`.
+    fn store_point_record(&mut self, point: LidarPointRecord) -> PointData {
+        match point {
+            LidarPointRecord::PointRecord0 { point_data }  => {
+                self.point_data.push(point_data);
+            },
+            LidarPointRecord::PointRecord1 { point_data, gps_data } => {
+                self.point_data.push(point_data);
+                self.gps_data.push(gps_data);
+            },
+            LidarPointRecord::PointRecord2 { point_data, rgb_data } => {
+                self.point_data.push(point_data);
+                self.rgb_data.push(rgb_data);
+            },
+            LidarPointRecord::PointRecord3 { point_data, gps_data, rgb_data } => {
+                self.point_data.push(point_data);
+                self.gps_data.push(gps_data);
+                self.rgb_data.push(rgb_data);
+            },
+            LidarPointRecord::PointRecord4 { point_data, gps_data, wave_packet } => {
+                self.point_data.push(point_data);
+                self.gps_data.push(gps_data);
+                self.waveform_data.push(wave_packet);
+            },
+            LidarPointRecord::PointRecord5 { point_data, gps_data, rgb_data, wave_packet } => {
+                self.point_data.push(point_data);
+                self.gps_data.push(gps_data);
+                self.rgb_data.push(rgb_data);
+                self.waveform_data.push(wave_packet);
+            },
+            LidarPointRecord::PointRecord6 { point_data, gps_data } => {
+                self.point_data.push(point_data);
+                self.gps_data.push(gps_data);
+            },
+            LidarPointRecord::PointRecord7 { point_data, gps_data, rgb_data } => {
+                self.point_data.push(point_data);
+                self.gps_data.push(gps_data);
+                self.rgb_data.push(rgb_data);
+            },
+            LidarPointRecord::PointRecord8 { point_data, gps_data, rgb_data, nir_data } => {
+                self.point_data.push(point_data);
+                self.gps_data.push(gps_data);
+                self.rgb_data.push(rgb_data);
+                self.nir_data.push(nir_data);
+            },
+            LidarPointRecord::PointRecord9 { point_data, gps_data, wave_packet } => {
+                self.point_data.push(point_data);
+                self.gps_data.push(gps_data);
+                self.waveform_data.push(wave_packet);
+            },
+            LidarPointRecord::PointRecord10 { point_data, gps_data, rgb_data, nir_data, wave_packet } => {
+                self.point_data.push(point_data);
+                self.gps_data.push(gps_data);
+                self.rgb_data.push(rgb_data);
+                self.nir_data.push(nir_data);
+                self.waveform_data.push(wave_packet);
+            },
+        }
+        point.point_data()
+    }
+
     pub fn write(&mut self) -> Result<(), Error> {
         if self.file_mode == "r" {
-            return Err(Error::new(ErrorKind::Other, "The file was opened in read-only mode"));
+            return Err(Error::other("The file was opened in read-only mode"));
         }
         if !self.header_is_set {
-            return Err(Error::new(ErrorKind::Other, "The header of a LAS file must be added before any point records. Please see add_header()."));
+            return Err(Error::other("The header of a LAS file must be added before any point records. Please see add_header()."));
         }
 
-        self.header.x_offset = self.header.min_x;
-        self.header.y_offset = self.header.min_y;
-        self.header.z_offset = self.header.min_z;
+        // The scale factors and offsets of the header, e.g. those of the input file, are
+        // retained unless the scale factors have not been set or the offsets are to be
+        // derived from the data.
+        let scales_unset = self.header.x_scale_factor == 0_f64 || self.header.y_scale_factor == 0_f64 ||
+            self.header.z_scale_factor == 0_f64;
 
         let mut mantissa: usize = (format!("{}", (self.header.max_x - self.header.min_x).floor())).to_string().len();
         let mut dec: f64 = 1.0 / 10_f64.powi(8 - mantissa as i32);
@@ -506,361 +872,210 @@ impl LasFile {
         dec = 1.0 / 10_f64.powi(8 - mantissa as i32);
         if self.header.z_scale_factor == 0_f64 { self.header.z_scale_factor = dec; }
 
-        let f = File::create(&self.file_name)?;
-        let mut writer = BufWriter::new(f);
+        if self.header.number_of_points == 0 {
+            self.header.min_x = 0f64;
+            self.header.max_x = 0f64;
+            self.header.min_y = 0f64;
+            self.header.max_y = 0f64;
+            self.header.min_z = 0f64;
+            self.header.max_z = 0f64;
+        } else {
+            if scales_unset || self.offsets_from_data {
+                self.header.x_offset = offset_from_bounds(self.header.min_x, self.header.x_scale_factor);
+                self.header.y_offset = offset_from_bounds(self.header.min_y, self.header.y_scale_factor);
+                self.header.z_offset = offset_from_bounds(self.header.min_z, self.header.z_scale_factor);
+            }
+            // Every point lies within the bounds, so checking the bounds checks every point.
+            let (min_x, min_y, min_z) = (self.header.min_x, self.header.min_y, self.header.min_z);
+            let (max_x, max_y, max_z) = (self.header.max_x, self.header.max_y, self.header.max_z);
+            check_coordinate_range(&self.header, min_x, min_y, min_z)?;
+            check_coordinate_range(&self.header, max_x, max_y, max_z)?;
+        }
 
-        /////////////////////////////////
-        // Write the header to the file /
-        /////////////////////////////////
-        let mut u16_bytes: [u8; 2];
-        let mut u32_bytes: [u8; 4];
-        let mut u64_bytes: [u8; 8];
+        let extra_bytes_length = self.extra_bytes_length();
 
-        self.header.file_signature = "LASF".to_string();
-        writer.write(self.header.file_signature.as_bytes())?;
+        // Output is LASzip-compressed if the file has a .laz extension. LASzip requires
+        // the standard point record lengths, i.e. intensity and user data must be present.
+        let (laz_vlr, laszip_vlr) = if is_laz_file_name(&self.file_name) {
+            self.use_point_intensity = true;
+            self.use_point_userdata = true;
+            let (v, vlr) = laszip_vlr(self.header.point_format, extra_bytes_length as u16)?;
+            (Some(v), Some(vlr))
+        } else {
+            (None, None)
+        };
 
-        u16_bytes = unsafe {mem::transmute(self.header.file_source_id)};
-        writer.write(&u16_bytes)?;
+        let f = File::create(&self.file_name)?;
+        let mut writer = BufWriter::new(f);
 
-        u16_bytes = unsafe { mem::transmute(self.header.global_encoding) };
-        writer.write(&u16_bytes)?;
+        // The CRS is described by the existing records, e.g. those copied from an input
+        // file, if there are any, or else by records created from the spatial reference.
+        let has_crs_records = self.vlr_data.iter().chain(self.evlr_data.iter()).any(|v| v.is_crs_record());
+        let crs_vlrs = if has_crs_records { vec![] } else { spatial_reference_vlrs(&self.spatial_reference, self.header.point_format) };
+        // Likewise, the extra bytes are described by the descriptors of the file.
+        let extra_vlr = if self.extra_bytes.is_empty() { None } else { Some(extra_bytes_vlr(&self.extra_bytes)) };
+        let mut vlrs: Vec<&Vlr> = self.vlr_data[0..self.header.number_of_vlrs as usize].iter().filter(|v| !v.is_extra_bytes()).collect();
+        vlrs.extend(crs_vlrs.iter());
+        if let Some(ref vlr) = extra_vlr {
+            vlrs.push(vlr);
+        }
+        if let Some(ref vlr) = laszip_vlr {
+            vlrs.push(vlr);
+        }
+        write_header(&mut writer, &mut self.header, &vlrs, &self.evlr_data, laszip_vlr.is_some(),
+            self.use_point_intensity, self.use_point_userdata, extra_bytes_length as u16)?;
 
-        if self.header.project_id_used {
-            u32_bytes = unsafe { mem::transmute(self.header.project_id1) };
-            writer.write(&u32_bytes)?;
+        ////////////////////////////////
+        // Write the point to the file /
+        ////////////////////////////////
+        match laz_vlr {
+            Some(laz_vlr) => {
+                let mut compressor = match LasZipCompressor::new(&mut writer, laz_vlr) {
+                    Ok(c) => c,
+                    Err(e) => return Err(Error::other(format!("Error compressing the LAZ file: {}", e))),
+                };
+                let mut point_bytes: Vec<u8> = Vec::with_capacity(self.header.point_record_length as usize);
+                for i in 0..self.header.number_of_points as usize {
+                    point_bytes.clear();
+                    encode_point_record(&mut point_bytes, &self.get_record(i), &self.header,
+                        self.use_point_intensity, self.use_point_userdata)?;
+                    point_bytes.extend_from_slice(&self.extra_byte_data[i * extra_bytes_length..(i + 1) * extra_bytes_length]);
+                    compressor.compress_one(&point_bytes)?;
+                }
+                compressor.done()?;
+            },
+            None => {
+                for i in 0..self.header.number_of_points as usize {
+                    encode_point_record(&mut writer, &self.get_record(i), &self.header,
+                        self.use_point_intensity, self.use_point_userdata)?;
+                    writer.write_all(&self.extra_byte_data[i * extra_bytes_length..(i + 1) * extra_bytes_length])?;
+                }
+            },
+        }
 
-            u16_bytes = unsafe { mem::transmute(self.header.project_id2) };
-            writer.write(&u16_bytes)?;
+        // The EVLRs follow the points; their location is only known once the points are
+        // written, so the header is then rewritten.
+        write_evlrs(&mut writer, &mut self.header, &self.evlr_data)?;
+        writer.seek(SeekFrom::Start(0))?;
+        write_header(&mut writer, &mut self.header, &vlrs, &self.evlr_data, laszip_vlr.is_some(),
+            self.use_point_intensity, self.use_point_userdata, extra_bytes_length as u16)?;
+        writer.flush()?;
 
-            u16_bytes = unsafe { mem::transmute(self.header.project_id3) };
-            writer.write(&u16_bytes)?;
+        Ok(())
+    }
 
-            u64_bytes = unsafe { mem::transmute(self.header.project_id4) };
-            writer.write(&u64_bytes)?;
+    /// Returns the spatial index of the file. The index is read from the file's .lax file
+    /// if there is one that is at least as recent as the file; otherwise it is built and
+    /// saved as a .lax file, so that later queries, by this or other programs, can reuse it.
+    /// The .lax file is only a cache, so the index is still returned if it cannot be saved,
+    /// e.g. because the directory is read-only.
+    pub fn get_spatial_index(&mut self) -> Result<&LasIndex, Error> {
+        if self.spatial_index.is_none() {
+            let lax_file = index_file_name(&self.file_name);
+            let is_current = match (fs::metadata(&self.file_name), fs::metadata(&lax_file)) {
+                (Ok(las_meta), Ok(lax_meta)) => match (las_meta.modified(), lax_meta.modified()) {
+                    (Ok(las_time), Ok(lax_time)) => lax_time >= las_time,
+                    _ => false,
+                },
+                _ => false,
+            };
+            let index = match if is_current { LasIndex::read(&lax_file).ok() } else { None } {
+                Some(index) => index,
+                None => {
+                    let index = self.build_spatial_index()?;
+                    let _ = index.write(&lax_file);
+                    index
+                },
+            };
+            self.spatial_index = Some(index);
         }
+        Ok(self.spatial_index.as_ref().unwrap())
+    }
 
-        self.header.version_major = 1u8;
-        let mut u8_bytes: [u8; 1] = unsafe {mem::transmute(self.header.version_major)};
-        writer.write(&u8_bytes)?;
+    /// Builds a spatial index of the points of the file on disk. The points held in memory
+    /// are used if the file was opened for reading, otherwise the points are read from
+    /// the file.
+    fn build_spatial_index(&self) -> Result<LasIndex, Error> {
+        let h = &self.header;
+        if self.file_mode == "r" {
+            let points = self.point_data.iter().map(|p| (p.x, p.y));
+            return LasIndex::build(points, h.min_x, h.max_x, h.min_y, h.max_y, h.number_of_points);
+        }
+        let mut reader = LasReader::new(&self.file_name)?;
+        let mut xy = Vec::with_capacity(reader.header.number_of_points as usize);
+        for chunk in &mut reader {
+            for point in chunk? {
+                let p = point.point_data();
+                xy.push((p.x, p.y));
+            }
+        }
+        let h = &reader.header;
+        LasIndex::build(xy.into_iter(), h.min_x, h.max_x, h.min_y, h.max_y, h.number_of_points)
+    }
 
-        self.header.version_minor = 3u8;
-        u8_bytes = unsafe {mem::transmute(self.header.version_minor)};
-        writer.write(&u8_bytes)?;
+    /// Returns the point records that lie within a bounding box. Only the ranges of point
+    /// records that the spatial index (see `get_spatial_index`) identifies as possibly
+    /// lying within the box are examined. For a file that was opened with file mode "rh",
+    /// once the index exists, the remaining point records are not read from disk at all.
+    pub fn points_in_bbox(&mut self, min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Result<Vec<LidarPointRecord>, Error> {
+        self.points_in_area(min_x, min_y, max_x, max_y, |x, y| x >= min_x && x <= max_x && y >= min_y && y <= max_y)
+    }
 
-        if self.header.system_id.len() == 0 {
-            self.header.system_id = fixed_length_string("OTHER", 32);
-        } else if !self.header.system_id.len() != 32 {
-            self.header.system_id = fixed_length_string(&(self.header.system_id), 32);
+    /// Returns the point records that lie within a polygon, given as a list of (x, y)
+    /// vertices. The polygon need not be closed, i.e. repeat its first vertex. As with
+    /// `points_in_bbox`, only the point records near the polygon are read.
+    pub fn points_in_polygon(&mut self, polygon: &[(f64, f64)]) -> Result<Vec<LidarPointRecord>, Error> {
+        if polygon.len() < 3 {
+            return Err(Error::new(ErrorKind::InvalidInput, "A polygon must have at least three vertices."));
         }
-        writer.write(self.header.system_id.as_bytes())?; //string_bytes));
-
-        self.header.generating_software = fixed_length_string("whitebox_tools by John Lindsay", 32);
-        //string_bytes = unsafe { mem::transmute("libgeospatial by John Lindsay   ") };
-        writer.write(self.header.generating_software.as_bytes())?;
-
-        let now = time::now();
-        self.header.file_creation_day = now.tm_yday as u16;
-        u16_bytes = unsafe { mem::transmute(self.header.file_creation_day) };
-        writer.write(&u16_bytes)?;
-
-        self.header.file_creation_year = (now.tm_year + 1900) as u16;
-        u16_bytes = unsafe { mem::transmute(self.header.file_creation_year) };
-        writer.write(&u16_bytes)?;
-
-        self.header.header_size = 235;
-        u16_bytes = unsafe { mem::transmute(self.header.header_size) };
-        writer.write(&u16_bytes)?;
-
-        // figure out the offset to points
-        let mut total_vlr_size = 54 * self.header.number_of_vlrs;
-        for i in 0..(self.header.number_of_vlrs as usize) {
-            total_vlr_size += self.vlr_data[i].record_length_after_header as u32;
-        }
-        self.header.offset_to_points = 235 + total_vlr_size;
-        u32_bytes = unsafe { mem::transmute(self.header.offset_to_points) };
-        writer.write(&u32_bytes)?;
-
-        u32_bytes = unsafe { mem::transmute(self.header.number_of_vlrs) };
-        writer.write(&u32_bytes)?;
-
-        u8_bytes = unsafe {mem::transmute(self.header.point_format)};
-        writer.write(&u8_bytes)?;
-
-        // Intensity and userdata are both optional. Figure out if they need to be read.
-        // The only way to do this is to compare the point record length by point format
-        let rec_lengths = [ [20_u16, 18_u16, 19_u16, 17_u16],
-                            [28_u16, 26_u16, 27_u16, 25_u16],
-                            [26_u16, 24_u16, 25_u16, 23_u16],
-                            [34_u16, 32_u16, 33_u16, 31_u16] ];
-
-        if self.use_point_intensity && self.use_point_userdata {
-            self.header.point_record_length = rec_lengths[self.header.point_format as usize][0];
-        } else if !self.use_point_intensity && self.use_point_userdata {
-            self.header.point_record_length = rec_lengths[self.header.point_format as usize][1];
-        } else if self.use_point_intensity && !self.use_point_userdata {
-            self.header.point_record_length = rec_lengths[self.header.point_format as usize][2];
-        } else { //if !self.use_point_intensity && !self.use_point_userdata {
-            self.header.point_record_length = rec_lengths[self.header.point_format as usize][3];
-        }
-
-        u16_bytes = unsafe { mem::transmute(self.header.point_record_length) };
-        writer.write(&u16_bytes)?;
-
-        u32_bytes = unsafe { mem::transmute(self.header.number_of_points) };
-        writer.write(&u32_bytes)?;
-
-        for i in 0..5 {
-            u32_bytes = unsafe { mem::transmute(self.header.number_of_points_by_return[i]) };
-            writer.write(&u32_bytes)?;
-        }
-
-        u64_bytes = unsafe { mem::transmute(self.header.x_scale_factor) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.y_scale_factor) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.z_scale_factor) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.x_offset) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.y_offset) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.z_offset) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.max_x) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.min_x) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.max_y) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.min_y) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.max_z) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.min_z) };
-        writer.write(&u64_bytes)?;
-
-        u64_bytes = unsafe { mem::transmute(self.header.waveform_data_start) };
-        writer.write(&u64_bytes)?;
-
-        ///////////////////////////////
-        // Write the VLRs to the file /
-        ///////////////////////////////
-        for i in 0..(self.header.number_of_vlrs as usize) {
-            let vlr = self.vlr_data[i].clone();
-            u16_bytes = unsafe { mem::transmute(vlr.reserved) };
-            writer.write(&u16_bytes)?;
-
-            let user_id: &str = &vlr.user_id;
-            //string_bytes = unsafe { mem::transmute(user_id) };
-            writer.write(user_id.as_bytes())?; //string_bytes));
-
-            u16_bytes = unsafe { mem::transmute(vlr.record_id) };
-            writer.write(&u16_bytes)?;
-
-            u16_bytes = unsafe { mem::transmute(vlr.record_length_after_header) };
-            writer.write(&u16_bytes)?;
-
-            let description: &str = &vlr.description;
-            //string_bytes = unsafe { mem::transmute(description) };
-            writer.write(description.as_bytes())?;
-
-            writer.write(&vlr.binary_data)?;
+        let mut min_x = f64::INFINITY;
+        let mut min_y = f64::INFINITY;
+        let mut max_x = f64::NEG_INFINITY;
+        let mut max_y = f64::NEG_INFINITY;
+        for &(x, y) in polygon {
+            if x < min_x { min_x = x; }
+            if x > max_x { max_x = x; }
+            if y < min_y { min_y = y; }
+            if y > max_y { max_y = y; }
         }
+        self.points_in_area(min_x, min_y, max_x, max_y, |x, y| point_in_polygon(x, y, polygon))
+    }
 
-        ////////////////////////////////
-        // Write the point to the file /
-        ////////////////////////////////
-        let mut val: i32;
-        match self.header.point_format {
-            0 => {
-                for i in 0..self.header.number_of_points as usize {
-                    val = ((self.point_data[i].x - self.header.x_offset) / self.header.x_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    val = ((self.point_data[i].y - self.header.y_offset) / self.header.y_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    val = ((self.point_data[i].z - self.header.z_offset) / self.header.z_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    if self.use_point_intensity {
-                        u16_bytes = unsafe { mem::transmute(self.point_data[i].intensity) };
-                        writer.write(&u16_bytes)?;
-                    }
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].bit_field.value)};
-                    writer.write(&u8_bytes)?;
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].class_bit_field.value)};
-                    writer.write(&u8_bytes)?;
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].scan_angle)};
-                    writer.write(&u8_bytes)?;
-
-                    if self.use_point_userdata {
-                        u8_bytes = unsafe {mem::transmute(self.point_data[i].user_data)};
-                        writer.write(&u8_bytes)?;
-                    }
-
-                    u16_bytes = unsafe { mem::transmute(self.point_data[i].point_source_id) };
-                    writer.write(&u16_bytes)?;
-                }
-            },
-            1 => {
-                for i in 0..self.header.number_of_points as usize {
-                    val = ((self.point_data[i].x - self.header.x_offset) / self.header.x_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    val = ((self.point_data[i].y - self.header.y_offset) / self.header.y_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    val = ((self.point_data[i].z - self.header.z_offset) / self.header.z_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    if self.use_point_intensity {
-                        u16_bytes = unsafe { mem::transmute(self.point_data[i].intensity) };
-                        writer.write(&u16_bytes)?;
-                    }
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].bit_field.value)};
-                    writer.write(&u8_bytes)?;
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].class_bit_field.value)};
-                    writer.write(&u8_bytes)?;
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].scan_angle)};
-                    writer.write(&u8_bytes)?;
-
-                    if self.use_point_userdata {
-                        u8_bytes = unsafe {mem::transmute(self.point_data[i].user_data)};
-                        writer.write(&u8_bytes)?;
-                    }
-
-                    u16_bytes = unsafe { mem::transmute(self.point_data[i].point_source_id) };
-                    writer.write(&u16_bytes)?;
-
-                    u64_bytes = unsafe { mem::transmute(self.gps_data[i]) };
-                    writer.write(&u64_bytes)?;
-                }
-            },
-            2 => {
-                for i in 0..self.header.number_of_points as usize {
-                    val = ((self.point_data[i].x - self.header.x_offset) / self.header.x_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    val = ((self.point_data[i].y - self.header.y_offset) / self.header.y_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    val = ((self.point_data[i].z - self.header.z_offset) / self.header.z_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    if self.use_point_intensity {
-                        u16_bytes = unsafe { mem::transmute(self.point_data[i].intensity) };
-                        writer.write(&u16_bytes)?;
-                    }
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].bit_field.value)};
-                    writer.write(&u8_bytes)?;
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].class_bit_field.value)};
-                    writer.write(&u8_bytes)?;
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].scan_angle)};
-                    writer.write(&u8_bytes)?;
-
-                    if self.use_point_userdata {
-                        u8_bytes = unsafe {mem::transmute(self.point_data[i].user_data)};
-                        writer.write(&u8_bytes)?;
+    fn points_in_area<F>(&mut self, min_x: f64, min_y: f64, max_x: f64, max_y: f64, contains: F) -> Result<Vec<LidarPointRecord>, Error>
+        where F: Fn(f64, f64) -> bool {
+        let intervals = self.get_spatial_index()?.intervals_in_bbox(min_x, min_y, max_x, max_y);
+        let mut points = vec![];
+        if self.file_mode == "r" {
+            for (start, end) in intervals {
+                for i in start as usize..cmp::min(end, self.header.number_of_points) as usize {
+                    let p = self.point_data[i];
+                    if contains(p.x, p.y) {
+                        points.push(self.get_record(i));
                     }
-
-                    u16_bytes = unsafe { mem::transmute(self.point_data[i].point_source_id) };
-                    writer.write(&u16_bytes)?;
-
-                    u16_bytes = unsafe { mem::transmute(self.rgb_data[i].red) };
-                    writer.write(&u16_bytes)?;
-
-                    u16_bytes = unsafe { mem::transmute(self.rgb_data[i].green) };
-                    writer.write(&u16_bytes)?;
-
-                    u16_bytes = unsafe { mem::transmute(self.rgb_data[i].blue) };
-                    writer.write(&u16_bytes)?;
                 }
-            },
-            3 => {
-                for i in 0..self.header.number_of_points as usize {
-                    val = ((self.point_data[i].x - self.header.x_offset) / self.header.x_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    val = ((self.point_data[i].y - self.header.y_offset) / self.header.y_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    val = ((self.point_data[i].z - self.header.z_offset) / self.header.z_scale_factor) as i32;
-                    u32_bytes = unsafe { mem::transmute(val) };
-                    writer.write(&u32_bytes)?;
-
-                    if self.use_point_intensity {
-                        u16_bytes = unsafe { mem::transmute(self.point_data[i].intensity) };
-                        writer.write(&u16_bytes)?;
-                    }
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].bit_field.value)};
-                    writer.write(&u8_bytes)?;
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].class_bit_field.value)};
-                    writer.write(&u8_bytes)?;
-
-                    u8_bytes = unsafe {mem::transmute(self.point_data[i].scan_angle)};
-                    writer.write(&u8_bytes)?;
-
-                    if self.use_point_userdata {
-                        u8_bytes = unsafe {mem::transmute(self.point_data[i].user_data)};
-                        writer.write(&u8_bytes)?;
+            }
+        } else {
+            let mut reader = LasReader::new(&self.file_name)?;
+            for (start, end) in intervals {
+                let end = cmp::min(end, reader.header.number_of_points);
+                if start >= end { continue; }
+                reader.seek(start)?;
+                let mut remaining = end - start;
+                while remaining > 0 {
+                    reader.set_chunk_size(cmp::min(remaining, 1_000_000) as usize);
+                    let chunk = reader.read_chunk()?;
+                    if chunk.is_empty() { break; }
+                    remaining -= chunk.len() as u64;
+                    for point in chunk {
+                        let p = point.point_data();
+                        if contains(p.x, p.y) {
+                            points.push(point);
+                        }
                     }
-
-                    u16_bytes = unsafe { mem::transmute(self.point_data[i].point_source_id) };
-                    writer.write(&u16_bytes)?;
-
-                    u64_bytes = unsafe { mem::transmute(self.gps_data[i]) };
-                    writer.write(&u64_bytes)?;
-
-                    u16_bytes = unsafe { mem::transmute(self.rgb_data[i].red) };
-                    writer.write(&u16_bytes)?;
-
-                    u16_bytes = unsafe { mem::transmute(self.rgb_data[i].green) };
-                    writer.write(&u16_bytes)?;
-
-                    u16_bytes = unsafe { mem::transmute(self.rgb_data[i].blue) };
-                    writer.write(&u16_bytes)?;
                 }
-            },
-            _ => {
-                return Err(Error::new(ErrorKind::Other, "Unsupported point format"));
-            },
+            }
         }
-
-        Ok(())
+        Ok(points)
     }
 
     pub fn get_vlr_data_as_string(&self) -> String {
@@ -870,7 +1085,12 @@ impl LasFile {
             s = s + &format!("\nVLR {}:\n{}", i, vlr);
             i += 1;
         }
-        return s;
+        i = 1;
+        for evlr in &self.evlr_data {
+            s = s + &format!("\nEVLR {}:\n{}", i, evlr);
+            i += 1;
+        }
+        s
     }
 
     // pub fn get_geokeys(self) -> String {
@@ -880,7 +1100,7 @@ impl LasFile {
 
 impl fmt::Display for LasFile {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", format!("File Name: {}\n{}", self.file_name, &self.header))
+        write!(f, "File Name: {}\n{}", self.file_name, &self.header)
     }
 }
 
@@ -959,7 +1179,113 @@ pub enum LidarPointRecord {
     PointRecord0 { point_data: PointData },
     PointRecord1 { point_data: PointData, gps_data: f64 },
     PointRecord2 { point_data: PointData, rgb_data: RgbData },
-    PointRecord3 { point_data: PointData, gps_data: f64, rgb_data: RgbData }
+    PointRecord3 { point_data: PointData, gps_data: f64, rgb_data: RgbData },
+    PointRecord4 { point_data: PointData, gps_data: f64, wave_packet: WaveformPacket },
+    PointRecord5 { point_data: PointData, gps_data: f64, rgb_data: RgbData, wave_packet: WaveformPacket },
+    PointRecord6 { point_data: PointData, gps_data: f64 },
+    PointRecord7 { point_data: PointData, gps_data: f64, rgb_data: RgbData },
+    PointRecord8 { point_data: PointData, gps_data: f64, rgb_data: RgbData, nir_data: u16 },
+    PointRecord9 { point_data: PointData, gps_data: f64, wave_packet: WaveformPacket },
+    PointRecord10 { point_data: PointData, gps_data: f64, rgb_data: RgbData, nir_data: u16, wave_packet: WaveformPacket },
+}
+
+impl LidarPointRecord {
+    /// Returns the point data common to all point record formats.
+    pub fn point_data(&self) -> PointData {
+        match *self {
+            LidarPointRecord::PointRecord0 { point_data } |
+            LidarPointRecord::PointRecord1 { point_data, .. } |
+            LidarPointRecord::PointRecord2 { point_data, .. } |
+            LidarPointRecord::PointRecord3 { point_data, .. } |
+            LidarPointRecord::PointRecord4 { point_data, .. } |
+            LidarPointRecord::PointRecord5 { point_data, .. } |
+            LidarPointRecord::PointRecord6 { point_data, .. } |
+            LidarPointRecord::PointRecord7 { point_data, .. } |
+            LidarPointRecord::PointRecord8 { point_data, .. } |
+            LidarPointRecord::PointRecord9 { point_data, .. } |
+            LidarPointRecord::PointRecord10 { point_data, .. } => point_data,
+        }
+    }
+
+    /// Returns the GPS time of the record, if its point format includes it.
+    pub fn gps_time(&self) -> Option<f64> {
+        match *self {
+            LidarPointRecord::PointRecord0 { .. } |
+            LidarPointRecord::PointRecord2 { .. } => None,
+            LidarPointRecord::PointRecord1 { gps_data, .. } |
+            LidarPointRecord::PointRecord3 { gps_data, .. } |
+            LidarPointRecord::PointRecord4 { gps_data, .. } |
+            LidarPointRecord::PointRecord5 { gps_data, .. } |
+            LidarPointRecord::PointRecord6 { gps_data, .. } |
+            LidarPointRecord::PointRecord7 { gps_data, .. } |
+            LidarPointRecord::PointRecord8 { gps_data, .. } |
+            LidarPointRecord::PointRecord9 { gps_data, .. } |
+            LidarPointRecord::PointRecord10 { gps_data, .. } => Some(gps_data),
+        }
+    }
+
+    /// Returns the colour of the record, if its point format includes it.
+    pub fn rgb(&self) -> Option<RgbData> {
+        match *self {
+            LidarPointRecord::PointRecord2 { rgb_data, .. } |
+            LidarPointRecord::PointRecord3 { rgb_data, .. } |
+            LidarPointRecord::PointRecord5 { rgb_data, .. } |
+            LidarPointRecord::PointRecord7 { rgb_data, .. } |
+            LidarPointRecord::PointRecord8 { rgb_data, .. } |
+            LidarPointRecord::PointRecord10 { rgb_data, .. } => Some(rgb_data),
+            _ => None,
+        }
+    }
+
+    /// Returns the near-infrared value of the record, if its point format includes it.
+    pub fn nir(&self) -> Option<u16> {
+        match *self {
+            LidarPointRecord::PointRecord8 { nir_data, .. } |
+            LidarPointRecord::PointRecord10 { nir_data, .. } => Some(nir_data),
+            _ => None,
+        }
+    }
+
+    /// Returns the waveform packet of the record, if its point format includes it.
+    pub fn waveform_packet(&self) -> Option<WaveformPacket> {
+        match *self {
+            LidarPointRecord::PointRecord4 { wave_packet, .. } |
+            LidarPointRecord::PointRecord5 { wave_packet, .. } |
+            LidarPointRecord::PointRecord9 { wave_packet, .. } |
+            LidarPointRecord::PointRecord10 { wave_packet, .. } => Some(wave_packet),
+            _ => None,
+        }
+    }
+
+    /// Returns a mutable reference to the point data of the record, e.g. to update its
+    /// coordinates.
+    pub fn point_data_mut(&mut self) -> &mut PointData {
+        match *self {
+            LidarPointRecord::PointRecord0 { ref mut point_data } |
+            LidarPointRecord::PointRecord1 { ref mut point_data, .. } |
+            LidarPointRecord::PointRecord2 { ref mut point_data, .. } |
+            LidarPointRecord::PointRecord3 { ref mut point_data, .. } |
+            LidarPointRecord::PointRecord4 { ref mut point_data, .. } |
+            LidarPointRecord::PointRecord5 { ref mut point_data, .. } |
+            LidarPointRecord::PointRecord6 { ref mut point_data, .. } |
+            LidarPointRecord::PointRecord7 { ref mut point_data, .. } |
+            LidarPointRecord::PointRecord8 { ref mut point_data, .. } |
+            LidarPointRecord::PointRecord9 { ref mut point_data, .. } |
+            LidarPointRecord::PointRecord10 { ref mut point_data, .. } => point_data,
+        }
+    }
+
+    /// Returns a mutable reference to the waveform packet of the record, if its point
+    /// format includes it, e.g. to update the offset to its waveform data.
+    pub fn waveform_packet_mut(&mut self) -> Option<&mut WaveformPacket> {
+        match *self {
+            LidarPointRecord::PointRecord4 { ref mut wave_packet, .. } |
+            LidarPointRecord::PointRecord5 { ref mut wave_packet, .. } |
+            LidarPointRecord::PointRecord9 { ref mut wave_packet, .. } |
+            LidarPointRecord::PointRecord10 { ref mut wave_packet, .. } => Some(wave_packet),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Default, Copy, Clone, Debug)]
@@ -1037,6 +1363,73 @@ impl PointRecord5 {
     }
 }
 
+#[derive(Default, Copy, Clone, Debug)]
+pub struct  PointRecord6 {
+    pub point_data: PointData,
+    pub gps_data: f64,
+}
+
+impl PointRecord6 {
+    pub fn get_format(&self) -> u8 {
+        6u8
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+pub struct  PointRecord7 {
+    pub point_data: PointData,
+    pub gps_data: f64,
+    pub rgb_data: RgbData,
+}
+
+impl PointRecord7 {
+    pub fn get_format(&self) -> u8 {
+        7u8
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+pub struct  PointRecord8 {
+    pub point_data: PointData,
+    pub gps_data: f64,
+    pub rgb_data: RgbData,
+    pub nir_data: u16,
+}
+
+impl PointRecord8 {
+    pub fn get_format(&self) -> u8 {
+        8u8
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+pub struct  PointRecord9 {
+    pub point_data: PointData,
+    pub gps_data: f64,
+    pub wave_packet: WaveformPacket,
+}
+
+impl PointRecord9 {
+    pub fn get_format(&self) -> u8 {
+        9u8
+    }
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+pub struct  PointRecord10 {
+    pub point_data: PointData,
+    pub gps_data: f64,
+    pub rgb_data: RgbData,
+    pub nir_data: u16,
+    pub wave_packet: WaveformPacket,
+}
+
+impl PointRecord10 {
+    pub fn get_format(&self) -> u8 {
+        10u8
+    }
+}
+
 fn fixed_length_string(s: &str, len: usize) -> String {
     //let array: &[u8: 32];
     let l = s.len();
@@ -1045,7 +1438,7 @@ fn fixed_length_string(s: &str, len: usize) -> String {
         // add spaces to end
         ret = s.to_string();
         for _ in 0..len-l {
-            ret.push_str("\0");
+            ret.push('\0');
         }
     } else {
         // truncate string
@@ -1053,3 +1446,917 @@ fn fixed_length_string(s: &str, len: usize) -> String {
     }
     ret
 }
+
+/// Returns an error for point formats that cannot be read or written.
+pub(crate) fn check_point_format(point_format: u8) -> Result<(), Error> {
+    match point_format {
+        0..=10 => Ok(()),
+        _ => Err(Error::other(format!("Unsupported point format ({}).", point_format))),
+    }
+}
+
+/// Returns the size of the header of a LAS 1.x file, by minor version. The header grows with
+/// the version: 1.3 adds the start of the waveform data and 1.4 the EVLRs and the 64-bit
+/// point counts.
+pub(crate) fn standard_header_size(version_minor: u8) -> u16 {
+    match version_minor {
+        0..=2 => 227,
+        3 => 235,
+        _ => 375,
+    }
+}
+
+/// Returns the length of the fields of a point format, i.e. of a point record without
+/// extra bytes. Intensity and user data are optional in point formats 0-3.
+pub(crate) fn standard_record_length(point_format: u8, use_point_intensity: bool, use_point_userdata: bool) -> u16 {
+    let length = match point_format {
+        0 => 20u16,
+        1 => 28u16,
+        2 => 26u16,
+        3 => 34u16,
+        4 => 57u16,
+        5 => 63u16,
+        6 => 30u16,
+        7 => 36u16,
+        8 => 38u16,
+        9 => 59u16,
+        _ => 67u16,
+    };
+    if point_format > 3 {
+        return length;
+    }
+    length - if use_point_intensity { 0 } else { 2 } - if use_point_userdata { 0 } else { 1 }
+}
+
+/// Returns the descriptions of the extra bytes of the point records, from the extra bytes
+/// VLR or EVLR. Bytes that are not described, or that are described inconsistently with
+/// the point record length, are treated as undocumented extra bytes so that they are kept.
+pub(crate) fn extra_bytes_descriptors(vlrs: &[Vlr], evlrs: &[Vlr], num_extra_bytes: usize) -> Vec<ExtraBytesDescriptor> {
+    let mut descriptors = match vlrs.iter().chain(evlrs.iter()).find(|v| v.is_extra_bytes()) {
+        Some(vlr) => descriptors_from_vlr(vlr),
+        None => vec![],
+    };
+    let mut described: usize = descriptors.iter().map(|d| d.size()).sum();
+    if described > num_extra_bytes {
+        descriptors.clear();
+        described = 0;
+    }
+    let mut remaining = num_extra_bytes - described;
+    while remaining > 0 {
+        let n = cmp::min(remaining, 255);
+        descriptors.push(ExtraBytesDescriptor::new("", ExtraBytesDataType::Undocumented(n as u8), ""));
+        remaining -= n;
+    }
+    descriptors
+}
+
+/// Returns true if the file name has a .laz extension, in which case the output is
+/// LASzip-compressed.
+pub(crate) fn is_laz_file_name(file_name: &str) -> bool {
+    file_name.to_lowercase().ends_with(".laz")
+}
+
+/// Creates the LASzip compressor description for a point format and number of extra bytes, along with the LASzip
+/// VLR that describes it in the output file.
+pub(crate) fn laszip_vlr(point_format: u8, num_extra_bytes: u16) -> Result<(LazVlr, Vlr), Error> {
+    let items = match LazItemRecordBuilder::default_for_point_format_id(point_format, num_extra_bytes) {
+        Ok(items) => items,
+        Err(e) => return Err(Error::other(format!("Unsupported point format for LAZ output: {}", e))),
+    };
+    let laz_vlr = LazVlr::from_laz_items(items);
+    let mut laz_vlr_data: Vec<u8> = vec![];
+    laz_vlr.write_to(&mut laz_vlr_data)?;
+    let vlr = Vlr {
+        reserved: 0u16,
+        user_id: "laszip encoded".to_string(),
+        record_id: 22_204u16,
+        record_length_after_header: laz_vlr_data.len() as u16,
+        description: "http://laszip.org".to_string(),
+        binary_data: laz_vlr_data,
+    };
+    Ok((laz_vlr, vlr))
+}
+
+/// Creates a decompressor for the LASzip-compressed point records of a file. The source
+/// must be positioned at the start of the point records.
+pub(crate) fn laszip_decompressor<'a, R>(source: R, laszip_vlr: &Vlr, header: &LasHeader) -> Result<LasZipDecompressor<'a, R>, Error>
+    where R: Read + Seek + Send + Sync + 'a {
+    let laz_vlr = match LazVlr::from_buffer(&laszip_vlr.binary_data) {
+        Ok(v) => v,
+        Err(e) => return Err(Error::new(ErrorKind::InvalidData, format!("Error reading the LASzip VLR: {}", e))),
+    };
+    if laz_vlr.items_size() != header.point_record_length as u64 {
+        return Err(Error::new(ErrorKind::InvalidData, "The LASzip VLR does not match the point record length of the file."));
+    }
+    match LasZipDecompressor::new(source, laz_vlr) {
+        Ok(d) => Ok(d),
+        Err(e) => Err(Error::new(ErrorKind::InvalidData, format!("Error decompressing the LAZ file: {}", e))),
+    }
+}
+
+/// Returns the CRS described by a file's VLRs: its OGC WKT record if it has one, with the
+/// EPSG code taken from the GeoKeys if the WKT lacks one, or else its GeoKeys.
+fn spatial_reference_from_vlrs(vlrs: &[Vlr], geokeys: &GeoKeys) -> SpatialReference {
+    let mut srs = match vlrs.iter().find(|v| v.is_wkt()) {
+        Some(vlr) => SpatialReference::from_wkt(&String::from_utf8_lossy(&vlr.binary_data)),
+        None => SpatialReference::default(),
+    };
+    if srs.epsg_code == 0 {
+        srs.epsg_code = geokeys.find_epsg_code();
+    }
+    srs
+}
+
+/// Returns the VLRs that describe a CRS: an OGC WKT record, if the WKT is known, and a GeoKey
+/// directory record, if the EPSG code is known. Point formats 6-10 must use WKT, so for them
+/// the GeoKeys are only included when the WKT is unknown.
+pub(crate) fn spatial_reference_vlrs(srs: &SpatialReference, point_format: u8) -> Vec<Vlr> {
+    let mut vlrs = vec![];
+    if !srs.wkt.is_empty() {
+        let mut data = srs.wkt.clone().into_bytes();
+        data.push(0u8);
+        vlrs.push(Vlr {
+            reserved: 0u16,
+            user_id: "LASF_Projection".to_string(),
+            record_id: 2112u16,
+            record_length_after_header: data.len() as u16,
+            description: "OGC Coordinate System WKT".to_string(),
+            binary_data: data,
+        });
+    }
+    if point_format < 6 || srs.wkt.is_empty() {
+        if let Some(directory) = srs.to_geokey_directory() {
+            let mut data = vec![0u8; directory.len() * 2];
+            LittleEndian::write_u16_into(&directory, &mut data);
+            vlrs.push(Vlr {
+                reserved: 0u16,
+                user_id: "LASF_Projection".to_string(),
+                record_id: 34_735u16,
+                record_length_after_header: data.len() as u16,
+                description: "GeoTiff GeoKeyDirectoryTag".to_string(),
+                binary_data: data,
+            });
+        }
+    }
+    vlrs
+}
+
+/// Returns an offset for a coordinate axis that places the minimum of the data at (or just
+/// above) zero in the stored integers, leaving the full positive range of the i32 for the
+/// extent of the data. Whole-unit offsets are used where possible so that coordinates
+/// which are multiples of the scale factor remain exactly representable.
+fn offset_from_bounds(min: f64, scale_factor: f64) -> f64 {
+    if scale_factor <= 1f64 {
+        min.floor()
+    } else {
+        (min / scale_factor).floor() * scale_factor
+    }
+}
+
+/// Checks that a point can be stored with the scale factors and offsets of the header, i.e.
+/// that its coordinates fit within the 32-bit integers of the point records.
+pub(crate) fn check_coordinate_range(header: &LasHeader, x: f64, y: f64, z: f64) -> Result<(), Error> {
+    let axes = [("x", x, header.x_scale_factor, header.x_offset),
+                ("y", y, header.y_scale_factor, header.y_offset),
+                ("z", z, header.z_scale_factor, header.z_offset)];
+    for &(axis, value, scale_factor, offset) in &axes {
+        let stored = ((value - offset) / scale_factor).round();
+        if !(stored >= i32::MIN as f64 && stored <= i32::MAX as f64) {
+            return Err(Error::new(ErrorKind::InvalidData, format!("The {} coordinate {} cannot be stored using a scale factor of {} and an offset of {}; it lies outside of the 32-bit integer range. Use a larger scale factor or offsets derived from the data.", axis, value, scale_factor, offset)));
+        }
+    }
+    Ok(())
+}
+
+/// Updates the bounding box and the point counts of a header with a newly added point.
+pub(crate) fn update_header_summary(header: &mut LasHeader, p: &PointData) {
+    if p.x < header.min_x { header.min_x = p.x; }
+    if p.x > header.max_x { header.max_x = p.x; }
+    if p.y < header.min_y { header.min_y = p.y; }
+    if p.y > header.max_y { header.max_y = p.y; }
+    if p.z < header.min_z { header.min_z = p.z; }
+    if p.z > header.max_z { header.max_z = p.z; }
+
+    header.number_of_points += 1;
+    let mut which_return = p.bit_field.return_number() as usize;
+    if which_return == 0 { which_return = 1; }
+    if which_return > 15 { which_return = 15; }
+    header.number_of_points_by_return[which_return-1] += 1;
+}
+
+/// Writes the file header and the VLRs to `writer`. The version, header size, offset to the
+/// points and point record length are derived from the point format, the VLRs and the EVLRs
+/// and are updated in `header`. The EVLRs themselves are written by `write_evlrs`, which
+/// sets their location in `header`.
+pub(crate) fn write_header<W: Write>(writer: &mut W, header: &mut LasHeader, vlrs: &[&Vlr], evlrs: &[Vlr],
+    is_laz: bool, use_point_intensity: bool, use_point_userdata: bool, extra_bytes_length: u16) -> Result<(), Error> {
+    // Waveform data is either stored internally, in the waveform data packet EVLR, or
+    // in an external file, but not both.
+    if evlrs.iter().any(|v| v.is_waveform_data()) {
+        header.global_encoding.value |= 0b0000_0010u16;
+        header.global_encoding.value &= !0b0000_0100u16;
+    } else {
+        header.global_encoding.value &= !0b0000_0010u16;
+    }
+    header.number_of_evlrs = evlrs.len() as u32;
+
+    // The WKT bit indicates that the CRS is described by WKT rather than GeoKeys.
+    if vlrs.iter().any(|v| v.is_wkt()) || evlrs.iter().any(|v| v.is_wkt()) {
+        header.global_encoding.value |= 0b0001_0000u16;
+    } else {
+        header.global_encoding.value &= !0b0001_0000u16;
+    }
+
+    /////////////////////////////////
+    // Write the header to the file /
+    /////////////////////////////////
+    let mut u16_bytes: [u8; 2];
+    let mut u32_bytes: [u8; 4];
+    let mut u64_bytes: [u8; 8];
+
+    header.file_signature = "LASF".to_string();
+    writer.write_all(header.file_signature.as_bytes())?;
+
+    u16_bytes = u16::to_le_bytes(header.file_source_id);
+    writer.write_all(&u16_bytes)?;
+
+    u16_bytes = u16::to_le_bytes(header.global_encoding.value);
+    writer.write_all(&u16_bytes)?;
+
+    if header.project_id_used {
+        u32_bytes = u32::to_le_bytes(header.project_id1);
+        writer.write_all(&u32_bytes)?;
+
+        u16_bytes = u16::to_le_bytes(header.project_id2);
+        writer.write_all(&u16_bytes)?;
+
+        u16_bytes = u16::to_le_bytes(header.project_id3);
+        writer.write_all(&u16_bytes)?;
+
+        writer.write_all(&header.project_id4)?;
+    }
+
+    header.version_major = 1u8;
+    let mut u8_bytes: [u8; 1] = u8::to_le_bytes(header.version_major);
+    writer.write_all(&u8_bytes)?;
+
+    // The header's version is retained unless the point format or the EVLRs require a later
+    // one. The extended point formats (6-10) require LAS 1.4, as do any EVLRs other than a
+    // single waveform data packet record, which LAS 1.3 supports. Point formats 4 and 5
+    // require LAS 1.3 and formats 2 and 3 require LAS 1.2.
+    let needs_evlr_fields = evlrs.len() > 1 || evlrs.iter().any(|v| !v.is_waveform_data());
+    let required_minor = if header.point_format >= 6 || needs_evlr_fields {
+        4u8
+    } else if header.point_format >= 4 || !evlrs.is_empty() {
+        3u8
+    } else if header.point_format >= 2 {
+        2u8
+    } else {
+        0u8
+    };
+    header.version_minor = cmp::min(cmp::max(header.version_minor, required_minor), 4u8);
+    u8_bytes = u8::to_le_bytes(header.version_minor);
+    writer.write_all(&u8_bytes)?;
+
+    if header.system_id.is_empty() {
+        header.system_id = fixed_length_string("OTHER", 32);
+    } else if !header.system_id.len() != 32 {
+        header.system_id = fixed_length_string(&(header.system_id), 32);
+    }
+    writer.write_all(header.system_id.as_bytes())?; //string_bytes));
+
+    if header.generating_software.trim_matches(char::from(0)).trim().is_empty() {
+        header.generating_software = "whitebox_tools by John Lindsay".to_string();
+    }
+    header.generating_software = fixed_length_string(&(header.generating_software), 32);
+    //string_bytes = unsafe { mem::transmute("libgeospatial by John Lindsay   ") };
+    writer.write_all(header.generating_software.as_bytes())?;
+
+    let now = time::now();
+    header.file_creation_day = now.tm_yday as u16;
+    u16_bytes = u16::to_le_bytes(header.file_creation_day);
+    writer.write_all(&u16_bytes)?;
+
+    header.file_creation_year = (now.tm_year + 1900) as u16;
+    u16_bytes = u16::to_le_bytes(header.file_creation_year);
+    writer.write_all(&u16_bytes)?;
+
+    header.header_size = standard_header_size(header.version_minor);
+    u16_bytes = u16::to_le_bytes(header.header_size);
+    writer.write_all(&u16_bytes)?;
+
+    // figure out the offset to points
+    let mut total_vlr_size = 54 * vlrs.len() as u32;
+    for vlr in vlrs {
+        total_vlr_size += vlr.record_length_after_header as u32;
+    }
+    header.offset_to_points = header.header_size as u32 + total_vlr_size;
+    u32_bytes = u32::to_le_bytes(header.offset_to_points);
+    writer.write_all(&u32_bytes)?;
+
+    u32_bytes = u32::to_le_bytes(vlrs.len() as u32);
+    writer.write_all(&u32_bytes)?;
+
+    // LASzip flags compressed point formats by setting the high bit.
+    let point_format_byte = if is_laz { header.point_format | 0b1000_0000u8 } else { header.point_format };
+    u8_bytes = u8::to_le_bytes(point_format_byte);
+    writer.write_all(&u8_bytes)?;
+
+    if header.point_format > 10 {
+        return Err(Error::other("Unsupported point format"));
+    }
+    header.point_record_length = standard_record_length(header.point_format, use_point_intensity, use_point_userdata) + extra_bytes_length;
+
+    u16_bytes = u16::to_le_bytes(header.point_record_length);
+    writer.write_all(&u16_bytes)?;
+
+    // The legacy 32-bit point counts must be zero for the extended point formats
+    // or when the counts are too large to be represented.
+    let use_legacy_counts = header.point_format < 6 && header.number_of_points <= u32::MAX as u64;
+    let legacy_count = if use_legacy_counts { header.number_of_points as u32 } else { 0u32 };
+    u32_bytes = u32::to_le_bytes(legacy_count);
+    writer.write_all(&u32_bytes)?;
+
+    for i in 0..5 {
+        let legacy_count = if use_legacy_counts { header.number_of_points_by_return[i] as u32 } else { 0u32 };
+        u32_bytes = u32::to_le_bytes(legacy_count);
+        writer.write_all(&u32_bytes)?;
+    }
+
+    u64_bytes = f64::to_le_bytes(header.x_scale_factor);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.y_scale_factor);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.z_scale_factor);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.x_offset);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.y_offset);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.z_offset);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.max_x);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.min_x);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.max_y);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.min_y);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.max_z);
+    writer.write_all(&u64_bytes)?;
+
+    u64_bytes = f64::to_le_bytes(header.min_z);
+    writer.write_all(&u64_bytes)?;
+
+    if header.version_minor >= 3 {
+        u64_bytes = u64::to_le_bytes(header.waveform_data_start);
+        writer.write_all(&u64_bytes)?;
+    }
+
+    if header.version_minor >= 4 {
+        u64_bytes = u64::to_le_bytes(header.start_of_first_evlr);
+        writer.write_all(&u64_bytes)?;
+
+        u32_bytes = u32::to_le_bytes(header.number_of_evlrs);
+        writer.write_all(&u32_bytes)?;
+
+        u64_bytes = u64::to_le_bytes(header.number_of_points);
+        writer.write_all(&u64_bytes)?;
+
+        for i in 0..15 {
+            u64_bytes = u64::to_le_bytes(header.number_of_points_by_return[i]);
+            writer.write_all(&u64_bytes)?;
+        }
+    }
+
+    ///////////////////////////////
+    // Write the VLRs to the file /
+    ///////////////////////////////
+    for vlr in vlrs {
+        u16_bytes = u16::to_le_bytes(vlr.reserved);
+        writer.write_all(&u16_bytes)?;
+
+        let user_id: &str = &fixed_length_string(&vlr.user_id, 16);
+        //string_bytes = unsafe { mem::transmute(user_id) };
+        writer.write_all(user_id.as_bytes())?; //string_bytes));
+
+        u16_bytes = u16::to_le_bytes(vlr.record_id);
+        writer.write_all(&u16_bytes)?;
+
+        u16_bytes = u16::to_le_bytes(vlr.record_length_after_header);
+        writer.write_all(&u16_bytes)?;
+
+        let description: &str = &fixed_length_string(&vlr.description, 32);
+        //string_bytes = unsafe { mem::transmute(description) };
+        writer.write_all(description.as_bytes())?;
+
+        writer.write_all(&vlr.binary_data)?;
+    }
+
+    Ok(())
+}
+
+/// Writes the EVLRs at the current position of `writer`, i.e. following the point records,
+/// and updates the EVLR and waveform data locations in `header`. The header must then be
+/// rewritten.
+pub(crate) fn write_evlrs<W: Write + Seek>(writer: &mut W, header: &mut LasHeader, evlrs: &[Vlr]) -> Result<(), Error> {
+    let mut position = writer.stream_position()?;
+    header.start_of_first_evlr = if evlrs.is_empty() { 0u64 } else { position };
+    header.number_of_evlrs = evlrs.len() as u32;
+    header.waveform_data_start = 0u64;
+    for evlr in evlrs {
+        if evlr.is_waveform_data() {
+            header.waveform_data_start = position;
+        }
+        let mut evlr_header = [0u8; 60];
+        LittleEndian::write_u16(&mut evlr_header[0..2], evlr.reserved);
+        evlr_header[2..18].copy_from_slice(fixed_length_string(&evlr.user_id, 16).as_bytes());
+        LittleEndian::write_u16(&mut evlr_header[18..20], evlr.record_id);
+        LittleEndian::write_u64(&mut evlr_header[20..28], evlr.binary_data.len() as u64);
+        evlr_header[28..60].copy_from_slice(fixed_length_string(&evlr.description, 32).as_bytes());
+        writer.write_all(&evlr_header)?;
+        writer.write_all(&evlr.binary_data)?;
+        position += 60 + evlr.binary_data.len() as u64;
+    }
+    Ok(())
+}
+
+/// Decodes a single uncompressed point record, laid out according to the header's point
+/// format. The point format must be supported (see `check_point_format`).
+pub(crate) fn decode_point_record(buffer: &[u8], header: &LasHeader, use_point_intensity: bool, use_point_userdata: bool) -> LidarPointRecord {
+    let fmt = header.point_format;
+    let mut offset = 0usize;
+    let mut p: PointData = Default::default();
+    p.x = LittleEndian::read_i32(&buffer[offset..offset+4]) as f64 * header.x_scale_factor + header.x_offset;
+    offset += 4;
+    p.y = LittleEndian::read_i32(&buffer[offset..offset+4]) as f64 * header.y_scale_factor + header.y_offset;
+    offset += 4;
+    p.z = LittleEndian::read_i32(&buffer[offset..offset+4]) as f64 * header.z_scale_factor + header.z_offset;
+    offset += 4;
+    if fmt >= 6 {
+        p.intensity = LittleEndian::read_u16(&buffer[offset..offset+2]);
+        offset += 2;
+        p.bit_field = PointBitField { value: buffer[offset], flags: buffer[offset+1], extended: true };
+        offset += 2;
+        p.class_bit_field = ClassificationBitField { value: buffer[offset] };
+        offset += 1;
+        p.user_data = buffer[offset];
+        offset += 1;
+        p.scan_angle = LittleEndian::read_i16(&buffer[offset..offset+2]);
+        offset += 2;
+    } else {
+        // Intensity and user data are only optional in the oldest formats (0-3).
+        if use_point_intensity || fmt > 3 {
+            p.intensity = LittleEndian::read_u16(&buffer[offset..offset+2]);
+            offset += 2;
+        }
+        p.bit_field = PointBitField { value: buffer[offset], ..Default::default() };
+        offset += 1;
+        p.class_bit_field = ClassificationBitField { value: buffer[offset] };
+        offset += 1;
+        p.scan_angle = buffer[offset] as i8 as i16;
+        offset += 1;
+        if use_point_userdata || fmt > 3 {
+            p.user_data = buffer[offset];
+            offset += 1;
+        }
+    }
+    p.point_source_id = LittleEndian::read_u16(&buffer[offset..offset+2]);
+    offset += 2;
+
+    let mut gps_data = 0f64;
+    if fmt != 0 && fmt != 2 {
+        gps_data = LittleEndian::read_f64(&buffer[offset..offset+8]);
+        offset += 8;
+    }
+
+    let mut rgb_data: RgbData = Default::default();
+    if fmt == 2 || fmt == 3 || fmt == 5 || fmt == 7 || fmt == 8 || fmt == 10 {
+        rgb_data.red = LittleEndian::read_u16(&buffer[offset..offset+2]);
+        offset += 2;
+        rgb_data.green = LittleEndian::read_u16(&buffer[offset..offset+2]);
+        offset += 2;
+        rgb_data.blue = LittleEndian::read_u16(&buffer[offset..offset+2]);
+        offset += 2;
+    }
+
+    let mut nir_data = 0u16;
+    if fmt == 8 || fmt == 10 {
+        nir_data = LittleEndian::read_u16(&buffer[offset..offset+2]);
+        offset += 2;
+    }
+
+    let mut wave_packet: WaveformPacket = Default::default();
+    if fmt == 4 || fmt == 5 || fmt == 9 || fmt == 10 {
+        wave_packet.packet_descriptor_index = buffer[offset];
+        offset += 1;
+        wave_packet.offset_to_waveform_data = LittleEndian::read_u64(&buffer[offset..offset+8]);
+        offset += 8;
+        wave_packet.waveform_packet_size = LittleEndian::read_u32(&buffer[offset..offset+4]);
+        offset += 4;
+        wave_packet.ret_point_waveform_loc = LittleEndian::read_f32(&buffer[offset..offset+4]);
+        offset += 4;
+        wave_packet.xt = LittleEndian::read_f32(&buffer[offset..offset+4]);
+        offset += 4;
+        wave_packet.yt = LittleEndian::read_f32(&buffer[offset..offset+4]);
+        offset += 4;
+        wave_packet.zt = LittleEndian::read_f32(&buffer[offset..offset+4]);
+    }
+
+    match fmt {
+        0 => LidarPointRecord::PointRecord0 { point_data: p },
+        1 => LidarPointRecord::PointRecord1 { point_data: p, gps_data },
+        2 => LidarPointRecord::PointRecord2 { point_data: p, rgb_data },
+        3 => LidarPointRecord::PointRecord3 { point_data: p, gps_data, rgb_data },
+        4 => LidarPointRecord::PointRecord4 { point_data: p, gps_data, wave_packet },
+        5 => LidarPointRecord::PointRecord5 { point_data: p, gps_data, rgb_data,
+            wave_packet },
+        6 => LidarPointRecord::PointRecord6 { point_data: p, gps_data },
+        7 => LidarPointRecord::PointRecord7 { point_data: p, gps_data, rgb_data },
+        8 => LidarPointRecord::PointRecord8 { point_data: p, gps_data, rgb_data, nir_data },
+        9 => LidarPointRecord::PointRecord9 { point_data: p, gps_data, wave_packet },
+        10 => LidarPointRecord::PointRecord10 { point_data: p, gps_data, rgb_data,
+            nir_data, wave_packet },
+        _ => panic!("Unsupported point format"),
+    }
+}
+
+/// Writes a single uncompressed point record, laid out according to the header's point
+/// format, to `writer`. Fields that the header's point format requires but the record
+/// lacks are written as zero.
+pub(crate) fn encode_point_record<W: Write>(writer: &mut W, point: &LidarPointRecord, header: &LasHeader,
+    use_point_intensity: bool, use_point_userdata: bool) -> Result<(), Error> {
+    let fmt = header.point_format;
+    let p = point.point_data();
+    let mut u16_bytes: [u8; 2];
+    let mut u32_bytes: [u8; 4];
+    let mut u64_bytes: [u8; 8];
+
+    let mut val = ((p.x - header.x_offset) / header.x_scale_factor).round() as i32;
+    u32_bytes = i32::to_le_bytes(val);
+    writer.write_all(&u32_bytes)?;
+
+    val = ((p.y - header.y_offset) / header.y_scale_factor).round() as i32;
+    u32_bytes = i32::to_le_bytes(val);
+    writer.write_all(&u32_bytes)?;
+
+    val = ((p.z - header.z_offset) / header.z_scale_factor).round() as i32;
+    u32_bytes = i32::to_le_bytes(val);
+    writer.write_all(&u32_bytes)?;
+
+    if fmt >= 6 {
+        u16_bytes = u16::to_le_bytes(p.intensity);
+        writer.write_all(&u16_bytes)?;
+
+        let (return_byte, flags_byte) = p.extended_bit_field_values();
+        writer.write_all(&[return_byte, flags_byte, p.classification(), p.user_data])?;
+
+        u16_bytes = i16::to_le_bytes(p.extended_scan_angle());
+        writer.write_all(&u16_bytes)?;
+    } else {
+        if use_point_intensity || fmt > 3 {
+            u16_bytes = u16::to_le_bytes(p.intensity);
+            writer.write_all(&u16_bytes)?;
+        }
+
+        writer.write_all(&[p.legacy_bit_field_value(), p.legacy_class_bit_field_value(), p.legacy_scan_angle() as u8])?;
+
+        if use_point_userdata || fmt > 3 {
+            writer.write_all(&[p.user_data])?;
+        }
+    }
+
+    u16_bytes = u16::to_le_bytes(p.point_source_id);
+    writer.write_all(&u16_bytes)?;
+
+    if fmt != 0 && fmt != 2 {
+        u64_bytes = f64::to_le_bytes(point.gps_time().unwrap_or(0f64));
+        writer.write_all(&u64_bytes)?;
+    }
+
+    if fmt == 2 || fmt == 3 || fmt == 5 || fmt == 7 || fmt == 8 || fmt == 10 {
+        let rgb = point.rgb().unwrap_or_default();
+        u16_bytes = u16::to_le_bytes(rgb.red);
+        writer.write_all(&u16_bytes)?;
+
+        u16_bytes = u16::to_le_bytes(rgb.green);
+        writer.write_all(&u16_bytes)?;
+
+        u16_bytes = u16::to_le_bytes(rgb.blue);
+        writer.write_all(&u16_bytes)?;
+    }
+
+    if fmt == 8 || fmt == 10 {
+        u16_bytes = u16::to_le_bytes(point.nir().unwrap_or(0u16));
+        writer.write_all(&u16_bytes)?;
+    }
+
+    if fmt == 4 || fmt == 5 || fmt == 9 || fmt == 10 {
+        let wp = point.waveform_packet().unwrap_or_default();
+        writer.write_all(&[wp.packet_descriptor_index])?;
+
+        u64_bytes = u64::to_le_bytes(wp.offset_to_waveform_data);
+        writer.write_all(&u64_bytes)?;
+
+        u32_bytes = u32::to_le_bytes(wp.waveform_packet_size);
+        writer.write_all(&u32_bytes)?;
+
+        u32_bytes = f32::to_le_bytes(wp.ret_point_waveform_loc);
+        writer.write_all(&u32_bytes)?;
+
+        u32_bytes = f32::to_le_bytes(wp.xt);
+        writer.write_all(&u32_bytes)?;
+
+        u32_bytes = f32::to_le_bytes(wp.yt);
+        writer.write_all(&u32_bytes)?;
+
+        u32_bytes = f32::to_le_bytes(wp.zt);
+        writer.write_all(&u32_bytes)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{Error, ErrorKind};
+    use byteorder::{ByteOrder, LittleEndian};
+    use crate::io_utils::temp_file_name;
+    use crate::lidar::extra_bytes::{ ExtraBytesDataType, ExtraBytesDescriptor };
+    use crate::lidar::header::LasHeader;
+    use crate::lidar::las_index::index_file_name;
+    use crate::lidar::point_data::PointData;
+    use crate::spatial_ref_system::SpatialReference;
+    use super::{LasFile, LidarPointRecord};
+
+    fn header(version_minor: u8, point_format: u8) -> LasHeader {
+        let mut header: LasHeader = Default::default();
+        header.project_id_used = true;
+        header.version_major = 1;
+        header.version_minor = version_minor;
+        header.point_format = point_format;
+        header.x_scale_factor = 0.01;
+        header.y_scale_factor = 0.01;
+        header.z_scale_factor = 0.01;
+        header
+    }
+
+    fn point_record(point_format: u8, i: usize) -> LidarPointRecord {
+        let mut p: PointData = Default::default();
+        p.x = 1000.0 + (i % 10) as f64 * 2.5;
+        p.y = 2000.0 + (i / 10) as f64 * 2.5;
+        p.z = 100.0 + (i % 7) as f64;
+        p.intensity = i as u16 * 3;
+        let gps_time = 5000.0 + i as f64;
+        if point_format == 6 {
+            p.bit_field.extended = true;
+            p.bit_field.value = 0b0001_0001;
+            p.set_classification(200);
+            LidarPointRecord::PointRecord6 { point_data: p, gps_data: gps_time }
+        } else {
+            p.bit_field.value = 0b0000_1001;
+            LidarPointRecord::PointRecord1 { point_data: p, gps_data: gps_time }
+        }
+    }
+
+    fn write_test_file(file_name: &str, header: LasHeader, num_points: usize) -> LasFile {
+        let point_format = header.point_format;
+        let mut output = LasFile::new(file_name, "w").unwrap();
+        output.add_header(header);
+        for i in 0..num_points {
+            output.add_point_record(point_record(point_format, i));
+        }
+        output.write().unwrap();
+        output
+    }
+
+    fn assert_same_points(a: &LasFile, b: &LasFile) {
+        assert_eq!(a.header.number_of_points, b.header.number_of_points);
+        for i in 0..a.header.number_of_points as usize {
+            let (pd, qd) = (a.get_point_info(i), b.get_point_info(i));
+            assert!((pd.x - qd.x).abs() < 1e-6 && (pd.y - qd.y).abs() < 1e-6 && (pd.z - qd.z).abs() < 1e-6);
+            assert_eq!((pd.intensity, pd.classification(), pd.bit_field.value), (qd.intensity, qd.classification(), qd.bit_field.value));
+            assert_eq!(a.get_gps_time(i).unwrap(), b.get_gps_time(i).unwrap());
+        }
+    }
+
+    fn write_test_file_with_extra_bytes(file_name: &str, header: LasHeader, num_points: usize) {
+        let point_format = header.point_format;
+        let mut output = LasFile::new(file_name, "w").unwrap();
+        output.add_header(header);
+        output.add_extra_bytes_descriptor(ExtraBytesDescriptor::new("Height", ExtraBytesDataType::F64, "height above ground")).unwrap();
+        output.add_extra_bytes_descriptor(ExtraBytesDescriptor::new("Segment", ExtraBytesDataType::U16, "segment identifier")).unwrap();
+        for i in 0..num_points {
+            output.add_point_record(point_record(point_format, i));
+            output.set_extra_f64(i, "Height", i as f64 * 0.25).unwrap();
+            output.set_extra_f64(i, "Segment", (i % 5) as f64).unwrap();
+        }
+        output.write().unwrap();
+    }
+
+    fn read_error(file_name: &str) -> Error {
+        match LasFile::new(file_name, "r") {
+            Ok(_) => panic!("{} was read without an error", file_name),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn laz_round_trip() {
+        for &(version_minor, point_format) in &[(2u8, 1u8), (4u8, 6u8)] {
+            let las_file = temp_file_name(&format!("round_trip_{}.las", point_format));
+            let laz_file = temp_file_name(&format!("round_trip_{}.laz", point_format));
+            write_test_file(&las_file, header(version_minor, point_format), 500);
+            write_test_file(&laz_file, header(version_minor, point_format), 500);
+            let las = LasFile::new(&las_file, "r").unwrap();
+            let laz = LasFile::new(&laz_file, "r").unwrap();
+            let las_size = fs::metadata(&las_file).unwrap().len();
+            let laz_bytes = fs::read(&laz_file).unwrap();
+            let _ = fs::remove_file(&las_file);
+            let _ = fs::remove_file(&laz_file);
+
+            // the compression bit of the point format
+            assert_eq!(laz_bytes[104], point_format | 0b1000_0000);
+            assert!((laz_bytes.len() as u64) < las_size);
+            assert_eq!(laz.header.point_format, point_format);
+            assert!(!laz.vlr_data.iter().any(|v| v.record_id == 22_204));
+            assert_same_points(&las, &laz);
+        }
+    }
+
+    fn write_with_spatial_reference(file_name: &str, header: LasHeader, srs: &SpatialReference) -> LasFile {
+        let point_format = header.point_format;
+        let mut output = LasFile::new(file_name, "w").unwrap();
+        output.add_header(header);
+        output.set_spatial_reference(srs);
+        for i in 0..10 {
+            output.add_point_record(point_record(point_format, i));
+        }
+        output.write().unwrap();
+        let input = LasFile::new(file_name, "r").unwrap();
+        let _ = fs::remove_file(file_name);
+        input
+    }
+
+    #[test]
+    fn spatial_reference_round_trip() {
+        let wkt = "PROJCS[\"WGS 84 / UTM zone 11N\",GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",SPHEROID[\"WGS 84\",6378137,298.257223563]],\
+PRIMEM[\"Greenwich\",0],UNIT[\"degree\",0.0174532925199433]],PROJECTION[\"Transverse_Mercator\"],UNIT[\"metre\",1],\
+AUTHORITY[\"EPSG\",\"32611\"]]";
+        let srs = SpatialReference::from_wkt(wkt);
+
+        // point formats 6-10 describe the CRS with WKT only
+        let input = write_with_spatial_reference(&temp_file_name("srs_1_4.las"), header(4, 6), &srs);
+        assert_eq!(input.get_spatial_reference(), srs);
+        assert!(input.vlr_data.iter().any(|v| v.is_wkt()));
+        assert!(!input.vlr_data.iter().any(|v| v.record_id == 34_735));
+        assert_eq!(input.header.global_encoding.value & 0b0001_0000, 0b0001_0000);
+
+        // older point formats have GeoKeys as well
+        let input = write_with_spatial_reference(&temp_file_name("srs_1_2.las"), header(2, 1), &srs);
+        assert_eq!(input.get_spatial_reference(), srs);
+        assert!(input.vlr_data.iter().any(|v| v.record_id == 34_735));
+
+        // an EPSG code alone is written as GeoKeys
+        let input = write_with_spatial_reference(&temp_file_name("srs_epsg.las"), header(2, 1), &SpatialReference::from_epsg(26917));
+        assert_eq!(input.get_spatial_reference(), SpatialReference::from_epsg(26917));
+        assert!(!input.vlr_data.iter().any(|v| v.is_wkt()));
+        assert_eq!(input.header.global_encoding.value & 0b0001_0000, 0);
+
+        // setting the CRS replaces the records of the input
+        let output_file = temp_file_name("srs_replaced.las");
+        let mut output = LasFile::initialize_using_file(&output_file, &input);
+        output.set_spatial_reference(&srs);
+        for i in 0..10 {
+            output.add_point_record(input.get_record(i));
+        }
+        output.write().unwrap();
+        let output = LasFile::new(&output_file, "r").unwrap();
+        let _ = fs::remove_file(&output_file);
+        assert_eq!(output.get_spatial_reference(), srs);
+        assert_eq!(output.vlr_data.iter().filter(|v| v.is_crs_record()).count(), 2);
+    }
+
+    #[test]
+    fn las_1_4_round_trip() {
+        let file_name = temp_file_name("round_trip_1_4.las");
+        write_test_file(&file_name, header(4, 6), 100);
+        let input = LasFile::new(&file_name, "r").unwrap();
+        let _ = fs::remove_file(&file_name);
+
+        assert_eq!((input.header.version_major, input.header.version_minor), (1, 4));
+        assert_eq!(input.header.point_format, 6);
+        assert_eq!(input.header.number_of_points, 100);
+        for i in 0..100 {
+            let expected = point_record(6, i);
+            let record = input.get_record(i);
+            let (p, q) = (record.point_data(), expected.point_data());
+            assert!((p.x - q.x).abs() < 1e-6 && (p.y - q.y).abs() < 1e-6 && (p.z - q.z).abs() < 1e-6);
+            assert_eq!(p.intensity, q.intensity);
+            assert_eq!(p.classification(), 200);
+            assert_eq!((p.return_number(), p.number_of_returns()), (1, 1));
+            assert_eq!(record.gps_time(), expected.gps_time());
+        }
+    }
+
+    #[test]
+    fn point_count_beyond_end_of_file_is_an_error() {
+        let file_name = temp_file_name("point_count.las");
+        write_test_file(&file_name, header(2, 1), 10);
+        let mut data = fs::read(&file_name).unwrap();
+        // the legacy point count
+        LittleEndian::write_u32(&mut data[107..111], u32::MAX);
+        fs::write(&file_name, &data).unwrap();
+        assert_eq!(read_error(&file_name).kind(), ErrorKind::InvalidData);
+
+        // a 64-bit point count whose length in bytes overflows
+        write_test_file(&file_name, header(4, 6), 10);
+        let mut data = fs::read(&file_name).unwrap();
+        LittleEndian::write_u64(&mut data[247..255], u64::MAX / 2);
+        fs::write(&file_name, &data).unwrap();
+        assert_eq!(read_error(&file_name).kind(), ErrorKind::InvalidData);
+        let _ = fs::remove_file(&file_name);
+    }
+
+    #[test]
+    fn short_header_is_an_error() {
+        let file_name = temp_file_name("short_header.las");
+        write_test_file(&file_name, header(4, 6), 0);
+        let data = fs::read(&file_name).unwrap();
+        fs::write(&file_name, &data[..300]).unwrap();
+        assert_eq!(read_error(&file_name).kind(), ErrorKind::InvalidData);
+
+        // a LAS 1.4 file that states the header size of LAS 1.2
+        let mut data = data.clone();
+        LittleEndian::write_u16(&mut data[94..96], 227);
+        fs::write(&file_name, &data).unwrap();
+        assert_eq!(read_error(&file_name).kind(), ErrorKind::InvalidData);
+        let _ = fs::remove_file(&file_name);
+    }
+
+    #[test]
+    fn spatial_index_is_returned_when_it_cannot_be_saved() {
+        let file_name = temp_file_name("unsaved_index.las");
+        write_test_file(&file_name, header(2, 1), 100);
+        // a directory in place of the .lax file cannot be read or written
+        let lax_file = index_file_name(&file_name);
+        let _ = fs::remove_file(&lax_file);
+        fs::create_dir_all(&lax_file).unwrap();
+
+        let mut input = LasFile::new(&file_name, "r").unwrap();
+        let num_points: u64 = input.get_spatial_index().unwrap()
+            .intervals_in_bbox(990.0, 1990.0, 1030.0, 2030.0)
+            .iter().map(|&(start, end)| end - start).sum();
+        let _ = fs::remove_dir(&lax_file);
+        let _ = fs::remove_file(&file_name);
+        assert_eq!(num_points, 100);
+    }
+
+    #[test]
+    fn initialize_using_file_retains_extra_bytes() {
+        let input_file = temp_file_name("extra_bytes_input.las");
+        let output_file = temp_file_name("extra_bytes_output.las");
+        write_test_file_with_extra_bytes(&input_file, header(2, 1), 50);
+        let input = LasFile::new(&input_file, "r").unwrap();
+        let mut output = LasFile::initialize_using_file(&output_file, &input);
+        for i in 0..50 {
+            output.add_point_record_with_extra_bytes(input.get_record(i), input.get_extra_bytes(i));
+        }
+        output.write().unwrap();
+        let output = LasFile::new(&output_file, "r").unwrap();
+        let _ = fs::remove_file(&input_file);
+        let _ = fs::remove_file(&output_file);
+
+        assert_eq!(output.get_extra_bytes_descriptors(), input.get_extra_bytes_descriptors());
+        assert_eq!(output.header.point_record_length, 28 + 10);
+        for i in 0..50 {
+            assert_eq!(output.get_extra_f64(i, "Height").unwrap(), i as f64 * 0.25);
+            assert_eq!(output.get_extra_f64(i, "Segment").unwrap(), (i % 5) as f64);
+        }
+    }
+
+    #[test]
+    fn laz_round_trip_of_extra_bytes() {
+        let file_name = temp_file_name("extra_bytes.laz");
+        write_test_file_with_extra_bytes(&file_name, header(2, 1), 50);
+        let input = LasFile::new(&file_name, "r").unwrap();
+        let _ = fs::remove_file(&file_name);
+        assert_eq!(input.get_extra_bytes_descriptors().len(), 2);
+        for i in 0..50 {
+            assert_eq!(input.get_extra_f64(i, "Height").unwrap(), i as f64 * 0.25);
+            assert_eq!(input.get_extra_f64(i, "Segment").unwrap(), (i % 5) as f64);
+        }
+    }
+}
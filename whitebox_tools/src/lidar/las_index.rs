@@ -0,0 +1,366 @@
+use std::cmp;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{BufReader, BufWriter, Error, ErrorKind};
+use std::path::Path;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+/// Points whose index is within this many points of the end of a cell's last interval
+/// extend that interval rather than starting a new one; reading a few unneeded points is
+/// cheaper than seeking.
+const INTERVAL_THRESHOLD: u32 = 1_000;
+
+/// Sibling cells that together hold fewer points than this are merged into their parent.
+const MINIMUM_CELL_POINTS: u32 = 100_000;
+
+/// The maximum depth of the quadtree.
+const MAX_LEVELS: u32 = 12;
+
+/// A spatial index of the points of a LAS file. The index is a quadtree whose cells record
+/// the ranges (intervals) of point indices that fall within them, such that a spatial
+/// query need only read the point records in the intervals of the cells that intersect the
+/// query area. The index is stored alongside the LAS file in a .lax file, using the layout
+/// of the LAStools lasindex format, so that indices can be shared with LAStools.
+///
+/// An index identifies candidate points only; the points within the intervals of a cell
+/// must still be tested against the query area.
+#[derive(Default, Clone, Debug)]
+pub struct LasIndex {
+    levels: u32,
+    min_x: f32,
+    max_x: f32,
+    min_y: f32,
+    max_y: f32,
+    cells: BTreeMap<i32, IndexCell>,
+}
+
+#[derive(Default, Clone, Debug)]
+struct IndexCell {
+    num_points: u32,
+    // inclusive point index ranges, in increasing order
+    intervals: Vec<(u32, u32)>,
+}
+
+impl LasIndex {
+    /// Builds an index of points from their xy coordinates, which are given in point
+    /// record order. The bounds are those of the data, e.g. from the LAS header.
+    pub fn build<I>(points: I, min_x: f64, max_x: f64, min_y: f64, max_y: f64, num_points: u64) -> Result<LasIndex, Error>
+        where I: Iterator<Item = (f64, f64)> {
+        if num_points > u32::MAX as u64 {
+            return Err(Error::new(ErrorKind::InvalidInput, "Files with more than 4,294,967,295 points cannot be indexed."));
+        }
+
+        // Leaf cells hold roughly a thousand points each, if the points are evenly spread.
+        let area = (max_x - min_x).max(1f64) * (max_y - min_y).max(1f64);
+        let mut cell_size = (area * INTERVAL_THRESHOLD as f64 / cmp::max(num_points, 1) as f64).sqrt();
+        let extent = (max_x - min_x).max(max_y - min_y);
+        if extent / cell_size > (1u64 << MAX_LEVELS) as f64 {
+            cell_size = extent / (1u64 << MAX_LEVELS) as f64;
+        }
+        let mut index = LasIndex::new(min_x, max_x, min_y, max_y, cell_size as f32)?;
+
+        let mut leaves: BTreeMap<i32, IndexCell> = BTreeMap::new();
+        for (point_index, (x, y)) in points.enumerate() {
+            let cell_index = index.cell_index(x, y, index.levels);
+            let cell = leaves.entry(cell_index).or_default();
+            cell.add(point_index as u32);
+        }
+        index.cells = leaves;
+        index.merge_sparse_cells();
+        Ok(index)
+    }
+
+    /// Creates an empty quadtree covering the bounds, enlarged to a whole number of cells
+    /// and to a power of two cells on each side, as LAStools does.
+    fn new(bb_min_x: f64, bb_max_x: f64, bb_min_y: f64, bb_max_y: f64, cell_size: f32) -> Result<LasIndex, Error> {
+        let cs = cell_size as f64;
+        let mut min_x = if bb_min_x >= 0f64 { cs * (bb_min_x / cs).trunc() } else { cs * ((bb_min_x / cs).trunc() - 1f64) };
+        let mut max_x = if bb_max_x >= 0f64 { cs * ((bb_max_x / cs).trunc() + 1f64) } else { cs * (bb_max_x / cs).trunc() };
+        let mut min_y = if bb_min_y >= 0f64 { cs * (bb_min_y / cs).trunc() } else { cs * ((bb_min_y / cs).trunc() - 1f64) };
+        let mut max_y = if bb_max_y >= 0f64 { cs * ((bb_max_y / cs).trunc() + 1f64) } else { cs * (bb_max_y / cs).trunc() };
+
+        let cells_x = ((max_x - min_x) / cs + 0.5).floor() as u64;
+        let cells_y = ((max_y - min_y) / cs + 0.5).floor() as u64;
+        if cells_x == 0 || cells_y == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "The spatial index bounds are empty."));
+        }
+
+        // the number of levels needed to get to that many cells
+        let mut c = cmp::max(cells_x, cells_y) - 1;
+        let mut levels = 0u32;
+        while c > 0 {
+            c >>= 1;
+            levels += 1;
+        }
+
+        // enlarge the bounds to the size of the quadtree
+        c = (1u64 << levels) - cells_x;
+        min_x -= (c - c / 2) as f64 * cs;
+        max_x += (c / 2) as f64 * cs;
+        c = (1u64 << levels) - cells_y;
+        min_y -= (c - c / 2) as f64 * cs;
+        max_y += (c / 2) as f64 * cs;
+
+        Ok(LasIndex {
+            levels,
+            min_x: min_x as f32,
+            max_x: max_x as f32,
+            min_y: min_y as f32,
+            max_y: max_y as f32,
+            cells: BTreeMap::new(),
+        })
+    }
+
+    /// Reads an index from a .lax file.
+    pub fn read(file_name: &str) -> Result<LasIndex, Error> {
+        let mut r = BufReader::new(File::open(file_name)?);
+        read_signature(&mut r, b"LASX")?;
+        let _version = r.read_u32::<LittleEndian>()?;
+
+        read_signature(&mut r, b"LASS")?;
+        let spatial_type = r.read_u32::<LittleEndian>()?;
+        if spatial_type != 0 {
+            return Err(Error::new(ErrorKind::InvalidData, "Only quadtree spatial indices are supported."));
+        }
+        read_signature(&mut r, b"LASQ")?;
+        let _version = r.read_u32::<LittleEndian>()?;
+        let mut index: LasIndex = Default::default();
+        index.levels = r.read_u32::<LittleEndian>()?;
+        let level_index = r.read_u32::<LittleEndian>()?;
+        let _implicit_levels = r.read_u32::<LittleEndian>()?;
+        if level_index != 0 || index.levels > 15 {
+            return Err(Error::new(ErrorKind::InvalidData, "The quadtree of the spatial index is not supported."));
+        }
+        index.min_x = r.read_f32::<LittleEndian>()?;
+        index.max_x = r.read_f32::<LittleEndian>()?;
+        index.min_y = r.read_f32::<LittleEndian>()?;
+        index.max_y = r.read_f32::<LittleEndian>()?;
+
+        read_signature(&mut r, b"LASV")?;
+        let _version = r.read_u32::<LittleEndian>()?;
+        let num_cells = r.read_u32::<LittleEndian>()?;
+        for _ in 0..num_cells {
+            let cell_index = r.read_i32::<LittleEndian>()?;
+            let num_intervals = r.read_u32::<LittleEndian>()?;
+            let mut cell = IndexCell { num_points: r.read_u32::<LittleEndian>()?, intervals: vec![] };
+            for _ in 0..num_intervals {
+                let start = r.read_u32::<LittleEndian>()?;
+                let end = r.read_u32::<LittleEndian>()?;
+                cell.intervals.push((start, end));
+            }
+            index.cells.insert(cell_index, cell);
+        }
+        Ok(index)
+    }
+
+    /// Writes the index to a .lax file.
+    pub fn write(&self, file_name: &str) -> Result<(), Error> {
+        let mut w = BufWriter::new(File::create(file_name)?);
+        w.write_all(b"LASX")?;
+        w.write_u32::<LittleEndian>(0)?;
+
+        w.write_all(b"LASS")?;
+        w.write_u32::<LittleEndian>(0)?; // quadtree
+        w.write_all(b"LASQ")?;
+        w.write_u32::<LittleEndian>(0)?;
+        w.write_u32::<LittleEndian>(self.levels)?;
+        w.write_u32::<LittleEndian>(0)?; // level index
+        w.write_u32::<LittleEndian>(0)?; // implicit levels
+        w.write_f32::<LittleEndian>(self.min_x)?;
+        w.write_f32::<LittleEndian>(self.max_x)?;
+        w.write_f32::<LittleEndian>(self.min_y)?;
+        w.write_f32::<LittleEndian>(self.max_y)?;
+
+        w.write_all(b"LASV")?;
+        w.write_u32::<LittleEndian>(0)?;
+        w.write_u32::<LittleEndian>(self.cells.len() as u32)?;
+        for (cell_index, cell) in &self.cells {
+            w.write_i32::<LittleEndian>(*cell_index)?;
+            w.write_u32::<LittleEndian>(cell.intervals.len() as u32)?;
+            w.write_u32::<LittleEndian>(cell.num_points)?;
+            for &(start, end) in &cell.intervals {
+                w.write_u32::<LittleEndian>(start)?;
+                w.write_u32::<LittleEndian>(end)?;
+            }
+        }
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Returns the ranges of point indices, as half-open ranges in increasing order, that
+    /// may contain points within the bounding box.
+    pub fn intervals_in_bbox(&self, min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Vec<(u64, u64)> {
+        let mut intervals = vec![];
+        for (cell_index, cell) in &self.cells {
+            let (cell_min_x, cell_min_y, cell_max_x, cell_max_y) = self.cell_bounds(*cell_index);
+            if cell_min_x <= max_x && cell_max_x >= min_x && cell_min_y <= max_y && cell_max_y >= min_y {
+                for &(start, end) in &cell.intervals {
+                    intervals.push((start as u64, end as u64 + 1));
+                }
+            }
+        }
+
+        // merge overlapping and adjacent ranges
+        intervals.sort();
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(intervals.len());
+        for (start, end) in intervals {
+            if let Some(last) = merged.last_mut() {
+                if start <= last.1 {
+                    last.1 = cmp::max(last.1, end);
+                    continue;
+                }
+            }
+            merged.push((start, end));
+        }
+        merged
+    }
+
+    /// Returns the index of the cell containing the point at a level of the quadtree.
+    /// Cells are numbered level by level, such that the cells of a level follow those of
+    /// all of the levels above it. Arithmetic is in f32, matching LAStools.
+    fn cell_index(&self, x: f64, y: f64, level: u32) -> i32 {
+        let (x, y) = (x as f32, y as f32);
+        let (mut cell_min_x, mut cell_max_x) = (self.min_x, self.max_x);
+        let (mut cell_min_y, mut cell_max_y) = (self.min_y, self.max_y);
+        let mut level_index = 0u32;
+        for _ in 0..level {
+            level_index <<= 2;
+            let cell_mid_x = (cell_min_x + cell_max_x) / 2f32;
+            let cell_mid_y = (cell_min_y + cell_max_y) / 2f32;
+            if x < cell_mid_x {
+                cell_max_x = cell_mid_x;
+            } else {
+                cell_min_x = cell_mid_x;
+                level_index |= 1;
+            }
+            if y < cell_mid_y {
+                cell_max_y = cell_mid_y;
+            } else {
+                cell_min_y = cell_mid_y;
+                level_index |= 2;
+            }
+        }
+        (level_offset(level) + level_index) as i32
+    }
+
+    /// Returns the bounds (min x, min y, max x, max y) of a cell.
+    fn cell_bounds(&self, cell_index: i32) -> (f64, f64, f64, f64) {
+        let cell_index = cell_index as u32;
+        let mut level = 0u32;
+        while level < 15 && level_offset(level + 1) <= cell_index {
+            level += 1;
+        }
+        let level_index = cell_index - level_offset(level);
+        let (mut cell_min_x, mut cell_max_x) = (self.min_x, self.max_x);
+        let (mut cell_min_y, mut cell_max_y) = (self.min_y, self.max_y);
+        for l in (0..level).rev() {
+            let quadrant = (level_index >> (2 * l)) & 3;
+            let cell_mid_x = (cell_min_x + cell_max_x) / 2f32;
+            let cell_mid_y = (cell_min_y + cell_max_y) / 2f32;
+            if quadrant & 1 == 0 { cell_max_x = cell_mid_x; } else { cell_min_x = cell_mid_x; }
+            if quadrant & 2 == 0 { cell_max_y = cell_mid_y; } else { cell_min_y = cell_mid_y; }
+        }
+        (cell_min_x as f64, cell_min_y as f64, cell_max_x as f64, cell_max_y as f64)
+    }
+
+    /// Merges sibling cells that together hold too few points to be worth reading
+    /// separately into their parent, working up the quadtree.
+    fn merge_sparse_cells(&mut self) {
+        for level in (1..self.levels + 1).rev() {
+            let mut groups: BTreeMap<u32, Vec<i32>> = BTreeMap::new();
+            for cell_index in self.cells.keys() {
+                let ci = *cell_index as u32;
+                if ci >= level_offset(level) && ci < level_offset(level + 1) {
+                    let parent = (ci - level_offset(level)) >> 2;
+                    groups.entry(parent).or_default().push(*cell_index);
+                }
+            }
+            for (parent, children) in groups {
+                let total: u64 = children.iter().map(|c| self.cells[c].num_points as u64).sum();
+                if total >= MINIMUM_CELL_POINTS as u64 {
+                    continue;
+                }
+                let parent_index = (level_offset(level - 1) + parent) as i32;
+                let mut merged = self.cells.remove(&parent_index).unwrap_or_default();
+                for c in &children {
+                    let cell = self.cells.remove(c).unwrap();
+                    merged.num_points += cell.num_points;
+                    merged.intervals.extend(cell.intervals);
+                }
+                merged.merge_intervals();
+                self.cells.insert(parent_index, merged);
+            }
+        }
+    }
+}
+
+impl IndexCell {
+    fn add(&mut self, point_index: u32) {
+        self.num_points += 1;
+        if let Some(last) = self.intervals.last_mut() {
+            if point_index <= last.1.saturating_add(INTERVAL_THRESHOLD) {
+                last.1 = point_index;
+                return;
+            }
+        }
+        self.intervals.push((point_index, point_index));
+    }
+
+    fn merge_intervals(&mut self) {
+        self.intervals.sort();
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(self.intervals.len());
+        for &(start, end) in &self.intervals {
+            if let Some(last) = merged.last_mut() {
+                if start <= last.1.saturating_add(INTERVAL_THRESHOLD) {
+                    last.1 = cmp::max(last.1, end);
+                    continue;
+                }
+            }
+            merged.push((start, end));
+        }
+        self.intervals = merged;
+    }
+}
+
+/// Returns the index of the first cell of a level of the quadtree.
+fn level_offset(level: u32) -> u32 {
+    let mut offset = 0u32;
+    for l in 0..level {
+        offset += 1u32 << (2 * l);
+    }
+    offset
+}
+
+/// Returns the name of the .lax file that indexes a LAS file.
+pub fn index_file_name(las_file_name: &str) -> String {
+    Path::new(las_file_name).with_extension("lax").to_string_lossy().to_string()
+}
+
+fn read_signature<R: Read>(r: &mut R, signature: &[u8; 4]) -> Result<(), Error> {
+    let mut buf = [0u8; 4];
+    r.read_exact(&mut buf)?;
+    if &buf != signature {
+        return Err(Error::new(ErrorKind::InvalidData, "The spatial index file is not formatted correctly."));
+    }
+    Ok(())
+}
+
+/// Returns true if the point lies within the polygon, using the even-odd rule.
+pub fn point_in_polygon(x: f64, y: f64, polygon: &[(f64, f64)]) -> bool {
+    let n = polygon.len();
+    if n < 3 {
+        return false;
+    }
+    let mut inside = false;
+    let mut j = n - 1;
+    for i in 0..n {
+        let (xi, yi) = polygon[i];
+        let (xj, yj) = polygon[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
@@ -0,0 +1,192 @@
+use std::cmp;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{BufReader, Error, ErrorKind, SeekFrom};
+use laz::LasZipDecompressor;
+use crate::lidar::extra_bytes::ExtraBytesDescriptor;
+use crate::lidar::header::LasHeader;
+use crate::lidar::las::{ check_point_format, decode_point_record, extra_bytes_descriptors, laszip_decompressor, standard_record_length,
+    LasFile, LidarPointRecord };
+use crate::lidar::vlr::Vlr;
+use crate::raster::geotiff::geokeys::GeoKeys;
+use crate::spatial_ref_system::SpatialReference;
+
+/// A LasReader reads the point records of a LAS or LAZ file in chunks, rather than
+/// loading the entire file into memory as a LasFile does. The header, VLRs and EVLRs
+/// are read when the reader is created.
+///
+/// Example:
+///
+/// ```ignore
+/// let mut reader = LasReader::new("file.las")?;
+/// reader.set_chunk_size(500_000);
+/// for chunk in reader {
+///     for point in chunk? {
+///         let p = point.point_data();
+///         ...
+///     }
+/// }
+/// ```
+pub struct LasReader {
+    file_name: String,
+    pub header: LasHeader,
+    pub vlr_data: Vec<Vlr>,
+    pub evlr_data: Vec<Vlr>,
+    pub geokeys: GeoKeys,
+    pub spatial_reference: SpatialReference,
+    pub use_point_intensity: bool,
+    pub use_point_userdata: bool,
+    extra_bytes: Vec<ExtraBytesDescriptor>,
+    chunk_size: usize,
+    next_point: u64,
+    source: PointSource,
+    buffer: Vec<u8>,
+}
+
+enum PointSource {
+    Uncompressed(BufReader<File>),
+    Compressed(LasZipDecompressor<'static, BufReader<File>>),
+}
+
+impl LasReader {
+    /// Opens a LAS or LAZ file, reading its header, VLRs and EVLRs. The point records are
+    /// read, in order, by `read_chunk` or by iterating over the reader.
+    pub fn new(file_name: &str) -> Result<LasReader, Error> {
+        let mut f = File::open(file_name)?;
+        let mut lf: LasFile = Default::default();
+        let laszip_vlr = lf.read_header_and_vlrs(&mut f)?;
+        check_point_format(lf.header.point_format)?;
+        let standard_length = standard_record_length(lf.header.point_format, lf.use_point_intensity, lf.use_point_userdata);
+        if lf.header.point_record_length < standard_length {
+            return Err(Error::new(ErrorKind::InvalidData, format!("The point record length of {} bytes is shorter than the {} bytes of point format {}.",
+                lf.header.point_record_length, standard_length, lf.header.point_format)));
+        }
+        lf.read_evlrs(&mut f)?;
+
+        f.seek(SeekFrom::Start(lf.header.offset_to_points as u64))?;
+        let source = match laszip_vlr {
+            Some(vlr) => PointSource::Compressed(laszip_decompressor(BufReader::new(f), &vlr, &lf.header)?),
+            None => PointSource::Uncompressed(BufReader::new(f)),
+        };
+
+        let num_extra_bytes = (lf.header.point_record_length - standard_length) as usize;
+        let extra_bytes = extra_bytes_descriptors(&lf.vlr_data, &lf.evlr_data, num_extra_bytes);
+        let spatial_reference = lf.get_spatial_reference();
+        Ok(LasReader {
+            file_name: file_name.to_string(),
+            header: lf.header,
+            vlr_data: lf.vlr_data,
+            evlr_data: lf.evlr_data,
+            geokeys: lf.geokeys,
+            spatial_reference,
+            use_point_intensity: lf.use_point_intensity,
+            use_point_userdata: lf.use_point_userdata,
+            extra_bytes,
+            chunk_size: 1_000_000,
+            next_point: 0,
+            source,
+            buffer: vec![],
+        })
+    }
+
+    pub fn get_file_name(&self) -> String {
+        self.file_name.clone()
+    }
+
+    /// Returns the descriptions of the extra bytes attributes of the points.
+    pub fn get_extra_bytes_descriptors(&self) -> &[ExtraBytesDescriptor] {
+        &self.extra_bytes
+    }
+
+    /// Returns the extra bytes of a point of the chunk that was last read, by its index
+    /// within the chunk, laid out as described by the descriptors.
+    pub fn get_extra_bytes(&self, index: usize) -> &[u8] {
+        let rec_length = self.header.point_record_length as usize;
+        let length: usize = self.extra_bytes.iter().map(|d| d.size()).sum();
+        &self.buffer[(index + 1) * rec_length - length..(index + 1) * rec_length]
+    }
+
+    /// Returns the maximum number of point records returned by `read_chunk`.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Sets the maximum number of point records returned by `read_chunk`. The default
+    /// is 1,000,000 points.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = cmp::max(chunk_size, 1);
+    }
+
+    /// Returns the index of the next point record to be read.
+    pub fn position(&self) -> u64 {
+        self.next_point
+    }
+
+    /// Moves the reader to a point record, such that the next chunk starts with it.
+    /// Seeking within a LAZ file requires decompressing the points that precede the
+    /// point within its compressed chunk, and so is slower than for a LAS file.
+    pub fn seek(&mut self, point_index: u64) -> Result<(), Error> {
+        if point_index > self.header.number_of_points {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("Point index {} is out of range; the file contains {} points.", point_index, self.header.number_of_points)));
+        }
+        match self.source {
+            PointSource::Uncompressed(ref mut r) => {
+                let offset = self.header.offset_to_points as u64 + point_index * self.header.point_record_length as u64;
+                r.seek(SeekFrom::Start(offset))?;
+            },
+            PointSource::Compressed(ref mut d) => {
+                if let Err(e) = d.seek(point_index) {
+                    return Err(Error::other(format!("Error seeking within the LAZ file: {}", e)));
+                }
+            },
+        }
+        self.next_point = point_index;
+        Ok(())
+    }
+
+    /// Reads the next chunk of point records. The returned vector is empty once all of
+    /// the point records have been read.
+    pub fn read_chunk(&mut self) -> Result<Vec<LidarPointRecord>, Error> {
+        let num_points = if self.next_point < self.header.number_of_points {
+            cmp::min(self.chunk_size as u64, self.header.number_of_points - self.next_point) as usize
+        } else {
+            0usize
+        };
+        let rec_length = self.header.point_record_length as usize;
+        self.buffer.resize(num_points * rec_length, 0u8);
+        match self.source {
+            PointSource::Uncompressed(ref mut r) => {
+                if r.read_exact(&mut self.buffer).is_err() {
+                    return Err(Error::new(ErrorKind::InvalidData, "The file is shorter than the number of point records stated in the header."));
+                }
+            },
+            PointSource::Compressed(ref mut d) => {
+                d.decompress_many(&mut self.buffer)?;
+            },
+        }
+
+        let mut points = Vec::with_capacity(num_points);
+        for i in 0..num_points {
+            points.push(decode_point_record(&self.buffer[i * rec_length..(i + 1) * rec_length], &self.header,
+                self.use_point_intensity, self.use_point_userdata));
+        }
+        self.next_point += num_points as u64;
+        Ok(points)
+    }
+}
+
+impl Iterator for LasReader {
+    type Item = Result<Vec<LidarPointRecord>, Error>;
+
+    fn next(&mut self) -> Option<Result<Vec<LidarPointRecord>, Error>> {
+        if self.next_point >= self.header.number_of_points {
+            return None;
+        }
+        let chunk = self.read_chunk();
+        if chunk.is_err() {
+            // don't keep reading past an error
+            self.next_point = self.header.number_of_points;
+        }
+        Some(chunk)
+    }
+}
@@ -0,0 +1,443 @@
+use std::f64;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io;
+use std::io::{BufWriter, Error, ErrorKind, SeekFrom};
+use std::mem;
+use laz::LasZipCompressor;
+use crate::lidar::extra_bytes::{ extra_bytes_vlr, ExtraBytesDescriptor };
+use crate::lidar::header::LasHeader;
+use crate::lidar::las::{ check_coordinate_range, check_point_format, encode_point_record, extra_bytes_descriptors, is_laz_file_name, laszip_vlr,
+    standard_record_length, update_header_summary, spatial_reference_vlrs, write_evlrs, write_header, LidarPointRecord };
+use crate::lidar::vlr::Vlr;
+use crate::spatial_ref_system::SpatialReference;
+
+/// A LasWriter streams point records to a LAS file, or to a LASzip-compressed file if
+/// the file name has a .laz extension, as they are added. Unlike a LasFile, the points
+/// are not held in memory. The header bounds and point counts are updated as points are
+/// added and are written to the file when the writer is closed.
+///
+/// Because the points are encoded as they are added, the scale factors and offsets of the
+/// header are fixed when the writer is created, rather than derived from the data; a point
+/// whose coordinates cannot be stored using them is rejected with an error. EVLRs, e.g.
+/// waveform data, are written after the points when the writer is closed. The header is
+/// then rewritten in place, and so an EVLR that requires a later LAS version, and a larger
+/// header, than the one written with the first point record, e.g. any EVLR other than
+/// waveform data in a LAS 1.2 or 1.3 file, must be added before the first point. The points
+/// have the extra bytes of the header's point record length, as described by the extra bytes
+/// VLR of the file that the writer is initialized using; add_point_record_with_extra_bytes
+/// writes them, e.g. those of the input points, and add_point_record writes zeros.
+pub struct LasWriter {
+    file_name: String,
+    pub header: LasHeader,
+    pub vlr_data: Vec<Vlr>,
+    pub evlr_data: Vec<Vlr>,
+    pub use_point_intensity: bool,
+    pub use_point_userdata: bool,
+    extra_bytes: Vec<ExtraBytesDescriptor>,
+    sink: PointSink,
+    laszip_vlr: Option<Vlr>,
+    point_bytes: Vec<u8>,
+}
+
+enum PointSink {
+    // The header has not been written yet and may still be modified.
+    Pending(BufWriter<File>),
+    Uncompressed(BufWriter<File>),
+    Compressed(LasZipCompressor<'static, BufWriter<File>>),
+    Closed,
+}
+
+impl LasWriter {
+    /// Creates a LAS file that is initialized using the header, VLRs and EVLRs of another
+    /// file, e.g. those of a LasReader or LasFile. The header's point format, scale factors
+    /// and offsets are retained; the bounds and point counts are reset. The header and VLRs
+    /// may be modified until the first point record is added, and the EVLRs until the
+    /// writer is closed.
+    pub fn new(file_name: &str, header: &LasHeader, vlrs: &[Vlr], evlrs: &[Vlr]) -> Result<LasWriter, Error> {
+        check_point_format(header.point_format)?;
+        let f = File::create(file_name)?;
+
+        // The extra bytes VLR is written from the descriptors when the writer starts.
+        let standard_length = standard_record_length(header.point_format, true, true);
+        let num_extra_bytes = header.point_record_length.saturating_sub(standard_length) as usize;
+        let extra_bytes = extra_bytes_descriptors(vlrs, evlrs, num_extra_bytes);
+        let vlr_data: Vec<Vlr> = vlrs.iter().filter(|v| !v.is_extra_bytes()).cloned().collect();
+        let mut header = header.clone();
+        if header.version_major != 1 {
+            header.version_major = 1;
+            header.version_minor = 3;
+        }
+        header.number_of_vlrs = vlr_data.len() as u32;
+        header.number_of_points = 0;
+        header.number_of_points_by_return = [0u64; 15];
+        header.min_x = f64::INFINITY;
+        header.max_x = f64::NEG_INFINITY;
+        header.min_y = f64::INFINITY;
+        header.max_y = f64::NEG_INFINITY;
+        header.min_z = f64::INFINITY;
+        header.max_z = f64::NEG_INFINITY;
+        if header.x_scale_factor == 0f64 { header.x_scale_factor = 0.001; }
+        if header.y_scale_factor == 0f64 { header.y_scale_factor = 0.001; }
+        if header.z_scale_factor == 0f64 { header.z_scale_factor = 0.001; }
+
+        Ok(LasWriter {
+            file_name: file_name.to_string(),
+            header,
+            vlr_data,
+            evlr_data: evlrs.iter().filter(|v| !v.is_extra_bytes()).cloned().collect(),
+            use_point_intensity: true,
+            use_point_userdata: true,
+            extra_bytes,
+            sink: PointSink::Pending(BufWriter::new(f)),
+            laszip_vlr: None,
+            point_bytes: vec![],
+        })
+    }
+
+    pub fn get_file_name(&self) -> String {
+        self.file_name.clone()
+    }
+
+    /// Returns the descriptions of the extra bytes attributes of the points.
+    pub fn get_extra_bytes_descriptors(&self) -> &[ExtraBytesDescriptor] {
+        &self.extra_bytes
+    }
+
+    /// Sets the coordinate reference system of the points, replacing any CRS records (GeoKeys
+    /// and WKT) among the VLRs and EVLRs. Like the other VLRs, the CRS cannot be changed
+    /// after the first point record is added.
+    pub fn set_spatial_reference(&mut self, srs: &SpatialReference) -> Result<(), Error> {
+        if let PointSink::Pending(_) = self.sink {
+            self.vlr_data.retain(|v| !v.is_crs_record());
+            self.evlr_data.retain(|v| !v.is_crs_record());
+            self.vlr_data.extend(spatial_reference_vlrs(srs, self.header.point_format));
+            return Ok(());
+        }
+        Err(Error::other("The spatial reference of a LasWriter cannot be set after point records are added."))
+    }
+
+    /// Encodes a point record and writes it to the file. The extra bytes of the point, if
+    /// there are any, are zero.
+    pub fn add_point_record(&mut self, point: LidarPointRecord) -> Result<(), Error> {
+        let extra_bytes = vec![0u8; self.extra_bytes_length()];
+        self.add_point_record_with_extra_bytes(point, &extra_bytes)
+    }
+
+    /// Encodes a point record and writes it to the file along with its extra bytes, which
+    /// are laid out as described by the extra bytes descriptors of the writer.
+    pub fn add_point_record_with_extra_bytes(&mut self, point: LidarPointRecord, extra_bytes: &[u8]) -> Result<(), Error> {
+        if extra_bytes.len() != self.extra_bytes_length() {
+            return Err(Error::new(ErrorKind::InvalidInput, format!("A point has {} extra bytes rather than the {} of the file.",
+                extra_bytes.len(), self.extra_bytes_length())));
+        }
+        if let PointSink::Pending(_) = self.sink {
+            self.start()?;
+        }
+        let p = point.point_data();
+        check_coordinate_range(&self.header, p.x, p.y, p.z)?;
+        update_header_summary(&mut self.header, &p);
+        match self.sink {
+            PointSink::Uncompressed(ref mut writer) => {
+                encode_point_record(writer, &point, &self.header, self.use_point_intensity, self.use_point_userdata)?;
+                writer.write_all(extra_bytes)?;
+            },
+            PointSink::Compressed(ref mut compressor) => {
+                self.point_bytes.clear();
+                encode_point_record(&mut self.point_bytes, &point, &self.header, self.use_point_intensity, self.use_point_userdata)?;
+                self.point_bytes.extend_from_slice(extra_bytes);
+                compressor.compress_one(&self.point_bytes)?;
+            },
+            _ => {
+                return Err(Error::other("Point records cannot be added to a LasWriter after it is closed."));
+            },
+        }
+        Ok(())
+    }
+
+    /// Finishes writing the point records and updates the header with the bounds and point
+    /// counts of the data. This is called when the writer is dropped, but any error is then
+    /// lost; call it explicitly to find out whether the file was written successfully.
+    pub fn close(&mut self) -> Result<(), Error> {
+        if let PointSink::Pending(_) = self.sink {
+            self.start()?;
+        }
+        if let PointSink::Closed = self.sink {
+            return Ok(());
+        }
+        let extra_bytes_length = self.extra_bytes_length() as u16;
+
+        // The header is rewritten over the one written by start(), and so must not grow,
+        // which it would if the EVLRs now require a later LAS version.
+        {
+            let mut header = self.header.clone();
+            let mut vlrs: Vec<&Vlr> = self.vlr_data.iter().collect();
+            if let Some(ref vlr) = self.laszip_vlr {
+                vlrs.push(vlr);
+            }
+            write_header(&mut io::sink(), &mut header, &vlrs, &self.evlr_data, self.laszip_vlr.is_some(),
+                self.use_point_intensity, self.use_point_userdata, extra_bytes_length)?;
+            if header.offset_to_points != self.header.offset_to_points {
+                return Err(Error::new(ErrorKind::InvalidInput, format!("The EVLRs require LAS {}.{}, of which the header is larger than the LAS {}.{} header written with the point records; add them before the first point record.",
+                    header.version_major, header.version_minor, self.header.version_major, self.header.version_minor)));
+            }
+        }
+        let mut writer = match mem::replace(&mut self.sink, PointSink::Closed) {
+            PointSink::Uncompressed(writer) => writer,
+            PointSink::Compressed(mut compressor) => {
+                compressor.done()?;
+                compressor.into_inner()
+            },
+            _ => return Ok(()),
+        };
+
+        if self.header.number_of_points == 0 {
+            self.header.min_x = 0f64;
+            self.header.max_x = 0f64;
+            self.header.min_y = 0f64;
+            self.header.max_y = 0f64;
+            self.header.min_z = 0f64;
+            self.header.max_z = 0f64;
+        }
+
+        write_evlrs(&mut writer, &mut self.header, &self.evlr_data)?;
+        writer.seek(SeekFrom::Start(0))?;
+        {
+            let mut vlrs: Vec<&Vlr> = self.vlr_data.iter().collect();
+            if let Some(ref vlr) = self.laszip_vlr {
+                vlrs.push(vlr);
+            }
+            write_header(&mut writer, &mut self.header, &vlrs, &self.evlr_data, self.laszip_vlr.is_some(),
+                self.use_point_intensity, self.use_point_userdata, extra_bytes_length)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn extra_bytes_length(&self) -> usize {
+        self.extra_bytes.iter().map(|d| d.size()).sum()
+    }
+
+    /// Writes the header and VLRs, which fixes the point record layout, and prepares
+    /// the file for the point records.
+    fn start(&mut self) -> Result<(), Error> {
+        let mut writer = match mem::replace(&mut self.sink, PointSink::Closed) {
+            PointSink::Pending(writer) => writer,
+            sink => {
+                self.sink = sink;
+                return Ok(());
+            },
+        };
+
+        // LASzip requires the standard point record lengths, i.e. intensity and user data
+        // must be present.
+        let mut laz_vlr = None;
+        if is_laz_file_name(&self.file_name) {
+            self.use_point_intensity = true;
+            self.use_point_userdata = true;
+            let (v, vlr) = laszip_vlr(self.header.point_format, self.extra_bytes_length() as u16)?;
+            laz_vlr = Some(v);
+            self.laszip_vlr = Some(vlr);
+        }
+
+        if !self.extra_bytes.is_empty() {
+            self.vlr_data.retain(|v| !v.is_extra_bytes());
+            self.vlr_data.push(extra_bytes_vlr(&self.extra_bytes));
+        }
+        self.header.number_of_vlrs = self.vlr_data.len() as u32;
+        let extra_bytes_length = self.extra_bytes_length() as u16;
+        {
+            let mut vlrs: Vec<&Vlr> = self.vlr_data.iter().collect();
+            if let Some(ref vlr) = self.laszip_vlr {
+                vlrs.push(vlr);
+            }
+            write_header(&mut writer, &mut self.header, &vlrs, &self.evlr_data, self.laszip_vlr.is_some(),
+                self.use_point_intensity, self.use_point_userdata, extra_bytes_length)?;
+        }
+
+        self.sink = match laz_vlr {
+            Some(v) => {
+                match LasZipCompressor::new(writer, v) {
+                    Ok(c) => PointSink::Compressed(c),
+                    Err(e) => return Err(Error::other(format!("Error compressing the LAZ file: {}", e))),
+                }
+            },
+            None => PointSink::Uncompressed(writer),
+        };
+        Ok(())
+    }
+}
+
+impl Drop for LasWriter {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{Error, ErrorKind};
+    use byteorder::{ByteOrder, LittleEndian};
+    use crate::io_utils::temp_file_name;
+    use crate::lidar::extra_bytes::{ ExtraBytesDataType, ExtraBytesDescriptor };
+    use crate::lidar::header::LasHeader;
+    use crate::lidar::las::{ LasFile, LidarPointRecord };
+    use crate::lidar::las_reader::LasReader;
+    use crate::lidar::point_data::PointData;
+    use crate::lidar::vlr::Vlr;
+    use super::LasWriter;
+
+    fn header(version_minor: u8) -> LasHeader {
+        let mut header: LasHeader = Default::default();
+        header.project_id_used = true;
+        header.version_major = 1;
+        header.version_minor = version_minor;
+        header.point_format = 1;
+        header.x_scale_factor = 0.01;
+        header.y_scale_factor = 0.01;
+        header.z_scale_factor = 0.01;
+        header
+    }
+
+    fn points() -> Vec<LidarPointRecord> {
+        (0..100).map(|i| {
+            let mut p: PointData = Default::default();
+            p.x = 1000.0 + i as f64 * 0.5;
+            p.y = 2000.0 - i as f64 * 0.25;
+            p.z = 100.0 + (i % 7) as f64;
+            p.intensity = i as u16 * 3;
+            LidarPointRecord::PointRecord1 { point_data: p, gps_data: 5000.0 + i as f64 }
+        }).collect()
+    }
+
+    fn evlr() -> Vlr {
+        Vlr {
+            reserved: 0,
+            user_id: "whitebox".to_string(),
+            record_id: 1,
+            record_length_after_header: 0,
+            description: "test record".to_string(),
+            binary_data: (0..200).map(|i| i as u8).collect(),
+        }
+    }
+
+    fn write(file_name: &str, header: &LasHeader, evlrs_before_points: &[Vlr], evlrs_after_points: &[Vlr]) -> Result<(), Error> {
+        let mut writer = LasWriter::new(file_name, header, &[], evlrs_before_points)?;
+        for p in points() {
+            writer.add_point_record(p)?;
+        }
+        writer.evlr_data.extend(evlrs_after_points.iter().cloned());
+        writer.close()
+    }
+
+    #[test]
+    fn round_trip_points_and_evlr() {
+        let file_name = temp_file_name("round_trip.las");
+        write(&file_name, &header(2), &[evlr()], &[]).unwrap();
+
+        let mut reader = LasReader::new(&file_name).unwrap();
+        assert_eq!(reader.header.version_minor, 4);
+        assert_eq!(reader.header.number_of_points, 100);
+        assert_eq!(reader.evlr_data.len(), 1);
+        assert_eq!(reader.evlr_data[0].binary_data, evlr().binary_data);
+        let read_points = reader.read_chunk().unwrap();
+        assert_eq!(read_points.len(), 100);
+        for (a, b) in points().iter().zip(read_points.iter()) {
+            let (pa, pb) = (a.point_data(), b.point_data());
+            assert!((pa.x - pb.x).abs() < 1e-6 && (pa.y - pb.y).abs() < 1e-6 && (pa.z - pb.z).abs() < 1e-6, "{} != {}", pa, pb);
+            assert_eq!(pa.intensity, pb.intensity);
+            assert_eq!(a.gps_time(), b.gps_time());
+        }
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn evlr_requiring_larger_header_after_points_is_an_error() {
+        let file_name = temp_file_name("late_evlr.las");
+        let err = write(&file_name, &header(2), &[], &[evlr()]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+        // an EVLR that the written header already supports may be added after the points
+        write(&file_name, &header(4), &[], &[evlr()]).unwrap();
+        let mut reader = LasReader::new(&file_name).unwrap();
+        assert_eq!(reader.evlr_data.len(), 1);
+        assert_eq!(reader.read_chunk().unwrap().len(), 100);
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn short_point_record_length_is_an_error() {
+        let file_name = temp_file_name("short_record.las");
+        write(&file_name, &header(2), &[], &[]).unwrap();
+        // a point format 1 file that states 20 byte point records, rather than 28
+        let mut data = fs::read(&file_name).unwrap();
+        LittleEndian::write_u16(&mut data[105..107], 20);
+        fs::write(&file_name, &data).unwrap();
+        match LasReader::new(&file_name) {
+            Ok(_) => panic!("the short point records were read without an error"),
+            Err(e) => assert_eq!(e.kind(), ErrorKind::InvalidData),
+        }
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    /// Writes the points along with two extra bytes attributes, and copies them with a
+    /// LasReader and a LasWriter.
+    fn copy_with_extra_bytes(input_file: &str, output_file: &str) -> LasFile {
+        let mut input = LasFile::new(input_file, "w").unwrap();
+        input.add_header(header(2));
+        input.add_extra_bytes_descriptor(ExtraBytesDescriptor::new("Height", ExtraBytesDataType::F64, "height above ground")).unwrap();
+        input.add_extra_bytes_descriptor(ExtraBytesDescriptor::new("Segment", ExtraBytesDataType::U16, "segment identifier")).unwrap();
+        for (i, p) in points().into_iter().enumerate() {
+            input.add_point_record(p);
+            input.set_extra_f64(i, "Height", i as f64 * 0.25).unwrap();
+            input.set_extra_f64(i, "Segment", (i % 5) as f64).unwrap();
+        }
+        input.write().unwrap();
+
+        let mut reader = LasReader::new(input_file).unwrap();
+        let mut writer = LasWriter::new(output_file, &reader.header, &reader.vlr_data, &reader.evlr_data).unwrap();
+        assert_eq!(writer.get_extra_bytes_descriptors(), input.get_extra_bytes_descriptors());
+        loop {
+            let chunk = reader.read_chunk().unwrap();
+            if chunk.is_empty() { break; }
+            for (j, p) in chunk.into_iter().enumerate() {
+                writer.add_point_record_with_extra_bytes(p, reader.get_extra_bytes(j)).unwrap();
+            }
+        }
+        writer.close().unwrap();
+        input
+    }
+
+    #[test]
+    fn round_trip_extra_bytes() {
+        for &extension in &["las", "laz"] {
+            let input_file = temp_file_name(&format!("extra_bytes_input.{}", extension));
+            let output_file = temp_file_name(&format!("extra_bytes_output.{}", extension));
+            let input = copy_with_extra_bytes(&input_file, &output_file);
+            let output = LasFile::new(&output_file, "r").unwrap();
+            let _ = fs::remove_file(&input_file);
+            let _ = fs::remove_file(&output_file);
+
+            assert_eq!(output.get_extra_bytes_descriptors(), input.get_extra_bytes_descriptors());
+            assert_eq!(output.header.number_of_points, 100);
+            for (i, p) in points().iter().enumerate() {
+                let q = output.get_record(i).point_data();
+                assert!((p.point_data().x - q.x).abs() < 1e-6 && (p.point_data().z - q.z).abs() < 1e-6);
+                assert_eq!(output.get_extra_f64(i, "Height").unwrap(), i as f64 * 0.25);
+                assert_eq!(output.get_extra_f64(i, "Segment").unwrap(), (i % 5) as f64);
+            }
+        }
+    }
+
+    #[test]
+    fn extra_bytes_of_the_wrong_length_are_an_error() {
+        let file_name = temp_file_name("extra_bytes_length.las");
+        let mut writer = LasWriter::new(&file_name, &header(2), &[], &[]).unwrap();
+        let err = writer.add_point_record_with_extra_bytes(points()[0], &[0u8; 4]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        writer.add_point_record_with_extra_bytes(points()[0], &[]).unwrap();
+        writer.close().unwrap();
+        let _ = fs::remove_file(&file_name);
+    }
+}
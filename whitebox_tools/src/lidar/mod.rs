@@ -1,4 +1,8 @@
+pub mod extra_bytes;
 pub mod header;
 pub mod las;
+pub mod las_index;
+pub mod las_reader;
+pub mod las_writer;
 pub mod point_data;
 pub mod vlr;
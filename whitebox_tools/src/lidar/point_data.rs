@@ -3,19 +3,33 @@ use std::fmt;
 #[derive(Default, Debug, Clone, Copy)]
 pub struct PointBitField {
     pub value: u8,
+    /// The second bit field byte used by the extended point formats (6-10) of LAS 1.4,
+    /// containing the classification flags, scanner channel, scan direction and edge of
+    /// flightline flags. Unused for point formats 0-5.
+    pub flags: u8,
+    /// `true` if the bit field follows the extended (point formats 6-10) layout.
+    pub extended: bool,
 }
 
 impl PointBitField {
     /// Return number
     pub fn return_number(&self) -> u8 {
-        let mut ret = self.value & 0b0000_0111u8;
+        let mut ret = if self.extended {
+            self.value & 0b0000_1111u8
+        } else {
+            self.value & 0b0000_0111u8
+        };
         if ret == 0 { ret = 1; }
         ret
     }
 
     /// Number of returns
     pub fn number_of_returns(&self) -> u8 {
-        let mut ret = (self.value & 0b0011_1000u8) >> 3u8;
+        let mut ret = if self.extended {
+            (self.value & 0b1111_0000u8) >> 4u8
+        } else {
+            (self.value & 0b0011_1000u8) >> 3u8
+        };
         if ret == 0 { ret = 1; }
         ret
     }
@@ -24,17 +38,43 @@ impl PointBitField {
     /// in-track direction to the right side and false the opposite.
     pub fn scan_direction_flag(&self) -> bool {
         //((self.value >> 6_u8) & 1_u8) == 1_u8
+        if self.extended {
+            return (self.flags & 0b0100_0000u8) == 0b0100_0000u8;
+        }
         (self.value & 0b0100_0000u8) == 0b0100_0000u8
     }
 
     /// Edge of flightline flag
     pub fn edge_of_flightline_flag(&self) -> bool {
+        if self.extended {
+            return (self.flags & 0b1000_0000u8) == 0b1000_0000u8;
+        }
         (self.value & 0b1000_0000u8) == 0b1000_0000u8
     }
+
+    /// Scanner channel (0-3) of multi-channel systems. Always 0 for point formats 0-5.
+    pub fn scanner_channel(&self) -> u8 {
+        if self.extended {
+            return (self.flags & 0b0011_0000u8) >> 4u8;
+        }
+        0u8
+    }
+
+    /// Overlap flag, `true` if the point is within the overlap region of two or more
+    /// swaths. Always `false` for point formats 0-5, which use class 12 instead.
+    pub fn overlap(&self) -> bool {
+        if self.extended {
+            return (self.flags & 0b0000_1000u8) == 0b0000_1000u8;
+        }
+        false
+    }
 }
 
 impl fmt::Display for PointBitField {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.extended {
+            return write!(f, "return={}, num. returns={}, scan direction={}, edge of flightline={}, scanner channel={}, overlap={}", self.return_number(), self.number_of_returns(), self.scan_direction_flag(), self.edge_of_flightline_flag(), self.scanner_channel(), self.overlap());
+        }
         write!(f, "return={}, num. returns={}, scan direction={}, edge of flightline={}", self.return_number(), self.number_of_returns(), self.scan_direction_flag(), self.edge_of_flightline_flag())
     }
 }
@@ -58,28 +98,27 @@ impl ClassificationBitField {
     /// Returns a string represenation of the classiciation type.
     pub fn classification_string(&self) -> String {
         match self.classification() {
-            0 => return String::from("Created, never classified"),
-            1 => return String::from("Unclassified"),
-            2 => return String::from("Ground"),
-            3 => return String::from("Low vegetation"),
-            4 => return String::from("Medium vegetation"),
-            5 => return String::from("High vegetation"),
-            6 => return String::from("Building"),
-            7 => return String::from("Low point (noise)"),
-            8 => return String::from("Reserved"),
-            9 => return String::from("Water"),
-            10 => return String::from("Rail"),
-            11 => return String::from("Road surface"),
-            12 => return String::from("Reserved"),
-            13 => return String::from("Wire – guard (shield)"),
-            14 => return String::from("Wire – conductor (phase)"),
-            15 => return String::from("Transmission tower"),
-            16 => return String::from("Wire-structure connector (e.g. insulator)"),
-            17 => return String::from("Bridge deck"),
-            18 => return String::from("High Noise"),
-            19...63 => return String::from("Reserved"),
-            64...255 => return String::from("User defined"),
-            _ => return String::from("Unknown class"),
+            0 => String::from("Created, never classified"),
+            1 => String::from("Unclassified"),
+            2 => String::from("Ground"),
+            3 => String::from("Low vegetation"),
+            4 => String::from("Medium vegetation"),
+            5 => String::from("High vegetation"),
+            6 => String::from("Building"),
+            7 => String::from("Low point (noise)"),
+            8 => String::from("Reserved"),
+            9 => String::from("Water"),
+            10 => String::from("Rail"),
+            11 => String::from("Road surface"),
+            12 => String::from("Reserved"),
+            13 => String::from("Wire – guard (shield)"),
+            14 => String::from("Wire – conductor (phase)"),
+            15 => String::from("Transmission tower"),
+            16 => String::from("Wire-structure connector (e.g. insulator)"),
+            17 => String::from("Bridge deck"),
+            18 => String::from("High Noise"),
+            19..=63 => String::from("Reserved"),
+            64..=255 => String::from("User defined"),
         }
     }
 
@@ -91,9 +130,9 @@ impl ClassificationBitField {
 
     pub fn set_synthetic(&mut self, val: bool) {
         if val {
-            self.value = self.value | 0b0010_0000u8; //(1 << 5_u8);
+            self.value |= 0b0010_0000u8; //(1 << 5_u8);
         } else {
-            self.value = self.value & 0b1101_1111u8;
+            self.value &= 0b1101_1111u8;
         }
     }
 
@@ -105,9 +144,9 @@ impl ClassificationBitField {
 
     pub fn set_keypoint(&mut self, val: bool) {
         if val {
-            self.value = self.value | 0b0100_0000u8;
+            self.value |= 0b0100_0000u8;
         } else {
-            self.value = self.value & 0b1011_1111u8;;
+            self.value &= 0b1011_1111u8;
         }
     }
 
@@ -119,9 +158,9 @@ impl ClassificationBitField {
 
     pub fn set_withheld(&mut self, val: bool) {
         if val {
-            self.value = self.value | 0b1000_0000u8;
+            self.value |= 0b1000_0000u8;
         } else {
-            self.value = self.value & 0b0111_1111u8;
+            self.value &= 0b0111_1111u8;
         }
     }
 }
@@ -135,28 +174,27 @@ impl fmt::Display for ClassificationBitField {
 /// Returns a string represenation of a classiciation numeric value.
 pub fn convert_class_val_to_class_string(value: u8) -> String {
     match value {
-        0 => return String::from("Created, never classified"),
-        1 => return String::from("Unclassified"),
-        2 => return String::from("Ground"),
-        3 => return String::from("Low vegetation"),
-        4 => return String::from("Medium vegetation"),
-        5 => return String::from("High vegetation"),
-        6 => return String::from("Building"),
-        7 => return String::from("Low point (noise)"),
-        8 => return String::from("Reserved"),
-        9 => return String::from("Water"),
-        10 => return String::from("Rail"),
-        11 => return String::from("Road surface"),
-        12 => return String::from("Reserved"),
-        13 => return String::from("Wire – guard (shield)"),
-        14 => return String::from("Wire – conductor (phase)"),
-        15 => return String::from("Transmission tower"),
-        16 => return String::from("Wire-structure connector (e.g. insulator)"),
-        17 => return String::from("Bridge deck"),
-        18 => return String::from("High Noise"),
-        19...63 => return String::from("Reserved"),
-        64...255 => return String::from("User defined"),
-        _ => return String::from("Unknown class"),
+        0 => String::from("Created, never classified"),
+        1 => String::from("Unclassified"),
+        2 => String::from("Ground"),
+        3 => String::from("Low vegetation"),
+        4 => String::from("Medium vegetation"),
+        5 => String::from("High vegetation"),
+        6 => String::from("Building"),
+        7 => String::from("Low point (noise)"),
+        8 => String::from("Reserved"),
+        9 => String::from("Water"),
+        10 => String::from("Rail"),
+        11 => String::from("Road surface"),
+        12 => String::from("Reserved"),
+        13 => String::from("Wire – guard (shield)"),
+        14 => String::from("Wire – conductor (phase)"),
+        15 => String::from("Transmission tower"),
+        16 => String::from("Wire-structure connector (e.g. insulator)"),
+        17 => String::from("Bridge deck"),
+        18 => String::from("High Noise"),
+        19..=63 => String::from("Reserved"),
+        64..=255 => String::from("User defined"),
     }
 }
 
@@ -168,7 +206,10 @@ pub struct PointData {
     pub intensity: u16,
     pub bit_field: PointBitField,
     pub class_bit_field: ClassificationBitField,
-    pub scan_angle: i8,
+    /// The scan angle. For point formats 0-5 this is the rounded angle in degrees
+    /// (-90 to +90); for the extended point formats (6-10) it is stored in increments
+    /// of 0.006 degrees (-30,000 to +30,000).
+    pub scan_angle: i16,
     pub user_data: u8,
     pub point_source_id: u16,
 }
@@ -183,6 +224,11 @@ impl fmt::Display for PointData {
 
 impl PointData {
 
+    /// Returns `true` if the point uses the extended (LAS 1.4, point formats 6-10) layout.
+    pub fn is_extended(&self) -> bool {
+        self.bit_field.extended
+    }
+
     /// The return number of the point.
     pub fn return_number(&self) -> u8 {
         self.bit_field.return_number()
@@ -215,46 +261,181 @@ impl PointData {
         false
     }
 
+    /// Returns the scan direction flag of the point.
+    pub fn scan_direction_flag(&self) -> bool {
+        self.bit_field.scan_direction_flag()
+    }
+
+    /// Returns the edge of flightline flag of the point.
+    pub fn edge_of_flightline_flag(&self) -> bool {
+        self.bit_field.edge_of_flightline_flag()
+    }
+
+    /// Returns the scanner channel of the point (point formats 6-10 only).
+    pub fn scanner_channel(&self) -> u8 {
+        self.bit_field.scanner_channel()
+    }
+
+    /// Returns `true` if the point is flagged as overlap. For point formats 0-5 the
+    /// overlap class value (12) is used instead.
+    pub fn overlap(&self) -> bool {
+        if self.is_extended() {
+            return self.bit_field.overlap();
+        }
+        self.class_bit_field.classification() == 12u8
+    }
+
+    /// Returns the scan angle of the point in degrees.
+    pub fn scan_angle_degrees(&self) -> f64 {
+        if self.is_extended() {
+            return self.scan_angle as f64 * 0.006f64;
+        }
+        self.scan_angle as f64
+    }
+
     /// Returns the classification value of the point.
     pub fn classification(&self) -> u8 {
+        if self.is_extended() {
+            return self.class_bit_field.value;
+        }
         self.class_bit_field.classification()
     }
 
     /// Returns the classification string associated with the point.
     pub fn classification_string(&self) -> String {
-        self.class_bit_field.classification_string()
+        convert_class_val_to_class_string(self.classification())
     }
 
     /// Sets the classification value of the point.
     pub fn set_classification(&mut self, value: u8) {
-        self.class_bit_field.set_classification(value);
+        if self.is_extended() {
+            self.class_bit_field.value = value;
+        } else {
+            self.class_bit_field.set_classification(value);
+        }
     }
 
     /// Returns `true` if the point is synthetic, `false` otherwise
     pub fn synthetic(&self) -> bool {
+        if self.is_extended() {
+            return (self.bit_field.flags & 0b0000_0001u8) == 0b0000_0001u8;
+        }
         self.class_bit_field.synthetic()
     }
 
     pub fn set_synthetic(&mut self, val: bool) {
-        self.class_bit_field.set_synthetic(val);
+        if self.is_extended() {
+            self.set_extended_flag(0b0000_0001u8, val);
+        } else {
+            self.class_bit_field.set_synthetic(val);
+        }
     }
 
     /// Returns `true` if the point is a keypoint, `false` otherwise
     pub fn keypoint(&self) -> bool {
+        if self.is_extended() {
+            return (self.bit_field.flags & 0b0000_0010u8) == 0b0000_0010u8;
+        }
         self.class_bit_field.keypoint()
     }
 
     pub fn set_keypoint(&mut self, val: bool) {
-        self.class_bit_field.set_keypoint(val);
+        if self.is_extended() {
+            self.set_extended_flag(0b0000_0010u8, val);
+        } else {
+            self.class_bit_field.set_keypoint(val);
+        }
     }
 
     /// Returns `true` if the point is withehld, `false` otherwise
     pub fn withheld(&self) -> bool {
+        if self.is_extended() {
+            return (self.bit_field.flags & 0b0000_0100u8) == 0b0000_0100u8;
+        }
         self.class_bit_field.withheld()
     }
 
     pub fn set_withheld(&mut self, val: bool) {
-        self.class_bit_field.set_withheld(val);
+        if self.is_extended() {
+            self.set_extended_flag(0b0000_0100u8, val);
+        } else {
+            self.class_bit_field.set_withheld(val);
+        }
+    }
+
+    /// Sets the overlap flag of the point (point formats 6-10 only).
+    pub fn set_overlap(&mut self, val: bool) {
+        if self.is_extended() {
+            self.set_extended_flag(0b0000_1000u8, val);
+        }
+    }
+
+    fn set_extended_flag(&mut self, mask: u8, val: bool) {
+        if val {
+            self.bit_field.flags |= mask;
+        } else {
+            self.bit_field.flags &= !mask;
+        }
+    }
+
+    /// Returns the return-information byte of the point laid out for point formats 0-5.
+    /// Return numbers greater than 7 cannot be represented and are clamped.
+    pub fn legacy_bit_field_value(&self) -> u8 {
+        if !self.is_extended() {
+            return self.bit_field.value;
+        }
+        let mut ret = self.return_number();
+        if ret > 7 { ret = 7; }
+        let mut nrets = self.number_of_returns();
+        if nrets > 7 { nrets = 7; }
+        let mut value = ret | (nrets << 3u8);
+        if self.scan_direction_flag() { value |= 0b0100_0000u8; }
+        if self.edge_of_flightline_flag() { value |= 0b1000_0000u8; }
+        value
+    }
+
+    /// Returns the classification byte of the point laid out for point formats 0-5.
+    pub fn legacy_class_bit_field_value(&self) -> u8 {
+        if !self.is_extended() {
+            return self.class_bit_field.value;
+        }
+        let mut value = self.classification();
+        if value > 31 { value = 1u8; } // values above 31 cannot be represented; mark unclassified
+        if self.synthetic() { value |= 0b0010_0000u8; }
+        if self.keypoint() { value |= 0b0100_0000u8; }
+        if self.withheld() { value |= 0b1000_0000u8; }
+        value
+    }
+
+    /// Returns the scan angle of the point in whole degrees, as stored by point formats 0-5.
+    pub fn legacy_scan_angle(&self) -> i8 {
+        if !self.is_extended() {
+            return self.scan_angle as i8;
+        }
+        self.scan_angle_degrees().round() as i8
+    }
+
+    /// Returns the scan angle of the point in 0.006 degree increments, as stored by point formats 6-10.
+    pub fn extended_scan_angle(&self) -> i16 {
+        if self.is_extended() {
+            return self.scan_angle;
+        }
+        (self.scan_angle as f64 / 0.006f64).round() as i16
+    }
+
+    /// Returns the pair of bit field bytes of the point laid out for point formats 6-10.
+    pub fn extended_bit_field_values(&self) -> (u8, u8) {
+        if self.is_extended() {
+            return (self.bit_field.value, self.bit_field.flags);
+        }
+        let value = self.return_number() | (self.number_of_returns() << 4u8);
+        let mut flags = 0u8;
+        if self.synthetic() { flags |= 0b0000_0001u8; }
+        if self.keypoint() { flags |= 0b0000_0010u8; }
+        if self.withheld() { flags |= 0b0000_0100u8; }
+        if self.scan_direction_flag() { flags |= 0b0100_0000u8; }
+        if self.edge_of_flightline_flag() { flags |= 0b1000_0000u8; }
+        (value, flags)
     }
 }
 
@@ -1,5 +1,7 @@
+use std::cmp;
 use std::fmt;
-use std::mem;
+use byteorder::{ByteOrder, LittleEndian};
+use crate::lidar::extra_bytes::descriptors_from_vlr;
 
 #[derive(Default, Clone, Debug)]
 pub struct Vlr {
@@ -11,6 +13,37 @@ pub struct Vlr {
     pub binary_data: Vec<u8>,
 }
 
+impl Vlr {
+    /// Returns true if the record is a waveform packet descriptor, i.e. one of the
+    /// LASF_Spec records with an ID of 100-354.
+    pub fn is_waveform_packet_descriptor(&self) -> bool {
+        self.user_id.starts_with("LASF_Spec") && self.record_id >= 100 && self.record_id <= 354
+    }
+
+    /// Returns true if the record is an OGC WKT coordinate system record.
+    pub fn is_wkt(&self) -> bool {
+        self.user_id.starts_with("LASF_Projection") && self.record_id == 2112
+    }
+
+    /// Returns true if the record describes the coordinate reference system of the points,
+    /// i.e. it is a GeoKey record (34735-34737) or an OGC WKT record (2111 or 2112).
+    pub fn is_crs_record(&self) -> bool {
+        self.user_id.starts_with("LASF_Projection") &&
+            (self.record_id == 2111 || self.record_id == 2112 || (self.record_id >= 34_735 && self.record_id <= 34_737))
+    }
+
+    /// Returns true if the record describes the extra bytes of the point records.
+    pub fn is_extra_bytes(&self) -> bool {
+        self.user_id.starts_with("LASF_Spec") && self.record_id == 4
+    }
+
+    /// Returns true if the record is the waveform data packet record (an EVLR), which
+    /// holds the waveform data of the points when it is stored internally to the file.
+    pub fn is_waveform_data(&self) -> bool {
+        self.user_id.starts_with("LASF_Spec") && self.record_id == 65_535
+    }
+}
+
 impl fmt::Display for Vlr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut s = format!("\tReserved: {}", self.reserved);
@@ -18,7 +51,7 @@ impl fmt::Display for Vlr {
         s = s + &format!("\n\tRecord ID: {}", self.record_id);
         s = s + &format!("\n\tRecord After Length: {}", self.record_length_after_header);
         s = s + &format!("\n\tDescription: {}", self.description);
-        s = s + &"\n\tVLR Data: [";
+        s += "\n\tVLR Data: [";
         if self.record_id == 34_735 {
             // convert the binary data to an array of u16's
             let mut i : usize = 0;
@@ -35,10 +68,10 @@ impl fmt::Display for Vlr {
             // convert the binary data to an array of f64's
             let mut i : usize = 0;
             while i < self.record_length_after_header as usize {
-                let k: f64 = unsafe { mem::transmute::<[u8; 8], f64>([self.binary_data[i],
+                let k: f64 = f64::from_le_bytes([self.binary_data[i],
                     self.binary_data[i + 1], self.binary_data[i + 2], self.binary_data[i + 3],
                     self.binary_data[i + 4], self.binary_data[i + 5], self.binary_data[i + 6],
-                    self.binary_data[i + 7]]) };
+                    self.binary_data[i + 7]]);
                 i += 8;
                 if i < self.record_length_after_header as usize {
                     s = s + &format!("{}, ", k);
@@ -46,9 +79,23 @@ impl fmt::Display for Vlr {
                     s = s + &format!("{}]", k);
                 }
             }
+        } else if self.is_waveform_packet_descriptor() && self.binary_data.len() >= 26 {
+            let d = &self.binary_data;
+            s = s + &format!("Bits per sample: {}, Compression type: {}, Number of samples: {}, Temporal spacing: {} ps, Digitizer gain: {}, Digitizer offset: {}]",
+                d[0], d[1], LittleEndian::read_u32(&d[2..6]), LittleEndian::read_u32(&d[6..10]),
+                LittleEndian::read_f64(&d[10..18]), LittleEndian::read_f64(&d[18..26]));
+        } else if self.is_wkt() {
+            s = s + String::from_utf8_lossy(&self.binary_data).trim_matches(char::from(0)).trim() + "]";
+        } else if self.is_extra_bytes() {
+            let descriptors = descriptors_from_vlr(self);
+            let names: Vec<String> = descriptors.iter().map(|d| format!("{}", d)).collect();
+            s = s + &names.join("; ") + "]";
+        } else if self.is_waveform_data() {
+            s = s + &format!("{} bytes of waveform data]", self.binary_data.len());
         } else {
             // convert the data to a string
-            s = s + String::from_utf8_lossy(&self.binary_data[0..self.record_length_after_header as usize]).trim() + "]";
+            let len = cmp::min(self.record_length_after_header as usize, self.binary_data.len());
+            s = s + String::from_utf8_lossy(&self.binary_data[0..len]).trim() + "]";
             //s = s + "uninterpreted data]";
         }
         write!(f, "{}", s)
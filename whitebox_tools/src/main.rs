@@ -1,30 +1,46 @@
+// The tools index their grids and point buffers by row, column and point number, and
+// many of the tools' helper functions take their whole state as arguments.
+#![allow(clippy::needless_range_loop, clippy::too_many_arguments, clippy::type_complexity,
+    clippy::field_reassign_with_default)]
+
 extern crate byteorder;
+extern crate laz;
+extern crate flate2;
+extern crate kdtree;
+extern crate nalgebra as na;
+extern crate rand;
 
 pub mod io_utils;
 pub mod lidar;
 pub mod raster;
+pub mod spatial_ref_system;
 pub mod tools;
 pub mod structures;
 
 use std::io::{Error, ErrorKind};
 use std::env;
 use std::path;
-use tools::ToolManager;
+use crate::tools::ToolManager;
 
 fn main() {
     match run() {
         Ok(()) => {},
-        Err(err) => panic!("{}", err),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        },
     }
 }
 
 fn run() -> Result<(), Error> {
-    let sep: &str = &path::MAIN_SEPARATOR.to_string();
+    let sep: &str = std::path::MAIN_SEPARATOR_STR;
     let mut working_dir = String::new();
     let mut tool_name = String::new();
     let mut run_tool = false;
     let mut tool_help = false;
+    let mut tool_parameters = false;
     let mut list_tools = false;
+    let mut json = false;
     let mut tool_args: String; // = String::new();
     let mut tool_args_vec: Vec<String> = vec![];
     let mut verbose = false;
@@ -86,7 +102,7 @@ fn run() -> Result<(), Error> {
                 }
                 previous_char = c;
             }
-            tool_args_vec.push(a.trim().to_string().clone());
+            if a.len() > 1 { tool_args_vec.push(a.trim().to_string().clone()); }
         } else if arg.starts_with("-toolhelp") || arg.starts_with("--toolhelp") {
             let mut v = arg.replace("--toolhelp", "").replace("-toolhelp", "").replace("\"", "").replace("\'", "");
             if v.starts_with("=") {
@@ -95,8 +111,17 @@ fn run() -> Result<(), Error> {
             tool_name = v;
             tool_help = true;
             //println!("running: {}", tool_name);
+        } else if arg.starts_with("-toolparameters") || arg.starts_with("--toolparameters") {
+            let mut v = arg.replace("--toolparameters", "").replace("-toolparameters", "").replace("\"", "").replace("\'", "");
+            if v.starts_with("=") {
+                v = v[1..v.len()].to_string();
+            }
+            tool_name = v;
+            tool_parameters = true;
         } else if arg.starts_with("-listtools") || arg.starts_with("--listtools") {
             list_tools = true;
+        } else if arg.starts_with("-json") || arg.starts_with("--json") {
+            json = true;
         } else if arg.starts_with("-license") || arg.starts_with("-licence") ||
           arg.starts_with("--license") || arg.starts_with("--licence") || arg.starts_with("-l") {
             license();
@@ -111,7 +136,7 @@ fn run() -> Result<(), Error> {
 
     let sep = path::MAIN_SEPARATOR;
     if !working_dir.ends_with(sep) {
-        working_dir.push_str(&(sep.to_string()));
+        working_dir.push(sep);
     }
     let tm = ToolManager::new(&working_dir, &verbose)?;
     if run_tool {
@@ -122,8 +147,14 @@ fn run() -> Result<(), Error> {
         return tm.run_tool(tool_name, tool_args_vec);
     } else if tool_help {
         return tm.tool_help(tool_name);
+    } else if tool_parameters {
+        return tm.tool_parameters(tool_name);
     } else if list_tools {
-        tm.list_tools();
+        if json {
+            tm.list_tools_json();
+        } else {
+            tm.list_tools();
+        }
     }
 
     Ok(())
@@ -143,9 +174,11 @@ The following commands are recognized:
 -a, --args       Sets the arguments for running tools; --args=\"-i=file.las -o=newfile.las\".
 --cd, --wd       Changes the working directory; used in conjunction with --run flag.
 -l, --license    Prints the whitebox-tools license.
+--json           Lists the tools as JSON, including their parameters; used with --listtools.
 --listtools      Lists all available tools.
 -r, --run        Runs a tool; used in conjuction with --args and --cd flags; -r=\"lidar_info\".
 --toolhelp       Prints the help associated with a tool; --toolhelp=\"lidar_info\".
+--toolparameters Prints the parameters of a tool as JSON; --toolparameters=\"lidar_info\".
 -h, --help       Prints help information.
 
 Example Usage:
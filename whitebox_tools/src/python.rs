@@ -0,0 +1,326 @@
+/*
+The Python extension module, which is built into the cdylib with the 'python' feature, e.g.
+'cargo build --release --lib --features python'. The library (libwhitebox_tools.so,
+libwhitebox_tools.dylib or whitebox_tools.dll) is renamed to whitebox.so (whitebox.pyd on
+Windows) and imported as 'whitebox'. The tools are run in-process, rather than by calling the
+whitebox-tools executable, and rasters and LAS files are read and written as NumPy arrays:
+
+    import whitebox
+    whitebox.run_tool("elev_percentile", ["-i=DEM.dep", "-o=out.dep", "--filter=25"], "/path/to/data")
+    dem = whitebox.Raster("/path/to/data/DEM.dep")
+    z = dem.data
+*/
+use std::io::{Error, ErrorKind};
+use numpy::ndarray::Array2;
+use numpy::{IntoPyArray, PyArray1, PyArray2, PyReadonlyArray1, PyReadonlyArray2, PyUntypedArrayMethods};
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use crate::lidar::las::LasFile;
+use crate::raster::{Raster, RasterType};
+use crate::tools;
+use crate::tools::ToolManager;
+
+fn to_py_err<E: Into<Error>>(err: E) -> PyErr {
+    let err: Error = err.into();
+    match err.kind() {
+        ErrorKind::InvalidInput | ErrorKind::InvalidData => PyValueError::new_err(err.to_string()),
+        _ => PyIOError::new_err(err.to_string()),
+    }
+}
+
+/// Runs a tool with its arguments, e.g. `["-i=DEM.dep", "-o=out.dep", "--filter=25"]`. File
+/// names that are not paths are in the working directory.
+#[pyfunction]
+#[pyo3(signature = (tool_name, args, working_directory = "", verbose = false))]
+fn run_tool(py: Python, tool_name: &str, args: Vec<String>, working_directory: &str, verbose: bool) -> PyResult<()> {
+    let tm = ToolManager { working_dir: working_directory.to_string(), verbose };
+    let tool_name = tool_name.to_string();
+    py.detach(move || tm.run_tool(tool_name, args)).map_err(to_py_err)
+}
+
+/// Returns a dict of the names of the tools and their descriptions.
+#[pyfunction]
+fn list_tools<'py>(py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+    let d = PyDict::new(py);
+    for tool in tools::get_tools() {
+        d.set_item(tool.get_tool_name(), tool.get_tool_description())?;
+    }
+    Ok(d)
+}
+
+/// Returns the help of a tool, i.e. its description, parameters and example usage.
+#[pyfunction]
+fn tool_help(tool_name: &str) -> PyResult<String> {
+    ToolManager::default().get_tool_help(tool_name).map_err(to_py_err)
+}
+
+/// Returns a JSON description of a tool and its parameters.
+#[pyfunction]
+fn tool_parameters(tool_name: &str) -> PyResult<String> {
+    ToolManager::default().get_tool_parameters_json(tool_name).map_err(to_py_err)
+}
+
+/// A raster, of which the first band is read and written as a 2D float64 array.
+#[pyclass(name = "Raster", unsendable)]
+struct PyRaster {
+    raster: Raster,
+}
+
+#[pymethods]
+impl PyRaster {
+    /// Reads a raster.
+    #[new]
+    fn new(file_name: &str) -> PyResult<PyRaster> {
+        let raster = Raster::new(file_name, "r").map_err(to_py_err)?;
+        Ok(PyRaster { raster })
+    }
+
+    /// Returns a raster that is written to a file, with the dimensions, extent, nodata value
+    /// and coordinate reference system of another raster. Its cells are nodata.
+    #[staticmethod]
+    fn initialize_using_file(file_name: &str, base: &PyRaster) -> PyRaster {
+        let mut raster = Raster::initialize_using_file(file_name, &base.raster);
+        let nodata = raster.configs.nodata;
+        for row in 0..raster.configs.rows as isize {
+            for col in 0..raster.configs.columns as isize {
+                raster.set_value(row, col, nodata);
+            }
+        }
+        PyRaster { raster }
+    }
+
+    #[getter]
+    fn file_name(&self) -> String { self.raster.file_name.clone() }
+    #[getter]
+    fn rows(&self) -> usize { self.raster.configs.rows }
+    #[getter]
+    fn columns(&self) -> usize { self.raster.configs.columns }
+    #[getter]
+    fn nodata(&self) -> f64 { self.raster.configs.nodata }
+    #[getter]
+    fn north(&self) -> f64 { self.raster.configs.north }
+    #[getter]
+    fn south(&self) -> f64 { self.raster.configs.south }
+    #[getter]
+    fn east(&self) -> f64 { self.raster.configs.east }
+    #[getter]
+    fn west(&self) -> f64 { self.raster.configs.west }
+    #[getter]
+    fn resolution_x(&self) -> f64 { self.raster.configs.resolution_x }
+    #[getter]
+    fn resolution_y(&self) -> f64 { self.raster.configs.resolution_y }
+    #[getter]
+    fn epsg_code(&self) -> u16 { self.raster.configs.epsg_code }
+
+    #[getter]
+    fn bands(&self) -> usize { self.raster.num_bands() }
+
+    /// The cell values of the first band, as an array of shape (rows, columns).
+    #[getter]
+    fn data<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<f64>>> {
+        self.read_band(py, 0)
+    }
+
+    #[setter]
+    fn set_data(&mut self, values: PyReadonlyArray2<f64>) -> PyResult<()> {
+        self.write_band(0, values)
+    }
+
+    /// Returns the cell values of a band, as an array of shape (rows, columns).
+    fn read_band<'py>(&self, py: Python<'py>, band: usize) -> PyResult<Bound<'py, PyArray2<f64>>> {
+        self.check_band(band)?;
+        let (rows, columns) = (self.raster.configs.rows, self.raster.configs.columns);
+        let mut values = Vec::with_capacity(rows * columns);
+        for row in 0..rows as isize {
+            for col in 0..columns as isize {
+                values.push(self.raster.get_band_value(band, row, col));
+            }
+        }
+        let array = Array2::from_shape_vec((rows, columns), values).map_err(|e| PyValueError::new_err(e.to_string()))?;
+        Ok(array.into_pyarray(py))
+    }
+
+    /// Sets the cell values of a band from an array of shape (rows, columns).
+    fn write_band(&mut self, band: usize, values: PyReadonlyArray2<f64>) -> PyResult<()> {
+        self.check_band(band)?;
+        let (rows, columns) = (self.raster.configs.rows, self.raster.configs.columns);
+        if values.shape() != [rows, columns] {
+            return Err(PyValueError::new_err(format!("The array has a shape of {:?}, rather than the raster's ({}, {}).",
+                values.shape(), rows, columns)));
+        }
+        let values = values.as_array();
+        for row in 0..rows {
+            for col in 0..columns {
+                self.raster.set_band_value(band, row as isize, col as isize, values[[row, col]]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the value of a cell of a band; cells outside of the raster are nodata.
+    #[pyo3(signature = (row, column, band = 0))]
+    fn get_value(&self, row: isize, column: isize, band: usize) -> PyResult<f64> {
+        self.check_band(band)?;
+        Ok(self.raster.get_band_value(band, row, column))
+    }
+
+    /// Sets the value of a cell of a band; cells outside of the raster are ignored.
+    #[pyo3(signature = (row, column, value, band = 0))]
+    fn set_value(&mut self, row: isize, column: isize, value: f64, band: usize) -> PyResult<()> {
+        self.check_band(band)?;
+        self.raster.set_band_value(band, row, column, value);
+        Ok(())
+    }
+
+    /// Writes the raster to its file, in the format given by the extension of the file name
+    /// or, if it is given, by the name of a format, e.g. "geotiff" or "envi".
+    #[pyo3(signature = (format = None))]
+    fn write(&mut self, format: Option<&str>) -> PyResult<()> {
+        if let Some(format) = format {
+            self.raster.raster_type = format.parse::<RasterType>().map_err(to_py_err)?;
+        }
+        self.raster.write().map_err(to_py_err)
+    }
+}
+
+impl PyRaster {
+    fn check_band(&self, band: usize) -> PyResult<()> {
+        if band >= self.raster.num_bands() {
+            return Err(PyValueError::new_err(format!("Band {} is out of range; the raster has {} bands.", band, self.raster.num_bands())));
+        }
+        Ok(())
+    }
+}
+
+/// A LAS or LAZ file, of which the point attributes are read as 1D arrays.
+#[pyclass(name = "LasFile", unsendable)]
+struct PyLasFile {
+    las: LasFile,
+}
+
+#[pymethods]
+impl PyLasFile {
+    /// Reads a LAS or LAZ file.
+    #[new]
+    fn new(file_name: &str) -> PyResult<PyLasFile> {
+        let las = LasFile::new(file_name, "r").map_err(to_py_err)?;
+        Ok(PyLasFile { las })
+    }
+
+    #[getter]
+    fn number_of_points(&self) -> usize { self.las.header.number_of_points as usize }
+    #[getter]
+    fn point_format(&self) -> u8 { self.las.header.point_format }
+
+    #[getter]
+    fn x<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        self.get_points(|las, i| las.get_point_info(i).x).into_pyarray(py)
+    }
+    #[getter]
+    fn y<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        self.get_points(|las, i| las.get_point_info(i).y).into_pyarray(py)
+    }
+    #[getter]
+    fn z<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f64>> {
+        self.get_points(|las, i| las.get_point_info(i).z).into_pyarray(py)
+    }
+    #[getter]
+    fn intensity<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u16>> {
+        self.get_points(|las, i| las.get_point_info(i).intensity).into_pyarray(py)
+    }
+    #[getter]
+    fn classification<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u8>> {
+        self.get_points(|las, i| las.get_point_info(i).classification()).into_pyarray(py)
+    }
+    #[getter]
+    fn return_number<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u8>> {
+        self.get_points(|las, i| las.get_point_info(i).return_number()).into_pyarray(py)
+    }
+    #[getter]
+    fn number_of_returns<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<u8>> {
+        self.get_points(|las, i| las.get_point_info(i).number_of_returns()).into_pyarray(py)
+    }
+
+    /// The GPS times of the points, for the point formats that have them.
+    #[getter]
+    fn gps_time<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        let mut values = Vec::with_capacity(self.number_of_points());
+        for i in 0..self.number_of_points() {
+            values.push(self.las.get_gps_time(i).map_err(to_py_err)?);
+        }
+        Ok(values.into_pyarray(py))
+    }
+
+    /// The names of the extra bytes attributes of the points, e.g. "HeightAboveGround".
+    #[getter]
+    fn extra_bytes_names(&self) -> Vec<String> {
+        self.las.get_extra_bytes_descriptors().iter().map(|d| d.name.clone()).collect()
+    }
+
+    /// Returns the values of an extra bytes attribute of the points.
+    fn get_extra<'py>(&self, py: Python<'py>, name: &str) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        let mut values = Vec::with_capacity(self.number_of_points());
+        for i in 0..self.number_of_points() {
+            values.push(self.las.get_extra_f64(i, name).map_err(to_py_err)?);
+        }
+        Ok(values.into_pyarray(py))
+    }
+
+    /// Writes the points to another file, with the coordinates, intensities and classes
+    /// replaced by those of the arrays that are given. The extra bytes of the points are
+    /// written unchanged.
+    #[pyo3(signature = (file_name, x = None, y = None, z = None, intensity = None, classification = None))]
+    fn write(&self, file_name: &str, x: Option<PyReadonlyArray1<f64>>, y: Option<PyReadonlyArray1<f64>>,
+        z: Option<PyReadonlyArray1<f64>>, intensity: Option<PyReadonlyArray1<u16>>,
+        classification: Option<PyReadonlyArray1<u8>>) -> PyResult<()> {
+
+        let n = self.number_of_points();
+        for &(name, len) in [("x", x.as_ref().map(|a| a.len())), ("y", y.as_ref().map(|a| a.len())),
+            ("z", z.as_ref().map(|a| a.len())), ("intensity", intensity.as_ref().map(|a| a.len())),
+            ("classification", classification.as_ref().map(|a| a.len()))].iter() {
+            if let Some(len) = len {
+                if len != n {
+                    return Err(PyValueError::new_err(format!("The {} array has {} values, rather than the {} points of the file.", name, len, n)));
+                }
+            }
+        }
+        let x = x.as_ref().map(|a| a.as_array());
+        let y = y.as_ref().map(|a| a.as_array());
+        let z = z.as_ref().map(|a| a.as_array());
+        let intensity = intensity.as_ref().map(|a| a.as_array());
+        let classification = classification.as_ref().map(|a| a.as_array());
+
+        let mut output = LasFile::initialize_using_file(file_name, &self.las);
+        for i in 0..n {
+            let mut pr = self.las.get_record(i);
+            {
+                let p = pr.point_data_mut();
+                if let Some(ref a) = x { p.x = a[i]; }
+                if let Some(ref a) = y { p.y = a[i]; }
+                if let Some(ref a) = z { p.z = a[i]; }
+                if let Some(ref a) = intensity { p.intensity = a[i]; }
+                if let Some(ref a) = classification { p.set_classification(a[i]); }
+            }
+            output.add_point_record_with_extra_bytes(pr, self.las.get_extra_bytes(i));
+        }
+        output.write().map_err(to_py_err)
+    }
+}
+
+impl PyLasFile {
+    fn get_points<T, F: Fn(&LasFile, usize) -> T>(&self, f: F) -> Vec<T> {
+        (0..self.number_of_points()).map(|i| f(&self.las, i)).collect()
+    }
+}
+
+#[pymodule]
+fn whitebox(m: &Bound<PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(run_tool, m)?)?;
+    m.add_function(wrap_pyfunction!(list_tools, m)?)?;
+    m.add_function(wrap_pyfunction!(tool_help, m)?)?;
+    m.add_function(wrap_pyfunction!(tool_parameters, m)?)?;
+    m.add_class::<PyRaster>()?;
+    m.add_class::<PyLasFile>()?;
+    Ok(())
+}
@@ -1,12 +1,13 @@
-use std::io::Error;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::prelude::*;
 use std::f64;
 use std::fs::File;
-use raster::*;
+use crate::raster::*;
+use crate::raster::raster_data::RasterData;
+use crate::raster::raster_error::*;
 
-pub fn read_arcascii(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec<f64>) -> Result<(), Error> {
+pub fn read_arcascii(file_name: &String, configs: &mut RasterConfigs, data: &mut RasterData) -> Result<(), RasterError> {
     // read the file
     let f = File::open(file_name)?;
     let f = BufReader::new(f);
@@ -15,26 +16,28 @@ pub fn read_arcascii(file_name: &String, configs: &mut RasterConfigs, data: &mut
     let mut yllcenter: f64 = f64::NEG_INFINITY;
     let mut xllcorner: f64 = f64::NEG_INFINITY;
     let mut yllcorner: f64 = f64::NEG_INFINITY;
+    // the data type is only guessed from the nodata value, so the values are held as f64
+    *data = RasterData::default();
     //let mut likely_float = false;
     for line in f.lines() {
-        let line_unwrapped = line.unwrap();
+        let line_unwrapped = line?;
         let line_split = line_unwrapped.split(" ");
         let vec = line_split.collect::<Vec<&str>>();
         if vec[0].to_lowercase().contains("nrows") {
-            configs.rows = vec[1].trim().to_string().parse::<usize>().unwrap();
+            configs.rows = parse_value::<usize>(vec[1])?;
         } else if vec[0].to_lowercase().contains("ncols") {
-            configs.columns = vec[1].trim().to_string().parse::<usize>().unwrap();
+            configs.columns = parse_value::<usize>(vec[1])?;
         } else if vec[0].to_lowercase().contains("xllcorner") {
-            xllcenter = vec[1].trim().to_string().parse::<f64>().unwrap();
+            xllcenter = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("yllcorner") {
-            yllcenter = vec[1].trim().to_string().parse::<f64>().unwrap();
+            yllcenter = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("xllcenter") {
-            xllcorner = vec[1].trim().to_string().parse::<f64>().unwrap();
+            xllcorner = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("yllcenter") {
-            yllcorner = vec[1].trim().to_string().parse::<f64>().unwrap();
+            yllcorner = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("cellsize") {
-            configs.resolution_x = vec[1].trim().to_string().parse::<f64>().unwrap();
-            configs.resolution_y = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.resolution_x = parse_value::<f64>(vec[1])?;
+            configs.resolution_y = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("nodata_value") {
             if vec[1].contains(".") {
                 //likely_float = true;
@@ -42,10 +45,10 @@ pub fn read_arcascii(file_name: &String, configs: &mut RasterConfigs, data: &mut
             } else {
                 configs.data_type = DataType::I32;
             }
-            configs.nodata = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.nodata = parse_value::<f64>(vec[1])?;
         } else { // it's a data line
             for val in vec {
-                data.push(val.trim().to_string().parse::<f64>().unwrap());
+                data.push(parse_value::<f64>(val)?);
             }
         }
     }
@@ -68,7 +71,7 @@ pub fn read_arcascii(file_name: &String, configs: &mut RasterConfigs, data: &mut
     Ok(())
 }
 
-pub fn write_arcascii<'a>(r: &'a mut Raster) -> Result<(), Error> {
+pub fn write_arcascii(r: &mut Raster) -> Result<(), RasterError> {
 
     // Save the file
     let f = File::create(&(r.file_name))?;
@@ -98,9 +101,9 @@ pub fn write_arcascii<'a>(r: &'a mut Raster) -> Result<(), Error> {
     let mut col = 0;
     for i in 0..num_cells {
         if col < r.configs.columns - 1 {
-            s2 += &format!("{:.*} ", 2, r.data[i]);
+            s2 += &format!("{:.*} ", 2, r.data.get_value(i));
         } else {
-            s2 += &format!("{:.*}\n", 2, r.data[i]);
+            s2 += &format!("{:.*}\n", 2, r.data.get_value(i));
         }
         col += 1;
         if col == r.configs.columns {
@@ -108,10 +111,10 @@ pub fn write_arcascii<'a>(r: &'a mut Raster) -> Result<(), Error> {
             s2 = String::new();
             col = 0;
         }
-        //try!(writer.write_all(format!("{:.*}", 2, r.data[i]).as_bytes()));
+        //writer.write_all(format!("{:.*}", 2, r.data[i]).as_bytes())?;
     }
 
-    let _ = writer.flush();
+    writer.flush()?;
 
     Ok(())
 }
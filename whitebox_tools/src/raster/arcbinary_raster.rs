@@ -1,14 +1,49 @@
-use std::io::Error;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::prelude::*;
 use std::f64;
 use std::fs::File;
-use std::mem;
-use raster::*;
-use io_utils::byte_order_reader::Endianness;
+use crate::raster::*;
+use crate::raster::raster_data::RasterData;
+use crate::raster::raster_error::*;
+use crate::raster::window::*;
+use crate::io_utils::byte_order_reader::Endianness;
+use crate::io_utils::read_fully;
 
-pub fn read_arcbinary(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec<f64>) -> Result<(), Error> {
+pub fn read_arcbinary(file_name: &str, configs: &mut RasterConfigs, data: &mut RasterData) -> Result<(), RasterError> {
+    let layout = read_arcbinary_header(file_name, configs)?;
+    layout.check_data_file(configs)?;
+
+    // read the data file
+    let data_file = file_name.replace(".hdr", ".flt");
+    let mut f = File::open(data_file.clone())?;
+
+    let data_size = 4;
+    let num_cells = configs.rows * configs.columns;
+    *data = RasterData::for_configs(configs, configs.data_type);
+    let buf_size = 1_000_000usize;
+    let mut j = 0;
+    while j < num_cells {
+        let mut buffer = vec![0; buf_size * data_size];
+
+        read_fully(&mut f, &mut buffer)?;
+
+        let mut offset: usize;
+        for i in 0..buf_size {
+            offset = i * 4;
+            data.set_value(j, f32::from_ne_bytes([buffer[offset], buffer[offset+1],
+                buffer[offset+2], buffer[offset+3]]) as f64);
+            j += 1;
+            if j == num_cells { break; }
+        }
+
+    }
+
+    Ok(())
+}
+
+/// Reads the header (.hdr) of an ArcGIS binary raster, returning the layout of its data file.
+pub fn read_arcbinary_header(file_name: &str, configs: &mut RasterConfigs) -> Result<RawLayout, RasterError> {
     // read the header file
     let header_file = file_name.replace(".flt", ".hdr");
     let f = File::open(header_file)?;
@@ -20,27 +55,27 @@ pub fn read_arcbinary(file_name: &String, configs: &mut RasterConfigs, data: &mu
     let mut yllcorner: f64 = f64::NEG_INFINITY;
 
     for line in f.lines() {
-        let line_unwrapped = line.unwrap();
+        let line_unwrapped = line?;
         // println!("{}", line_unwrapped);
         let line_split = line_unwrapped.split(" ");
         let vec = line_split.collect::<Vec<&str>>();
         if vec[0].to_lowercase().contains("nrows") {
-            configs.rows = vec[1].trim().to_string().parse::<usize>().unwrap();
+            configs.rows = parse_value::<usize>(vec[1])?;
         } else if vec[0].to_lowercase().contains("ncols") {
-            configs.columns = vec[1].trim().to_string().parse::<usize>().unwrap();
+            configs.columns = parse_value::<usize>(vec[1])?;
         } else if vec[0].to_lowercase().contains("xllcorner") {
-            xllcenter = vec[1].trim().to_string().parse::<f64>().unwrap();
+            xllcenter = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("yllcorner") {
-            yllcenter = vec[1].trim().to_string().parse::<f64>().unwrap();
+            yllcenter = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("xllcenter") {
-            xllcorner = vec[1].trim().to_string().parse::<f64>().unwrap();
+            xllcorner = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("yllcenter") {
-            yllcorner = vec[1].trim().to_string().parse::<f64>().unwrap();
+            yllcorner = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("cellsize") {
-            configs.resolution_x = vec[1].trim().to_string().parse::<f64>().unwrap();
-            configs.resolution_y = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.resolution_x = parse_value::<f64>(vec[1])?;
+            configs.resolution_y = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("nodata_value") {
-            configs.nodata = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.nodata = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("byteorder") {
             if vec[1].trim().to_lowercase().contains("lsb") {
                  configs.endian = Endianness::LittleEndian;
@@ -67,34 +102,17 @@ pub fn read_arcbinary(file_name: &String, configs: &mut RasterConfigs, data: &mu
         configs.north = yllcenter - (0.5 * configs.resolution_y) + (configs.rows as f64)*configs.resolution_y;
     }
 
-    // read the data file
-    let data_file = file_name.replace(".hdr", ".flt");
-    let mut f = File::open(data_file.clone())?;
-
-    let data_size = 4;
-    let num_cells = configs.rows * configs.columns;
-    let buf_size = 1_000_000usize;
-    let mut j = 0;
-    while j < num_cells {
-        let mut buffer = vec![0; buf_size * data_size];
-
-        f.read(&mut buffer)?;
-
-        let mut offset: usize;
-        for i in 0..buf_size {
-            offset = i * 4;
-            data.push(unsafe { mem::transmute::<[u8; 4], f32>([buffer[offset], buffer[offset+1],
-                buffer[offset+2], buffer[offset+3]])} as f64);
-            j += 1;
-            if j == num_cells { break; }
-        }
-
-    }
-
-    Ok(())
+    Ok(RawLayout {
+        data_file: file_name.replace(".hdr", ".flt"),
+        offset: 0,
+        data_type: configs.data_type,
+        endian: configs.endian,
+        top_to_bottom: true,
+        z_factor: 1f64,
+    })
 }
 
-pub fn write_arcbinary<'a>(r: &'a mut Raster) -> Result<(), Error> {
+pub fn write_arcbinary(r: &mut Raster) -> Result<(), RasterError> {
 
     // Save the header file
     let header_file = r.file_name.replace(".flt", ".hdr");
@@ -127,7 +145,7 @@ pub fn write_arcbinary<'a>(r: &'a mut Raster) -> Result<(), Error> {
         writer.write_all("BYTEORDER MSBFIRST\n".as_bytes())?;
     }
 
-    let _ = writer.flush();
+    writer.flush()?;
 
     // read the data file
     let data_file = r.file_name.replace(".hdr", ".flt");
@@ -138,11 +156,11 @@ pub fn write_arcbinary<'a>(r: &'a mut Raster) -> Result<(), Error> {
 
     let num_cells: usize = r.configs.rows * r.configs.columns;
     for i in 0..num_cells {
-        u32_bytes = unsafe { mem::transmute(r.data[i] as f32) };
-        writer.write(&u32_bytes)?;
+        u32_bytes = f32::to_ne_bytes(r.data.get_value(i) as f32);
+        writer.write_all(&u32_bytes)?;
     }
 
-    let _ = writer.flush();
+    writer.flush()?;
 
     Ok(())
 }
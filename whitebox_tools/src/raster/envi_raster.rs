@@ -0,0 +1,418 @@
+use std::io::Error;
+use std::io::ErrorKind;
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::f64;
+use std::fs::File;
+use std::path::Path;
+use std::str::FromStr;
+use byteorder::{ByteOrder, BigEndian, LittleEndian, WriteBytesExt};
+use crate::raster::*;
+use crate::raster::raster_data::RasterData;
+use crate::raster::raster_error::*;
+use crate::io_utils::byte_order_reader::ByteOrderReader;
+use crate::io_utils::byte_order_reader::Endianness;
+
+/// Reads an ENVI raster, a binary data file of one or more bands accompanied by a text
+/// header (.hdr). The file name may be that of either file.
+pub fn read_envi(file_name: &str, configs: &mut RasterConfigs, data: &mut RasterData) -> Result<(), RasterError> {
+    let (header_file, data_file) = get_envi_file_names(file_name, true);
+
+    let mut contents = String::new();
+    File::open(&header_file)?.read_to_string(&mut contents)?;
+    if !contents.trim_start().starts_with("ENVI") {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, format!("{} is not an ENVI header file.", header_file)));
+    }
+
+    let mut header_offset = 0usize;
+    let mut map_info: Vec<String> = vec![];
+    for (key, value) in parse_header(&contents) {
+        match key.as_ref() {
+            "samples" => configs.columns = parse_value(&key, &value)?,
+            "lines" => configs.rows = parse_value(&key, &value)?,
+            "bands" => configs.bands = parse_value(&key, &value)?,
+            "header offset" => header_offset = parse_value(&key, &value)?,
+            "data type" => {
+                configs.data_type = match parse_value::<u16>(&key, &value)? {
+                    1 => DataType::U8,
+                    2 => DataType::I16,
+                    3 => DataType::I32,
+                    4 => DataType::F32,
+                    5 => DataType::F64,
+                    12 => DataType::U16,
+                    13 => DataType::U32,
+                    14 => DataType::I64,
+                    15 => DataType::U64,
+                    _ => return Err(RasterError::new(RasterErrorKind::Unsupported, format!("Unsupported ENVI data type ({}).", value))),
+                };
+            },
+            "interleave" => {
+                configs.interleave = match value.to_lowercase().as_ref() {
+                    "bsq" => Interleave::Bsq,
+                    "bil" => Interleave::Bil,
+                    "bip" => Interleave::Bip,
+                    _ => return Err(RasterError::new(RasterErrorKind::InvalidData, format!("Unrecognized ENVI interleave ({}).", value))),
+                };
+            },
+            "byte order" => {
+                configs.endian = if value == "1" { Endianness::BigEndian } else { Endianness::LittleEndian };
+            },
+            "map info" => map_info = value.split(",").map(|s| s.trim().to_string()).collect(),
+            "coordinate system string" => configs.coordinate_ref_system_wkt = value,
+            "data ignore value" => configs.nodata = parse_value(&key, &value)?,
+            "description" => configs.title = value,
+            _ => {},
+        }
+    }
+    if configs.rows == 0 || configs.columns == 0 || configs.data_type == DataType::Unknown {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, "The ENVI header does not specify the samples, lines and data type."));
+    }
+    configs.photometric_interp = PhotometricInterpretation::Continuous;
+
+    // The map info gives the name of the projection, a reference pixel (1-based, with
+    // (1, 1) the upper-left corner of the upper-left pixel), its map coordinates and the
+    // pixel size, followed, for UTM, by the zone and hemisphere, and then the datum and units.
+    if map_info.len() >= 7 {
+        let reference_x: f64 = parse_value("map info", &map_info[1])?;
+        let reference_y: f64 = parse_value("map info", &map_info[2])?;
+        let easting: f64 = parse_value("map info", &map_info[3])?;
+        let northing: f64 = parse_value("map info", &map_info[4])?;
+        configs.resolution_x = parse_value("map info", &map_info[5])?;
+        configs.resolution_y = parse_value("map info", &map_info[6])?;
+        configs.west = easting - (reference_x - 1f64) * configs.resolution_x;
+        configs.north = northing + (reference_y - 1f64) * configs.resolution_y;
+
+        let projection = map_info[0].to_lowercase();
+        let datum = map_info.iter().skip(7).find(|s| !s.contains("=") && s.parse::<f64>().is_err() && !s.to_lowercase().starts_with("north") && !s.to_lowercase().starts_with("south"));
+        let is_wgs84 = match datum {
+            Some(d) => d.to_lowercase().replace("-", "").replace(" ", "") == "wgs84",
+            None => false,
+        };
+        if projection == "utm" && map_info.len() >= 9 && is_wgs84 {
+            let zone: u16 = parse_value("map info", &map_info[7])?;
+            configs.epsg_code = if map_info[8].to_lowercase().starts_with("south") { 32700 + zone } else { 32600 + zone };
+        } else if projection.starts_with("geographic") && is_wgs84 {
+            configs.epsg_code = 4326;
+        }
+        if projection != "arbitrary" {
+            configs.projection = map_info[0].clone();
+        }
+        for item in map_info.iter().skip(7) {
+            if item.to_lowercase().starts_with("units") {
+                configs.xy_units = item.split("=").last().unwrap_or("").trim().to_lowercase();
+            }
+        }
+    } else {
+        configs.west = 0f64;
+        configs.north = configs.rows as f64;
+        configs.resolution_x = 1f64;
+        configs.resolution_y = 1f64;
+    }
+    configs.east = configs.west + configs.columns as f64 * configs.resolution_x;
+    configs.south = configs.north - configs.rows as f64 * configs.resolution_y;
+
+    // read the data file
+    let mut buffer = vec![];
+    File::open(&data_file)?.read_to_end(&mut buffer)?;
+    let (rows, columns, bands) = (configs.rows, configs.columns, configs.bands);
+    let num_values = rows * columns * bands;
+    if buffer.len() < header_offset + num_values * configs.data_type.get_data_size() {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, format!("The ENVI data file {} is smaller than its header describes.", data_file)).at_offset(buffer.len() as u64));
+    }
+    let mut bor = ByteOrderReader::new(buffer, configs.endian);
+    bor.seek(header_offset);
+
+    // The bands are held band sequentially, whatever the interleave of the file.
+    *data = RasterData::for_configs(configs, configs.data_type);
+    let data_type = configs.data_type;
+    match configs.interleave {
+        Interleave::Bsq => {
+            for i in 0..num_values {
+                data.set_value(i, read_sample(&mut bor, data_type));
+            }
+        },
+        Interleave::Bil => {
+            for row in 0..rows {
+                for band in 0..bands {
+                    for col in 0..columns {
+                        data.set_value((band * rows + row) * columns + col, read_sample(&mut bor, data_type));
+                    }
+                }
+            }
+        },
+        Interleave::Bip => {
+            for row in 0..rows {
+                for col in 0..columns {
+                    for band in 0..bands {
+                        data.set_value((band * rows + row) * columns + col, read_sample(&mut bor, data_type));
+                    }
+                }
+            }
+        },
+    }
+
+    Ok(())
+}
+
+pub fn write_envi(r: &mut Raster) -> Result<(), RasterError> {
+    let (header_file, data_file) = get_envi_file_names(&r.file_name, false);
+    let data_type_code = match r.configs.data_type {
+        DataType::U8 => 1,
+        DataType::I16 => 2,
+        DataType::I32 => 3,
+        DataType::F32 => 4,
+        DataType::F64 => 5,
+        DataType::U16 => 12,
+        DataType::U32 => 13,
+        DataType::I64 => 14,
+        DataType::U64 => 15,
+        _ => return Err(RasterError::new(RasterErrorKind::Unsupported, "ENVI rasters cannot hold RGB or unknown data types.")),
+    };
+    let bands = r.num_bands();
+    let (rows, columns) = (r.configs.rows, r.configs.columns);
+
+    // Save the header file
+    let f = File::create(&header_file)?;
+    let mut writer = BufWriter::new(f);
+    writer.write_all("ENVI\n".as_bytes())?;
+    let description = if r.configs.title.is_empty() { "Created by whitebox-tools".to_string() } else { r.configs.title.clone() };
+    writer.write_all(format!("description = {{{}}}\n", description).as_bytes())?;
+    writer.write_all(format!("samples = {}\n", columns).as_bytes())?;
+    writer.write_all(format!("lines = {}\n", rows).as_bytes())?;
+    writer.write_all(format!("bands = {}\n", bands).as_bytes())?;
+    writer.write_all("header offset = 0\n".as_bytes())?;
+    writer.write_all("file type = ENVI Standard\n".as_bytes())?;
+    writer.write_all(format!("data type = {}\n", data_type_code).as_bytes())?;
+    let interleave = match r.configs.interleave {
+        Interleave::Bsq => "bsq",
+        Interleave::Bil => "bil",
+        Interleave::Bip => "bip",
+    };
+    writer.write_all(format!("interleave = {}\n", interleave).as_bytes())?;
+    let byte_order = if r.configs.endian == Endianness::BigEndian { 1 } else { 0 };
+    writer.write_all(format!("byte order = {}\n", byte_order).as_bytes())?;
+
+    // UTM (WGS 84) and geographic coordinates are described in the map info; other
+    // systems are described by the coordinate system string only.
+    let epsg = r.configs.epsg_code;
+    let projection = if epsg > 32600 && epsg <= 32660 {
+        format!("UTM, 1, 1, {}, {}, {}, {}, {}, North, WGS-84", r.configs.west, r.configs.north, r.configs.resolution_x, r.configs.resolution_y, epsg - 32600)
+    } else if epsg > 32700 && epsg <= 32760 {
+        format!("UTM, 1, 1, {}, {}, {}, {}, {}, South, WGS-84", r.configs.west, r.configs.north, r.configs.resolution_x, r.configs.resolution_y, epsg - 32700)
+    } else if epsg == 4326 {
+        format!("Geographic Lat/Lon, 1, 1, {}, {}, {}, {}, WGS-84", r.configs.west, r.configs.north, r.configs.resolution_x, r.configs.resolution_y)
+    } else {
+        format!("Arbitrary, 1, 1, {}, {}, {}, {}, 0", r.configs.west, r.configs.north, r.configs.resolution_x, r.configs.resolution_y)
+    };
+    let units = if epsg == 4326 || r.configs.xy_units.to_lowercase().contains("deg") { "Degrees" } else { "Meters" };
+    writer.write_all(format!("map info = {{{}, units={}}}\n", projection, units).as_bytes())?;
+    let wkt = &r.configs.coordinate_ref_system_wkt;
+    if !wkt.is_empty() && wkt != "not specified" {
+        writer.write_all(format!("coordinate system string = {{{}}}\n", wkt.replace("\n", "")).as_bytes())?;
+    }
+
+    // ENVI has a single nodata value for all of the bands
+    writer.write_all(format!("data ignore value = {}\n", r.configs.nodata).as_bytes())?;
+    let band_names: Vec<String> = (1..bands + 1).map(|b| format!("Band {}", b)).collect();
+    writer.write_all(format!("band names = {{{}}}\n", band_names.join(", ")).as_bytes())?;
+    writer.flush()?;
+
+    // write the data file
+    let f = File::create(&data_file)?;
+    let mut writer = BufWriter::new(f);
+    write_interleaved_data(&mut writer, r, r.configs.interleave)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Writes the cells of a raster, in its data type and byte order, with its bands
+/// arranged by an interleave.
+pub fn write_interleaved_data<W: Write>(writer: &mut W, r: &Raster, interleave: Interleave) -> Result<(), Error> {
+    let bands = r.num_bands();
+    let (rows, columns) = (r.configs.rows, r.configs.columns);
+    let data_type = r.configs.data_type;
+    let endian = r.configs.endian;
+    match interleave {
+        Interleave::Bsq => {
+            for i in 0..rows * columns * bands {
+                write_sample(writer, r.data.get_value(i), data_type, endian)?;
+            }
+        },
+        Interleave::Bil => {
+            for row in 0..rows {
+                for band in 0..bands {
+                    for col in 0..columns {
+                        write_sample(writer, r.data.get_value((band * rows + row) * columns + col), data_type, endian)?;
+                    }
+                }
+            }
+        },
+        Interleave::Bip => {
+            for row in 0..rows {
+                for col in 0..columns {
+                    for band in 0..bands {
+                        write_sample(writer, r.data.get_value((band * rows + row) * columns + col), data_type, endian)?;
+                    }
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Returns the names of the header and data files of an ENVI raster. The header has the
+/// extension .hdr, either replacing or appended to the name of the data file. When the
+/// header is given, the data file is the first of the usual names that exists when
+/// reading, and has the extension .img when writing.
+fn get_envi_file_names(file_name: &str, reading: bool) -> (String, String) {
+    let path = Path::new(file_name);
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    if extension == "hdr" {
+        let data_file = if reading {
+            ["img", "dat", "bin", "raw", "bsq", "bil", "bip", ""].iter()
+                .map(|e| path.with_extension(e).to_string_lossy().to_string())
+                .find(|f| Path::new(f).is_file())
+                .unwrap_or(path.with_extension("img").to_string_lossy().to_string())
+        } else {
+            path.with_extension("img").to_string_lossy().to_string()
+        };
+        return (file_name.to_string(), data_file);
+    }
+    let appended = format!("{}.hdr", file_name);
+    let header_file = if reading && Path::new(&appended).is_file() {
+        appended
+    } else {
+        path.with_extension("hdr").to_string_lossy().to_string()
+    };
+    (header_file, file_name.to_string())
+}
+
+/// Splits an ENVI header into its key = value fields. Values in braces may span several
+/// lines; the braces are removed. Keys are returned in lower case.
+fn parse_header(contents: &str) -> Vec<(String, String)> {
+    let mut fields = vec![];
+    let mut lines = contents.lines().skip(1); // ENVI
+    while let Some(line) = lines.next() {
+        let mut parts = line.splitn(2, "=");
+        let key = parts.next().unwrap_or("").trim().to_lowercase();
+        let mut value = parts.next().unwrap_or("").trim().to_string();
+        if key.is_empty() {
+            continue;
+        }
+        if value.starts_with("{") {
+            while !value.contains("}") {
+                match lines.next() {
+                    Some(l) => {
+                        value.push(' ');
+                        value.push_str(l.trim());
+                    },
+                    None => break,
+                }
+            }
+            value = value.trim_start_matches('{').split("}").next().unwrap_or("").trim().to_string();
+        }
+        fields.push((key, value));
+    }
+    fields
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> Result<T, RasterError> {
+    match value.trim().parse::<T>() {
+        Ok(v) => Ok(v),
+        Err(_) => Err(RasterError::new(RasterErrorKind::InvalidData, format!("Invalid ENVI header value for '{}' ({}).", key, value))),
+    }
+}
+
+/// Reads a sample of a data type.
+pub fn read_sample(bor: &mut ByteOrderReader, data_type: DataType) -> f64 {
+    match data_type {
+        DataType::U8 => bor.read_u8() as f64,
+        DataType::I8 => bor.read_i8() as f64,
+        DataType::I16 => bor.read_i16() as f64,
+        DataType::I32 => bor.read_i32() as f64,
+        DataType::F32 => bor.read_f32() as f64,
+        DataType::F64 => bor.read_f64(),
+        DataType::U16 => bor.read_u16() as f64,
+        DataType::U32 => bor.read_u32() as f64,
+        DataType::I64 => bor.read_i64() as f64,
+        DataType::U64 => bor.read_u64() as f64,
+        _ => f64::NAN,
+    }
+}
+
+/// Writes a sample of a data type in a byte order.
+pub fn write_sample<W: Write>(writer: &mut W, value: f64, data_type: DataType, endian: Endianness) -> Result<(), Error> {
+    match endian {
+        Endianness::LittleEndian => write_sample_with_byte_order::<W, LittleEndian>(writer, value, data_type),
+        Endianness::BigEndian => write_sample_with_byte_order::<W, BigEndian>(writer, value, data_type),
+    }
+}
+
+fn write_sample_with_byte_order<W: Write, B: ByteOrder>(writer: &mut W, value: f64, data_type: DataType) -> Result<(), Error> {
+    match data_type {
+        DataType::U8 => writer.write_u8(value as u8),
+        DataType::I8 => writer.write_i8(value as i8),
+        DataType::I16 => writer.write_i16::<B>(value as i16),
+        DataType::I32 => writer.write_i32::<B>(value as i32),
+        DataType::F32 => writer.write_f32::<B>(value as f32),
+        DataType::F64 => writer.write_f64::<B>(value),
+        DataType::U16 => writer.write_u16::<B>(value as u16),
+        DataType::U32 => writer.write_u32::<B>(value as u32),
+        DataType::I64 => writer.write_i64::<B>(value as i64),
+        DataType::U64 => writer.write_u64::<B>(value as u64),
+        _ => Err(Error::new(ErrorKind::InvalidInput, "Unsupported data type.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::io_utils::temp_file_name;
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let file_name = temp_file_name("envi_round_trip.img");
+        let mut configs = RasterConfigs::default();
+        configs.rows = 3;
+        configs.columns = 5;
+        configs.bands = 3;
+        configs.north = 4_800_000.0;
+        configs.south = 4_799_970.0;
+        configs.east = 600_050.0;
+        configs.west = 600_000.0;
+        configs.resolution_x = 10.0;
+        configs.resolution_y = 10.0;
+        configs.nodata = -1.0;
+        configs.data_type = DataType::F32;
+        configs.interleave = Interleave::Bip;
+        configs.epsg_code = 32617;
+        let mut r = Raster::initialize_using_config(&file_name, &configs);
+        for band in 0..3 {
+            for row in 0..3 {
+                for col in 0..5 {
+                    r.set_band_value(band, row, col, band as f64 + row as f64 * 0.5 + col as f64 * 0.25);
+                }
+            }
+        }
+        r.set_band_value(2, 2, 4, -1.0);
+        r.write().unwrap();
+        let input = Raster::new(&file_name, "r");
+        let _ = fs::remove_file(&file_name);
+        let _ = fs::remove_file(Path::new(&file_name).with_extension("hdr"));
+        let input = input.unwrap();
+
+        assert_eq!((input.configs.rows, input.configs.columns, input.configs.bands), (3, 5, 3));
+        assert_eq!((input.configs.data_type, input.configs.interleave), (DataType::F32, Interleave::Bip));
+        assert_eq!((input.configs.west, input.configs.north), (600_000.0, 4_800_000.0));
+        assert_eq!(input.configs.nodata, -1.0);
+        for band in 0..3 {
+            for row in 0..3 {
+                for col in 0..5 {
+                    assert_eq!(input.get_band_value(band, row, col), r.get_band_value(band, row, col));
+                }
+            }
+        }
+    }
+}
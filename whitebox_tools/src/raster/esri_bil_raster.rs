@@ -0,0 +1,360 @@
+use std::io::BufWriter;
+use std::io::prelude::*;
+use std::f64;
+use std::cmp::max;
+use std::fs::File;
+use std::path::Path;
+use crate::raster::*;
+use crate::raster::envi_raster::{read_sample, write_interleaved_data};
+use crate::raster::raster_data::RasterData;
+use crate::raster::raster_error::*;
+use crate::io_utils::byte_order_reader::ByteOrderReader;
+use crate::io_utils::byte_order_reader::Endianness;
+
+/// Reads an ESRI band interleaved by line (BIL), band interleaved by pixel (BIP) or band
+/// sequential (BSQ) raster, a binary data file (.bil, .bip or .bsq) described by a text
+/// header (.hdr). The location of the raster is read from a world file (e.g. .blw), if
+/// there is one, and otherwise from the header, and its coordinate system from a .prj
+/// file. The file name may be that of either the data file or the header.
+pub fn read_esri_bil(file_name: &str, configs: &mut RasterConfigs, data: &mut RasterData) -> Result<(), RasterError> {
+    let (header_file, data_file) = get_esri_bil_file_names(file_name, true);
+
+    let mut contents = String::new();
+    File::open(&header_file)?.read_to_string(&mut contents)?;
+
+    // The defaults are those of the format, with the layout also given by the extension.
+    configs.bands = 1;
+    configs.endian = Endianness::LittleEndian;
+    configs.interleave = get_interleave(&data_file);
+    let mut nbits = 8usize;
+    let mut pixel_type = String::new();
+    let mut skip_bytes = 0usize;
+    let mut band_row_bytes = 0usize;
+    let mut total_row_bytes = 0usize;
+    let mut band_gap_bytes = 0usize;
+    let mut ulxmap = f64::NAN;
+    let mut ulymap = f64::NAN;
+    let mut xdim = 1f64;
+    let mut ydim = 1f64;
+    for line in contents.lines() {
+        let mut parts = line.split_whitespace();
+        let key = match parts.next() {
+            Some(k) => k.to_lowercase(),
+            None => continue,
+        };
+        let value = parts.collect::<Vec<&str>>().join(" ");
+        match key.as_ref() {
+            "nrows" => configs.rows = parse_value(&value)?,
+            "ncols" => configs.columns = parse_value(&value)?,
+            "nbands" => configs.bands = parse_value(&value)?,
+            "nbits" => nbits = parse_value(&value)?,
+            "pixeltype" => pixel_type = value.to_lowercase(),
+            "byteorder" => {
+                configs.endian = if value.to_lowercase().starts_with("m") { Endianness::BigEndian } else { Endianness::LittleEndian };
+            },
+            "layout" | "interleaving" => {
+                configs.interleave = match value.to_lowercase().as_ref() {
+                    "bil" => Interleave::Bil,
+                    "bip" => Interleave::Bip,
+                    "bsq" => Interleave::Bsq,
+                    _ => return Err(RasterError::new(RasterErrorKind::InvalidData, format!("Unrecognized ESRI layout ({}).", value))),
+                };
+            },
+            "skipbytes" => skip_bytes = parse_value(&value)?,
+            "bandrowbytes" => band_row_bytes = parse_value(&value)?,
+            "totalrowbytes" => total_row_bytes = parse_value(&value)?,
+            "bandgapbytes" => band_gap_bytes = parse_value(&value)?,
+            "ulxmap" => ulxmap = parse_value(&value)?,
+            "ulymap" => ulymap = parse_value(&value)?,
+            "xdim" => xdim = parse_value(&value)?,
+            "ydim" => ydim = parse_value(&value)?,
+            "nodata" | "nodata_value" => configs.nodata = parse_value(&value)?,
+            _ => {},
+        }
+    }
+    if configs.rows == 0 || configs.columns == 0 {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, "The ESRI header does not specify the number of rows and columns."));
+    }
+    configs.data_type = match (nbits, pixel_type.as_ref()) {
+        (8, "signedint") => DataType::I8,
+        (8, _) => DataType::U8,
+        (16, "signedint") => DataType::I16,
+        (16, _) => DataType::U16,
+        (32, "float") => DataType::F32,
+        (32, "signedint") => DataType::I32,
+        (32, _) => DataType::U32,
+        (64, "float") => DataType::F64,
+        (64, "signedint") => DataType::I64,
+        (64, _) => DataType::U64,
+        _ => return Err(RasterError::new(RasterErrorKind::Unsupported, format!("ESRI rasters of {} bits per pixel are not supported.", nbits))),
+    };
+    configs.photometric_interp = PhotometricInterpretation::Continuous;
+
+    // The world file and the header give the centre of the upper-left cell.
+    match read_world_file(&data_file)? {
+        Some(world) => {
+            xdim = world[0];
+            ydim = -world[3];
+            ulxmap = world[4];
+            ulymap = world[5];
+        },
+        None => {
+            if ulxmap.is_nan() { ulxmap = 0f64; }
+            if ulymap.is_nan() { ulymap = (configs.rows - 1) as f64 * ydim; }
+        },
+    }
+    configs.resolution_x = xdim;
+    configs.resolution_y = ydim;
+    configs.west = ulxmap - 0.5 * xdim;
+    configs.north = ulymap + 0.5 * ydim;
+    configs.east = configs.west + configs.columns as f64 * xdim;
+    configs.south = configs.north - configs.rows as f64 * ydim;
+
+    let prj_file = Path::new(&data_file).with_extension("prj");
+    if prj_file.is_file() {
+        let mut wkt = String::new();
+        File::open(&prj_file)?.read_to_string(&mut wkt)?;
+        // older .prj files describe the projection in keywords rather than WKT
+        let wkt = wkt.trim();
+        if wkt.starts_with("PROJCS") || wkt.starts_with("GEOGCS") {
+            configs.coordinate_ref_system_wkt = wkt.to_string();
+        }
+    }
+
+    // read the data file
+    let mut buffer = vec![];
+    File::open(&data_file)?.read_to_end(&mut buffer)?;
+    let (rows, columns, bands) = (configs.rows, configs.columns, max(configs.bands, 1));
+    let data_size = configs.data_type.get_data_size();
+    // Rows may be padded, e.g. to a multiple of four bytes, and the bands of a BSQ file
+    // separated by gaps.
+    if band_row_bytes == 0 {
+        band_row_bytes = columns * data_size;
+    }
+    if total_row_bytes == 0 {
+        total_row_bytes = match configs.interleave {
+            Interleave::Bil => bands * band_row_bytes,
+            Interleave::Bip => bands * columns * data_size,
+            Interleave::Bsq => band_row_bytes,
+        };
+    }
+    let interleave = configs.interleave;
+    let position = |band: usize, row: usize, col: usize| -> usize {
+        match interleave {
+            Interleave::Bil => skip_bytes + row * total_row_bytes + band * band_row_bytes + col * data_size,
+            Interleave::Bip => skip_bytes + row * total_row_bytes + (col * bands + band) * data_size,
+            Interleave::Bsq => skip_bytes + band * (rows * band_row_bytes + band_gap_bytes) + row * band_row_bytes + col * data_size,
+        }
+    };
+    let file_size = position(bands - 1, rows - 1, columns - 1) + data_size;
+    if buffer.len() < file_size {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, format!("The data file {} is smaller than its header describes.", data_file))
+            .at_offset(buffer.len() as u64));
+    }
+    let mut bor = ByteOrderReader::new(buffer, configs.endian);
+
+    // The bands are held band sequentially, whatever the layout of the file.
+    *data = RasterData::for_configs(configs, configs.data_type);
+    let data_type = configs.data_type;
+    for band in 0..bands {
+        for row in 0..rows {
+            for col in 0..columns {
+                bor.seek(position(band, row, col));
+                data.set_value((band * rows + row) * columns + col, read_sample(&mut bor, data_type));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes an ESRI BIL, BIP or BSQ raster, with the layout given by the extension of the
+/// data file (BIL for a header file name), along with a .prj file if the coordinate
+/// system of the raster is known.
+pub fn write_esri_bil(r: &mut Raster) -> Result<(), RasterError> {
+    let (header_file, data_file) = get_esri_bil_file_names(&r.file_name, false);
+    let (nbits, pixel_type) = match r.configs.data_type {
+        DataType::U8 => (8, "UNSIGNEDINT"),
+        DataType::I8 => (8, "SIGNEDINT"),
+        DataType::U16 => (16, "UNSIGNEDINT"),
+        DataType::I16 => (16, "SIGNEDINT"),
+        DataType::U32 => (32, "UNSIGNEDINT"),
+        DataType::I32 => (32, "SIGNEDINT"),
+        DataType::F32 => (32, "FLOAT"),
+        DataType::F64 => (64, "FLOAT"),
+        DataType::U64 => (64, "UNSIGNEDINT"),
+        DataType::I64 => (64, "SIGNEDINT"),
+        _ => return Err(RasterError::new(RasterErrorKind::Unsupported, format!("ESRI rasters cannot hold the {:?} data type.", r.configs.data_type))),
+    };
+    r.configs.interleave = get_interleave(&data_file);
+    let bands = r.num_bands();
+    let columns = r.configs.columns;
+    let band_row_bytes = columns * nbits / 8;
+    let (layout, total_row_bytes) = match r.configs.interleave {
+        Interleave::Bil => ("BIL", bands * band_row_bytes),
+        Interleave::Bip => ("BIP", bands * band_row_bytes),
+        Interleave::Bsq => ("BSQ", band_row_bytes),
+    };
+
+    // Save the header file
+    let f = File::create(&header_file)?;
+    let mut writer = BufWriter::new(f);
+    let byte_order = if r.configs.endian == Endianness::BigEndian { "M" } else { "I" };
+    writer.write_all(format!("BYTEORDER      {}\n", byte_order).as_bytes())?;
+    writer.write_all(format!("LAYOUT         {}\n", layout).as_bytes())?;
+    writer.write_all(format!("NROWS          {}\n", r.configs.rows).as_bytes())?;
+    writer.write_all(format!("NCOLS          {}\n", columns).as_bytes())?;
+    writer.write_all(format!("NBANDS         {}\n", bands).as_bytes())?;
+    writer.write_all(format!("NBITS          {}\n", nbits).as_bytes())?;
+    writer.write_all(format!("PIXELTYPE      {}\n", pixel_type).as_bytes())?;
+    writer.write_all(format!("BANDROWBYTES   {}\n", band_row_bytes).as_bytes())?;
+    writer.write_all(format!("TOTALROWBYTES  {}\n", total_row_bytes).as_bytes())?;
+    writer.write_all("BANDGAPBYTES   0\n".as_bytes())?;
+    writer.write_all(format!("ULXMAP         {}\n", r.configs.west + 0.5 * r.configs.resolution_x).as_bytes())?;
+    writer.write_all(format!("ULYMAP         {}\n", r.configs.north - 0.5 * r.configs.resolution_y).as_bytes())?;
+    writer.write_all(format!("XDIM           {}\n", r.configs.resolution_x).as_bytes())?;
+    writer.write_all(format!("YDIM           {}\n", r.configs.resolution_y).as_bytes())?;
+    // the format has a single nodata value for all of the bands
+    writer.write_all(format!("NODATA         {}\n", r.configs.nodata).as_bytes())?;
+    writer.flush()?;
+
+    let wkt = &r.configs.coordinate_ref_system_wkt;
+    if !wkt.is_empty() && wkt != "not specified" {
+        let mut f = File::create(Path::new(&data_file).with_extension("prj"))?;
+        f.write_all(wkt.replace("\n", "").as_bytes())?;
+    }
+
+    // write the data file
+    let f = File::create(&data_file)?;
+    let mut writer = BufWriter::new(f);
+    write_interleaved_data(&mut writer, r, r.configs.interleave)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Returns the names of the header and data files of an ESRI raster. When the header is
+/// given, the data file is the first of .bil, .bip and .bsq that exists when reading,
+/// and .bil when writing.
+fn get_esri_bil_file_names(file_name: &str, reading: bool) -> (String, String) {
+    let path = Path::new(file_name);
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    if extension == "hdr" {
+        let bil_file = path.with_extension("bil").to_string_lossy().to_string();
+        let data_file = if reading {
+            ["bil", "bip", "bsq"].iter()
+                .map(|e| path.with_extension(e).to_string_lossy().to_string())
+                .find(|f| Path::new(f).is_file())
+                .unwrap_or(bil_file)
+        } else {
+            bil_file
+        };
+        return (file_name.to_string(), data_file);
+    }
+    (path.with_extension("hdr").to_string_lossy().to_string(), file_name.to_string())
+}
+
+/// Returns the layout of an ESRI raster given by the extension of its data file.
+fn get_interleave(data_file: &str) -> Interleave {
+    match Path::new(data_file).extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase().as_ref() {
+        "bip" => Interleave::Bip,
+        "bsq" => Interleave::Bsq,
+        _ => Interleave::Bil,
+    }
+}
+
+/// Reads the world file of an ESRI raster, if there is one. Its extension is formed from
+/// the first and last letters of that of the data file followed by a 'w', e.g. .blw.
+/// The six values are the cell width, two rotation terms, the negative of the cell
+/// height and the coordinates of the centre of the upper-left cell.
+fn read_world_file(data_file: &str) -> Result<Option<Vec<f64>>, RasterError> {
+    let path = Path::new(data_file);
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    if extension.len() < 2 {
+        return Ok(None);
+    }
+    let world_extension = format!("{}{}w", &extension[0..1], &extension[extension.len() - 1..]);
+    let world_file = path.with_extension(world_extension);
+    if !world_file.is_file() {
+        return Ok(None);
+    }
+    let mut contents = String::new();
+    File::open(&world_file)?.read_to_string(&mut contents)?;
+    let mut values = vec![];
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        values.push(parse_value::<f64>(line)?);
+    }
+    if values.len() < 6 {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, format!("The world file {} does not hold six values.", world_file.to_string_lossy())));
+    }
+    Ok(Some(values))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::io_utils::temp_file_name;
+    use super::*;
+
+    /// Writes and reads back a raster of two bands, returning it as it was read.
+    fn round_trip(name: &str, data_type: DataType, endian: Endianness) -> Raster {
+        let file_name = temp_file_name(name);
+        let mut configs = RasterConfigs::default();
+        configs.rows = 3;
+        configs.columns = 4;
+        configs.bands = 2;
+        configs.north = 40.0;
+        configs.south = 10.0;
+        configs.east = 140.0;
+        configs.west = 100.0;
+        configs.resolution_x = 10.0;
+        configs.resolution_y = 10.0;
+        configs.nodata = 0.0;
+        configs.data_type = data_type;
+        configs.endian = endian;
+        let mut r = Raster::initialize_using_config(&file_name, &configs);
+        for band in 0..2 {
+            for row in 0..3 {
+                for col in 0..4 {
+                    r.set_band_value(band, row, col, (band as isize * 100 + row * 10 + col) as f64);
+                }
+            }
+        }
+        r.write().unwrap();
+        let input = Raster::new(&file_name, "r");
+        let _ = fs::remove_file(&file_name);
+        let _ = fs::remove_file(Path::new(&file_name).with_extension("hdr"));
+        input.unwrap()
+    }
+
+    fn assert_cells(r: &Raster) {
+        assert_eq!((r.configs.rows, r.configs.columns, r.configs.bands), (3, 4, 2));
+        assert_eq!((r.configs.west, r.configs.north, r.configs.resolution_x), (100.0, 40.0, 10.0));
+        for band in 0..2 {
+            for row in 0..3 {
+                for col in 0..4 {
+                    assert_eq!(r.get_band_value(band, row, col), (band as isize * 100 + row * 10 + col) as f64);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_of_each_layout() {
+        for &(name, interleave) in &[("bil.bil", Interleave::Bil), ("bip.bip", Interleave::Bip), ("bsq.bsq", Interleave::Bsq)] {
+            let r = round_trip(name, DataType::I16, Endianness::LittleEndian);
+            assert_eq!(r.configs.interleave, interleave);
+            assert_cells(&r);
+        }
+    }
+
+    #[test]
+    fn round_trip_of_64_bit_integers() {
+        let r = round_trip("u64.bil", DataType::U64, Endianness::BigEndian);
+        assert_eq!((r.configs.data_type, r.configs.endian), (DataType::U64, Endianness::BigEndian));
+        assert_cells(&r);
+        let r = round_trip("i64.bil", DataType::I64, Endianness::LittleEndian);
+        assert_eq!(r.configs.data_type, DataType::I64);
+        assert_cells(&r);
+    }
+}
@@ -0,0 +1,487 @@
+use std::collections::HashMap;
+use std::io::prelude::*;
+use std::io::{Error, ErrorKind};
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use crate::io_utils::byte_order_reader::Endianness;
+
+const LZW_CLEAR_CODE: usize = 256;
+const LZW_EOI_CODE: usize = 257;
+const LZW_FIRST_CODE: usize = 258;
+const LZW_MAX_CODE_WIDTH: usize = 12;
+
+/// Decodes a TIFF LZW-compressed strip or tile. Codes are packed most-significant bit
+/// first and, as in libtiff, the code width increases one code early.
+pub fn lzw_decoder(input_data: &[u8]) -> Result<Vec<u8>, Error> {
+    // Files written using the pre-TIFF 6.0 (LSB-first) variant start with a clear
+    // code in the opposite bit order.
+    if input_data.len() > 1 && input_data[0] == 0 && input_data[1] & 0x01 == 1 {
+        return Err(Error::new(ErrorKind::InvalidData, "Old-style (LSB-first) TIFF LZW compression is not supported."));
+    }
+
+    let mut output_data: Vec<u8> = Vec::with_capacity(input_data.len() * 3);
+
+    // Each table entry is a string of the output, stored as its position and length;
+    // an entry is always the previous string plus the first byte of the next, which
+    // follow one another in the output. Entries below 258 are the single bytes.
+    let mut table: Vec<(usize, usize)> = Vec::with_capacity(1 << LZW_MAX_CODE_WIDTH);
+    let mut code_width = 9;
+    let mut previous: Option<(usize, usize)> = None;
+
+    let mut bit_buffer = 0u32;
+    let mut bit_count = 0;
+    let mut pos = 0;
+    loop {
+        while bit_count < code_width {
+            if pos >= input_data.len() {
+                // some encoders omit the end of information code
+                return Ok(output_data);
+            }
+            bit_buffer = (bit_buffer << 8) | input_data[pos] as u32;
+            pos += 1;
+            bit_count += 8;
+        }
+        let code = ((bit_buffer >> (bit_count - code_width)) & ((1u32 << code_width) - 1)) as usize;
+        bit_count -= code_width;
+        bit_buffer &= (1u32 << bit_count) - 1;
+
+        if code == LZW_CLEAR_CODE {
+            table.clear();
+            code_width = 9;
+            previous = None;
+            continue;
+        }
+        if code == LZW_EOI_CODE {
+            return Ok(output_data);
+        }
+
+        let start = output_data.len();
+        let next_code = LZW_FIRST_CODE + table.len();
+        if code < LZW_CLEAR_CODE {
+            output_data.push(code as u8);
+        } else if code < next_code {
+            let (offset, length) = table[code - LZW_FIRST_CODE];
+            for i in offset..offset + length {
+                let b = output_data[i];
+                output_data.push(b);
+            }
+        } else if let Some((offset, length)) = previous.filter(|_| code == next_code) {
+            // the string is the previous string plus its own first byte
+            for i in offset..offset + length {
+                let b = output_data[i];
+                output_data.push(b);
+            }
+            let b = output_data[offset];
+            output_data.push(b);
+        } else {
+            return Err(Error::new(ErrorKind::InvalidData, "Invalid code in LZW-compressed data."));
+        }
+
+        if let Some((offset, length)) = previous {
+            if next_code < (1 << LZW_MAX_CODE_WIDTH) {
+                table.push((offset, length + 1));
+                if next_code + 2 == (1 << code_width) && code_width < LZW_MAX_CODE_WIDTH {
+                    code_width += 1;
+                }
+            }
+        }
+        previous = Some((start, output_data.len() - start));
+    }
+}
+
+/// Encodes a strip or tile using TIFF LZW compression, the inverse of `lzw_decoder`. The
+/// string table is cleared before it is full.
+pub fn lzw_encoder(input_data: &[u8]) -> Vec<u8> {
+    let mut output_data: Vec<u8> = Vec::with_capacity(input_data.len() / 2);
+    let mut bit_buffer = 0u32;
+    let mut bit_count = 0;
+    let mut code_width = 9;
+    {
+        let mut emit = |code: usize, code_width: usize| {
+            bit_buffer = (bit_buffer << code_width) | code as u32;
+            bit_count += code_width;
+            while bit_count >= 8 {
+                output_data.push((bit_buffer >> (bit_count - 8)) as u8);
+                bit_count -= 8;
+            }
+            bit_buffer &= (1u32 << bit_count) - 1;
+        };
+
+        // the table maps a string, as the code of its prefix and its last byte, to its code
+        let mut table: HashMap<(usize, u8), usize> = HashMap::new();
+        let mut next_code = LZW_FIRST_CODE;
+        emit(LZW_CLEAR_CODE, code_width);
+        let mut current: Option<usize> = None;
+        for &b in input_data {
+            let prefix = match current {
+                Some(c) => c,
+                None => {
+                    current = Some(b as usize);
+                    continue;
+                },
+            };
+            if let Some(&code) = table.get(&(prefix, b)) {
+                current = Some(code);
+                continue;
+            }
+            emit(prefix, code_width);
+            table.insert((prefix, b), next_code);
+            next_code += 1;
+            if next_code == (1 << code_width) && code_width < LZW_MAX_CODE_WIDTH {
+                code_width += 1;
+            }
+            if next_code == (1 << LZW_MAX_CODE_WIDTH) - 3 {
+                emit(LZW_CLEAR_CODE, code_width);
+                table.clear();
+                next_code = LZW_FIRST_CODE;
+                code_width = 9;
+            }
+            current = Some(b as usize);
+        }
+        if let Some(c) = current {
+            emit(c, code_width);
+            next_code += 1;
+            if next_code == (1 << code_width) && code_width < LZW_MAX_CODE_WIDTH {
+                code_width += 1;
+            }
+        }
+        emit(LZW_EOI_CODE, code_width);
+    }
+    if bit_count > 0 {
+        output_data.push((bit_buffer << (8 - bit_count)) as u8);
+    }
+    output_data
+}
+
+/// Decodes a Deflate-compressed (zlib) strip or tile.
+pub fn deflate_decoder(input_data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut output_data = vec![];
+    match ZlibDecoder::new(input_data).read_to_end(&mut output_data) {
+        Ok(_) => Ok(output_data),
+        Err(e) => Err(Error::new(ErrorKind::InvalidData, format!("Error decompressing Deflate-compressed data: {}", e))),
+    }
+}
+
+/// Encodes a strip or tile using Deflate (zlib) compression.
+pub fn deflate_encoder(input_data: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut encoder = ZlibEncoder::new(Vec::with_capacity(input_data.len() / 2), Compression::default());
+    encoder.write_all(input_data)?;
+    encoder.finish()
+}
+
+/// Encodes a strip or tile using PackBits run-length compression, the inverse of
+/// `packbits_decoder`. Runs of three or more repeated bytes are replicated; other bytes
+/// are copied literally, up to 128 bytes at a time.
+pub fn packbits_encoder(input_data: &[u8]) -> Vec<u8> {
+    let mut output_data = vec![];
+    let n = input_data.len();
+    let mut i = 0;
+    while i < n {
+        let mut run = 1;
+        while i + run < n && run < 128 && input_data[i + run] == input_data[i] {
+            run += 1;
+        }
+        if run > 2 {
+            output_data.push((257 - run) as u8);
+            output_data.push(input_data[i]);
+            i += run;
+        } else {
+            // a literal sequence extends up to the next run of three repeated bytes
+            let start = i;
+            while i < n && i - start < 128 {
+                if i + 2 < n && input_data[i] == input_data[i + 1] && input_data[i] == input_data[i + 2] {
+                    break;
+                }
+                i += 1;
+            }
+            output_data.push((i - start - 1) as u8);
+            output_data.extend_from_slice(&input_data[start..i]);
+        }
+    }
+    output_data
+}
+
+/// Applies horizontal differencing (predictor 2) to a strip or tile, in place, before it
+/// is compressed. The samples are in the given byte order.
+pub fn apply_horizontal_predictor(data: &mut [u8], row_width: usize, samples_per_pixel: usize,
+    bytes_per_sample: usize, byte_order: Endianness) -> Result<(), Error> {
+    let row_length = row_width * samples_per_pixel * bytes_per_sample;
+    if row_length == 0 {
+        return Ok(());
+    }
+    let step = samples_per_pixel * bytes_per_sample;
+    match bytes_per_sample {
+        1 => {
+            for row in data.chunks_mut(row_length) {
+                for i in (step..row.len()).rev() {
+                    row[i] = row[i].wrapping_sub(row[i - step]);
+                }
+            }
+        },
+        2 | 4 | 8 => {
+            let mask = if bytes_per_sample == 8 { u64::MAX } else { (1u64 << (bytes_per_sample * 8)) - 1 };
+            for row in data.chunks_mut(row_length) {
+                let num_samples = row.len() / bytes_per_sample;
+                for k in (samples_per_pixel..num_samples).rev() {
+                    let i = k * bytes_per_sample;
+                    let value = read_uint(&row[i..i + bytes_per_sample], byte_order)
+                        .wrapping_sub(read_uint(&row[i - step..i - step + bytes_per_sample], byte_order)) & mask;
+                    write_uint(&mut row[i..i + bytes_per_sample], value, byte_order);
+                }
+            }
+        },
+        _ => return Err(Error::new(ErrorKind::InvalidInput, "The horizontal predictor is not supported for this sample size.")),
+    }
+    Ok(())
+}
+
+/// Applies the floating-point predictor (predictor 3) to a strip or tile, in place, before
+/// it is compressed; see `undo_floating_point_predictor`. The samples are in the given
+/// byte order.
+pub fn apply_floating_point_predictor(data: &mut [u8], row_width: usize, samples_per_pixel: usize,
+    bytes_per_sample: usize, byte_order: Endianness) -> Result<(), Error> {
+    if bytes_per_sample != 2 && bytes_per_sample != 4 && bytes_per_sample != 8 {
+        return Err(Error::new(ErrorKind::InvalidInput, "The floating-point predictor is not supported for this sample size."));
+    }
+    let num_samples = row_width * samples_per_pixel;
+    let row_length = num_samples * bytes_per_sample;
+    if row_length == 0 {
+        return Ok(());
+    }
+    let mut samples = vec![0u8; row_length];
+    for row in data.chunks_mut(row_length) {
+        if row.len() < row_length {
+            break;
+        }
+        samples.copy_from_slice(row);
+        for k in 0..num_samples {
+            for b in 0..bytes_per_sample {
+                let j = match byte_order {
+                    Endianness::BigEndian => b,
+                    Endianness::LittleEndian => bytes_per_sample - 1 - b,
+                };
+                row[b * num_samples + k] = samples[k * bytes_per_sample + j];
+            }
+        }
+        for i in (samples_per_pixel..row_length).rev() {
+            row[i] = row[i].wrapping_sub(row[i - samples_per_pixel]);
+        }
+    }
+    Ok(())
+}
+
+/// Reverses horizontal differencing (predictor 2) in a decompressed strip or tile, in place.
+/// Each sample is stored as its difference from the same sample of the previous pixel
+/// in the row.
+pub fn undo_horizontal_predictor(data: &mut [u8], row_width: usize, samples_per_pixel: usize,
+    bytes_per_sample: usize, byte_order: Endianness) -> Result<(), Error> {
+    let row_length = row_width * samples_per_pixel * bytes_per_sample;
+    if row_length == 0 {
+        return Ok(());
+    }
+    let step = samples_per_pixel * bytes_per_sample;
+    match bytes_per_sample {
+        1 => {
+            for row in data.chunks_mut(row_length) {
+                for i in step..row.len() {
+                    row[i] = row[i].wrapping_add(row[i - step]);
+                }
+            }
+        },
+        2 | 4 | 8 => {
+            let mask = if bytes_per_sample == 8 { u64::MAX } else { (1u64 << (bytes_per_sample * 8)) - 1 };
+            for row in data.chunks_mut(row_length) {
+                let mut i = step;
+                while i + bytes_per_sample <= row.len() {
+                    let value = read_uint(&row[i - step..i - step + bytes_per_sample], byte_order)
+                        .wrapping_add(read_uint(&row[i..i + bytes_per_sample], byte_order)) & mask;
+                    write_uint(&mut row[i..i + bytes_per_sample], value, byte_order);
+                    i += bytes_per_sample;
+                }
+            }
+        },
+        _ => return Err(Error::new(ErrorKind::InvalidData, "The horizontal predictor is not supported for this sample size.")),
+    }
+    Ok(())
+}
+
+/// Reverses the floating-point predictor (predictor 3) in a decompressed strip or tile, in
+/// place. The bytes of each row are differenced and are stored as byte planes, from the
+/// most to the least significant byte of the samples. The output samples are in the file's
+/// byte order.
+pub fn undo_floating_point_predictor(data: &mut [u8], row_width: usize, samples_per_pixel: usize,
+    bytes_per_sample: usize, byte_order: Endianness) -> Result<(), Error> {
+    if bytes_per_sample != 2 && bytes_per_sample != 4 && bytes_per_sample != 8 {
+        return Err(Error::new(ErrorKind::InvalidData, "The floating-point predictor is not supported for this sample size."));
+    }
+    let num_samples = row_width * samples_per_pixel;
+    let row_length = num_samples * bytes_per_sample;
+    if row_length == 0 {
+        return Ok(());
+    }
+    let mut planes = vec![0u8; row_length];
+    for row in data.chunks_mut(row_length) {
+        if row.len() < row_length {
+            break;
+        }
+        for i in samples_per_pixel..row_length {
+            row[i] = row[i].wrapping_add(row[i - samples_per_pixel]);
+        }
+        planes.copy_from_slice(row);
+        for k in 0..num_samples {
+            for b in 0..bytes_per_sample {
+                let j = match byte_order {
+                    Endianness::BigEndian => b,
+                    Endianness::LittleEndian => bytes_per_sample - 1 - b,
+                };
+                row[k * bytes_per_sample + j] = planes[b * num_samples + k];
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_uint(bytes: &[u8], byte_order: Endianness) -> u64 {
+    let mut value = 0u64;
+    match byte_order {
+        Endianness::BigEndian => {
+            for b in bytes {
+                value = (value << 8) | *b as u64;
+            }
+        },
+        Endianness::LittleEndian => {
+            for b in bytes.iter().rev() {
+                value = (value << 8) | *b as u64;
+            }
+        },
+    }
+    value
+}
+
+fn write_uint(bytes: &mut [u8], value: u64, byte_order: Endianness) {
+    let n = bytes.len();
+    for i in 0..n {
+        let b = (value >> (8 * i)) as u8;
+        match byte_order {
+            Endianness::BigEndian => bytes[n - 1 - i] = b,
+            Endianness::LittleEndian => bytes[i] = b,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io_utils::byte_order_reader::Endianness;
+    use super::*;
+
+    /// Bytes with short runs and a longer pattern, so that the LZW string table fills and is
+    /// cleared several times.
+    fn sample_data(n: usize) -> Vec<u8> {
+        (0..n).map(|i| ((i * i / 7) % 251) as u8 ^ (i / 1000) as u8).collect()
+    }
+
+    #[test]
+    fn lzw_example_of_the_tiff_specification() {
+        let decoded = vec![7u8, 7, 7, 8, 8, 7, 7, 6, 6];
+        let encoded = vec![0x80u8, 0x01, 0xe0, 0x40, 0x80, 0x44, 0x08, 0x0c, 0x06, 0x80, 0x80];
+        assert_eq!(lzw_decoder(&encoded).unwrap(), decoded);
+        assert_eq!(lzw_encoder(&decoded), encoded);
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        for &n in &[0usize, 1, 2, 255, 4096, 100_000] {
+            let data = sample_data(n);
+            assert_eq!(lzw_decoder(&lzw_encoder(&data)).unwrap(), data, "{} bytes", n);
+        }
+        let runs = vec![3u8; 50_000];
+        assert_eq!(lzw_decoder(&lzw_encoder(&runs)).unwrap(), runs);
+    }
+
+    #[test]
+    fn invalid_lzw_data_is_an_error() {
+        // a clear code followed by a code that is not yet in the table
+        assert!(lzw_decoder(&[0x80, 0x40, 0x80]).is_err());
+        // the old-style, LSB-first variant
+        assert!(lzw_decoder(&[0x00, 0x01, 0x00]).is_err());
+    }
+
+    #[test]
+    fn deflate_round_trip() {
+        let data = sample_data(20_000);
+        let encoded = deflate_encoder(&data).unwrap();
+        assert!(encoded.len() < data.len());
+        assert_eq!(deflate_decoder(&encoded).unwrap(), data);
+        assert!(deflate_decoder(&data[..100]).is_err());
+    }
+
+    #[test]
+    fn horizontal_predictor_round_trip() {
+        // 3 rows of 5 pixels of 2 samples
+        let (row_width, samples_per_pixel) = (5, 2);
+        for &bytes_per_sample in &[1usize, 2, 4, 8] {
+            for &byte_order in &[Endianness::LittleEndian, Endianness::BigEndian] {
+                let data = sample_data(3 * row_width * samples_per_pixel * bytes_per_sample);
+                let mut predicted = data.clone();
+                apply_horizontal_predictor(&mut predicted, row_width, samples_per_pixel, bytes_per_sample, byte_order).unwrap();
+                assert!(predicted != data);
+                undo_horizontal_predictor(&mut predicted, row_width, samples_per_pixel, bytes_per_sample, byte_order).unwrap();
+                assert_eq!(predicted, data, "{} bytes, {:?}", bytes_per_sample, byte_order);
+            }
+        }
+        assert!(apply_horizontal_predictor(&mut [0u8; 6], 2, 1, 3, Endianness::LittleEndian).is_err());
+    }
+
+    #[test]
+    fn horizontal_predictor_differences_samples() {
+        // two pixels of two 16-bit samples, little-endian
+        let mut data = vec![10u8, 0, 1, 1, 12, 0, 0, 1];
+        apply_horizontal_predictor(&mut data, 2, 2, 2, Endianness::LittleEndian).unwrap();
+        assert_eq!(data, vec![10u8, 0, 1, 1, 2, 0, 255, 255]);
+    }
+
+    #[test]
+    fn horizontal_predictor_sums_samples() {
+        let mut data = vec![10u8, 0, 1, 1, 2, 0, 255, 255];
+        undo_horizontal_predictor(&mut data, 2, 2, 2, Endianness::LittleEndian).unwrap();
+        assert_eq!(data, vec![10u8, 0, 1, 1, 12, 0, 0, 1]);
+    }
+
+    #[test]
+    fn floating_point_predictor_round_trip() {
+        let (row_width, samples_per_pixel) = (6, 1);
+        for &bytes_per_sample in &[2usize, 4, 8] {
+            for &byte_order in &[Endianness::LittleEndian, Endianness::BigEndian] {
+                let data = sample_data(4 * row_width * samples_per_pixel * bytes_per_sample);
+                let mut predicted = data.clone();
+                apply_floating_point_predictor(&mut predicted, row_width, samples_per_pixel, bytes_per_sample, byte_order).unwrap();
+                undo_floating_point_predictor(&mut predicted, row_width, samples_per_pixel, bytes_per_sample, byte_order).unwrap();
+                assert_eq!(predicted, data, "{} bytes, {:?}", bytes_per_sample, byte_order);
+            }
+        }
+        assert!(undo_floating_point_predictor(&mut [0u8; 4], 4, 1, 1, Endianness::LittleEndian).is_err());
+    }
+
+    #[test]
+    fn floating_point_predictor_stores_byte_planes() {
+        // two big-endian 16-bit samples, 0x0102 and 0x0304, are stored as the planes [1, 3]
+        // and [2, 4] and then differenced
+        let mut data = vec![1u8, 2, 3, 4];
+        apply_floating_point_predictor(&mut data, 2, 1, 2, Endianness::BigEndian).unwrap();
+        assert_eq!(data, vec![1u8, 2, 255, 2]);
+        let mut data = vec![2u8, 1, 4, 3];
+        apply_floating_point_predictor(&mut data, 2, 1, 2, Endianness::LittleEndian).unwrap();
+        assert_eq!(data, vec![1u8, 2, 255, 2]);
+    }
+
+    #[test]
+    fn floating_point_predictor_restores_byte_planes() {
+        let mut data = vec![1u8, 2, 255, 2];
+        undo_floating_point_predictor(&mut data, 2, 1, 2, Endianness::BigEndian).unwrap();
+        assert_eq!(data, vec![1u8, 2, 3, 4]);
+        let mut data = vec![1u8, 2, 255, 2];
+        undo_floating_point_predictor(&mut data, 2, 1, 2, Endianness::LittleEndian).unwrap();
+        assert_eq!(data, vec![2u8, 1, 4, 3]);
+    }
+}
@@ -1,9 +1,7 @@
 use std::collections::HashMap;
 use std::fmt;
-use std::mem;
-use std::mem::transmute;
-use raster::geotiff::IfdDirectory;
-use io_utils::byte_order_reader::Endianness;
+use crate::raster::geotiff::IfdDirectory;
+use crate::io_utils::byte_order_reader::Endianness;
 
 macro_rules! hashmap {
     ($( $key: expr => $val: expr ),*) => {{
@@ -24,34 +22,34 @@ pub struct GeoKeys {
 
 impl GeoKeys {
 
-    pub fn add_key_directory(&mut self, data: &Vec<u8>) {
+    pub fn add_key_directory(&mut self, data: &[u8]) {
         // convert the binary data to an array of u16's
         let mut i : usize = 0;
-        while i < data.len() as usize {
+        while i + 1 < data.len() {
             let k : u16 = data[i] as u16 | ((data[i + 1] as u16) << 8u16);
             self.geo_key_directory.push(k);
             i += 2;
         }
     }
 
-    pub fn add_double_params(&mut self, data: &Vec<u8>) {
+    pub fn add_double_params(&mut self, data: &[u8]) {
         let mut i : usize = 0;
-        while i < data.len() as usize {
-            let k: f64 = unsafe { mem::transmute::<[u8; 8], f64>([data[i],
+        while i + 8 <= data.len() {
+            let k: f64 = f64::from_ne_bytes([data[i],
                 data[i + 1], data[i + 2], data[i + 3],
                 data[i + 4], data[i + 5], data[i + 6],
-                data[i + 7]]) };
+                data[i + 7]]);
             i += 8;
             self.geo_double_params.push(k);
         }
     }
 
-    pub fn add_ascii_params(&mut self, data: &Vec<u8>) {
+    pub fn add_ascii_params(&mut self, data: &[u8]) {
         self.geo_ascii_params = String::from_utf8_lossy(&data[0..data.len()]).trim().to_owned();
     }
 
     pub fn get_ifd_map(&self, byte_order: Endianness) -> HashMap<u16, IfdDirectory> {
-        if self.geo_key_directory.len() == 0 {
+        if self.geo_key_directory.is_empty() {
             panic!("Error reading geokeys");
         }
         let number_of_keys = self.geo_key_directory[3];
@@ -77,7 +75,7 @@ impl GeoKeys {
                 field_type = 12;
                 let value = &self.geo_double_params[value_offset as usize..(value_offset+count) as usize];
                 for &v in value {
-                    let byte_array = unsafe { transmute::<f64, [u8; 8]>(v) };
+                    let byte_array = f64::to_ne_bytes(v);
                     for i in 0..8 {
                         data.push(byte_array[i]);
                     }
@@ -85,19 +83,17 @@ impl GeoKeys {
             } else if tiff_tag_location == 0 {
                 // short (u16) data
                 field_type = 3;
-                let byte_array = unsafe { transmute::<u16, [u8; 2]>(value_offset) };
+                let byte_array = u16::to_ne_bytes(value_offset);
                 for i in 0..2 {
                     data.push(byte_array[i]);
                 }
-            } else {
-
-            }
+            } 
 
             let ifd = IfdDirectory::new(
                 key_id,
                 field_type,
                 count as u32,
-                value_offset as u32,
+                value_offset as u64,
                 data,
                 byte_order
             );
@@ -108,8 +104,33 @@ impl GeoKeys {
         ifd_map
     }
 
+    /// Returns the EPSG code of the CRS, taken from the projected CRS key (3072), or failing
+    /// that the geographic CRS key (2048). Returns zero if neither key holds a code, e.g.
+    /// because the CRS is user-defined (32767).
+    pub fn find_epsg_code(&self) -> u16 {
+        if self.geo_key_directory.len() < 4 {
+            return 0;
+        }
+        let number_of_keys = self.geo_key_directory[3] as usize;
+        for key_id in &[3072u16, 2048u16] {
+            for i in 0..number_of_keys {
+                let offset = 4 * (i+1);
+                if offset + 3 >= self.geo_key_directory.len() {
+                    break;
+                }
+                if self.geo_key_directory[offset] == *key_id && self.geo_key_directory[offset+1] == 0 {
+                    let value = self.geo_key_directory[offset+3];
+                    if value != 0 && value != 32767 {
+                        return value;
+                    }
+                }
+            }
+        }
+        0
+    }
+
     pub fn interpret_geokeys(&self) -> String {
-        if self.geo_key_directory.len() == 0 {
+        if self.geo_key_directory.is_empty() {
             return "GeoKeys have not been set.".to_string();
         }
         let keys = get_keys_map();
@@ -178,10 +199,10 @@ impl GeoKeys {
     Value={}", i+1, key, value_offset);
                 }
             } else {
-                s = s + "Unknown tag";
+                s += "Unknown tag";
             }
     }
-        return s;
+        s
     }
 
 }
@@ -2152,7 +2173,10 @@ pub fn get_field_type_map() -> HashMap<u16, &'static str> {
         9u16 => "DT_Slong",
         10u16 => "DT_Srational",
         11u16 => "DT_Float",
-        12u16 => "DT_Double"
+        12u16 => "DT_Double",
+        16u16 => "DT_Long8",
+        17u16 => "DT_Slong8",
+        18u16 => "DT_Ifd8"
     ]
 }
 
@@ -1,4 +1,5 @@
 #![allow(unused_assignments, dead_code)]
+pub mod compression;
 pub mod geokeys;
 
 use std::collections::HashMap;
@@ -6,142 +7,224 @@ use std::io::Error;
 use std::io::ErrorKind;
 use std::fmt;
 use std::default::Default;
-use std::cmp::min;
-use std::io::BufWriter;
+use std::cmp::{max, min};
+use std::io::{BufWriter, SeekFrom};
 use std::io::prelude::*;
 use std::f64;
 use std::fs::File;
-use std::fs;
-use std::mem;
-use raster::*;
-use raster::geotiff::geokeys::*;
-use io_utils::byte_order_reader::ByteOrderReader;
-use io_utils::byte_order_reader::Endianness;
+use std::ops::Range;
+use byteorder::{LittleEndian, WriteBytesExt};
+use crate::raster::*;
+use crate::raster::raster_data::RasterData;
+use crate::raster::raster_error::*;
+use crate::raster::window::RasterWindow;
+use crate::raster::geotiff::compression::*;
+use crate::raster::geotiff::geokeys::*;
+use crate::io_utils::byte_order_reader::ByteOrderReader;
+use crate::io_utils::byte_order_reader::Endianness;
+
+pub fn read_geotiff<'a>(file_name: &'a str, configs: &'a mut RasterConfigs, data: &'a mut RasterData) -> Result<(), RasterError> {
+    let mut f = File::open(file_name)?;
+    let layout = read_header(&mut f, configs)?;
+
+    *data = RasterData::for_configs(configs, configs.data_type);
+    let image = RasterWindow { row: 0, column: 0, rows: layout.height, columns: layout.width };
+    for plane in 0..layout.num_planes {
+        for j in 0..layout.blocks_down {
+            for i in 0..layout.blocks_across {
+                read_block(&mut f, &layout, plane, i, j, &image, data)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the header of a GeoTIFF file, but not its image, returning the layout of the
+/// image that windows of it are read using.
+pub fn read_geotiff_header(file_name: &String, configs: &mut RasterConfigs) -> Result<BlockLayout, RasterError> {
+    let mut f = File::open(file_name)?;
+    read_header(&mut f, configs)
+}
+
+/// Reads a window of a GeoTIFF, reading only the strips or tiles that it overlaps. The
+/// layout and configs are those read by `read_geotiff_header`.
+pub fn read_geotiff_window(file_name: &String, layout: &BlockLayout, configs: &RasterConfigs, window: &RasterWindow, data: &mut RasterData) -> Result<(), RasterError> {
+    let mut f = File::open(file_name)?;
+
+    *data = RasterData::for_configs(&window.get_configs(configs), configs.data_type);
+    let col_start = max(window.column, 0) as usize;
+    let col_end = min(window.column + window.columns as isize, layout.width as isize);
+    let row_start = max(window.row, 0) as usize;
+    let row_end = min(window.row + window.rows as isize, layout.height as isize);
+    if col_end <= col_start as isize || row_end <= row_start as isize {
+        return Ok(()); // the window lies outside of the image
+    }
+    let blocks_across = col_start / layout.block_width..(col_end as usize - 1) / layout.block_width + 1;
+    let blocks_down = row_start / layout.block_height..(row_end as usize - 1) / layout.block_height + 1;
+    for plane in 0..layout.num_planes {
+        for j in blocks_down.clone() {
+            for i in blocks_across.clone() {
+                read_block(&mut f, layout, plane, i, j, window, data)?;
+            }
+        }
+    }
 
-pub fn read_geotiff<'a>(file_name: &'a String, configs: &'a mut RasterConfigs, data: &'a mut Vec<f64>) -> Result<(), Error> {
-    let mut f = File::open(file_name.clone())?;
+    Ok(())
+}
 
-    let metadata = fs::metadata(file_name.clone())?;
-    let file_size: usize = metadata.len() as usize;
-    let mut buffer = vec![0; file_size];
+/// The layout of the image of a GeoTIFF: the type of its samples and how it is divided
+/// into strips or tiles.
+pub struct BlockLayout {
+    endian: Endianness,
+    mode: ImageMode,
+    compression: u16,
+    predictor: u16,
+    bits_per_sample: Vec<u16>,
+    sample_format: Vec<u16>,
+    palette: Vec<u32>,
+    width: usize,
+    height: usize,
+    block_width: usize,
+    block_height: usize,
+    blocks_across: usize,
+    blocks_down: usize,
+    block_padding: bool,
+    block_offsets: Vec<u64>,
+    block_counts: Vec<u64>,
+    planar: bool,
+    num_planes: usize,
+    num_bands: usize,
+}
 
-    // read the file's bytes into a buffer
-    f.read(&mut buffer)?;
+/// Reads the header and first IFD of a GeoTIFF file, returning the layout of the image.
+/// Only the parts of the file that hold them are read.
+fn read_header(f: &mut File, configs: &mut RasterConfigs) -> Result<BlockLayout, RasterError> {
+    let file_size = f.metadata()?.len();
+    let buffer = read_bytes(f, 0, min(file_size, 16) as usize)?;
+    if buffer.len() < 8 {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, "Incorrect TIFF header.").at_offset(0));
+    }
 
     //let byte_order = LittleEndian::read_u16(&buffer[0..2]);
     match &buffer[0..2] { //byte_order { //LittleEndian::read_u16(&buffer[0..2]) {
         b"II" => configs.endian = Endianness::LittleEndian,
         b"MM" => configs.endian = Endianness::BigEndian,
-        _ => return Err(Error::new(ErrorKind::InvalidData, "Incorrect TIFF header."))
+        _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "Incorrect TIFF header.").at_offset(0))
     }
 
     let mut th = ByteOrderReader::new(buffer, configs.endian);
     th.seek(2);
 
-    match th.read_u16() {
-        42 => (), // do nothing
-        43 => return Err(Error::new(ErrorKind::InvalidData, "The BigTiff format is not currently supported.")),
-        _ => return Err(Error::new(ErrorKind::InvalidData, "Incorrect TIFF header."))
-    }
+    // A BigTIFF file has 64-bit offsets and counts, and so larger IFD entries.
+    let big_tiff = match th.read_u16() {
+        42 => false,
+        43 => {
+            if th.read_u16() != 8 {
+                return Err(RasterError::new(RasterErrorKind::InvalidData, "Incorrect BigTIFF header; the size of offsets must be 8 bytes.").at_offset(4));
+            }
+            th.read_u16(); // always zero
+            true
+        },
+        _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "Incorrect TIFF header.").at_offset(0))
+    };
+    configs.geotiff_options.big_tiff = big_tiff;
+    let offset_size = if big_tiff { 8 } else { 4 };
 
-    let mut ifd_offset = th.read_u32() as usize;
+    // the position of the offset of the next IFD, for reporting errors
+    let mut pointer_position = th.pos as u64;
+    let mut ifd_offset = if big_tiff { th.read_u64() as usize } else { th.read_u32() as usize };
 
     let mut ifd_map = HashMap::new();
 
     let mut geokeys: GeoKeys = Default::default();
-    let mut cur_pos: usize;
+    let mut num_ifds = 0usize;
     while ifd_offset > 0 {
-        th.seek(ifd_offset);
-        let num_directories = th.read_u16();
+        if ifd_offset as u64 >= file_size {
+            return Err(RasterError::new(RasterErrorKind::InvalidData, "The offset of an IFD is beyond the end of the file.").at_offset(pointer_position));
+        }
+        let count_size = if big_tiff { 8 } else { 2 };
+        let mut th = ByteOrderReader::new(read_bytes(f, ifd_offset as u64, count_size)?, configs.endian);
+        let num_directories = if big_tiff { th.read_u64() } else { th.read_u16() as u64 };
+        // the entries, followed by the offset of the next IFD
+        let entry_size = if big_tiff { 20 } else { 12 };
+        let ifd_size = (num_directories as usize).saturating_mul(entry_size).saturating_add(offset_size);
+        let mut th = ByteOrderReader::new(read_bytes(f, (ifd_offset + count_size) as u64, ifd_size)?, configs.endian);
+        num_ifds += 1;
+        if num_ifds > 1 {
+            // Later IFDs hold reduced-resolution copies of the image (overviews) or masks,
+            // which are skipped; only their number is needed.
+            if num_ifds > 65_536 {
+                return Err(RasterError::new(RasterErrorKind::InvalidData, "The IFDs of the file form a cycle."));
+            }
+            th.seek(num_directories as usize * entry_size);
+            pointer_position = (ifd_offset + count_size + th.pos) as u64;
+            ifd_offset = if big_tiff { th.read_u64() as usize } else { th.read_u32() as usize };
+            continue;
+        }
         for _ in 0..num_directories {
+            let entry_offset = (ifd_offset + count_size + th.pos) as u64;
             let tag_id = th.read_u16();
             let field_type = th.read_u16();
-            let num_values = th.read_u32();
-            let value_offset = th.read_u32();
-            let data_size = match field_type {
+            let num_values = if big_tiff { th.read_u64() } else { th.read_u32() as u64 };
+            let value_offset = if big_tiff { th.read_u64() } else { th.read_u32() as u64 };
+            let data_size: u64 = match field_type {
                 1u16 | 2u16 | 6u16 | 7u16 => 1,
                 3u16 | 8u16 => 2,
                 4u16 | 9u16 | 11u16 => 4,
-                5u16 | 10u16 | 12u16 => 8,
-                _ => return Err(Error::new(ErrorKind::InvalidInput, "Error reading the IFDs."))
+                5u16 | 10u16 | 12u16 | 16u16 | 17u16 | 18u16 => 8,
+                _ => return Err(RasterError::new(RasterErrorKind::InvalidData, format!("Error reading the IFDs; unknown field type ({}).", field_type)).at_offset(entry_offset))
             };
 
             // read the tag data
-            let mut data: Vec<u8> = vec![];
-            if (data_size * num_values) > 4 {
+            let data: Vec<u8> = if data_size.saturating_mul(num_values) > offset_size as u64 {
                 // the values are stored at the offset location
-                cur_pos = th.pos;
-                th.seek(value_offset as usize);
-                for _ in 0..num_values*data_size {
-                    data.push(th.read_u8());
-                }
-                th.seek(cur_pos);
+                read_bytes(f, value_offset, data_size.saturating_mul(num_values) as usize)?
             } else {
                 // the value(s) are contained in the offset
-                cur_pos = th.pos;
-                th.seek(cur_pos - 4);
-                for _ in 0..num_values*data_size {
-                    data.push(th.read_u8());
-                }
-                th.seek(cur_pos);
-            }
+                let start = th.pos - offset_size;
+                th.buffer[start..start + (num_values * data_size) as usize].to_vec()
+            };
 
             let ifd = IfdDirectory::new(
                 tag_id,
                 field_type,
-                num_values,
+                num_values as u32,
                 value_offset,
                 data,
                 configs.endian
             );
             ifd_map.insert(tag_id, ifd.clone());
         }
-        ifd_offset = th.read_u32() as usize;
+        pointer_position = (ifd_offset + count_size + th.pos) as u64;
+        ifd_offset = if big_tiff { th.read_u64() as usize } else { th.read_u32() as usize };
     }
 
-    configs.columns = match ifd_map.get(&256) {
-        Some(ifd) => {
-            // The 256 tag can be either u16 or u32 type
-            if ifd.ifd_type == 3 {
-                ifd.interpret_as_u16()[0] as usize
-            } else {
-                ifd.interpret_as_u32()[0] as usize
-            }
-        },
-        _ => return Err(Error::new(ErrorKind::InvalidData, "The raster Columns value was not read correctly")),
+    configs.columns = match get_tag_value(&ifd_map, 256)? {
+        Some(v) if v > 0 => v as usize,
+        _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster Columns value was not read correctly")),
     };
 
-    configs.rows = match ifd_map.get(&257) {
-        Some(ifd) => {
-            // The 257 tag can be either u16 or u32 type
-            if ifd.ifd_type == 3 {
-                ifd.interpret_as_u16()[0] as usize
-            } else {
-                ifd.interpret_as_u32()[0] as usize
-            }
-        },
-        _ => return Err(Error::new(ErrorKind::InvalidData, "The raster Rows value was not read correctly")),
+    configs.rows = match get_tag_value(&ifd_map, 257)? {
+        Some(v) if v > 0 => v as usize,
+        _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster Rows value was not read correctly")),
     };
 
-    //data = vec![0.0f64; configs.rows * configs.columns];
-    data.clear();
-    for _ in 0..configs.rows*configs.columns {
-        data.push(0.0f64);
-    }
-
     let bits_per_sample = match ifd_map.get(&258) {
-        Some(ifd) => ifd.interpret_as_u16(),
-        _ => return Err(Error::new(ErrorKind::InvalidData, "The raster BitsPerSample value was not read correctly")),
+        Some(ifd) if !ifd.interpret_as_u16().is_empty() => ifd.interpret_as_u16(),
+        _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster BitsPerSample value was not read correctly")),
     };
 
-    let compression = match ifd_map.get(&259) {
-        Some(ifd) => ifd.interpret_as_u16()[0],
-        _ => return Err(Error::new(ErrorKind::InvalidData, "The raster Compression method value was not read correctly")),
+    let compression = match get_tag_value(&ifd_map, 259)? {
+        Some(v) => v as u16,
+        _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster Compression method value was not read correctly")),
     };
 
-    let photometric_interp = match ifd_map.get(&262) {
-        Some(ifd) => ifd.interpret_as_u16()[0],
-        _ => return Err(Error::new(ErrorKind::InvalidData, "The raster PhotometricInterpretation value was not read correctly")),
+    let predictor = get_tag_value(&ifd_map, TAG_PREDICTOR)?.unwrap_or(1) as u16;
+
+    let photometric_interp = match get_tag_value(&ifd_map, 262)? {
+        Some(v) => v as u16,
+        _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster PhotometricInterpretation value was not read correctly")),
     };
 
     // let num_samples = match ifd_map.get(&277) {
@@ -149,32 +232,35 @@ pub fn read_geotiff<'a>(file_name: &'a String, configs: &'a mut RasterConfigs, d
     //     _ => 0,
     // };
 
-    let extra_samples = match ifd_map.get(&338) {
-        Some(ifd) => ifd.interpret_as_u16()[0],
-        _ => 0,
-    };
+    let extra_samples = get_tag_value(&ifd_map, 338)?.unwrap_or(0);
 
     let sample_format = match ifd_map.get(&339) {
-        Some(ifd) => ifd.interpret_as_u16(),
-        _ => [0].to_vec(),
+        Some(ifd) if !ifd.interpret_as_u16().is_empty() => ifd.interpret_as_u16(),
+        Some(_) => return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster SampleFormat value was not read correctly")),
+        _ => [1].to_vec(), // unsigned integer data, by default
     };
 
-    match ifd_map.get(&34735) {
-        Some(ifd) => geokeys.add_key_directory(&ifd.data),
-        _ => return Err(Error::new(ErrorKind::InvalidData, "The TIFF file does not contain geokeys")),
+    // GDAL_NODATA holds one value for all bands, or, as written by write_geotiff, the
+    // values of each band separated by spaces, of which GDAL reads the first.
+    if let Some(ifd) = ifd_map.get(&TAG_GDAL_NODATA) {
+        match ifd.interpret_as_ascii().split_whitespace().map(|v| v.parse::<f64>()).collect::<Result<Vec<f64>, _>>() {
+            Ok(ref values) if values.len() == 1 => configs.nodata = values[0],
+            Ok(values) => if values.len() > 1 {
+                configs.nodata = values[0];
+                configs.band_nodata = values;
+            },
+            Err(_) => {},
+        }
     };
 
-    match ifd_map.get(&34736) {
-        Some(ifd) => geokeys.add_double_params(&ifd.data),
-        _ => {},
+    match ifd_map.get(&34735) {
+        Some(ifd) => geokeys.add_key_directory(&ifd.data),
+        _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The TIFF file does not contain geokeys")),
     };
 
-    match ifd_map.get(&34737) {
-        Some(ifd) => geokeys.add_ascii_params(&ifd.data),
-        _ => {},
-    };
+    if let Some(ifd) = ifd_map.get(&34736) { geokeys.add_double_params(&ifd.data) };
 
-    let geokeys_map = geokeys.get_ifd_map(configs.endian);
+    if let Some(ifd) = ifd_map.get(&34737) { geokeys.add_ascii_params(&ifd.data) };
 
     let model_tiepoints = match ifd_map.get(&33922) {
         Some(ifd) => ifd.interpret_as_f64(),
@@ -195,26 +281,30 @@ pub fn read_geotiff<'a>(file_name: &'a String, configs: &'a mut RasterConfigs, d
         configs.south = configs.north - configs.resolution_y * configs.rows as f64;
     }
 
-    // Get the EPSG code
-    if geokeys_map.contains_key(&2048) {
-        configs.epsg_code = geokeys_map.get(&2048).unwrap().interpret_as_u16()[0];
-    }
+    // Get the EPSG code, of the projected CRS if there is one
+    configs.epsg_code = geokeys.find_epsg_code();
 
 
     // Determine the image mode.
     let kw_map = get_keyword_map();
     let photomet_map = kw_map.get(&262).unwrap();
-    let photomet_str: String = photomet_map.get(&photometric_interp).unwrap().to_string();
+    let photomet_str: String = match photomet_map.get(&photometric_interp) {
+        Some(s) => s.to_string(),
+        None => return Err(RasterError::new(RasterErrorKind::Unsupported, format!("Unsupported PhotometricInterpretation ({}).", photometric_interp))),
+    };
     let mode: ImageMode;
     let mut palette = vec![];
     if photomet_str == "RGB" {
+        if bits_per_sample.len() < 3 {
+            return Err(RasterError::new(RasterErrorKind::InvalidData, "Wrong number of samples for RGB."));
+        }
         if bits_per_sample[0] == 16 {
             if bits_per_sample[1] != 16 || bits_per_sample[2] != 16 {
-                return Err(Error::new(ErrorKind::InvalidData, "Wrong number of samples for 16bit RGB."));
+                return Err(RasterError::new(RasterErrorKind::InvalidData, "Wrong number of samples for 16bit RGB."));
             }
         } else {
             if bits_per_sample[0] != 8 || bits_per_sample[1] != 8 || bits_per_sample[2] != 8 {
-                return Err(Error::new(ErrorKind::InvalidData, "Wrong number of samples for 8bit RGB."));
+                return Err(RasterError::new(RasterErrorKind::InvalidData, "Wrong number of samples for 8bit RGB."));
             }
         }
         // RGB images normally have 3 samples per pixel.
@@ -228,21 +318,21 @@ pub fn read_geotiff<'a>(file_name: &'a String, configs: &'a mut RasterConfigs, d
                 match extra_samples {
                     1 => ImageMode::RGBA,
                     2 => ImageMode::NRGBA,
-                    _ => return Err(Error::new(ErrorKind::InvalidData, "Wrong number of samples for RGB."))
+                    _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "Wrong number of samples for RGB."))
                 }
             },
-            _ => return Err(Error::new(ErrorKind::InvalidData, "Wrong number of samples for RGB."))
+            _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "Wrong number of samples for RGB."))
         };
     } else if photomet_str == "Paletted" {
         mode = ImageMode::Paletted;
         // retreive the palette colour data
         let color_map = match ifd_map.get(&320) {
             Some(ifd) => ifd.interpret_as_u16(),
-            _ => return Err(Error::new(ErrorKind::InvalidData, "Colour map not present in Paletted TIFF.")),
+            _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "Colour map not present in Paletted TIFF.")),
         };
         let num_colors = color_map.len() / 3;
-        if color_map.len()%3 != 0 || num_colors <= 0 || num_colors > 256 {
-            return Err(Error::new(ErrorKind::InvalidData, "bad ColorMap length"));
+        if color_map.len()%3 != 0 || num_colors == 0 || num_colors > 256 {
+            return Err(RasterError::new(RasterErrorKind::InvalidData, "bad ColorMap length"));
         }
         for i in 0..num_colors {
             // colours in the colour map are given in 16-bit channels
@@ -251,7 +341,7 @@ pub fn read_geotiff<'a>(file_name: &'a String, configs: &'a mut RasterConfigs, d
             let green = (color_map[i+num_colors] as f64 / 65535.0 * 255.0) as u32;
             let blue = (color_map[i+2*num_colors] as f64 / 65535.0 * 255.0) as u32;
             let a = 255u32;
-            let val = ((a << 24) | (red << 16) | (green << 8) | blue) as u32;
+            let val = (a << 24) | (red << 16) | (green << 8) | blue;
             palette.push(val);
         }
     } else if photomet_str == "WhiteIsZero" {
@@ -259,9 +349,55 @@ pub fn read_geotiff<'a>(file_name: &'a String, configs: &'a mut RasterConfigs, d
     } else if photomet_str == "BlackIsZero" {
         mode = ImageMode::Gray;
     } else {
-        return Err(Error::new(ErrorKind::InvalidData, "Unsupported image format."))
+        return Err(RasterError::new(RasterErrorKind::Unsupported, "Unsupported image format."))
+    }
+
+    match mode {
+        ImageMode::Gray | ImageMode::GrayInvert => {
+            configs.photometric_interp = PhotometricInterpretation::Continuous;
+            configs.data_type = match (sample_format[0], bits_per_sample[0]) {
+                (1, 8) => DataType::U8,
+                (1, 16) => DataType::U16,
+                (1, 32) => DataType::U32,
+                (1, 64) => DataType::U64,
+                (2, 8) => DataType::I8,
+                (2, 16) => DataType::I16,
+                (2, 32) => DataType::I32,
+                (2, 64) => DataType::I64,
+                (3, 32) => DataType::F32,
+                (3, 64) => DataType::F64,
+                _ => DataType::Unknown,
+            };
+        },
+        ImageMode::RGB => {
+            // 16-bit channels are rescaled to 8 bits when they are read
+            configs.photometric_interp = PhotometricInterpretation::RGB;
+            configs.data_type = DataType::RGB24;
+        },
+        ImageMode::RGBA | ImageMode::NRGBA => {
+            configs.photometric_interp = PhotometricInterpretation::RGB;
+            configs.data_type = DataType::RGBA32;
+        },
+        ImageMode::Paletted => {
+            configs.photometric_interp = PhotometricInterpretation::Paletted;
+            configs.data_type = DataType::RGBA32;
+        },
+        _ => {},
     }
 
+    // Outputs based on this raster are written in the same layout by default.
+    configs.geotiff_options.compression = match compression {
+        COMPRESS_PACKBITS => GeoTiffCompression::PackBits,
+        COMPRESS_LZW => GeoTiffCompression::Lzw,
+        COMPRESS_DEFLATE | COMPRESS_DEFLATEOLD => GeoTiffCompression::Deflate,
+        _ => GeoTiffCompression::None,
+    };
+    configs.geotiff_options.predictor = match predictor {
+        2 => GeoTiffPredictor::Horizontal,
+        3 => GeoTiffPredictor::FloatingPoint,
+        _ => GeoTiffPredictor::None,
+    };
+
     let width = configs.columns;
     let height = configs.rows;
 
@@ -271,730 +407,894 @@ pub fn read_geotiff<'a>(file_name: &'a String, configs: &'a mut RasterConfigs, d
     let mut blocks_across = 1;
     let blocks_down; // = 1;
 
-    let block_offsets: Vec<u32>; //  = vec![];
-    let block_counts: Vec<u32>; // = vec![];
+    let block_offsets: Vec<u64>; //  = vec![];
+    let block_counts: Vec<u64>; // = vec![];
 
     if ifd_map.contains_key(&322) {
         block_padding = true;
 
-        block_width = match ifd_map.get(&322) {
-            Some(ifd) => {
-                // The 322 tag can be either u16 or u32 type
-                if ifd.ifd_type == 3 {
-                    ifd.interpret_as_u16()[0] as usize
-                } else {
-                    ifd.interpret_as_u32()[0] as usize
-                }
-            },
-            _ => return Err(Error::new(ErrorKind::InvalidData, "The TileWidth value was not read correctly")),
+        block_width = match get_tag_value(&ifd_map, 322)? {
+            Some(v) if v > 0 => v as usize,
+            _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The TileWidth value was not read correctly")),
         };
 
-        block_height = match ifd_map.get(&323) {
-            Some(ifd) => {
-                // The 323 tag can be either u16 or u32 type
-                if ifd.ifd_type == 3 {
-                    ifd.interpret_as_u16()[0] as usize
-                } else {
-                    ifd.interpret_as_u32()[0] as usize
-                }
-            },
-            _ => return Err(Error::new(ErrorKind::InvalidData, "The TileLength value was not read correctly")),
+        block_height = match get_tag_value(&ifd_map, 323)? {
+            Some(v) if v > 0 => v as usize,
+            _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The TileLength value was not read correctly")),
         };
 
-        blocks_across = (width + block_width - 1) / block_width;
-        blocks_down = (height + block_height - 1) / block_height;
+        blocks_across = width.div_ceil(block_width);
+        blocks_down = height.div_ceil(block_height);
+        configs.geotiff_options.tile_width = block_width;
+        configs.geotiff_options.tile_height = block_height;
+        // A tiled file with overviews is taken to be a COG, so that it is written as one
+        configs.geotiff_options.cloud_optimized = num_ifds > 1;
 
         block_offsets = match ifd_map.get(&324) {
-            Some(ifd) => ifd.interpret_as_u32(),
-            _ => return Err(Error::new(ErrorKind::InvalidData, "The raster BitsPerSample value was not read correctly")),
+            Some(ifd) => ifd.interpret_as_u64(),
+            _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster TileOffsets value was not read correctly")),
         };
 
         block_counts = match ifd_map.get(&325) {
-            Some(ifd) => {
-                // The 325 tag can be either u16 or u32 type
-                if ifd.ifd_type == 3 {
-                    let ifd_data = ifd.interpret_as_u16();
-                    let mut ret: Vec<u32> = vec![];
-                    for val in ifd_data.iter() {
-                        ret.push(*val as u32);
-                    }
-                    ret
-                } else {
-                    ifd.interpret_as_u32()
-                }
-            },
-            _ => return Err(Error::new(ErrorKind::InvalidData, "The TileLength value was not read correctly")),
+            Some(ifd) => ifd.interpret_as_u64(),
+            _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster TileByteCounts value was not read correctly")),
         };
     } else {
-        block_height = match ifd_map.get(&278) {
-            Some(ifd) => {
-                // The 278 tag can be either u16 or u32 type
-                if ifd.ifd_type == 3 {
-                    ifd.interpret_as_u16()[0] as usize
-                } else {
-                    ifd.interpret_as_u32()[0] as usize
-                }
-            },
-            _ => return Err(Error::new(ErrorKind::InvalidData, "The RowsPerStrip value was not read correctly")),
+        block_height = match get_tag_value(&ifd_map, 278)? {
+            Some(v) if v > 0 => v as usize,
+            _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The RowsPerStrip value was not read correctly")),
         };
 
-        blocks_down = (height + block_height - 1) / block_height;
+        blocks_down = height.div_ceil(block_height);
 
         block_offsets = match ifd_map.get(&273) {
-            Some(ifd) => {
-                // The 273 tag can be either u16 or u32 type
-                if ifd.ifd_type == 3 {
-                    let ifd_data = ifd.interpret_as_u16();
-                    let mut ret: Vec<u32> = vec![];
-                    for val in ifd_data.iter() {
-                        ret.push(*val as u32);
-                    }
-                    ret
-                } else {
-                    ifd.interpret_as_u32()
-                }
-            },
-            _ => return Err(Error::new(ErrorKind::InvalidData, "The raster StripOffsets value was not read correctly")),
+            Some(ifd) => ifd.interpret_as_u64(),
+            _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster StripOffsets value was not read correctly")),
         };
 
         block_counts = match ifd_map.get(&279) {
-            Some(ifd) => {
-                // The 279 tag can be either u16 or u32 type
-                if ifd.ifd_type == 3 {
-                    let ifd_data = ifd.interpret_as_u16();
-                    let mut ret: Vec<u32> = vec![];
-                    for val in ifd_data.iter() {
-                        ret.push(*val as u32);
-                    }
-                    ret
-                } else {
-                    ifd.interpret_as_u32()
-                }
-            },
-            _ => return Err(Error::new(ErrorKind::InvalidData, "The raster StripByteCounts value was not read correctly")),
+            Some(ifd) => ifd.interpret_as_u64(),
+            _ => return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster StripByteCounts value was not read correctly")),
         };
     }
 
-    for i in 0..blocks_across {
-        let mut blk_w = block_width;
-        if !block_padding && i == blocks_across-1 && width%block_width != 0 {
-            blk_w = width % block_width;
+    // The samples of a pixel are stored together (chunky) or the bands are stored in
+    // separate planes, each divided into blocks.
+    let planar = get_tag_value(&ifd_map, TAG_PLANARCONFIGURATION)? == Some(2);
+    let num_bands = match mode {
+        ImageMode::Gray | ImageMode::GrayInvert => bits_per_sample.len(),
+        _ => 1,
+    };
+    if planar && num_bands == 1 && bits_per_sample.len() > 1 {
+        return Err(RasterError::new(RasterErrorKind::Unsupported, "Planar colour GeoTIFFs are currently unsupported."));
+    }
+    configs.bands = num_bands;
+    configs.geotiff_options.planar = planar && num_bands > 1;
+    let num_planes = if planar { num_bands } else { 1 };
+    if block_offsets.len() < num_planes * blocks_across * blocks_down || block_counts.len() < block_offsets.len() {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, "The GeoTIFF has too few blocks for the size of the image."));
+    }
+
+    let layout = BlockLayout {
+        endian: configs.endian,
+        mode,
+        compression,
+        predictor,
+        bits_per_sample,
+        sample_format,
+        palette,
+        width,
+        height,
+        block_width,
+        block_height,
+        blocks_across,
+        blocks_down,
+        block_padding,
+        block_offsets,
+        block_counts,
+        planar,
+        num_planes,
+        num_bands,
+    };
+    Ok(layout)
+}
+
+/// Reads a strip or tile of an image (of one plane of a planar image) into the cells of a
+/// window of the image that it overlaps.
+fn read_block(f: &mut File, layout: &BlockLayout, plane: usize, i: usize, j: usize, window: &RasterWindow, data: &mut RasterData) -> Result<(), RasterError> {
+    let (width, height) = (layout.width, layout.height);
+    let (block_width, block_height) = (layout.block_width, layout.block_height);
+    let bits_per_sample = &layout.bits_per_sample;
+    let (compression, predictor) = (layout.compression, layout.predictor);
+    let (planar, num_bands) = (layout.planar, layout.num_bands);
+    let samples_per_block = if planar { 1 } else { bits_per_sample.len() };
+
+    let mut blk_w = block_width;
+    if !layout.block_padding && i == layout.blocks_across-1 && width%block_width != 0 {
+        blk_w = width % block_width;
+    }
+    let mut blk_h = block_height;
+    if !layout.block_padding && j == layout.blocks_down-1 && height%block_height != 0 {
+        blk_h = height % block_height;
+    }
+    let block = (plane * layout.blocks_down + j) * layout.blocks_across + i;
+    let offset = layout.block_offsets[block];
+    let n = layout.block_counts[block] as usize;
+    if offset.saturating_add(n as u64) > f.metadata()?.len() {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, "The raster data extend beyond the end of the file.").at_offset(offset));
+    }
+    let raw = read_bytes(f, offset, n)?;
+    let mut buf: Vec<u8> = match compression {
+        COMPRESS_NONE => { // no compression
+            raw
         }
-        for j in 0..blocks_down {
-            let mut blk_h = block_height;
-            if !block_padding && j == blocks_down-1 && height%block_height != 0 {
-                blk_h = height % block_height;
-            }
-            let offset = block_offsets[j*blocks_across+i] as usize;
-            let n = block_counts[j*blocks_across+i] as usize;
-            let mut buf: Vec<u8> = vec![];
-            match compression {
-                COMPRESS_NONE => { // no compression
-                    buf = th.buffer[offset..(offset+n)].to_vec();
-                }
-                COMPRESS_PACKBITS => {
-                    buf = packbits_decoder(th.buffer[offset..(offset+n)].to_vec());
-                },
-                _ => {
+        COMPRESS_PACKBITS => {
+            packbits_decoder(raw)
+        },
+        COMPRESS_LZW => {
+            lzw_decoder(&raw).map_err(|e| RasterError::from(e).at_offset(offset))?
+        },
+        COMPRESS_DEFLATE | COMPRESS_DEFLATEOLD => {
+            deflate_decoder(&raw).map_err(|e| RasterError::from(e).at_offset(offset))?
+        },
+        _ => return Err(RasterError::new(RasterErrorKind::Unsupported, format!("Unsupported TIFF compression method ({}).", compression)).at_offset(offset)),
+    };
+    // Predictors difference the samples along each row of the block, which for
+    // strips is the width of the image.
+    let bytes_per_sample = bits_per_sample[0] as usize / 8;
+    match predictor {
+        1 => {},
+        2 => undo_horizontal_predictor(&mut buf, block_width, samples_per_block, bytes_per_sample, layout.endian).map_err(|e| RasterError::from(e).at_offset(offset))?,
+        3 => undo_floating_point_predictor(&mut buf, block_width, samples_per_block, bytes_per_sample, layout.endian).map_err(|e| RasterError::from(e).at_offset(offset))?,
+        _ => return Err(RasterError::new(RasterErrorKind::Unsupported, format!("Unsupported TIFF predictor ({}).", predictor))),
+    }
 
+    let xmin = i * block_width;
+    let ymin = j * block_height;
+    let mut xmax = xmin + blk_w;
+    let mut ymax = ymin + blk_h;
+
+    xmax = min(xmax, width);
+    ymax = min(ymax, height);
+
+    // Tiles on the right edge of the image are padded beyond it. The padding is
+    // dropped so that the rows of the tile that lie within the image are contiguous.
+    let bytes_per_pixel = samples_per_block * bits_per_sample[0] as usize / 8;
+    if layout.block_padding && xmax - xmin < block_width {
+        let tile_row_size = block_width * bytes_per_pixel;
+        let image_row_size = (xmax - xmin) * bytes_per_pixel;
+        let mut compacted = Vec::with_capacity(block_height * image_row_size);
+        for tile_row in buf.chunks(tile_row_size) {
+            compacted.extend_from_slice(&tile_row[..min(image_row_size, tile_row.len())]);
+        }
+        buf = compacted;
+    }
+    if buf.len() < (ymax - ymin) * (xmax - xmin) * bytes_per_pixel {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, format!("A strip or tile decodes to {} bytes, fewer than the {} that its pixels require.",
+            buf.len(), (ymax - ymin) * (xmax - xmin) * bytes_per_pixel)).at_offset(offset));
+    }
+    let mut bor = ByteOrderReader::new(buf, layout.endian);
+
+    match layout.mode {
+        ImageMode::GrayInvert | ImageMode::Gray => {
+            let read_sample: fn(&mut ByteOrderReader) -> f64 = match (layout.sample_format[0], bits_per_sample[0]) {
+                (1, 8) => |bor| bor.read_u8() as f64,
+                (1, 16) => |bor| bor.read_u16() as f64,
+                (1, 32) => |bor| bor.read_u32() as f64,
+                (1, 64) => |bor| bor.read_u64() as f64,
+                (2, 8) => |bor| bor.read_i8() as f64,
+                (2, 16) => |bor| bor.read_i16() as f64,
+                (2, 32) => |bor| bor.read_i32() as f64,
+                (2, 64) => |bor| bor.read_i64() as f64,
+                (3, 32) => |bor| bor.read_f32() as f64,
+                (3, 64) => |bor| bor.read_f64(),
+                _ => return Err(RasterError::new(RasterErrorKind::Unsupported, "The raster was not read correctly")),
+            };
+            // Each pixel holds a sample of every band, unless the bands are in separate planes
+            let bands = if planar { plane..plane + 1 } else { 0..num_bands };
+            for y in ymin..ymax {
+                for x in xmin..xmax {
+                    for band in bands.clone() {
+                        let value = read_sample(&mut bor);
+                        if let Some(k) = window.get_index(band, y, x) {
+                            data.set_value(k, value);
+                        }
+                    }
                 }
             }
-            let mut bor = ByteOrderReader::new(buf, configs.endian);
-
-
-            let xmin = i * block_width;
-            let ymin = j * block_height;
-            let mut xmax = xmin + blk_w;
-            let mut ymax = ymin + blk_h;
-
-            xmax = min(xmax, width);
-            ymax = min(ymax, height);
-
-            let mut off = 0;
-
-            match mode {
-                ImageMode::GrayInvert | ImageMode::Gray => {
-                    match sample_format[0] {
-                        1 => { // unsigned integer
-                            match bits_per_sample[0] {
-                                8 => {
-                                    for y in ymin..ymax {
-                                        for x in xmin..xmax {
-                                            if off <= bor.len() {
-                                                let i = y*width + x;
-                                                data[i] = bor.read_u8() as f64; //buf[off] as f64;
-                                                off += 1;
-                                            }
-                                        }
-                                    }
-                                },
-                                16 => {
-                                    for y in ymin..ymax {
-                                        for x in xmin..xmax {
-                                            if off <= bor.len() {
-                                                let value = bor.read_u16(); // g.ByteOrder.Uint16(g.buf[g.off : g.off+2])
-                                                let i = y*width + x;
-                                                data[i] = value as f64;
-                                                off += 2;
-                                            }
-                                        }
-                                    }
-                                },
-                                32 => {
-                                    for y in ymin..ymax {
-                                        for x in xmin..xmax {
-                                            if off <= bor.len() {
-                                                let value = bor.read_u32();
-                                                let i = y*width + x;
-                                                data[i] = value as f64;
-                                                off += 4;
-                                            }
-                                        }
-                                    }
-                                },
-                                64 => {
-                                    for y in ymin..ymax {
-                                        for x in xmin..xmax {
-                                            if off <= bor.len() {
-                                                let value = bor.read_u64();
-                                                let i = y*width + x;
-                                                data[i] = value as f64;
-                                                off += 8;
-                                            }
-                                        }
-                                    }
-                                },
-                                _ => return Err(Error::new(ErrorKind::InvalidData, "The raster was not read correctly")),
-                            }
-                        },
-                        2 => { // signed integer
-                            match bits_per_sample[0] {
-                                8 => {
-                                    for y in ymin..ymax {
-                                        for x in xmin..xmax {
-                                            if off <= bor.len() {
-                                                let i = y*width + x;
-                                                data[i] = bor.read_i8() as f64;
-                                                off += 1;
-                                            }
-                                        }
-                                    }
-                                },
-                                16 => {
-                                    for y in ymin..ymax {
-                                        for x in xmin..xmax {
-                                            if off <= bor.len() {
-                                                let value = bor.read_i16();
-                                                let i = y*width + x;
-                                                data[i] = value as f64;
-                                                off += 2;
-                                            }
-                                        }
-                                    }
-                                },
-                                32 => {
-                                    for y in ymin..ymax {
-                                        for x in xmin..xmax {
-                                            if off <= bor.len() {
-                                                let value = bor.read_i32();
-                                                let i = y*width + x;
-                                                data[i] = value as f64;
-                                                off += 4;
-                                            }
-                                        }
-                                    }
-                                },
-                                64 => {
-                                    for y in ymin..ymax {
-                                        for x in xmin..xmax {
-                                            if off <= bor.len() {
-                                                let value = bor.read_i64();
-                                                let i = y*width + x;
-                                                data[i] = value as f64;
-                                                off += 8;
-                                            }
-                                        }
-                                    }
-                                },
-                                _ => return Err(Error::new(ErrorKind::InvalidData, "The raster was not read correctly")),
-                            }
-                        },
-                        3 => { // floating point
-                            match bits_per_sample[0] {
-                                32 => {
-                                    for y in ymin..ymax {
-                                        for x in xmin..xmax {
-                                            let value = bor.read_f32();
-                                            let i = y*width + x;
-                                            data[i] = value as f64;
-                                            off += 4;
-                                        }
-                                    }
-                                },
-                                64 => {
-                                    for y in ymin..ymax {
-                                        for x in xmin..xmax {
-                                            if off <= bor.len() {
-                                                let value = bor.read_f64();
-                                                let i = y*width + x;
-                                                data[i] = value;
-                                                off += 8;
-                                            }
-                                        }
-                                    }
-                                },
-                                _ => return Err(Error::new(ErrorKind::InvalidData, "The raster was not read correctly")),
-                            }
-                        },
-                        _ => return Err(Error::new(ErrorKind::InvalidData, "The raster was not read correctly")),
+        },
+        ImageMode::Paletted => {
+            for y in ymin..ymax {
+                for x in xmin..xmax {
+                    let value = bor.read_u8() as usize;
+                    if value >= layout.palette.len() {
+                        return Err(RasterError::new(RasterErrorKind::InvalidData, format!("The colour index {} is beyond the end of the colour map.", value)).at_offset(offset));
                     }
-                },
-                ImageMode::Paletted => {
-                    for y in ymin..ymax {
-                        for x in xmin..xmax {
-                            let i = y*width + x;
-                            let value = bor.read_u8() as usize;
-                            data[i] = palette[value] as f64;
-                        }
+                    if let Some(k) = window.get_index(0, y, x) {
+                        data.set_value(k, layout.palette[value] as f64);
                     }
-                },
-                ImageMode::RGB => {
-                    if bits_per_sample[0] == 8 {
-                        for y in ymin..ymax {
-                            for x in xmin..xmax {
-                                let red = bor.read_u8() as u32; //uint32(g.buf[g.off]);
-                                let green = bor.read_u8() as u32; //uint32(g.buf[g.off+1]);
-                                let blue = bor.read_u8() as u32; //uint32(g.buf[g.off+2]);
-                                let a = 255u32;
-                                let value = (a << 24) | (red << 16) | (green << 8) | blue;
-                                let i = y*width + x;
-                                data[i] = value as f64;
-                            }
+                }
+            }
+        },
+        ImageMode::RGB => {
+            if bits_per_sample[0] == 8 {
+                for y in ymin..ymax {
+                    for x in xmin..xmax {
+                        let red = bor.read_u8() as u32; //uint32(g.buf[g.off]);
+                        let green = bor.read_u8() as u32; //uint32(g.buf[g.off+1]);
+                        let blue = bor.read_u8() as u32; //uint32(g.buf[g.off+2]);
+                        let a = 255u32;
+                        let value = (a << 24) | (red << 16) | (green << 8) | blue;
+                        if let Some(k) = window.get_index(0, y, x) {
+                            data.set_value(k, value as f64);
                         }
-                    } else if bits_per_sample[0] == 16 {
-                        // the spec doesn't talk about 16-bit RGB images so
-                        // I'm not sure why I bother with this. They specifically
-                        // say that RGB images are 8-bits per channel. Anyhow,
-                        // I rescale the 16-bits to an 8-bit channel for simplicity.
-                        for y in ymin..ymax {
-                            for x in xmin..xmax {
-                                let red = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
-                                let green = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
-                                let blue = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
-                                let a = 255u32;
-                                let value = (a << 24) | (red << 16) | (green << 8) | blue;
-                                let i = y*width + x;
-                                data[i] = value as f64;
-                            }
+                    }
+                }
+            } else if bits_per_sample[0] == 16 {
+                // the spec doesn't talk about 16-bit RGB images so
+                // I'm not sure why I bother with this. They specifically
+                // say that RGB images are 8-bits per channel. Anyhow,
+                // I rescale the 16-bits to an 8-bit channel for simplicity.
+                for y in ymin..ymax {
+                    for x in xmin..xmax {
+                        let red = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
+                        let green = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
+                        let blue = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
+                        let a = 255u32;
+                        let value = (a << 24) | (red << 16) | (green << 8) | blue;
+                        if let Some(k) = window.get_index(0, y, x) {
+                            data.set_value(k, value as f64);
                         }
-                    } else {
-                        return Err(Error::new(ErrorKind::InvalidData, "The raster was not read correctly"));
                     }
-                },
-                ImageMode::NRGBA | ImageMode::RGBA => {
-                    if bits_per_sample[0] == 8 {
-                        for y in ymin..ymax {
-                            for x in xmin..xmax {
-                                let red = bor.read_u8() as u32; //uint32(g.buf[g.off]);
-                                let green = bor.read_u8() as u32; //uint32(g.buf[g.off+1]);
-                                let blue = bor.read_u8() as u32; //uint32(g.buf[g.off+2]);
-                                let a = bor.read_u8() as u32;
-                                let value = (a << 24) | (red << 16) | (green << 8) | blue;
-                                let i = y*width + x;
-                                data[i] = value as f64;
-                            }
+                }
+            } else {
+                return Err(RasterError::new(RasterErrorKind::Unsupported, "The raster was not read correctly"));
+            }
+        },
+        ImageMode::NRGBA | ImageMode::RGBA => {
+            if bits_per_sample[0] == 8 {
+                for y in ymin..ymax {
+                    for x in xmin..xmax {
+                        let red = bor.read_u8() as u32; //uint32(g.buf[g.off]);
+                        let green = bor.read_u8() as u32; //uint32(g.buf[g.off+1]);
+                        let blue = bor.read_u8() as u32; //uint32(g.buf[g.off+2]);
+                        let a = bor.read_u8() as u32;
+                        let value = (a << 24) | (red << 16) | (green << 8) | blue;
+                        if let Some(k) = window.get_index(0, y, x) {
+                            data.set_value(k, value as f64);
                         }
-                    } else if bits_per_sample[0] == 16 {
-                        // the spec doesn't talk about 16-bit RGB images so
-                        // I'm not sure why I bother with this. They specifically
-                        // say that RGB images are 8-bits per channel. Anyhow,
-                        // I rescale the 16-bits to an 8-bit channel for simplicity.
-                        for y in ymin..ymax {
-                            for x in xmin..xmax {
-                                let red = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
-                                let green = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
-                                let blue = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
-                                let a = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
-                                let value = (a << 24) | (red << 16) | (green << 8) | blue;
-                                let i = y*width + x;
-                                data[i] = value as f64;
-                            }
+                    }
+                }
+            } else if bits_per_sample[0] == 16 {
+                // the spec doesn't talk about 16-bit RGB images so
+                // I'm not sure why I bother with this. They specifically
+                // say that RGB images are 8-bits per channel. Anyhow,
+                // I rescale the 16-bits to an 8-bit channel for simplicity.
+                for y in ymin..ymax {
+                    for x in xmin..xmax {
+                        let red = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
+                        let green = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
+                        let blue = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
+                        let a = (bor.read_u16() as f64 / 65535f64 * 255f64) as u32;
+                        let value = (a << 24) | (red << 16) | (green << 8) | blue;
+                        if let Some(k) = window.get_index(0, y, x) {
+                            data.set_value(k, value as f64);
                         }
-                    } else {
-                        return Err(Error::new(ErrorKind::InvalidData, "The raster was not read correctly"));
                     }
-                },
-                _ => return Err(Error::new(ErrorKind::InvalidData, "The raster was not read correctly")),
+                }
+            } else {
+                return Err(RasterError::new(RasterErrorKind::Unsupported, "The raster was not read correctly"));
             }
-        }
+        },
+        _ => return Err(RasterError::new(RasterErrorKind::Unsupported, "The raster was not read correctly")),
     }
 
-    // match geokeys_map.get(&1024) {
-    //     Some(ifd) => geokeys.add_key_directory(&ifd.data),
-    //     _ => return Err(Error::new(ErrorKind::InvalidData, "The TIFF file does not contain geokeys")),
-    // };
+    Ok(())
+}
 
-    let mut map_sorter = vec![];
-    for (key, _) in ifd_map.iter() {
-        map_sorter.push(key);
-        //println!("{}", ifd);
-    }
-    map_sorter.sort();
-    for key in map_sorter.iter() {
-        println!("{}", ifd_map.get(&key).unwrap());
+/// Reads a number of bytes from a position in a file.
+fn read_bytes(f: &mut File, position: u64, n: usize) -> Result<Vec<u8>, RasterError> {
+    let file_size = f.metadata()?.len();
+    if position.saturating_add(n as u64) > file_size {
+        return Err(RasterError::new(RasterErrorKind::InvalidData, "The TIFF file is truncated; part of it lies beyond the end of the file.").at_offset(file_size));
     }
+    f.seek(SeekFrom::Start(position))?;
+    let mut buffer = vec![0u8; n];
+    f.read_exact(&mut buffer).map_err(|e| RasterError::from(e).at_offset(position))?;
+    Ok(buffer)
+}
 
-    map_sorter.clear();
-    for (key, _) in geokeys_map.iter() {
-        map_sorter.push(key);
-        //println!("{}", ifd);
-    }
-    for key in map_sorter.iter() {
-        println!("{}", geokeys_map.get(&key).unwrap());
+/// Returns the first value of an unsigned integer tag, e.g. a SHORT or LONG, or None if the
+/// IFD does not contain the tag.
+fn get_tag_value(ifd_map: &HashMap<u16, IfdDirectory>, tag: u16) -> Result<Option<u64>, RasterError> {
+    match ifd_map.get(&tag) {
+        Some(ifd) => match ifd.interpret_as_u64().first() {
+            Some(v) => Ok(Some(*v)),
+            None => Err(RasterError::new(RasterErrorKind::InvalidData, format!("The TIFF tag {} has no values.", tag))),
+        },
+        None => Ok(None),
     }
-
-
-    // println!("\nGeoKeys:\n");
-    // println!("{}", geokeys.interpret_geokeys());
-
-    Ok(())
 }
 
-pub fn write_geotiff<'a>(r: &'a mut Raster) -> Result<(), Error> {
-    let f = File::create(r.file_name.clone())?;
-    let mut writer = BufWriter::new(f);
+/// The compression method of GeoTIFF output.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GeoTiffCompression {
+    None,
+    PackBits,
+    Lzw,
+    Deflate,
+}
 
-    let mut u16_bytes: [u8; 2];
-    let mut u32_bytes: [u8; 4];
-    let mut u64_bytes: [u8; 8];
+/// The predictor applied to the samples of GeoTIFF output before they are compressed.
+/// Horizontal differencing suits integer data and the floating-point predictor suits
+/// floating-point data; either generally improves the compression of elevation models.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GeoTiffPredictor {
+    None,
+    Horizontal,
+    FloatingPoint,
+}
 
-    // This will write a geotiff file in LittleEndian byte order regardless of the native endianness
-    writer.write("II".as_bytes())?;
+/// Options controlling the layout of GeoTIFF output, held by `RasterConfigs`. By default
+/// the image is written uncompressed, in strips. Setting a tile width and height (both
+/// multiples of 16) writes the image in tiles instead. A BigTIFF file is written if
+/// `big_tiff` is set or if the image may be too large for the 32-bit offsets of a classic
+/// TIFF file. Setting `cloud_optimized` writes a Cloud-Optimized GeoTIFF (COG): a tiled
+/// file (of 512 x 512 tiles unless a tile size is set) with internal overviews, laid out so
+/// that a client can read any part of the image at any resolution using a few range
+/// requests. The bands of a multi-band raster are interleaved by pixel (chunky) unless
+/// `planar` is set. When a GeoTIFF is read, the options are set to those of the file.
+///
+/// Example:
+///
+/// ```ignore
+/// output.configs.geotiff_options = GeoTiffWriteOptions {
+///     compression: GeoTiffCompression::Deflate,
+///     predictor: GeoTiffPredictor::FloatingPoint,
+///     tile_width: 256,
+///     tile_height: 256,
+///     big_tiff: false,
+///     cloud_optimized: false,
+///     planar: false,
+/// };
+/// output.write()?;
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GeoTiffWriteOptions {
+    pub compression: GeoTiffCompression,
+    pub predictor: GeoTiffPredictor,
+    pub tile_width: usize,
+    pub tile_height: usize,
+    pub big_tiff: bool,
+    pub cloud_optimized: bool,
+    pub planar: bool,
+}
 
-    u16_bytes = unsafe { mem::transmute(42u16.to_le()) };
-    writer.write(&u16_bytes)?;
+impl Default for GeoTiffWriteOptions {
+    fn default() -> GeoTiffWriteOptions {
+        GeoTiffWriteOptions {
+            compression: GeoTiffCompression::None,
+            predictor: GeoTiffPredictor::None,
+            tile_width: 0,
+            tile_height: 0,
+            big_tiff: false,
+            cloud_optimized: false,
+            planar: false,
+        }
+    }
+}
 
-    // output the offset to the IFD
-	let total_bytes_per_pixel = r.configs.data_type.get_data_size();
-    if total_bytes_per_pixel == 0 {
-        return Err(Error::new(ErrorKind::InvalidData, "Unknown data type."));
+impl GeoTiffWriteOptions {
+    pub fn is_tiled(&self) -> bool {
+        self.tile_width > 0 && self.tile_height > 0
     }
+}
 
-    let image_len = (r.configs.rows as usize * r.configs.columns as usize * total_bytes_per_pixel + 8usize) as u32;
-    u32_bytes = unsafe { mem::transmute(image_len.to_le()) };
-    writer.write(&u32_bytes)?;
+pub fn write_geotiff(r: &mut Raster) -> Result<(), RasterError> {
+    let mut options = r.configs.geotiff_options;
+    if options.cloud_optimized && !options.is_tiled() {
+        options.tile_width = 512;
+        options.tile_height = 512;
+    }
+    let columns = r.configs.columns;
+    let rows = r.configs.rows;
 
-    // output the data; compression is not currently supported for output
-    match r.configs.photometric_interp {
+    let (bits_per_sample, sample_format) = match r.configs.photometric_interp {
         PhotometricInterpretation::Continuous |
         PhotometricInterpretation::Categorical |
         PhotometricInterpretation::Boolean => {
-            match r.configs.data_type {
-                DataType::F64 => {
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            u64_bytes = unsafe { mem::transmute(r.data[i]) };
-                            writer.write(&u64_bytes)?;
-                        }
-                    }
-                },
-                DataType::F32 => {
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            u32_bytes = unsafe { mem::transmute(r.data[i] as f32) };
-                            writer.write(&u32_bytes)?;
-                        }
-                    }
-                },
-                DataType::U64 => {
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            u64_bytes = unsafe { mem::transmute(r.data[i] as u64) };
-                            writer.write(&u64_bytes)?;
-                        }
-                    }
-                },
-                DataType::U32 => {
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            u32_bytes = unsafe { mem::transmute(r.data[i] as u32) };
-                            writer.write(&u32_bytes)?;
-                        }
-                    }
-                },
-                DataType::U16 => {
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            u16_bytes = unsafe { mem::transmute(r.data[i] as u16) };
-                            writer.write(&u16_bytes)?;
-                        }
-                    }
-                },
-                DataType::U8 => {
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            writer.write(&[r.data[i] as u8])?;
-                        }
-                    }
-                },
-                DataType::I64 => {
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            u64_bytes = unsafe { mem::transmute(r.data[i] as i64) };
-                            writer.write(&u64_bytes)?;
-                        }
-                    }
-                },
-                DataType::I32 => {
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            u32_bytes = unsafe { mem::transmute(r.data[i] as i32) };
-                            writer.write(&u32_bytes)?;
-                        }
-                    }
-                },
-                DataType::I16 => {
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            u16_bytes = unsafe { mem::transmute(r.data[i] as i16) };
-                            writer.write(&u16_bytes)?;
-                        }
-                    }
-                },
-                DataType::I8 => {
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            writer.write(&[r.data[i] as u8])?;
-                        }
-                    }
-                },
-                _ => { return Err(Error::new(ErrorKind::InvalidData, "Unknown data type.")); },
-            }
+            // each band is a sample of the pixel
+            let (bits, format) = match r.configs.data_type {
+                DataType::U8 => (8u16, 1u16),
+                DataType::U16 => (16u16, 1u16),
+                DataType::U32 => (32u16, 1u16),
+                DataType::U64 => (64u16, 1u16),
+                DataType::I8 => (8u16, 2u16),
+                DataType::I16 => (16u16, 2u16),
+                DataType::I32 => (32u16, 2u16),
+                DataType::I64 => (64u16, 2u16),
+                DataType::F32 => (32u16, 3u16),
+                DataType::F64 => (64u16, 3u16),
+                _ => return Err(RasterError::new(RasterErrorKind::Unsupported, format!("GeoTIFFs cannot hold the {:?} data type.", r.configs.data_type))),
+            };
+            (vec![bits; r.num_bands()], format)
         },
         PhotometricInterpretation::RGB => {
             match r.configs.data_type {
-                DataType::RGB24 => {
-                    let mut bytes: [u8; 3] = [0u8; 3];
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            let val = r.data[i] as u32;
-                            bytes[0] = ((val >> 16u32) & 0xFF) as u8; // red
-                            bytes[1] = ((val >> 8u32) & 0xFF) as u8; // green
-                            bytes[2] = (val & 0xFF) as u8; // blue
-                            writer.write(&bytes)?;
-                        }
-                    }
-                },
-                DataType::RGBA32 => {
-                    let mut bytes: [u8; 4] = [0u8; 4];
-                    let mut i: usize;
-                    for row in (0..r.configs.rows).rev() {
-                        for col in 0..r.configs.columns {
-                            i = row * r.configs.columns + col;
-                            let val = r.data[i] as u32;
-                            bytes[3] = ((val >> 24u32) & 0xFF) as u8; // alpha
-                            bytes[0] = ((val >> 16u32) & 0xFF) as u8; // red
-                            bytes[1] = ((val >> 8u32) & 0xFF) as u8; // green
-                            bytes[2] = (val & 0xFF) as u8; // blue
-                            writer.write(&bytes)?;
-                        }
-                    }
-                },
-                _ => { return Err(Error::new(ErrorKind::InvalidData, "Unknown data type.")); },
+                DataType::RGB24 => (vec![8u16; 3], 1u16),
+                DataType::RGBA32 => (vec![8u16; 4], 1u16),
+                _ => return Err(RasterError::new(RasterErrorKind::Unsupported, format!("GeoTIFFs cannot hold the {:?} data type.", r.configs.data_type))),
             }
         },
         PhotometricInterpretation::Paletted => {
-            return Err(Error::new(ErrorKind::InvalidData, "Paletted GeoTIFFs are currently unsupported for writing."));
+            return Err(RasterError::new(RasterErrorKind::Unsupported, "Paletted GeoTIFFs are currently unsupported for writing."));
         },
         PhotometricInterpretation::Unknown => {
-            return Err(Error::new(ErrorKind::InvalidData, "Error while writing GeoTIFF file."));
+            return Err(RasterError::new(RasterErrorKind::InvalidInput, "Error while writing GeoTIFF file; the photometric interpretation is unknown."));
         },
+    };
+    let samples_per_pixel = bits_per_sample.len();
+    let bytes_per_sample = bits_per_sample[0] as usize / 8;
+    // The bands of a colour image are packed into a single band of the raster.
+    let num_bands = if r.configs.photometric_interp == PhotometricInterpretation::RGB { 1 } else { samples_per_pixel };
+    // Planar images hold each band in its own set of blocks, of one sample per pixel.
+    let planar = options.planar && num_bands > 1;
+    let num_planes = if planar { num_bands } else { 1 };
+    let samples_per_block = if planar { 1 } else { samples_per_pixel };
+    let bytes_per_pixel = samples_per_block * bytes_per_sample;
+
+    // The predictor only applies to compressed output, and must suit the data type.
+    let predictor = if options.compression == GeoTiffCompression::None { GeoTiffPredictor::None } else { options.predictor };
+    if predictor == GeoTiffPredictor::FloatingPoint && sample_format != 3 {
+        return Err(RasterError::new(RasterErrorKind::InvalidInput, "The floating-point predictor can only be used with floating-point data."));
+    }
+    if options.is_tiled() && (!options.tile_width.is_multiple_of(16) || !options.tile_height.is_multiple_of(16)) {
+        return Err(RasterError::new(RasterErrorKind::InvalidInput, "The GeoTIFF tile width and height must be multiples of 16."));
     }
+    let compression = match options.compression {
+        GeoTiffCompression::None => COMPRESS_NONE,
+        GeoTiffCompression::PackBits => COMPRESS_PACKBITS,
+        GeoTiffCompression::Lzw => COMPRESS_LZW,
+        GeoTiffCompression::Deflate => COMPRESS_DEFLATE,
+    };
 
-    // create the ifd's
-    let mut ifd: Vec<IfdDirectory> = vec![];
+    // The image is written at full resolution and, in a COG, as overviews, each of half the
+    // resolution of the last, down to the size of a single tile.
+    let r: &Raster = r;
+    let mut overviews: Vec<(usize, usize, RasterData)> = vec![];
+    if options.cloud_optimized {
+        let (mut c, mut rr) = (columns, rows);
+        while c > options.tile_width || rr > options.tile_height {
+            let overview = {
+                let level = match overviews.last() {
+                    Some(&(oc, orr, ref data)) => ImageLevel { columns: oc, rows: orr, bands: num_bands, data },
+                    None => ImageLevel { columns, rows, bands: num_bands, data: &r.data },
+                };
+                create_overview(&level, &r.configs)
+            };
+            c = overview.0;
+            rr = overview.1;
+            overviews.push(overview);
+        }
+    }
+    let mut levels = vec![ImageLevel { columns, rows, bands: num_bands, data: &r.data }];
+    for &(c, rr, ref data) in &overviews {
+        levels.push(ImageLevel { columns: c, rows: rr, bands: num_bands, data });
+    }
 
-    u32_bytes = unsafe { mem::transmute((r.configs.columns as u32).to_le()) };
-    ifd.push(IfdDirectory::new( TAG_IMAGEWIDTH, DT_SHORT, 1u32, r.configs.columns as u32,
-        u32_bytes.iter().cloned().collect(), Endianness::LittleEndian ));
+    // Divide each level into blocks, either tiles or strips of about 64 KB, as
+    // (block width, block height, blocks across, blocks down).
+    let layouts: Vec<(usize, usize, usize, usize)> = levels.iter().map(|level| {
+        let (block_width, block_height) = if options.is_tiled() {
+            (options.tile_width, options.tile_height)
+        } else {
+            (level.columns, min(level.rows, max(1, 65_536 / max(1, level.columns * bytes_per_pixel))))
+        };
+        (block_width, block_height, level.columns.div_ceil(block_width), level.rows.div_ceil(block_height))
+    }).collect();
+
+    // The file must be written as a BigTIFF if its size may exceed 4 GB. The image data
+    // are written before the size of the compressed data is known, and so the estimate
+    // allows for LZW compression expanding incompressible data, and for the IFDs.
+    let mut data_size = 0u64;
+    let mut num_blocks = 0u64;
+    for &(block_width, block_height, blocks_across, blocks_down) in &layouts {
+        data_size += (num_planes * blocks_across * blocks_down * block_width * block_height * bytes_per_pixel) as u64;
+        num_blocks += (num_planes * blocks_across * blocks_down) as u64;
+    }
+    let estimated_size = match options.compression {
+        GeoTiffCompression::Lzw => data_size * 3 / 2,
+        _ => data_size + data_size / 64,
+    } + num_blocks * 16 + 1_048_576u64;
+    let big_tiff = options.big_tiff || estimated_size > u32::MAX as u64;
+
+    // Creates the IFD of a level, given the offsets and sizes of its blocks. The
+    // georeferencing is only described for the full-resolution image.
+    let create_ifd = |k: usize, block_offsets: &[u64], block_byte_counts: &[u64]| -> Vec<IfdDirectory> {
+        let (block_width, block_height, _, _) = layouts[k];
+        let mut ifd: Vec<IfdDirectory> = vec![];
+        if k > 0 {
+            ifd.push(ifd_long(TAG_NEWSUBFILETYPE, &[1u32])); // reduced-resolution image
+        }
+        ifd.push(ifd_long(TAG_IMAGEWIDTH, &[levels[k].columns as u32]));
+        ifd.push(ifd_long(TAG_IMAGELENGTH, &[levels[k].rows as u32]));
+        ifd.push(ifd_short(TAG_BITSPERSAMPLE, &bits_per_sample));
+        ifd.push(ifd_short(TAG_COMPRESSION, &[compression]));
+        let out_pi = match r.configs.photometric_interp {
+            PhotometricInterpretation::RGB => PI_RGB,
+            _ => PI_BLACKISZERO,
+        };
+        ifd.push(ifd_short(TAG_PHOTOMETRICINTERPRETATION, &[out_pi]));
+        if !options.is_tiled() {
+            ifd.push(ifd_offsets(TAG_STRIPOFFSETS, block_offsets, big_tiff));
+        }
+        ifd.push(ifd_short(TAG_SAMPLESPERPIXEL, &[samples_per_pixel as u16]));
+        if !options.is_tiled() {
+            ifd.push(ifd_long(TAG_ROWSPERSTRIP, &[block_height as u32]));
+            ifd.push(ifd_offsets(TAG_STRIPBYTECOUNTS, block_byte_counts, big_tiff));
+        }
+        ifd.push(ifd_short(TAG_PLANARCONFIGURATION, &[if planar { 2u16 } else { 1u16 }]));
+        if k == 0 {
+            ifd.push(ifd_ascii(TAG_SOFTWARE, "whitebox-tools"));
+        }
+        match predictor {
+            GeoTiffPredictor::None => {},
+            GeoTiffPredictor::Horizontal => ifd.push(ifd_short(TAG_PREDICTOR, &[2u16])),
+            GeoTiffPredictor::FloatingPoint => ifd.push(ifd_short(TAG_PREDICTOR, &[3u16])),
+        }
+        if options.is_tiled() {
+            ifd.push(ifd_long(TAG_TILEWIDTH, &[block_width as u32]));
+            ifd.push(ifd_long(TAG_TILELENGTH, &[block_height as u32]));
+            ifd.push(ifd_offsets(TAG_TILEOFFSETS, block_offsets, big_tiff));
+            ifd.push(ifd_offsets(TAG_TILEBYTECOUNTS, block_byte_counts, big_tiff));
+        }
+        if r.configs.photometric_interp == PhotometricInterpretation::RGB {
+            if samples_per_pixel == 4 {
+                ifd.push(ifd_short(TAG_EXTRASAMPLES, &[1u16])); // associated alpha
+            }
+        } else if samples_per_pixel > 1 {
+            ifd.push(ifd_short(TAG_EXTRASAMPLES, &vec![0u16; samples_per_pixel - 1])); // unspecified bands
+        }
+        ifd.push(ifd_short(TAG_SAMPLEFORMAT, &vec![sample_format; samples_per_pixel]));
+
+        if k == 0 {
+            // Add the ModelPixelScaleTag and ModelTiepointTag tags
+            ifd.push(ifd_double(TAG_MODELPIXELSCALETAG, &[r.configs.resolution_x, r.configs.resolution_y, 0f64]));
+            ifd.push(ifd_double(TAG_MODELTIEPOINTTAG, &[0f64, 0f64, 0f64, r.configs.west, r.configs.north, 0f64]));
+
+            // The GeoKeys describe the CRS by its EPSG code, when it is known, and whether the
+            // cells are areas or points.
+            let raster_type = if r.configs.pixel_is_area { 1u16 } else { 2u16 };
+            let geokeys = match r.configs.get_spatial_reference().to_geokey_directory() {
+                Some(mut keys) => {
+                    keys[3] += 1;
+                    for (i, v) in [1025u16, 0, 1, raster_type].iter().enumerate() {
+                        keys.insert(8 + i, *v);
+                    }
+                    keys
+                },
+                None => vec![1, 1, 0, 1, 1025, 0, 1, raster_type],
+            };
+            ifd.push(ifd_short(TAG_GEOKEYDIRECTORYTAG, &geokeys));
+        }
 
-    u32_bytes = unsafe { mem::transmute((r.configs.rows as u32).to_le()) };
-    ifd.push(IfdDirectory::new( TAG_IMAGELENGTH, DT_SHORT, 1u32, r.configs.columns as u32,
-        u32_bytes.iter().cloned().collect(), Endianness::LittleEndian ));
+        let mut band_nodata: Vec<String> = (0..r.num_bands()).map(|b| format!("{}", r.configs.get_band_nodata(b))).collect();
+        if band_nodata.iter().all(|v| *v == band_nodata[0]) {
+            band_nodata.truncate(1);
+        }
+        ifd.push(ifd_ascii(TAG_GDAL_NODATA, &band_nodata.join(" ")));
+        ifd
+    };
 
-    let bps = match r.configs.photometric_interp {
-        PhotometricInterpretation::RGB => {
-            match r.configs.data_type {
-                DataType::RGB24 => vec![8u16, 8u16, 8u16],
-                DataType::RGBA32 => vec![8u16, 8u16, 8u16, 8u16],
-                DataType::RGB48 => vec![16u16, 16u16, 16u16],
-                _ => { return Err(Error::new(ErrorKind::InvalidData, "Unknown data type.")); }
-            }
-        },
-        _ => {
-            match r.configs.data_type {
-                DataType::I8 | DataType::U8 => vec![8u16],
-                DataType::I16 | DataType::U16 => vec![16u16],
-                DataType::I32 | DataType::U32 | DataType::F32 => vec![32u16],
-                DataType::I64 | DataType::U64 | DataType::F64 => vec![64u16],
-                _ => { return Err(Error::new(ErrorKind::InvalidData, "Unknown data type.")); }
+    // Writes the blocks of a level, returning their offsets and sizes.
+    let write_blocks = |writer: &mut BufWriter<File>, k: usize, pos: &mut usize| -> Result<(Vec<u64>, Vec<u64>), Error> {
+        let (block_width, block_height, blocks_across, blocks_down) = layouts[k];
+        let num_blocks = num_planes * blocks_across * blocks_down;
+        let mut block_offsets: Vec<u64> = Vec::with_capacity(num_blocks);
+        let mut block_byte_counts: Vec<u64> = Vec::with_capacity(num_blocks);
+        let mut block: Vec<u8> = Vec::with_capacity(block_width * block_height * bytes_per_pixel);
+        for plane in 0..num_planes {
+            let bands = if planar { plane..plane + 1 } else { 0..num_bands };
+            for j in 0..blocks_down {
+                for i in 0..blocks_across {
+                    // Tiles are always complete, and are padded with nodata beyond the edges of
+                    // the image; the last strip holds only the remaining rows.
+                    let row_start = j * block_height;
+                    let row_end = if options.is_tiled() { row_start + block_height } else { min(row_start + block_height, levels[k].rows) };
+                    block.clear();
+                    for row in row_start..row_end {
+                        encode_row(&levels[k], &r.configs, bands.clone(), row, i * block_width, block_width, &mut block)?;
+                    }
+                    match predictor {
+                        GeoTiffPredictor::None => {},
+                        GeoTiffPredictor::Horizontal => apply_horizontal_predictor(&mut block, block_width, samples_per_block, bytes_per_sample, Endianness::LittleEndian)?,
+                        GeoTiffPredictor::FloatingPoint => apply_floating_point_predictor(&mut block, block_width, samples_per_block, bytes_per_sample, Endianness::LittleEndian)?,
+                    }
+                    let compressed = match options.compression {
+                        GeoTiffCompression::None => None,
+                        GeoTiffCompression::PackBits => Some(packbits_encoder(&block)),
+                        GeoTiffCompression::Lzw => Some(lzw_encoder(&block)),
+                        GeoTiffCompression::Deflate => Some(deflate_encoder(&block)?),
+                    };
+                    let bytes: &[u8] = match compressed {
+                        Some(ref c) => c,
+                        None => &block,
+                    };
+                    if !big_tiff && *pos + bytes.len() > u32::MAX as usize {
+                        return Err(Error::new(ErrorKind::InvalidInput, "The raster is too large to be written as a classic TIFF file. Please write it as a BigTIFF file."));
+                    }
+                    block_offsets.push(*pos as u64);
+                    block_byte_counts.push(bytes.len() as u64);
+                    writer.write_all(bytes)?;
+                    *pos += bytes.len();
+                }
             }
         }
+        Ok((block_offsets, block_byte_counts))
     };
-    let _byte_size = bps.len() * 2 as usize;
 
+    let f = File::create(r.file_name.clone())?;
+    let mut writer = BufWriter::new(f);
+
+    // This will write a geotiff file in LittleEndian byte order regardless of the native endianness.
+    // The offset to the first IFD is filled in once the image data are written.
+    writer.write_all("II".as_bytes())?;
+    if big_tiff {
+        writer.write_u16::<LittleEndian>(43u16)?;
+        writer.write_u16::<LittleEndian>(8u16)?; // the size of offsets
+        writer.write_u16::<LittleEndian>(0u16)?;
+        writer.write_u64::<LittleEndian>(0u64)?;
+    } else {
+        writer.write_u16::<LittleEndian>(42u16)?;
+        writer.write_u32::<LittleEndian>(0u32)?;
+    }
+    let mut pos = if big_tiff { 16usize } else { 8usize };
+
+    let first_ifd_offset: usize;
+    if options.cloud_optimized {
+        // In a COG, the IFDs precede the image data, so that a client can read them in a
+        // single request, and the overviews precede the full-resolution image, from the
+        // smallest to the largest. The size of the IFDs does not depend upon the offsets
+        // of the blocks, and so space is left for them until the offsets are known.
+        let mut ifd_offsets: Vec<usize> = vec![];
+        let mut ifd_pos = pos;
+        for k in 0..levels.len() {
+            let (_, _, blocks_across, blocks_down) = layouts[k];
+            let placeholder = vec![0u64; num_planes * blocks_across * blocks_down];
+            let mut bytes: Vec<u8> = vec![];
+            write_ifd(&mut bytes, &create_ifd(k, &placeholder, &placeholder), ifd_pos, 0, big_tiff)?;
+            ifd_offsets.push(ifd_pos);
+            ifd_pos += bytes.len();
+        }
+        writer.write_all(&vec![0u8; ifd_pos - pos])?;
+        pos = ifd_pos;
+
+        let mut blocks: Vec<(Vec<u64>, Vec<u64>)> = vec![(vec![], vec![]); levels.len()];
+        for k in (0..levels.len()).rev() {
+            blocks[k] = write_blocks(&mut writer, k, &mut pos)?;
+        }
 
-    //let raw_bytes : [u8; byte_size] = unsafe {mem::transmute(bps)};
-    // var bps = make([]uint16, g.samplesPerPixel)
-    // for i := 0; i < int(g.samplesPerPixel); i++ {
-    //     bps[i] = uint16(g.BitsPerSample[i])
-    // }
-    // ifd = append(ifd, CreateIfdEntry(tBitsPerSample, dtShort, uint32(g.samplesPerPixel), bps, g.ByteOrder))
+        for k in 0..levels.len() {
+            let next_ifd_offset = if k + 1 < levels.len() { ifd_offsets[k + 1] as u64 } else { 0u64 };
+            writer.seek(SeekFrom::Start(ifd_offsets[k] as u64))?;
+            write_ifd(&mut writer, &create_ifd(k, &blocks[k].0, &blocks[k].1), ifd_offsets[k], next_ifd_offset, big_tiff)?;
+        }
+        first_ifd_offset = ifd_offsets[0];
+    } else {
+        let (block_offsets, block_byte_counts) = write_blocks(&mut writer, 0, &mut pos)?;
+        // the IFD must begin on a word boundary
+        if pos % 2 == 1 {
+            writer.write_all(&[0u8])?;
+            pos += 1;
+        }
+        write_ifd(&mut writer, &create_ifd(0, &block_offsets, &block_byte_counts), pos, 0, big_tiff)?;
+        first_ifd_offset = pos;
+    }
 
-    // fn new(tag: u16, ifd_type: u16, num_values: u32, offset: u32, data: Vec<u8>, byte_order: RasterByteOrder)
-    ifd.push(IfdDirectory::new( TAG_COMPRESSION, DT_SHORT, 1u32, COMPRESS_NONE as u32,
-        vec![1u8], Endianness::LittleEndian ));
+    if big_tiff {
+        writer.seek(SeekFrom::Start(8))?;
+        writer.write_u64::<LittleEndian>(first_ifd_offset as u64)?;
+    } else {
+        writer.seek(SeekFrom::Start(4))?;
+        writer.write_u32::<LittleEndian>(first_ifd_offset as u32)?;
+    }
+    writer.flush()?;
 
-    let out_pi = match r.configs.photometric_interp {
-        PhotometricInterpretation::Continuous | PhotometricInterpretation::Categorical => PI_BLACKISZERO,
-        PhotometricInterpretation::RGB => PI_RGB,
-        PhotometricInterpretation::Paletted => PI_PALETTED,
-        _ => { return Err(Error::new(ErrorKind::InvalidData, "Unknown data type.")); }
-    };
-    ifd.push(IfdDirectory::new( TAG_PHOTOMETRICINTERPRETATION, DT_SHORT, 1u32, out_pi as u32,
-        vec![out_pi as u8], Endianness::LittleEndian ));
-
-    //let mut strip_offsets: Vec<u32> = vec![];
-    let total_bytes_per_pixel = match r.configs.data_type {
-        DataType::I8 | DataType::U8 => 1u32,
-        DataType::I16 | DataType::U16 => 2u32,
-        DataType::I32 | DataType::U32 | DataType::F32 => 4u32,
-        DataType::I64 | DataType::U64 | DataType::F64 => 8u32,
-        DataType::RGB24 => 3u32,
-        DataType::RGBA32 => 4u32,
-        DataType::RGB48 => 6u32,
-        _ => { return Err(Error::new(ErrorKind::InvalidData, "Unknown data type.")); }
-    };
-    let row_length_in_bytes: u32 = r.configs.columns as u32 * total_bytes_per_pixel;
-    //let strip_byte_count: Vec<u32> = vec![row_length_in_bytes; r.configs.rows as usize];
-    let mut strip_offsets_as_bytes: Vec<u8> = vec![];
-    let mut strip_byte_count_as_bytes: Vec<u8> = vec![];
-    for i in 0..r.configs.rows as u32 {
-        let val = 8u32 + row_length_in_bytes * i;
-        //strip_offsets.push(val);
-        u32_bytes = unsafe { mem::transmute(val.to_le()) };
-        strip_offsets_as_bytes.push(u32_bytes[0]);
-        strip_offsets_as_bytes.push(u32_bytes[1]);
-        strip_offsets_as_bytes.push(u32_bytes[2]);
-        strip_offsets_as_bytes.push(u32_bytes[3]);
-
-        u32_bytes = unsafe { mem::transmute(row_length_in_bytes.to_le()) };
-        strip_byte_count_as_bytes.push(u32_bytes[0]);
-        strip_byte_count_as_bytes.push(u32_bytes[1]);
-        strip_byte_count_as_bytes.push(u32_bytes[2]);
-        strip_byte_count_as_bytes.push(u32_bytes[3]);
-    }
-
-    ifd.push(IfdDirectory::new( TAG_STRIPOFFSETS, DT_LONG, r.configs.rows as u32, 0u32,
-        strip_offsets_as_bytes, Endianness::LittleEndian ));
-
-    let samples_per_pixel = match r.configs.data_type {
-        DataType::RGB24 => 3u16,
-        DataType::RGBA32 => 4u16,
-        DataType::RGB48 => 3u16,
-        _ => 1u16
-    };
-    u16_bytes = unsafe { mem::transmute(samples_per_pixel.to_le()) };
-    ifd.push(IfdDirectory::new( TAG_SAMPLESPERPIXEL, DT_SHORT, 1u32, 0u32,
-        u16_bytes.iter().cloned().collect(), Endianness::LittleEndian ));
-
-    u16_bytes = unsafe { mem::transmute(1u16.to_le()) };
-    ifd.push(IfdDirectory::new( TAG_ROWSPERSTRIP, DT_SHORT, 1u32, 1u32,
-        u16_bytes.iter().cloned().collect(), Endianness::LittleEndian ));
-
-    ifd.push(IfdDirectory::new( TAG_STRIPBYTECOUNTS, DT_LONG, r.configs.rows as u32, 0u32,
-        strip_byte_count_as_bytes, Endianness::LittleEndian ));
-
-    let software: String = String::from("whitebox-tools");
-    let software_len = software.len() as u32;
-    ifd.push(IfdDirectory::new( TAG_SOFTWARE, DT_ASCII, software_len, 0u32,
-        software.into_bytes(), Endianness::LittleEndian ));
-
-    // sf := make([]uint16, g.samplesPerPixel)
-    // for i := 0; i < int(g.samplesPerPixel); i++ {
-    //     sf[i] = uint16(g.SampleFormat)
-    // }
-    // ifd = append(ifd, CreateIfdEntry(tSampleFormat, dtShort, uint32(g.samplesPerPixel), sf, g.ByteOrder))
-    //
-    // if g.samplesPerPixel > 1 {
-    //     ifd = append(ifd, CreateIfdEntry(tPlanarConfiguration, dtShort, 1, uint16(1), g.ByteOrder))
-    // }
-    //
-    // if g.PhotometricInterp == PI_RGB && g.samplesPerPixel == 4 {
-    //     ifd = append(ifd, CreateIfdEntry(tExtraSamples, dtShort, 1, uint16(1), g.ByteOrder))
-    // }
-    //
-    // // There is currently no support for storing the image
-    // // resolution, so give a bogus value of 72x72 dpi.
-    // ifd = append(ifd, CreateIfdEntry(tXResolution, dtRational, 1, []uint32{72, 1}, g.ByteOrder))
-    // ifd = append(ifd, CreateIfdEntry(tYResolution, dtRational, 1, []uint32{72, 1}, g.ByteOrder))
-    // ifd = append(ifd, CreateIfdEntry(tResolutionUnit, dtShort, 1, []uint32{resPerInch}, g.ByteOrder))
-    //
-    // // Add the ModelPixelScaleTag and ModelTiepointTag tags
-    // ifd = append(ifd, CreateIfdEntry(tModelTiepointTag, dtDouble, 6, g.TiepointData.getModelTiepointTagData(), g.ByteOrder))
-    // ifd = append(ifd, CreateIfdEntry(tModelPixelScaleTag, dtDouble, 3, g.TiepointData.getModelPixelScaleTagData(), g.ByteOrder))
-    //
-    // if g.NodataValue != "" {
-    //     ifd = append(ifd, CreateIfdEntry(tGDAL_NODATA, dtASCII, uint32(len(g.NodataValue)), g.NodataValue, g.ByteOrder))
-    // }
-
-
-    let _ = writer.flush();
+    Ok(())
+}
+
+/// An image written to a GeoTIFF file: the full-resolution raster or an overview of it.
+/// The bands are held one after the other, as in a `Raster`.
+struct ImageLevel<'a> {
+    columns: usize,
+    rows: usize,
+    bands: usize,
+    data: &'a RasterData,
+}
+
+/// Creates an overview of half the resolution of an image. Continuous data are averaged,
+/// ignoring nodata; other data, e.g. classes or colours, are sampled from the upper-left
+/// cell of each 2 x 2 block.
+fn create_overview(level: &ImageLevel, configs: &RasterConfigs) -> (usize, usize, RasterData) {
+    let columns = level.columns.div_ceil(2);
+    let rows = level.rows.div_ceil(2);
+    let average = configs.photometric_interp == PhotometricInterpretation::Continuous;
+    let mut data = RasterData::with_capacity(level.data.data_type(), level.bands * rows * columns);
+    for band in 0..level.bands {
+        let nodata = configs.get_band_nodata(band);
+        let offset = band * level.rows * level.columns;
+        for row in 0..rows {
+            for col in 0..columns {
+                if !average {
+                    data.push(level.data.get_value(offset + 2 * row * level.columns + 2 * col));
+                    continue;
+                }
+                let mut sum = 0f64;
+                let mut n = 0f64;
+                for r in 2 * row..min(2 * row + 2, level.rows) {
+                    for c in 2 * col..min(2 * col + 2, level.columns) {
+                        let z = level.data.get_value(offset + r * level.columns + c);
+                        if z != nodata {
+                            sum += z;
+                            n += 1f64;
+                        }
+                    }
+                }
+                data.push(if n > 0f64 { sum / n } else { nodata });
+            }
+        }
+    }
+    (columns, rows, data)
+}
 
+/// Appends the little-endian samples of a range of bands, for part of a row of an image,
+/// to a buffer. Cells beyond the edges of the image are nodata.
+fn encode_row(level: &ImageLevel, configs: &RasterConfigs, bands: Range<usize>, row: usize, column_start: usize, width: usize, buf: &mut Vec<u8>) -> Result<(), Error> {
+    for (col, band) in (column_start..column_start + width).flat_map(|c| bands.clone().map(move |b| (c, b))) {
+        let value = if row < level.rows && col < level.columns {
+            level.data.get_value((band * level.rows + row) * level.columns + col)
+        } else {
+            configs.get_band_nodata(band)
+        };
+        match configs.data_type {
+            DataType::F64 => buf.write_f64::<LittleEndian>(value)?,
+            DataType::F32 => buf.write_f32::<LittleEndian>(value as f32)?,
+            DataType::U64 => buf.write_u64::<LittleEndian>(value as u64)?,
+            DataType::U32 => buf.write_u32::<LittleEndian>(value as u32)?,
+            DataType::U16 => buf.write_u16::<LittleEndian>(value as u16)?,
+            DataType::U8 => buf.push(value as u8),
+            DataType::I64 => buf.write_i64::<LittleEndian>(value as i64)?,
+            DataType::I32 => buf.write_i32::<LittleEndian>(value as i32)?,
+            DataType::I16 => buf.write_i16::<LittleEndian>(value as i16)?,
+            DataType::I8 => buf.push(value as i8 as u8),
+            DataType::RGB24 => {
+                let val = value as u32;
+                buf.push(((val >> 16u32) & 0xFF) as u8); // red
+                buf.push(((val >> 8u32) & 0xFF) as u8); // green
+                buf.push((val & 0xFF) as u8); // blue
+            },
+            DataType::RGBA32 => {
+                let val = value as u32;
+                buf.push(((val >> 16u32) & 0xFF) as u8); // red
+                buf.push(((val >> 8u32) & 0xFF) as u8); // green
+                buf.push((val & 0xFF) as u8); // blue
+                buf.push(((val >> 24u32) & 0xFF) as u8); // alpha
+            },
+            _ => return Err(Error::new(ErrorKind::InvalidData, "Unknown data type.")),
+        }
+    }
+    Ok(())
+}
+
+/// Writes an IFD, followed by those of its values that do not fit within its entries.
+/// The entries must be sorted by tag. BigTIFF IFDs have 64-bit counts and offsets.
+fn write_ifd<W: Write>(writer: &mut W, ifd: &[IfdDirectory], ifd_offset: usize, next_ifd_offset: u64, big_tiff: bool) -> Result<(), Error> {
+    let mut extra_data: Vec<u8> = vec![];
+    // the sizes of the entry count, an entry and an offset
+    let (count_size, entry_size, offset_size) = if big_tiff { (8, 20, 8) } else { (2, 12, 4) };
+    let extra_data_offset = ifd_offset + count_size + entry_size * ifd.len() + offset_size;
+    if big_tiff {
+        writer.write_u64::<LittleEndian>(ifd.len() as u64)?;
+    } else {
+        writer.write_u16::<LittleEndian>(ifd.len() as u16)?;
+    }
+    for entry in ifd {
+        writer.write_u16::<LittleEndian>(entry.tag)?;
+        writer.write_u16::<LittleEndian>(entry.ifd_type)?;
+        if big_tiff {
+            writer.write_u64::<LittleEndian>(entry.num_values as u64)?;
+        } else {
+            writer.write_u32::<LittleEndian>(entry.num_values)?;
+        }
+        if entry.data.len() <= offset_size {
+            let mut value = entry.data.clone();
+            value.resize(offset_size, 0u8);
+            writer.write_all(&value)?;
+        } else {
+            let offset = extra_data_offset + extra_data.len();
+            if big_tiff {
+                writer.write_u64::<LittleEndian>(offset as u64)?;
+            } else {
+                writer.write_u32::<LittleEndian>(offset as u32)?;
+            }
+            extra_data.extend_from_slice(&entry.data);
+            if extra_data.len() % 2 == 1 {
+                extra_data.push(0u8);
+            }
+        }
+    }
+    if big_tiff {
+        writer.write_u64::<LittleEndian>(next_ifd_offset)?;
+    } else {
+        writer.write_u32::<LittleEndian>(next_ifd_offset as u32)?;
+    }
+    writer.write_all(&extra_data)?;
     Ok(())
 }
 
+/// Creates an entry holding offsets or byte counts, which are LONG8 values in a BigTIFF
+/// file and LONG values otherwise.
+fn ifd_offsets(tag: u16, values: &[u64], big_tiff: bool) -> IfdDirectory {
+    if !big_tiff {
+        let values: Vec<u32> = values.iter().map(|v| *v as u32).collect();
+        return ifd_long(tag, &values);
+    }
+    let mut data = vec![];
+    for v in values {
+        data.write_u64::<LittleEndian>(*v).unwrap();
+    }
+    IfdDirectory::new(tag, DT_LONG8, values.len() as u32, 0u64, data, Endianness::LittleEndian)
+}
+
+fn ifd_short(tag: u16, values: &[u16]) -> IfdDirectory {
+    let mut data = vec![];
+    for v in values {
+        data.write_u16::<LittleEndian>(*v).unwrap();
+    }
+    IfdDirectory::new(tag, DT_SHORT, values.len() as u32, 0u64, data, Endianness::LittleEndian)
+}
+
+fn ifd_long(tag: u16, values: &[u32]) -> IfdDirectory {
+    let mut data = vec![];
+    for v in values {
+        data.write_u32::<LittleEndian>(*v).unwrap();
+    }
+    IfdDirectory::new(tag, DT_LONG, values.len() as u32, 0u64, data, Endianness::LittleEndian)
+}
+
+fn ifd_double(tag: u16, values: &[f64]) -> IfdDirectory {
+    let mut data = vec![];
+    for v in values {
+        data.write_f64::<LittleEndian>(*v).unwrap();
+    }
+    IfdDirectory::new(tag, DT_DOUBLE, values.len() as u32, 0u64, data, Endianness::LittleEndian)
+}
+
+fn ifd_ascii(tag: u16, value: &str) -> IfdDirectory {
+    let mut data = value.as_bytes().to_vec();
+    data.push(0u8);
+    IfdDirectory::new(tag, DT_ASCII, data.len() as u32, 0u64, data, Endianness::LittleEndian)
+}
+
 #[derive(Default, Clone, Debug)]
 pub struct IfdDirectory {
     pub tag: u16,
     pub ifd_type: u16,
     pub num_values: u32,
-    pub offset: u32,
+    pub offset: u64,
     pub data: Vec<u8>,
     byte_order: Endianness
 }
 
 impl IfdDirectory {
-    pub fn new(tag: u16, ifd_type: u16, num_values: u32, offset: u32, data: Vec<u8>, byte_order: Endianness) -> IfdDirectory {
-        IfdDirectory { tag: tag, ifd_type: ifd_type, num_values: num_values, offset: offset, data: data, byte_order: byte_order}
+    pub fn new(tag: u16, ifd_type: u16, num_values: u32, offset: u64, data: Vec<u8>, byte_order: Endianness) -> IfdDirectory {
+        IfdDirectory { tag, ifd_type, num_values, offset, data, byte_order}
     }
 
     pub fn interpret_as_u16(&self) -> Vec<u16> {
         let mut bor = ByteOrderReader::new(self.data.clone(), self.byte_order);
         let mut vals: Vec<u16> = vec![];
-        for _ in 0..self.num_values {
+        for _ in 0..min(self.num_values as usize, self.data.len() / 2) {
             let val = bor.read_u16();
             vals.push(val);
         }
@@ -1004,17 +1304,35 @@ impl IfdDirectory {
     pub fn interpret_as_u32(&self) -> Vec<u32> {
         let mut bor = ByteOrderReader::new(self.data.clone(), self.byte_order);
         let mut vals: Vec<u32> = vec![];
-        for _ in 0..self.num_values {
+        for _ in 0..min(self.num_values as usize, self.data.len() / 4) {
             let val = bor.read_u32();
             vals.push(val);
         }
         vals
     }
 
+    /// Interprets unsigned integer values of any size, e.g. offsets, which are LONG8
+    /// values in BigTIFF files and SHORT or LONG values otherwise.
+    pub fn interpret_as_u64(&self) -> Vec<u64> {
+        let mut bor = ByteOrderReader::new(self.data.clone(), self.byte_order);
+        let mut vals: Vec<u64> = vec![];
+        let value_size = match self.ifd_type { 1 => 1, 3 => 2, 16 => 8, _ => 4 };
+        for _ in 0..min(self.num_values as usize, self.data.len() / value_size) {
+            let val = match self.ifd_type {
+                1 => bor.read_u8() as u64,
+                3 => bor.read_u16() as u64,
+                16 => bor.read_u64(),
+                _ => bor.read_u32() as u64,
+            };
+            vals.push(val);
+        }
+        vals
+    }
+
     pub fn interpret_as_f64(&self) -> Vec<f64> {
         let mut bor = ByteOrderReader::new(self.data.clone(), self.byte_order);
         let mut vals: Vec<f64> = vec![];
-        for _ in 0..self.num_values {
+        for _ in 0..min(self.num_values as usize, self.data.len() / 8) {
             let val = bor.read_f64();
             vals.push(val);
         }
@@ -1022,54 +1340,50 @@ impl IfdDirectory {
     }
 
     pub fn interpret_as_ascii(&self) -> String {
-        if self.data[self.data.len()-1] == 0 {
-            let s = &self.data[0..self.data.len()-1];
-            return String::from_utf8(s.to_vec()).unwrap();
-        } else {
-            return String::from_utf8(self.data.clone()).unwrap();
-        }
-
+        String::from_utf8_lossy(&self.data).trim_matches(char::from(0)).to_string()
     }
 
     pub fn interpret_data(&self) -> String {
         let mut bor = ByteOrderReader::new(self.data.clone(), self.byte_order);
         if self.ifd_type == 2 { // ascii
-            return String::from_utf8(self.data.clone()).unwrap();
+            String::from_utf8_lossy(&self.data).to_string()
         } else if self.ifd_type == 3 { // u16
             let mut vals: Vec<u16> = vec![];
-            for _ in 0..self.num_values {
+            for _ in 0..min(self.num_values as usize, self.data.len() / 2) {
                 let val = bor.read_u16();
                 vals.push(val);
             }
-            if self.num_values == 1 {
+            if vals.len() == 1 {
                 let kw_map = get_keyword_map();
                 let map = match kw_map.get(&self.tag) {
                     Some(map) => map,
                     None => return format!("{:?}", vals),
                 };
                 match map.get(&vals[0]) {
-                    Some(v) => return format!("{:?} ({})", v, vals[0]),
-                    None => return format!("{:?}", vals),
+                    Some(v) => format!("{:?} ({})", v, vals[0]),
+                    None => format!("{:?}", vals),
                 }
             } else {
-                return format!("{:?}", vals);
+                format!("{:?}", vals)
             }
         } else if self.ifd_type == 4 { // u32
             let mut vals: Vec<u32> = vec![];
-            for _ in 0..self.num_values {
+            for _ in 0..min(self.num_values as usize, self.data.len() / 4) {
                 let val = bor.read_u32();
                 vals.push(val);
             }
-            return format!("{:?}", vals);
+            format!("{:?}", vals)
+        } else if self.ifd_type == 16 { // u64
+            format!("{:?}", self.interpret_as_u64())
         } else if self.ifd_type == 12 { // f64
             let mut vals: Vec<f64> = vec![];
-            for _ in 0..self.num_values {
+            for _ in 0..min(self.num_values as usize, self.data.len() / 8) {
                 let val = bor.read_f64();
                 vals.push(val);
             }
-            return format!("{:?}", vals);
+            format!("{:?}", vals)
         } else {
-            return format!("{:?}", self.data);
+            format!("{:?}", self.data)
         }
     }
 }
@@ -1122,6 +1436,9 @@ const DT_SLONG: u16     = 9;
 const DT_SRATIONAL: u16 = 10;
 const DT_FLOAT: u16     = 11;
 const DT_DOUBLE: u16    = 12;
+const DT_LONG8: u16     = 16; // BigTIFF
+const DT_SLONG8: u16    = 17; // BigTIFF
+const DT_IFD8: u16      = 18; // BigTIFF
 
 const PI_WHITEISZERO: u16 = 0;
 const PI_BLACKISZERO: u16 = 1;
@@ -1246,3 +1563,433 @@ pub fn packbits_decoder(input_data: Vec<u8>) -> Vec<u8> {
     }
     output_data
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::prelude::*;
+    use crate::io_utils::temp_file_name;
+    use super::*;
+
+    /// Creates a raster whose cells are distinct whole numbers, with a nodata cell.
+    fn test_raster(file_name: &str, rows: usize, columns: usize, data_type: DataType, options: GeoTiffWriteOptions) -> Raster {
+        let mut configs = RasterConfigs::default();
+        configs.rows = rows;
+        configs.columns = columns;
+        configs.north = 1000.0 + rows as f64;
+        configs.south = 1000.0;
+        configs.east = 500.0 + columns as f64;
+        configs.west = 500.0;
+        configs.resolution_x = 1.0;
+        configs.resolution_y = 1.0;
+        configs.nodata = -9999.0;
+        configs.data_type = data_type;
+        configs.photometric_interp = PhotometricInterpretation::Continuous;
+        configs.geotiff_options = options;
+        let mut r = Raster::initialize_using_config(file_name, &configs);
+        for row in 0..rows as isize {
+            for col in 0..columns as isize {
+                r.set_value(row, col, (row * 7 + col * 3 - 40) as f64);
+            }
+        }
+        r.set_value(1, 1, -9999.0);
+        r
+    }
+
+    fn assert_same_cells(a: &Raster, b: &Raster) {
+        assert_eq!((a.configs.rows, a.configs.columns), (b.configs.rows, b.configs.columns));
+        for row in 0..a.configs.rows as isize {
+            for col in 0..a.configs.columns as isize {
+                assert_eq!(a.get_value(row, col), b.get_value(row, col), "cell ({}, {})", row, col);
+            }
+        }
+    }
+
+    fn round_trip(name: &str, data_type: DataType, options: GeoTiffWriteOptions) -> (Raster, Raster) {
+        let file_name = temp_file_name(name);
+        let mut r = test_raster(&file_name, 37, 45, data_type, options);
+        r.write().unwrap();
+        let input = Raster::new(&file_name, "r").unwrap();
+        fs::remove_file(&file_name).unwrap();
+        assert_same_cells(&r, &input);
+        assert_eq!(input.configs.data_type, data_type);
+        (r, input)
+    }
+
+    #[test]
+    fn compressed_round_trip() {
+        let compressions = [GeoTiffCompression::PackBits, GeoTiffCompression::Lzw, GeoTiffCompression::Deflate];
+        for &compression in &compressions {
+            for &(data_type, predictor) in &[(DataType::I16, GeoTiffPredictor::None), (DataType::I16, GeoTiffPredictor::Horizontal),
+                (DataType::I32, GeoTiffPredictor::Horizontal), (DataType::F32, GeoTiffPredictor::FloatingPoint),
+                (DataType::F64, GeoTiffPredictor::FloatingPoint), (DataType::F64, GeoTiffPredictor::Horizontal)] {
+                let mut options = GeoTiffWriteOptions::default();
+                options.compression = compression;
+                options.predictor = predictor;
+                let (_, input) = round_trip("compressed.tif", data_type, options);
+                assert_eq!(input.configs.geotiff_options.compression, compression);
+                if compression != GeoTiffCompression::PackBits {
+                    assert_eq!(input.configs.geotiff_options.predictor, predictor);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compressed_tiles_round_trip() {
+        let mut options = GeoTiffWriteOptions::default();
+        options.compression = GeoTiffCompression::Lzw;
+        options.predictor = GeoTiffPredictor::Horizontal;
+        options.tile_width = 32;
+        options.tile_height = 16;
+        let file_name = temp_file_name("tiled.tif");
+        let mut r = test_raster(&file_name, 32, 64, DataType::I16, options);
+        r.write().unwrap();
+        let input = Raster::new(&file_name, "r").unwrap();
+        fs::remove_file(&file_name).unwrap();
+        assert_same_cells(&r, &input);
+        assert_eq!((input.configs.geotiff_options.tile_width, input.configs.geotiff_options.tile_height), (32, 16));
+    }
+
+    #[test]
+    fn compressed_tiles_and_planes() {
+        let file_name = temp_file_name("planar.tif");
+        let mut options = GeoTiffWriteOptions::default();
+        options.compression = GeoTiffCompression::Lzw;
+        options.predictor = GeoTiffPredictor::Horizontal;
+        options.tile_width = 32;
+        options.tile_height = 16;
+        options.planar = true;
+        let mut configs = test_raster(&file_name, 40, 50, DataType::U16, options).configs;
+        configs.bands = 3;
+        configs.nodata = 0.0;
+        let mut r = Raster::initialize_using_config(&file_name, &configs);
+        for band in 0..3 {
+            for row in 0..40isize {
+                for col in 0..50isize {
+                    r.set_band_value(band, row, col, (band as isize * 1000 + row * 50 + col) as f64);
+                }
+            }
+        }
+        r.write().unwrap();
+        let input = Raster::new(&file_name, "r").unwrap();
+        fs::remove_file(&file_name).unwrap();
+
+        assert_eq!(input.configs.bands, 3);
+        assert!(input.configs.geotiff_options.planar);
+        assert_eq!((input.configs.geotiff_options.tile_width, input.configs.geotiff_options.tile_height), (32, 16));
+        for band in 0..3 {
+            for row in 0..40isize {
+                for col in 0..50isize {
+                    assert_eq!(input.get_band_value(band, row, col), r.get_band_value(band, row, col));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_write_options_are_errors() {
+        let file_name = temp_file_name("invalid_options.tif");
+        let mut options = GeoTiffWriteOptions::default();
+        options.compression = GeoTiffCompression::Deflate;
+        options.predictor = GeoTiffPredictor::FloatingPoint;
+        let mut r = test_raster(&file_name, 10, 10, DataType::I32, options);
+        assert_eq!(write_geotiff(&mut r).unwrap_err().kind, RasterErrorKind::InvalidInput);
+
+        let mut options = GeoTiffWriteOptions::default();
+        options.tile_width = 20;
+        options.tile_height = 16;
+        let mut r = test_raster(&file_name, 10, 10, DataType::I32, options);
+        assert_eq!(write_geotiff(&mut r).unwrap_err().kind, RasterErrorKind::InvalidInput);
+        let _ = fs::remove_file(&file_name);
+    }
+
+    #[test]
+    fn packbits_round_trip() {
+        let mut data: Vec<u8> = (0..1000).map(|i| (i % 3) as u8).collect();
+        data.extend(vec![9u8; 300]);
+        data.extend(vec![1u8, 2, 2, 3]);
+        let encoded = packbits_encoder(&data);
+        assert!(encoded.len() < data.len());
+        assert_eq!(packbits_decoder(encoded), data);
+    }
+
+    /// Builds a little-endian BigTIFF of a single-band image held in one strip, which
+    /// follows the 16-byte header; the values of each tag must fit within its 8-byte entry.
+    fn big_tiff_with_tags(tags: &[(u16, u16, Vec<u32>)], strip: &[u8]) -> Vec<u8> {
+        let mut tags = tags.to_vec();
+        tags.sort_by_key(|t| t.0);
+        let mut tiff = b"II".to_vec();
+        tiff.write_u16::<LittleEndian>(43).unwrap();
+        tiff.write_u16::<LittleEndian>(8).unwrap();
+        tiff.write_u16::<LittleEndian>(0).unwrap();
+        tiff.write_u64::<LittleEndian>(16 + strip.len() as u64).unwrap();
+        tiff.extend_from_slice(strip);
+        tiff.write_u64::<LittleEndian>(tags.len() as u64).unwrap();
+        for &(tag, field_type, ref values) in tags.iter() {
+            let mut bytes = vec![];
+            for v in values {
+                match field_type {
+                    3 => bytes.write_u16::<LittleEndian>(*v as u16).unwrap(),
+                    4 => bytes.write_u32::<LittleEndian>(*v).unwrap(),
+                    _ => bytes.write_u64::<LittleEndian>(*v as u64).unwrap(),
+                }
+            }
+            assert!(bytes.len() <= 8);
+            bytes.resize(8, 0);
+            tiff.write_u16::<LittleEndian>(tag).unwrap();
+            tiff.write_u16::<LittleEndian>(field_type).unwrap();
+            tiff.write_u64::<LittleEndian>(values.len() as u64).unwrap();
+            tiff.extend_from_slice(&bytes);
+        }
+        tiff.write_u64::<LittleEndian>(0).unwrap();
+        tiff
+    }
+
+    /// Builds a little-endian TIFF of a single-band image held in one strip, which
+    /// follows the 8-byte header, with the given SHORT (3) or LONG (4) tags.
+    fn tiff_with_tags(tags: &[(u16, u16, Vec<u32>)], strip: &[u8]) -> Vec<u8> {
+        let mut tags = tags.to_vec();
+        tags.sort_by_key(|t| t.0);
+        let ifd_offset = 8 + strip.len();
+        let mut extra_offset = ifd_offset + 2 + tags.len() * 12 + 4;
+        let mut ifd = vec![];
+        let mut extra = vec![];
+        ifd.write_u16::<LittleEndian>(tags.len() as u16).unwrap();
+        for &(tag, field_type, ref values) in tags.iter() {
+            let mut bytes = vec![];
+            for v in values {
+                if field_type == 3 {
+                    bytes.write_u16::<LittleEndian>(*v as u16).unwrap();
+                } else {
+                    bytes.write_u32::<LittleEndian>(*v).unwrap();
+                }
+            }
+            ifd.write_u16::<LittleEndian>(tag).unwrap();
+            ifd.write_u16::<LittleEndian>(field_type).unwrap();
+            ifd.write_u32::<LittleEndian>(values.len() as u32).unwrap();
+            if bytes.len() <= 4 {
+                bytes.resize(4, 0);
+                ifd.extend_from_slice(&bytes);
+            } else {
+                ifd.write_u32::<LittleEndian>(extra_offset as u32).unwrap();
+                extra_offset += bytes.len();
+                extra.extend_from_slice(&bytes);
+            }
+        }
+        ifd.write_u32::<LittleEndian>(0).unwrap();
+
+        let mut tiff = b"II*\0".to_vec();
+        tiff.write_u32::<LittleEndian>(ifd_offset as u32).unwrap();
+        tiff.extend_from_slice(strip);
+        tiff.extend_from_slice(&ifd);
+        tiff.extend_from_slice(&extra);
+        tiff
+    }
+
+    /// The tags of a 2 x 2 image of 8-bit unsigned integers, in a 4-byte strip.
+    fn grey_tags() -> Vec<(u16, u16, Vec<u32>)> {
+        vec![
+            (256, 3, vec![2]), // ImageWidth
+            (257, 3, vec![2]), // ImageLength
+            (258, 3, vec![8]), // BitsPerSample
+            (259, 3, vec![1]), // Compression
+            (262, 3, vec![1]), // PhotometricInterpretation
+            (273, 4, vec![8]), // StripOffsets
+            (278, 3, vec![2]), // RowsPerStrip
+            (279, 4, vec![4]), // StripByteCounts
+            (34735, 3, vec![1, 1, 0, 0]), // GeoKeyDirectory
+        ]
+    }
+
+    fn with_tag(tags: &[(u16, u16, Vec<u32>)], tag: u16, field_type: u16, values: Vec<u32>) -> Vec<(u16, u16, Vec<u32>)> {
+        let mut tags: Vec<(u16, u16, Vec<u32>)> = tags.iter().filter(|t| t.0 != tag).cloned().collect();
+        tags.push((tag, field_type, values));
+        tags
+    }
+
+    fn read_tiff(name: &str, tiff: &[u8]) -> Result<Raster, RasterError> {
+        let file_name = temp_file_name(name);
+        fs::File::create(&file_name).unwrap().write_all(tiff).unwrap();
+        let result = Raster::new(&file_name, "r");
+        fs::remove_file(&file_name).unwrap();
+        result
+    }
+
+    #[test]
+    fn read_minimal_big_tiff() {
+        // the strip offset and byte count as LONG8 values
+        let tags = with_tag(&with_tag(&grey_tags(), 273, 16, vec![16]), 279, 16, vec![4]);
+        let r = read_tiff("minimal_big.tif", &big_tiff_with_tags(&tags, &[1, 2, 3, 4])).unwrap();
+        assert!(r.configs.geotiff_options.big_tiff);
+        assert_eq!((r.get_value(0, 0), r.get_value(0, 1), r.get_value(1, 0), r.get_value(1, 1)), (1.0, 2.0, 3.0, 4.0));
+
+        let mut tiff = big_tiff_with_tags(&tags, &[1, 2, 3, 4]);
+        tiff[4] = 4;
+        let err = read_tiff_error("big_offset_size.tif", &tiff);
+        assert_eq!(err.kind, RasterErrorKind::InvalidData);
+        assert_eq!(err.offset, Some(4));
+    }
+
+    #[test]
+    fn big_tiff_round_trip() {
+        let mut options = GeoTiffWriteOptions::default();
+        options.big_tiff = true;
+        let (_, input) = round_trip("big.tif", DataType::F64, options);
+        assert!(input.configs.geotiff_options.big_tiff);
+
+        let file_name = temp_file_name("big_tiled.tif");
+        let mut options = GeoTiffWriteOptions::default();
+        options.big_tiff = true;
+        options.tile_width = 16;
+        options.tile_height = 16;
+        options.compression = GeoTiffCompression::Lzw;
+        let mut r = test_raster(&file_name, 48, 32, DataType::I32, options);
+        r.write().unwrap();
+        let mut header = [0u8; 16];
+        fs::File::open(&file_name).unwrap().read_exact(&mut header).unwrap();
+        assert_eq!(&header[0..8], &[b'I', b'I', 43, 0, 8, 0, 0, 0]);
+        let input = Raster::new(&file_name, "r").unwrap();
+        fs::remove_file(&file_name).unwrap();
+        assert_same_cells(&r, &input);
+        assert!(input.configs.geotiff_options.big_tiff);
+
+        // a classic TIFF is written unless a BigTIFF is needed or asked for
+        let (_, input) = round_trip("classic.tif", DataType::F64, GeoTiffWriteOptions::default());
+        assert!(!input.configs.geotiff_options.big_tiff);
+    }
+
+    fn read_tiff_error(name: &str, tiff: &[u8]) -> RasterError {
+        match read_tiff(name, tiff) {
+            Ok(_) => panic!("{} was read without error", name),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn read_minimal_tiff() {
+        let r = read_tiff("minimal.tif", &tiff_with_tags(&grey_tags(), &[1, 2, 3, 4])).unwrap();
+        assert_eq!(r.configs.data_type, DataType::U8);
+        assert_eq!((r.get_value(0, 0), r.get_value(0, 1), r.get_value(1, 0), r.get_value(1, 1)), (1.0, 2.0, 3.0, 4.0));
+    }
+
+    #[test]
+    fn short_strip_is_an_error() {
+        let tags = with_tag(&grey_tags(), 279, 4, vec![3]);
+        let err = read_tiff_error("short_strip.tif", &tiff_with_tags(&tags, &[1, 2, 3, 4]));
+        assert_eq!(err.kind, RasterErrorKind::InvalidData);
+        assert_eq!(err.offset, Some(8));
+    }
+
+    #[test]
+    fn tiled_round_trip() {
+        let mut options = GeoTiffWriteOptions::default();
+        options.tile_width = 16;
+        options.tile_height = 16;
+        let (_, input) = round_trip("tiled.tif", DataType::F32, options);
+        assert_eq!((input.configs.geotiff_options.tile_width, input.configs.geotiff_options.tile_height), (16, 16));
+        assert!(!input.configs.geotiff_options.cloud_optimized);
+    }
+
+    #[test]
+    fn many_bands_with_their_own_nodata() {
+        let file_name = temp_file_name("bands.tif");
+        let mut configs = test_raster(&file_name, 2, 3, DataType::I16, GeoTiffWriteOptions::default()).configs;
+        configs.bands = 300;
+        for band in 0..300 {
+            configs.set_band_nodata(band, -(band as f64));
+        }
+        let mut r = Raster::initialize_using_config(&file_name, &configs);
+        for band in 0..300 {
+            r.set_band_value(band, 1, 2, band as f64 + 1.0);
+        }
+        r.write().unwrap();
+        let input = Raster::new(&file_name, "r").unwrap();
+        fs::remove_file(&file_name).unwrap();
+
+        assert_eq!(input.configs.bands, 300);
+        assert_eq!(input.configs.nodata, 0.0);
+        for band in 0..300 {
+            assert_eq!(input.configs.get_band_nodata(band), -(band as f64));
+            assert_eq!(input.get_band_value(band, 0, 0), -(band as f64));
+            assert_eq!(input.get_band_value(band, 1, 2), band as f64 + 1.0);
+        }
+    }
+
+    #[test]
+    fn cloud_optimized_round_trip_and_window() {
+        let file_name = temp_file_name("cog.tif");
+        let mut options = GeoTiffWriteOptions::default();
+        options.cloud_optimized = true;
+        options.tile_width = 16;
+        options.tile_height = 16;
+        options.compression = GeoTiffCompression::Deflate;
+        let mut r = test_raster(&file_name, 70, 90, DataType::F64, options);
+        r.write().unwrap();
+
+        let input = Raster::new(&file_name, "r").unwrap();
+        assert_same_cells(&r, &input);
+        assert!(input.configs.geotiff_options.cloud_optimized);
+
+        // a window that spans several tiles and extends beyond the image
+        let opened = Raster::open(&file_name).unwrap();
+        let window = opened.read_window(60, 20, 20, 30).unwrap();
+        for row in 0..20isize {
+            for col in 0..30isize {
+                assert_eq!(window.get_value(row, col), r.get_value(60 + row, 20 + col));
+            }
+        }
+        fs::remove_file(&file_name).unwrap();
+    }
+
+    #[test]
+    fn unsupported_photometric_interpretation_is_an_error() {
+        let tags = with_tag(&grey_tags(), 262, 3, vec![9]);
+        let err = read_tiff_error("photometric.tif", &tiff_with_tags(&tags, &[1, 2, 3, 4]));
+        assert_eq!(err.kind, RasterErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn rgb_with_too_few_samples_is_an_error() {
+        let tags = with_tag(&grey_tags(), 262, 3, vec![2]);
+        let err = read_tiff_error("rgb_samples.tif", &tiff_with_tags(&tags, &[1, 2, 3, 4]));
+        assert_eq!(err.kind, RasterErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn zero_block_size_is_an_error() {
+        let tags = with_tag(&grey_tags(), 278, 3, vec![0]);
+        let err = read_tiff_error("rows_per_strip.tif", &tiff_with_tags(&tags, &[1, 2, 3, 4]));
+        assert_eq!(err.kind, RasterErrorKind::InvalidData);
+
+        let mut tags: Vec<(u16, u16, Vec<u32>)> = grey_tags().into_iter().filter(|t| t.0 != 273 && t.0 != 278 && t.0 != 279).collect();
+        tags.push((322, 3, vec![0])); // TileWidth
+        tags.push((323, 3, vec![16])); // TileLength
+        tags.push((324, 4, vec![8])); // TileOffsets
+        tags.push((325, 4, vec![4])); // TileByteCounts
+        let err = read_tiff_error("tile_width.tif", &tiff_with_tags(&tags, &[1, 2, 3, 4]));
+        assert_eq!(err.kind, RasterErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn empty_tag_is_an_error() {
+        let tags = with_tag(&grey_tags(), 259, 3, vec![]);
+        let err = read_tiff_error("empty_tag.tif", &tiff_with_tags(&tags, &[1, 2, 3, 4]));
+        assert_eq!(err.kind, RasterErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn corrupt_files_do_not_panic() {
+        let tiff = tiff_with_tags(&grey_tags(), &[1, 2, 3, 4]);
+        for len in 0..tiff.len() {
+            let _ = read_tiff("truncated.tif", &tiff[..len]);
+        }
+        for i in 0..tiff.len() {
+            for &b in &[0u8, 1u8, 0xffu8] {
+                let mut corrupt = tiff.clone();
+                corrupt[i] = b;
+                let _ = read_tiff("corrupt.tif", &corrupt);
+            }
+        }
+    }
+}
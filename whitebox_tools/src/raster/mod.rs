@@ -2,179 +2,258 @@
 
 pub mod arcascii_raster;
 pub mod arcbinary_raster;
+pub mod envi_raster;
+pub mod esri_bil_raster;
 pub mod geotiff;
 pub mod grass_raster;
 pub mod idrisi_raster;
+pub mod raster_data;
+pub mod raster_error;
 pub mod saga_raster;
 pub mod surfer7_raster;
 pub mod surfer_ascii_raster;
 pub mod whitebox_raster;
+pub mod window;
 
 use std::io::Error;
 use std::io::prelude::*;
-use std::io::ErrorKind;
-use std::io::BufReader;
 // use std::io::BufRead;
 use std::default::Default;
 use std::fs::File;
 use std::path::Path;
+use std::str::FromStr;
 use std::f64;
-use raster::arcascii_raster::*;
-use raster::arcbinary_raster::*;
-use raster::geotiff::*;
-use raster::grass_raster::*;
-use raster::idrisi_raster::*;
-use raster::saga_raster::*;
-use raster::surfer7_raster::*;
-use raster::surfer_ascii_raster::*;
-use raster::whitebox_raster::*;
-use io_utils::byte_order_reader::*;
-use std::ops::{Index, IndexMut};
-
+use std::cmp::max;
+use std::sync::Arc;
+use crate::raster::arcascii_raster::*;
+use crate::raster::arcbinary_raster::*;
+use crate::raster::envi_raster::*;
+use crate::raster::esri_bil_raster::*;
+use crate::raster::geotiff::*;
+use crate::raster::grass_raster::*;
+use crate::raster::idrisi_raster::*;
+use crate::raster::raster_data::RasterData;
+use crate::raster::raster_error::*;
+use crate::raster::saga_raster::*;
+use crate::raster::surfer7_raster::*;
+use crate::raster::surfer_ascii_raster::*;
+use crate::raster::whitebox_raster::*;
+use crate::raster::window::*;
+use crate::io_utils::byte_order_reader::*;
+use crate::spatial_ref_system::SpatialReference;
+
+/// A raster of one or more bands. The bands are held one after the other (band
+/// sequential), each in row-major order, in the raster's data type (see
+/// `RasterData`). The single-band accessors, e.g. `get_value`, refer to the first band.
 #[derive(Default, Clone)]
 pub struct Raster {
     pub file_name: String,
     file_mode: String,
+    /// The format of the raster, given by the extension of its file name and, when it is
+    /// read, the contents of the file. It may be set before `write` to write the raster
+    /// in another format, e.g. a GeoTIFF to a file without a .tif extension.
     pub raster_type: RasterType,
     pub configs: RasterConfigs,
-    data: Vec<f64>,
-}
-
-impl Index<(isize, isize)> for Raster {
-    type Output = f64;
-
-    fn index<'a>(&'a self, index: (isize, isize)) -> &'a f64 {
-        let row = index.0;
-        let column = index.1;
-
-        if column < 0 { return &self.configs.nodata; }
-        if row < 0 { return &self.configs.nodata; }
-
-        let c: usize = column as usize;
-        let r: usize = row as usize;
-
-        if c >= self.configs.columns { return &self.configs.nodata; }
-        if r >= self.configs.rows { return &self.configs.nodata; }
-        let idx: usize = r * self.configs.columns + c;
-        &self.data[idx]
-    }
-}
-
-impl IndexMut<(isize, isize)> for Raster {
-    fn index_mut<'a>(&'a mut self, index: (isize, isize)) -> &'a mut f64 {
-        let row = index.0;
-        let column = index.1;
-        if column < 0 { return &mut self.configs.nodata; }
-        if row < 0 { return &mut self.configs.nodata; }
-        let c: usize = column as usize;
-        let r: usize = row as usize;
-        if c >= self.configs.columns { return &mut self.configs.nodata; }
-        if r >= self.configs.rows { return &mut self.configs.nodata; }
-        let idx = r * self.configs.columns + c;
-        &mut self.data[idx as usize]
-    }
+    data: RasterData,
+    // The layout of the cells in the file of a raster opened with `open`.
+    layout: Option<Arc<WindowLayout>>,
 }
 
 impl Raster {
-    pub fn new<'a>(file_name: &'a str, file_mode: &'a str) -> Result<Raster, Error> {
+    pub fn new<'a>(file_name: &'a str, file_mode: &'a str) -> Result<Raster, RasterError> {
         let fm: String = file_mode.to_lowercase();
         let mut r = Raster {
             file_name: file_name.to_string(),
             file_mode: fm.clone(),
-            raster_type: get_raster_type_from_file(file_name.to_string(), fm.clone()),
+            raster_type: get_raster_type_from_file(file_name.to_string(), fm)?,
             ..Default::default()
         };
         if r.file_mode == "r" {
-            match get_raster_type_from_file(file_name.to_string(), fm) {
-                RasterType::ArcBinary => {
-                    let _ = read_arcbinary(&r.file_name, &mut r.configs, &mut r.data).unwrap();
-                    return Ok(r);
-                },
-                RasterType::ArcAscii => {
-                    let _ = read_arcascii(&r.file_name, &mut r.configs, &mut r.data).unwrap();
-                    return Ok(r);
-                },
-                RasterType::GeoTiff => {
-                    let _ = read_geotiff(&r.file_name, &mut r.configs, &mut r.data).unwrap();
-                    return Ok(r);
-                },
-                RasterType::GrassAscii => {
-                    let _ = read_grass_raster(&r.file_name, &mut r.configs, &mut r.data).unwrap();
-                    return Ok(r);
-                },
-                RasterType::IdrisiBinary => {
-                    let _ = read_idrisi(&r.file_name, &mut r.configs, &mut r.data).unwrap();
-                    return Ok(r);
-                },
-                RasterType::SagaBinary => {
-                    let _ = read_saga(&r.file_name, &mut r.configs, &mut r.data).unwrap();
-                    return Ok(r);
-                },
-                RasterType::Surfer7Binary => {
-                    let _ = read_surfer7(&r.file_name, &mut r.configs, &mut r.data).unwrap();
-                    return Ok(r);
-                },
-                RasterType::SurferAscii => {
-                    let _ = read_surfer_ascii_raster(&r.file_name, &mut r.configs, &mut r.data).unwrap();
-                    return Ok(r);
-                },
-                RasterType::Whitebox => {
-                    let _ = read_whitebox(&r.file_name, &mut r.configs, &mut r.data).unwrap();
-                    return Ok(r);
-                },
-                RasterType::Unknown => { return Err(Error::new(ErrorKind::Other, "Unrecognized raster type")); },
-            }
+            let result = match r.raster_type {
+                RasterType::ArcBinary => read_arcbinary(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::ArcAscii => read_arcascii(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::Envi => read_envi(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::EsriBIL => read_esri_bil(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::GeoTiff => read_geotiff(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::GrassAscii => read_grass_raster(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::IdrisiBinary => read_idrisi(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::SagaBinary => read_saga(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::Surfer7Binary => read_surfer7(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::SurferAscii => read_surfer_ascii_raster(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::Whitebox => read_whitebox(&r.file_name, &mut r.configs, &mut r.data),
+                RasterType::Unknown => Err(RasterError::new(RasterErrorKind::UnknownFormat, "Unrecognized raster type")),
+            };
+            return match result {
+                Ok(_) => Ok(r),
+                Err(e) => Err(e.in_file(file_name, r.raster_type)),
+            };
         } else { // write
 
         }
-        Err(Error::new(ErrorKind::Other, "Error creating raster"))
+        Err(RasterError::new(RasterErrorKind::InvalidInput, "Error creating raster").in_file(file_name, r.raster_type))
+    }
+
+    /// Opens a raster for reading by window (see `read_window` and `blocks`), reading its
+    /// header but none of its cells, so that rasters larger than memory can be processed.
+    /// Whitebox, ArcGIS binary, Idrisi, SAGA and GeoTIFF rasters are read by window from
+    /// their files; rasters of other formats are read whole. The single-cell accessors,
+    /// e.g. `get_value`, cannot be used on a raster opened in this way.
+    pub fn open(file_name: &str) -> Result<Raster, RasterError> {
+        let mut r = Raster {
+            file_name: file_name.to_string(),
+            file_mode: "r".to_string(),
+            raster_type: get_raster_type_from_file(file_name.to_string(), "r".to_string())?,
+            ..Default::default()
+        };
+        let result = match r.raster_type {
+            RasterType::ArcBinary => read_arcbinary_header(&r.file_name, &mut r.configs).map(WindowLayout::Raw),
+            RasterType::GeoTiff => read_geotiff_header(&r.file_name, &mut r.configs).map(WindowLayout::GeoTiff),
+            RasterType::IdrisiBinary => read_idrisi_header(&r.file_name, &mut r.configs).map(WindowLayout::Raw),
+            RasterType::SagaBinary => read_saga_header(&r.file_name, &mut r.configs).map(WindowLayout::Raw),
+            RasterType::Whitebox => read_whitebox_header(&r.file_name, &mut r.configs).map(WindowLayout::Raw),
+            _ => return Raster::new(file_name, "r"),
+        };
+        match result {
+            Ok(layout) => {
+                r.layout = Some(Arc::new(layout));
+                Ok(r)
+            },
+            Err(e) => Err(e.in_file(file_name, r.raster_type)),
+        }
+    }
+
+    /// Reads a window of a raster, given by the row and column of its upper-left cell
+    /// and its size, as a raster of its own. The window may extend beyond the edges of
+    /// the raster; cells outside of it are nodata. The cells are read from the file if
+    /// the raster was opened with `open`.
+    pub fn read_window(&self, row: isize, column: isize, rows: usize, columns: usize) -> Result<Raster, RasterError> {
+        let window = RasterWindow { row, column, rows, columns };
+        let mut output = Raster {
+            file_name: self.file_name.clone(),
+            file_mode: "r".to_string(),
+            raster_type: self.raster_type.clone(),
+            configs: window.get_configs(&self.configs),
+            data: RasterData::default(),
+            layout: None,
+        };
+        if !self.data.is_empty() {
+            output.data = RasterData::for_configs(&output.configs, self.data.data_type());
+            for band in 0..self.num_bands() {
+                for r in 0..rows {
+                    for c in 0..columns {
+                        let z = self.get_band_value(band, row + r as isize, column + c as isize);
+                        output.data.set_value((band * rows + r) * columns + c, z);
+                    }
+                }
+            }
+            return Ok(output);
+        }
+        let result = match self.layout.as_deref() {
+            Some(WindowLayout::Raw(layout)) => read_raw_window(layout, &self.configs, &window, &mut output.data),
+            Some(WindowLayout::GeoTiff(layout)) => read_geotiff_window(&self.file_name, layout, &self.configs, &window, &mut output.data),
+            None => Err(RasterError::new(RasterErrorKind::InvalidInput, format!("Windows of {:?} rasters cannot be read from the file.", self.raster_type))),
+        };
+        match result {
+            Ok(_) => Ok(output),
+            Err(e) => Err(e.in_file(&self.file_name, self.raster_type.clone())),
+        }
+    }
+
+    /// Returns an iterator over the blocks of a raster, in row-major order, each read as
+    /// it is reached. Blocks have `block_rows` rows and `block_columns` columns, except
+    /// along the bottom and right edges, and include an overlap of `overlap` cells
+    /// around them, e.g. half the size of a filter, so that neighbourhood operations can
+    /// be applied to all of their cells. Blocks of whole rows, i.e. with `block_columns`
+    /// equal to the number of columns, are read most efficiently.
+    pub fn blocks(&self, block_rows: usize, block_columns: usize, overlap: usize) -> RasterBlocks<'_> {
+        RasterBlocks::new(self, block_rows, block_columns, overlap)
     }
 
     pub fn initialize_using_config<'a>(file_name: &'a str, configs: &'a RasterConfigs) -> Raster {
         let mut output = Raster { file_name: file_name.to_string(), configs: configs.clone(), ..Default::default() };
         output.file_mode = "w".to_string();
-        output.raster_type = get_raster_type_from_file(file_name.to_string(), "w".to_string());
-
-        output.data = vec![output.configs.nodata; output.configs.rows * output.configs.columns];
+        output.raster_type = get_raster_type_from_file(file_name.to_string(), "w".to_string()).unwrap_or(RasterType::Unknown);
+        output.data = RasterData::for_configs(&output.configs, output.configs.data_type);
 
         output
     }
 
+    /// Creates a raster with the extent, data type and nodata values of another raster. The
+    /// cells are held in the data type of the input, as they are written; use
+    /// `initialize_using_config` for an output of another data type.
     pub fn initialize_using_file<'a>(file_name: &'a str, input: &'a Raster) -> Raster {
         let mut output = Raster { file_name: file_name.to_string(), ..Default::default() };
         output.file_mode = "w".to_string();
-        output.raster_type = get_raster_type_from_file(file_name.to_string(), "w".to_string());
-        output.configs.rows = input.configs.rows;
-        output.configs.columns = input.configs.columns;
-        output.configs.north = input.configs.north;
-        output.configs.south = input.configs.south;
-        output.configs.east = input.configs.east;
-        output.configs.west = input.configs.west;
-        output.configs.resolution_x = input.configs.resolution_x;
-        output.configs.resolution_y = input.configs.resolution_y;
-        output.configs.nodata = input.configs.nodata;
-        output.configs.data_type = input.configs.data_type;
-        output.configs.photometric_interp = input.configs.photometric_interp;
-        output.configs.palette = input.configs.palette.clone();
-        output.configs.projection = input.configs.projection.clone();
-        output.configs.xy_units = input.configs.xy_units.clone();
-        output.configs.z_units = input.configs.z_units.clone();
-        output.configs.endian = input.configs.endian.clone();
-        output.configs.palette_nonlinearity = input.configs.palette_nonlinearity;
-        output.configs.pixel_is_area = input.configs.pixel_is_area;
-    	output.configs.epsg_code = input.configs.epsg_code;
-        output.configs.coordinate_ref_system_wkt = input.configs.coordinate_ref_system_wkt.clone();
-
-        if output.raster_type == RasterType::SurferAscii ||
-            output.raster_type == RasterType::Surfer7Binary {
-            output.configs.nodata = 1.71041e38;
-        }
+        output.raster_type = get_raster_type_from_file(file_name.to_string(), "w".to_string()).unwrap_or(RasterType::Unknown);
+        output.configs = output_configs(&input.configs, &output.raster_type);
 
-        output.data = vec![output.configs.nodata; output.configs.rows * output.configs.columns];
+        output.data = RasterData::for_configs(&output.configs, output.configs.data_type);
 
         output
     }
 
+    /// Returns the number of bands.
+    pub fn num_bands(&self) -> usize {
+        max(self.configs.bands, 1)
+    }
+
+    /// Returns the value of a cell in a band, or the band's nodata value for cells
+    /// outside of the raster.
+    pub fn get_band_value(&self, band: usize, row: isize, column: isize) -> f64 {
+        if band >= self.num_bands() || row < 0 || column < 0 {
+            return self.configs.get_band_nodata(band);
+        }
+        let (r, c) = (row as usize, column as usize);
+        if r >= self.configs.rows || c >= self.configs.columns {
+            return self.configs.get_band_nodata(band);
+        }
+        self.data.get_value((band * self.configs.rows + r) * self.configs.columns + c)
+    }
+
+    pub fn set_band_value(&mut self, band: usize, row: isize, column: isize, value: f64) {
+        if band < self.num_bands() && row >= 0 && column >= 0 {
+            let (r, c) = (row as usize, column as usize);
+            if r < self.configs.rows && c < self.configs.columns {
+                let idx = (band * self.configs.rows + r) * self.configs.columns + c;
+                self.data.set_value(idx, value);
+            }
+        }
+    }
+
+    /// Calculates the statistics of the valid (not nodata) cells of a band.
+    pub fn get_band_statistics(&self, band: usize) -> BandStatistics {
+        let mut stats = BandStatistics {
+            minimum: f64::INFINITY,
+            maximum: f64::NEG_INFINITY,
+            mean: f64::NAN,
+            std_dev: f64::NAN,
+            num_valid_cells: 0,
+        };
+        if band >= self.num_bands() {
+            return stats;
+        }
+        let nodata = self.configs.get_band_nodata(band);
+        let num_cells = self.configs.rows * self.configs.columns;
+        let mut sum = 0f64;
+        let mut sum_sqr = 0f64;
+        for i in band * num_cells..(band + 1) * num_cells {
+            let z = self.data.get_value(i);
+            if z != nodata {
+                if z < stats.minimum { stats.minimum = z; }
+                if z > stats.maximum { stats.maximum = z; }
+                sum += z;
+                sum_sqr += z * z;
+                stats.num_valid_cells += 1;
+            }
+        }
+        if stats.num_valid_cells > 0 {
+            let n = stats.num_valid_cells as f64;
+            stats.mean = sum / n;
+            stats.std_dev = (sum_sqr / n - stats.mean * stats.mean).max(0f64).sqrt();
+        }
+        stats
+    }
+
     pub fn get_value(&self, row: isize, column: isize) -> f64 {
         if column < 0 { return self.configs.nodata; }
         if row < 0 { return self.configs.nodata; }
@@ -185,7 +264,7 @@ impl Raster {
         if c >= self.configs.columns { return self.configs.nodata; }
         if r >= self.configs.rows { return self.configs.nodata; }
         let idx: usize = r * self.configs.columns + c;
-        self.data[idx]
+        self.data.get_value(idx)
     }
 
     pub fn set_value(&mut self, row: isize, column: isize, value: f64) {
@@ -194,7 +273,7 @@ impl Raster {
             let r: usize = row as usize;
             if c < self.configs.columns && r < self.configs.rows {
                 let idx = r * self.configs.columns + c;
-                self.data[idx] = value;
+                self.data.set_value(idx, value);
             }
         }
     }
@@ -202,75 +281,48 @@ impl Raster {
     pub fn set_row_data(&mut self, row: isize, values: Vec<f64>) {
         for column in 0..values.len() {
             if row >= 0 {
-                let c: usize = column as usize;
+                let c: usize = column;
                 let r: usize = row as usize;
                 if c < self.configs.columns && r < self.configs.rows {
                     let idx = r * self.configs.columns + c;
-                    self.data[idx] = values[c];
+                    self.data.set_value(idx, values[c]);
                 }
             }
         }
     }
 
-    pub fn write(&mut self) -> Result<(), Error> {
-        match self.raster_type {
-            RasterType::ArcAscii => {
-                let _ = match write_arcascii(self) {
-                    Ok(_) => (),
-                    Err(e) => println!("error while writing: {:?}", e),
-                };
-            },
-            RasterType::ArcBinary => {
-                let _ = match write_arcbinary(self) {
-                    Ok(_) => (),
-                    Err(e) => println!("error while writing: {:?}", e),
-                };
-            },
-            RasterType::GeoTiff => {
-                let _ = match write_geotiff(self) {
-                    Ok(_) => (),
-                    Err(e) => println!("error while writing: {:?}", e),
-                };
-            },
-            RasterType::GrassAscii => {
-                let _ = match write_grass_raster(self) {
-                    Ok(_) => (),
-                    Err(e) => println!("error while writing: {:?}", e),
-                };
-            },
-            RasterType::IdrisiBinary => {
-                let _ = match write_idrisi(self) {
-                    Ok(_) => (),
-                    Err(e) => println!("error while writing: {:?}", e),
-                };
-            },
-            RasterType::SagaBinary => {
-                let _ = match write_saga(self) {
-                    Ok(_) => (),
-                    Err(e) => println!("error while writing: {:?}", e),
-                };
-            },
-            RasterType::Surfer7Binary => {
-                let _ = match write_surfer7(self) {
-                    Ok(_) => (),
-                    Err(e) => println!("error while writing: {:?}", e),
-                };
-            },
-            RasterType::SurferAscii => {
-                let _ = match write_surfer_ascii_raster(self) {
-                    Ok(_) => (),
-                    Err(e) => println!("error while writing: {:?}", e),
-                };
-            },
-            RasterType::Whitebox => {
-                let _ = match write_whitebox(self) {
-                    Ok(_) => (),
-                    Err(e) => println!("error while writing: {:?}", e),
-                };
-            },
-            RasterType::Unknown => { return Err(Error::new(ErrorKind::Other, "Unrecognized raster type")); },
+    pub fn write(&mut self) -> Result<(), RasterError> {
+        // The header and data files of these formats are named by their extensions.
+        let extensions: &[&str] = match self.raster_type {
+            RasterType::ArcBinary => &["flt", "hdr"],
+            RasterType::IdrisiBinary => &["rst", "rdc"],
+            RasterType::SagaBinary => &["sdat", "sgrd"],
+            RasterType::Whitebox => &["tas", "dep"],
+            _ => &[],
+        };
+        let extension = Path::new(&self.file_name).extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+        if !extensions.is_empty() && !extensions.contains(&extension.as_ref()) {
+            return Err(RasterError::new(RasterErrorKind::InvalidInput, format!("{:?} rasters must be written to a file with a .{} extension.", self.raster_type, extensions.join(" or .")))
+                .in_file(&self.file_name, self.raster_type.clone()));
+        }
+        let result = match self.raster_type {
+            RasterType::ArcAscii => write_arcascii(self),
+            RasterType::ArcBinary => write_arcbinary(self),
+            RasterType::GeoTiff => write_geotiff(self),
+            RasterType::GrassAscii => write_grass_raster(self),
+            RasterType::IdrisiBinary => write_idrisi(self),
+            RasterType::SagaBinary => write_saga(self),
+            RasterType::Surfer7Binary => write_surfer7(self),
+            RasterType::SurferAscii => write_surfer_ascii_raster(self),
+            RasterType::Whitebox => write_whitebox(self),
+            RasterType::Envi => write_envi(self),
+            RasterType::EsriBIL => write_esri_bil(self),
+            RasterType::Unknown => Err(RasterError::new(RasterErrorKind::UnknownFormat, "Unrecognized raster type")),
+        };
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => Err(e.in_file(&self.file_name, self.raster_type.clone())),
         }
-        Ok(())
     }
 
     pub fn add_metadata_entry(&mut self, value: String) {
@@ -300,12 +352,50 @@ impl Raster {
     }
 }
 
+/// Returns the configs of an output raster of a given type that is initialized using an
+/// input raster, i.e. with the input's extent, data type, nodata value and styling.
+fn output_configs(input: &RasterConfigs, raster_type: &RasterType) -> RasterConfigs {
+    let mut configs = RasterConfigs::default();
+    configs.rows = input.rows;
+    configs.columns = input.columns;
+    configs.north = input.north;
+    configs.south = input.south;
+    configs.east = input.east;
+    configs.west = input.west;
+    configs.resolution_x = input.resolution_x;
+    configs.resolution_y = input.resolution_y;
+    configs.nodata = input.nodata;
+    configs.data_type = input.data_type;
+    configs.photometric_interp = input.photometric_interp;
+    configs.palette = input.palette.clone();
+    configs.projection = input.projection.clone();
+    configs.xy_units = input.xy_units.clone();
+    configs.z_units = input.z_units.clone();
+    configs.endian = input.endian;
+    configs.palette_nonlinearity = input.palette_nonlinearity;
+    configs.pixel_is_area = input.pixel_is_area;
+    configs.epsg_code = input.epsg_code;
+    configs.coordinate_ref_system_wkt = input.coordinate_ref_system_wkt.clone();
+    configs.geotiff_options = input.geotiff_options;
+    configs.bands = input.bands;
+    configs.band_nodata = input.band_nodata.clone();
+    configs.interleave = input.interleave;
+
+    if *raster_type == RasterType::SurferAscii ||
+        *raster_type == RasterType::Surfer7Binary {
+        configs.nodata = 1.71041e38;
+        configs.band_nodata = vec![];
+    }
+
+    configs
+}
+
 #[derive(Debug, Clone)]
 pub struct RasterConfigs {
     pub title: String,
     pub rows: usize,
     pub columns: usize,
-    pub bands: u8,
+    pub bands: usize,
     pub nodata: f64,
     pub north: f64,
     pub south: f64,
@@ -330,6 +420,54 @@ pub struct RasterConfigs {
 	pub epsg_code: u16,
     pub coordinate_ref_system_wkt: String,
     pub metadata: Vec<String>,
+    pub geotiff_options: GeoTiffWriteOptions,
+    /// The nodata value of each band, if they differ; when empty, every band uses `nodata`.
+    pub band_nodata: Vec<f64>,
+    /// The arrangement of the bands in formats that offer a choice, e.g. ENVI.
+    pub interleave: Interleave,
+}
+
+impl RasterConfigs {
+    /// Returns the coordinate reference system described by the EPSG code and WKT.
+    pub fn get_spatial_reference(&self) -> SpatialReference {
+        let mut srs = if self.coordinate_ref_system_wkt.is_empty() || self.coordinate_ref_system_wkt == "not specified" {
+            SpatialReference::default()
+        } else {
+            SpatialReference::from_wkt(&self.coordinate_ref_system_wkt)
+        };
+        if self.epsg_code != 0 {
+            srs.epsg_code = self.epsg_code;
+        }
+        srs
+    }
+
+    /// Sets the EPSG code and WKT from a coordinate reference system, e.g. that of a
+    /// LAS file.
+    pub fn set_spatial_reference(&mut self, srs: &SpatialReference) {
+        self.epsg_code = srs.epsg_code;
+        self.coordinate_ref_system_wkt = if srs.wkt.is_empty() { "not specified".to_string() } else { srs.wkt.clone() };
+    }
+
+    /// Returns the nodata value of a band.
+    pub fn get_band_nodata(&self, band: usize) -> f64 {
+        match self.band_nodata.get(band) {
+            Some(v) => *v,
+            None => self.nodata,
+        }
+    }
+
+    /// Sets the nodata value of a band, leaving those of the other bands unchanged.
+    pub fn set_band_nodata(&mut self, band: usize, value: f64) {
+        let bands = max(self.bands, band + 1);
+        while self.band_nodata.len() < bands {
+            let nodata = self.nodata;
+            self.band_nodata.push(nodata);
+        }
+        self.band_nodata[band] = value;
+        if band == 0 {
+            self.nodata = value;
+        }
+    }
 }
 
 impl Default for RasterConfigs {
@@ -363,12 +501,16 @@ impl Default for RasterConfigs {
             epsg_code: 0u16,
             coordinate_ref_system_wkt: "not specified".to_string(),
             metadata: vec![],
+            geotiff_options: GeoTiffWriteOptions::default(),
+            band_nodata: vec![],
+            interleave: Interleave::Bsq,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Default)]
 pub enum RasterType {
+    #[default]
     Unknown,
     ArcAscii,
     ArcBinary,
@@ -378,24 +520,65 @@ pub enum RasterType {
     SagaBinary,
     Surfer7Binary,
     SurferAscii,
-    Whitebox
-    // EsriBIL,
+    Whitebox,
+    Envi,
+    EsriBIL,
 }
 
-impl Default for RasterType {
-    fn default() -> RasterType { RasterType::Unknown }
+
+/// Parses the name of a raster format, e.g. given by a user to override the format of
+/// an output file. The names are those of the variants, in any case, or the common
+/// extensions of the formats, e.g. 'tif'.
+impl FromStr for RasterType {
+    type Err = RasterError;
+
+    fn from_str(s: &str) -> Result<RasterType, RasterError> {
+        match s.trim().to_lowercase().as_ref() {
+            "arcascii" | "asc" => Ok(RasterType::ArcAscii),
+            "arcbinary" | "flt" => Ok(RasterType::ArcBinary),
+            "geotiff" | "tif" | "tiff" => Ok(RasterType::GeoTiff),
+            "grassascii" => Ok(RasterType::GrassAscii),
+            "idrisibinary" | "idrisi" | "rst" => Ok(RasterType::IdrisiBinary),
+            "sagabinary" | "saga" | "sdat" => Ok(RasterType::SagaBinary),
+            "surfer7binary" | "surfer7" | "grd" => Ok(RasterType::Surfer7Binary),
+            "surferascii" => Ok(RasterType::SurferAscii),
+            "whitebox" | "dep" => Ok(RasterType::Whitebox),
+            "envi" | "img" => Ok(RasterType::Envi),
+            "esribil" | "bil" | "bip" | "bsq" => Ok(RasterType::EsriBIL),
+            _ => Err(RasterError::new(RasterErrorKind::UnknownFormat, format!("The raster format '{}' is not recognized.", s))),
+        }
+    }
 }
 
 
+/// The summary statistics of a band.
 #[derive(Debug, Copy, Clone, PartialEq)]
-pub enum DataType {
-    F64, F32, I64, I32, I16, I8, U64, U32, U16, U8, RGB24, RGB48, RGBA32, Unknown
+pub struct BandStatistics {
+    pub minimum: f64,
+    pub maximum: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub num_valid_cells: usize,
+}
+
+/// The arrangement of the bands of a multi-band raster in a file: band sequential, band
+/// interleaved by line (row), or band interleaved by pixel.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Interleave {
+    #[default]
+    Bsq,
+    Bil,
+    Bip,
 }
 
-impl Default for DataType {
-    fn default() -> DataType { DataType::Unknown }
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum DataType {
+    F64, F32, I64, I32, I16, I8, U64, U32, U16, U8, RGB24, RGB48, RGBA32, #[default]
+    Unknown
 }
 
+
 impl DataType {
     pub fn get_data_size(&self) -> usize {
         match *self {
@@ -417,7 +600,7 @@ impl DataType {
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub enum PhotometricInterpretation {
     Continuous,
     Categorical,
@@ -426,72 +609,148 @@ pub enum PhotometricInterpretation {
     Paletted,
     // Rgb32,
     // Rgb24,
+    #[default]
     Unknown
 }
 
-impl Default for PhotometricInterpretation {
-    fn default() -> PhotometricInterpretation { PhotometricInterpretation::Unknown }
-}
 
-fn get_raster_type_from_file(file_name: String, file_mode: String) -> RasterType {
+/// Determines the format of a raster. When the file is being read, its format is given
+/// by its contents, e.g. the signature of a GeoTIFF or the keys of a text header, or for
+/// the data file of an ENVI or ESRI raster by the contents of its header, so that
+/// misnamed files are read correctly. Otherwise, and for files whose contents are not
+/// recognized, the format is given by the extension of the file name.
+fn get_raster_type_from_file(file_name: String, file_mode: String) -> Result<RasterType, RasterError> {
     // get the file extension
-    let extension: String = match Path::new(&file_name).extension().unwrap().to_str() {
-        Some(n) => n.to_string().to_lowercase(),
+    let extension: String = match Path::new(&file_name).extension() {
+        Some(ext) => ext.to_string_lossy().to_lowercase(),
         None => "".to_string(),
     };
+    let file_error = |e: Error| RasterError::from(e).in_file(&file_name, RasterType::Unknown);
+
+    if file_mode == "r" && Path::new(&file_name).is_file() {
+        if let Some(raster_type) = get_raster_type_from_contents(&file_name, &extension).map_err(&file_error)? {
+            return Ok(raster_type);
+        }
+    }
 
     if extension == "tas" || extension == "dep" {
-        return RasterType::Whitebox;
+        return Ok(RasterType::Whitebox);
     } else if extension == "tif" || extension == "tiff" {
-        return RasterType::GeoTiff;
+        return Ok(RasterType::GeoTiff);
     } else if extension == "flt" {
-        return RasterType::ArcBinary;
+        return Ok(RasterType::ArcBinary);
+    } else if extension == "img" || extension == "dat" {
+        return Ok(RasterType::Envi);
+    } else if extension == "bil" || extension == "bip" || extension == "bsq" {
+        return Ok(RasterType::EsriBIL);
+    } else if extension == "hdr" {
+        // It could be the header of an ENVI, an ESRI BIL/BIP/BSQ or an ArcBinary raster.
+        if file_mode == "r" {
+            return Ok(RasterType::ArcBinary);
+        }
+        return Ok(RasterType::Envi);
     } else if extension == "rdc" || extension == "rst" {
-        return RasterType::IdrisiBinary;
+        return Ok(RasterType::IdrisiBinary);
     } else if extension == "sdat" || extension == "sgrd" {
-        return RasterType::SagaBinary;
+        return Ok(RasterType::SagaBinary);
     } else if extension == "grd" {
-        if file_mode == "r" {
-            // It could be a SurferAscii or a Surfer7Binary.
-            let mut f = File::open(file_name).unwrap();
-            let mut buffer = [0; 4];
-            f.read_exact(&mut buffer).unwrap();
-            //let small_chunk = String::from_utf8_lossy(&buffer[0..8]).to_string();
-            //if small_chunk.contains("DSAA") {
-            if buffer[0] == 68 && buffer[1] == 83 && buffer[2] == 65 && buffer[3] == 65  { // DSAA signature
-                return RasterType::SurferAscii;
-            } else {
-                return RasterType::Surfer7Binary;
-            }
+        return Ok(RasterType::Surfer7Binary);
+    } else if extension == "asc" {
+        return Ok(RasterType::ArcAscii);
+    } else if (extension == "txt" || extension.is_empty()) && file_mode != "r" {
+        // There is no way of knowing if it is an Arc or GRASS ASCII raster. Default to
+        // ArcAscii.
+        return Ok(RasterType::ArcAscii);
+    }
+
+    if file_mode == "r" && (extension == "txt" || extension.is_empty()) {
+        return Err(RasterError::new(RasterErrorKind::UnknownFormat, "The raster format of the file could not be determined from its contents.")
+            .in_file(&file_name, RasterType::Unknown));
+    }
+    Err(RasterError::new(RasterErrorKind::UnknownFormat, format!("The raster format of the file extension '{}' is not recognized.", extension))
+        .in_file(&file_name, RasterType::Unknown))
+}
+
+/// Determines the format of a raster from the contents of its file, or None if they are
+/// not recognized.
+fn get_raster_type_from_contents(file_name: &str, extension: &str) -> Result<Option<RasterType>, Error> {
+    let buffer = read_file_start(file_name)?;
+    if buffer.len() >= 4 {
+        match &buffer[0..4] {
+            b"II*\0" | b"MM\0*" | b"II+\0" | b"MM\0+" => return Ok(Some(RasterType::GeoTiff)), // classic and BigTIFF
+            b"DSRB" => return Ok(Some(RasterType::Surfer7Binary)),
+            b"DSAA" => return Ok(Some(RasterType::SurferAscii)),
+            _ => {},
         }
-        return RasterType::Surfer7Binary;
-    } else if extension == "asc" || extension == "txt" || extension == "" {
-        // what mode is this raster in?
-        if file_mode == "r" {
-            // It could be an ArcAscii or a GrassAscii.
-            let f = File::open(file_name).unwrap();
-            let file = BufReader::new(&f);
-            let mut line_count = 0;
-            for line in file.lines() {
-                let l = line.unwrap();
-                if l.contains("north") || l.contains("south") || l.contains("east") || l.contains("west") {
-                    return RasterType::GrassAscii;
-                }
-                if l.contains("xllcorner") || l.contains("yllcorner") || l.contains("xllcenter") || l.contains("yllcenter") {
-                    return RasterType::ArcAscii;;
-                }
-                if line_count > 7 {
-                    break;
-                }
-                line_count += 1;
+    }
+    if let Some(raster_type) = get_raster_type_from_header(&buffer, extension) {
+        return Ok(Some(raster_type));
+    }
+
+    // The data file of an ENVI or ESRI raster is known by its header, which either
+    // replaces the extension of the data file or is appended to it.
+    if extension != "hdr" {
+        let path = Path::new(file_name);
+        for header_file in [format!("{}.hdr", file_name), path.with_extension("hdr").to_string_lossy().to_string()].iter() {
+            if Path::new(header_file).is_file() {
+                return match get_raster_type_from_header(&read_file_start(header_file)?, "hdr") {
+                    Some(RasterType::Envi) => Ok(Some(RasterType::Envi)),
+                    Some(RasterType::EsriBIL) => Ok(Some(RasterType::EsriBIL)),
+                    _ => Ok(None),
+                };
             }
         }
-        // For a file_mode "w", there is not way of knowing if it is an Arc or GRASS ASCII raster.
-        // Default to ArcAscii.
-        return RasterType::ArcAscii;
     }
+    Ok(None)
+}
 
-    RasterType::Unknown
+/// Reads the start of a file, which is enough to hold the header of any of the text
+/// formats.
+fn read_file_start(file_name: &str) -> Result<Vec<u8>, Error> {
+    let mut buffer = vec![];
+    File::open(file_name)?.take(4096).read_to_end(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Determines the format of a raster from the keys of a text header, i.e. that of an
+/// ASCII raster or the header file of a binary raster, or None if they are not recognized.
+fn get_raster_type_from_header(buffer: &[u8], extension: &str) -> Option<RasterType> {
+    if buffer.contains(&0u8) {
+        return None; // binary data
+    }
+    let contents = String::from_utf8_lossy(buffer).to_lowercase();
+    if contents.trim_start().starts_with("envi") {
+        return Some(RasterType::Envi);
+    }
+    // The key of each line precedes a ':' or '=', or otherwise a space.
+    let keys: Vec<&str> = contents.lines().map(|line| {
+        match line.find([':', '=']) {
+            Some(i) => line[..i].trim(),
+            None => line.split_whitespace().next().unwrap_or(""),
+        }
+    }).collect();
+    let has_key = |key: &str| keys.contains(&key);
+
+    if has_key("file format") && contents.contains("idrisi") {
+        Some(RasterType::IdrisiBinary)
+    } else if has_key("datafile_offset") || has_key("position_xmin") {
+        Some(RasterType::SagaBinary)
+    } else if has_key("data type") && has_key("min") && has_key("north") {
+        Some(RasterType::Whitebox)
+    } else if has_key("north") && has_key("rows") {
+        Some(RasterType::GrassAscii)
+    } else if has_key("ncols") && has_key("nrows") {
+        // ArcGIS rasters give the cell size; ESRI BIL/BIP/BSQ rasters the cell dimensions.
+        if !has_key("cellsize") {
+            Some(RasterType::EsriBIL)
+        } else if extension == "hdr" || has_key("byteorder") {
+            Some(RasterType::ArcBinary)
+        } else {
+            Some(RasterType::ArcAscii)
+        }
+    } else {
+        None
+    }
 }
 
 // #[derive(Debug, Copy, Clone, PartialEq)]
@@ -533,3 +792,71 @@ fn get_raster_type_from_file(file_name: String, file_mode: String) -> RasterType
 //     }
 //
 // }
+
+#[cfg(test)]
+mod tests {
+    use crate::io_utils::temp_file_name;
+    use super::*;
+
+    fn test_configs(rows: usize, columns: usize) -> RasterConfigs {
+        let mut configs = RasterConfigs::default();
+        configs.rows = rows;
+        configs.columns = columns;
+        configs.north = rows as f64;
+        configs.south = 0.0;
+        configs.east = columns as f64;
+        configs.west = 0.0;
+        configs.resolution_x = 1.0;
+        configs.resolution_y = 1.0;
+        configs.data_type = DataType::F32;
+        configs
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn failed_write_is_an_error() {
+        // /dev/full accepts opening but fails every write, as a full disk would. The cells
+        // of a small raster are buffered and only written when the writer is flushed.
+        let mut r = Raster::initialize_using_config("/dev/full", &test_configs(2, 2));
+        assert_eq!(r.raster_type, RasterType::ArcAscii);
+        let err = r.write().unwrap_err();
+        assert_eq!(err.kind, RasterErrorKind::Io);
+    }
+
+    #[test]
+    fn output_keeps_the_data_type_of_the_input() {
+        let mut configs = test_configs(2, 2);
+        configs.data_type = DataType::U8;
+        configs.nodata = 255.0;
+        let input = Raster::initialize_using_config("input.tif", &configs);
+        let output = Raster::initialize_using_file("output.tif", &input);
+        assert_eq!(output.configs.data_type, DataType::U8);
+        assert_eq!(output.data.data_type(), DataType::U8);
+        assert_eq!(output.get_value(1, 1), 255.0);
+    }
+
+    #[test]
+    fn format_names() {
+        assert_eq!("GeoTIFF".parse::<RasterType>().unwrap(), RasterType::GeoTiff);
+        assert_eq!(" bsq ".parse::<RasterType>().unwrap(), RasterType::EsriBIL);
+        assert_eq!("dep".parse::<RasterType>().unwrap(), RasterType::Whitebox);
+        assert_eq!("jpeg".parse::<RasterType>().unwrap_err().kind, RasterErrorKind::UnknownFormat);
+    }
+
+    #[test]
+    fn format_is_overridden_and_detected_from_the_contents() {
+        let file_name = temp_file_name("raster_override.raster");
+        let mut configs = test_configs(3, 2);
+        configs.photometric_interp = PhotometricInterpretation::Continuous;
+        let mut r = Raster::initialize_using_config(&file_name, &configs);
+        assert_eq!(r.raster_type, RasterType::Unknown);
+        r.set_value(2, 1, 5.0);
+        r.raster_type = "geotiff".parse().unwrap();
+        r.write().unwrap();
+        let input = Raster::new(&file_name, "r");
+        let _ = ::std::fs::remove_file(&file_name);
+        let input = input.unwrap();
+        assert_eq!(input.raster_type, RasterType::GeoTiff);
+        assert_eq!(input.get_value(2, 1), 5.0);
+    }
+}
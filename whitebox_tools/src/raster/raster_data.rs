@@ -0,0 +1,177 @@
+use std::f64;
+use std::cmp::max;
+use crate::raster::*;
+
+/// The cells of a raster, held in their native data type so that, e.g., a U8 raster
+/// needs one byte per cell. Values are converted to and from f64 on access; integer
+/// types round to the nearest value, saturating at the limits of the type. Colour data
+/// are held as packed integers.
+#[derive(Debug, Clone)]
+pub enum RasterData {
+    F64(Vec<f64>),
+    F32(Vec<f32>),
+    I64(Vec<i64>),
+    I32(Vec<i32>),
+    I16(Vec<i16>),
+    I8(Vec<i8>),
+    U64(Vec<u64>),
+    U32(Vec<u32>),
+    U16(Vec<u16>),
+    U8(Vec<u8>),
+}
+
+impl Default for RasterData {
+    fn default() -> RasterData { RasterData::F64(vec![]) }
+}
+
+impl RasterData {
+    /// Creates storage of a data type, filled with a value.
+    pub fn new(data_type: DataType, len: usize, value: f64) -> RasterData {
+        let mut data = RasterData::with_capacity(data_type, len);
+        for _ in 0..len {
+            data.push(value);
+        }
+        data
+    }
+
+    /// Creates empty storage of a data type.
+    pub fn with_capacity(data_type: DataType, capacity: usize) -> RasterData {
+        match RasterData::storage_type(data_type) {
+            DataType::F32 => RasterData::F32(Vec::with_capacity(capacity)),
+            DataType::I64 => RasterData::I64(Vec::with_capacity(capacity)),
+            DataType::I32 => RasterData::I32(Vec::with_capacity(capacity)),
+            DataType::I16 => RasterData::I16(Vec::with_capacity(capacity)),
+            DataType::I8 => RasterData::I8(Vec::with_capacity(capacity)),
+            DataType::U64 => RasterData::U64(Vec::with_capacity(capacity)),
+            DataType::U32 => RasterData::U32(Vec::with_capacity(capacity)),
+            DataType::U16 => RasterData::U16(Vec::with_capacity(capacity)),
+            DataType::U8 => RasterData::U8(Vec::with_capacity(capacity)),
+            _ => RasterData::F64(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Creates the storage for a raster described by its configs, with each band filled
+    /// with its nodata value. The cells are held as `data_type` unless a nodata value
+    /// cannot be represented in it, in which case they are held as f64.
+    pub fn for_configs(configs: &RasterConfigs, data_type: DataType) -> RasterData {
+        let bands = max(configs.bands, 1);
+        let num_cells = configs.rows * configs.columns;
+        let nodata_representable = (0..bands).all(|b| RasterData::can_hold(data_type, configs.get_band_nodata(b)));
+        let data_type = if nodata_representable { data_type } else { DataType::F64 };
+        let mut data = RasterData::with_capacity(data_type, bands * num_cells);
+        for band in 0..bands {
+            let nodata = configs.get_band_nodata(band);
+            for _ in 0..num_cells {
+                data.push(nodata);
+            }
+        }
+        data
+    }
+
+    /// Returns the type in which cells of a data type are held.
+    pub fn storage_type(data_type: DataType) -> DataType {
+        match data_type {
+            DataType::RGB24 | DataType::RGBA32 => DataType::U32,
+            DataType::RGB48 => DataType::U64,
+            DataType::Unknown => DataType::F64,
+            dt => dt,
+        }
+    }
+
+    /// Returns whether a value is held exactly by a data type.
+    pub fn can_hold(data_type: DataType, value: f64) -> bool {
+        let (min, max) = match RasterData::storage_type(data_type) {
+            DataType::F64 => return true,
+            DataType::F32 => return value.is_nan() || value.is_infinite() || value as f32 as f64 == value,
+            DataType::I64 => (-9.223372036854776e18, 9.223372036854776e18),
+            DataType::I32 => (i32::MIN as f64, i32::MAX as f64),
+            DataType::I16 => (i16::MIN as f64, i16::MAX as f64),
+            DataType::I8 => (i8::MIN as f64, i8::MAX as f64),
+            DataType::U64 => (0f64, 1.8446744073709552e19),
+            DataType::U32 => (0f64, u32::MAX as f64),
+            DataType::U16 => (0f64, u16::MAX as f64),
+            _ => (0f64, u8::MAX as f64), // U8
+        };
+        value.fract() == 0f64 && value >= min && value <= max
+    }
+
+    /// Returns the data type in which the cells are held.
+    pub fn data_type(&self) -> DataType {
+        match *self {
+            RasterData::F64(_) => DataType::F64,
+            RasterData::F32(_) => DataType::F32,
+            RasterData::I64(_) => DataType::I64,
+            RasterData::I32(_) => DataType::I32,
+            RasterData::I16(_) => DataType::I16,
+            RasterData::I8(_) => DataType::I8,
+            RasterData::U64(_) => DataType::U64,
+            RasterData::U32(_) => DataType::U32,
+            RasterData::U16(_) => DataType::U16,
+            RasterData::U8(_) => DataType::U8,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match *self {
+            RasterData::F64(ref v) => v.len(),
+            RasterData::F32(ref v) => v.len(),
+            RasterData::I64(ref v) => v.len(),
+            RasterData::I32(ref v) => v.len(),
+            RasterData::I16(ref v) => v.len(),
+            RasterData::I8(ref v) => v.len(),
+            RasterData::U64(ref v) => v.len(),
+            RasterData::U32(ref v) => v.len(),
+            RasterData::U16(ref v) => v.len(),
+            RasterData::U8(ref v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get_value(&self, index: usize) -> f64 {
+        match *self {
+            RasterData::F64(ref v) => v[index],
+            RasterData::F32(ref v) => v[index] as f64,
+            RasterData::I64(ref v) => v[index] as f64,
+            RasterData::I32(ref v) => v[index] as f64,
+            RasterData::I16(ref v) => v[index] as f64,
+            RasterData::I8(ref v) => v[index] as f64,
+            RasterData::U64(ref v) => v[index] as f64,
+            RasterData::U32(ref v) => v[index] as f64,
+            RasterData::U16(ref v) => v[index] as f64,
+            RasterData::U8(ref v) => v[index] as f64,
+        }
+    }
+
+    pub fn set_value(&mut self, index: usize, value: f64) {
+        match *self {
+            RasterData::F64(ref mut v) => v[index] = value,
+            RasterData::F32(ref mut v) => v[index] = value as f32,
+            RasterData::I64(ref mut v) => v[index] = value.round() as i64,
+            RasterData::I32(ref mut v) => v[index] = value.round() as i32,
+            RasterData::I16(ref mut v) => v[index] = value.round() as i16,
+            RasterData::I8(ref mut v) => v[index] = value.round() as i8,
+            RasterData::U64(ref mut v) => v[index] = value.round() as u64,
+            RasterData::U32(ref mut v) => v[index] = value.round() as u32,
+            RasterData::U16(ref mut v) => v[index] = value.round() as u16,
+            RasterData::U8(ref mut v) => v[index] = value.round() as u8,
+        }
+    }
+
+    pub fn push(&mut self, value: f64) {
+        match *self {
+            RasterData::F64(ref mut v) => v.push(value),
+            RasterData::F32(ref mut v) => v.push(value as f32),
+            RasterData::I64(ref mut v) => v.push(value.round() as i64),
+            RasterData::I32(ref mut v) => v.push(value.round() as i32),
+            RasterData::I16(ref mut v) => v.push(value.round() as i16),
+            RasterData::I8(ref mut v) => v.push(value.round() as i8),
+            RasterData::U64(ref mut v) => v.push(value.round() as u64),
+            RasterData::U32(ref mut v) => v.push(value.round() as u32),
+            RasterData::U16(ref mut v) => v.push(value.round() as u16),
+            RasterData::U8(ref mut v) => v.push(value.round() as u8),
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+use crate::raster::RasterType;
+
+/// The kinds of error that can occur while reading or writing a raster.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RasterErrorKind {
+    /// A file could not be opened, read or written.
+    Io,
+    /// The format of a file could not be determined.
+    UnknownFormat,
+    /// A file uses a part of its format, e.g. a compression method or data type, that is
+    /// not supported.
+    Unsupported,
+    /// A file is corrupt, e.g. a header value cannot be parsed or the data are truncated.
+    InvalidData,
+    /// A request cannot be met, e.g. a window of a format that cannot be read by window.
+    InvalidInput,
+}
+
+/// An error reading or writing a raster. Along with what went wrong, it records the
+/// file and format in which it happened and, where it is known, the position (byte
+/// offset) in the file.
+#[derive(Debug)]
+pub struct RasterError {
+    pub kind: RasterErrorKind,
+    pub message: String,
+    /// The file being read or written; empty if it is not known.
+    pub file_name: String,
+    pub raster_type: RasterType,
+    /// The position in the file at which the error was found.
+    pub offset: Option<u64>,
+    source: Option<io::Error>,
+}
+
+impl RasterError {
+    pub fn new<S: Into<String>>(kind: RasterErrorKind, message: S) -> RasterError {
+        RasterError {
+            kind,
+            message: message.into(),
+            file_name: String::new(),
+            raster_type: RasterType::Unknown,
+            offset: None,
+            source: None,
+        }
+    }
+
+    /// Records the position in the file at which the error was found, unless it is
+    /// already known.
+    pub fn at_offset(mut self, offset: u64) -> RasterError {
+        if self.offset.is_none() {
+            self.offset = Some(offset);
+        }
+        self
+    }
+
+    /// Records the file, and its format, in which the error happened, unless they are
+    /// already known.
+    pub fn in_file(mut self, file_name: &str, raster_type: RasterType) -> RasterError {
+        if self.file_name.is_empty() {
+            self.file_name = file_name.to_string();
+            self.raster_type = raster_type;
+        }
+        self
+    }
+}
+
+impl fmt::Display for RasterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        if !self.file_name.is_empty() {
+            write!(f, " (file: {}, format: {:?}", self.file_name, self.raster_type)?;
+            if let Some(offset) = self.offset {
+                write!(f, ", byte offset: {}", offset)?;
+            }
+            write!(f, ")")?;
+        } else if let Some(offset) = self.offset {
+            write!(f, " (byte offset: {})", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for RasterError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        self.source.as_ref().map(|x| x as _)
+    }
+}
+
+impl From<io::Error> for RasterError {
+    fn from(e: io::Error) -> RasterError {
+        let kind = match e.kind() {
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => RasterErrorKind::InvalidData,
+            io::ErrorKind::InvalidInput => RasterErrorKind::InvalidInput,
+            _ => RasterErrorKind::Io,
+        };
+        let mut err = RasterError::new(kind, e.to_string());
+        err.source = Some(e);
+        err
+    }
+}
+
+/// Lets the tools, which report `std::io::Error`s, use `?` on the results of reading
+/// and writing rasters.
+impl From<RasterError> for io::Error {
+    fn from(e: RasterError) -> io::Error {
+        let kind = match e.kind {
+            RasterErrorKind::Io => match e.source {
+                Some(ref source) => source.kind(),
+                None => io::ErrorKind::Other,
+            },
+            RasterErrorKind::InvalidData => io::ErrorKind::InvalidData,
+            _ => io::ErrorKind::InvalidInput,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+/// Parses a number read from a text raster or header, e.g. the number of rows.
+pub fn parse_value<T: FromStr>(value: &str) -> Result<T, RasterError> {
+    match value.trim().parse::<T>() {
+        Ok(v) => Ok(v),
+        Err(_) => Err(RasterError::new(RasterErrorKind::InvalidData, format!("'{}' is not a valid number.", value.trim()))),
+    }
+}
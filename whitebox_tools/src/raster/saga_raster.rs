@@ -1,5 +1,3 @@
-use std::io::Error;
-use std::io::ErrorKind;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::prelude::*;
@@ -7,88 +5,17 @@ use std::io::SeekFrom;
 use std::path::Path;
 use std::f64;
 use std::fs::File;
-use std::mem;
-use raster::*;
-use io_utils::byte_order_reader::Endianness;
-
-pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec<f64>) -> Result<(), Error> {
-    // read the header file
-    let header_file = file_name.replace(".sdat", ".sgrd");
-    let f = try!(File::open(header_file));
-    let f = BufReader::new(f);
-    let mut data_file_offset = 0u64;
-    let mut top_to_bottom = false;
-    let mut z_factor = 1.0;
-    for line in f.lines() {
-        let line_unwrapped = line.unwrap();
-        //let line_split = line_unwrapped.split("\t");
-        let line_split = line_unwrapped.split("=");
-        let vec = line_split.collect::<Vec<&str>>();
-        if vec[0].to_lowercase().contains("name") {
-            configs.title = vec[1].replace("=", "").trim().to_string();
-        } else if vec[0].to_lowercase().contains("description") {
-            if vec[1].replace("=", "").trim() != "" {
-                configs.metadata.push(vec[1].trim().replace("=", "").to_string());
-            }
-        } else if vec[0].to_lowercase().contains("unit") {
-            if vec[1].replace("=", "").trim() != "" {
-                configs.xy_units = vec[1].trim().replace("=", "").to_string();
-            }
-        } else if vec[0].to_lowercase().contains("datafile_offset") {
-            data_file_offset = vec[1].replace("=", "").trim().to_string().parse::<u64>().unwrap();
-        } else if vec[0].to_lowercase().contains("dataformat") {
-            let data_format = vec[1].replace("=", "").trim().to_lowercase().to_string();
-            match &data_format[..] {
-                "bit" => {
-                    return Err(Error::new(ErrorKind::InvalidInput,
-                        "Reading of this kind of SAGA raster file is not currently supported"))
-                },
-                "byte_unsigned" => configs.data_type = DataType::U8,
-                "byte" => configs.data_type = DataType::U8,
-                "shortint_unsigned" => configs.data_type = DataType::U16,
-                "shortint" => configs.data_type = DataType::I16,
-                "integer_unsigned" => configs.data_type = DataType::U32,
-                "integer" => configs.data_type = DataType::I32,
-                "float" => configs.data_type = DataType::F32,
-                "double" => configs.data_type = DataType::F64,
-                _  => {
-                    return Err(Error::new(ErrorKind::InvalidInput,
-                        "Reading of this kind of SAGA raster file is not currently supported"))
-                }
-            }
-        } else if vec[0].to_lowercase().contains("byteorder_big") {
-            if vec[1].replace("=", "").trim().to_lowercase().contains("f") ||
-              vec[1].replace("=", "").trim().to_lowercase().contains("lsb") {
-                 configs.endian = Endianness::LittleEndian;
-             } else {
-                 configs.endian = Endianness::BigEndian;
-             }
-        } else if vec[0].to_lowercase().contains("position_xmin") {
-            configs.west = vec[1].replace("=", "").trim().to_string().parse::<f64>().unwrap();
-        } else if vec[0].to_lowercase().contains("position_ymin") {
-            configs.south = vec[1].replace("=", "").trim().to_string().parse::<f64>().unwrap();
-        } else if vec[0].to_lowercase().contains("cellcount_x") {
-            configs.columns = vec[1].replace("=", "").trim().to_string().parse::<usize>().unwrap();
-        } else if vec[0].to_lowercase().contains("cellcount_y") {
-            configs.rows = vec[1].replace("=", "").trim().to_string().parse::<usize>().unwrap();
-        } else if vec[0].to_lowercase().contains("cellsize") {
-            configs.resolution_x = vec[1].replace("=", "").trim().to_string().parse::<f64>().unwrap();
-            configs.resolution_y = vec[1].replace("=", "").trim().to_string().parse::<f64>().unwrap();
-        } else if vec[0].to_lowercase().contains("z_factor") {
-            z_factor = vec[1].replace("=", "").trim().to_string().parse::<f64>().unwrap();
-        } else if vec[0].to_lowercase().contains("nodata_value") {
-            configs.nodata = vec[1].replace("=", "").trim().to_string().parse::<f64>().unwrap();
-        } else if vec[0].to_lowercase().contains("toptobottom") {
-            top_to_bottom = vec[1].replace("=", "").trim().to_lowercase().contains("t")
-        }
-    }
-
-    configs.north = configs.south + configs.resolution_y * configs.rows as f64;
-    configs.east = configs.west + configs.resolution_x * configs.columns as f64;
-
-    if z_factor < 0.0 && (configs.data_type == DataType::F32 || configs.data_type == DataType::F64) {
-        configs.data_type = DataType::F32;
-    }
+use crate::raster::*;
+use crate::raster::raster_data::RasterData;
+use crate::raster::raster_error::*;
+use crate::raster::window::*;
+use crate::io_utils::byte_order_reader::Endianness;
+use crate::io_utils::read_fully;
+
+pub fn read_saga(file_name: &str, configs: &mut RasterConfigs, data: &mut RasterData) -> Result<(), RasterError> {
+    let layout = read_saga_header(file_name, configs)?;
+    layout.check_data_file(configs)?;
+    let (data_file_offset, top_to_bottom, z_factor) = (layout.offset, layout.top_to_bottom, layout.z_factor);
 
     let mut row_start = 0;
     if !top_to_bottom {
@@ -97,8 +24,8 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
 
     // read the data file
     let data_file = file_name.replace(".sgrd", ".sdat");
-    let mut f = try!(File::open(data_file.clone()));
-    try!(f.seek(SeekFrom::Start(data_file_offset)));
+    let mut f = File::open(data_file.clone())?;
+    f.seek(SeekFrom::Start(data_file_offset))?;
 
     let data_size = if configs.data_type == DataType::F64 {
         8
@@ -114,11 +41,9 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
     };
 
     let num_cells = configs.rows * configs.columns;
-    //data = vec![configs.nodata; num_cells];
-    data.clear();
-    for _ in 0..num_cells {
-        data.push(configs.nodata);
-    }
+    // scaled values are no longer of the file's data type
+    let storage_type = if z_factor == 1f64 { configs.data_type } else { DataType::F64 };
+    *data = RasterData::for_configs(configs, storage_type);
 
     let buf_size = 1_000_000usize;
     let mut j = 0;
@@ -128,7 +53,7 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
     while j < num_cells {
         let mut buffer = vec![0; buf_size * data_size];
 
-        try!(f.read(&mut buffer));
+        read_fully(&mut f, &mut buffer)?;
 
         let mut offset: usize;
         match configs.data_type {
@@ -136,9 +61,9 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
                 for i in 0..buf_size {
                     offset = i * data_size;
                     k = row * configs.columns + col;
-                    data[k] = unsafe { mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1],
+                    data.set_value(k, f64::from_ne_bytes([buffer[offset], buffer[offset+1],
                         buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5],
-                        buffer[offset+6], buffer[offset+7]])} * z_factor;
+                        buffer[offset+6], buffer[offset+7]]) * z_factor);
                     col += 1;
                     if col >= configs.columns {
                         col = 0;
@@ -156,8 +81,8 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
                 for i in 0..buf_size {
                     offset = i * data_size;
                     k = row * configs.columns + col;
-                    data[k] = unsafe { mem::transmute::<[u8; 4], f32>([buffer[offset], buffer[offset+1],
-                        buffer[offset+2], buffer[offset+3]])} as f64 * z_factor;
+                    data.set_value(k, f32::from_ne_bytes([buffer[offset], buffer[offset+1],
+                        buffer[offset+2], buffer[offset+3]]) as f64 * z_factor);
                     col += 1;
                     if col >= configs.columns {
                         col = 0;
@@ -175,8 +100,8 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
                 for i in 0..buf_size {
                     offset = i * data_size;
                     k = row * configs.columns + col;
-                    data[k] = unsafe { mem::transmute::<[u8; 4], i32>([buffer[offset], buffer[offset+1],
-                        buffer[offset+2], buffer[offset+3]])} as f64 * z_factor;
+                    data.set_value(k, i32::from_ne_bytes([buffer[offset], buffer[offset+1],
+                        buffer[offset+2], buffer[offset+3]]) as f64 * z_factor);
                     col += 1;
                     if col >= configs.columns {
                         col = 0;
@@ -194,8 +119,8 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
                 for i in 0..buf_size {
                     offset = i * data_size;
                     k = row * configs.columns + col;
-                    data[k] = unsafe { mem::transmute::<[u8; 4], u32>([buffer[offset], buffer[offset+1],
-                        buffer[offset+2], buffer[offset+3]])} as f64 * z_factor;
+                    data.set_value(k, u32::from_ne_bytes([buffer[offset], buffer[offset+1],
+                        buffer[offset+2], buffer[offset+3]]) as f64 * z_factor);
                     col += 1;
                     if col >= configs.columns {
                         col = 0;
@@ -213,7 +138,7 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
                 for i in 0..buf_size {
                     offset = i * data_size;
                     k = row * configs.columns + col;
-                    data[k] = unsafe { mem::transmute::<[u8; 2], i16>([buffer[offset], buffer[offset+1]])} as f64 * z_factor;
+                    data.set_value(k, i16::from_ne_bytes([buffer[offset], buffer[offset+1]]) as f64 * z_factor);
                     col += 1;
                     if col >= configs.columns {
                         col = 0;
@@ -231,7 +156,7 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
                 for i in 0..buf_size {
                     offset = i * data_size;
                     k = row * configs.columns + col;
-                    data[k] = unsafe { mem::transmute::<[u8; 2], u16>([buffer[offset], buffer[offset+1]])} as f64 * z_factor;
+                    data.set_value(k, u16::from_ne_bytes([buffer[offset], buffer[offset+1]]) as f64 * z_factor);
                     col += 1;
                     if col >= configs.columns {
                         col = 0;
@@ -248,7 +173,7 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
             DataType::I8 => {
                 for i in 0..buf_size {
                     k = row * configs.columns + col;
-                    data[k] = (buffer[i] as i8) as f64 * z_factor;
+                    data.set_value(k, (buffer[i] as i8) as f64 * z_factor);
                     col += 1;
                     if col >= configs.columns {
                         col = 0;
@@ -265,7 +190,7 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
             DataType::U8 => {
                 for i in 0..buf_size {
                     k = row * configs.columns + col;
-                    data[k] = buffer[i] as f64 * z_factor;
+                    data.set_value(k, buffer[i] as f64 * z_factor);
                     col += 1;
                     if col >= configs.columns {
                         col = 0;
@@ -280,7 +205,7 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
                 }
             },
             _ => {
-                return Err(Error::new(ErrorKind::NotFound, "Raster data type is unknown."));
+                return Err(RasterError::new(RasterErrorKind::Unsupported, "Raster data type is unknown."));
             },
         }
     }
@@ -288,10 +213,100 @@ pub fn read_saga(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec
     Ok(())
 }
 
-pub fn write_saga<'a>(r: &'a mut Raster) -> Result<(), Error> {
+/// Reads the header (.sgrd) of a SAGA raster, returning the layout of its data file.
+pub fn read_saga_header(file_name: &str, configs: &mut RasterConfigs) -> Result<RawLayout, RasterError> {
+    // read the header file
+    let header_file = file_name.replace(".sdat", ".sgrd");
+    let f = File::open(header_file)?;
+    let f = BufReader::new(f);
+    let mut data_file_offset = 0u64;
+    let mut top_to_bottom = false;
+    let mut z_factor = 1.0;
+    for line in f.lines() {
+        let line_unwrapped = line?;
+        //let line_split = line_unwrapped.split("\t");
+        let line_split = line_unwrapped.split("=");
+        let vec = line_split.collect::<Vec<&str>>();
+        if vec[0].to_lowercase().contains("name") {
+            configs.title = vec[1].replace("=", "").trim().to_string();
+        } else if vec[0].to_lowercase().contains("description") {
+            if vec[1].replace("=", "").trim() != "" {
+                configs.metadata.push(vec[1].trim().replace("=", "").to_string());
+            }
+        } else if vec[0].to_lowercase().contains("unit") {
+            if vec[1].replace("=", "").trim() != "" {
+                configs.xy_units = vec[1].trim().replace("=", "").to_string();
+            }
+        } else if vec[0].to_lowercase().contains("datafile_offset") {
+            data_file_offset = parse_value::<u64>(&vec[1].replace("=", ""))?;
+        } else if vec[0].to_lowercase().contains("dataformat") {
+            let data_format = vec[1].replace("=", "").trim().to_lowercase().to_string();
+            match &data_format[..] {
+                "bit" => {
+                    return Err(RasterError::new(RasterErrorKind::Unsupported,
+                        "Reading of this kind of SAGA raster file is not currently supported"))
+                },
+                "byte_unsigned" => configs.data_type = DataType::U8,
+                "byte" => configs.data_type = DataType::U8,
+                "shortint_unsigned" => configs.data_type = DataType::U16,
+                "shortint" => configs.data_type = DataType::I16,
+                "integer_unsigned" => configs.data_type = DataType::U32,
+                "integer" => configs.data_type = DataType::I32,
+                "float" => configs.data_type = DataType::F32,
+                "double" => configs.data_type = DataType::F64,
+                _  => {
+                    return Err(RasterError::new(RasterErrorKind::Unsupported,
+                        "Reading of this kind of SAGA raster file is not currently supported"))
+                }
+            }
+        } else if vec[0].to_lowercase().contains("byteorder_big") {
+            if vec[1].replace("=", "").trim().to_lowercase().contains("f") ||
+              vec[1].replace("=", "").trim().to_lowercase().contains("lsb") {
+                 configs.endian = Endianness::LittleEndian;
+             } else {
+                 configs.endian = Endianness::BigEndian;
+             }
+        } else if vec[0].to_lowercase().contains("position_xmin") {
+            configs.west = parse_value::<f64>(&vec[1].replace("=", ""))?;
+        } else if vec[0].to_lowercase().contains("position_ymin") {
+            configs.south = parse_value::<f64>(&vec[1].replace("=", ""))?;
+        } else if vec[0].to_lowercase().contains("cellcount_x") {
+            configs.columns = parse_value::<usize>(&vec[1].replace("=", ""))?;
+        } else if vec[0].to_lowercase().contains("cellcount_y") {
+            configs.rows = parse_value::<usize>(&vec[1].replace("=", ""))?;
+        } else if vec[0].to_lowercase().contains("cellsize") {
+            configs.resolution_x = parse_value::<f64>(&vec[1].replace("=", ""))?;
+            configs.resolution_y = parse_value::<f64>(&vec[1].replace("=", ""))?;
+        } else if vec[0].to_lowercase().contains("z_factor") {
+            z_factor = parse_value::<f64>(&vec[1].replace("=", ""))?;
+        } else if vec[0].to_lowercase().contains("nodata_value") {
+            configs.nodata = parse_value::<f64>(&vec[1].replace("=", ""))?;
+        } else if vec[0].to_lowercase().contains("toptobottom") {
+            top_to_bottom = vec[1].replace("=", "").trim().to_lowercase().contains("t")
+        }
+    }
+
+    configs.north = configs.south + configs.resolution_y * configs.rows as f64;
+    configs.east = configs.west + configs.resolution_x * configs.columns as f64;
+
+    if z_factor < 0.0 && (configs.data_type == DataType::F32 || configs.data_type == DataType::F64) {
+        configs.data_type = DataType::F32;
+    }
+
+    Ok(RawLayout {
+        data_file: file_name.replace(".sgrd", ".sdat"),
+        offset: data_file_offset,
+        data_type: configs.data_type,
+        endian: configs.endian,
+        top_to_bottom,
+        z_factor,
+    })
+}
+
+pub fn write_saga(r: &mut Raster) -> Result<(), RasterError> {
     // figure out the minimum and maximum values
-    for val in &r.data {
-        let v = *val;
+    for i in 0..r.data.len() {
+        let v = r.data.get_value(i);
         if v != r.configs.nodata {
             if v < r.configs.minimum { r.configs.minimum = v; }
             if v > r.configs.maximum { r.configs.maximum = v; }
@@ -307,7 +322,7 @@ pub fn write_saga<'a>(r: &'a mut Raster) -> Result<(), Error> {
 
     // Save the header file
     let header_file = r.file_name.replace(".sdat", ".sgrd");
-    let f = try!(File::create(header_file.clone()));
+    let f = File::create(header_file.clone())?;
     let mut writer = BufWriter::new(f);
 
     // get the short file NAME
@@ -316,80 +331,80 @@ pub fn write_saga<'a>(r: &'a mut Raster) -> Result<(), Error> {
         None => "".to_string(),
     };
 
-    try!(writer.write_all(format!("NAME\t= {}\n", short_name).as_bytes()));
+    writer.write_all(format!("NAME\t= {}\n", short_name).as_bytes())?;
 
-    if r.configs.metadata.len() > 0 {
-        try!(writer.write_all(format!("DESCRIPTION\t= {}\n", r.configs.metadata[0]).as_bytes()));
+    if !r.configs.metadata.is_empty() {
+        writer.write_all(format!("DESCRIPTION\t= {}\n", r.configs.metadata[0]).as_bytes())?;
     } else {
-        try!(writer.write_all("DESCRIPTION\t=\n".as_bytes()));
+        writer.write_all("DESCRIPTION\t=\n".as_bytes())?;
     }
 
     if r.configs.xy_units != "not specified" {
-        try!(writer.write_all(format!("UNIT\t= {}\n", r.configs.xy_units).as_bytes()));
+        writer.write_all(format!("UNIT\t= {}\n", r.configs.xy_units).as_bytes())?;
     } else {
-        try!(writer.write_all("UNIT\t=\n".as_bytes()));
+        writer.write_all("UNIT\t=\n".as_bytes())?;
     }
 
-    try!(writer.write_all("DATAFILE_OFFSET\t= 0\n".as_bytes()));
+    writer.write_all("DATAFILE_OFFSET\t= 0\n".as_bytes())?;
 
     match r.configs.data_type {
         DataType::F64 => {
-            try!(writer.write_all("DATAFORMAT\t= DOUBLE\n".as_bytes()));
+            writer.write_all("DATAFORMAT\t= DOUBLE\n".as_bytes())?;
         },
         DataType::F32 => {
-            try!(writer.write_all("DATAFORMAT\t= FLOAT\n".as_bytes()));
+            writer.write_all("DATAFORMAT\t= FLOAT\n".as_bytes())?;
         },
         DataType::I32 => {
-            try!(writer.write_all("DATAFORMAT\t= INTEGER\n".as_bytes()));
+            writer.write_all("DATAFORMAT\t= INTEGER\n".as_bytes())?;
         },
         DataType::U32 => {
-            try!(writer.write_all("DATAFORMAT\t= INTEGER_UNSIGNED\n".as_bytes()));
+            writer.write_all("DATAFORMAT\t= INTEGER_UNSIGNED\n".as_bytes())?;
         },
         DataType::I16 => {
-            try!(writer.write_all("DATAFORMAT\t= SHORTINT\n".as_bytes()));
+            writer.write_all("DATAFORMAT\t= SHORTINT\n".as_bytes())?;
         },
         DataType::U16 => {
-            try!(writer.write_all("DATAFORMAT\t= SHORTINT_UNSIGNED\n".as_bytes()));
+            writer.write_all("DATAFORMAT\t= SHORTINT_UNSIGNED\n".as_bytes())?;
         },
         DataType::U8 => {
-            try!(writer.write_all("DATAFORMAT\t= BYTE_UNSIGNED\n".as_bytes()));
+            writer.write_all("DATAFORMAT\t= BYTE_UNSIGNED\n".as_bytes())?;
         },
         DataType::I8 => {
-            try!(writer.write_all("DATAFORMAT\t= BYTE\n".as_bytes()));
+            writer.write_all("DATAFORMAT\t= BYTE\n".as_bytes())?;
         },
         _ => {
-            return Err(Error::new(ErrorKind::NotFound, "Raster Data Type not specified."));
+            return Err(RasterError::new(RasterErrorKind::Unsupported, "Raster Data Type not specified."));
         },
     }
 
     if r.configs.endian == Endianness::LittleEndian {
-        try!(writer.write_all("BYTEORDER_BIG\t= FALSE\n".as_bytes()));
+        writer.write_all("BYTEORDER_BIG\t= FALSE\n".as_bytes())?;
     } else {
-        try!(writer.write_all("BYTEORDER_BIG\t= TRUE\n".as_bytes()));
+        writer.write_all("BYTEORDER_BIG\t= TRUE\n".as_bytes())?;
     }
 
-    try!(writer.write_all(format!("POSITION_XMIN\t= {}\n", r.configs.west).as_bytes()));
+    writer.write_all(format!("POSITION_XMIN\t= {}\n", r.configs.west).as_bytes())?;
 
-    try!(writer.write_all(format!("POSITION_YMIN\t= {}\n", r.configs.south).as_bytes()));
+    writer.write_all(format!("POSITION_YMIN\t= {}\n", r.configs.south).as_bytes())?;
 
-    try!(writer.write_all(format!("CELLCOUNT_X\t= {}\n", r.configs.columns).as_bytes()));
+    writer.write_all(format!("CELLCOUNT_X\t= {}\n", r.configs.columns).as_bytes())?;
 
-    try!(writer.write_all(format!("CELLCOUNT_Y\t= {}\n", r.configs.rows).as_bytes()));
+    writer.write_all(format!("CELLCOUNT_Y\t= {}\n", r.configs.rows).as_bytes())?;
 
-    try!(writer.write_all(format!("CELLSIZE\t= {}\n", (r.configs.resolution_x + r.configs.resolution_y) / 2.0).as_bytes()));
+    writer.write_all(format!("CELLSIZE\t= {}\n", (r.configs.resolution_x + r.configs.resolution_y) / 2.0).as_bytes())?;
 
-    try!(writer.write_all("Z_FACTOR\t= 1.000000\n".as_bytes()));
+    writer.write_all("Z_FACTOR\t= 1.000000\n".as_bytes())?;
 
-    try!(writer.write_all(format!("NODATA_VALUE\t= {}\n", r.configs.nodata).as_bytes()));
+    writer.write_all(format!("NODATA_VALUE\t= {}\n", r.configs.nodata).as_bytes())?;
 
-    try!(writer.write_all("TOPTOBOTTOM\t= FALSE\n".as_bytes()));
+    writer.write_all("TOPTOBOTTOM\t= FALSE\n".as_bytes())?;
 
-    let _ = writer.flush();
+    writer.flush()?;
 
 
     // write the data file
     let data_file = r.file_name.replace(".sgrd", ".sdat");
-    let f = try!(File::create(&data_file));
+    let f = File::create(&data_file)?;
     let mut writer = BufWriter::new(f);
 
     let mut u16_bytes: [u8; 2];
@@ -401,8 +416,8 @@ pub fn write_saga<'a>(r: &'a mut Raster) -> Result<(), Error> {
             for row in (0..r.configs.rows).rev() {
                 for col in 0..r.configs.columns {
                     i = row * r.configs.columns + col;
-                    u64_bytes = unsafe { mem::transmute(r.data[i]) };
-                    try!(writer.write(&u64_bytes));
+                    u64_bytes = f64::to_ne_bytes(r.data.get_value(i));
+                    writer.write_all(&u64_bytes)?;
                 }
             }
         },
@@ -410,8 +425,8 @@ pub fn write_saga<'a>(r: &'a mut Raster) -> Result<(), Error> {
             for row in (0..r.configs.rows).rev() {
                 for col in 0..r.configs.columns {
                     i = row * r.configs.columns + col;
-                    u32_bytes = unsafe { mem::transmute(r.data[i] as f32) };
-                    try!(writer.write(&u32_bytes));
+                    u32_bytes = f32::to_ne_bytes(r.data.get_value(i) as f32);
+                    writer.write_all(&u32_bytes)?;
                 }
             }
         },
@@ -419,8 +434,8 @@ pub fn write_saga<'a>(r: &'a mut Raster) -> Result<(), Error> {
             for row in (0..r.configs.rows).rev() {
                 for col in 0..r.configs.columns {
                     i = row * r.configs.columns + col;
-                    u32_bytes = unsafe { mem::transmute(r.data[i] as i32) };
-                    try!(writer.write(&u32_bytes));
+                    u32_bytes = i32::to_ne_bytes(r.data.get_value(i) as i32);
+                    writer.write_all(&u32_bytes)?;
                 }
             }
         },
@@ -428,8 +443,8 @@ pub fn write_saga<'a>(r: &'a mut Raster) -> Result<(), Error> {
             for row in (0..r.configs.rows).rev() {
                 for col in 0..r.configs.columns {
                     i = row * r.configs.columns + col;
-                    u32_bytes = unsafe { mem::transmute(r.data[i] as u32) };
-                    try!(writer.write(&u32_bytes));
+                    u32_bytes = u32::to_ne_bytes(r.data.get_value(i) as u32);
+                    writer.write_all(&u32_bytes)?;
                 }
             }
         },
@@ -437,8 +452,8 @@ pub fn write_saga<'a>(r: &'a mut Raster) -> Result<(), Error> {
             for row in (0..r.configs.rows).rev() {
                 for col in 0..r.configs.columns {
                     i = row * r.configs.columns + col;
-                    u16_bytes = unsafe { mem::transmute(r.data[i] as i16) };
-                    try!(writer.write(&u16_bytes));
+                    u16_bytes = i16::to_ne_bytes(r.data.get_value(i) as i16);
+                    writer.write_all(&u16_bytes)?;
                 }
             }
         },
@@ -446,8 +461,8 @@ pub fn write_saga<'a>(r: &'a mut Raster) -> Result<(), Error> {
             for row in (0..r.configs.rows).rev() {
                 for col in 0..r.configs.columns {
                     i = row * r.configs.columns + col;
-                    u16_bytes = unsafe { mem::transmute(r.data[i] as u16) };
-                    try!(writer.write(&u16_bytes));
+                    u16_bytes = u16::to_ne_bytes(r.data.get_value(i) as u16);
+                    writer.write_all(&u16_bytes)?;
                 }
             }
         },
@@ -455,16 +470,16 @@ pub fn write_saga<'a>(r: &'a mut Raster) -> Result<(), Error> {
             for row in (0..r.configs.rows).rev() {
                 for col in 0..r.configs.columns {
                     i = row * r.configs.columns + col;
-                    try!(writer.write(&[r.data[i] as u8]));
+                    writer.write_all(&[r.data.get_value(i) as u8])?;
                 }
             }
         },
         _ => {
-            return Err(Error::new(ErrorKind::NotFound, "Raster data type is unsupported."));
+            return Err(RasterError::new(RasterErrorKind::Unsupported, "Raster data type is unsupported."));
         },
     }
 
-    let _ = writer.flush();
+    writer.flush()?;
 
     Ok(())
 }
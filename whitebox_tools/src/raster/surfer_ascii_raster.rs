@@ -1,15 +1,15 @@
-use std::io::Error;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::prelude::*;
-use std::io::ErrorKind;
 use std::f64;
 use std::fs::File;
-use raster::*;
+use crate::raster::*;
+use crate::raster::raster_data::RasterData;
+use crate::raster::raster_error::*;
 
-pub fn read_surfer_ascii_raster(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec<f64>) -> Result<(), Error> {
+pub fn read_surfer_ascii_raster(file_name: &String, configs: &mut RasterConfigs, data: &mut RasterData) -> Result<(), RasterError> {
     // read the file
-    let f = try!(File::open(file_name));
+    let f = File::open(file_name)?;
     let f = BufReader::new(f);
 
     configs.nodata = 1.71041e38;
@@ -18,9 +18,8 @@ pub fn read_surfer_ascii_raster(file_name: &String, configs: &mut RasterConfigs,
     let mut col = 0usize;
     let mut cell_count = 0usize;
     let mut num_cells = 0usize;
-    let mut line_num = 0;
-    for line in f.lines() {
-        let line_unwrapped = line.unwrap();
+    for (line_num, line) in f.lines().enumerate() {
+        let line_unwrapped = line?;
         let mut line_split = line_unwrapped.split(" ");
         let mut vec = line_split.collect::<Vec<&str>>();
         if vec.is_empty() && line_num > 0 {
@@ -30,38 +29,35 @@ pub fn read_surfer_ascii_raster(file_name: &String, configs: &mut RasterConfigs,
         if line_num == 0 {
             // this line should contain the string DSAA; if not, there is a problem.
             if !vec[0].to_lowercase().contains("dsaa") {
-                return Err(Error::new(ErrorKind::InvalidData, "The Surfer file appears to be improperly formated."));
+                return Err(RasterError::new(RasterErrorKind::InvalidData, "The Surfer file appears to be improperly formated."));
             }
         } else if line_num == 1 {
             if vec.len() != 2 {
-                return Err(Error::new(ErrorKind::InvalidData, "The Surfer file appears to be improperly formated."));
+                return Err(RasterError::new(RasterErrorKind::InvalidData, "The Surfer file appears to be improperly formated."));
             }
-            configs.columns = vec[0].trim().to_string().parse::<usize>().unwrap();
-            configs.rows = vec[1].trim().to_string().parse::<usize>().unwrap();
+            configs.columns = parse_value::<usize>(vec[0])?;
+            configs.rows = parse_value::<usize>(vec[1])?;
             row = configs.rows - 1; // files are stored row major, bottom-to-top
             num_cells = configs.rows * configs.columns;
-            data.clear();
-            for _ in 0..num_cells {
-                data.push(configs.nodata);
-            }
+            *data = RasterData::for_configs(configs, configs.data_type);
         } else if line_num == 2 {
             if vec.len() != 2 {
-                return Err(Error::new(ErrorKind::InvalidData, "The Surfer file appears to be improperly formated."));
+                return Err(RasterError::new(RasterErrorKind::InvalidData, "The Surfer file appears to be improperly formated."));
             }
-            configs.west = vec[0].trim().to_string().parse::<f64>().unwrap();
-            configs.east = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.west = parse_value::<f64>(vec[0])?;
+            configs.east = parse_value::<f64>(vec[1])?;
         } else if line_num == 3 {
             if vec.len() != 2 {
-                return Err(Error::new(ErrorKind::InvalidData, "The Surfer file appears to be improperly formated."));
+                return Err(RasterError::new(RasterErrorKind::InvalidData, "The Surfer file appears to be improperly formated."));
             }
-            configs.south = vec[0].trim().to_string().parse::<f64>().unwrap();
-            configs.north = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.south = parse_value::<f64>(vec[0])?;
+            configs.north = parse_value::<f64>(vec[1])?;
         } else if line_num == 4 {
             if vec.len() != 2 {
-                return Err(Error::new(ErrorKind::InvalidData, "The Surfer file appears to be improperly formated."));
+                return Err(RasterError::new(RasterErrorKind::InvalidData, "The Surfer file appears to be improperly formated."));
             }
-            configs.minimum = vec[0].trim().to_string().parse::<f64>().unwrap();
-            configs.maximum = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.minimum = parse_value::<f64>(vec[0])?;
+            configs.maximum = parse_value::<f64>(vec[1])?;
         } else { // it's a data line
             let mut val_num;
             let mut i;
@@ -72,10 +68,10 @@ pub fn read_surfer_ascii_raster(file_name: &String, configs: &mut RasterConfigs,
                 } else {
                     i = row * configs.columns + col;
                     if !val.contains("1.71041e38") {
-                        val_num = val.trim().to_string().parse::<f64>().unwrap();
-                        data[i] = val_num;
+                        val_num = parse_value::<f64>(val)?;
+                        data.set_value(i, val_num);
                     } else {
-                        data[i] = configs.nodata;
+                        data.set_value(i, configs.nodata);
                     }
                     col += 1;
                     if col == configs.columns {
@@ -85,7 +81,6 @@ pub fn read_surfer_ascii_raster(file_name: &String, configs: &mut RasterConfigs,
                 }
             }
         }
-        line_num += 1;
     }
 
     configs.resolution_x = (configs.east - configs.west) / configs.columns as f64;
@@ -94,13 +89,13 @@ pub fn read_surfer_ascii_raster(file_name: &String, configs: &mut RasterConfigs,
     Ok(())
 }
 
-pub fn write_surfer_ascii_raster<'a>(r: &'a mut Raster) -> Result<(), Error> {
+pub fn write_surfer_ascii_raster(r: &mut Raster) -> Result<(), RasterError> {
 
     if r.configs.nodata != 1.71041e38 { r.configs.nodata = 1.71041e38; }
 
     // figure out the minimum and maximum values
-    for val in &r.data {
-        let v = *val;
+    for i in 0..r.data.len() {
+        let v = r.data.get_value(i);
         if v != r.configs.nodata {
             if v < r.configs.minimum { r.configs.minimum = v; }
             if v > r.configs.maximum { r.configs.maximum = v; }
@@ -108,14 +103,14 @@ pub fn write_surfer_ascii_raster<'a>(r: &'a mut Raster) -> Result<(), Error> {
     }
 
     // Save the file
-    let f = try!(File::create(&(r.file_name)));
+    let f = File::create(&(r.file_name))?;
     let mut writer = BufWriter::new(f);
 
-    try!(writer.write_all("DSAA\n".as_bytes()));
-    try!(writer.write_all(format!("{} {}\n", r.configs.columns, r.configs.rows).as_bytes()));
-    try!(writer.write_all(format!("{} {}\n", r.configs.west, r.configs.east).as_bytes()));
-    try!(writer.write_all(format!("{} {}\n", r.configs.south, r.configs.north).as_bytes()));
-    try!(writer.write_all(format!("{} {}\n", r.configs.minimum, r.configs.maximum).as_bytes()));
+    writer.write_all("DSAA\n".as_bytes())?;
+    writer.write_all(format!("{} {}\n", r.configs.columns, r.configs.rows).as_bytes())?;
+    writer.write_all(format!("{} {}\n", r.configs.west, r.configs.east).as_bytes())?;
+    writer.write_all(format!("{} {}\n", r.configs.south, r.configs.north).as_bytes())?;
+    writer.write_all(format!("{} {}\n", r.configs.minimum, r.configs.maximum).as_bytes())?;
 
     // write the data
     let mut s2 = String::new();
@@ -128,24 +123,24 @@ pub fn write_surfer_ascii_raster<'a>(r: &'a mut Raster) -> Result<(), Error> {
         for col in 0..r.configs.columns {
             let i = row * r.configs.columns + col;
             if col < r.configs.columns - 1 {
-                if r.data[i] != r.configs.nodata {
-                    s2 += &format!("{:.*} ", num_decimals, r.data[i]);
+                if r.data.get_value(i) != r.configs.nodata {
+                    s2 += &format!("{:.*} ", num_decimals, r.data.get_value(i));
                 } else {
-                    s2 += &format!("1.71041e38 ");
+                    s2 += "1.71041e38 ";
                 }
             } else {
-                if r.data[i] != r.configs.nodata {
-                    s2 += &format!("{:.*}\n", num_decimals, r.data[i]);
+                if r.data.get_value(i) != r.configs.nodata {
+                    s2 += &format!("{:.*}\n", num_decimals, r.data.get_value(i));
                 } else {
-                    s2 += &format!("1.71041e38\n");
+                    s2 += "1.71041e38\n";
                 }
             }
         }
-        try!(writer.write_all(s2.as_bytes()));
+        writer.write_all(s2.as_bytes())?;
         s2 = String::new();
     }
 
-    let _ = writer.flush();
+    writer.flush()?;
 
     Ok(())
 }
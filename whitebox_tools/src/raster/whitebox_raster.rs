@@ -1,47 +1,132 @@
-use std::io::Error;
-use std::io::ErrorKind;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::prelude::*;
 use std::f64;
 use std::fs::File;
-use std::mem;
-use raster::*;
-use io_utils::byte_order_reader::Endianness;
+use std::cmp::max;
+use crate::raster::*;
+use crate::raster::raster_data::RasterData;
+use crate::raster::raster_error::*;
+use crate::raster::window::*;
+use crate::io_utils::byte_order_reader::Endianness;
+use crate::io_utils::read_fully;
+
+pub fn read_whitebox(file_name: &str, configs: &mut RasterConfigs, data: &mut RasterData) -> Result<(), RasterError> {
+    let layout = read_whitebox_header(file_name, configs)?;
+    layout.check_data_file(configs)?;
 
-pub fn read_whitebox(file_name: &String, configs: &mut RasterConfigs, data: &mut Vec<f64>) -> Result<(), Error> {
+    // read the data file
+    let data_file = file_name.replace(".dep", ".tas");
+    let mut f = File::open(data_file.clone())?;
+    //let br = BufReader::new(f);
+    // let metadata = fs::metadata(data_file.clone())?;
+    // let file_size: usize = metadata.len() as usize;
+    // let mut buffer = vec![0; file_size];
+
+    let data_size = if configs.data_type == DataType::F64 {
+        8
+    } else if configs.data_type == DataType::F32 {
+        4
+    } else if configs.data_type == DataType::I16 {
+        2
+    } else { // DataType::Byte
+        1
+    };
+
+    // the stacks (bands) follow one another
+    let num_cells = configs.rows * configs.columns * max(configs.bands, 1);
+    *data = RasterData::for_configs(configs, configs.data_type);
+    let buf_size = 1_000_000usize;
+    let mut j = 0;
+    while j < num_cells {
+        let mut buffer = vec![0; buf_size * data_size];
+
+        read_fully(&mut f, &mut buffer)?;
+
+        // read the file's bytes into a buffer
+        //f.read_to_end(&mut buffer)?;
+
+        //br.fill_buf().unwrap()(&mut buffer)?;
+
+        let mut offset: usize;
+        match configs.data_type {
+            DataType::F64 => {
+                for i in 0..buf_size {
+                    offset = i * data_size;
+                    data.set_value(j, f64::from_ne_bytes([buffer[offset], buffer[offset+1],
+                        buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5],
+                        buffer[offset+6], buffer[offset+7]]));
+                    j += 1;
+                    if j == num_cells { break; }
+                }
+            },
+            DataType::F32 => {
+                for i in 0..buf_size {
+                    offset = i * data_size;
+                    data.set_value(j, f32::from_ne_bytes([buffer[offset], buffer[offset+1],
+                        buffer[offset+2], buffer[offset+3]]) as f64);
+                    j += 1;
+                    if j == num_cells { break; }
+                }
+            },
+            DataType::I16 => {
+                for i in 0..buf_size {
+                    offset = i * data_size;
+                    data.set_value(j, i16::from_ne_bytes([buffer[offset], buffer[offset+1]]) as f64);
+                    j += 1;
+                    if j == num_cells { break; }
+                }
+            },
+            DataType::U8 => {
+                for i in 0..buf_size {
+                    data.set_value(j, buffer[i] as f64);
+                    j += 1;
+                    if j == num_cells { break; }
+                }
+            },
+            _ => {
+                return Err(RasterError::new(RasterErrorKind::Unsupported, "Raster data type is unknown."));
+            },
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads the header (.dep) of a Whitebox raster, returning the layout of its data file.
+pub fn read_whitebox_header(file_name: &str, configs: &mut RasterConfigs) -> Result<RawLayout, RasterError> {
     // read the header file
     let header_file = file_name.replace(".tas", ".dep");
     let f = File::open(header_file)?;
     let f = BufReader::new(f);
 
     for line in f.lines() {
-        let line_unwrapped = line.unwrap();
+        let line_unwrapped = line?;
         // println!("{}", line_unwrapped);
         let line_split = line_unwrapped.split(":");
         let vec = line_split.collect::<Vec<&str>>();
         if vec[0].to_lowercase().contains("rows") {
-            configs.rows = vec[1].trim().to_string().parse::<usize>().unwrap();
+            configs.rows = parse_value::<usize>(vec[1])?;
         } else if vec[0].to_lowercase().contains("col") {
-            configs.columns = vec[1].trim().to_string().parse::<usize>().unwrap();
+            configs.columns = parse_value::<usize>(vec[1])?;
         } else if vec[0].to_lowercase().contains("stacks") {
-            configs.bands = vec[1].trim().to_string().parse::<u8>().unwrap();
+            configs.bands = parse_value::<usize>(vec[1])?;
         } else if vec[0].to_lowercase().contains("north") {
-            configs.north = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.north = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("south") {
-            configs.south = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.south = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("east") {
-            configs.east = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.east = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("west") {
-            configs.west = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.west = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("display min") {
-            configs.display_min = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.display_min = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("display max") {
-            configs.display_max = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.display_max = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("min") && !vec[0].to_lowercase().contains("display") {
-            configs.minimum = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.minimum = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("max") && !vec[0].to_lowercase().contains("display") {
-            configs.maximum = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.maximum = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("data type") {
             if vec[1].trim().to_lowercase().to_string().contains("double") {
                 configs.data_type = DataType::F64;
@@ -70,11 +155,11 @@ pub fn read_whitebox(file_name: &String, configs: &mut RasterConfigs, data: &mut
         } else if vec[0].to_lowercase().contains("projection") {
             configs.projection = vec[1].trim().to_string();
         } else if vec[0].to_lowercase().contains("nodata") {
-            configs.nodata = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.nodata = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("preferred palette") {
             configs.palette = vec[1].trim().to_string();
         } else if vec[0].to_lowercase().contains("nonlinearity") {
-            configs.palette_nonlinearity = vec[1].trim().to_string().parse::<f64>().unwrap();
+            configs.palette_nonlinearity = parse_value::<f64>(vec[1])?;
         } else if vec[0].to_lowercase().contains("byte order") {
             if vec[1].trim().to_lowercase().contains("little") ||
               vec[1].trim().to_lowercase().contains("lsb") {
@@ -90,132 +175,115 @@ pub fn read_whitebox(file_name: &String, configs: &mut RasterConfigs, data: &mut
     configs.resolution_x = (configs.east - configs.west) / configs.columns as f64;
     configs.resolution_y = (configs.north - configs.south) / configs.rows as f64;
 
-    // read the data file
-    let data_file = file_name.replace(".dep", ".tas");
-    let mut f = File::open(data_file.clone())?;
-    //let br = BufReader::new(f);
-    // let metadata = try!(fs::metadata(data_file.clone()));
-    // let file_size: usize = metadata.len() as usize;
-    // let mut buffer = vec![0; file_size];
-
-    let data_size = if configs.data_type == DataType::F64 {
-        8
-    } else if configs.data_type == DataType::F32 {
-        4
-    } else if configs.data_type == DataType::I16 {
-        2
-    } else { // DataType::Byte
-        1
-    };
+    Ok(RawLayout {
+        data_file: file_name.replace(".dep", ".tas"),
+        offset: 0,
+        data_type: configs.data_type,
+        endian: configs.endian,
+        top_to_bottom: true,
+        z_factor: 1f64,
+    })
+}
 
-    let num_cells = configs.rows * configs.columns;
-    let buf_size = 1_000_000usize;
-    let mut j = 0;
-    while j < num_cells {
-        let mut buffer = vec![0; buf_size * data_size];
+pub fn write_whitebox(r: &mut Raster) -> Result<(), RasterError> {
+    // figure out the minimum and maximum values
+    for i in 0..r.data.len() {
+        let v = r.data.get_value(i);
+        if v != r.configs.nodata {
+            if v < r.configs.minimum { r.configs.minimum = v; }
+            if v > r.configs.maximum { r.configs.maximum = v; }
+        }
+    }
 
-        f.read(&mut buffer)?;
+    write_whitebox_header(&r.file_name, &mut r.configs)?;
 
-        // read the file's bytes into a buffer
-        //try!(f.read_to_end(&mut buffer));
+    // write the data file
+    let data_file = r.file_name.replace(".dep", ".tas");
+    let f = File::create(&data_file)?;
+    let mut writer = BufWriter::new(f);
 
-        //try!(br.fill_buf().unwrap()(&mut buffer));
+    let mut u16_bytes: [u8; 2];
+    let mut u32_bytes: [u8; 4];
+    let mut u64_bytes: [u8; 8];
 
-        let mut offset: usize;
-        match configs.data_type {
-            DataType::F64 => {
-                for i in 0..buf_size {
-                    offset = i * data_size;
-                    data.push(unsafe { mem::transmute::<[u8; 8], f64>([buffer[offset], buffer[offset+1],
-                        buffer[offset+2], buffer[offset+3], buffer[offset+4], buffer[offset+5],
-                        buffer[offset+6], buffer[offset+7]])});
-                    j += 1;
-                    if j == num_cells { break; }
-                }
-            },
-            DataType::F32 => {
-                for i in 0..buf_size {
-                    offset = i * data_size;
-                    data.push(unsafe { mem::transmute::<[u8; 4], f32>([buffer[offset], buffer[offset+1],
-                        buffer[offset+2], buffer[offset+3]])} as f64);
-                    j += 1;
-                    if j == num_cells { break; }
-                }
-            },
-            DataType::I16 => {
-                for i in 0..buf_size {
-                    offset = i * data_size;
-                    data.push(unsafe { mem::transmute::<[u8; 2], i16>([buffer[offset], buffer[offset+1]])} as f64);
-                    j += 1;
-                    if j == num_cells { break; }
-                }
-            },
-            DataType::U8 => {
-                for i in 0..buf_size {
-                    data.push(buffer[i] as f64);
-                    j += 1;
-                    if j == num_cells { break; }
-                }
-            },
-            _ => {
-                return Err(Error::new(ErrorKind::NotFound, "Raster data type is unknown."));
-            },
-        }
+    let num_cells: usize = r.configs.rows * r.configs.columns * r.num_bands();
+    match r.configs.data_type {
+        DataType::F64 => {
+            for i in 0..num_cells {
+                u64_bytes = f64::to_ne_bytes(r.data.get_value(i));
+                writer.write_all(&u64_bytes)?;
+            }
+        },
+        DataType::F32 => {
+            for i in 0..num_cells {
+                u32_bytes = f32::to_ne_bytes(r.data.get_value(i) as f32);
+                writer.write_all(&u32_bytes)?;
+            }
+        },
+        DataType::I16 => {
+            for i in 0..num_cells {
+                u16_bytes = u16::to_ne_bytes(r.data.get_value(i) as u16);
+                writer.write_all(&u16_bytes)?;
+            }
+        },
+        DataType::U8 => {
+            for i in 0..num_cells {
+                writer.write_all(&[r.data.get_value(i) as u8])?;
+            }
+        },
+        _ => {
+            return Err(RasterError::new(RasterErrorKind::Unsupported, "Raster data type is unknown."));
+        },
     }
 
+    writer.flush()?;
+
     Ok(())
 }
 
-pub fn write_whitebox<'a>(r: &'a mut Raster) -> Result<(), Error> {
-    // figure out the minimum and maximum values
-    for val in &r.data {
-        let v = *val;
-        if v != r.configs.nodata {
-            if v < r.configs.minimum { r.configs.minimum = v; }
-            if v > r.configs.maximum { r.configs.maximum = v; }
-        }
-    }
-
-    if r.configs.display_min == f64::INFINITY {
-        r.configs.display_min = r.configs.minimum;
+/// Writes the header (.dep) file of a Whitebox raster. The display minimum and maximum
+/// default to the minimum and maximum of the configs.
+pub fn write_whitebox_header(file_name: &str, configs: &mut RasterConfigs) -> Result<(), RasterError> {
+    if configs.display_min == f64::INFINITY {
+        configs.display_min = configs.minimum;
     }
-    if r.configs.display_max == f64::NEG_INFINITY {
-        r.configs.display_max = r.configs.maximum;
+    if configs.display_max == f64::NEG_INFINITY {
+        configs.display_max = configs.maximum;
     }
 
     // Save the header file
-    let header_file = r.file_name.replace(".tas", ".dep");
+    let header_file = file_name.replace(".tas", ".dep");
     let f = File::create(header_file)?;
     let mut writer = BufWriter::new(f);
 
-    let s = format!("Min:\t{}\n", r.configs.minimum);
+    let s = format!("Min:\t{}\n", configs.minimum);
     writer.write_all(s.as_bytes())?; //.expect("Unable to write data)
 
-    let s = format!("Max:\t{}\n", r.configs.maximum);
+    let s = format!("Max:\t{}\n", configs.maximum);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("North:\t{}\n", r.configs.north);
+    let s = format!("North:\t{}\n", configs.north);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("South:\t{}\n", r.configs.south);
+    let s = format!("South:\t{}\n", configs.south);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("East:\t{}\n", r.configs.east);
+    let s = format!("East:\t{}\n", configs.east);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("West:\t{}\n", r.configs.west);
+    let s = format!("West:\t{}\n", configs.west);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("Cols:\t{}\n", r.configs.columns);
+    let s = format!("Cols:\t{}\n", configs.columns);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("Rows:\t{}\n", r.configs.rows);
+    let s = format!("Rows:\t{}\n", configs.rows);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("Stacks:\t{}\n", r.configs.bands);
+    let s = format!("Stacks:\t{}\n", configs.bands);
     writer.write_all(s.as_bytes())?;
 
-    match r.configs.data_type {
+    match configs.data_type {
         DataType::F64 => {
             writer.write_all("Data Type:\tDOUBLE\n".as_bytes())?;
         },
@@ -229,20 +297,20 @@ pub fn write_whitebox<'a>(r: &'a mut Raster) -> Result<(), Error> {
             writer.write_all("Data Type:\tBYTE\n".as_bytes())?;
         },
         _ => {
-            return Err(Error::new(ErrorKind::NotFound, "Raster Data Type not specified."));
+            return Err(RasterError::new(RasterErrorKind::Unsupported, "Raster Data Type not specified."));
         },
     }
 
-    let s = format!("Z Units:\t{}\n", r.configs.z_units);
+    let s = format!("Z Units:\t{}\n", configs.z_units);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("XY Units:\t{}\n", r.configs.xy_units);
+    let s = format!("XY Units:\t{}\n", configs.xy_units);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("Projection:\t{}\n", r.configs.projection);
+    let s = format!("Projection:\t{}\n", configs.projection);
     writer.write_all(s.as_bytes())?;
 
-    match r.configs.photometric_interp {
+    match configs.photometric_interp {
         PhotometricInterpretation::Continuous => {
             writer.write_all("Data Scale:\tcontinuous\n".as_bytes())?;
         },
@@ -261,77 +329,35 @@ pub fn write_whitebox<'a>(r: &'a mut Raster) -> Result<(), Error> {
         },
     }
 
-    let s = format!("Display Min:\t{}\n", r.configs.display_min);
+    let s = format!("Display Min:\t{}\n", configs.display_min);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("Display Max:\t{}\n", r.configs.display_max);
+    let s = format!("Display Max:\t{}\n", configs.display_max);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("Preferred Palette:\t{}\n", r.configs.palette);
+    let s = format!("Preferred Palette:\t{}\n", configs.palette);
     writer.write_all(s.as_bytes())?;
 
-    let s = format!("NoData:\t{}\n", r.configs.nodata);
+    let s = format!("NoData:\t{}\n", configs.nodata);
     writer.write_all(s.as_bytes())?;
 
 
-    if r.configs.endian == Endianness::LittleEndian {
+    if configs.endian == Endianness::LittleEndian {
         writer.write_all("Byte Order:\tLITTLE_ENDIAN\n".as_bytes())?;
     } else {
         writer.write_all("Byte Order:\tBIG_ENDIAN\n".as_bytes())?;
     }
 
-    if r.configs.palette_nonlinearity < 0.0 { r.configs.palette_nonlinearity = 1.0; }
-    let s = format!("Palette Nonlinearity:\t{}\n", r.configs.palette_nonlinearity);
+    if configs.palette_nonlinearity < 0.0 { configs.palette_nonlinearity = 1.0; }
+    let s = format!("Palette Nonlinearity:\t{}\n", configs.palette_nonlinearity);
     writer.write_all(s.as_bytes())?;
 
-    for md in &r.configs.metadata {
+    for md in &configs.metadata {
         let s = format!("Metadata Entry:\t{}\n", md.replace(":", ";"));
         writer.write_all(s.as_bytes())?;
     }
 
-    let _ = writer.flush();
-
-
-    // write the data file
-    let data_file = r.file_name.replace(".dep", ".tas");
-    let f = File::create(&data_file)?;
-    let mut writer = BufWriter::new(f);
-
-    let mut u16_bytes: [u8; 2];
-    let mut u32_bytes: [u8; 4];
-    let mut u64_bytes: [u8; 8];
-
-    let num_cells: usize = r.configs.rows * r.configs.columns;
-    match r.configs.data_type {
-        DataType::F64 => {
-            for i in 0..num_cells {
-                u64_bytes = unsafe { mem::transmute(r.data[i]) };
-                writer.write(&u64_bytes)?;
-            }
-        },
-        DataType::F32 => {
-            for i in 0..num_cells {
-                u32_bytes = unsafe { mem::transmute(r.data[i] as f32) };
-                writer.write(&u32_bytes)?;
-            }
-        },
-        DataType::I16 => {
-            for i in 0..num_cells {
-                u16_bytes = unsafe { mem::transmute(r.data[i] as u16) };
-                writer.write(&u16_bytes)?;
-            }
-        },
-        DataType::U8 => {
-            for i in 0..num_cells {
-                writer.write(&[r.data[i] as u8])?;
-            }
-        },
-        _ => {
-            return Err(Error::new(ErrorKind::NotFound, "Raster data type is unknown."));
-        },
-    }
-
-    let _ = writer.flush();
+    writer.flush()?;
 
     Ok(())
 }
@@ -0,0 +1,508 @@
+use std::io::prelude::*;
+use std::io::{BufWriter, SeekFrom};
+use std::cmp::{max, min};
+use std::f64;
+use std::fs;
+use std::fs::File;
+use byteorder::{ByteOrder, BigEndian, LittleEndian};
+use crate::raster::*;
+use crate::raster::geotiff::BlockLayout;
+use crate::raster::raster_data::RasterData;
+use crate::raster::raster_error::*;
+use crate::raster::whitebox_raster::write_whitebox_header;
+use crate::io_utils::byte_order_reader::{ByteOrderReader, Endianness};
+
+/// A rectangular part of a raster, given by the row and column of its upper-left cell and
+/// its size. A window may extend beyond the edges of the raster, e.g. to include the
+/// neighbours of the cells along them; cells outside of the raster are nodata.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RasterWindow {
+    pub row: isize,
+    pub column: isize,
+    pub rows: usize,
+    pub columns: usize,
+}
+
+impl RasterWindow {
+    /// Returns the configs of a raster holding the window, i.e. those of the whole raster
+    /// with its size and extent changed to those of the window.
+    pub fn get_configs(&self, configs: &RasterConfigs) -> RasterConfigs {
+        let mut window_configs = configs.clone();
+        window_configs.rows = self.rows;
+        window_configs.columns = self.columns;
+        window_configs.north = configs.north - self.row as f64 * configs.resolution_y;
+        window_configs.south = window_configs.north - self.rows as f64 * configs.resolution_y;
+        window_configs.west = configs.west + self.column as f64 * configs.resolution_x;
+        window_configs.east = window_configs.west + self.columns as f64 * configs.resolution_x;
+        window_configs
+    }
+
+    /// Returns the index, within the band-sequential cells of the window, of a cell given
+    /// by its band, row and column in the whole raster, or None if the cell is outside of
+    /// the window.
+    pub fn get_index(&self, band: usize, row: usize, column: usize) -> Option<usize> {
+        let r = row as isize - self.row;
+        let c = column as isize - self.column;
+        if r < 0 || c < 0 || r >= self.rows as isize || c >= self.columns as isize {
+            return None;
+        }
+        Some((band * self.rows + r as usize) * self.columns + c as usize)
+    }
+}
+
+/// A block of a raster, read by `Raster::blocks`. The block holds the cells of its window
+/// and of an overlap of neighbouring cells around it.
+pub struct RasterBlock {
+    /// The part of the raster that the block covers, excluding the overlap.
+    pub window: RasterWindow,
+    /// The width of the overlap, in cells.
+    pub overlap: usize,
+    /// The cells of the window and the overlap.
+    pub raster: Raster,
+}
+
+impl RasterBlock {
+    /// Returns the value of a cell of the first band, given by its row and column in the
+    /// whole raster, or nodata if the cell is outside of the block and its overlap.
+    pub fn get_value(&self, row: isize, column: isize) -> f64 {
+        let o = self.overlap as isize;
+        self.raster.get_value(row - self.window.row + o, column - self.window.column + o)
+    }
+
+    /// Returns the value of a cell of a band, given by its row and column in the whole
+    /// raster.
+    pub fn get_band_value(&self, band: usize, row: isize, column: isize) -> f64 {
+        let o = self.overlap as isize;
+        self.raster.get_band_value(band, row - self.window.row + o, column - self.window.column + o)
+    }
+}
+
+/// An iterator over the blocks of a raster, in row-major order, returned by
+/// `Raster::blocks`. Each block is read from the file as it is reached.
+pub struct RasterBlocks<'a> {
+    raster: &'a Raster,
+    block_rows: usize,
+    block_columns: usize,
+    overlap: usize,
+    row: usize,
+    column: usize,
+}
+
+impl<'a> RasterBlocks<'a> {
+    pub fn new(raster: &'a Raster, block_rows: usize, block_columns: usize, overlap: usize) -> RasterBlocks<'a> {
+        RasterBlocks {
+            raster,
+            block_rows: max(block_rows, 1),
+            block_columns: max(block_columns, 1),
+            overlap,
+            row: 0,
+            column: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for RasterBlocks<'a> {
+    type Item = Result<RasterBlock, RasterError>;
+
+    fn next(&mut self) -> Option<Result<RasterBlock, RasterError>> {
+        let (rows, columns) = (self.raster.configs.rows, self.raster.configs.columns);
+        if self.row >= rows || columns == 0 {
+            return None;
+        }
+        let window = RasterWindow {
+            row: self.row as isize,
+            column: self.column as isize,
+            rows: min(self.block_rows, rows - self.row),
+            columns: min(self.block_columns, columns - self.column),
+        };
+        self.column += self.block_columns;
+        if self.column >= columns {
+            self.column = 0;
+            self.row += self.block_rows;
+        }
+        let o = self.overlap as isize;
+        let raster = self.raster.read_window(window.row - o, window.column - o, window.rows + 2 * self.overlap, window.columns + 2 * self.overlap);
+        Some(raster.map(|raster| RasterBlock { window, overlap: self.overlap, raster }))
+    }
+}
+
+/// Writes a raster block by block, e.g. the results of processing the blocks of
+/// `Raster::blocks`, so that rasters larger than memory can be created. The cells of a
+/// Whitebox raster are written to its data file as they are given, in any order, and its
+/// header is written by `close`. Rasters of other formats are held in memory and written
+/// by `close`. Cells that are not written are nodata.
+pub struct RasterBlockWriter {
+    file_name: String,
+    /// The configs of the raster. The metadata and display properties may be changed until
+    /// the writer is closed, but not the size or data type of the raster.
+    pub configs: RasterConfigs,
+    sink: BlockSink,
+}
+
+enum BlockSink {
+    // The data file of a Whitebox raster.
+    Whitebox(File),
+    // A raster of another format, which is written whole.
+    Memory(Box<Raster>),
+}
+
+impl RasterBlockWriter {
+    /// Creates a raster with the extent, data type and nodata value of another raster,
+    /// e.g. one opened with `Raster::open`, as `Raster::initialize_using_file` does.
+    pub fn initialize_using_file<'a>(file_name: &'a str, input: &'a Raster) -> Result<RasterBlockWriter, RasterError> {
+        let raster_type = get_raster_type_from_file(file_name.to_string(), "w".to_string())?;
+        let configs = output_configs(&input.configs, &raster_type);
+        RasterBlockWriter::new(file_name, raster_type, configs)
+    }
+
+    /// Creates a raster described by its configs, as `Raster::initialize_using_config` does.
+    pub fn initialize_using_config<'a>(file_name: &'a str, configs: &'a RasterConfigs) -> Result<RasterBlockWriter, RasterError> {
+        let raster_type = get_raster_type_from_file(file_name.to_string(), "w".to_string())?;
+        RasterBlockWriter::new(file_name, raster_type, configs.clone())
+    }
+
+    fn new(file_name: &str, raster_type: RasterType, mut configs: RasterConfigs) -> Result<RasterBlockWriter, RasterError> {
+        if raster_type != RasterType::Whitebox {
+            return Ok(RasterBlockWriter {
+                file_name: file_name.to_string(),
+                sink: BlockSink::Memory(Box::new(Raster::initialize_using_config(file_name, &configs))),
+                configs,
+            });
+        }
+        match configs.data_type {
+            DataType::F64 | DataType::F32 | DataType::I16 | DataType::U8 => {},
+            _ => return Err(RasterError::new(RasterErrorKind::Unsupported, format!("Whitebox rasters of the {:?} data type cannot be written.", configs.data_type))),
+        }
+
+        // the data file is filled with nodata, which the blocks then overwrite
+        let f = File::create(file_name.replace(".dep", ".tas"))?;
+        {
+            let mut writer = BufWriter::new(&f);
+            for band in 0..max(configs.bands, 1) {
+                let row = encode_values(&vec![configs.get_band_nodata(band); configs.columns], configs.data_type, configs.endian);
+                for _ in 0..configs.rows {
+                    writer.write_all(&row)?;
+                }
+            }
+            writer.flush()?;
+        }
+        configs.minimum = f64::INFINITY;
+        configs.maximum = f64::NEG_INFINITY;
+        Ok(RasterBlockWriter { file_name: file_name.to_string(), configs, sink: BlockSink::Whitebox(f) })
+    }
+
+    /// Writes the cells of a raster, e.g. the output for a block, to the window of the
+    /// raster whose upper-left cell is at the given row and column. Cells that fall outside
+    /// of the raster are ignored.
+    pub fn write_window(&mut self, row: isize, column: isize, raster: &Raster) -> Result<(), RasterError> {
+        let col_start = max(column, 0);
+        let col_end = min(column + raster.configs.columns as isize, self.configs.columns as isize);
+        if col_end <= col_start {
+            return Ok(());
+        }
+        let num_bands = min(raster.num_bands(), max(self.configs.bands, 1));
+        for band in 0..num_bands {
+            for r in 0..raster.configs.rows as isize {
+                let values: Vec<f64> = (col_start..col_end).map(|c| raster.get_band_value(band, r, c - column)).collect();
+                self.write_values(band, row + r, col_start as usize, &values)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a row of the first band, as `Raster::set_row_data` does.
+    pub fn set_row_data(&mut self, row: isize, values: Vec<f64>) -> Result<(), RasterError> {
+        let n = min(values.len(), self.configs.columns);
+        self.write_values(0, row, 0, &values[..n])
+    }
+
+    pub fn add_metadata_entry(&mut self, value: String) {
+        self.configs.metadata.push(value);
+    }
+
+    /// Writes the values of consecutive cells of a row, starting at a column.
+    fn write_values(&mut self, band: usize, row: isize, column: usize, values: &[f64]) -> Result<(), RasterError> {
+        if row < 0 || row >= self.configs.rows as isize {
+            return Ok(());
+        }
+        match self.sink {
+            BlockSink::Memory(ref mut raster) => {
+                for (i, &value) in values.iter().enumerate() {
+                    raster.set_band_value(band, row, (column + i) as isize, value);
+                }
+            },
+            BlockSink::Whitebox(ref mut f) => {
+                let nodata = self.configs.get_band_nodata(band);
+                for &value in values {
+                    if value != nodata {
+                        if value < self.configs.minimum { self.configs.minimum = value; }
+                        if value > self.configs.maximum { self.configs.maximum = value; }
+                    }
+                }
+                let (rows, columns) = (self.configs.rows, self.configs.columns);
+                let position = (((band * rows + row as usize) * columns + column) * self.configs.data_type.get_data_size()) as u64;
+                f.seek(SeekFrom::Start(position))?;
+                f.write_all(&encode_values(values, self.configs.data_type, self.configs.endian))?;
+            },
+        }
+        Ok(())
+    }
+
+    /// Finishes writing the raster: the header of a Whitebox raster, or the whole raster
+    /// of other formats.
+    pub fn close(self) -> Result<(), RasterError> {
+        let mut configs = self.configs;
+        match self.sink {
+            BlockSink::Memory(mut raster) => {
+                raster.configs = configs;
+                raster.write()
+            },
+            BlockSink::Whitebox(mut f) => {
+                f.flush()?;
+                write_whitebox_header(&self.file_name, &mut configs)
+            },
+        }
+    }
+}
+
+/// Encodes the values of cells in one of the data types of Whitebox rasters.
+fn encode_values(values: &[f64], data_type: DataType, endian: Endianness) -> Vec<u8> {
+    match endian {
+        Endianness::LittleEndian => encode_values_with::<LittleEndian>(values, data_type),
+        Endianness::BigEndian => encode_values_with::<BigEndian>(values, data_type),
+    }
+}
+
+fn encode_values_with<B: ByteOrder>(values: &[f64], data_type: DataType) -> Vec<u8> {
+    let size = data_type.get_data_size();
+    let mut bytes = vec![0u8; values.len() * size];
+    for (&value, b) in values.iter().zip(bytes.chunks_mut(size)) {
+        match data_type {
+            DataType::F64 => B::write_f64(b, value),
+            DataType::F32 => B::write_f32(b, value as f32),
+            DataType::I16 => B::write_i16(b, value as i16),
+            _ => b[0] = value as u8,
+        }
+    }
+    bytes
+}
+
+/// The layout of the cells of a raster file, read with its header by `Raster::open` so
+/// that windows are read without reading the header again.
+pub enum WindowLayout {
+    Raw(RawLayout),
+    GeoTiff(BlockLayout),
+}
+
+/// The layout of a raster data file holding uncompressed cells in row-major order, with
+/// any bands following one another.
+pub struct RawLayout {
+    pub data_file: String,
+    /// The position of the first cell in the file.
+    pub offset: u64,
+    pub data_type: DataType,
+    pub endian: Endianness,
+    /// Whether the first row of the file is the northern-most; otherwise it is the
+    /// southern-most.
+    pub top_to_bottom: bool,
+    /// A factor by which the values of the cells are multiplied.
+    pub z_factor: f64,
+}
+
+impl RawLayout {
+    /// Checks that the data file holds all of the cells of a raster described by its
+    /// configs, i.e. that it is not truncated.
+    pub fn check_data_file(&self, configs: &RasterConfigs) -> Result<(), RasterError> {
+        let file_size = fs::metadata(&self.data_file)?.len();
+        let num_cells = configs.rows * configs.columns * max(configs.bands, 1);
+        let expected_size = self.offset + (num_cells * self.data_type.get_data_size()) as u64;
+        if file_size < expected_size {
+            return Err(RasterError::new(RasterErrorKind::InvalidData,
+                format!("The data file {} is truncated; it holds {} of the {} bytes of the raster.", self.data_file, file_size, expected_size))
+                .at_offset(file_size));
+        }
+        Ok(())
+    }
+}
+
+/// Reads a window of a raster from a data file of uncompressed cells, reading only the
+/// rows that it overlaps. The configs are those of the whole raster.
+pub fn read_raw_window(layout: &RawLayout, configs: &RasterConfigs, window: &RasterWindow, data: &mut RasterData) -> Result<(), RasterError> {
+    let read_sample: fn(&mut ByteOrderReader) -> f64 = match layout.data_type {
+        DataType::F64 => |bor| bor.read_f64(),
+        DataType::F32 => |bor| bor.read_f32() as f64,
+        DataType::I64 => |bor| bor.read_i64() as f64,
+        DataType::I32 => |bor| bor.read_i32() as f64,
+        DataType::I16 => |bor| bor.read_i16() as f64,
+        DataType::I8 => |bor| bor.read_i8() as f64,
+        DataType::U64 => |bor| bor.read_u64() as f64,
+        DataType::U32 => |bor| bor.read_u32() as f64,
+        DataType::U16 => |bor| bor.read_u16() as f64,
+        DataType::U8 => |bor| bor.read_u8() as f64,
+        _ => return Err(RasterError::new(RasterErrorKind::Unsupported, format!("Windows of rasters of the {:?} data type cannot be read.", layout.data_type))),
+    };
+    let data_size = layout.data_type.get_data_size();
+
+    // scaled values are no longer of the file's data type
+    let storage_type = if layout.z_factor == 1f64 { configs.data_type } else { DataType::F64 };
+    *data = RasterData::for_configs(&window.get_configs(configs), storage_type);
+
+    let (rows, columns) = (configs.rows, configs.columns);
+    let col_start = max(window.column, 0) as usize;
+    let col_end = min(window.column + window.columns as isize, columns as isize);
+    if col_end <= col_start as isize {
+        return Ok(()); // the window lies outside of the raster
+    }
+    let col_end = col_end as usize;
+    let mut f = File::open(&layout.data_file)?;
+    let mut buffer = vec![0u8; (col_end - col_start) * data_size];
+    for band in 0..max(configs.bands, 1) {
+        for r in 0..window.rows {
+            let row = window.row + r as isize;
+            if row < 0 || row >= rows as isize {
+                continue;
+            }
+            let row = row as usize;
+            let file_row = if layout.top_to_bottom { row } else { rows - 1 - row };
+            let position = layout.offset + (((band * rows + file_row) * columns + col_start) * data_size) as u64;
+            f.seek(SeekFrom::Start(position))?;
+            f.read_exact(&mut buffer).map_err(|e| RasterError::from(e).at_offset(position))?;
+            let mut bor = ByteOrderReader::new(buffer, layout.endian);
+            for col in col_start..col_end {
+                let value = read_sample(&mut bor) * layout.z_factor;
+                if let Some(k) = window.get_index(band, row, col) {
+                    data.set_value(k, value);
+                }
+            }
+            buffer = bor.buffer;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::io_utils::temp_file_name;
+    use super::*;
+
+    fn test_configs(rows: usize, columns: usize, data_type: DataType) -> RasterConfigs {
+        let mut configs = RasterConfigs::default();
+        configs.rows = rows;
+        configs.columns = columns;
+        configs.north = 100.0 + rows as f64;
+        configs.south = 100.0;
+        configs.east = 200.0 + columns as f64;
+        configs.west = 200.0;
+        configs.resolution_x = 1.0;
+        configs.resolution_y = 1.0;
+        configs.nodata = -32768.0;
+        configs.data_type = data_type;
+        configs.photometric_interp = PhotometricInterpretation::Continuous;
+        configs
+    }
+
+    fn cell_value(row: isize, col: isize) -> f64 {
+        (row * 11 + col * 3 - 50) as f64
+    }
+
+    /// Writes the cells of a raster with a block writer, block by block from the bottom of
+    /// the raster up, and then the last row, except for its last cell, which is left unwritten.
+    fn write_by_block(file_name: &str, rows: usize, columns: usize, data_type: DataType) {
+        let configs = test_configs(rows, columns, data_type);
+        let mut writer = RasterBlockWriter::initialize_using_config(file_name, &configs).unwrap();
+        let block_rows = 4;
+        let mut row = ((rows - 1) / block_rows * block_rows) as isize;
+        while row >= 0 {
+            let window = RasterWindow { row, column: -1, rows: block_rows, columns: columns + 2 };
+            let mut block = Raster::initialize_using_config(file_name, &window.get_configs(&configs));
+            for r in 0..min(block_rows as isize, rows as isize - 1 - row) {
+                for c in 0..block.configs.columns as isize {
+                    block.set_value(r, c, cell_value(row + r, c - 1));
+                }
+            }
+            writer.write_window(window.row, window.column, &block).unwrap();
+            row -= block_rows as isize;
+        }
+        writer.set_row_data(rows as isize - 1, vec![cell_value(rows as isize - 1, 0); columns - 1]).unwrap();
+        writer.add_metadata_entry("Written by block".to_string());
+        writer.close().unwrap();
+    }
+
+    fn assert_written_by_block(r: &Raster) {
+        let (rows, columns) = (r.configs.rows as isize, r.configs.columns as isize);
+        for row in 0..rows {
+            for col in 0..columns {
+                let expected = if row == rows - 1 && col == columns - 1 {
+                    r.configs.nodata
+                } else if row == rows - 1 {
+                    cell_value(row, 0)
+                } else {
+                    cell_value(row, col)
+                };
+                assert_eq!(r.get_value(row, col), expected, "cell ({}, {})", row, col);
+            }
+        }
+    }
+
+    #[test]
+    fn whitebox_raster_written_by_block() {
+        let file_name = temp_file_name("by_block.dep");
+        write_by_block(&file_name, 10, 7, DataType::I16);
+        let r = Raster::new(&file_name, "r").unwrap();
+        fs::remove_file(&file_name).unwrap();
+        fs::remove_file(file_name.replace(".dep", ".tas")).unwrap();
+        assert_eq!(r.configs.data_type, DataType::I16);
+        assert_eq!(r.configs.minimum, cell_value(0, 0));
+        assert_eq!(r.configs.maximum, cell_value(8, 6));
+        assert!(r.configs.metadata.iter().any(|m| m == "Written by block"));
+        assert_written_by_block(&r);
+    }
+
+    #[test]
+    fn geotiff_written_by_block() {
+        let file_name = temp_file_name("by_block.tif");
+        write_by_block(&file_name, 10, 7, DataType::F32);
+        let r = Raster::new(&file_name, "r").unwrap();
+        fs::remove_file(&file_name).unwrap();
+        assert_eq!(r.configs.data_type, DataType::F32);
+        assert_written_by_block(&r);
+    }
+
+    #[test]
+    fn unsupported_whitebox_data_type_is_an_error() {
+        let file_name = temp_file_name("by_block_u32.dep");
+        match RasterBlockWriter::initialize_using_config(&file_name, &test_configs(2, 2, DataType::U32)) {
+            Ok(_) => panic!("a U32 Whitebox raster was created"),
+            Err(e) => assert_eq!(e.kind, RasterErrorKind::Unsupported),
+        }
+    }
+
+    #[test]
+    fn blocks_of_a_geotiff() {
+        let file_name = temp_file_name("blocks.tif");
+        let configs = test_configs(23, 9, DataType::F64);
+        let mut r = Raster::initialize_using_config(&file_name, &configs);
+        for row in 0..23 {
+            for col in 0..9 {
+                r.set_value(row, col, cell_value(row, col));
+            }
+        }
+        r.write().unwrap();
+        let opened = Raster::open(&file_name).unwrap();
+        let blocks: Vec<RasterBlock> = opened.blocks(5, 4, 2).map(|b| b.unwrap()).collect();
+        fs::remove_file(&file_name).unwrap();
+
+        assert_eq!(blocks.len(), 5 * 3);
+        for block in blocks.iter() {
+            assert_eq!(block.raster.configs.rows, block.window.rows + 4);
+            for row in block.window.row - 2..block.window.row + block.window.rows as isize + 2 {
+                for col in block.window.column - 2..block.window.column + block.window.columns as isize + 2 {
+                    let expected = if row < 0 || col < 0 || row >= 23 || col >= 9 { configs.nodata } else { cell_value(row, col) };
+                    assert_eq!(block.get_value(row, col), expected, "cell ({}, {})", row, col);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::f64;
+use crate::spatial_ref_system::ellipsoid::*;
+
+/// A 7-parameter Helmert transformation between geocentric coordinates, using the
+/// position vector convention (EPSG method 9606), as do the TOWGS84 parameters of WKT.
+/// Translations are in metres, rotations in arc-seconds and the scale difference in
+/// parts per million.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Helmert {
+    pub tx: f64,
+    pub ty: f64,
+    pub tz: f64,
+    pub rx: f64,
+    pub ry: f64,
+    pub rz: f64,
+    pub ds: f64,
+}
+
+impl Helmert {
+    pub fn new(params: [f64; 7]) -> Helmert {
+        Helmert { tx: params[0], ty: params[1], tz: params[2], rx: params[3], ry: params[4], rz: params[5], ds: params[6] }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Helmert::default()
+    }
+
+    /// Applies the transformation to geocentric coordinates.
+    pub fn apply(&self, x: f64, y: f64, z: f64) -> (f64, f64, f64) {
+        let (rx, ry, rz) = self.rotations_in_radians();
+        let s = 1f64 + self.ds * 1e-6;
+        (self.tx + s * (x - rz * y + ry * z),
+         self.ty + s * (rz * x + y - rx * z),
+         self.tz + s * (-ry * x + rx * y + z))
+    }
+
+    /// Applies the reverse transformation to geocentric coordinates. The rotations are
+    /// small, so the transposed rotation matrix is used as its inverse.
+    pub fn apply_inverse(&self, x: f64, y: f64, z: f64) -> (f64, f64, f64) {
+        let (rx, ry, rz) = self.rotations_in_radians();
+        let s = 1f64 + self.ds * 1e-6;
+        let (x, y, z) = ((x - self.tx) / s, (y - self.ty) / s, (z - self.tz) / s);
+        (x + rz * y - ry * z,
+         -rz * x + y + rx * z,
+         ry * x - rx * y + z)
+    }
+
+    fn rotations_in_radians(&self) -> (f64, f64, f64) {
+        let sec_to_rad = f64::consts::PI / (180f64 * 3600f64);
+        (self.rx * sec_to_rad, self.ry * sec_to_rad, self.rz * sec_to_rad)
+    }
+}
+
+/// A geodetic datum: a reference ellipsoid and the Helmert transformation that takes
+/// geocentric coordinates on the datum to WGS84.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Datum {
+    pub ellipsoid: Ellipsoid,
+    pub to_wgs84: Helmert,
+}
+
+impl Datum {
+    pub fn new(ellipsoid: Ellipsoid, to_wgs84: [f64; 7]) -> Datum {
+        Datum { ellipsoid, to_wgs84: Helmert::new(to_wgs84) }
+    }
+
+    pub fn wgs84() -> Datum {
+        Datum::new(WGS84, [0f64; 7])
+    }
+
+    /// Returns a datum by name, e.g. `NAD83` or `OSGB_1936`, as used in the names of the
+    /// GeoTIFF coordinate system codes. Case and underscores are ignored. The datums that
+    /// are realizations of the ITRS (NAD83, ETRS89, GDA94, etc.) are treated as coincident
+    /// with WGS84, which they are at the metre level.
+    pub fn from_name(name: &str) -> Option<Datum> {
+        let name = name.replace("_", "").replace(" ", "").to_uppercase();
+        let datum = match name.as_ref() {
+            "WGS84" => Datum::wgs84(),
+            "NAD83" | "NAD83CSRS" | "ETRS89" | "EUREF89" | "GDA94" | "NZGD2000" | "RGF93" => Datum::new(GRS80, [0f64; 7]),
+            "WGS72" => Datum::new(WGS72, [0.0, 0.0, 4.5, 0.0, 0.0, 0.554, 0.2263]),
+            "WGS72BE" => Datum::new(WGS72, [0.0, 0.0, 1.9, 0.0, 0.0, 0.814, -0.38]),
+            "NAD27" => Datum::new(CLARKE_1866, [-8.0, 160.0, 176.0, 0.0, 0.0, 0.0, 0.0]),
+            "ED50" => Datum::new(INTERNATIONAL_1924, [-87.0, -98.0, -121.0, 0.0, 0.0, 0.0, 0.0]),
+            "OSGB1936" | "OSGB36" => Datum::new(AIRY_1830, [446.448, -125.157, 542.06, 0.15, 0.247, 0.842, -20.489]),
+            "AGD66" => Datum::new(AUSTRALIAN_NATIONAL, [-133.0, -48.0, 148.0, 0.0, 0.0, 0.0, 0.0]),
+            "AGD84" => Datum::new(AUSTRALIAN_NATIONAL, [-134.0, -48.0, 149.0, 0.0, 0.0, 0.0, 0.0]),
+            "DHDN" => Datum::new(BESSEL_1841, [598.1, 73.7, 418.2, 0.202, 0.045, -2.455, 6.7]),
+            "PULKOVO1942" => Datum::new(KRASSOWSKY_1940, [23.92, -141.27, -80.9, 0.0, 0.35, 0.82, -0.12]),
+            "TOKYO" => Datum::new(BESSEL_1841, [-146.414, 507.337, 680.507, 0.0, 0.0, 0.0, 0.0]),
+            _ => return None,
+        };
+        Some(datum)
+    }
+
+    /// Converts geodetic coordinates (degrees and metres above the ellipsoid) to
+    /// geocentric (earth-centred, earth-fixed) coordinates.
+    pub fn geodetic_to_geocentric(&self, lon: f64, lat: f64, h: f64) -> (f64, f64, f64) {
+        let e2 = self.ellipsoid.e2();
+        let (lon, lat) = (lon.to_radians(), lat.to_radians());
+        let n = self.ellipsoid.a / (1f64 - e2 * lat.sin() * lat.sin()).sqrt();
+        ((n + h) * lat.cos() * lon.cos(),
+         (n + h) * lat.cos() * lon.sin(),
+         (n * (1f64 - e2) + h) * lat.sin())
+    }
+
+    /// Converts geocentric coordinates to geodetic coordinates (degrees and metres above
+    /// the ellipsoid).
+    pub fn geocentric_to_geodetic(&self, x: f64, y: f64, z: f64) -> (f64, f64, f64) {
+        let a = self.ellipsoid.a;
+        let e2 = self.ellipsoid.e2();
+        let p = (x * x + y * y).sqrt();
+        let lon = y.atan2(x);
+        let mut lat = z.atan2(p * (1f64 - e2));
+        let mut h = 0f64;
+        for _ in 0..10 {
+            let n = a / (1f64 - e2 * lat.sin() * lat.sin()).sqrt();
+            h = if lat.cos().abs() > 1e-10 { p / lat.cos() - n } else { z.abs() - n * (1f64 - e2) };
+            let new_lat = z.atan2(p * (1f64 - e2 * n / (n + h)));
+            if (new_lat - lat).abs() < 1e-14 {
+                lat = new_lat;
+                break;
+            }
+            lat = new_lat;
+        }
+        (lon.to_degrees(), lat.to_degrees(), h)
+    }
+
+    /// Shifts geodetic coordinates on this datum to WGS84.
+    pub fn shift_to_wgs84(&self, lon: f64, lat: f64, h: f64) -> (f64, f64, f64) {
+        let (x, y, z) = self.geodetic_to_geocentric(lon, lat, h);
+        let (x, y, z) = self.to_wgs84.apply(x, y, z);
+        Datum::wgs84().geocentric_to_geodetic(x, y, z)
+    }
+
+    /// Shifts geodetic coordinates on WGS84 to this datum.
+    pub fn shift_from_wgs84(&self, lon: f64, lat: f64, h: f64) -> (f64, f64, f64) {
+        let (x, y, z) = Datum::wgs84().geodetic_to_geocentric(lon, lat, h);
+        let (x, y, z) = self.to_wgs84.apply_inverse(x, y, z);
+        self.geocentric_to_geodetic(x, y, z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dms(degrees: f64, minutes: f64, seconds: f64) -> f64 {
+        degrees.signum() * (degrees.abs() + minutes / 60f64 + seconds / 3600f64)
+    }
+
+    #[test]
+    fn nad27_to_wgs84() {
+        // Meades Ranch, Kansas, the origin of NAD27, and its published NAD83 coordinates,
+        // which are within a metre of WGS84.
+        let (lon27, lat27) = (dms(-98.0, 32.0, 30.506), dms(39.0, 13.0, 26.686));
+        let (lon83, lat83) = (dms(-98.0, 32.0, 31.7454), dms(39.0, 13.0, 26.7122));
+        let nad27 = Datum::from_name("NAD27").unwrap();
+        let (lon, lat, h) = nad27.shift_to_wgs84(lon27, lat27, 0.0);
+
+        // The shift is about 30 m. The mean translation of NAD27 is accurate to about 10 m
+        // in the conterminous US, while a degree of latitude is about 111 km.
+        let metres = |dlon: f64, dlat: f64| ((dlon * lat83.to_radians().cos()).hypot(dlat)) * 111_000f64;
+        assert!(metres(lon27 - lon83, lat27 - lat83) > 25.0);
+        assert!(metres(lon - lon83, lat - lat83) < 10.0, "({}, {})", lon, lat);
+
+        let (lon, lat, h) = nad27.shift_from_wgs84(lon, lat, h);
+        assert!(metres(lon - lon27, lat - lat27) < 0.001 && h.abs() < 0.001);
+    }
+}
@@ -0,0 +1,39 @@
+/// A reference ellipsoid, defined by its semi-major axis (metres) and inverse flattening.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ellipsoid {
+    pub a: f64,
+    pub inv_f: f64,
+}
+
+pub const WGS84: Ellipsoid = Ellipsoid { a: 6_378_137.0, inv_f: 298.257_223_563 };
+pub const GRS80: Ellipsoid = Ellipsoid { a: 6_378_137.0, inv_f: 298.257_222_101 };
+pub const WGS72: Ellipsoid = Ellipsoid { a: 6_378_135.0, inv_f: 298.26 };
+pub const CLARKE_1866: Ellipsoid = Ellipsoid { a: 6_378_206.4, inv_f: 294.978_698_213_898 };
+pub const INTERNATIONAL_1924: Ellipsoid = Ellipsoid { a: 6_378_388.0, inv_f: 297.0 };
+pub const AIRY_1830: Ellipsoid = Ellipsoid { a: 6_377_563.396, inv_f: 299.324_964_6 };
+pub const BESSEL_1841: Ellipsoid = Ellipsoid { a: 6_377_397.155, inv_f: 299.152_812_8 };
+pub const KRASSOWSKY_1940: Ellipsoid = Ellipsoid { a: 6_378_245.0, inv_f: 298.3 };
+pub const AUSTRALIAN_NATIONAL: Ellipsoid = Ellipsoid { a: 6_378_160.0, inv_f: 298.25 };
+
+impl Ellipsoid {
+    /// Returns the flattening.
+    pub fn f(&self) -> f64 {
+        1f64 / self.inv_f
+    }
+
+    /// Returns the semi-minor axis.
+    pub fn b(&self) -> f64 {
+        self.a * (1f64 - self.f())
+    }
+
+    /// Returns the square of the first eccentricity.
+    pub fn e2(&self) -> f64 {
+        let f = self.f();
+        f * (2f64 - f)
+    }
+
+    /// Returns the first eccentricity.
+    pub fn e(&self) -> f64 {
+        self.e2().sqrt()
+    }
+}
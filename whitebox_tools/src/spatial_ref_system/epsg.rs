@@ -0,0 +1,88 @@
+use crate::raster::geotiff::geokeys::get_keyword_map;
+use crate::spatial_ref_system::CoordinateSystem;
+use crate::spatial_ref_system::datum::Datum;
+use crate::spatial_ref_system::projections::Projection;
+
+/// Returns the definition of the coordinate system with an EPSG code, or `None` if the
+/// code is unknown or uses an unsupported projection or datum.
+///
+/// The geographic systems and the UTM zones of the GeoTIFF code tables (see `geokeys`) are
+/// recognized by name, e.g. `PCS_NAD83_UTM_zone_17N` or `GCS_WGS_84`, so that any such
+/// code whose datum is supported can be used. Systems that are not in the tables, or that
+/// use other projections, are defined below.
+pub fn coordinate_system_from_epsg(epsg_code: u16) -> Option<CoordinateSystem> {
+    let (datum, projection) = match epsg_code {
+        // geographic systems that are more recent than the GeoTIFF tables
+        4617 => ("NAD83CSRS", Projection::Geographic),
+        4167 => ("NZGD2000", Projection::Geographic),
+        4171 => ("RGF93", Projection::Geographic),
+
+        // UTM zones that are more recent than the GeoTIFF tables
+        25828..=25838 => ("ETRS89", Projection::utm((epsg_code - 25800) as u8, true)),
+        28348..=28358 => ("GDA94", Projection::utm((epsg_code - 28300) as u8, false)),
+        2955..=2957 => ("NAD83CSRS", Projection::utm((epsg_code - 2944) as u8, true)),
+        2958..=2962 => ("NAD83CSRS", Projection::utm((epsg_code - 2941) as u8, true)),
+        3154..=3160 => ("NAD83CSRS", Projection::utm(match epsg_code { 3154 => 7, 3155 => 8, 3156 => 9, 3157 => 10, 3158 => 14, 3159 => 15, _ => 16 }, true)),
+
+        // other Transverse Mercator systems
+        27700 => ("OSGB_1936", Projection::TransverseMercator { lat0: 49.0, lon0: -2.0, k0: 0.999_601_271_7, false_easting: 400_000.0, false_northing: -100_000.0 }),
+        2193 => ("NZGD2000", Projection::TransverseMercator { lat0: 0.0, lon0: 173.0, k0: 0.9996, false_easting: 1_600_000.0, false_northing: 10_000_000.0 }),
+        3400 => ("NAD83", Projection::TransverseMercator { lat0: 0.0, lon0: -115.0, k0: 0.9992, false_easting: 500_000.0, false_northing: 0.0 }),
+        2180 => ("ETRS89", Projection::TransverseMercator { lat0: 0.0, lon0: 19.0, k0: 0.9993, false_easting: 500_000.0, false_northing: -5_300_000.0 }),
+        31466..=31469 => {
+            // DHDN / 3-degree Gauss-Kruger zones 2 to 5
+            let zone = (epsg_code - 31464) as f64;
+            ("DHDN", Projection::TransverseMercator { lat0: 0.0, lon0: zone * 3.0, k0: 1.0, false_easting: zone * 1_000_000.0 + 500_000.0, false_northing: 0.0 })
+        },
+
+        // Lambert Conformal Conic
+        3347 => ("NAD83", Projection::LambertConformalConic { lat0: 63.390_675, lon0: -91.866_666_666_666_67, lat1: 49.0, lat2: 77.0, k0: 1.0, false_easting: 6_200_000.0, false_northing: 3_000_000.0 }),
+        3978 => ("NAD83", Projection::LambertConformalConic { lat0: 49.0, lon0: -95.0, lat1: 49.0, lat2: 77.0, k0: 1.0, false_easting: 0.0, false_northing: 0.0 }),
+        2154 => ("RGF93", Projection::LambertConformalConic { lat0: 46.5, lon0: 3.0, lat1: 49.0, lat2: 44.0, k0: 1.0, false_easting: 700_000.0, false_northing: 6_600_000.0 }),
+        3034 => ("ETRS89", Projection::LambertConformalConic { lat0: 52.0, lon0: 10.0, lat1: 35.0, lat2: 65.0, k0: 1.0, false_easting: 4_000_000.0, false_northing: 2_800_000.0 }),
+
+        // Albers Equal Area
+        5070 => ("NAD83", Projection::AlbersEqualArea { lat0: 23.0, lon0: -96.0, lat1: 29.5, lat2: 45.5, false_easting: 0.0, false_northing: 0.0 }),
+        3005 => ("NAD83", Projection::AlbersEqualArea { lat0: 45.0, lon0: -126.0, lat1: 50.0, lat2: 58.5, false_easting: 1_000_000.0, false_northing: 0.0 }),
+        3577 => ("GDA94", Projection::AlbersEqualArea { lat0: 0.0, lon0: 132.0, lat1: -18.0, lat2: -36.0, false_easting: 0.0, false_northing: 0.0 }),
+
+        // Web Mercator
+        3857 => ("WGS84", Projection::WebMercator),
+
+        _ => return from_geotiff_tables(epsg_code),
+    };
+    Datum::from_name(datum).map(|d| CoordinateSystem { datum: d, projection })
+}
+
+/// Finds a geographic system or UTM zone in the GeoTIFF code tables and interprets its
+/// name, e.g. `GCS_NAD27`, `PCS_WGS84_UTM_zone_17N`, `PCS_Corrego_Alegre_UTM_23S` or
+/// `PCS_AGD66_AMG_zone_55` (the Australian Map Grid zones are UTM zones south).
+fn from_geotiff_tables(epsg_code: u16) -> Option<CoordinateSystem> {
+    let kw_map = get_keyword_map();
+    if let Some(name) = kw_map.get(&2048).and_then(|m| m.get(&epsg_code)) {
+        let datum = Datum::from_name(name.trim_start_matches("GCS_"))?;
+        return Some(CoordinateSystem { datum, projection: Projection::Geographic });
+    }
+    let name = kw_map.get(&3072).and_then(|m| m.get(&epsg_code))?.trim_start_matches("PCS_");
+    for &(separator, is_amg) in &[("_UTM_zone_", false), ("_UTM_", false), ("_AMG_zone_", true)] {
+        if let Some(pos) = name.find(separator) {
+            let datum = Datum::from_name(&name[..pos])?;
+            let zone = &name[pos + separator.len()..];
+            let (digits, north) = if is_amg {
+                (zone, false)
+            } else if let Some(digits) = zone.strip_suffix('N') {
+                (digits, true)
+            } else if let Some(digits) = zone.strip_suffix('S') {
+                (digits, false)
+            } else {
+                return None;
+            };
+            let zone = digits.parse::<u8>().ok()?;
+            if !(1..=60).contains(&zone) {
+                return None;
+            }
+            return Some(CoordinateSystem { datum, projection: Projection::utm(zone, north) });
+        }
+    }
+    None
+}
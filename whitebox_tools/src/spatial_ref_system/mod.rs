@@ -0,0 +1,256 @@
+pub mod datum;
+pub mod ellipsoid;
+pub mod epsg;
+pub mod projections;
+pub mod transform;
+
+use std::fmt;
+use std::io::{Error, ErrorKind};
+use crate::raster::geotiff::geokeys::{get_keyword_map, GeoKeys};
+use crate::spatial_ref_system::datum::Datum;
+use crate::spatial_ref_system::epsg::coordinate_system_from_epsg;
+use crate::spatial_ref_system::projections::Projection;
+
+/// A coordinate reference system (CRS), identified by an EPSG code and/or described by
+/// OGC Well Known Text (WKT). This is the representation shared by rasters (see the
+/// `epsg_code` and `coordinate_ref_system_wkt` fields of `RasterConfigs`) and LAS files,
+/// which may describe their CRS either with GeoTIFF GeoKeys or with a WKT VLR.
+///
+/// An EPSG code of zero means that the code is unknown, and an empty WKT string that the
+/// WKT is unknown; a CRS for which both are unknown is undefined.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct SpatialReference {
+    pub epsg_code: u16,
+    pub wkt: String,
+}
+
+impl SpatialReference {
+    pub fn from_epsg(epsg_code: u16) -> SpatialReference {
+        SpatialReference { epsg_code, wkt: String::new() }
+    }
+
+    /// Creates a CRS from OGC WKT, taking the EPSG code from the authority of the
+    /// outermost CRS definition, if there is one.
+    pub fn from_wkt(wkt: &str) -> SpatialReference {
+        let wkt = wkt.trim_matches(char::from(0)).trim();
+        SpatialReference { epsg_code: epsg_code_from_wkt(wkt), wkt: wkt.to_string() }
+    }
+
+    /// Creates a CRS from GeoTIFF GeoKeys, using the projected CRS key (3072), or failing
+    /// that the geographic CRS key (2048). User-defined CRSs are undefined.
+    pub fn from_geokeys(geokeys: &GeoKeys) -> SpatialReference {
+        SpatialReference::from_epsg(geokeys.find_epsg_code())
+    }
+
+    pub fn is_defined(&self) -> bool {
+        self.epsg_code != 0 || !self.wkt.is_empty()
+    }
+
+    /// Returns true if the CRS is geographic, i.e. its coordinates are longitude and
+    /// latitude rather than projected.
+    pub fn is_geographic(&self) -> bool {
+        if !self.wkt.is_empty() {
+            let upper = self.wkt.to_uppercase();
+            return upper.starts_with("GEOGCS") || upper.starts_with("GEOGCRS") || upper.starts_with("GEODCRS");
+        }
+        is_geographic_epsg_code(self.epsg_code)
+    }
+
+    /// Returns the name of the CRS, taken from the WKT or the GeoTIFF code tables.
+    pub fn name(&self) -> String {
+        if !self.wkt.is_empty() {
+            if let Some(start) = self.wkt.find("[\"") {
+                if let Some(len) = self.wkt[start + 2..].find('"') {
+                    return self.wkt[start + 2..start + 2 + len].to_string();
+                }
+            }
+        }
+        let kw_map = get_keyword_map();
+        for key in &[3072u16, 2048u16] {
+            if let Some(name) = kw_map.get(key).and_then(|m| m.get(&self.epsg_code)) {
+                return name.to_string();
+            }
+        }
+        String::new()
+    }
+
+    /// Returns the definition of the CRS, i.e. its datum and projection, which is needed
+    /// to transform coordinates. The definition is found using the EPSG code.
+    pub fn coordinate_system(&self) -> Result<CoordinateSystem, Error> {
+        if self.epsg_code == 0 {
+            return Err(Error::new(ErrorKind::InvalidInput, "The EPSG code of the coordinate reference system is unknown."));
+        }
+        match coordinate_system_from_epsg(self.epsg_code) {
+            Some(cs) => Ok(cs),
+            None => Err(Error::new(ErrorKind::InvalidInput,
+                format!("The coordinate reference system EPSG:{} is not supported.", self.epsg_code))),
+        }
+    }
+
+    /// Returns the GeoKeyDirectory (GeoTIFF tag 34735) entries describing the CRS by its
+    /// EPSG code, or `None` if the code is unknown.
+    pub fn to_geokey_directory(&self) -> Option<Vec<u16>> {
+        if self.epsg_code == 0 {
+            return None;
+        }
+        // header: version, revision, minor revision, number of keys; then the keys,
+        // each of which is: id, location (0 = value held in the entry), count, value
+        if self.is_geographic() {
+            Some(vec![1, 1, 0, 2,
+                1024, 0, 1, 2, // GTModelTypeGeoKey = geographic
+                2048, 0, 1, self.epsg_code])
+        } else {
+            Some(vec![1, 1, 0, 2,
+                1024, 0, 1, 1, // GTModelTypeGeoKey = projected
+                3072, 0, 1, self.epsg_code])
+        }
+    }
+}
+
+/// The definition of a coordinate system: a datum and a projection.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CoordinateSystem {
+    pub datum: Datum,
+    pub projection: Projection,
+}
+
+impl fmt::Display for SpatialReference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.is_defined() {
+            return write!(f, "not specified");
+        }
+        let mut s = String::new();
+        if self.epsg_code != 0 {
+            s = format!("EPSG:{}", self.epsg_code);
+        }
+        let name = self.name();
+        if !name.is_empty() {
+            s = if s.is_empty() { name } else { format!("{} ({})", s, name) };
+        }
+        if !self.wkt.is_empty() {
+            s = s + &format!("\nWKT: {}", self.wkt);
+        }
+        write!(f, "{}", s)
+    }
+}
+
+/// Returns true if an EPSG code is that of a geographic CRS. Codes found in the GeoTIFF
+/// code tables are classified by their table; others by the EPSG convention that
+/// geographic CRSs have codes from 4000 to 4999.
+pub fn is_geographic_epsg_code(epsg_code: u16) -> bool {
+    let kw_map = get_keyword_map();
+    if kw_map.get(&3072).is_some_and(|m| m.contains_key(&epsg_code)) {
+        return false;
+    }
+    if kw_map.get(&2048).is_some_and(|m| m.contains_key(&epsg_code)) {
+        return true;
+    }
+    (4000..5000).contains(&epsg_code)
+}
+
+/// Finds the EPSG code in the AUTHORITY (WKT1) or ID (WKT2) element of the outermost CRS
+/// definition of a WKT string. Returns zero if there is none.
+fn epsg_code_from_wkt(wkt: &str) -> u16 {
+    let bytes = wkt.as_bytes();
+    let mut depth = 0i32;
+    let mut in_quotes = false;
+    let mut code = 0u16;
+    for i in 0..bytes.len() {
+        match bytes[i] {
+            b'"' => in_quotes = !in_quotes,
+            b'[' | b'(' if !in_quotes => {
+                // an element that is a direct child of the outermost element
+                if depth == 1 {
+                    let start = wkt[..i].rfind([',', '[', '(']).map_or(0, |j| j + 1);
+                    let keyword = wkt[start..i].trim().to_uppercase();
+                    if keyword == "AUTHORITY" || keyword == "ID" {
+                        let end = wkt[i..].find([']', ')']).map_or(wkt.len(), |j| i + j);
+                        let parts: Vec<&str> = wkt[i + 1..end].split(',').map(|p| p.trim().trim_matches('"')).collect();
+                        if parts.len() >= 2 && parts[0].eq_ignore_ascii_case("EPSG") {
+                            code = parts[1].parse::<u16>().unwrap_or(0);
+                        }
+                    }
+                }
+                depth += 1;
+            },
+            b']' | b')' if !in_quotes => depth -= 1,
+            _ => {},
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use byteorder::{ByteOrder, LittleEndian};
+    use crate::raster::geotiff::geokeys::GeoKeys;
+    use super::*;
+
+    const UTM_11N_WKT: &str = "PROJCS[\"WGS 84 / UTM zone 11N\",GEOGCS[\"WGS 84\",DATUM[\"WGS_1984\",\
+SPHEROID[\"WGS 84\",6378137,298.257223563,AUTHORITY[\"EPSG\",\"7030\"]],AUTHORITY[\"EPSG\",\"6326\"]],\
+PRIMEM[\"Greenwich\",0],UNIT[\"degree\",0.0174532925199433],AUTHORITY[\"EPSG\",\"4326\"]],\
+PROJECTION[\"Transverse_Mercator\"],PARAMETER[\"central_meridian\",-117],UNIT[\"metre\",1],\
+AUTHORITY[\"EPSG\",\"32611\"]]";
+
+    fn geokeys(directory: &[u16]) -> GeoKeys {
+        let mut data = vec![0u8; directory.len() * 2];
+        LittleEndian::write_u16_into(directory, &mut data);
+        let mut geokeys = GeoKeys::default();
+        geokeys.add_key_directory(&data);
+        geokeys
+    }
+
+    #[test]
+    fn epsg_code_of_the_outermost_crs() {
+        let srs = SpatialReference::from_wkt(&format!("{}\0", UTM_11N_WKT));
+        assert_eq!(srs.epsg_code, 32611);
+        assert_eq!(srs.wkt, UTM_11N_WKT);
+        assert_eq!(srs.name(), "WGS 84 / UTM zone 11N");
+        assert!(!srs.is_geographic());
+
+        let wkt2 = "GEOGCRS[\"WGS 84\",DATUM[\"World Geodetic System 1984\",ELLIPSOID[\"WGS 84\",6378137,298.257223563]],\
+CS[ellipsoidal,2],ID[\"EPSG\",4326]]";
+        let srs = SpatialReference::from_wkt(wkt2);
+        assert_eq!(srs.epsg_code, 4326);
+        assert!(srs.is_geographic());
+
+        // the authority of the datum is not that of the CRS
+        let srs = SpatialReference::from_wkt("GEOGCS[\"Unknown\",DATUM[\"D\",SPHEROID[\"S\",6378137,298.26],AUTHORITY[\"EPSG\",\"6326\"]]]");
+        assert_eq!(srs.epsg_code, 0);
+        assert!(srs.is_defined() && srs.is_geographic());
+    }
+
+    #[test]
+    fn crs_of_geokeys() {
+        let projected = SpatialReference::from_geokeys(&geokeys(&[1, 1, 0, 2, 1024, 0, 1, 1, 3072, 0, 1, 26917]));
+        assert_eq!(projected, SpatialReference::from_epsg(26917));
+        assert_eq!(projected.name(), "PCS_NAD83_UTM_zone_17N");
+        assert_eq!(projected.to_string(), "EPSG:26917 (PCS_NAD83_UTM_zone_17N)");
+
+        let geographic = SpatialReference::from_geokeys(&geokeys(&[1, 1, 0, 2, 1024, 0, 1, 2, 2048, 0, 1, 4326]));
+        assert_eq!(geographic.epsg_code, 4326);
+        assert!(geographic.is_geographic());
+
+        let user_defined = SpatialReference::from_geokeys(&geokeys(&[1, 1, 0, 1, 3072, 0, 1, 32767]));
+        assert!(!user_defined.is_defined());
+        assert_eq!(user_defined.to_string(), "not specified");
+        assert!(!SpatialReference::from_geokeys(&GeoKeys::default()).is_defined());
+    }
+
+    #[test]
+    fn geokey_directory_of_a_crs() {
+        let directory = SpatialReference::from_epsg(32611).to_geokey_directory().unwrap();
+        assert_eq!(directory, vec![1, 1, 0, 2, 1024, 0, 1, 1, 3072, 0, 1, 32611]);
+        assert_eq!(SpatialReference::from_geokeys(&geokeys(&directory)).epsg_code, 32611);
+        let directory = SpatialReference::from_epsg(4326).to_geokey_directory().unwrap();
+        assert_eq!(&directory[4..], &[1024, 0, 1, 2, 2048, 0, 1, 4326]);
+        assert!(SpatialReference::from_wkt(UTM_11N_WKT).to_geokey_directory().is_some());
+        assert!(SpatialReference::default().to_geokey_directory().is_none());
+    }
+
+    #[test]
+    fn coordinate_systems_are_found_by_epsg_code() {
+        assert!(SpatialReference::from_epsg(32611).coordinate_system().is_ok());
+        assert_eq!(SpatialReference::default().coordinate_system().unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,411 @@
+use std::f64;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+use crate::spatial_ref_system::ellipsoid::Ellipsoid;
+
+/// The definition of a map projection. Angles are in degrees and the false eastings and
+/// northings in metres. The projected coordinates of a geographic 'projection' are the
+/// longitude and latitude themselves.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Projection {
+    Geographic,
+    TransverseMercator { lat0: f64, lon0: f64, k0: f64, false_easting: f64, false_northing: f64 },
+    /// The one standard parallel variant has `lat1 == lat2 == lat0`; the two standard
+    /// parallel variant has `k0 == 1`.
+    LambertConformalConic { lat0: f64, lon0: f64, lat1: f64, lat2: f64, k0: f64, false_easting: f64, false_northing: f64 },
+    AlbersEqualArea { lat0: f64, lon0: f64, lat1: f64, lat2: f64, false_easting: f64, false_northing: f64 },
+    /// Popular Visualisation Pseudo Mercator (EPSG:3857), i.e. spherical Mercator using the
+    /// semi-major axis of the ellipsoid as the radius.
+    WebMercator,
+}
+
+impl Projection {
+    /// Returns the Transverse Mercator projection of a UTM zone.
+    pub fn utm(zone: u8, north: bool) -> Projection {
+        Projection::TransverseMercator {
+            lat0: 0f64,
+            lon0: zone as f64 * 6f64 - 183f64,
+            k0: 0.9996,
+            false_easting: 500_000f64,
+            false_northing: if north { 0f64 } else { 10_000_000f64 },
+        }
+    }
+
+    /// Computes the constants of the projection for an ellipsoid.
+    pub fn prepare(&self, ellipsoid: &Ellipsoid) -> Projector {
+        match *self {
+            Projection::Geographic => Projector::Geographic,
+            Projection::TransverseMercator { lat0, lon0, k0, false_easting, false_northing } => {
+                Projector::TransverseMercator(TransverseMercator::new(ellipsoid, lat0, lon0, k0, false_easting, false_northing))
+            },
+            Projection::LambertConformalConic { lat0, lon0, lat1, lat2, k0, false_easting, false_northing } => {
+                Projector::LambertConformalConic(LambertConformalConic::new(ellipsoid, lat0, lon0, lat1, lat2, k0, false_easting, false_northing))
+            },
+            Projection::AlbersEqualArea { lat0, lon0, lat1, lat2, false_easting, false_northing } => {
+                Projector::AlbersEqualArea(AlbersEqualArea::new(ellipsoid, lat0, lon0, lat1, lat2, false_easting, false_northing))
+            },
+            Projection::WebMercator => Projector::WebMercator { radius: ellipsoid.a },
+        }
+    }
+}
+
+/// A projection whose constants have been computed for an ellipsoid. Geographic
+/// coordinates are longitude and latitude in degrees.
+#[derive(Clone, Debug)]
+pub enum Projector {
+    Geographic,
+    TransverseMercator(TransverseMercator),
+    LambertConformalConic(LambertConformalConic),
+    AlbersEqualArea(AlbersEqualArea),
+    WebMercator { radius: f64 },
+}
+
+impl Projector {
+    /// Projects geographic coordinates.
+    pub fn forward(&self, lon: f64, lat: f64) -> (f64, f64) {
+        match *self {
+            Projector::Geographic => (lon, lat),
+            Projector::TransverseMercator(ref p) => p.forward(lon, lat),
+            Projector::LambertConformalConic(ref p) => p.forward(lon, lat),
+            Projector::AlbersEqualArea(ref p) => p.forward(lon, lat),
+            Projector::WebMercator { radius } => {
+                (radius * lon.to_radians(), radius * (FRAC_PI_4 + lat.to_radians() / 2f64).tan().ln())
+            },
+        }
+    }
+
+    /// Returns the geographic coordinates of projected coordinates. The longitudes of
+    /// projected systems are normalized to the range -180 to 180.
+    pub fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let (lon, lat) = match *self {
+            Projector::Geographic => (x, y),
+            Projector::TransverseMercator(ref p) => p.inverse(x, y),
+            Projector::LambertConformalConic(ref p) => p.inverse(x, y),
+            Projector::AlbersEqualArea(ref p) => p.inverse(x, y),
+            Projector::WebMercator { radius } => {
+                ((x / radius).to_degrees(), (FRAC_PI_2 - 2f64 * (-y / radius).exp().atan()).to_degrees())
+            },
+        };
+        match *self {
+            Projector::Geographic => (lon, lat),
+            _ => (normalize_longitude(lon), lat),
+        }
+    }
+}
+
+/// Transverse Mercator, using Krüger's series in the sixth power of the third flattening
+/// (Karney, 2011, J. Geodesy 85:475-485), which are accurate to well below a millimetre
+/// within several thousand kilometres of the central meridian.
+#[derive(Clone, Debug)]
+pub struct TransverseMercator {
+    e: f64,
+    lon0: f64,
+    k0_a: f64,
+    false_easting: f64,
+    false_northing: f64,
+    y0: f64,
+    alpha: [f64; 6],
+    beta: [f64; 6],
+}
+
+impl TransverseMercator {
+    pub fn new(ellipsoid: &Ellipsoid, lat0: f64, lon0: f64, k0: f64, false_easting: f64, false_northing: f64) -> TransverseMercator {
+        let f = ellipsoid.f();
+        let n = f / (2f64 - f);
+        let (n2, n3) = (n * n, n * n * n);
+        let (n4, n5, n6) = (n3 * n, n3 * n2, n3 * n3);
+        // the rectifying radius
+        let a = ellipsoid.a / (1f64 + n) * (1f64 + n2 / 4f64 + n4 / 64f64 + n6 / 256f64);
+        let alpha = [
+            n / 2f64 - 2f64 * n2 / 3f64 + 5f64 * n3 / 16f64 + 41f64 * n4 / 180f64 - 127f64 * n5 / 288f64 + 7891f64 * n6 / 37800f64,
+            13f64 * n2 / 48f64 - 3f64 * n3 / 5f64 + 557f64 * n4 / 1440f64 + 281f64 * n5 / 630f64 - 1983433f64 * n6 / 1935360f64,
+            61f64 * n3 / 240f64 - 103f64 * n4 / 140f64 + 15061f64 * n5 / 26880f64 + 167603f64 * n6 / 181440f64,
+            49561f64 * n4 / 161280f64 - 179f64 * n5 / 168f64 + 6601661f64 * n6 / 7257600f64,
+            34729f64 * n5 / 80640f64 - 3418889f64 * n6 / 1995840f64,
+            212378941f64 * n6 / 319334400f64,
+        ];
+        let beta = [
+            n / 2f64 - 2f64 * n2 / 3f64 + 37f64 * n3 / 96f64 - n4 / 360f64 - 81f64 * n5 / 512f64 + 96199f64 * n6 / 604800f64,
+            n2 / 48f64 + n3 / 15f64 - 437f64 * n4 / 1440f64 + 46f64 * n5 / 105f64 - 1118711f64 * n6 / 3870720f64,
+            17f64 * n3 / 480f64 - 37f64 * n4 / 840f64 - 209f64 * n5 / 4480f64 + 5569f64 * n6 / 90720f64,
+            4397f64 * n4 / 161280f64 - 11f64 * n5 / 504f64 - 830251f64 * n6 / 7257600f64,
+            4583f64 * n5 / 161280f64 - 108847f64 * n6 / 3991680f64,
+            20648693f64 * n6 / 638668800f64,
+        ];
+        let mut tm = TransverseMercator {
+            e: ellipsoid.e(),
+            lon0: lon0.to_radians(),
+            k0_a: k0 * a,
+            false_easting,
+            false_northing,
+            y0: 0f64,
+            alpha,
+            beta,
+        };
+        // the northing of the latitude of origin on the central meridian
+        tm.y0 = tm.forward(lon0, lat0).1 - false_northing;
+        tm
+    }
+
+    pub fn forward(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let lat = lat.to_radians();
+        let dlon = lon.to_radians() - self.lon0;
+        let tau = lat.tan();
+        let sigma = (self.e * (self.e * tau / (1f64 + tau * tau).sqrt()).atanh()).sinh();
+        let tau_prime = tau * (1f64 + sigma * sigma).sqrt() - sigma * (1f64 + tau * tau).sqrt();
+        let xi_prime = tau_prime.atan2(dlon.cos());
+        let eta_prime = (dlon.sin() / (tau_prime * tau_prime + dlon.cos() * dlon.cos()).sqrt()).asinh();
+        let mut xi = xi_prime;
+        let mut eta = eta_prime;
+        for j in 0..6 {
+            let k = 2f64 * (j + 1) as f64;
+            xi += self.alpha[j] * (k * xi_prime).sin() * (k * eta_prime).cosh();
+            eta += self.alpha[j] * (k * xi_prime).cos() * (k * eta_prime).sinh();
+        }
+        (self.false_easting + self.k0_a * eta, self.false_northing + self.k0_a * xi - self.y0)
+    }
+
+    pub fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let xi = (y - self.false_northing + self.y0) / self.k0_a;
+        let eta = (x - self.false_easting) / self.k0_a;
+        let mut xi_prime = xi;
+        let mut eta_prime = eta;
+        for j in 0..6 {
+            let k = 2f64 * (j + 1) as f64;
+            xi_prime -= self.beta[j] * (k * xi).sin() * (k * eta).cosh();
+            eta_prime -= self.beta[j] * (k * xi).cos() * (k * eta).sinh();
+        }
+        let sinh_eta = eta_prime.sinh();
+        let (sin_xi, cos_xi) = (xi_prime.sin(), xi_prime.cos());
+        let tau_prime = sin_xi / (sinh_eta * sinh_eta + cos_xi * cos_xi).sqrt();
+        let dlon = sinh_eta.atan2(cos_xi);
+
+        // Newton's method for the latitude whose conformal latitude is tau_prime
+        let e2 = self.e * self.e;
+        let mut tau = tau_prime;
+        for _ in 0..10 {
+            let sigma = (self.e * (self.e * tau / (1f64 + tau * tau).sqrt()).atanh()).sinh();
+            let tau_i = tau * (1f64 + sigma * sigma).sqrt() - sigma * (1f64 + tau * tau).sqrt();
+            let delta = (tau_prime - tau_i) / (1f64 + tau_i * tau_i).sqrt()
+                * (1f64 + (1f64 - e2) * tau * tau) / ((1f64 - e2) * (1f64 + tau * tau).sqrt());
+            tau += delta;
+            if delta.abs() < 1e-12 { break; }
+        }
+        ((self.lon0 + dlon).to_degrees(), tau.atan().to_degrees())
+    }
+}
+
+/// Lambert Conformal Conic, with one or two standard parallels (EPSG methods 9801 and
+/// 9802).
+#[derive(Clone, Debug)]
+pub struct LambertConformalConic {
+    e: f64,
+    lon0: f64,
+    n: f64,
+    a_f: f64,
+    r0: f64,
+    false_easting: f64,
+    false_northing: f64,
+}
+
+impl LambertConformalConic {
+    pub fn new(ellipsoid: &Ellipsoid, lat0: f64, lon0: f64, lat1: f64, lat2: f64, k0: f64,
+        false_easting: f64, false_northing: f64) -> LambertConformalConic {
+        let e = ellipsoid.e();
+        let (lat0, lat1, lat2) = (lat0.to_radians(), lat1.to_radians(), lat2.to_radians());
+        let (m1, m2) = (conic_m(e, lat1), conic_m(e, lat2));
+        let (t1, t2) = (conic_t(e, lat1), conic_t(e, lat2));
+        let n = if (lat1 - lat2).abs() > 1e-10 {
+            (m1.ln() - m2.ln()) / (t1.ln() - t2.ln())
+        } else {
+            lat1.sin()
+        };
+        let f = m1 / (n * t1.powf(n));
+        let a_f = ellipsoid.a * f * k0;
+        LambertConformalConic {
+            e,
+            lon0: lon0.to_radians(),
+            n,
+            a_f,
+            r0: a_f * conic_t(e, lat0).powf(n),
+            false_easting,
+            false_northing,
+        }
+    }
+
+    pub fn forward(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let r = self.a_f * conic_t(self.e, lat.to_radians()).powf(self.n);
+        let theta = self.n * (lon.to_radians() - self.lon0);
+        (self.false_easting + r * theta.sin(), self.false_northing + self.r0 - r * theta.cos())
+    }
+
+    pub fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let dx = x - self.false_easting;
+        let dy = self.r0 - (y - self.false_northing);
+        let r = self.n.signum() * (dx * dx + dy * dy).sqrt();
+        let t = (r / self.a_f).powf(1f64 / self.n);
+        let theta = if self.n > 0f64 { dx.atan2(dy) } else { (-dx).atan2(-dy) };
+        let mut lat = FRAC_PI_2 - 2f64 * t.atan();
+        for _ in 0..15 {
+            let es = self.e * lat.sin();
+            let new_lat = FRAC_PI_2 - 2f64 * (t * ((1f64 - es) / (1f64 + es)).powf(self.e / 2f64)).atan();
+            if (new_lat - lat).abs() < 1e-12 {
+                lat = new_lat;
+                break;
+            }
+            lat = new_lat;
+        }
+        ((theta / self.n + self.lon0).to_degrees(), lat.to_degrees())
+    }
+}
+
+/// Albers Equal Area Conic (EPSG method 9822).
+#[derive(Clone, Debug)]
+pub struct AlbersEqualArea {
+    a: f64,
+    e: f64,
+    lon0: f64,
+    n: f64,
+    c: f64,
+    rho0: f64,
+    false_easting: f64,
+    false_northing: f64,
+}
+
+impl AlbersEqualArea {
+    pub fn new(ellipsoid: &Ellipsoid, lat0: f64, lon0: f64, lat1: f64, lat2: f64,
+        false_easting: f64, false_northing: f64) -> AlbersEqualArea {
+        let a = ellipsoid.a;
+        let e = ellipsoid.e();
+        let (lat0, lat1, lat2) = (lat0.to_radians(), lat1.to_radians(), lat2.to_radians());
+        let (m1, m2) = (conic_m(e, lat1), conic_m(e, lat2));
+        let (q1, q2) = (albers_q(e, lat1), albers_q(e, lat2));
+        let n = if (lat1 - lat2).abs() > 1e-10 {
+            (m1 * m1 - m2 * m2) / (q2 - q1)
+        } else {
+            lat1.sin()
+        };
+        let c = m1 * m1 + n * q1;
+        AlbersEqualArea {
+            a,
+            e,
+            lon0: lon0.to_radians(),
+            n,
+            c,
+            rho0: a * (c - n * albers_q(e, lat0)).sqrt() / n,
+            false_easting,
+            false_northing,
+        }
+    }
+
+    pub fn forward(&self, lon: f64, lat: f64) -> (f64, f64) {
+        let rho = self.a * (self.c - self.n * albers_q(self.e, lat.to_radians())).sqrt() / self.n;
+        let theta = self.n * (lon.to_radians() - self.lon0);
+        (self.false_easting + rho * theta.sin(), self.false_northing + self.rho0 - rho * theta.cos())
+    }
+
+    pub fn inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let dx = x - self.false_easting;
+        let dy = self.rho0 - (y - self.false_northing);
+        let rho = (dx * dx + dy * dy).sqrt();
+        let q = (self.c - rho * rho * self.n * self.n / (self.a * self.a)) / self.n;
+        let theta = if self.n > 0f64 { dx.atan2(dy) } else { (-dx).atan2(-dy) };
+
+        // iterate for the latitude with the authalic value q (Snyder, 1987, eq. 3-16)
+        let e = self.e;
+        let e2 = e * e;
+        let mut lat = (q / 2f64).clamp(-1f64, 1f64).asin();
+        for _ in 0..15 {
+            let sin_lat = lat.sin();
+            let one_minus = 1f64 - e2 * sin_lat * sin_lat;
+            let delta = one_minus * one_minus / (2f64 * lat.cos())
+                * (q / (1f64 - e2) - sin_lat / one_minus + 1f64 / (2f64 * e) * ((1f64 - e * sin_lat) / (1f64 + e * sin_lat)).ln());
+            lat += delta;
+            if delta.abs() < 1e-12 { break; }
+        }
+        ((theta / self.n + self.lon0).to_degrees(), lat.to_degrees())
+    }
+}
+
+fn normalize_longitude(lon: f64) -> f64 {
+    if !(-180f64..=180f64).contains(&lon) {
+        lon - 360f64 * ((lon + 180f64) / 360f64).floor()
+    } else {
+        lon
+    }
+}
+
+// The constants m and t of the conic projections (EPSG Guidance Note 7-2).
+fn conic_m(e: f64, lat: f64) -> f64 {
+    lat.cos() / (1f64 - e * e * lat.sin() * lat.sin()).sqrt()
+}
+
+fn conic_t(e: f64, lat: f64) -> f64 {
+    let es = e * lat.sin();
+    (FRAC_PI_4 - lat / 2f64).tan() / ((1f64 - es) / (1f64 + es)).powf(e / 2f64)
+}
+
+fn albers_q(e: f64, lat: f64) -> f64 {
+    let es = e * lat.sin();
+    (1f64 - e * e) * (lat.sin() / (1f64 - es * es) - 1f64 / (2f64 * e) * ((1f64 - es) / (1f64 + es)).ln())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::spatial_ref_system::ellipsoid::CLARKE_1866;
+    use crate::spatial_ref_system::epsg::coordinate_system_from_epsg;
+    use super::*;
+
+    fn dms(degrees: f64, minutes: f64, seconds: f64) -> f64 {
+        degrees.signum() * (degrees.abs() + minutes / 60f64 + seconds / 3600f64)
+    }
+
+    fn projector(epsg_code: u16) -> Projector {
+        let cs = coordinate_system_from_epsg(epsg_code).unwrap();
+        cs.projection.prepare(&cs.datum.ellipsoid)
+    }
+
+    /// Checks the projected coordinates of a point, to within `tolerance` metres, and that
+    /// they are projected back to the point, to within about the same distance.
+    fn assert_projects_to(p: &Projector, lon: f64, lat: f64, x: f64, y: f64, tolerance: f64) {
+        let (px, py) = p.forward(lon, lat);
+        assert!((px - x).abs() < tolerance && (py - y).abs() < tolerance, "({}, {}) != ({}, {})", px, py, x, y);
+        let (plon, plat) = p.inverse(x, y);
+        let tolerance_deg = tolerance / 100_000f64;
+        assert!((plon - lon).abs() < tolerance_deg && (plat - lat).abs() < tolerance_deg, "({}, {}) != ({}, {})", plon, plat, lon, lat);
+    }
+
+    #[test]
+    fn transverse_mercator() {
+        // EPSG Guidance Note 7-2, the example of Transverse Mercator (OSGB 1936 / British National Grid)
+        let p = projector(27700);
+        assert_projects_to(&p, dms(0.0, 30.0, 0.0), dms(50.0, 30.0, 0.0), 577_274.99, 69_740.50, 0.01);
+        // Ordnance Survey, A Guide to Coordinate Systems in Great Britain, Annex C (Caister water tower)
+        assert_projects_to(&p, dms(1.0, 43.0, 4.5177), dms(52.0, 39.0, 27.2531), 651_409.903, 313_177.270, 0.001);
+    }
+
+    #[test]
+    fn lambert_conformal_conic() {
+        // EPSG Guidance Note 7-2, the example of Lambert Conic Conformal (2SP) (NAD27 / Texas
+        // South Central), whose false easting and coordinates are in US survey feet
+        let us_foot = 1200f64 / 3937f64;
+        let p = Projection::LambertConformalConic { lat0: dms(27.0, 50.0, 0.0), lon0: -99.0, lat1: dms(28.0, 23.0, 0.0), lat2: dms(30.0, 17.0, 0.0),
+            k0: 1.0, false_easting: 2_000_000.0 * us_foot, false_northing: 0.0 }.prepare(&CLARKE_1866);
+        assert_projects_to(&p, -96.0, 28.5, 2_963_503.91 * us_foot, 254_759.80 * us_foot, 0.01 * us_foot);
+
+        // RGF93 / Lambert-93 is defined by the coordinates of its origin, 3°E 46°30'N
+        assert_projects_to(&projector(2154), 3.0, 46.5, 700_000.0, 6_600_000.0, 0.001);
+    }
+
+    #[test]
+    fn albers_equal_area() {
+        // Guidance Note 7-2 gives no example of Albers, so the example of Snyder (1987, Map
+        // Projections: A Working Manual, p. 292) is used. Its parameters are those of the
+        // conterminous US Albers of EPSG 5070, on the Clarke 1866 ellipsoid.
+        let p = Projection::AlbersEqualArea { lat0: 23.0, lon0: -96.0, lat1: 29.5, lat2: 45.5,
+            false_easting: 0.0, false_northing: 0.0 }.prepare(&CLARKE_1866);
+        assert_projects_to(&p, -75.0, 35.0, 1_885_472.7, 1_535_925.0, 0.1);
+
+        // NAD83 / Conus Albers has its origin at 96°W 23°N
+        assert_projects_to(&projector(5070), -96.0, 23.0, 0.0, 0.0, 0.001);
+    }
+}
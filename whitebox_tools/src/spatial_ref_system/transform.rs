@@ -0,0 +1,81 @@
+use std::f64;
+use std::io::Error;
+use crate::spatial_ref_system::SpatialReference;
+use crate::spatial_ref_system::datum::Datum;
+use crate::spatial_ref_system::projections::Projector;
+
+/// Transforms coordinates from one coordinate reference system to another: the source
+/// coordinates are unprojected, shifted between the datums via WGS84 if the datums
+/// differ, and projected into the target system.
+///
+/// Example:
+///
+/// ```ignore
+/// let transform = CoordinateTransform::new(&SpatialReference::from_epsg(26917), &SpatialReference::from_epsg(32617))?;
+/// let (x, y) = transform.transform_xy(630_084.0, 4_833_439.0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct CoordinateTransform {
+    source: Projector,
+    source_datum: Datum,
+    target: Projector,
+    target_datum: Datum,
+    shift_datum: bool,
+}
+
+impl CoordinateTransform {
+    pub fn new(source: &SpatialReference, target: &SpatialReference) -> Result<CoordinateTransform, Error> {
+        let source = source.coordinate_system()?;
+        let target = target.coordinate_system()?;
+        Ok(CoordinateTransform {
+            source: source.projection.prepare(&source.datum.ellipsoid),
+            source_datum: source.datum,
+            target: target.projection.prepare(&target.datum.ellipsoid),
+            target_datum: target.datum,
+            shift_datum: source.datum != target.datum,
+        })
+    }
+
+    /// Transforms a point. The z value is treated as a height above the source ellipsoid
+    /// and is returned as a height above the target ellipsoid.
+    pub fn transform(&self, x: f64, y: f64, z: f64) -> (f64, f64, f64) {
+        let (lon, lat) = self.source.inverse(x, y);
+        let (lon, lat, h) = if self.shift_datum {
+            let (lon, lat, h) = self.source_datum.shift_to_wgs84(lon, lat, z);
+            self.target_datum.shift_from_wgs84(lon, lat, h)
+        } else {
+            (lon, lat, z)
+        };
+        let (x, y) = self.target.forward(lon, lat);
+        (x, y, h)
+    }
+
+    /// Returns the extent (min x, min y, max x, max y) of the transformed bounding box of
+    /// an area. Points along its edges are transformed, as well as its corners, because the
+    /// edges of the box are generally curved in the target system.
+    pub fn transform_extent(&self, min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> (f64, f64, f64, f64) {
+        let num_steps = 20;
+        let (mut ext_min_x, mut ext_min_y) = (f64::INFINITY, f64::INFINITY);
+        let (mut ext_max_x, mut ext_max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for i in 0..(num_steps + 1) {
+            let fx = min_x + (max_x - min_x) * i as f64 / num_steps as f64;
+            let fy = min_y + (max_y - min_y) * i as f64 / num_steps as f64;
+            for &(x, y) in &[(fx, min_y), (fx, max_y), (min_x, fy), (max_x, fy)] {
+                let (x, y) = self.transform_xy(x, y);
+                if x < ext_min_x { ext_min_x = x; }
+                if x > ext_max_x { ext_max_x = x; }
+                if y < ext_min_y { ext_min_y = y; }
+                if y > ext_max_y { ext_max_y = y; }
+            }
+        }
+        (ext_min_x, ext_min_y, ext_max_x, ext_max_y)
+    }
+
+    /// Transforms the horizontal coordinates of a point. This is appropriate when the
+    /// heights are relative to the geoid, as most elevations are, which the datum shift
+    /// leaves unchanged.
+    pub fn transform_xy(&self, x: f64, y: f64) -> (f64, f64) {
+        let (x, y, _) = self.transform(x, y, 0f64);
+        (x, y)
+    }
+}
@@ -18,31 +18,29 @@ impl<T: Copy> Array2D<T> {
             return Err(Error::new(ErrorKind::InvalidData, "Only non-negative rows and columns values accepted."));
         }
         let array = Array2D{
-            columns: columns,
-            rows: rows,
-            nodata: nodata,
+            columns,
+            rows,
+            nodata,
             data: vec![initial_value; (rows * columns) as usize],
         };
         Ok(array)
     }
 
     pub fn set_value(&mut self, row: isize, column: isize, value: T) {
-        if column >= 0 && row >= 0 {
-            if column < self.columns && row < self.rows {
+        if column >= 0 && row >= 0
+            && column < self.columns && row < self.rows {
                 let idx = row * self.columns + column;
                 self.data[idx as usize] = value;
             }
-        }
     }
 
     pub fn set_row_data(&mut self, row: isize, values: Vec<T>) {
         for column in 0..values.len() as isize  {
-            if row >= 0 {
-                if column < self.columns && row < self.rows {
+            if row >= 0
+                && column < self.columns && row < self.rows {
                     let idx = row * self.columns + column;
                     self.data[idx as usize] = values[column as usize];
                 }
-            }
         }
     }
 
@@ -63,7 +61,7 @@ impl<T: Copy> Array2D<T> {
 impl<T: Copy> Index<(isize, isize)> for Array2D<T> {
     type Output = T;
 
-    fn index<'a>(&'a self, index: (isize, isize)) -> &'a T {
+    fn index(&self, index: (isize, isize)) -> &T {
         let row = index.0;
         let column = index.1;
         if column < 0 { return &self.nodata; }
@@ -76,7 +74,7 @@ impl<T: Copy> Index<(isize, isize)> for Array2D<T> {
 }
 
 impl<T: Copy> IndexMut<(isize, isize)> for Array2D<T> {
-    fn index_mut<'a>(&'a mut self, index: (isize, isize)) -> &'a mut T {
+    fn index_mut(&mut self, index: (isize, isize)) -> &mut T {
         let row = index.0;
         let column = index.1;
         if column < 0 { return &mut self.nodata; }
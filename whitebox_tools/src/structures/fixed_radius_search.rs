@@ -42,9 +42,9 @@ impl<T: Copy> FixedRadiusSearch<T> {
             r: radius,
             r_sqr: radius*radius,
             hm: map,
-            values: values,
+            values,
             length: 0usize,
-            num_cpus: num_cpus,
+            num_cpus,
             run_concurrently: false
         }
     }
@@ -55,7 +55,7 @@ impl<T: Copy> FixedRadiusSearch<T> {
            Vacant(entry) => entry.insert(vec![]),
            Occupied(entry) => entry.into_mut(),
         };
-        val.push(FixedRadiusSearchEntry { x: x, y: y, index: self.length, dist: -1f64});
+        val.push(FixedRadiusSearchEntry { x, y, index: self.length, dist: -1f64});
         self.values.push(value);
         self.length += 1;
 
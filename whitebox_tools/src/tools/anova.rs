@@ -1,104 +1,63 @@
-// #![allow(dead_code, unused_assignments)]
-
-extern crate whitebox_tools;
-
 use std::io::Error;
 use std::io::ErrorKind;
 use std::process::Command;
 use std::io::BufWriter;
 use std::fs::File;
 use std::io::prelude::*;
-use std::env;
-use std::path;
 use std::path::Path;
 use std::f64;
 use std::f64::consts::PI;
-use whitebox_tools::raster::*;
-
-fn main() {
-    let sep: String = path::MAIN_SEPARATOR.to_string();
-    let mut input_file = String::new();
-    let mut output_file = String::new();
-    let mut feature_file = String::new();
-    let mut working_directory: String = "".to_string();
-    let mut verbose: bool = false;
-    let mut keyval: bool;
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 { panic!("Tool run with no paramters. Please see help (-h) for parameter descriptions."); }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--input" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-features" || vec[0].to_lowercase() == "--features" {
-            if keyval {
-                feature_file = vec[1].to_string();
-            } else {
-                feature_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--output" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-wd" || vec[0].to_lowercase() == "--wd" {
-            if keyval {
-                working_directory = vec[1].to_string();
-            } else {
-                working_directory = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-v" || vec[0].to_lowercase() == "--verbose" {
-            verbose = true;
-        } else if vec[0].to_lowercase() == "-h" || vec[0].to_lowercase() == "--help" ||
-            vec[0].to_lowercase() == "--h"{
-            let mut s: String = "Help:\n".to_owned();
-                     s.push_str("-i          Input raster file.\n");
-                     s.push_str("-o          Output HTML file.\n");
-                     s.push_str("-features   Feature definition (or class) raster.\n");
-                     s.push_str("-wd         Optional working directory. If specified, filenames parameters need not include a full path.\n");
-                     s.push_str("-version    Prints the tool version number.\n");
-                     s.push_str("-h          Prints help information.\n\n");
-                     s.push_str("Example usage:\n\n");
-                     s.push_str(&">> .*anova -wd *path*to*data* -i input.tif -features classes.tif -o anova.html -v\n".replace("*", &sep));
-            println!("{}", s);
-            return;
-        } else if vec[0].to_lowercase() == "-version" || vec[0].to_lowercase() == "--version" {
-            const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
-            println!("slope v{}", VERSION.unwrap_or("unknown"));
-            return;
-        }
-    }
+use crate::raster::*;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
 
-    if !working_directory.ends_with(&sep) {
-        working_directory.push_str(&(sep.to_string()));
+pub struct Anova;
+
+impl WhiteboxTool for Anova {
+    fn get_tool_name(&self) -> String {
+        "anova".to_string()
     }
 
-    if !input_file.contains(&sep) {
-        input_file = format!("{}{}", working_directory, input_file);
+    fn get_tool_description(&self) -> String {
+        let s = "Performs a one-way analysis of variance (ANOVA) test on the values of a raster grouped by the classes of a
+feature definition raster, and outputs the results as an HTML report.";
+
+        s.to_string()
     }
-    if !output_file.contains(&sep) {
-        output_file = format!("{}{}", working_directory, output_file);
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::required("Feature Definition File", &["--features"], ParameterType::ExistingFile, "Feature definition (or class) raster.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output HTML file.").with_file_type(ParameterFileType::Html),
+        ]
     }
-    if !output_file.ends_with(".html") {
-        output_file = output_file + ".html";
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=anova --wd=\"/dir/to/data\" --args=\"-i=input.tif --features=classes.tif -o=anova.html\"";
+        Some(s.to_string())
     }
-    if !feature_file.contains(&sep) {
-        feature_file = format!("{}{}", working_directory, feature_file);
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
     }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let feature_file = args.get_string("--features")?;
+    let mut output_file = args.get_string("--output")?;
 
-    match anova(input_file, feature_file, output_file, verbose) {
-        Ok(()) => println!("Complete!"),
-        Err(err) => panic!("Error: {}", err),
+    if !output_file.ends_with(".html") {
+        output_file += ".html";
     }
+
+    anova(input_file, feature_file, output_file, verbose)?;
+    println!("Complete!");
+
+    Ok(())
 }
 
 fn anova(input_file: String, feature_file: String, output_file: String, verbose: bool) ->  Result<(), Error> {
@@ -190,21 +149,18 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
     let s1 = &format!("<p><strong>Nominal variable:</strong> {}</p><br>", path.file_name().unwrap().to_str().unwrap());
     writer.write_all(s1.as_bytes())?;
 
-    let features = match Raster::new(&feature_file, "r") {
-        Ok(f) => f,
-        Err(err) => panic!("Error: {}", err),
-    };
+    let features = Raster::new(&feature_file, "r")?;
     let nodata_features = features.configs.nodata;
     if features.configs.columns != columns || features.configs.rows != rows {
-        panic!(Error::new(ErrorKind::InvalidInput, "The input and feature definition rasters must have the same number of rows and columns."));
+        return Err(Error::new(ErrorKind::InvalidInput, "The input and feature definition rasters must have the same number of rows and columns."));
     }
     // How many features/classes are there?
     let mut z: f64;
     let mut id: f64;
     let mut id_int: i32;
     let mut vec_id: usize;
-    let mut min_id = i32::max_value();
-    let mut max_id = i32::min_value();
+    let mut min_id = i32::MAX;
+    let mut max_id = i32::MIN;
     let mut overall_sum = 0f64;
     let mut overall_sum_sqr = 0f64;
     //let mut overall_k = -9999f64;
@@ -229,10 +185,10 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
                 overall_sum_sqr += z * z; //(z - overall_k) * (z - overall_k);
             }
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Progress (loop 1 of 2): {}%", progress);
+                update_progress("Progress (loop 1 of 2)", progress)?;
                 old_progress = progress;
             }
         }
@@ -266,10 +222,10 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
                 sum_sqr[vec_id] += z * z; //(z - k[vec_id]) * (z - k[vec_id]);
             }
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Progress (loop 2 of 2): {}%", progress);
+                update_progress("Progress (loop 2 of 2)", progress)?;
                 old_progress = progress;
             }
         }
@@ -336,13 +292,13 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
             let s1 = &format!("<tr>
                 <td class=\"numberCell\">{}</td>
                 <td class=\"numberCell\">{}</td>
-                <td class=\"numberCell\">{}</td>
-                <td class=\"numberCell\">{}</td>
+                <td class=\"numberCell\">{:.4}</td>
+                <td class=\"numberCell\">{:.4}</td>
             </tr>\n",
             i as i32 + min_id,
             n[i],
-            format!("{:.*}", 4, mean[i]),
-            format!("{:.*}", 4, variance[i].sqrt()));
+            mean[i],
+            variance[i].sqrt());
             writer.write_all(s1.as_bytes())?;
         }
     }
@@ -350,12 +306,12 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
     let s1 = &format!("<tr>
         <td class=\"numberCell\">Overall</td>
         <td class=\"numberCell\">{}</td>
-        <td class=\"numberCell\">{}</td>
-        <td class=\"numberCell\">{}</td>
+        <td class=\"numberCell\">{:.4}</td>
+        <td class=\"numberCell\">{:.4}</td>
     </tr>\n",
     overall_n,
-    format!("{:.*}", 4, overall_mean),
-    format!("{:.*}", 4, overall_variance.sqrt()));
+    overall_mean,
+    overall_variance.sqrt());
     writer.write_all(s1.as_bytes())?;
 
     s = "</table>";
@@ -385,41 +341,41 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
 
     let s1 = &format!("<tr>
         <td class=\"numberCell\">Between groups</td>
+        <td class=\"numberCell\">{:.3}</td>
         <td class=\"numberCell\">{}</td>
-        <td class=\"numberCell\">{}</td>
-        <td class=\"numberCell\">{}</td>
-        <td class=\"numberCell\">{}</td>
+        <td class=\"numberCell\">{:.3}</td>
+        <td class=\"numberCell\">{:.3}</td>
         <td class=\"numberCell\">{}</td>
     </tr>\n",
-    format!("{:.*}", 3, ss_b),
+    ss_b,
     df_b,
-    format!("{:.*}", 3, ms_b),
-    format!("{:.*}", 3, f),
+    ms_b,
+    f,
     p_str);
     writer.write_all(s1.as_bytes())?;
 
     let s1 = &format!("<tr>
         <td class=\"numberCell\">Within groups</td>
+        <td class=\"numberCell\">{:.3}</td>
         <td class=\"numberCell\">{}</td>
-        <td class=\"numberCell\">{}</td>
-        <td class=\"numberCell\">{}</td>
+        <td class=\"numberCell\">{:.3}</td>
         <td class=\"numberCell\"></td>
         <td class=\"numberCell\"></td>
     </tr>\n",
-    format!("{:.*}", 3, ss_w),
+    ss_w,
     df_w,
-    format!("{:.*}", 3, ms_w));
+    ms_w);
     writer.write_all(s1.as_bytes())?;
 
     let s1 = &format!("<tr>
         <td class=\"numberCell\">Total variation</td>
-        <td class=\"numberCell\">{}</td>
+        <td class=\"numberCell\">{:.3}</td>
         <td class=\"numberCell\">{}</td>
         <td class=\"numberCell\"></td>
         <td class=\"numberCell\"></td>
         <td class=\"numberCell\"></td>
     </tr>\n",
-    format!("{:.*}", 3, ss_t),
+    ss_t,
     df_t);
     writer.write_all(s1.as_bytes())?;
 
@@ -427,17 +383,16 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
     writer.write_all(s.as_bytes())?;
 
     if p < 0.05 {
-        let p_str;
-        if p > 0.0001 {
-            p_str = format!("={:.*}", 5, p);
+        let p_str = if p > 0.0001 {
+            format!("={:.*}", 5, p)
         } else {
-            p_str = format!("< 0.0001");
-        }
+            "< 0.0001".to_string()
+        };
         let s1 = &format!("<br><br>
         <h3>Interpretation:</h3>
         <p>The null hypothesis states that the means of the measurement variable are the same for the different categories of data;
         the alternative hypothesis states that they are not all the same.
-        The analysis showed that the category means were significantly heterogeneous (one-way anova, F<sub>&alpha;=0.05, df1={}, df2={}</sub>={}, p{}), i.e.
+        The analysis showed that the category means were significantly heterogeneous (one-way anova, F<sub>&alpha;=0.05, df1={}, df2={}</sub>={:.3}, p{}), i.e.
         using an &alpha; of 0.05 the null hypothesis should be <strong>rejected</strong>.</p>
         <p>Caveat: Given a sufficiently large sample, extremely small and non-notable differences can be found to be statistically significant
         and statistical significance says nothing about the practical significance of a difference.</p>
@@ -452,7 +407,7 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
         </ol>",
         df_b,
         df_w,
-        format!("{:.*}", 3, f),
+        f,
         p_str);
         writer.write_all(s1.as_bytes())?;
     } else {
@@ -460,7 +415,7 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
         <h3>Interpretation:</h3>
         <p>The null hypothesis states that the means of the measurement variable are the same for the different categories of data;
         the alternative hypothesis states that they are not all the same.
-        The analysis showed that the category means were not significantly different (one-way anova, F<sub>&alpha;=0.05, df1={}, df2={}</sub>={}, p{}), i.e.
+        The analysis showed that the category means were not significantly different (one-way anova, F<sub>&alpha;=0.05, df1={}, df2={}</sub>={:.3}, p={:.3}), i.e.
         using an &alpha; of 0.05 the null hypothesis should be <strong>accepted</strong>.</p>
         <p>Caveat: Given a sufficiently large sample, extremely small and non-notable differences can be found to be statistically significant
         and statistical significance says nothing about the practical significance of a difference.</p>
@@ -475,8 +430,8 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
         </ol>",
         df_b,
         df_w,
-        format!("{:.*}", 3, f),
-        format!("={:.*}", 3, p));
+        f,
+        p);
         writer.write_all(s1.as_bytes())?;
     }
 
@@ -485,12 +440,8 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
 
     let _ = writer.flush();
 
-    let output = Command::new("open")
-                     .arg(output_file)
-                     .output()
-                     .expect("failed to execute process");
-
-    let _ = output.stdout;
+    // open the report for viewing, where the system provides a viewer
+    let _ = Command::new("open").arg(output_file).output();
 
     Ok(())
 }
@@ -506,13 +457,11 @@ fn anova(input_file: String, feature_file: String, output_file: String, verbose:
 // }
 
 fn f_call(x: f64) -> f64 {
-  let zk;
   if x >= 0.0 {
-    zk = x + 0.0000005;
+    x + 0.0000005
   } else {
-    zk = x - 0.0000005;
+    x - 0.0000005
   }
-  zk
 }
 
 const PJ2: f64 = PI / 2.0;
@@ -541,9 +490,9 @@ fn f_spin(f: f64, df1: usize, df2: usize) -> f64 {
     let mut k = 2.0;
     while k <= (df2 as f64 - 1.0) / 2.0 {
       c = c * k / (k - 0.5);
-      k = k + 1.0;
+      k += 1.0;
     }
-    return 1.0 - a + c;
+    1.0 - a + c
 }
 
 fn lj_spin(q: f64, i: f64, j: f64, b: f64) -> f64 {
@@ -552,8 +501,8 @@ fn lj_spin(q: f64, i: f64, j: f64, b: f64) -> f64 {
    let mut k = i;
    while k <= j {
      zz = zz * q * k / (k - b);
-     z = z + zz;
-     k = k + 2.0;
+     z += zz;
+     k += 2.0;
    }
    z
 }
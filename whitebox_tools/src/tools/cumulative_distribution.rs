@@ -1,100 +1,59 @@
-// #![allow(dead_code, unused_assignments)]
-
-extern crate whitebox_tools;
-
 use std::io::Error;
 use std::io::ErrorKind;
 use std::process::Command;
 use std::io::BufWriter;
 use std::fs::File;
 use std::io::prelude::*;
-use std::env;
-use std::path;
 use std::f64;
-use whitebox_tools::raster::*;
-
-fn main() {
-    let sep: String = path::MAIN_SEPARATOR.to_string();
-    let mut input_file = String::new();
-    let mut output_file = String::new();
-    let mut feature_file = String::new();
-    let mut working_directory: String = "".to_string();
-    let mut verbose: bool = false;
-    let mut keyval: bool;
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 { panic!("Tool run with no paramters. Please see help (-h) for parameter descriptions."); }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--input" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-features" || vec[0].to_lowercase() == "--features" {
-            if keyval {
-                feature_file = vec[1].to_string();
-            } else {
-                feature_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--output" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-wd" || vec[0].to_lowercase() == "--wd" {
-            if keyval {
-                working_directory = vec[1].to_string();
-            } else {
-                working_directory = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-v" || vec[0].to_lowercase() == "--verbose" {
-            verbose = true;
-        } else if vec[0].to_lowercase() == "-h" || vec[0].to_lowercase() == "--help" ||
-            vec[0].to_lowercase() == "--h"{
-            let mut s: String = "Help:\n".to_owned();
-                     s.push_str("-i          Input raster file.\n");
-                     s.push_str("-o          Output HTML file.\n");
-                     s.push_str("-features   Optional feature definition (or class) raster.\n");
-                     s.push_str("-wd         Optional working directory. If specified, filenames parameters need not include a full path.\n");
-                     s.push_str("-version    Prints the tool version number.\n");
-                     s.push_str("-h          Prints help information.\n\n");
-                     s.push_str("Example usage:\n\n");
-                     s.push_str(&">> .*cumulative_distribution -wd *path*to*data* -i input.tif -features classes.tif -o distros.html -v\n".replace("*", &sep));
-                     s.push_str(&">> .*cumulative_distribution -wd *path*to*data* -i input.tif -o distros.html -v\n".replace("*", &sep));
-            println!("{}", s);
-            return;
-        } else if vec[0].to_lowercase() == "-version" || vec[0].to_lowercase() == "--version" {
-            const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
-            println!("slope v{}", VERSION.unwrap_or("unknown"));
-            return;
-        }
+use crate::raster::*;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+pub struct CumulativeDistribution;
+
+impl WhiteboxTool for CumulativeDistribution {
+    fn get_tool_name(&self) -> String {
+        "cumulative_distribution".to_string()
     }
 
-    if !working_directory.ends_with(&sep) {
-        working_directory.push_str(&(sep.to_string()));
+    fn get_tool_description(&self) -> String {
+        let s = "Outputs an HTML report of the cumulative distribution of the values of a raster, either overall or for each of
+the classes of a feature definition raster.";
+
+        s.to_string()
     }
 
-    if !input_file.contains(&sep) {
-        input_file = format!("{}{}", working_directory, input_file);
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::optional("Feature Definition File", &["--features"], ParameterType::ExistingFile, None, "Feature definition (or class) raster.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output HTML file.").with_file_type(ParameterFileType::Html),
+        ]
     }
-    if !output_file.contains(&sep) {
-        output_file = format!("{}{}", working_directory, output_file);
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=cumulative_distribution --wd=\"/dir/to/data\" --args=\"-i=input.tif --features=classes.tif -o=distros.html\"
+./whitebox-tools -r=cumulative_distribution --wd=\"/dir/to/data\" --args=\"-i=input.tif -o=distros.html\"";
+        Some(s.to_string())
     }
-    if !output_file.ends_with(".html") {
-        output_file = output_file + ".html";
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
     }
-    if !feature_file.contains(&sep) && feature_file.len() != 0 {
-        feature_file = format!("{}{}", working_directory, feature_file);
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let feature_file = args.get_string("--features").unwrap_or_default();
+    let mut output_file = args.get_string("--output")?;
+
+    if !output_file.ends_with(".html") {
+        output_file += ".html";
     }
 
-    let _ = cumulative_distribution(input_file, feature_file, output_file, verbose);
+    cumulative_distribution(input_file, feature_file, output_file, verbose)
 }
 
 fn cumulative_distribution(input_file: String, feature_file: String, output_file: String, verbose: bool) ->  Result<(), Error> {
@@ -105,10 +64,7 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
     }
 
     println!("Reading data...");
-    let input = match Raster::new(&input_file, "r") {
-        Ok(f) => f,
-        Err(err) => panic!("Error: {}", err),
-    };
+    let input = Raster::new(&input_file, "r")?;
     let nodata = input.configs.nodata;
     let columns = input.configs.columns;
     let rows = input.configs.rows;
@@ -166,22 +122,19 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
     ";
     writer.write_all(s.as_bytes())?;
 
-    if feature_file.len() > 0 {
-        let features = match Raster::new(&feature_file, "r") {
-            Ok(f) => f,
-            Err(err) => panic!("Error: {}", err),
-        };
+    if !feature_file.is_empty() {
+        let features = Raster::new(&feature_file, "r")?;
         let nodata_features = features.configs.nodata;
         if features.configs.columns != columns || features.configs.rows != rows {
-            panic!(Error::new(ErrorKind::InvalidInput, "The input and feature definition rasters must have the same number of rows and columns."));
+            return Err(Error::new(ErrorKind::InvalidInput, "The input and feature definition rasters must have the same number of rows and columns."));
         }
         // How many features/classes are there?
         let mut z: f64;
         let mut id: f64;
         let mut id_int: i32;
         let mut vec_id: usize;
-        let mut min_id = i32::max_value();
-        let mut max_id = i32::min_value();
+        let mut min_id = i32::MAX;
+        let mut max_id = i32::MIN;
         let mut progress: usize;
         let mut old_progress: usize = 1;
         for row in 0..rows as isize {
@@ -194,10 +147,10 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
                     if id_int < min_id { min_id = id_int; }
                 }
             }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
                 if progress != old_progress {
-                    println!("Progress (loop 1 of 2): {}%", progress);
+                    update_progress("Progress (loop 1 of 2)", progress)?;
                     old_progress = progress;
                 }
             }
@@ -205,7 +158,7 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
 
         let range = (max_id - min_id + 1) as usize;
         if range > 255 {
-            panic!(Error::new(ErrorKind::InvalidInput, "The feature definition raster has greater than 255 features/classes."));
+            return Err(Error::new(ErrorKind::InvalidInput, "The feature definition raster has greater than 255 features/classes."));
         }
 
         //println!("min {} max {} range {}", min_id, max_id, range);
@@ -226,10 +179,10 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
                     n[vec_id] += 1;
                 }
             }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
                 if progress != old_progress {
-                    println!("Progress (loop 2 of 2): {}%", progress);
+                    update_progress("Progress (loop 2 of 2)", progress)?;
                     old_progress = progress;
                 }
             }
@@ -262,11 +215,11 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
                 // writer.write_all("Percentile,Value\n".as_bytes())?;
                 let s1 = &format!("<tr>
                     <td class=\"numberCell\">0.0</td>
-                    <td class=\"numberCell\">{}</td>
-                    </tr>\n", format!("{:.*}", 4, data[i][0]));
+                    <td class=\"numberCell\">{:.4}</td>
+                    </tr>\n", data[i][0]);
                 writer.write_all(s1.as_bytes())?;
                 let mut val: f64;
-                for j in 1..100 as usize {
+                for j in 1..100_usize {
                     let percentile = j as f64;
                     let a = percentile / 100f64 * n[i] as f64;
                     let b = a.floor();
@@ -276,7 +229,7 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
                     } else {
                         val = data[i][b as usize];
                     }
-                    let s1 = &format!("<tr><td class=\"numberCell\">{}</td><td class=\"numberCell\">{}</td></tr>\n", format!("{:.*}", 1, percentile), format!("{:.*}", 4, val));
+                    let s1 = &format!("<tr><td class=\"numberCell\">{:.1}</td><td class=\"numberCell\">{:.4}</td></tr>\n", percentile, val);
                     writer.write_all(s1.as_bytes())?;
                 }
 
@@ -300,10 +253,10 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
                     data.push(z);
                 }
             }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
                 if progress != old_progress {
-                    println!("Progress: {}%", progress);
+                    update_progress("Progress", progress)?;
                     old_progress = progress;
                 }
             }
@@ -324,8 +277,8 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
         writer.write_all(s.as_bytes())?;
         let s1 = &format!("<tr>
             <td class=\"numberCell\">0.0</td>
-            <td class=\"numberCell\">{}</td>
-            </tr>\n", format!("{:.*}", 4, data[0]));
+            <td class=\"numberCell\">{:.4}</td>
+            </tr>\n", data[0]);
         writer.write_all(s1.as_bytes())?;
 
         // let mut s = format!("\nn={}", n);
@@ -335,7 +288,7 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
         // s = format!("0,{}", data[0]);
         // writer.write_all(s.as_bytes())?;
         let mut val: f64;
-        for j in 1..100 as usize {
+        for j in 1..100_usize {
             let percentile = j as f64;
             let a = percentile / 100f64 * n as f64;
             let b = a.floor();
@@ -347,7 +300,7 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
             }
             //println!("{},{}", percentile, val);
             //s = format!("{},{}", percentile, val);
-            let s1 = &format!("<tr><td class=\"numberCell\">{}</td><td class=\"numberCell\">{}</td></tr>\n", format!("{:.*}", 1, percentile), format!("{:.*}", 4, val));
+            let s1 = &format!("<tr><td class=\"numberCell\">{:.1}</td><td class=\"numberCell\">{:.4}</td></tr>\n", percentile, val);
             writer.write_all(s1.as_bytes())?;
         }
     }
@@ -359,12 +312,8 @@ fn cumulative_distribution(input_file: String, feature_file: String, output_file
 
     println!("Complete! Please see {} for output", output_file);
 
-    let output = Command::new("open")
-                     .arg(output_file)
-                     .output()
-                     .expect("failed to execute process");
-
-    let _ = output.stdout;
+    // open the report for viewing, where the system provides a viewer
+    let _ = Command::new("open").arg(output_file).output();
 
     Ok(())
 }
@@ -0,0 +1,367 @@
+extern crate time;
+extern crate num_cpus;
+
+use std::io;
+use std::io::Error;
+use std::f64;
+use std::cmp::max;
+use std::sync::Arc;
+use std::sync::mpsc;
+use std::thread;
+use crate::raster::*;
+use crate::raster::window::RasterBlockWriter;
+use crate::structures::array2d::Array2D;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+const TOOL_NAME: &str = "dev_from_mean_elev";
+
+/// The maximum number of cells of a block of the DEM that is read and processed at once.
+const MAX_BLOCK_CELLS: usize = 4_000_000;
+
+pub struct DevFromMeanElev;
+
+impl WhiteboxTool for DevFromMeanElev {
+    fn get_tool_name(&self) -> String {
+        TOOL_NAME.to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Calculates the deviation from mean elevation (DEV), the difference between the elevation of each cell of a
+DEM and the mean elevation of its neighbourhood, divided by the standard deviation of the neighbourhood.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input raster DEM file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::optional("Filter Size", &["--filter"], ParameterType::Integer, Some("11"), "Size of the filter kernel.").with_min_value(3.0),
+            ToolParameter::optional("Filter Size X", &["--filterx"], ParameterType::Integer, Some("11"), "Size of the filter kernel in the x-direction; not used if --filter is specified.").with_min_value(3.0),
+            ToolParameter::optional("Filter Size Y", &["--filtery"], ParameterType::Integer, Some("11"), "Size of the filter kernel in the y-direction; not used if --filter is specified.").with_min_value(3.0),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=dev_from_mean_elev -v --wd=\"/dir/to/data\" --args=\"-i=DEM.dep -o=output.dep --filter=25\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let (filter_size_x, filter_size_y) = if args.is_given("--filter") {
+        let filter_size = args.get_integer("--filter")? as usize;
+        (filter_size, filter_size)
+    } else {
+        (args.get_integer("--filterx")? as usize, args.get_integer("--filtery")? as usize)
+    };
+
+    dev_from_mean_elev(input_file, output_file,
+        filter_size_x, filter_size_y, MAX_BLOCK_CELLS, verbose)?;
+    println!("Complete!");
+
+    Ok(())
+}
+
+fn dev_from_mean_elev(input_file: String, output_file: String,
+    mut filter_size_x: usize, mut filter_size_y: usize, max_block_cells: usize, verbose: bool) -> Result<(), io::Error> {
+
+    if verbose {
+        println!("***************{}", "*".repeat(TOOL_NAME.len()));
+        println!("* Welcome to {} *", TOOL_NAME);
+        println!("***************{}", "*".repeat(TOOL_NAME.len()));
+    }
+
+    if filter_size_x < 3 { filter_size_x = 3; }
+    if filter_size_y < 3 { filter_size_y = 3; }
+
+	// The filter dimensions must be odd numbers such that there is a middle pixel
+    if (filter_size_x as f64 / 2f64).floor() == (filter_size_x as f64 / 2f64) {
+        filter_size_x += 1;
+    }
+    if (filter_size_y as f64 / 2f64).floor() == (filter_size_y as f64 / 2f64) {
+        filter_size_y += 1;
+    }
+
+    let midpoint_x = (filter_size_x as f64 / 2f64).floor() as isize;
+    let midpoint_y = (filter_size_y as f64 / 2f64).floor() as isize;
+    let mut progress: usize;
+    let mut old_progress: usize = 1;
+
+    if verbose { println!("Reading data...") };
+
+    // the DEM is processed in blocks of rows, which overlap by the height of the filter,
+    // so that DEMs larger than memory can be processed
+    let input = Raster::open(&input_file)?;
+
+    let start = time::now();
+
+    let rows = input.configs.rows as isize;
+    let columns = input.configs.columns as isize;
+    let nodata = input.configs.nodata;
+    let num_sig_digits = 2;
+    let multiplier = 10f64.powi(num_sig_digits);
+    let min_val = if input.configs.minimum.is_finite() { input.configs.minimum } else { 0f64 };
+	let min_bin = (min_val * multiplier).floor() as i32;
+    let bin_nodata32 = i32::MIN;
+
+    let overlap = midpoint_y as usize + 1;
+    let block_rows = max(max_block_cells / (columns as usize + 2 * overlap), 1);
+    let num_procs = num_cpus::get() as isize;
+
+    let mut output = RasterBlockWriter::initialize_using_file(&output_file, &input)?;
+    let mut num_rows_done = 0;
+    for block in input.blocks(block_rows, columns as usize, overlap) {
+        let block = block?;
+        let o = overlap as isize;
+        let first_row = block.window.row - o; // the row of the raster at the top of the block
+        let block_rows = block.raster.configs.rows as isize;
+        let block_columns = block.raster.configs.columns as isize;
+
+        // bin the data and create the integral images of the block
+        let mut binned_data : Array2D<i32> = Array2D::new(block_rows, block_columns, bin_nodata32, bin_nodata32)?;
+        let mut integral : Array2D<f64> = Array2D::new(block_rows, block_columns, 0f64, nodata)?;
+        let mut integral2 : Array2D<f64> = Array2D::new(block_rows, block_columns, 0f64, nodata)?;
+        let mut integral_n : Array2D<i32> = Array2D::new(block_rows, block_columns, 0, bin_nodata32)?;
+
+        let mut z : f64;
+        let mut val : f64;
+        let mut sum : f64;
+        let mut sum_sqr : f64;
+        let mut sum_n : i32;
+        let (mut i_prev, mut i2_prev) : (f64, f64);
+        let mut n_prev : i32;
+        for row in 0..block_rows {
+            sum = 0f64;
+            sum_sqr = 0f64;
+            sum_n = 0;
+            for col in 0..block_columns {
+                z = block.raster.get_value(row, col);
+                if z != nodata {
+                    let bin = (z*multiplier).floor() as i32 - min_bin;
+                    binned_data.set_value(row, col, bin);
+                    val = bin as f64;
+                    sum_n += 1;
+                } else {
+                    val = 0f64;
+                }
+                sum += val;
+                sum_sqr += val * val;
+                if row > 0 {
+                    i_prev = integral.get_value(row-1, col);
+                    i2_prev = integral2.get_value(row-1, col);
+                    n_prev = integral_n.get_value(row-1, col);
+                    integral.set_value(row, col, sum + i_prev);
+                    integral2.set_value(row, col, sum_sqr + i2_prev);
+                    integral_n.set_value(row, col, sum_n + n_prev);
+                } else {
+                    integral.set_value(row, col, sum);
+                    integral2.set_value(row, col, sum_sqr);
+                    integral_n.set_value(row, col, sum_n);
+                }
+            }
+        }
+
+        let bd = Arc::new(binned_data); // wrap binned_data in an Arc
+        let i = Arc::new(integral); // wrap integral in an Arc
+        let i2 = Arc::new(integral2); // wrap integral2 in an Arc
+        let i_n = Arc::new(integral_n); // wrap integral_n in an Arc
+
+        let window = block.window;
+        let window_rows = window.rows as isize;
+        let row_block_size = max(window_rows / num_procs, 1);
+        let (tx, rx) = mpsc::channel();
+        let mut starting_row;
+        let mut ending_row = 0;
+        let mut id = 0;
+        while ending_row < window_rows {
+            let binned_data = bd.clone();
+            let i = i.clone();
+            let i2 = i2.clone();
+            let i_n = i_n.clone();
+            starting_row = window.row + id * row_block_size;
+            ending_row = starting_row - window.row + row_block_size;
+            if ending_row > window_rows {
+                ending_row = window_rows;
+            }
+            let ending_row_global = window.row + ending_row;
+            id += 1;
+            let tx1 = tx.clone();
+            thread::spawn(move || {
+                // the rows and columns of the raster are those of the block offset by these
+                let (r0, c0) = (first_row, -o);
+                let (mut x1, mut x2, mut y1, mut y2) : (isize, isize, isize, isize);
+                let mut n : i32;
+                let (mut sum, mut sum_sqr) : (f64, f64);
+                let (mut v, mut s, mut mean) : (f64, f64, f64);
+                let mut z : i32;
+                for row in starting_row..ending_row_global {
+                    y1 = row - midpoint_y - 1;
+                    if y1 < 0 {
+                        y1 = 0;
+                    }
+                    if y1 >= rows {
+                        y1 = rows - 1;
+                    }
+
+                    y2 = row + midpoint_y;
+                    if y2 < 0 {
+                        y2 = 0;
+                    }
+                    if y2 >= rows {
+                        y2 = rows - 1;
+                    }
+                    let (y1, y2) = (y1 - r0, y2 - r0);
+                    let mut data = vec![nodata; columns as usize];
+                    for col in 0..columns {
+                        z = binned_data.get_value(row - r0, col - c0);
+                        if z != bin_nodata32 {
+                            x1 = col - midpoint_x - 1;
+                            if x1 < 0 {
+                                x1 = 0;
+                            }
+                            if x1 >= columns {
+                                x1 = columns - 1;
+                            }
+
+                            x2 = col + midpoint_x;
+                            if x2 < 0 {
+                                x2 = 0;
+                            }
+                            if x2 >= columns {
+                                x2 = columns - 1;
+                            }
+                            let (x1, x2) = (x1 - c0, x2 - c0);
+                            n = i_n.get_value(y2, x2) + i_n.get_value(y1, x1) - i_n.get_value(y1, x2) - i_n.get_value(y2, x1);
+                            if n > 0 {
+                                sum = i.get_value(y2, x2) + i.get_value(y1, x1) - i.get_value(y1, x2) - i.get_value(y2, x1);
+                                sum_sqr = i2.get_value(y2, x2) + i2.get_value(y1, x1) - i2.get_value(y1, x2) - i2.get_value(y2, x1);
+                                v = (sum_sqr - (sum * sum) / n as f64) / n as f64;
+                                if v > 0f64 {
+                                    s = v.sqrt();
+                                    mean = sum / n as f64;
+                                    data[col as usize] = (z as f64 - mean) / s;
+                                } else {
+                                    data[col as usize] = 0f64;
+                                }
+                            } else {
+                                data[col as usize] = 0f64;
+                            }
+                        }
+                    }
+
+                    if tx1.send((row, data)).is_err() {
+                        // the tool was cancelled
+                        break;
+                    }
+                }
+            });
+        }
+        drop(tx);
+
+        for (row, data) in rx {
+            output.set_row_data(row, data)?;
+            num_rows_done += 1;
+            if progress_enabled(verbose) {
+                progress = (100.0_f64 * num_rows_done as f64 / rows as f64) as usize;
+                if progress != old_progress {
+                    update_progress("Performing analysis", progress)?;
+                    old_progress = progress;
+                }
+            }
+        }
+    }
+
+    let end = time::now();
+    let elapsed_time = end - start;
+    output.configs.display_min = -2.0;
+    output.configs.display_max = 2.0;
+    output.configs.palette = "blue_white_red.plt".to_string();
+    output.add_metadata_entry(format!("Created by whitebox_tools\' {} tool", TOOL_NAME));
+    output.add_metadata_entry(format!("Input file: {}", input_file));
+    output.add_metadata_entry(format!("Filter size x: {}", filter_size_x));
+    output.add_metadata_entry(format!("Filter size y: {}", filter_size_y));
+    output.add_metadata_entry(format!("Elapsed Time (excluding I/O): {}", elapsed_time).replace("PT", ""));
+
+    if verbose { println!("Saving data...") };
+    match output.close() {
+        Ok(_) => if verbose { println!("Output file written") },
+        Err(e) => return Err(e.into()),
+    };
+
+    println!("{}", &format!("Elapsed Time (excluding I/O): {}", elapsed_time).replace("PT", ""));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::io_utils::temp_file_name;
+    use crate::raster::*;
+    use super::dev_from_mean_elev;
+
+    fn run(input_file: &str, name: &str, max_block_cells: usize) -> Raster {
+        let output_file = temp_file_name(name);
+        dev_from_mean_elev(input_file.to_string(), output_file.clone(), 5, 3, max_block_cells, false).unwrap();
+        let output = Raster::new(&output_file, "r").unwrap();
+        fs::remove_file(&output_file).unwrap();
+        fs::remove_file(output_file.replace(".dep", ".tas")).unwrap();
+        output
+    }
+
+    #[test]
+    fn blocks_give_the_same_result_as_the_whole_dem() {
+        let input_file = temp_file_name("dem.dep");
+        let mut configs = RasterConfigs::default();
+        configs.rows = 17;
+        configs.columns = 13;
+        configs.north = 17.0;
+        configs.east = 13.0;
+        configs.resolution_x = 1.0;
+        configs.resolution_y = 1.0;
+        configs.nodata = -9999.0;
+        configs.data_type = DataType::F32;
+        let mut dem = Raster::initialize_using_config(&input_file, &configs);
+        for row in 0..17 {
+            for col in 0..13 {
+                dem.set_value(row, col, ((row * 7 + col * col) % 23) as f64);
+            }
+        }
+        dem.set_value(8, 6, -9999.0);
+        dem.write().unwrap();
+
+        let whole = run(&input_file, "whole.dep", 1_000_000);
+        // blocks of a single row, which overlap the rows above and below them
+        let by_row = run(&input_file, "by_row.dep", 1);
+        fs::remove_file(&input_file).unwrap();
+        fs::remove_file(input_file.replace(".dep", ".tas")).unwrap();
+
+        for row in 0..17 {
+            for col in 0..13 {
+                assert_eq!(whole.get_value(row, col), by_row.get_value(row, col), "cell ({}, {})", row, col);
+            }
+        }
+        assert_eq!(whole.get_value(8, 6), -9999.0);
+
+        // the 5 x 3 neighbourhood of an interior cell
+        let (row, col) = (4isize, 3isize);
+        let values: Vec<f64> = (row - 1..row + 2)
+            .flat_map(|r| (col - 2..col + 3).map(move |c| ((r * 7 + c * c) % 23) as f64))
+            .collect();
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let sd = (values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / n).sqrt();
+        let expected = (((row * 7 + col * col) % 23) as f64 - mean) / sd;
+        assert!((whole.get_value(row, col) - expected).abs() < 1e-4);
+    }
+}
@@ -1,107 +1,72 @@
-extern crate whitebox_tools;
 extern crate time;
 extern crate num_cpus;
 
 use std::io;
-use std::env;
-use std::path;
+use std::io::Error;
 use std::f64;
-use std::i64;
 use std::sync::Arc;
 use std::sync::mpsc;
 use std::thread;
-use whitebox_tools::raster::*;
-use whitebox_tools::structures::array2d::Array2D;
+use crate::raster::*;
+use crate::structures::array2d::Array2D;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
 
 const TOOL_NAME: &str = "elev_percentile";
 
-fn main() {
-    let sep: String = path::MAIN_SEPARATOR.to_string();
-    let mut input_file = String::new();
-    let mut output_file = String::new();
-    let mut working_directory: String = "".to_string();
-    let mut filter_size_x = 11usize;
-    let mut filter_size_y = 11usize;
-    let mut verbose: bool = false;
-    let mut keyval: bool;
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 { panic!("Tool run with no paramters. Please see help (-h) for parameter descriptions."); }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--input" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--output" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-wd" || vec[0].to_lowercase() == "--wd" {
-            if keyval {
-                working_directory = vec[1].to_string();
-            } else {
-                working_directory = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-filter" || vec[0].to_lowercase() == "--filter" {
-            if keyval {
-                filter_size_x = vec[1].to_string().parse::<usize>().unwrap();
-            } else {
-                filter_size_x = args[i+1].to_string().parse::<usize>().unwrap();
-            }
-            filter_size_y = filter_size_x;
-        } else if vec[0].to_lowercase() == "-filterx" || vec[0].to_lowercase() == "--filterx" {
-            if keyval {
-                filter_size_x = vec[1].to_string().parse::<usize>().unwrap();
-            } else {
-                filter_size_x = args[i+1].to_string().parse::<usize>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-filtery" || vec[0].to_lowercase() == "--filtery" {
-            if keyval {
-                filter_size_y = vec[1].to_string().parse::<usize>().unwrap();
-            } else {
-                filter_size_y = args[i+1].to_string().parse::<usize>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-v" || vec[0].to_lowercase() == "--verbose" {
-            verbose = true;
-        } else if vec[0].to_lowercase() == "-h" || vec[0].to_lowercase() == "--help" ||
-            vec[0].to_lowercase() == "--h"{
-            let mut s: String = "Help:\n".to_owned();
-                     s.push_str("-i, --input   Input raster DEM file.\n");
-                     s.push_str("-o, --output  Output raster file.\n");
-                     s.push_str("-wd, --wd     Optional working directory. If specified, filenames parameters need not include a full path.\n");
-                     s.push_str("--filter      Size of the filter kernel (default is 11).\n");
-                     s.push_str("--filterx     Optional size of the filter kernel in the x-direction (default is 11; not used if --filter is specified).\n");
-                     s.push_str("--filtery     Optional size of the filter kernel in the y-direction (default is 11; not used if --filter is specified).\n");
-                     s.push_str("-version      Prints the tool version number.\n");
-                     s.push_str("-h            Prints help information.\n\n");
-                     s.push_str("Example usage:\n\n");
-                     s.push_str(&format!(">> .*{} -wd *path*to*data* -i=input.dep -o=output.dep --filter=25\n", TOOL_NAME).replace("*", &sep));
-            println!("{}", s);
-            return;
-        } else if vec[0].to_lowercase() == "-version" || vec[0].to_lowercase() == "--version" {
-            const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
-            println!("{} v{}", TOOL_NAME, VERSION.unwrap_or("unknown"));
-            return;
-        }
+pub struct ElevPercentile;
+
+impl WhiteboxTool for ElevPercentile {
+    fn get_tool_name(&self) -> String {
+        TOOL_NAME.to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Calculates the percentile of the elevation of each cell of a DEM within the elevations of its neighbourhood.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input raster DEM file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::optional("Filter Size", &["--filter"], ParameterType::Integer, Some("11"), "Size of the filter kernel.").with_min_value(3.0),
+            ToolParameter::optional("Filter Size X", &["--filterx"], ParameterType::Integer, Some("11"), "Size of the filter kernel in the x-direction; not used if --filter is specified.").with_min_value(3.0),
+            ToolParameter::optional("Filter Size Y", &["--filtery"], ParameterType::Integer, Some("11"), "Size of the filter kernel in the y-direction; not used if --filter is specified.").with_min_value(3.0),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=elev_percentile -v --wd=\"/dir/to/data\" --args=\"-i=DEM.dep -o=output.dep --filter=25\"";
+        Some(s.to_string())
     }
 
-    match run(input_file, output_file, working_directory,
-        filter_size_x, filter_size_y, verbose) {
-        Ok(()) => println!("Complete!"),
-        Err(err) => panic!("{}", err),
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
     }
 }
 
-fn run(mut input_file: String, mut output_file: String, mut working_directory: String,
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let (filter_size_x, filter_size_y) = if args.is_given("--filter") {
+        let filter_size = args.get_integer("--filter")? as usize;
+        (filter_size, filter_size)
+    } else {
+        (args.get_integer("--filterx")? as usize, args.get_integer("--filtery")? as usize)
+    };
+
+    elev_percentile(input_file, output_file,
+        filter_size_x, filter_size_y, verbose)?;
+    println!("Complete!");
+
+    Ok(())
+}
+
+fn elev_percentile(input_file: String, output_file: String,
     mut filter_size_x: usize, mut filter_size_y: usize, verbose: bool) -> Result<(), io::Error> {
 
     if verbose {
@@ -110,8 +75,6 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
         println!("***************{}", "*".repeat(TOOL_NAME.len()));
     }
 
-    let sep: String = path::MAIN_SEPARATOR.to_string();
-
     if filter_size_x < 3 { filter_size_x = 3; }
     if filter_size_y < 3 { filter_size_y = 3; }
 
@@ -129,17 +92,6 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     let mut progress: usize;
     let mut old_progress: usize = 1;
 
-    if !working_directory.ends_with(&sep) {
-        working_directory.push_str(&(sep.to_string()));
-    }
-
-    if !input_file.contains(&sep) {
-        input_file = format!("{}{}", working_directory, input_file);
-    }
-    if !output_file.contains(&sep) {
-        output_file = format!("{}{}", working_directory, output_file);
-    }
-
     if verbose { println!("Reading data...") };
 
     let input = Arc::new(Raster::new(&input_file, "r")?);
@@ -168,7 +120,6 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     let mut id = 0;
     while ending_row < rows {
         let input = input.clone();
-        let rows = rows.clone();
         starting_row = id * row_block_size;
         ending_row = starting_row + row_block_size;
 		if ending_row > rows {
@@ -190,7 +141,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
                         data[col as usize] = val;
                     }
                 }
-                tx1.send((row, data)).unwrap();
+                if tx1.send((row, data)).is_err() {
+                    // the tool was cancelled
+                    break;
+                }
             }
         });
     }
@@ -198,10 +152,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     for row in 0..rows {
         let data = rx.recv().unwrap();
         binned_data.set_row_data(data.0, data.1);
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Binning data: {}%", progress);
+                update_progress("Binning data", progress)?;
                 old_progress = progress;
             }
         }
@@ -216,7 +170,6 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     while ending_row < rows {
         let input = input.clone();
         let binned_data = bd.clone();
-        let rows = rows.clone();
         starting_row = id * row_block_size;
         ending_row = starting_row + row_block_size;
 		if ending_row > rows {
@@ -312,7 +265,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
 
                     old_bin_val = bin_val;
                 }
-                tx1.send((row, data)).unwrap();
+                if tx1.send((row, data)).is_err() {
+                    // the tool was cancelled
+                    break;
+                }
             }
         });
     }
@@ -320,10 +276,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     for row in 0..rows {
         let data = rx.recv().unwrap();
         output.set_row_data(data.0, data.1);
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Performing analysis: {}%", progress);
+                update_progress("Performing analysis", progress)?;
                 old_progress = progress;
             }
         }
@@ -341,9 +297,9 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     output.add_metadata_entry(format!("Elapsed Time (excluding I/O): {}", elapsed_time).replace("PT", ""));
 
     if verbose { println!("Saving data...") };
-    let _ = match output.write() {
+    match output.write() {
         Ok(_) => if verbose { println!("Output file written") },
-        Err(e) => return Err(e),
+        Err(e) => return Err(e.into()),
     };
 
     println!("{}", &format!("Elapsed Time (excluding I/O): {}", elapsed_time).replace("PT", ""));
@@ -1,83 +1,59 @@
-extern crate whitebox_tools;
 extern crate time;
 
 use std::io;
-use std::env;
-use std::path;
+use std::io::Error;
 use std::f64;
-use whitebox_tools::raster::*;
-use whitebox_tools::structures::fixed_radius_search::FixedRadiusSearch;
-
-fn main() {
-    let sep: String = path::MAIN_SEPARATOR.to_string();
-    let mut input_file = String::new();
-    let mut output_file = String::new();
-    let mut working_directory: String = "".to_string();
-    let mut filter_size = 11usize;
-    let mut verbose: bool = false;
-    let mut keyval: bool;
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 { panic!("Tool run with no paramters. Please see help (-h) for parameter descriptions."); }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--input" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--output" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-wd" || vec[0].to_lowercase() == "--wd" {
-            if keyval {
-                working_directory = vec[1].to_string();
-            } else {
-                working_directory = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-filter" || vec[0].to_lowercase() == "--filter" {
-            if keyval {
-                filter_size = vec[1].to_string().parse::<usize>().unwrap();
-            } else {
-                filter_size = args[i+1].to_string().parse::<usize>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-v" || vec[0].to_lowercase() == "--verbose" {
-            verbose = true;
-        } else if vec[0].to_lowercase() == "-h" || vec[0].to_lowercase() == "--help" ||
-            vec[0].to_lowercase() == "--h"{
-            let mut s: String = "Help:\n".to_owned();
-                     s.push_str("-i       Input LAS file (classification).\n");
-                     s.push_str("-o       Output HTML file.\n");
-                     s.push_str("-wd      Optional working directory. If specified, filenames parameters need not include a full path.\n");
-                     s.push_str("-filter  Size of the filter kernel (default is 11).\n");
-                     s.push_str("-version Prints the tool version number.\n");
-                     s.push_str("-h       Prints help information.\n\n");
-                     s.push_str("Example usage:\n\n");
-                     s.push_str(&">> .*fill_missing_data -wd *path*to*data* -i input.dep -o NoOTOs.dep -filter 25 -slope 15.0\n".replace("*", &sep));
-            println!("{}", s);
-            return;
-        } else if vec[0].to_lowercase() == "-version" || vec[0].to_lowercase() == "--version" {
-            const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
-            println!("slope v{}", VERSION.unwrap_or("unknown"));
-            return;
-        }
+use crate::raster::*;
+use crate::structures::fixed_radius_search::FixedRadiusSearch;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+pub struct FillMissingData;
+
+impl WhiteboxTool for FillMissingData {
+    fn get_tool_name(&self) -> String {
+        "fill_missing_data".to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Fills the NoData holes in a raster by inverse-distance weighted interpolation of the valid cells that
+surround each hole, within the filter radius.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::optional("Filter Size", &["--filter"], ParameterType::Integer, Some("11"), "Size of the filter kernel, i.e. the search radius in cells.").with_min_value(1.0),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=fill_missing_data -v --wd=\"/dir/to/data\" --args=\"-i=DEM.dep -o=output.dep --filter=25\"";
+        Some(s.to_string())
     }
 
-    match run(input_file, output_file, working_directory, filter_size, verbose) {
-        Ok(()) => println!("Complete!"),
-        Err(err) => panic!("{}", err),
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
     }
 }
 
-fn run(mut input_file: String, mut output_file: String, mut working_directory: String,
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let filter_size = args.get_integer("--filter")? as usize;
+
+    fill_missing_data(input_file, output_file, filter_size, verbose)?;
+    println!("Complete!");
+
+    Ok(())
+}
+
+fn fill_missing_data(input_file: String, output_file: String,
     mut filter_size: usize, verbose: bool) -> Result<(), io::Error> {
 
     if verbose {
@@ -86,8 +62,6 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
         println!("********************************");
     }
 
-    let sep: String = path::MAIN_SEPARATOR.to_string();
-
 	// The filter dimensions must be odd numbers such that there is a middle pixel
     if (filter_size as f64 / 2f64).floor() == (filter_size as f64 / 2f64) {
         filter_size += 1;
@@ -98,17 +72,6 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     let mut progress: usize;
     let mut old_progress: usize = 1;
 
-    if !working_directory.ends_with(&sep) {
-        working_directory.push_str(&(sep.to_string()));
-    }
-
-    if !input_file.contains(&sep) {
-        input_file = format!("{}{}", working_directory, input_file);
-    }
-    if !output_file.contains(&sep) {
-        output_file = format!("{}{}", working_directory, output_file);
-    }
-
     if verbose { println!("Reading data...") };
 
     let input = Raster::new(&input_file, "r")?;
@@ -127,21 +90,21 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     let mut frs: FixedRadiusSearch<f64> = FixedRadiusSearch::new(filter_size as f64);
     for row in 0..rows {
         for col in 0..columns {
-            if input[(row, col)] != nodata {
+            if input.get_value(row, col) != nodata {
                 for i in 0..8 {
                     row_n = row + d_y[i];
                     col_n = col + d_x[i];
-                    if input[(row_n, col_n)] == nodata {
-                        frs.insert(col as f64, row as f64, input[(row, col)]);
+                    if input.get_value(row_n, col_n) == nodata {
+                        frs.insert(col as f64, row as f64, input.get_value(row, col));
                         break;
                     }
                 }
             }
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Finding OTO edge cells: {}%", progress);
+                update_progress("Finding OTO edge cells", progress)?;
                 old_progress = progress;
             }
         }
@@ -151,7 +114,7 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     let mut dist: f64;
     for row in 0..rows {
         for col in 0..columns {
-            if input[(row, col)] == nodata {
+            if input.get_value(row, col) == nodata {
                 sum_weights = 0f64;
                 let ret = frs.search(col as f64, row as f64);
                 for j in 0..ret.len() {
@@ -167,15 +130,15 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
                         z += ret[j].0 * (1.0 / (dist * dist)) / sum_weights;
                     }
                 }
-                output[(row, col)] = z;
+                output.set_value(row, col, z);
             } else {
-                output[(row, col)] = input[(row, col)];
+                output.set_value(row, col, input.get_value(row, col));
             }
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Interpolating data holes: {}%", progress);
+                update_progress("Interpolating data holes", progress)?;
                 old_progress = progress;
             }
         }
@@ -189,9 +152,9 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     output.add_metadata_entry(format!("Elapsed Time (excluding I/O): {}", elapsed_time).replace("PT", ""));
 
     if verbose { println!("Saving data...") };
-    let _ = match output.write() {
+    match output.write() {
         Ok(_) => if verbose { println!("Output file written") },
-        Err(e) => return Err(e),
+        Err(e) => return Err(e.into()),
     };
 
 
@@ -244,7 +207,7 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     //     if verbose {
     //         progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
     //         if progress != old_progress {
-    //             println!("Performing Erosion: {}%", progress);
+    //             update_progress("Performing Erosion", progress)?;
     //             old_progress = progress;
     //         }
     //     }
@@ -269,7 +232,7 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     //     if verbose {
     //         progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
     //         if progress != old_progress {
-    //             println!("Performing Dilation: {}%", progress);
+    //             update_progress("Performing Dilation", progress)?;
     //             old_progress = progress;
     //         }
     //     }
@@ -314,7 +277,7 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     //     if verbose {
     //         progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
     //         if progress != old_progress {
-    //             println!("Progress: {}%", progress);
+    //             update_progress("Progress", progress)?;
     //             old_progress = progress;
     //         }
     //     }
@@ -350,7 +313,7 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     //     if verbose {
     //         progress = (100.0_f64 * num_solved_cells as f64 / (num_cells - 1) as f64) as usize;
     //         if progress != old_progress {
-    //             println!("Progress: {}%", progress);
+    //             update_progress("Progress", progress)?;
     //             old_progress = progress;
     //         }
     //     }
@@ -384,7 +347,7 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     //     if verbose {
     //         progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
     //         if progress != old_progress {
-    //             println!("Progress: {}%", progress);
+    //             update_progress("Progress", progress)?;
     //             old_progress = progress;
     //         }
     //     }
@@ -408,7 +371,7 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     //         if verbose {
     //             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
     //             if progress != old_progress {
-    //                 println!("Progress: {}%", progress);
+    //                 update_progress("Progress", progress)?;
     //                 old_progress = progress;
     //             }
     //         }
@@ -424,9 +387,3 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     Ok(())
 }
 
-#[derive(Copy, Clone, Eq, PartialEq)]
-struct GridCell {
-    // priority: isize,
-    row: isize,
-    column: isize,
-}
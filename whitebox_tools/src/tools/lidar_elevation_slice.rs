@@ -1,108 +1,62 @@
 extern crate time;
 
 use std::f64;
-use std::path;
+use std::fs;
 use std::io::{Error, ErrorKind};
-use lidar::las;
-
-pub fn get_tool_name() -> String {
-    return "lidar_elevation_slice".to_string();
-}
+use crate::lidar::las;
+use crate::lidar::las_reader::LasReader;
+use crate::lidar::las_writer::LasWriter;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+pub struct LidarElevationSlice;
+
+impl WhiteboxTool for LidarElevationSlice {
+    fn get_tool_name(&self) -> String {
+        "lidar_elevation_slice".to_string()
+    }
 
-pub fn get_tool_description() -> String {
-    let s = "Outputs all of the points within a LiDAR (LAS) point file that lie between a specified
+    fn get_tool_description(&self) -> String {
+        let s = "Outputs all of the points within a LiDAR (LAS) point file that lie between a specified
 elevation range.";
 
-    return s.to_string();
-}
-
-pub fn get_tool_parameters() -> String {
-    let s = "-i, --input        Input LAS file.
--o, --output       Output LAS file.
---maxz             Maximum elevation value.
---minz             Minimum elevation value.
---class            Optional boolean flag indicating whether points outside the range should be retained in output but reclassified.
---inclassval       Optional parameter specifying the class value assigned to points within the slice; default is 2.
---outclassval      Optional parameter specifying the class value assigned to points outside the slice; default is 1.";
-    return s.to_string();
-}
-
-pub fn get_example_usage() -> Option<String> {
-    let s = "./whitebox-tools -v --wd=\"/dir/to/data\" --args=\"-i=\"input.las\" -o=\"output.las\" --minz=100.0 --maxz=250.0\"
-./whitebox-tools -v --args=\"-i=\"*path*to*data*input.las\" -o=\"*path*to*data*output.las\" --minz=100.0 --maxz=250.0 --class\"
-./whitebox-tools -v --args=\"-i=\"*path*to*data*input.las\" -o=\"*path*to*data*output.las\" --minz=100.0 --maxz=250.0 --inclassval=1 --outclassval=0\"";
-    return Some(s.to_string());
-}
-
-pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) -> Result<(), Error> {
-    let mut input_file: String = "".to_string();
-    let mut output_file: String = "".to_string();
-    let mut minz = -f64::INFINITY;
-    let mut maxz = f64::INFINITY;
-    let mut filter = true;
-    let mut in_class_value = 2u8;
-    let mut out_class_value = 1u8;
-
-    // read the arguments
-    if args.len() == 0 {
-        return Err(Error::new(ErrorKind::InvalidInput, "Tool run with no paramters. Please see help (-h) for parameter descriptions."));
+        s.to_string()
     }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        let mut keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--input" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--output" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-maxz" || vec[0].to_lowercase() == "--maxz" {
-            if keyval {
-                maxz = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                maxz = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-minz" || vec[0].to_lowercase() == "--minz" {
-            if keyval {
-                minz = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                minz = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-class" || vec[0].to_lowercase() == "--class" {
-            filter = false;
-        } else if vec[0].to_lowercase() == "-inclassval" || vec[0].to_lowercase() == "--inclassval" {
-            filter = false;
-            if keyval {
-                in_class_value = vec[1].to_string().parse::<u8>().unwrap();
-            } else {
-                in_class_value = args[i+1].to_string().parse::<u8>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-outclassval" || vec[0].to_lowercase() == "--outclassval" {
-            filter = false;
-            if keyval {
-                out_class_value = vec[1].to_string().parse::<u8>().unwrap();
-            } else {
-                out_class_value = args[i+1].to_string().parse::<u8>().unwrap();
-            }
-        }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS or LAZ file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file; a .laz extension produces a compressed (LAZ) file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Maximum Elevation", &["--maxz"], ParameterType::Float, None, "Maximum elevation value."),
+            ToolParameter::optional("Minimum Elevation", &["--minz"], ParameterType::Float, None, "Minimum elevation value."),
+            ToolParameter::optional("Retain Points Outside Slice", &["--class"], ParameterType::Boolean, None, "Flag indicating whether points outside the range should be retained in output but reclassified."),
+            ToolParameter::optional("Class Value Within Slice", &["--inclassval"], ParameterType::Integer, Some("2"), "Class value assigned to points within the slice; values greater than 31 require point formats 6-10.").with_range(0.0, 255.0),
+            ToolParameter::optional("Class Value Outside Slice", &["--outclassval"], ParameterType::Integer, Some("1"), "Class value assigned to points outside the slice; values greater than 31 require point formats 6-10.").with_range(0.0, 255.0),
+        ]
     }
 
-    if !input_file.contains(path::MAIN_SEPARATOR) {
-        input_file = format!("{}{}", working_directory, input_file);
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_elevation_slice -v --wd=\"/dir/to/data\" --args=\"-i=input.las -o=output.las --minz=100.0 --maxz=250.0\"
+./whitebox-tools -r=lidar_elevation_slice -v --wd=\"/dir/to/data\" --args=\"-i=input.las -o=output.las --minz=100.0 --maxz=250.0 --class\"
+./whitebox-tools -r=lidar_elevation_slice -v --wd=\"/dir/to/data\" --args=\"-i=input.las -o=output.las --minz=100.0 --maxz=250.0 --inclassval=1 --outclassval=0\"";
+        Some(s.to_string())
     }
-    if !output_file.contains(path::MAIN_SEPARATOR) {
-        output_file = format!("{}{}", working_directory, output_file);
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
     }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let minz = args.get_float("--minz").unwrap_or(f64::NEG_INFINITY);
+    let maxz = args.get_float("--maxz").unwrap_or(f64::INFINITY);
+    let filter = !(args.get_bool("--class") || args.is_given("--inclassval") || args.is_given("--outclassval"));
+    let in_class_value = args.get_integer("--inclassval")? as u8;
+    let out_class_value = args.get_integer("--outclassval")? as u8;
 
     if verbose {
         println!("***********************************");
@@ -110,25 +64,15 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
         println!("************************************");
     }
 
-    if in_class_value > 31 || out_class_value > 31 {
-        return Err(Error::new(ErrorKind::InvalidInput, "Error: Either the in-slice or out-of-slice class values are larger than 31."));
-    }
-
-    let sep = path::MAIN_SEPARATOR;
-    if !input_file.contains(sep) {
-        input_file = format!("{}{}", working_directory, input_file);
-    }
-    if !output_file.contains(sep) {
-        output_file = format!("{}{}", working_directory, output_file);
-    }
-
     if verbose { println!("Reading input LAS file..."); }
-    //let input = las::LasFile::new(&input_file, "r");
-    let input: las::LasFile = match las::LasFile::new(&input_file, "r") {
-        Ok(lf) => lf,
-        Err(_) => return Err(Error::new(ErrorKind::NotFound, format!("No such file or directory ({})", input_file))),
-    };
-    let mut output = las::LasFile::initialize_using_file(&output_file, &input);
+    // The points are streamed from the input file to the output file in chunks.
+    let mut input = LasReader::new(&input_file)?;
+    // the point formats before 6 hold class values of 0-31 only
+    if !filter && input.header.point_format < 6 && (in_class_value > 31 || out_class_value > 31) {
+        return Err(Error::new(ErrorKind::InvalidInput,
+            format!("Class values greater than 31 cannot be assigned to the points of LAS point format {}.", input.header.point_format)));
+    }
+    let mut output = LasWriter::new(&output_file, &input.header, &input.vlr_data, &input.evlr_data)?;
     output.header.system_id = "EXTRACTION".to_string();
 
     if verbose { println!("Performing analysis..."); }
@@ -136,73 +80,174 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     let mut progress: i32;
     let mut old_progress: i32 = -1;
     let mut num_points_filtered: i64 = 0;
-    let num_points: f64 = (input.header.number_of_points - 1) as f64;
-
-    if filter {
-        for i in 0..input.header.number_of_points as usize {
-            z = input.get_point_info(i).z;
-            if z >= minz && z <= maxz {
-                output.add_point_record(input.get_record(i));
-                num_points_filtered += 1;
-            }
-            if verbose {
-                progress = (100.0_f64 * i as f64 / num_points) as i32;
-                if progress != old_progress {
-                    println!("Progress: {}%", progress);
-                    old_progress = progress;
+    let num_points: f64 = (input.header.number_of_points as f64 - 1.0).max(1.0);
+    let mut i = 0usize;
+
+    loop {
+        let chunk = input.read_chunk()?;
+        if chunk.is_empty() { break; }
+        for (j, pr) in chunk.into_iter().enumerate() {
+            z = pr.point_data().z;
+            if filter {
+                if z >= minz && z <= maxz {
+                    output.add_point_record_with_extra_bytes(pr, input.get_extra_bytes(j))?;
+                    num_points_filtered += 1;
                 }
+            } else {
+                let mut class_val = out_class_value; // outside elevation slice
+                if z >= minz && z <= maxz {
+                    class_val = in_class_value; // inside elevation slice
+                }
+                let pr2: las::LidarPointRecord = match pr {
+                    las::LidarPointRecord::PointRecord0 { mut point_data }  => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord0 { point_data }
+
+                    },
+                    las::LidarPointRecord::PointRecord1 { mut point_data, gps_data } => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord1 { point_data, gps_data }
+                    },
+                    las::LidarPointRecord::PointRecord2 { mut point_data, rgb_data } => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord2 { point_data, rgb_data }
+                    },
+                    las::LidarPointRecord::PointRecord3 { mut point_data, gps_data, rgb_data } => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord3 { point_data,
+                            gps_data, rgb_data}
+                    },
+                    las::LidarPointRecord::PointRecord4 { mut point_data, gps_data, wave_packet } => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord4 { point_data,
+                            gps_data, wave_packet}
+                    },
+                    las::LidarPointRecord::PointRecord5 { mut point_data, gps_data, rgb_data, wave_packet } => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord5 { point_data,
+                            gps_data, rgb_data, wave_packet}
+                    },
+                    las::LidarPointRecord::PointRecord6 { mut point_data, gps_data } => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord6 { point_data,
+                            gps_data}
+                    },
+                    las::LidarPointRecord::PointRecord7 { mut point_data, gps_data, rgb_data } => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord7 { point_data,
+                            gps_data, rgb_data}
+                    },
+                    las::LidarPointRecord::PointRecord8 { mut point_data, gps_data, rgb_data, nir_data } => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord8 { point_data,
+                            gps_data, rgb_data, nir_data}
+                    },
+                    las::LidarPointRecord::PointRecord9 { mut point_data, gps_data, wave_packet } => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord9 { point_data,
+                            gps_data, wave_packet}
+                    },
+                    las::LidarPointRecord::PointRecord10 { mut point_data, gps_data, rgb_data, nir_data, wave_packet } => {
+                        point_data.set_classification(class_val);
+                        las::LidarPointRecord::PointRecord10 { point_data,
+                            gps_data, rgb_data,
+                            nir_data, wave_packet}
+                    },
+                };
+                output.add_point_record_with_extra_bytes(pr2, input.get_extra_bytes(j))?;
+                num_points_filtered += 1;
             }
-        }
-    } else {
-        for i in 0..input.header.number_of_points as usize {
-            let mut class_val = out_class_value; // outside elevation slice
-            z = input.get_point_info(i).z;
-            if z >= minz && z <= maxz {
-                class_val = in_class_value; // inside elevation slice
-            }
-            let pr = input.get_record(i);
-            let pr2: las::LidarPointRecord;
-            match pr {
-                las::LidarPointRecord::PointRecord0 { mut point_data }  => {
-                    point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord0 { point_data: point_data };
-
-                },
-                las::LidarPointRecord::PointRecord1 { mut point_data, gps_data } => {
-                    point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord1 { point_data: point_data, gps_data: gps_data };
-                },
-                las::LidarPointRecord::PointRecord2 { mut point_data, rgb_data } => {
-                    point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord2 { point_data: point_data, rgb_data: rgb_data };
-                },
-                las::LidarPointRecord::PointRecord3 { mut point_data, gps_data, rgb_data } => {
-                    point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord3 { point_data: point_data,
-                        gps_data: gps_data, rgb_data: rgb_data};
-                },
-            }
-            output.add_point_record(pr2);
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Saving data: {}%", progress);
+                    update_progress("Progress", progress as usize)?;
                     old_progress = progress;
                 }
             }
+            i += 1;
         }
-        num_points_filtered = 1;
     }
 
     if num_points_filtered > 0 {
         if verbose { println!("Writing output LAS file..."); }
-        let _ = match output.write() {
-            Ok(_) => println!("Complete!"),
-            Err(e) => println!("error while writing: {:?}", e),
-        };
+        output.close()?;
+        println!("Complete!");
     } else {
+        drop(output);
+        let _ = fs::remove_file(&output_file);
         println!("No points were contained in the elevation slice.");
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::ErrorKind;
+    use crate::io_utils::temp_file_name;
+    use crate::lidar::header::LasHeader;
+    use crate::lidar::las::{ LasFile, LidarPointRecord };
+    use crate::lidar::point_data::PointData;
+    use crate::tools::WhiteboxTool;
+    use super::LidarElevationSlice;
+
+    fn write_input(file_name: &str, num_points: usize) {
+        let mut header: LasHeader = Default::default();
+        header.project_id_used = true;
+        header.version_major = 1;
+        header.version_minor = 2;
+        header.x_scale_factor = 0.01;
+        header.y_scale_factor = 0.01;
+        header.z_scale_factor = 0.01;
+        let mut input = LasFile::new(file_name, "w").unwrap();
+        input.add_header(header);
+        for i in 0..num_points {
+            let mut p: PointData = Default::default();
+            p.x = i as f64;
+            p.z = i as f64;
+            p.bit_field.value = 0b0000_1001;
+            input.add_point_record(LidarPointRecord::PointRecord0 { point_data: p });
+        }
+        input.write().unwrap();
+    }
+
+    fn run(input_file: &str, output_file: &str, args: &[&str]) -> Result<(), ::std::io::Error> {
+        let mut all_args = vec![format!("-i={}", input_file), format!("-o={}", output_file)];
+        all_args.extend(args.iter().map(|a| a.to_string()));
+        LidarElevationSlice.run(all_args, "", false)
+    }
+
+    #[test]
+    fn empty_file_gives_no_slice() {
+        let input_file = temp_file_name("empty.las");
+        let output_file = temp_file_name("empty_out.las");
+        write_input(&input_file, 0);
+        let result = run(&input_file, &output_file, &["--minz=1", "--maxz=2"]);
+        let _ = fs::remove_file(&input_file);
+        result.unwrap();
+        assert!(fs::metadata(&output_file).is_err());
+    }
+
+    #[test]
+    fn class_values_are_limited_by_the_point_format() {
+        let input_file = temp_file_name("classes.las");
+        let output_file = temp_file_name("classes_out.las");
+        write_input(&input_file, 10);
+        let too_large = run(&input_file, &output_file, &["--minz=2", "--maxz=4", "--inclassval=200"]);
+        let out_of_range = run(&input_file, &output_file, &["--inclassval=256"]);
+        let classified = run(&input_file, &output_file, &["--minz=2", "--maxz=4", "--inclassval=31", "--outclassval=0"]);
+        let output = LasFile::new(&output_file, "r");
+        let _ = fs::remove_file(&input_file);
+        let _ = fs::remove_file(&output_file);
+
+        assert_eq!(too_large.unwrap_err().kind(), ErrorKind::InvalidInput);
+        assert_eq!(out_of_range.unwrap_err().kind(), ErrorKind::InvalidInput);
+        classified.unwrap();
+        let output = output.unwrap();
+        for i in 0..10 {
+            let class = output.get_point_info(i).classification();
+            assert_eq!(class, if (2..=4).contains(&i) { 31 } else { 0 });
+        }
+    }
+}
@@ -2,85 +2,55 @@ extern crate time;
 
 use std::f64;
 use std::io::{Error, ErrorKind};
-use std::path;
-use lidar::las;
-use raster::*;
-use structures::fixed_radius_search::FixedRadiusSearch;
-
-pub fn get_tool_name() -> String {
-    return "lidar_flightline_overlap".to_string();
-}
+use crate::lidar::las;
+use crate::raster::*;
+use crate::structures::fixed_radius_search::FixedRadiusSearch;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+pub struct LidarFlightlineOverlap;
+
+impl WhiteboxTool for LidarFlightlineOverlap {
+    fn get_tool_name(&self) -> String {
+        "lidar_flightline_overlap".to_string()
+    }
 
-pub fn get_tool_description() -> String {
-    let s = "Reads a LiDAR (LAS) point file and outputs a raster containing the number of overlapping
+    fn get_tool_description(&self) -> String {
+        let s = "Reads a LiDAR (LAS) point file and outputs a raster containing the number of overlapping
 flight lines in each grid cell.";
 
-    return s.to_string();
-}
+        s.to_string()
+    }
 
-pub fn get_tool_parameters() -> String {
-    let s = "-i, --input        Input LAS file.
--o, --output       Output raster file.
---resolution       Output raster's grid resolution.
---palette          Optional palette name (for use with Whitebox raster files).";
-    return s.to_string();
-}
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::optional("Grid Resolution", &["--resolution"], ParameterType::Float, Some("1.0"), "Output raster's grid resolution."),
+            ToolParameter::optional("Palette", &["--palette"], ParameterType::String, Some("default"), "Palette name (for use with Whitebox raster files)."),
+        ]
+    }
 
-pub fn get_example_usage() -> Option<String> {
-    let s = "./whitebox-tools -r=lidar_flightline_overlap --wd=\"/dir/to/data\" --args=\"-i=file.las -o=outfile.dep --resolution=2.0\"
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_flightline_overlap --wd=\"/dir/to/data\" --args=\"-i=file.las -o=outfile.dep --resolution=2.0\"
 ./whitebox-tools -r=lidar_flightline_overlap --wd=\"/dir/to/data\" --args=\"-i=file.las -o=outfile.dep --resolution=5.0 --palette=light_quant.plt\"";
-    return Some(s.to_string());
-}
-
-pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) -> Result<(), Error> {
-    let mut input_file: String = "".to_string();
-    let mut output_file: String = "".to_string();
-    let mut grid_res: f64 = 1.0;
-    let mut palette = "default".to_string();
-
-    // read the arguments
-    if args.len() == 0 {
-        return Err(Error::new(ErrorKind::InvalidInput, "Tool run with no paramters. Please see help (-h) for parameter descriptions."));
-    }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        let mut keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--input" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--output" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-resolution" || vec[0].to_lowercase() == "--resolution" {
-            if keyval {
-                grid_res = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                grid_res = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-palette" || vec[0].to_lowercase() == "--palette" {
-            if keyval {
-                palette = vec[1].to_string();
-            } else {
-                palette = args[i+1].to_string();
-            }
-        }
+        Some(s.to_string())
     }
 
-    if !input_file.contains(path::MAIN_SEPARATOR) {
-        input_file = format!("{}{}", working_directory, input_file);
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
     }
-    if !output_file.contains(path::MAIN_SEPARATOR) {
-        output_file = format!("{}{}", working_directory, output_file);
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let grid_res = args.get_float("--resolution")?;
+    let palette = args.get_string("--palette")?;
+    if grid_res <= 0.0 {
+        return Err(Error::new(ErrorKind::InvalidInput, "The grid resolution must be greater than zero."));
     }
 
     if verbose {
@@ -92,10 +62,7 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     let start = time::now();
 
     if verbose { println!("Reading input LAS file..."); }
-    let input = match las::LasFile::new(&input_file, "r") {
-        Ok(lf) => lf,
-        Err(_) => return Err(Error::new(ErrorKind::NotFound, format!("No such file or directory ({})", input_file))),
-    };
+    let input = las::LasFile::new(&input_file, "r")?;
 
     // Make sure that the input LAS file have GPS time data?
     if input.header.point_format == 0u8 || input.header.point_format == 2u8 {
@@ -103,7 +70,10 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     }
 
     let n_points = input.header.number_of_points as usize;
-    let num_points: f64 = (input.header.number_of_points - 1) as f64; // used for progress calculation only
+    if input.header.number_of_points == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("The input file {} contains no points.", input_file)));
+    }
+    let num_points: f64 = (input.header.number_of_points as f64 - 1.0).max(1.0); // used for progress calculation only
 
     if verbose { println!("Performing analysis..."); }
     // let search_dist = grid_res / 2.0;
@@ -125,16 +95,27 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
                 gps_time = gps_data;
                 let _ = rgb_data; // just to kill the 'unused variable' warning
             },
+            las::LidarPointRecord::PointRecord4 { point_data, gps_data, .. } |
+            las::LidarPointRecord::PointRecord5 { point_data, gps_data, .. } |
+            las::LidarPointRecord::PointRecord6 { point_data, gps_data } |
+            las::LidarPointRecord::PointRecord7 { point_data, gps_data, .. } |
+            las::LidarPointRecord::PointRecord8 { point_data, gps_data, .. } |
+            las::LidarPointRecord::PointRecord9 { point_data, gps_data, .. } |
+            las::LidarPointRecord::PointRecord10 { point_data, gps_data, .. } => {
+                x = point_data.x;
+                y = point_data.y;
+                gps_time = gps_data;
+            },
             _ => {
                 panic!("The input file has a Point Format that does not include GPS time, which is required for the operation of this tool.");
             }
         };
         frs.insert(x, y, i);
         gps_times[i] = gps_time;
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * i as f64 / num_points) as usize;
             if progress != old_progress {
-                println!("Binning points: {}%", progress);
+                update_progress("Binning points", progress)?;
                 old_progress = progress;
             }
         }
@@ -161,7 +142,7 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     configs.data_type = DataType::F64;
     configs.photometric_interp = PhotometricInterpretation::Continuous;
     configs.palette = palette;
-    // configs.projection = input.configs.projection.clone();
+    configs.set_spatial_reference(&input.get_spatial_reference());
     // configs.xy_units = input.configs.xy_units.clone();
     // configs.z_units = input.configs.z_units.clone();
     // configs.endian = input.configs.endian.clone();
@@ -177,7 +158,7 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
             x = west + col as f64 * grid_res + 0.5;
             y = north - row as f64 * grid_res - 0.5;
             let ret = frs.search(x, y);
-            if ret.len() > 0 {
+            if !ret.is_empty() {
                 let mut times = vec![];
                 for j in 0..ret.len() {
                     index_n = ret[j].0;
@@ -188,8 +169,8 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
                         times.push(gps_times[ret[j].0]);
                     }
                 }
-                if times.len() > 0 {
-                    times.sort_by(|a, b| a.partial_cmp(&b).unwrap());
+                if !times.is_empty() {
+                    times.sort_by(|a, b| a.partial_cmp(b).unwrap());
                     let mut num_flightlines = 1.0;
                     for j in 1..times.len() {
                         if times[j] - times[j-1] > time_threshold {
@@ -204,10 +185,10 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
                 output.set_value(row, col, nodata);
             }
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Progress: {}%", progress);
+                update_progress("Progress", progress)?;
                 old_progress = progress;
             }
         }
@@ -220,9 +201,9 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     output.add_metadata_entry(format!("Elapsed Time (excluding I/O): {}", elapsed_time).replace("PT", ""));
 
     if verbose { println!("Saving data...") };
-    let _ = match output.write() {
+    match output.write() {
         Ok(_) => if verbose { println!("Output file written") },
-        Err(e) => return Err(e),
+        Err(e) => return Err(e.into()),
     };
 
     Ok(())
@@ -3,155 +3,76 @@
 // a mathematical morphology operator known as an 'Opening', which is an erosion (min filter)
 // followed by a dialation (max filter).
 //////////////////////////////////////////////////////////////////////////////////////////////
-extern crate kdtree;
-extern crate whitebox_tools;
 
-use std::env;
 use std::f64;
 use std::io::Error;
 use std::io::ErrorKind;
-use std::path;
-use whitebox_tools::lidar::las;
-use whitebox_tools::lidar::point_data::*;
-use whitebox_tools::structures::fixed_radius_search::FixedRadiusSearch;
+use crate::lidar::las;
+use crate::lidar::point_data::*;
+use crate::structures::fixed_radius_search::FixedRadiusSearch;
 use kdtree::KdTree;
 use kdtree::distance::squared_euclidean;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
 
-fn main() {
-    let sep: String = path::MAIN_SEPARATOR.to_string();
-    let mut input_file: String = "".to_string();
-    let mut output_file: String = "".to_string();
-    let mut working_directory: String = "".to_string();
-    let mut search_dist = 2.0f64;
-    let mut max_elev_diff = 1.5;
-    let mut min_elev_diff = 0.15;
-    let mut slope_threshold = 60f64;
-    let mut minz = f64::NEG_INFINITY;
-    let mut verbose = false;
-    let mut filter = true;
-    let mut ground_class_value = 2u8;
-    let mut oto_class_value = 1u8;
-    let mut variable_dist = true;
-    let mut num_neighbouring_points = 25;
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 { panic!("Tool run with no paramters. Please see help (-h) for parameter descriptions."); }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        let mut keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--i" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--o" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-wd" || vec[0].to_lowercase() == "--wd" {
-            if keyval {
-                working_directory = vec[1].to_string();
-            } else {
-                working_directory = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-dist" || vec[0].to_lowercase() == "--dist" {
-            variable_dist = false;
-            if keyval {
-                search_dist = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                search_dist = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-num_points" || vec[0].to_lowercase() == "--num_points" {
-            variable_dist = true;
-            if keyval {
-                num_neighbouring_points = vec[1].to_string().parse::<usize>().unwrap();
-            } else {
-                num_neighbouring_points = args[i+1].to_string().parse::<usize>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-slope" || vec[0].to_lowercase() == "--slope" {
-            if keyval {
-                slope_threshold = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                slope_threshold = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-minz" || vec[0].to_lowercase() == "--minz" {
-            if keyval {
-                minz = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                minz = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-minzdiff" || vec[0].to_lowercase() == "--minzdiff" {
-            if keyval {
-                min_elev_diff = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                min_elev_diff = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-maxzdiff" || vec[0].to_lowercase() == "--maxzdiff" {
-            if keyval {
-                max_elev_diff = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                max_elev_diff = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-filter" || vec[0].to_lowercase() == "--filter" {
-            filter = true;
-        } else if vec[0].to_lowercase() == "-class" || vec[0].to_lowercase() == "--class" {
-            filter = false;
-        } else if vec[0].to_lowercase() == "-groundclass" || vec[0].to_lowercase() == "--groundclass" {
-            filter = false;
-            if keyval {
-                ground_class_value = vec[1].to_string().parse::<u8>().unwrap();
-            } else {
-                ground_class_value = args[i+1].to_string().parse::<u8>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-otoclass" || vec[0].to_lowercase() == "--otoclass" {
-            filter = false;
-            if keyval {
-                oto_class_value = vec[1].to_string().parse::<u8>().unwrap();
-            } else {
-                oto_class_value = args[i+1].to_string().parse::<u8>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-v" || vec[0].to_lowercase() == "--verbose" {
-            verbose = true;
-        } else if vec[0].to_lowercase() == "-h" || vec[0].to_lowercase() == "--help" ||
-            vec[0].to_lowercase() == "--h"{
-            let mut s: String = "Help:\n".to_owned();
-             s.push_str("-i           Input LAS file.\n");
-             s.push_str("-o           Output LAS file.\n");
-             s.push_str("-wd          Optional working directory. If specified, filenames parameters need not include a full path.\n");
-             s.push_str("-dist        Optional search distance in xy units; default is 2.0.\n");
-             s.push_str("-slope       Slope threshold, in degrees; default is 60.0.\n");
-             s.push_str("-num_points  Optional number (integer) of nearest-neighbour points in place of constant search distance.\n");
-             s.push_str("-minz        Minimum elevation used in the analysis (optional).\n");
-             s.push_str("-maxzdiff    Maximum elevaton difference between points and opening surface; default 1.5.\n");
-             s.push_str("-minzdiff    Minimum elevaton difference between points to be considered an off-terrain point; default 0.15.\n");
-             s.push_str("-class       If this flag is used, the output LAS file will contain all the points of the input, but classified to indicate whether a point belongs to the slice.\n");
-             s.push_str("-groundclass Class value (integer between 0-31) to be assigned to ground points; default is 2.\n");
-             s.push_str("-otoclass    Class value (integer between 0-31) to be assigned to off-terrain objects (OTOs); default is 1.\n");
-             s.push_str("-v           Verbose mode; if this flag is present, the tool will report progress if this flag is provided.\n");
-             s.push_str("-version     Prints the tool version number.\n");
-             s.push_str("-h           Prints help information.\n\n");
-             s.push_str("Example usage:\n\n");
-             s.push_str(&">> .*lidar_ground_point_separation -wd \"*path*to*data*\" -i \"input.las\" -o \"output.las\" -dist 5.0 -slope 45.0 -v\n".replace("*", &sep));
-             s.push_str(&">> .*lidar_ground_point_separation -wd \"*path*to*data*\" -i \"input.las\" -o \"output.las\" -dist 5.0 -slope 45.0 -minz 0.0 -class -v\n".replace("*", &sep));
-             s.push_str(&">> .*lidar_ground_point_separation -wd \"*path*to*data*\" -i \"input.las\" -o \"output.las\" -dist 5.0 -slope 45.0 -class -groundclass 1 -otoclass 0 -v\n".replace("*", &sep));
-            println!("{}", s);
-            return;
-        } else if vec[0].to_lowercase() == "-version" || vec[0].to_lowercase() == "--version" {
-            const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
-            println!("lidar_ground_point_separation v{}", VERSION.unwrap_or("unknown"));
-            return;
-        }
+pub struct LidarGroundPointSeparation;
+
+impl WhiteboxTool for LidarGroundPointSeparation {
+    fn get_tool_name(&self) -> String {
+        "lidar_ground_point_separation".to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Separates the ground points in a LAS file from the off-terrain objects (OTOs), based on the slope between
+each point and its neighbours and the elevation of the point above a morphological opening of the surface.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Search Distance", &["--dist"], ParameterType::Float, None, "Search distance in xy units; if specified, a constant search distance is used in place of --num_points.").with_min_value(0.0),
+            ToolParameter::optional("Number of Points", &["--num_points"], ParameterType::Integer, Some("25"), "Number of nearest-neighbour points used in place of a constant search distance.").with_min_value(1.0),
+            ToolParameter::optional("Slope Threshold", &["--slope"], ParameterType::Float, Some("60.0"), "Slope threshold, in degrees.").with_range(0.0, 90.0),
+            ToolParameter::optional("Minimum Elevation", &["--minz"], ParameterType::Float, None, "Minimum elevation used in the analysis."),
+            ToolParameter::optional("Maximum Elevation Difference", &["--maxzdiff"], ParameterType::Float, Some("1.5"), "Maximum elevation difference between points and the opening surface."),
+            ToolParameter::optional("Minimum Elevation Difference", &["--minzdiff"], ParameterType::Float, Some("0.15"), "Minimum elevation difference between points to be considered an off-terrain point."),
+            ToolParameter::optional("Classify Points", &["--class"], ParameterType::Boolean, None, "Output all of the points of the input, classified as ground or OTO points, rather than only the ground points."),
+            ToolParameter::optional("Ground Class", &["--groundclass"], ParameterType::Integer, Some("2"), "Class value (integer between 0-31) to be assigned to ground points; implies --class.").with_range(0.0, 31.0),
+            ToolParameter::optional("OTO Class", &["--otoclass"], ParameterType::Integer, Some("1"), "Class value (integer between 0-31) to be assigned to off-terrain objects (OTOs); implies --class.").with_range(0.0, 31.0),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_ground_point_separation -v --wd=\"/path/to/data/\" --args=\"-i=input.las -o=output.las --dist=5.0 --slope=45.0 --minz=0.0 --class\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
     }
+}
 
-    match run(
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let variable_dist = !args.is_given("--dist");
+    let search_dist = args.get_float("--dist").unwrap_or(2.0);
+    let num_neighbouring_points = args.get_integer("--num_points")? as usize;
+    let slope_threshold = args.get_float("--slope")?;
+    let minz = args.get_float("--minz").unwrap_or(f64::NEG_INFINITY);
+    let max_elev_diff = args.get_float("--maxzdiff")?;
+    let min_elev_diff = args.get_float("--minzdiff")?;
+    let filter = !(args.get_bool("--class") || args.is_given("--groundclass") || args.is_given("--otoclass"));
+    let ground_class_value = args.get_integer("--groundclass")? as u8;
+    let oto_class_value = args.get_integer("--otoclass")? as u8;
+
+    lidar_ground_point_separation(
         input_file, output_file,
-        working_directory,
         search_dist,
         min_elev_diff,
         max_elev_diff,
@@ -163,13 +84,13 @@ fn main() {
         oto_class_value,
         variable_dist,
         num_neighbouring_points
-    ) {
-        Ok(()) => println!("Complete!"),
-        Err(err) => panic!("{}", err),
-    }
+    )?;
+    println!("Complete!");
+
+    Ok(())
 }
 
-fn run(mut input_file: String, mut output_file: String, mut working_directory: String,
+fn lidar_ground_point_separation(input_file: String, output_file: String,
     search_dist: f64, min_elev_diff: f64, max_elev_diff: f64, mut slope_threshold: f64, minz: f64,
     verbose: bool, filter: bool, ground_class_value: u8, oto_class_value: u8,
     variable_dist: bool, num_neighbouring_points: usize)
@@ -185,27 +106,17 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
 
     slope_threshold = slope_threshold.to_radians().tan();
 
-    let sep = std::path::MAIN_SEPARATOR;
-    if !working_directory.ends_with(sep) {
-        working_directory.push_str(&(sep.to_string()));
-    }
-
-    if !input_file.contains(sep) {
-        input_file = format!("{}{}", working_directory, input_file);
-    }
-
-    if !output_file.contains(sep) {
-        output_file = format!("{}{}", working_directory, output_file);
-    }
-
     // let input: las::LasFile = las::LasFile::new(&input_file, "r")?;
     let input = match las::LasFile::new(&input_file, "r") {
         Ok(lf) => lf,
-        Err(err) => panic!("Error reading file {}: {}", input_file, err),
+        Err(err) => return Err(Error::new(err.kind(), format!("Error reading file {}: {}", input_file, err))),
     };
 
     let n_points = input.header.number_of_points as usize;
-    let num_points: f64 = (input.header.number_of_points - 1) as f64; // used for progress calculation only
+    if input.header.number_of_points == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("The input file {} contains no points.", input_file)));
+    }
+    let num_points: f64 = (input.header.number_of_points as f64 - 1.0).max(1.0); // used for progress calculation only
     let mut is_off_terrain = vec![false; n_points];
 
     let mut progress: i32;
@@ -224,10 +135,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
             let p: PointData = input.get_point_info(i);
             if p.z > minz && p.classification() != 7u8 && p.classification() != 18u8 {
                 frs.insert(p.x, p.y, i);
-                if verbose {
+                if progress_enabled(verbose) {
                     progress = (100.0_f64 * i as f64 / num_points) as i32;
                     if progress != old_progress {
-                        println!("Binning points: {}%", progress);
+                        update_progress("Binning points", progress as usize)?;
                         old_progress = progress;
                     }
                 }
@@ -249,10 +160,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
                     }
                 }
             // }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Performing erosion: {}%", progress);
+                    update_progress("Performing erosion", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -273,10 +184,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
                 // }
                 residuals[i] = p.z - neighbourhood_max_min[i];
             // }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Performing dilation: {}%", progress);
+                    update_progress("Performing dilation", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -327,10 +238,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
             } else {
                 is_off_terrain[i] = true;
             }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Performing analysis: {}%", progress);
+                    update_progress("Performing analysis", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -375,7 +286,7 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
         //     if verbose {
         //         progress = (100.0_f64 * i as f64 / num_points) as i32;
         //         if progress != old_progress {
-        //             println!("Performing Interpolation: {}%", progress);
+        //             update_progress("Performing Interpolation", progress as usize)?;
         //             old_progress = progress;
         //         }
         //     }
@@ -391,11 +302,11 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
             let p: PointData = input.get_point_info(i);
             if p.z > minz {
                 let coords: [f64; 2] = [ p.x, p.y ];
-                kdtree.add(coords.clone(), i).unwrap();
-                if verbose {
+                kdtree.add(coords, i).unwrap();
+                if progress_enabled(verbose) {
                     progress = (100.0_f64 * i as f64 / num_points) as i32;
                     if progress != old_progress {
-                        println!("Creating tree: {}%", progress);
+                        update_progress("Creating tree", progress as usize)?;
                         old_progress = progress;
                     }
                 }
@@ -415,10 +326,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
                     }
                 }
             }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Performing Analysis (Loop 1 of 2): {}%", progress);
+                    update_progress("Performing Analysis (Loop 1 of 2)", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -438,10 +349,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
                     is_off_terrain[i] = true;
                 }
             }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Performing Analysis (Loop 2 of 2): {}%", progress);
+                    update_progress("Performing Analysis (Loop 2 of 2)", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -456,7 +367,7 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
     if filter {
         for i in 0..input.header.number_of_points as usize {
             if !is_off_terrain[i] {
-                output.add_point_record(input.get_record(i));
+                output.add_point_record_with_extra_bytes(input.get_record(i), input.get_extra_bytes(i));
                 // let pr = input.get_record(i);
                 // let pr2: las::LidarPointRecord;
                 // match pr {
@@ -482,10 +393,10 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
                 // output.add_point_record(pr2);
                 num_points_filtered += 1;
             }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Saving data: {}%", progress);
+                    update_progress("Saving data", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -496,32 +407,67 @@ fn run(mut input_file: String, mut output_file: String, mut working_directory: S
             let mut class_val = ground_class_value; // ground point
             if is_off_terrain[i] { class_val = oto_class_value; } // off terrain point
             let pr = input.get_record(i);
-            let pr2: las::LidarPointRecord;
-            match pr {
+            let pr2: las::LidarPointRecord = match pr {
                 las::LidarPointRecord::PointRecord0 { mut point_data }  => {
                     point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord0 { point_data: point_data };
+                    las::LidarPointRecord::PointRecord0 { point_data }
 
                 },
                 las::LidarPointRecord::PointRecord1 { mut point_data, gps_data } => {
                     point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord1 { point_data: point_data, gps_data: gps_data };
+                    las::LidarPointRecord::PointRecord1 { point_data, gps_data }
                 },
                 las::LidarPointRecord::PointRecord2 { mut point_data, rgb_data } => {
                     point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord2 { point_data: point_data, rgb_data: rgb_data };
+                    las::LidarPointRecord::PointRecord2 { point_data, rgb_data }
                 },
                 las::LidarPointRecord::PointRecord3 { mut point_data, gps_data, rgb_data } => {
                     point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord3 { point_data: point_data,
-                        gps_data: gps_data, rgb_data: rgb_data};
+                    las::LidarPointRecord::PointRecord3 { point_data,
+                        gps_data, rgb_data}
                 },
-            }
-            output.add_point_record(pr2);
-            if verbose {
+                las::LidarPointRecord::PointRecord4 { mut point_data, gps_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord4 { point_data,
+                        gps_data, wave_packet}
+                },
+                las::LidarPointRecord::PointRecord5 { mut point_data, gps_data, rgb_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord5 { point_data,
+                        gps_data, rgb_data, wave_packet}
+                },
+                las::LidarPointRecord::PointRecord6 { mut point_data, gps_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord6 { point_data,
+                        gps_data}
+                },
+                las::LidarPointRecord::PointRecord7 { mut point_data, gps_data, rgb_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord7 { point_data,
+                        gps_data, rgb_data}
+                },
+                las::LidarPointRecord::PointRecord8 { mut point_data, gps_data, rgb_data, nir_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord8 { point_data,
+                        gps_data, rgb_data, nir_data}
+                },
+                las::LidarPointRecord::PointRecord9 { mut point_data, gps_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord9 { point_data,
+                        gps_data, wave_packet}
+                },
+                las::LidarPointRecord::PointRecord10 { mut point_data, gps_data, rgb_data, nir_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord10 { point_data,
+                        gps_data, rgb_data,
+                        nir_data, wave_packet}
+                },
+            };
+            output.add_point_record_with_extra_bytes(pr2, input.get_extra_bytes(i));
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Saving data: {}%", progress);
+                    update_progress("Saving data", progress as usize)?;
                     old_progress = progress;
                 }
             }
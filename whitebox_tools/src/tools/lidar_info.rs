@@ -1,87 +1,58 @@
-use std;
-use std::io::{Error, ErrorKind};
-use std::u16;
-use lidar::las;
-use lidar::point_data::*;
-
-pub fn get_tool_name() -> String {
-    return "lidar_info".to_string();
-}
+use std::io::Error;
+use crate::lidar::las_reader::LasReader;
+use crate::lidar::point_data::*;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
 
-pub fn get_tool_description() -> String {
-    let s = "Prints information about a LiDAR (LAS) dataset, including header, point return frequency,
-and classification data and information about the variable length records (VLRs) and geokeys.";
+const TOOL_NAME: &str = "lidar_info";
 
-    return s.to_string();
-}
+pub struct LidarInfo;
 
-pub fn get_tool_parameters() -> String {
-    let s = "-i, input        Input LAS file.
---vlr            Flag indicates whether to print variable length records (VLRs).
---geokeys        Flag indicates whether to print the geokeys.";
+impl WhiteboxTool for LidarInfo {
+    fn get_tool_name(&self) -> String {
+        TOOL_NAME.to_string()
+    }
 
-    return s.to_string();
-}
+    fn get_tool_description(&self) -> String {
+        let s = "Prints information about a LiDAR (LAS) dataset, including header, point return frequency,
+and classification data and information about the variable length records (VLRs) and geokeys.";
 
-pub fn get_example_usage() -> Option<String> {
-    let s = "./whitebox-tools -r=lidar_info --wd=\"/dir/to/data\" --args=\"-i=file.las --vlr --geokeys\"
-./whitebox-tools -r=lidar_info --wd=\"/dir/to/data\" --args=\"-i=file.las\"";
-    return Some(s.to_string());
-}
+        s.to_string()
+    }
 
-pub fn run<'a>(args: Vec<String>, working_directory: &'a str) -> Result<(), Error> {
-    let mut input_file: String = "".to_string();
-    // let mut working_directory: String = "".to_string();
-    let mut show_vlrs = false;
-    let mut show_geokeys = false;
-    let mut keyval: bool;
-    if args.len() == 0 {
-        return Err(Error::new(ErrorKind::InvalidInput, "Tool run with no paramters. Please see help (-h) for parameter descriptions."));
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS or LAZ file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Print VLRs", &["--vlr"], ParameterType::Boolean, None, "Flag indicates whether to print variable length records (VLRs and EVLRs)."),
+            ToolParameter::optional("Print Geokeys", &["--geokeys"], ParameterType::Boolean, None, "Flag indicates whether to print the geokeys."),
+        ]
     }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--input" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        // } else if vec[0].to_lowercase() == "-wd" || vec[0].to_lowercase() == "--wd" {
-        //     if keyval {
-        //         working_directory = vec[1].to_string();
-        //     } else {
-        //         working_directory = args[i+1].to_string();
-        //     }
-        } else if vec[0].to_lowercase() == "-vlr" || vec[0].to_lowercase() == "--vlr" {
-            show_vlrs = true;
-        } else if vec[0].to_lowercase() == "-geokeys" || vec[0].to_lowercase() == "--geokeys" {
-            show_geokeys = true;
-        }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_info --wd=\"/dir/to/data\" --args=\"-i=file.las --vlr --geokeys\"
+./whitebox-tools -r=lidar_info --wd=\"/dir/to/data\" --args=\"-i=file.las\"";
+        Some(s.to_string())
     }
 
-    println!("**************");
-    println!("* {} *", get_tool_name());
-    println!("**************");
+    fn run(&self, args: Vec<String>, working_directory: &str, _verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args)
+    }
+}
 
-    let sep = std::path::MAIN_SEPARATOR;
-    // if !working_directory.ends_with(sep) {
-    //     working_directory.push_str(&(sep.to_string()));
-    // }
+fn run(args: &ToolArgs) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let show_vlrs = args.get_bool("--vlr");
+    let show_geokeys = args.get_bool("--geokeys");
 
-    if !input_file.contains(sep) {
-        input_file = format!("{}{}", working_directory, input_file);
-    }
+    println!("**************");
+    println!("* {} *", TOOL_NAME);
+    println!("**************");
 
-    let input: las::LasFile = match las::LasFile::new(&input_file, "r") {
-        Ok(lf) => lf,
-        Err(_) => return Err(Error::new(ErrorKind::NotFound, format!("No such file or directory ({})", input_file))),
-    };
-    println!("{}", input);
+    // The points are streamed in chunks, so that files larger than memory can be summarized.
+    let mut input = LasReader::new(&input_file)?;
+    println!("File Name: {}\n{}", input_file, input.header);
+    println!("Coordinate Reference System: {}", input.spatial_reference);
 
     let num_points = input.header.number_of_points;
     let mut min_i = u16::MAX;
@@ -94,36 +65,40 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str) -> Result<(), Erro
     let mut ret: u8;
     let mut nrets: u8;
     let mut p: PointData;
-    let mut ret_array: [i32; 5] = [0; 5];
-    let mut class_array: [i32; 256] = [0; 256];
-    for i in 0..input.header.number_of_points as usize {
-        p = input[i]; //.get_point_info(i);
-        ret = p.return_number();
-        if ret > 5 {
-            // Return is too high
-            ret = 5;
-        }
-        ret_array[(ret - 1) as usize] += 1;
-        nrets = p.number_of_returns();
-        class_array[p.classification() as usize] += 1;
-        if nrets == 1 {
-            num_only += 1;
-        } else if ret == 1 && nrets > 1 {
-            num_first += 1;
-        } else if ret == nrets {
-            num_last += 1;
-        } else {
-            num_intermediate += 1;
+    let mut ret_array: [i64; 15] = [0; 15];
+    // The extended point formats (6-10) of LAS 1.4 support up to 15 returns.
+    let max_returns: u8 = if input.header.point_format >= 6 { 15 } else { 5 };
+    let mut class_array: [i64; 256] = [0; 256];
+    for chunk in &mut input {
+        for pr in chunk? {
+            p = pr.point_data();
+            ret = p.return_number();
+            if ret > max_returns {
+                // Return is too high
+                ret = max_returns;
+            }
+            ret_array[(ret - 1) as usize] += 1;
+            nrets = p.number_of_returns();
+            class_array[p.classification() as usize] += 1;
+            if nrets == 1 {
+                num_only += 1;
+            } else if ret == 1 && nrets > 1 {
+                num_first += 1;
+            } else if ret == nrets {
+                num_last += 1;
+            } else {
+                num_intermediate += 1;
+            }
+            intensity = p.intensity;
+            if intensity > max_i { max_i = intensity; }
+            if intensity < min_i { min_i = intensity; }
         }
-        intensity = p.intensity;
-        if intensity > max_i { max_i = intensity; }
-        if intensity < min_i { min_i = intensity; }
     }
 
     println!("\n\nMin I: {}\nMax I: {}", min_i, max_i);
 
     println!("\nPoint Return Table");
-    for i in 0..5 {
+    for i in 0..max_returns as usize {
         println!("Return {}:           {}", i + 1, ret_array[i]);
     }
 
@@ -148,6 +123,9 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str) -> Result<(), Erro
         for i in 0..(input.header.number_of_vlrs as usize) {
             println!("\nVLR {}:\n{}", i, input.vlr_data[i].clone());
         }
+        for i in 0..input.evlr_data.len() {
+            println!("\nEVLR {}:\n{}", i, input.evlr_data[i].clone());
+        }
     }
 
     if show_geokeys {
@@ -1,125 +1,148 @@
 extern crate nalgebra as na;
 
-use std;
+use std::f64;
 use std::io::{Error, ErrorKind};
-use lidar::las;
+use crate::lidar::header::LasHeader;
+use crate::lidar::las;
+use crate::lidar::las_reader::LasReader;
+use crate::lidar::las_writer::LasWriter;
+use crate::lidar::vlr::Vlr;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
 
-pub fn get_tool_name() -> String {
-    return "lidar_join".to_string();
-}
-
-pub fn get_tool_description() -> String {
-    let s = "Joins multiple LiDAR (LAS) files into a single LAS file.";
-
-    return s.to_string();
-}
+const TOOL_NAME: &str = "lidar_join";
 
-pub fn get_tool_parameters() -> String {
-    let s = "-i, --inputs       Input LAS files, separated by commas
--o, --output       Output LAS file.";
+pub struct LidarJoin;
 
-    return s.to_string();
-}
-
-pub fn get_example_usage() -> Option<String> {
-    let s = "./whitebox-tools -r=lidar_join --wd=\"/dir/to/data\" --args=\"-i=file1.las, file2.las, file3.las -o=outfile.las\"";
-    return Some(s.to_string());
-}
+impl WhiteboxTool for LidarJoin {
+    fn get_tool_name(&self) -> String {
+        TOOL_NAME.to_string()
+    }
 
-pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) -> Result<(), Error> {
-    let mut input_files: String = String::new();
-    let mut output_file = String::new();
+    fn get_tool_description(&self) -> String {
+        let s = "Joins multiple LiDAR (LAS or LAZ) files into a single LAS file.";
 
-    // read the arguments
-    if args.len() == 0 {
-        return Err(Error::new(ErrorKind::InvalidInput, "Tool run with no paramters. Please see help (-h) for parameter descriptions."));
-    }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        let mut keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--inputs" {
-            if keyval {
-                input_files = vec[1].to_string();
-            } else {
-                input_files = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--output" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        }
+        s.to_string()
     }
 
-    if verbose {
-        println!("***************{}", "*".repeat(get_tool_name().len()));
-        println!("* Welcome to {} *", get_tool_name());
-        println!("***************{}", "*".repeat(get_tool_name().len()));
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input Files", &["-i", "--inputs"], ParameterType::FileList, "Input LAS or LAZ files, separated by commas.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file; a .laz extension produces a compressed (LAZ) file.").with_file_type(ParameterFileType::Lidar),
+        ]
     }
 
-    let sep = std::path::MAIN_SEPARATOR;
-    // if !working_directory.ends_with(sep) {
-    //     working_directory.push_str(&(sep.to_string()));
-    // }
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_join --wd=\"/dir/to/data\" --args=\"-i=file1.las, file2.las, file3.las -o=outfile.las\"";
+        Some(s.to_string())
+    }
 
-    if !output_file.contains(sep) {
-        output_file = format!("{}{}", working_directory, output_file);
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
     }
+}
 
-    let mut output: las::LasFile = las::LasFile::new(&output_file, "w")?;
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let inputs = args.get_list("--inputs")?;
+    let output_file = args.get_string("--output")?;
 
-    let mut cmd = input_files.split(";");
-    let mut vec = cmd.collect::<Vec<&str>>();
-    if vec.len() == 1 {
-        cmd = input_files.split(",");
-        vec = cmd.collect::<Vec<&str>>();
+    if verbose {
+        println!("***************{}", "*".repeat(TOOL_NAME.len()));
+        println!("* Welcome to {} *", TOOL_NAME);
+        println!("***************{}", "*".repeat(TOOL_NAME.len()));
     }
-    let mut i = 0;
-    let num_files = vec.len();
-    let mut file_format = -1i32;
-    for value in vec {
-        if !value.trim().is_empty() {
-            let mut input_file = value.trim().to_owned();
-            if !input_file.contains(sep) {
-                input_file = format!("{}{}", working_directory, input_file);
-            }
 
-            let input: las::LasFile = match las::LasFile::new(&input_file, "r") {
-                Ok(lf) => lf,
-                Err(_) => return Err(Error::new(ErrorKind::NotFound, format!("No such file or directory ({})", input_file))),
-            };
-
-            if file_format == -1 {
-                file_format = input.header.point_format as i32;
-            } else {
-                if input.header.point_format as i32 != file_format {
-                    return Err(Error::new(ErrorKind::InvalidData, "All input files must be of the same LAS Point Format."));
+    // The points are streamed from each input file to the output file, so that the inputs
+    // need not fit in memory. The headers are read first to verify the point formats and to
+    // find the combined extent, which determines the output offsets.
+    let mut headers: Vec<LasHeader> = vec![];
+    let mut vlrs: Vec<Vlr> = vec![];
+    for input_file in &inputs {
+        let input = las::LasFile::new(input_file, "rh")?;
+        if headers.is_empty() {
+            vlrs = input.vlr_data.clone();
+        } else if input.header.point_format != headers[0].point_format {
+            return Err(Error::new(ErrorKind::InvalidData, "All input files must be of the same LAS Point Format."));
+        } else {
+            // keep any waveform packet descriptors that the first file lacks
+            for vlr in &input.vlr_data {
+                if vlr.is_waveform_packet_descriptor() &&
+                    !vlrs.iter().any(|v| v.is_waveform_packet_descriptor() && v.record_id == vlr.record_id) {
+                    vlrs.push(vlr.clone());
                 }
             }
+        }
+        headers.push(input.header);
+    }
+    if headers.is_empty() {
+        return Err(Error::new(ErrorKind::InvalidInput, "No input files were specified."));
+    }
 
-            if i == 0 {
-                output = las::LasFile::initialize_using_file(&output_file, &input);
+    let mut header = headers[0].clone();
+    header.x_offset = f64::INFINITY;
+    header.y_offset = f64::INFINITY;
+    header.z_offset = f64::INFINITY;
+    for h in &headers {
+        if h.number_of_points == 0 { continue; }
+        if h.min_x < header.x_offset { header.x_offset = h.min_x; }
+        if h.min_y < header.y_offset { header.y_offset = h.min_y; }
+        if h.min_z < header.z_offset { header.z_offset = h.min_z; }
+    }
+    if header.x_offset == f64::INFINITY {
+        // there are no points
+        header.x_offset = 0f64;
+        header.y_offset = 0f64;
+        header.z_offset = 0f64;
+    }
+    let mut output = LasWriter::new(&output_file, &header, &vlrs, &[])?;
+
+    // The waveform data of the inputs is appended to a single waveform data packet record
+    // and the offsets of the points into it are shifted accordingly. Any other EVLRs are
+    // taken from the first file.
+    let mut waveform_evlr: Option<Vlr> = None;
+    let num_files = inputs.len();
+    for i in 0..num_files {
+        let mut input = LasReader::new(&inputs[i])?;
+        // the extra bytes of the points are retained if they are laid out as those of the first file
+        let same_extra_bytes = input.get_extra_bytes_descriptors() == output.get_extra_bytes_descriptors();
+        if i == 0 {
+            output.evlr_data = input.evlr_data.iter().filter(|v| !v.is_waveform_data()).cloned().collect();
+        }
+        let mut waveform_offset = 0u64;
+        if let Some(evlr) = input.evlr_data.iter().find(|v| v.is_waveform_data()) {
+            match waveform_evlr {
+                Some(ref mut w) => {
+                    waveform_offset = w.binary_data.len() as u64;
+                    w.binary_data.extend_from_slice(&evlr.binary_data);
+                },
+                None => waveform_evlr = Some(evlr.clone()),
             }
-
-            let n_points = input.header.number_of_points as usize;
-
-            let mut pr: las::LidarPointRecord;
-            for i in 0..n_points {
-                pr = input.get_record(i);
-                output.add_point_record(pr);
+        }
+        loop {
+            let chunk = input.read_chunk()?;
+            if chunk.is_empty() { break; }
+            for (j, mut pr) in chunk.into_iter().enumerate() {
+                if waveform_offset > 0 {
+                    if let Some(wp) = pr.waveform_packet_mut() {
+                        wp.offset_to_waveform_data += waveform_offset;
+                    }
+                }
+                if same_extra_bytes {
+                    output.add_point_record_with_extra_bytes(pr, input.get_extra_bytes(j))?;
+                } else {
+                    output.add_point_record(pr)?;
+                }
             }
         }
-        i += 1;
-        if verbose { println!("Adding file: {} of {}", i, num_files); }
+        if verbose { println!("Adding file: {} of {}", i + 1, num_files); }
+    }
+    if let Some(w) = waveform_evlr {
+        output.evlr_data.push(w);
     }
 
     if verbose { println!("Writing output LAS file..."); }
-    output.write()?;
+    output.close()?;
 
     Ok(())
 }
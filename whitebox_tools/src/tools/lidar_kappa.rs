@@ -1,116 +1,73 @@
 #![allow(dead_code, unused_assignments)]
 
-extern crate whitebox_tools;
-
-use std::env;
 use std::io::prelude::*;
 use std::fs::File;
-use std::path;
+use std::io::Error;
+use std::io::ErrorKind;
 use std::process::Command;
-use whitebox_tools::lidar::las;
-use whitebox_tools::lidar::point_data::*;
-
-fn main() {
-    let sep: String = path::MAIN_SEPARATOR.to_string();
-    let mut input_file1: String = "".to_string();
-    let mut input_file2: String = "".to_string();
-    let mut output_file: String = "".to_string();
-    let mut working_directory: String = "".to_string();
-    let mut keyval = false;
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 { panic!("Tool run with no paramters. Please see help (-h) for parameter descriptions."); }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i1" || vec[0].to_lowercase() == "--input1" {
-            if keyval {
-                input_file1 = vec[1].to_string();
-            } else {
-                input_file1 = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-i2" || vec[0].to_lowercase() == "--input2" {
-            if keyval {
-                input_file2 = vec[1].to_string();
-            } else {
-                input_file2 = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--output" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-wd" || vec[0].to_lowercase() == "--wd" {
-            if keyval {
-                working_directory = vec[1].to_string();
-            } else {
-                working_directory = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-h" || vec[0].to_lowercase() == "--help" ||
-            vec[0].to_lowercase() == "--h"{
-            let mut s: String = "Help:\n".to_owned();
-                     s.push_str("-i1      Input LAS file (classification).\n");
-                     s.push_str("-i2      Input LAS file (reference).\n");
-                     s.push_str("-o       Output HTML file.\n");
-                     s.push_str("-wd      Optional working directory. If specified, filenames parameters need not include a full path.\n");
-                     s.push_str("-version Prints the tool version number.\n");
-                     s.push_str("-h       Prints help information.\n\n");
-                     s.push_str("Example usage:\n\n");
-                     s.push_str(&">> .*lidar_kappa -wd *path*to*data* -i1 class.las -i2 ref.las -o kappa.html\n".replace("*", &sep));
-            println!("{}", s);
-            return;
-        } else if vec[0].to_lowercase() == "-version" || vec[0].to_lowercase() == "--version" {
-            const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
-            println!("lidar_segmentation v{}", VERSION.unwrap_or("unknown"));
-            return;
-        }
+use crate::lidar::las;
+use crate::lidar::point_data::*;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+
+pub struct LidarKappa;
+
+impl WhiteboxTool for LidarKappa {
+    fn get_tool_name(&self) -> String {
+        "lidar_kappa".to_string()
     }
 
-    println!("**************************");
-    println!("* Welcome to lidar_kappa *");
-    println!("**************************");
+    fn get_tool_description(&self) -> String {
+        let s = "Performs a kappa index of agreement (KIA) analysis on the classifications of two LAS files that contain the
+same points, and outputs the error matrix and accuracy statistics as an HTML report.";
 
-    let sep = std::path::MAIN_SEPARATOR;
-    if !working_directory.ends_with(sep) {
-        working_directory.push_str(&(sep.to_string()));
+        s.to_string()
     }
 
-    if !input_file1.contains(sep) {
-        input_file1 = format!("{}{}", working_directory, input_file1);
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File 1", &["-i1", "--input1"], ParameterType::ExistingFile, "Input LAS file (classification).").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Input File 2", &["-i2", "--input2"], ParameterType::ExistingFile, "Input LAS file (reference).").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output HTML file.").with_file_type(ParameterFileType::Html),
+        ]
     }
 
-    if !input_file2.contains(sep) {
-        input_file2 = format!("{}{}", working_directory, input_file2);
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_kappa -v --wd=\"/path/to/data/\" --args=\"-i1=class.las -i2=ref.las -o=kappa.html\"";
+        Some(s.to_string())
     }
 
-    if !output_file.contains(sep) {
-        output_file = format!("{}{}", working_directory, output_file);
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file1 = args.get_string("--input1")?;
+    let input_file2 = args.get_string("--input2")?;
+    let mut output_file = args.get_string("--output")?;
+
+    if verbose {
+        println!("**************************");
+        println!("* Welcome to lidar_kappa *");
+        println!("**************************");
     }
 
     if !output_file.ends_with(".html") {
-        output_file = output_file + ".html";
+        output_file += ".html";
     }
 
     // let input1 = las::LasFile::new(&input_file1, "r");
     // let input2 = las::LasFile::new(&input_file2, "r");
 
-    let input1: las::LasFile = match las::LasFile::new(&input_file1, "r") {
-        Ok(lf) => lf,
-        Err(err) => panic!("Error: {}", err),
-    };
+    let input1: las::LasFile = las::LasFile::new(&input_file1, "r")?;
 
-    let input2: las::LasFile = match las::LasFile::new(&input_file2, "r") {
-        Ok(lf) => lf,
-        Err(err) => panic!("Error: {}", err),
-    };
+    let input2: las::LasFile = las::LasFile::new(&input_file2, "r")?;
 
     let num_points = input1.header.number_of_points;
     if input2.header.number_of_points != num_points {
-        panic!("Error: The input files do not contain the same number of points.");
+        return Err(Error::new(ErrorKind::InvalidInput, "The input files do not contain the same number of points."));
     }
     let mut error_matrix: [[usize; 256]; 256] = [[0; 256]; 256];
     let mut active_class: [bool; 256] = [false; 256];
@@ -157,7 +114,7 @@ fn main() {
         expected_frequency += (col_total as f64 * row_total as f64) / (n as f64);
     }
 
-    kappa = (agreements as f64 - expected_frequency as f64) / (n as f64 - expected_frequency as f64);
+    kappa = (agreements as f64 - expected_frequency) / (n as f64 - expected_frequency);
     overall_accuracy = agreements as f64 / n as f64;
 
     //for a in 0..256usize {
@@ -172,7 +129,7 @@ fn main() {
     //    }
     //}
 
-    let mut f = File::create(output_file.as_str()).unwrap();
+    let mut f = File::create(output_file.as_str())?;
 
     // let mut s = "<!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">";
     // f.write(s.as_bytes()).unwrap();
@@ -225,62 +182,62 @@ fn main() {
         </style>
     </head>
     <body>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     s = "<body><h1>Kappa Index of Agreement</h1>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     let s2 = &format!("{}{}{}{}{}", "<b>Input Data:</b> <br><br><b>Classification Data:</b> ", input_file1, "<br><b>Reference Data:</b> ", input_file2, "<br>");
-    f.write(s2.as_bytes()).unwrap();
+    f.write_all(s2.as_bytes()).unwrap();
     s = "<br><b>Contingency Table:</b><br>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     s = "<br><table border=\"1\" cellspacing=\"0\" cellpadding=\"3\">";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     s = "<tr>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     let s3 = &format!("{}{}{}", "<th colspan=\"2\" rowspan=\"2\"></th><th colspan=\"", num_classes, "\">Reference Data</th><th rowspan=\"2\">Row<br>Totals</th>");
-    f.write(s3.as_bytes()).unwrap();
+    f.write_all(s3.as_bytes()).unwrap();
     s = "</tr>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     s = "<tr>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     for a in 0..256 {
         if active_class[a] {
             let s = &format!("{}{}{}", "<th>", convert_class_val_to_class_string(a as u8), "</th>");
-            f.write(s.as_bytes()).unwrap();
+            f.write_all(s.as_bytes()).unwrap();
         }
     }
 
     s = "</tr>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     let mut first_entry = true;
     for a in 0..256 {
         if active_class[a] {
             if first_entry {
                 let s = format!("{}{}{}{}{}", "<tr><th rowspan=\"", num_classes, "\">Class<br>Data</th> <th>", convert_class_val_to_class_string(a as u8), "</th>");
-                f.write(s.as_bytes()).unwrap();
+                f.write_all(s.as_bytes()).unwrap();
             } else {
                 let s = format!("{}{}{}", "<tr><th>", convert_class_val_to_class_string(a as u8), "</th>");
-                f.write(s.as_bytes()).unwrap();
+                f.write_all(s.as_bytes()).unwrap();
             }
             row_total = 0;
             for b in 0..256 {
                 if active_class[b] {
                     row_total += error_matrix[a][b];
                     let s = format!("{}{}{}", "<td>", error_matrix[a][b], "</td>");
-                    f.write(s.as_bytes()).unwrap();
+                    f.write_all(s.as_bytes()).unwrap();
                 }
             }
             let s = format!("{}{}{}", "<td>", row_total, "</td>");
-            f.write(s.as_bytes()).unwrap();
+            f.write_all(s.as_bytes()).unwrap();
 
             let s2 = "</tr>";
-            f.write(s2.as_bytes()).unwrap();
+            f.write_all(s2.as_bytes()).unwrap();
             first_entry = false;
         }
     }
     s = "<tr>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     s = "<th colspan=\"2\">Column Totals</th>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     for a in 0..256 {
         if active_class[a] {
             col_total = 0;
@@ -290,20 +247,20 @@ fn main() {
                 }
             }
             let s = &format!("{}{}{}", "<td>", col_total, "</td>");
-            f.write(s.as_bytes()).unwrap();
+            f.write_all(s.as_bytes()).unwrap();
         }
     }
 
     let s4 = &format!("{}{}{}", "<td><b>N</b>=", n, "</td></tr>");
-    f.write(s4.as_bytes()).unwrap();
+    f.write_all(s4.as_bytes()).unwrap();
     s = "</table>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     s = "<br><b>Class Statistics:</b><br><br>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     s = "<table border=\"1\" cellspacing=\"0\" cellpadding=\"3\">";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
     s = "<tr><td><b>Class</b></td><td><b>User's Accuracy</b><sup>1</sup><br>(Reliability)</td><td><b>Producer's Accuracy</b><sup>1</sup><br>(Accuracy)</td></tr>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
 
     //DecimalFormat df = new DecimalFormat("0.00%");
     //DecimalFormat df2 = new DecimalFormat("0.000");
@@ -323,31 +280,31 @@ fn main() {
             }
             average_users += 100.0 * error_matrix[a][a] as f64 / col_total as f64;
             average_producers += 100.0 * error_matrix[a][a] as f64 / row_total as f64;
-            let s = &format!("{}{}{}{}{}{}{}", "<tr><td>",  convert_class_val_to_class_string(a as u8), "</td><td>", format!("{:.*}", 2, (100.0 * error_matrix[a][a] as f64 / col_total as f64)),
-                    "%</td><td>", format!("{:.*}", 2, (100.0 * error_matrix[a][a] as f64 / row_total as f64)), "%</td></tr>");
-            f.write(s.as_bytes()).unwrap();
+            let s = &format!("{}{}{}{:.2}{}{:.2}{}", "<tr><td>",  convert_class_val_to_class_string(a as u8), "</td><td>", (100.0 * error_matrix[a][a] as f64 / col_total as f64),
+                    "%</td><td>", (100.0 * error_matrix[a][a] as f64 / row_total as f64), "%</td></tr>");
+            f.write_all(s.as_bytes()).unwrap();
         }
     }
-    f.write(format!("<tr><td>Average</td><td>{}%</td><td>{}%</td></tr>", format!("{:.*}", 2, average_users / num_active),
-            format!("{:.*}", 2, average_producers / num_active)).as_bytes()).unwrap();
+    f.write_all(format!("<tr><td>Average</td><td>{:.2}%</td><td>{:.2}%</td></tr>", average_users / num_active,
+            average_producers / num_active).as_bytes()).unwrap();
 
 
     s = "</table>";
-    f.write(s.as_bytes()).unwrap();
-    let s6 = &format!("{}{}", "<br><b>Overall Accuracy</b> = ", format!("{:.*}%", 2, overall_accuracy * 100.0));
-    f.write(s6.as_bytes()).unwrap();
-    let s7 = &format!("<br><br><b>Kappa</b><sup>2</sup> = {}<br>", format!("{:.*}", 3, kappa));
-    f.write(s7.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
+    let s6 = &format!("{}{:.2}%", "<br><b>Overall Accuracy</b> = ", overall_accuracy * 100.0);
+    f.write_all(s6.as_bytes()).unwrap();
+    let s7 = &format!("<br><br><b>Kappa</b><sup>2</sup> = {:.3}<br>", kappa);
+    f.write_all(s7.as_bytes()).unwrap();
     let s5 = &format!("{}{}", "<br>Notes:<br>1. User's accuracy refers to the proportion of points correctly assigned to a class (i.e. the number of points correctly classified for a category divided by the row total in the contingency table) and is a measure of the reliability. ",
             "Producer's accuracy is a measure of the proportion of the points in each category correctly classified (i.e. the number of points correctly classified for a category divided by the column total in the contingency table) and is a measure of the accuracy.<br>");
-    f.write(s5.as_bytes()).unwrap();
-    f.write("<br>2. Cohen's kappa coefficient is a statistic that measures inter-rater agreement for qualitative (categorical)
+    f.write_all(s5.as_bytes()).unwrap();
+    f.write_all("<br>2. Cohen's kappa coefficient is a statistic that measures inter-rater agreement for qualitative (categorical)
     items. It is generally thought to be a more robust measure than simple percent agreement calculation, since
     kappa takes into account the agreement occurring by chance. Kappa measures the percentage of data values in the
     main diagonal of the table and then adjusts these values for the amount of agreement that could be expected due
     to chance alone.".as_bytes()).unwrap();
     s = "</body>";
-    f.write(s.as_bytes()).unwrap();
+    f.write_all(s.as_bytes()).unwrap();
 
 
     //println!("Overall Accuracy: {}%", format!("{:.*}", 2, overall_accuracy * 100.0));
@@ -355,10 +312,8 @@ fn main() {
 
     println!("Complete, please see output file for results.");
 
-    let output = Command::new("open")
-                     .arg(output_file)
-                     .output()
-                     .expect("failed to execute process");
+    // open the report for viewing, where the system provides a viewer
+    let _ = Command::new("open").arg(output_file).output();
 
-    let _ = output.stdout;
+    Ok(())
 }
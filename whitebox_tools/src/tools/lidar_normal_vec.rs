@@ -0,0 +1,230 @@
+use std::io::{Error, ErrorKind};
+use crate::lidar::las;
+use crate::lidar::extra_bytes::{ ExtraBytesDataType, ExtraBytesDescriptor };
+use crate::lidar::point_data::*;
+use crate::na::Vector3;
+use kdtree::KdTree;
+use kdtree::distance::squared_euclidean;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+pub struct LidarNormalVec;
+
+impl WhiteboxTool for LidarNormalVec {
+    fn get_tool_name(&self) -> String {
+        "lidar_normal_vec".to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Calculates the normal vector of each point in a LAS file from a plane fitted to its nearest neighbours. The
+normals are stored as extra bytes attributes and rendered as the colour of the points.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Number of Points", &["--num_points"], ParameterType::Integer, Some("10"), "Number of nearest-neighbour points used for plane fitting.").with_min_value(3.0),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_normal_vec -v --wd=\"/path/to/data/\" --args=\"-i=input.las -o=output.las --num_points=15\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let min_points = args.get_integer("--num_points")? as usize;
+
+    lidar_normal_vec(input_file, output_file, min_points, verbose)
+}
+
+fn lidar_normal_vec(input_file: String, output_file: String, min_points: usize, verbose: bool) -> Result<(), Error> {
+    if verbose {
+        println!("*******************************");
+        println!("* Welcome to lidar_normal_vec *");
+        println!("*******************************");
+    }
+
+    if verbose { println!("Reading input LAS file..."); }
+    //let input = las::LasFile::new(&input_file, "r");
+    let input: las::LasFile = las::LasFile::new(&input_file, "r")?;
+
+    if verbose { println!("Performing analysis..."); }
+
+    let dimensions = 3;
+    let capacity_per_node = 128;
+    let mut kdtree = KdTree::new_with_capacity(dimensions, capacity_per_node);
+
+    let n_points = input.header.number_of_points as usize;
+    if input.header.number_of_points == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("The input file {} contains no points.", input_file)));
+    }
+    let num_points: f64 = (input.header.number_of_points as f64 - 1.0).max(1.0); // used for progress calculation only
+
+    let mut progress: i32;
+    let mut old_progress: i32 = -1;
+    for i in 0..n_points {
+        let p: PointData = input.get_point_info(i);
+        let coords: [f64; 3] = [ p.x, p.y, p.z ];
+        kdtree.add(coords, i).unwrap();
+        if progress_enabled(verbose) {
+            progress = (100.0_f64 * i as f64 / num_points) as i32;
+            if progress != old_progress {
+                update_progress("Creating tree", progress as usize)?;
+                //io::stdout().flush().ok().expect("Could not flush stdout");
+                //stdout().flush();
+                old_progress = progress;
+            }
+        }
+    }
+
+    if verbose { println!(); }
+
+    let mut index_n: usize;
+    let mut normal_values: Vec<Vector3<f64>> = vec![];
+    for i in 0..n_points {
+        let p: PointData = input.get_point_info(i);
+        let ret = kdtree.nearest(&[ p.x, p.y, p.z ], min_points, &squared_euclidean).unwrap();
+        let mut data: Vec<Vector3<f64>> = vec![];
+        for j in 0..ret.len() {
+            index_n = *ret[j].1;
+            let p2: PointData = input.get_point_info(index_n);
+            data.push(Vector3::new(p2.x, p2.y, p2.z));
+        }
+        normal_values.push(plane_from_points(&data));
+        if progress_enabled(verbose) {
+            progress = (100.0_f64 * i as f64 / num_points) as i32;
+            if progress != old_progress {
+                update_progress("Calculating point normals", progress as usize)?;
+                old_progress = progress;
+            }
+        }
+    }
+
+    if verbose { println!(); }
+
+    // now output the data
+    let mut output = las::LasFile::initialize_using_file(&output_file, &input);
+    output.header.point_format = 2;
+    // the normals are stored as extra bytes attributes, as well as being rendered as colour;
+    // those of an input that already has them are replaced
+    for &(name, description) in &[("NormalX", "x component of normal vector"),
+                                  ("NormalY", "y component of normal vector"),
+                                  ("NormalZ", "z component of normal vector")] {
+        if output.get_extra_bytes_descriptors().iter().all(|d| d.name != name) {
+            output.add_extra_bytes_descriptor(ExtraBytesDescriptor::new(name, ExtraBytesDataType::F32, description))?;
+        }
+    }
+
+    let (mut r, mut g, mut b): (u16, u16, u16);
+    for i in 0..input.header.number_of_points as usize {
+        let p: PointData = input.get_point_info(i);
+        r = ((1.0 + normal_values[i].x) / 2.0 * 255.0) as u16 * 256u16; //((1.0 + normal_values[i].x) / 2.0 * 65535.0) as u16;
+        g = ((1.0 + normal_values[i].y) / 2.0 * 255.0) as u16 * 256u16; //((1.0 + normal_values[i].y) / 2.0 * 65535.0) as u16;
+        b = ((1.0 + normal_values[i].z) / 2.0 * 255.0) as u16 * 256u16; //((1.0 + normal_values[i].z) / 2.0 * 65535.0) as u16;
+        let rgb: RgbData = RgbData{ red: r, green: g, blue: b };
+        let lpr: las::LidarPointRecord = las::LidarPointRecord::PointRecord2 { point_data: p, rgb_data: rgb };
+        output.add_point_record_with_extra_bytes(lpr, input.get_extra_bytes(i));
+        output.set_extra_f64(i, "NormalX", normal_values[i].x)?;
+        output.set_extra_f64(i, "NormalY", normal_values[i].y)?;
+        output.set_extra_f64(i, "NormalZ", normal_values[i].z)?;
+        if progress_enabled(verbose) {
+            progress = (100.0_f64 * i as f64 / num_points) as i32;
+            if progress != old_progress {
+                update_progress("\rSaving data", progress as usize)?;
+                old_progress = progress;
+            }
+        }
+    }
+
+    println!();
+    if verbose { println!("Writing output LAS file..."); }
+    output.write()?;
+    println!("Complete!");
+
+    Ok(())
+}
+
+// Constructs a plane from a collection of points
+// so that the summed squared distance to all points is minimzized
+#[inline]
+fn plane_from_points(points: &Vec<Vector3<f64>>) -> Vector3<f64> {
+    let n = points.len();
+    // assert!(n >= 3, "At least three points required");
+
+    let mut sum = Vector3::new(0.0, 0.0, 0.0);
+    for p in points {
+        sum += *p;
+    }
+    let centroid = sum * (1.0 / (n as f64));
+
+    // Calc full 3x3 covariance matrix, excluding symmetries:
+    let mut xx = 0.0; let mut xy = 0.0; let mut xz = 0.0;
+    let mut yy = 0.0; let mut yz = 0.0; let mut zz = 0.0;
+
+    for p in points {
+        let r = p - centroid;
+        xx += r.x * r.x;
+        xy += r.x * r.y;
+        xz += r.x * r.z;
+        yy += r.y * r.y;
+        yz += r.y * r.z;
+        zz += r.z * r.z;
+    }
+
+    let det_x = yy*zz - yz*yz;
+    let det_y = xx*zz - xz*xz;
+    let det_z = xx*yy - xy*xy;
+
+    let det_max = det_x.max(det_y).max(det_z); //max3(det_x, det_y, det_z);
+    // assert!(det_max > 0.0, "The points don't span a plane");
+
+    // Pick path with best conditioning:
+    let dir =
+        if det_max == det_x {
+            let a = (xz*yz - xy*zz) / det_x;
+            let b = (xy*yz - xz*yy) / det_x;
+            Vector3::new(1.0, a, b)
+        } else if det_max == det_y {
+            let a = (yz*xz - xy*zz) / det_y;
+            let b = (xy*xz - yz*xx) / det_y;
+            Vector3::new(a, 1.0, b)
+        } else {
+            let a = (yz*xy - xz*yy) / det_z;
+            let b = (xz*xy - yz*xx) / det_z;
+            Vector3::new(a, b, 1.0)
+        };
+
+    //plane_from_point_and_normal(centroid, normalize(dir))
+    normalize(dir)
+}
+
+#[inline]
+fn normalize(v: Vector3<f64>) -> Vector3<f64> {
+    let norm = (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
+    Vector3::new(v.x/norm, v.y/norm, v.z/norm)
+}
+
+// struct Plane {
+//     a: f64,
+//     b: f64,
+//     c: f64,
+//     d: f64,
+// }
+
+// fn plane_from_point_and_normal(p: Vector3<f64>, normal: Vector3<f64>) -> Plane {
+//     let d = normal.x * p.x + normal.y * p.y + normal.z * p.z;
+//     Plane { a: normal.x, b: normal.y, c: normal.z, d: d }
+// }
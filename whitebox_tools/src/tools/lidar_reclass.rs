@@ -0,0 +1,209 @@
+use std::io;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::prelude::*;
+use std::collections::HashMap;
+use crate::lidar::las;
+use crate::lidar::point_data::RgbData;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+//use libgeospatial::lidar::point_data::*;
+
+pub struct LidarReclass;
+
+impl WhiteboxTool for LidarReclass {
+    fn get_tool_name(&self) -> String {
+        "lidar_reclass".to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Reclassifies the points in a LAS file based on their colour, using a reclassification file that maps RGB
+values to class values.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Reclass File", &["--reclass_file"], ParameterType::ExistingFile, "Input reclassification file. This is a CSV file of the format 'red,green,blue,class'.").with_file_type(ParameterFileType::Csv),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Unclassed Value", &["--unclassed_value"], ParameterType::Integer, Some("1"), "Value assigned to points that are not specified in the reclassification file.").with_range(0.0, 255.0),
+            ToolParameter::optional("16-bit Mode", &["--16bitmode"], ParameterType::Boolean, None, "Use 16-bit RGB values in the reclassification file; 8-bit colour is assumed otherwise."),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_reclass -v --wd=\"/path/to/data/\" --args=\"-i=input.las --reclass_file=reclass.txt -o=output.las --unclassed_value=1\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let reclass_file = args.get_string("--reclass_file")?;
+    let output_file = args.get_string("--output")?;
+    let byte_bit_mode = !args.get_bool("--16bitmode");
+    let unclassed_value = args.get_integer("--unclassed_value")? as u8;
+
+    lidar_reclass(input_file, reclass_file, output_file, verbose, byte_bit_mode, unclassed_value)?;
+    println!("Complete!");
+
+    Ok(())
+}
+
+fn lidar_reclass(input_file: String, reclass_file: String, output_file: String, verbose: bool,
+    byte_bit_mode: bool, unclassed_value: u8) -> Result<(), io::Error> {
+    if verbose {
+        println!("****************************");
+        println!("* Welcome to lidar_reclass *");
+        println!("****************************");
+    }
+
+    if verbose { println!("Reading input LAS file..."); }
+    // read the input LAS file
+    let input: las::LasFile = las::LasFile::new(&input_file, "r")?;
+    let point_format = input.header.point_format;
+
+    // read the reclass file
+    let f = File::open(reclass_file)?;
+    let f = BufReader::new(f);
+
+    let mut reclass_data = HashMap::new();
+    if byte_bit_mode {
+        for line in f.lines() {
+            let line_unwrapped = line.unwrap();
+            if !line_unwrapped.contains("r") && !line_unwrapped.contains("g")
+               && !line_unwrapped.contains("b") && !line_unwrapped.contains("class") {
+                let line_split = line_unwrapped.split(",");
+                let vec = line_split.collect::<Vec<&str>>();
+                let r = vec[0].trim().to_string().parse::<u8>().unwrap() as u16 * 256u16;
+                let g = vec[1].trim().to_string().parse::<u8>().unwrap() as u16 * 256u16;
+                let b = vec[2].trim().to_string().parse::<u8>().unwrap() as u16 * 256u16;
+                let cls = vec[3].trim().to_string().parse::<u8>().unwrap();
+                reclass_data.insert(RgbData{ red: r, green: g, blue: b }, cls);
+            }
+        }
+    } else {
+        for line in f.lines() {
+            let line_unwrapped = line.unwrap();
+            if !line_unwrapped.contains("r") && !line_unwrapped.contains("g")
+               && !line_unwrapped.contains("b") && !line_unwrapped.contains("class") {
+                let line_split = line_unwrapped.split(",");
+                let vec = line_split.collect::<Vec<&str>>();
+                let r = vec[0].trim().to_string().parse::<u16>().unwrap();
+                let g = vec[1].trim().to_string().parse::<u16>().unwrap();
+                let b = vec[2].trim().to_string().parse::<u16>().unwrap();
+                let cls = vec[3].trim().to_string().parse::<u8>().unwrap();
+                reclass_data.insert(RgbData{ red: r, green: g, blue: b }, cls);
+            }
+        }
+    }
+
+    // create the output LAS file
+    let mut output = las::LasFile::initialize_using_file(&output_file, &input);
+
+    if point_format == 2 || point_format == 3 {
+        let n_points = input.header.number_of_points as usize;
+        let num_points = (n_points - 1) as f64;
+        let mut progress: i32;
+        let mut old_progress: i32 = -1;
+
+        //let mut p: PointData;
+        let mut pr: las::LidarPointRecord;
+        let mut pr2: las::LidarPointRecord;
+        let mut rgb: RgbData;
+        let mut class_val: u8;
+        for i in 0..n_points {
+            rgb = input.get_rgb(i).unwrap();
+
+            match reclass_data.get(&rgb) {
+                Some(&cls) => class_val = cls,
+                _ => class_val = unclassed_value,
+            }
+
+            pr = input.get_record(i);
+            match pr {
+                las::LidarPointRecord::PointRecord0 { mut point_data }  => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord0 { point_data };
+
+                },
+                las::LidarPointRecord::PointRecord1 { mut point_data, gps_data } => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord1 { point_data, gps_data };
+                },
+                las::LidarPointRecord::PointRecord2 { mut point_data, rgb_data } => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord2 { point_data, rgb_data };
+                },
+                las::LidarPointRecord::PointRecord3 { mut point_data, gps_data, rgb_data } => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord3 { point_data,
+                        gps_data, rgb_data};
+                },
+                las::LidarPointRecord::PointRecord4 { mut point_data, gps_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord4 { point_data,
+                        gps_data, wave_packet};
+                },
+                las::LidarPointRecord::PointRecord5 { mut point_data, gps_data, rgb_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord5 { point_data,
+                        gps_data, rgb_data, wave_packet};
+                },
+                las::LidarPointRecord::PointRecord6 { mut point_data, gps_data } => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord6 { point_data,
+                        gps_data};
+                },
+                las::LidarPointRecord::PointRecord7 { mut point_data, gps_data, rgb_data } => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord7 { point_data,
+                        gps_data, rgb_data};
+                },
+                las::LidarPointRecord::PointRecord8 { mut point_data, gps_data, rgb_data, nir_data } => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord8 { point_data,
+                        gps_data, rgb_data, nir_data};
+                },
+                las::LidarPointRecord::PointRecord9 { mut point_data, gps_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord9 { point_data,
+                        gps_data, wave_packet};
+                },
+                las::LidarPointRecord::PointRecord10 { mut point_data, gps_data, rgb_data, nir_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    pr2 = las::LidarPointRecord::PointRecord10 { point_data,
+                        gps_data, rgb_data,
+                        nir_data, wave_packet};
+                },
+            }
+
+            output.add_point_record_with_extra_bytes(pr2, input.get_extra_bytes(i));
+
+            if progress_enabled(verbose) {
+                progress = (100.0_f64 * i as f64 / num_points) as i32;
+                if progress != old_progress {
+                    update_progress("Progress", progress as usize)?;
+                    old_progress = progress;
+                }
+            }
+        }
+
+        if verbose { println!("Writing output LAS file..."); }
+        output.write()?;
+    } else {
+        return Err(Error::new(ErrorKind::InvalidInput, "The input LAS file does not contain RGB colour data."));
+    }
+    Ok(())
+}
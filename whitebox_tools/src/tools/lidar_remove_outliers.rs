@@ -0,0 +1,131 @@
+#![allow(dead_code, unused_assignments)]
+
+use std::f64;
+use std::io::{Error, ErrorKind};
+use crate::lidar::las;
+use crate::lidar::point_data::*;
+use kdtree::KdTree;
+use kdtree::distance::squared_euclidean;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+pub struct LidarRemoveOutliers;
+
+impl WhiteboxTool for LidarRemoveOutliers {
+    fn get_tool_name(&self) -> String {
+        "lidar_remove_outliers".to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Removes outlier points from a LAS file, i.e. points in regions of the cloud where the point density is below a
+threshold.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Threshold Density", &["--threshold_density"], ParameterType::Float, Some("1.0"), "Threshold in point density (pts / m^3) below which points are filtered from the cloud.").with_min_value(0.0),
+            ToolParameter::optional("Number of Neighbours", &["--num_neighbours"], ParameterType::Integer, Some("10"), "Number of neighbouring points used to determine point density in the region surrounding each point.").with_min_value(1.0),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_remove_outliers -v --wd=\"/path/to/data/\" --args=\"-i=input.las -o=output.las --threshold_density=0.5 --num_neighbours=15\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let threshold_density = args.get_float("--threshold_density")?;
+    let num_neighbours = args.get_integer("--num_neighbours")? as usize;
+
+    lidar_remove_outliers(input_file, output_file, threshold_density, num_neighbours, verbose)
+}
+
+fn lidar_remove_outliers(input_file: String, output_file: String, threshold_density: f64, num_neighbours: usize, verbose: bool) -> Result<(), Error> {
+    if verbose {
+        println!("************************************");
+        println!("* Welcome to lidar_remove_outliers *");
+        println!("************************************");
+    }
+
+    if verbose { println!("Reading input LAS file..."); }
+    //let input = las::LasFile::new(&input_file, "r");
+    let input: las::LasFile = las::LasFile::new(&input_file, "r")?;
+
+    if verbose { println!("Performing analysis..."); }
+
+    let dimensions = 3;
+    let capacity_per_node = 128;
+    let mut kdtree = KdTree::new_with_capacity(dimensions, capacity_per_node);
+
+    let n_points = input.header.number_of_points as usize;
+    if input.header.number_of_points == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("The input file {} contains no points.", input_file)));
+    }
+    let num_points: f64 = (input.header.number_of_points as f64 - 1.0).max(1.0); // used for progress calculation only
+
+    let mut progress: i32;
+    let mut old_progress: i32 = -1;
+    for i in 0..n_points {
+        let p: PointData = input.get_point_info(i);
+        let coords: [f64; 3] = [ p.x, p.y, p.z ];
+        kdtree.add(coords, i).unwrap();
+        if progress_enabled(verbose) {
+            progress = (100.0_f64 * i as f64 / num_points) as i32;
+            if progress != old_progress {
+                update_progress("Creating tree", progress as usize)?;
+                old_progress = progress;
+            }
+        }
+    }
+
+    let num_neighbours_less_one = num_neighbours - 1;
+    let mut output = las::LasFile::initialize_using_file(&output_file, &input);
+    let mut p: PointData;
+    let mut area: f64;
+    let mut r: f64;
+    let four_thirds_pi = 4.0 / 3.0 * f64::consts::PI;
+    let mut density: f64;
+    let mut num_points_in_filtered: i64 = 0;
+    for i in 0..n_points {
+        p = input[i];
+        let ret = kdtree.nearest(&[ p.x, p.y, p.z ], num_neighbours, &squared_euclidean).unwrap();
+        r = ret[num_neighbours_less_one].0.sqrt();
+        area = four_thirds_pi * r * r * r;
+        density = num_neighbours as f64 / area;
+        if density >= threshold_density {
+            // output the point
+            output.add_point_record_with_extra_bytes(input.get_record(i), input.get_extra_bytes(i));
+            num_points_in_filtered += 1;
+        }
+        if progress_enabled(verbose) {
+            progress = (100.0_f64 * i as f64 / num_points) as i32;
+            if progress != old_progress {
+                update_progress("Progress", progress as usize)?;
+                old_progress = progress;
+            }
+        }
+    }
+
+    if num_points_in_filtered > 0 {
+        if verbose { println!("Writing output LAS file..."); }
+        output.write()?;
+        println!("Complete!");
+    } else {
+        println!("No points were contained in the elevation slice.");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::io::{Error, ErrorKind};
+use crate::lidar::las_reader::LasReader;
+use crate::lidar::las_writer::LasWriter;
+use crate::spatial_ref_system::SpatialReference;
+use crate::spatial_ref_system::transform::CoordinateTransform;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+const TOOL_NAME: &str = "lidar_reproject";
+
+pub struct LidarReproject;
+
+impl WhiteboxTool for LidarReproject {
+    fn get_tool_name(&self) -> String {
+        TOOL_NAME.to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Transforms the coordinates of a LiDAR (LAS) file into another coordinate reference system.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS or LAZ file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file; a .laz extension produces a compressed (LAZ) file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("EPSG Code", &["--epsg"], ParameterType::Integer, "EPSG code of the output coordinate reference system.").with_range(1.0, 32767.0),
+            ToolParameter::optional("Source EPSG Code", &["--source_epsg"], ParameterType::Integer, None, "EPSG code of the input coordinate reference system, if the file does not specify it or specifies it incorrectly.").with_range(1.0, 32767.0),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_reproject -v --wd=\"/dir/to/data\" --args=\"-i=input.las -o=output.las --epsg=26917\"
+./whitebox-tools -r=lidar_reproject -v --wd=\"/dir/to/data\" --args=\"-i=input.las -o=output.las --epsg=4326 --source_epsg=2958\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let epsg_code = args.get_integer("--epsg")? as u16;
+    let source_epsg_code = args.get_integer("--source_epsg").unwrap_or(0) as u16;
+
+    if verbose {
+        println!("***************{}", "*".repeat(TOOL_NAME.len()));
+        println!("* Welcome to {} *", TOOL_NAME);
+        println!("***************{}", "*".repeat(TOOL_NAME.len()));
+    }
+
+    let mut input = LasReader::new(&input_file)?;
+    let source = if source_epsg_code != 0 {
+        SpatialReference::from_epsg(source_epsg_code)
+    } else {
+        input.spatial_reference.clone()
+    };
+    if source.epsg_code == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput,
+            "The coordinate reference system of the input file is unknown. Please specify its EPSG code (--source_epsg)."));
+    }
+    let target = SpatialReference::from_epsg(epsg_code);
+    let transform = CoordinateTransform::new(&source, &target)?;
+    if verbose { println!("Transforming from {} to {}", source, target); }
+
+    // The points are streamed and so the scale factors and offsets of the output must be
+    // chosen before they are transformed. The offsets are taken from the transformed
+    // extent of the input; the scale factors are retained unless the coordinates change
+    // between degrees and linear units.
+    let mut header = input.header.clone();
+    let (min_x, min_y, _, _) = transform.transform_extent(header.min_x, header.min_y, header.max_x, header.max_y);
+    if target.is_geographic() && !source.is_geographic() {
+        header.x_scale_factor = 0.000_000_1;
+        header.y_scale_factor = 0.000_000_1;
+    } else if source.is_geographic() && !target.is_geographic() {
+        header.x_scale_factor = 0.001;
+        header.y_scale_factor = 0.001;
+    }
+    if header.number_of_points > 0 {
+        header.x_offset = min_x.floor();
+        header.y_offset = min_y.floor();
+    }
+
+    let mut output = LasWriter::new(&output_file, &header, &input.vlr_data, &input.evlr_data)?;
+    output.set_spatial_reference(&target)?;
+
+    let mut progress: i32;
+    let mut old_progress: i32 = -1;
+    let num_points = input.header.number_of_points;
+    let mut i = 0u64;
+    loop {
+        let chunk = input.read_chunk()?;
+        if chunk.is_empty() { break; }
+        for (j, mut pr) in chunk.into_iter().enumerate() {
+            {
+                let p = pr.point_data_mut();
+                let (x, y) = transform.transform_xy(p.x, p.y);
+                p.x = x;
+                p.y = y;
+            }
+            output.add_point_record_with_extra_bytes(pr, input.get_extra_bytes(j))?;
+            i += 1;
+            if progress_enabled(verbose) {
+                progress = (100.0_f64 * i as f64 / num_points as f64) as i32;
+                if progress != old_progress {
+                    update_progress("Progress", progress as usize)?;
+                    old_progress = progress;
+                }
+            }
+        }
+    }
+
+    if verbose { println!("Writing output LAS file..."); }
+    output.close()?;
+
+    Ok(())
+}
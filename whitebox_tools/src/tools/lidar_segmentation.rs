@@ -1,142 +1,74 @@
-extern crate whitebox_tools;
-extern crate nalgebra as na;
-extern crate kdtree;
-extern crate rand;
-
-use std::env;
 use std::f64;
 use std::cmp;
-use std::path;
-use std::default::Default;
-use whitebox_tools::lidar::las;
-use whitebox_tools::lidar::point_data::*;
-use whitebox_tools::structures::fixed_radius_search::FixedRadiusSearch;
-use na::{ Dot, Vector3 };
+use std::io::{Error, ErrorKind};
+use crate::lidar::las;
+use crate::lidar::extra_bytes::{ ExtraBytesDataType, ExtraBytesDescriptor };
+use crate::lidar::point_data::*;
+use crate::structures::fixed_radius_search::FixedRadiusSearch;
+use crate::na::Vector3;
 use kdtree::KdTree;
 use kdtree::distance::squared_euclidean;
 use rand::Rng;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
 
-fn main() {
-    let sep: String = path::MAIN_SEPARATOR.to_string();
-    let mut input_file: String = "".to_string();
-    let mut output_file: String = "".to_string();
-    let mut working_directory: String = "".to_string();
-    let mut search_dist: f64 = 5.0;
-    let mut num_neighbouring_points: usize = 10;
-    let mut max_normal_angle = 2.0f64;
-    let mut max_z_diff = 1.0;
-    let mut verbose: bool = false;
-    let mut variable_dist = true;
-    let mut detrend_surface = 0.0f64;
-    let mut classify_ground = false;
-
-    // read the arguments
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 { panic!("Tool run with no paramters. Please see help (-h) for parameter descriptions."); }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        let mut keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--i" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--o" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-wd" || vec[0].to_lowercase() == "--wd" {
-            if keyval {
-                working_directory = vec[1].to_string();
-            } else {
-                working_directory = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-dist" || vec[0].to_lowercase() == "--dist" {
-            variable_dist = false;
-            if keyval {
-                search_dist = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                search_dist = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-num_points" || vec[0].to_lowercase() == "--num_points" {
-            if keyval {
-                num_neighbouring_points = vec[1].to_string().parse::<usize>().unwrap();
-            } else {
-                num_neighbouring_points = args[i+1].to_string().parse::<usize>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-max_norm_angle" || vec[0].to_lowercase() == "--max_norm_angle" {
-            if keyval {
-                max_normal_angle = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                max_normal_angle = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-maxzdiff" || vec[0].to_lowercase() == "--maxzdiff" {
-            if keyval {
-                max_z_diff = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                max_z_diff = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-detrend" || vec[0].to_lowercase() == "--detrend" {
-            if keyval {
-                detrend_surface = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                detrend_surface = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-classify_ground" || vec[0].to_lowercase() == "--classify_ground" {
-            classify_ground = true;
-        } else if vec[0].to_lowercase() == "-v" || vec[0].to_lowercase() == "--verbose" {
-            verbose = true;
-        } else if vec[0].to_lowercase() == "-h" || vec[0].to_lowercase() == "--help" ||
-          vec[0].to_lowercase() == "--h" {
-            let mut s: String = "Help:\n".to_owned();
-            s.push_str("-i               Input LAS file.\n");
-            s.push_str("-o               Output LAS file.\n");
-            s.push_str("-wd              Optional working directory. If specified, input and output filenames need not include a full path.\n");
-            s.push_str("-dist            Optional search distance in xy units; default is variable, determined by num_points.\n");
-            s.push_str("-num_points      Number (integer) of nearest-neighbour points used for plane fitting; default is 10.\n");
-            s.push_str("-max_norm_angle  Maximum deviation (degrees) in normal vectors between neighbouring points of the same segment; default is 2.0.\n");
-            s.push_str("-maxzdiff        Maximum difference in elevation (z units) between neighbouring points of the same segment; defuault is 1.0.\n");
-            s.push_str("-classify_ground Optional mode. Surface in contact with the opening surface will be classified as ground points.");
-            s.push_str("-v               Optional verbose mode. Tool will report progress if this flag is provided.\n");
-            s.push_str("-version         Prints the tool version number.\n");
-            s.push_str("-h               Prints help information.\n\n");
-            s.push_str("Example usage:\n\n");
-            s.push_str(&">> .*lidar_segmentation -wd *path*to*data* -i input.las -o output.las -num_points 15 -max_norm_angle 3.5 -max_z_diff 0.5 -v\n".replace("*", &sep));
-            println!("{}", s);
-            return;
-        } else if vec[0].to_lowercase() == "-version" || vec[0].to_lowercase() == "--version" {
-            const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
-            println!("lidar_segmentation v{}", VERSION.unwrap_or("unknown"));
-            return;
-        }
+pub struct LidarSegmentation;
+
+impl WhiteboxTool for LidarSegmentation {
+    fn get_tool_name(&self) -> String {
+        "lidar_segmentation".to_string()
     }
 
-    let sep = std::path::MAIN_SEPARATOR;
-    if !working_directory.ends_with(sep) {
-        working_directory.push_str(&(sep.to_string()));
+    fn get_tool_description(&self) -> String {
+        let s = "Segments a LAS file into groups of neighbouring points with similar normal vectors and elevations, i.e. into
+planar surfaces. The segment identifier of each point is stored as an extra bytes attribute.";
+
+        s.to_string()
     }
 
-    if !input_file.contains(sep) {
-        input_file = format!("{}{}", working_directory, input_file);
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Search Distance", &["--dist"], ParameterType::Float, None, "Search distance in xy units; if unspecified, the search distance is variable and determined by --num_points.").with_min_value(0.0),
+            ToolParameter::optional("Number of Points", &["--num_points"], ParameterType::Integer, Some("10"), "Number of nearest-neighbour points used for plane fitting.").with_min_value(3.0),
+            ToolParameter::optional("Maximum Normal Angle", &["--max_norm_angle"], ParameterType::Float, Some("2.0"), "Maximum deviation (degrees) in normal vectors between neighbouring points of the same segment.").with_range(0.0, 90.0),
+            ToolParameter::optional("Maximum Elevation Difference", &["--maxzdiff"], ParameterType::Float, Some("1.0"), "Maximum difference in elevation (z units) between neighbouring points of the same segment.").with_min_value(0.0),
+            ToolParameter::optional("Detrending Distance", &["--detrend"], ParameterType::Float, None, "Search distance of the opening surface used to detrend the elevations before segmenting; no detrending is performed if unspecified.").with_min_value(0.0),
+            ToolParameter::optional("Classify Ground", &["--classify_ground"], ParameterType::Boolean, None, "Classify the surface in contact with the opening surface as ground points."),
+        ]
     }
-    if !output_file.contains(sep) {
-        output_file = format!("{}{}", working_directory, output_file);
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_segmentation -v --wd=\"/path/to/data/\" --args=\"-i=input.las -o=output.las --num_points=15 --max_norm_angle=3.5 --maxzdiff=0.5\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
     }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let variable_dist = !args.is_given("--dist");
+    let search_dist = args.get_float("--dist").unwrap_or(5.0);
+    let num_neighbouring_points = args.get_integer("--num_points")? as usize;
+    let max_normal_angle = args.get_float("--max_norm_angle")?;
+    let max_z_diff = args.get_float("--maxzdiff")?;
+    let detrend_surface = args.get_float("--detrend").unwrap_or(0.0);
+    let classify_ground = args.get_bool("--classify_ground");
 
     lidar_segmentation(input_file, output_file, search_dist, num_neighbouring_points,
-        max_normal_angle, max_z_diff, verbose, variable_dist, detrend_surface, classify_ground);
+        max_normal_angle, max_z_diff, verbose, variable_dist, detrend_surface, classify_ground)
 }
 
 fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64, num_neighbouring_points: usize,
                       mut max_angle: f64, mut max_z_diff: f64, verbose: bool, variable_dist: bool,
-                      detrend_surface: f64, classify_ground: bool) {
+                      detrend_surface: f64, classify_ground: bool) -> Result<(), Error> {
     if verbose {
         println!("*********************************");
         println!("* Welcome to lidar_segmentation *");
@@ -150,7 +82,7 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
     //let input = las::LasFile::new(&input_file, "r");
     let input = match las::LasFile::new(&input_file, "r") {
         Ok(lf) => lf,
-        Err(err) => panic!("Error reading file {}: {}", input_file, err),
+        Err(err) => return Err(Error::new(err.kind(), format!("Error reading file {}: {}", input_file, err))),
     };
 
     if verbose { println!("Performing analysis..."); }
@@ -160,7 +92,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
     let mut kdtree = KdTree::new_with_capacity(dimensions, capacity_per_node);
 
     let n_points = input.header.number_of_points as usize;
-    let num_points: f64 = (input.header.number_of_points - 1) as f64; // used for progress calculation only
+    if input.header.number_of_points == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("The input file {} contains no points.", input_file)));
+    }
+    let num_points: f64 = (input.header.number_of_points as f64 - 1.0).max(1.0); // used for progress calculation only
 
     let mut progress: i32;
     let mut old_progress: i32 = -1;
@@ -177,10 +112,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
             for i in 0..n_points {
                 let p: PointData = input.get_point_info(i);
                 frs.insert(p.x, p.y, i);
-                if verbose {
+                if progress_enabled(verbose) {
                     progress = (100.0_f64 * i as f64 / num_points) as i32;
                     if progress != old_progress {
-                        println!("Binning points: {}%", progress);
+                        update_progress("Binning points", progress as usize)?;
                         old_progress = progress;
                     }
                 }
@@ -196,10 +131,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                         neighbourhood_min[i] = zn;
                     }
                 }
-                if verbose {
+                if progress_enabled(verbose) {
                     progress = (100.0_f64 * i as f64 / num_points) as i32;
                     if progress != old_progress {
-                        println!("Performing erosion: {}%", progress);
+                        update_progress("Performing erosion", progress as usize)?;
                         old_progress = progress;
                     }
                 }
@@ -215,17 +150,15 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                     }
                 }
                 residuals[i] = p.z - neighbourhood_max_min[i];
-                if verbose {
+                if progress_enabled(verbose) {
                     progress = (100.0_f64 * i as f64 / num_points) as i32;
                     if progress != old_progress {
-                        println!("Performing dilation: {}%", progress);
+                        update_progress("Performing dilation", progress as usize)?;
                         old_progress = progress;
                     }
                 }
             }
-        } else {
-
-        }
+        } 
     } else {
         for i in 0..n_points {
             let p: PointData = input[i];
@@ -240,10 +173,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
         for i in 0..n_points {
             let p: PointData = input.get_point_info(i);
             frs.insert(p.x, p.y, i);
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Binning points: {}%", progress);
+                    update_progress("Binning points", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -252,11 +185,11 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
         for i in 0..n_points {
             let p: PointData = input.get_point_info(i);
             let coords: [f64; 3] = [ p.x, p.y, p.z ];
-            kdtree.add(coords.clone(), i).unwrap();
-            if verbose {
+            kdtree.add(coords, i).unwrap();
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Creating tree: {}%", progress);
+                    update_progress("Creating tree", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -272,17 +205,17 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
         for i in 0..n_points {
             let p: PointData = input.get_point_info(i);
             let ret = frs.search(p.x, p.y);
-            let mut data: Vec<Vector3<f64>> = vec![];;
+            let mut data: Vec<Vector3<f64>> = vec![];
             for j in 0..ret.len() {
                 index_n = ret[j].0;
                 let p2: PointData = input.get_point_info(index_n);
-                data.push(Vector3 { x: p2.x, y: p2.y, z: residuals[index_n]}); //p2.z });
+                data.push(Vector3::new(p2.x, p2.y, residuals[index_n])); //p2.z });
             }
             normal_vectors.push(plane_from_points(&data));
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Calculating point normals: {}%", progress);
+                    update_progress("Calculating point normals", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -291,17 +224,17 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
         for i in 0..n_points {
             let p: PointData = input.get_point_info(i);
             let ret = kdtree.nearest(&[ p.x, p.y, p.z ], num_neighbouring_points, &squared_euclidean).unwrap();
-            let mut data: Vec<Vector3<f64>> = vec![];;
+            let mut data: Vec<Vector3<f64>> = vec![];
             for j in 0..ret.len() {
                 index_n = *ret[j].1;
                 let p2: PointData = input.get_point_info(index_n);
-                data.push(Vector3 { x: p2.x, y: p2.y, z: residuals[index_n]}); //p2.z });
+                data.push(Vector3::new(p2.x, p2.y, residuals[index_n])); //p2.z });
             }
             normal_vectors.push(plane_from_points(&data));
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Calculating point normals: {}%", progress);
+                    update_progress("Calculating point normals", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -352,8 +285,8 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
             num_solved_points += 1.0;
 
             if !variable_dist {
-                while !stack.is_empty() {
-                    let i = stack.pop().unwrap();
+                while let Some(i) = stack.pop() {
+                    
                     let p = input[i];
                     let ret = frs.search(p.x, p.y);
                     let z = residuals[i]; //p.z;
@@ -371,17 +304,17 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                             }
                         }
                     }
-                    if verbose {
+                    if progress_enabled(verbose) {
                         progress = (100.0_f64 * num_solved_points / num_points) as i32;
                         if progress != old_progress {
-                            println!("Segmenting point cloud: {}%", progress);
+                            update_progress("Segmenting point cloud", progress as usize)?;
                             old_progress = progress;
                         }
                     }
                 }
             } else {
-                while !stack.is_empty() {
-                    let i = stack.pop().unwrap();
+                while let Some(i) = stack.pop() {
+                    
                     let p = input[i]; //.get_point_info(i);
                     let ret = kdtree.nearest(&[ p.x, p.y, p.z ], num_neighbouring_points, &squared_euclidean).unwrap();
                     let z = residuals[i]; //p.z;
@@ -399,10 +332,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                             }
                         }
                     }
-                    if verbose {
+                    if progress_enabled(verbose) {
                         progress = (100.0_f64 * num_solved_points / num_points) as i32;
                         if progress != old_progress {
-                            println!("Segmenting point cloud: {}%", progress);
+                            update_progress("Segmenting point cloud", progress as usize)?;
                             old_progress = progress;
                         }
                     }
@@ -426,13 +359,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
     let mut cur_seed_loc: usize = 0;
     while num_solved_points < num_points {
         let mut seed_found = false;
-        for i in cur_seed_loc..n_points {
-            if !is_assigned[i] {
-                seed_id = i;
-                cur_seed_loc = i;
-                seed_found = true;
-                break;
-            }
+        if let Some(i) = (cur_seed_loc..n_points).find(|&i| !is_assigned[i]) {
+            seed_id = i;
+            cur_seed_loc = i;
+            seed_found = true;
         }
 
         if seed_found {
@@ -447,8 +377,8 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
             num_solved_points += 1.0;
 
             if !variable_dist {
-                while !stack.is_empty() {
-                    let i = stack.pop().unwrap();
+                while let Some(i) = stack.pop() {
+                    
                     let p = input.get_point_info(i);
                     let ret = frs.search(p.x, p.y);
                     for j in 0..ret.len() {
@@ -461,17 +391,17 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                             segment_histo[current_seg_id] += 1;
                         }
                     }
-                    if verbose {
+                    if progress_enabled(verbose) {
                         progress = (100.0_f64 * num_solved_points / num_points) as i32;
                         if progress != old_progress {
-                            println!("Segmenting point cloud: {}%", progress);
+                            update_progress("Segmenting point cloud", progress as usize)?;
                             old_progress = progress;
                         }
                     }
                 }
             } else {
-                while !stack.is_empty() {
-                    let i = stack.pop().unwrap();
+                while let Some(i) = stack.pop() {
+                    
                     let p = input.get_point_info(i);
                     let ret = kdtree.nearest(&[ p.x, p.y, p.z ], num_neighbouring_points, &squared_euclidean).unwrap();
                     for j in 0..ret.len() {
@@ -484,10 +414,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                             segment_histo[current_seg_id] += 1;
                         }
                     }
-                    if verbose {
+                    if progress_enabled(verbose) {
                         progress = (100.0_f64 * num_solved_points / num_points) as i32;
                         if progress != old_progress {
-                            println!("Segmenting point cloud: {}%", progress);
+                            update_progress("Segmenting point cloud", progress as usize)?;
                             old_progress = progress;
                         }
                     }
@@ -511,12 +441,16 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
     // now output the data
     let mut output = las::LasFile::initialize_using_file(&output_file, &input);
     output.header.point_format = 2;
+    // the segment of each point is stored as an extra bytes attribute, as well as being rendered as colour
+    if output.get_extra_bytes_descriptors().iter().all(|d| d.name != "SegmentID") {
+        output.add_extra_bytes_descriptor(ExtraBytesDescriptor::new("SegmentID", ExtraBytesDataType::U32, "segment identifier"))?;
+    }
 
     let mut clrs: Vec<(u16, u16, u16)> = Vec::new();
     let mut rng = rand::thread_rng();
     let (mut r, mut g, mut b): (u16, u16, u16) = (0u16, 0u16, 0u16);
     current_seg_id = segment_histo.len();
-    for _ in 0..current_seg_id+1 as usize {
+    for _ in 0..current_seg_id+1_usize {
         let mut flag = false;
         while !flag {
             r = rng.gen::<u8>() as u16 * 256u16;
@@ -524,7 +458,7 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
             b = rng.gen::<u8>() as u16 * 256u16;
             let max_val = cmp::max(cmp::max(r, g), b);
             //let min_val = cmp::min(cmp::min(r, g), b);
-            if max_val >= u16::max_value() / 2 { // && min_val >= u16::max_value() / 4 {
+            if max_val >= u16::MAX / 2 { // && min_val >= u16::MAX / 4 {
                 flag = true;
             }
         }
@@ -534,8 +468,8 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
         //     b = rng.gen::<u16>();
         // } else {
         //     r = 0;
-        //     g = u16::max_value();
-        //     b = u16::max_value() / 4;
+        //     g = u16::MAX;
+        //     b = u16::MAX / 4;
         // }
         clrs.push((r, g, b));
     }
@@ -562,11 +496,12 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
             let seg_val = segment_id[i];
             let rgb: RgbData = RgbData{ red: clrs[seg_val].0, green: clrs[seg_val].1, blue: clrs[seg_val].2 };
             let lpr: las::LidarPointRecord = las::LidarPointRecord::PointRecord2 { point_data: p, rgb_data: rgb };
-            output.add_point_record(lpr);
-            if verbose {
+            output.add_point_record_with_extra_bytes(lpr, input.get_extra_bytes(i));
+            output.set_extra_f64(i, "SegmentID", seg_val as f64)?;
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Saving data: {}%", progress);
+                    update_progress("Saving data", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -577,11 +512,12 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
             let seg_val = segment_id[i];
             let rgb: RgbData = RgbData{ red: clrs[seg_val].0, green: clrs[seg_val].1, blue: clrs[seg_val].2 };
             let lpr: las::LidarPointRecord = las::LidarPointRecord::PointRecord2 { point_data: p, rgb_data: rgb };
-            output.add_point_record(lpr);
-            if verbose {
+            output.add_point_record_with_extra_bytes(lpr, input.get_extra_bytes(i));
+            output.set_extra_f64(i, "SegmentID", seg_val as f64)?;
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Saving data: {}%", progress);
+                    update_progress("Saving data", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -589,10 +525,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
     }
 
     if verbose { println!("Writing output LAS file..."); }
-    let _ = match output.write() {
-        Ok(_) => println!("Complete!"),
-        Err(e) => println!("error while writing: {:?}", e),
-    };
+    output.write()?;
+    println!("Complete!");
+
+    Ok(())
 }
 
 #[inline]
@@ -612,9 +548,9 @@ fn plane_from_points(points: &Vec<Vector3<f64>>) -> Vector3<f64> {
     let n = points.len();
     // assert!(n >= 3, "At least three points required");
 
-    let mut sum = Vector3{ x: 0.0, y: 0.0, z: 0.0 };
+    let mut sum = Vector3::new(0.0, 0.0, 0.0);
     for p in points {
-        sum = sum + *p;
+        sum += *p;
     }
     let centroid = sum * (1.0 / (n as f64));
 
@@ -623,7 +559,7 @@ fn plane_from_points(points: &Vec<Vector3<f64>>) -> Vector3<f64> {
     let mut yy = 0.0; let mut yz = 0.0; let mut zz = 0.0;
 
     for p in points {
-        let r = p - &centroid;
+        let r = p - centroid;
         xx += r.x * r.x;
         xy += r.x * r.y;
         xz += r.x * r.z;
@@ -643,15 +579,15 @@ fn plane_from_points(points: &Vec<Vector3<f64>>) -> Vector3<f64> {
         if det_max == det_x {
             let a = (xz*yz - xy*zz) / det_x;
             let b = (xy*yz - xz*yy) / det_x;
-            Vector3{ x: 1.0, y: a, z: b }
+            Vector3::new(1.0, a, b)
         } else if det_max == det_y {
             let a = (yz*xz - xy*zz) / det_y;
             let b = (xy*xz - yz*xx) / det_y;
-            Vector3{ x: a, y: 1.0, z: b }
+            Vector3::new(a, 1.0, b)
         } else {
             let a = (yz*xy - xz*yy) / det_z;
             let b = (xz*xy - yz*xx) / det_z;
-            Vector3{ x: a, y: b, z: 1.0 }
+            Vector3::new(a, b, 1.0)
         };
 
     //plane_from_point_and_normal(centroid, normalize(dir))
@@ -661,18 +597,5 @@ fn plane_from_points(points: &Vec<Vector3<f64>>) -> Vector3<f64> {
 #[inline]
 fn normalize(v: Vector3<f64>) -> Vector3<f64> {
     let norm = (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
-    Vector3 { x: v.x/norm, y: v.y/norm, z: v.z/norm }
-}
-
-#[derive(Debug, Clone, Copy)]
-pub struct ClusterData {
-    mean: Vector3<f64>,
-    variance: Vector3<f64>,
-    n: f64,
-}
-
-impl Default for ClusterData {
-    fn default() -> ClusterData {
-        ClusterData { mean: Vector3{ x: 0.0, y: 0.0, z: 0.0 }, variance: Vector3{ x: 0.0, y: 0.0, z: 0.0 }, n: 0.0 }
-    }
+    Vector3::new(v.x/norm, v.y/norm, v.z/norm)
 }
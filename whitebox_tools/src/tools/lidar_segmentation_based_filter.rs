@@ -1,172 +1,79 @@
-extern crate whitebox_tools;
-extern crate nalgebra as na;
-extern crate kdtree;
-extern crate rand;
-
-use std::env;
 use std::f64;
-use std::path;
+use std::io::{Error, ErrorKind};
 use std::default::Default;
-use whitebox_tools::lidar::las;
-use whitebox_tools::lidar::point_data::*;
-use na::{ Dot, Vector3 };
+use crate::lidar::las;
+use crate::lidar::point_data::*;
+use crate::na::Vector3;
 use kdtree::KdTree;
 use kdtree::distance::squared_euclidean;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
 
-fn main() {
-    let sep: String = path::MAIN_SEPARATOR.to_string();
-    let mut input_file: String = "".to_string();
-    let mut output_file: String = "".to_string();
-    let mut working_directory: String = "".to_string();
-    let mut search_dist: f64 = 5.0;
-    let mut num_neighbouring_points: usize = 10;
-    let mut max_normal_angle = 2.0f64;
-    let mut maxzdiff = 1.0;
-    let mut minz = f64::NEG_INFINITY;
-    let mut filter = true;
-    let mut ground_class_value = 2u8;
-    let mut oto_class_value = 1u8;
-    let mut last_only = false;
-    let mut verbose: bool = false;
-    let mut variable_dist = true;
-
-    // read the arguments
-    let args: Vec<String> = env::args().collect();
-    if args.len() <= 1 { panic!("Tool run with no paramters. Please see help (-h) for parameter descriptions."); }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        let mut keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--i" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--o" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-wd" || vec[0].to_lowercase() == "--wd" {
-            if keyval {
-                working_directory = vec[1].to_string();
-            } else {
-                working_directory = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-dist" || vec[0].to_lowercase() == "--dist" {
-            variable_dist = false;
-            if keyval {
-                search_dist = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                search_dist = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-num_points" || vec[0].to_lowercase() == "--num_points" {
-            if keyval {
-                num_neighbouring_points = vec[1].to_string().parse::<usize>().unwrap();
-            } else {
-                num_neighbouring_points = args[i+1].to_string().parse::<usize>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-max_norm_angle" || vec[0].to_lowercase() == "--max_norm_angle" {
-            if keyval {
-                max_normal_angle = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                max_normal_angle = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-max_z_diff" || vec[0].to_lowercase() == "--max_z_diff" {
-            if keyval {
-                maxzdiff = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                maxzdiff = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-minz" || vec[0].to_lowercase() == "--minz" {
-            if keyval {
-                minz = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                minz = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-filter" || vec[0].to_lowercase() == "--filter" {
-            filter = true;
-        } else if vec[0].to_lowercase() == "-class" || vec[0].to_lowercase() == "--class" {
-            filter = false;
-        } else if vec[0].to_lowercase() == "-groundclass" || vec[0].to_lowercase() == "--groundclass" {
-            filter = false;
-            if keyval {
-                ground_class_value = vec[1].to_string().parse::<u8>().unwrap();
-            } else {
-                ground_class_value = args[i+1].to_string().parse::<u8>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-otoclass" || vec[0].to_lowercase() == "--otoclass" {
-            filter = false;
-            if keyval {
-                oto_class_value = vec[1].to_string().parse::<u8>().unwrap();
-            } else {
-                oto_class_value = args[i+1].to_string().parse::<u8>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-last_only" || vec[0].to_lowercase() == "--last_only" {
-            last_only = true;
-        } else if vec[0].to_lowercase() == "-v" || vec[0].to_lowercase() == "--verbose" {
-            verbose = true;
-        } else if vec[0].to_lowercase() == "-h" || vec[0].to_lowercase() == "--help" ||
-          vec[0].to_lowercase() == "--h" {
-            let mut s: String = "Help:\n".to_owned();
-            s.push_str("This tool can be used to filter a LiDAR point cloud for ground points. This filtering
-is based on a segmentation procedure and entire segments are classed as either
-'ground' or 'off-terrain' segments. Off-terrain segments are considered to be those
-with mean normal vectors that are not upwards-facing and that are elevated above
-neighbouring segments. The tool can either remove off-terrain points within the
-output file, or if the optional -class flag is provided, it can simply classify
-points in the output file as 'ground' and 'unclassified'.\n");
-            s.push_str("\nTool flags:\n");
-            s.push_str("-i               Input LAS file.\n");
-            s.push_str("-o               Output LAS file.\n");
-            s.push_str("-wd              Optional working directory. If specified, input and output filenames need not include a full path.\n");
-            s.push_str("-dist            Optional search distance in xy units; default is variable, determined by num_points.\n");
-            s.push_str("-num_points      Number (integer) of nearest-neighbour points used for plane fitting; default is 10.\n");
-            s.push_str("-max_norm_angle  Maximum deviation (degrees) in normal vectors between neighbouring points of the same segment; default is 2.0.\n");
-            s.push_str("-max_z_diff      Maximum difference in elevation (z units) between neighbouring points of the same segment; defuault is 1.0.\n");
-            s.push_str("-last_only       Optional boolean indicating whether only last-return points should be considered.\n");
-            s.push_str("-class           If this flag is used, the output LAS file will contain all the points of the input, but classified to indicate whether a point belongs to the slice.\n");
-            s.push_str("-groundclass     Class value (integer between 0-31) to be assigned to ground points; default is 2.\n");
-            s.push_str("-otoclass        Class value (integer between 0-31) to be assigned to off-terrain objects (OTOs); default is 1.\n");
-            s.push_str("-v               Optional verbose mode. Tool will report progress if this flag is provided.\n");
-            s.push_str("-version         Prints the tool version number.\n");
-            s.push_str("-h               Prints help information.\n\n");
-            s.push_str("Example usage:\n\n");
-            s.push_str(&">> .*lidar_segmentation_based_filter -wd *path*to*data* -i input.las -o output.las -num_points 50 -max_norm_angle 3.5 -max_z_diff 0.5 -v\n".replace("*", &sep));
-            println!("{}", s);
-            return;
-        } else if vec[0].to_lowercase() == "-version" || vec[0].to_lowercase() == "--version" {
-            const VERSION: Option<&'static str> = option_env!("CARGO_PKG_VERSION");
-            println!("lidar_segmentation v{}", VERSION.unwrap_or("unknown"));
-            return;
-        }
+pub struct LidarSegmentationBasedFilter;
+
+impl WhiteboxTool for LidarSegmentationBasedFilter {
+    fn get_tool_name(&self) -> String {
+        "lidar_segmentation_based_filter".to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Filters a LAS file for ground points using a segmentation procedure. Entire segments are classed as either
+ground or off-terrain segments, the latter being those with mean normal vectors that are not upwards-facing
+and that are elevated above neighbouring segments.";
+
+        s.to_string()
     }
 
-    let sep = std::path::MAIN_SEPARATOR;
-    if !working_directory.ends_with(sep) {
-        working_directory.push_str(&(sep.to_string()));
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Search Distance", &["--dist"], ParameterType::Float, None, "Search distance in xy units; if unspecified, the search distance is variable and determined by --num_points.").with_min_value(0.0),
+            ToolParameter::optional("Number of Points", &["--num_points"], ParameterType::Integer, Some("10"), "Number of nearest-neighbour points used for plane fitting.").with_min_value(3.0),
+            ToolParameter::optional("Maximum Normal Angle", &["--max_norm_angle"], ParameterType::Float, Some("2.0"), "Maximum deviation (degrees) in normal vectors between neighbouring points of the same segment.").with_range(0.0, 90.0),
+            ToolParameter::optional("Maximum Elevation Difference", &["--max_z_diff"], ParameterType::Float, Some("1.0"), "Maximum difference in elevation (z units) between neighbouring points of the same segment.").with_min_value(0.0),
+            ToolParameter::optional("Minimum Elevation", &["--minz"], ParameterType::Float, None, "Minimum elevation used in the analysis."),
+            ToolParameter::optional("Last Returns Only", &["--last_only"], ParameterType::Boolean, None, "Only consider last-return points."),
+            ToolParameter::optional("Classify Points", &["--class"], ParameterType::Boolean, None, "Output all of the points of the input, classified as ground or OTO points, rather than only the ground points."),
+            ToolParameter::optional("Ground Class", &["--groundclass"], ParameterType::Integer, Some("2"), "Class value (integer between 0-31) to be assigned to ground points; implies --class.").with_range(0.0, 31.0),
+            ToolParameter::optional("OTO Class", &["--otoclass"], ParameterType::Integer, Some("1"), "Class value (integer between 0-31) to be assigned to off-terrain objects (OTOs); implies --class.").with_range(0.0, 31.0),
+        ]
     }
 
-    if !input_file.contains(sep) {
-        input_file = format!("{}{}", working_directory, input_file);
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_segmentation_based_filter -v --wd=\"/path/to/data/\" --args=\"-i=input.las -o=output.las --num_points=50 --max_norm_angle=3.5 --max_z_diff=0.5\"";
+        Some(s.to_string())
     }
-    if !output_file.contains(sep) {
-        output_file = format!("{}{}", working_directory, output_file);
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
     }
+}
 
-    lidar_segmentation(input_file, output_file, search_dist, num_neighbouring_points,
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let variable_dist = !args.is_given("--dist");
+    let search_dist = args.get_float("--dist").unwrap_or(5.0);
+    let num_neighbouring_points = args.get_integer("--num_points")? as usize;
+    let max_normal_angle = args.get_float("--max_norm_angle")?;
+    let maxzdiff = args.get_float("--max_z_diff")?;
+    let minz = args.get_float("--minz").unwrap_or(f64::NEG_INFINITY);
+    let filter = !(args.get_bool("--class") || args.is_given("--groundclass") || args.is_given("--otoclass"));
+    let ground_class_value = args.get_integer("--groundclass")? as u8;
+    let oto_class_value = args.get_integer("--otoclass")? as u8;
+    let last_only = args.get_bool("--last_only");
+
+    lidar_segmentation_based_filter(input_file, output_file, search_dist, num_neighbouring_points,
         max_normal_angle, maxzdiff, verbose, variable_dist, minz, filter, ground_class_value,
-        oto_class_value, last_only);
+        oto_class_value, last_only)
 }
 
-fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64, num_neighbouring_points: usize,
-                      mut max_angle: f64, mut max_z_diff: f64, verbose: bool, variable_dist: bool, minz: f64,
-                      filter: bool, ground_class_value: u8, oto_class_value: u8, last_only: bool) {
+fn lidar_segmentation_based_filter(input_file: String, output_file: String, search_dist: f64, num_neighbouring_points: usize,
+                                      mut max_angle: f64, mut max_z_diff: f64, verbose: bool, variable_dist: bool, minz: f64,
+                                      filter: bool, ground_class_value: u8, oto_class_value: u8, last_only: bool) -> Result<(), Error> {
     if verbose {
         println!("*********************************");
         println!("* Welcome to lidar_segmentation *");
@@ -178,10 +85,7 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
 
     if verbose { println!("Reading input LAS file..."); }
     //let input = las::LasFile::new(&input_file, "r");
-    let input: las::LasFile = match las::LasFile::new(&input_file, "r") {
-        Ok(lf) => lf,
-        Err(err) => panic!("Error: {}", err),
-    };
+    let input: las::LasFile = las::LasFile::new(&input_file, "r")?;
 
     if verbose { println!("Performing analysis..."); }
 
@@ -190,7 +94,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
     let mut kdtree = KdTree::new_with_capacity(dimensions, capacity_per_node);
 
     let n_points = input.header.number_of_points as usize;
-    let num_points: f64 = (input.header.number_of_points - 1) as f64; // used for progress calculation only
+    if input.header.number_of_points == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("The input file {} contains no points.", input_file)));
+    }
+    let num_points: f64 = (input.header.number_of_points as f64 - 1.0).max(1.0); // used for progress calculation only
 
     let mut is_off_terrain = vec![false; n_points];
 
@@ -203,12 +110,12 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
         }
         if p.z > minz {
             let coords: [f64; 3] = [ p.x, p.y, p.z ];
-            kdtree.add(coords.clone(), i).unwrap();
+            kdtree.add(coords, i).unwrap();
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * i as f64 / num_points) as i32;
             if progress != old_progress {
-                println!("Creating 3D-tree: {}%", progress);
+                update_progress("Creating 3D-tree", progress as usize)?;
                 old_progress = progress;
             }
         }
@@ -221,17 +128,17 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
     for i in 0..n_points {
         let p: PointData = input.get_point_info(i);
         let ret = kdtree.nearest(&[ p.x, p.y, p.z ], num_neighbouring_points, &squared_euclidean).unwrap();
-        let mut data: Vec<Vector3<f64>> = vec![];;
+        let mut data: Vec<Vector3<f64>> = vec![];
         for j in 0..ret.len() {
             index_n = *ret[j].1;
             let p2: PointData = input.get_point_info(index_n);
-            data.push(Vector3 { x: p2.x, y: p2.y, z: p2.z });
+            data.push(Vector3::new(p2.x, p2.y, p2.z));
         }
         normal_vectors.push(plane_from_points(&data));
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * i as f64 / num_points) as i32;
             if progress != old_progress {
-                println!("Calculating point normal vectors: {}%", progress);
+                update_progress("Calculating point normal vectors", progress as usize)?;
                 old_progress = progress;
             }
         }
@@ -286,11 +193,11 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
             segment_histo.push(1);
             num_solved_points += 1.0;
 
-            let mut clust_data = ClusterData{mean: normal_vectors[seed_id].clone(), variance: Vector3{x: 0.0, y: 0.0, z: 0.0}, n: 1.0};
+            let mut clust_data = ClusterData{mean: normal_vectors[seed_id], variance: Vector3::new(0.0, 0.0, 0.0), n: 1.0};
 
             if !variable_dist {
-                while !stack.is_empty() {
-                    let i = stack.pop().unwrap();
+                while let Some(i) = stack.pop() {
+                    
                     let p = input.get_point_info(i);
                     let ret = kdtree.within(&[ p.x, p.y, p.z ], search_dist_sqrd, &squared_euclidean).unwrap();
                     let z = p.z;
@@ -309,21 +216,21 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                                 clust_data.n += 1.0;
                                 let delta = normal_vectors[index_n] - clust_data.mean;
                                 clust_data.mean += delta / clust_data.n;
-                                clust_data.variance += delta*(normal_vectors[index_n] - clust_data.mean);
+                                clust_data.variance += delta.component_mul(&(normal_vectors[index_n] - clust_data.mean));
                             }
                         }
                     }
-                    if verbose {
+                    if progress_enabled(verbose) {
                         progress = (100.0_f64 * num_solved_points / num_points) as i32;
                         if progress != old_progress {
-                            println!("Segmenting point cloud: {}%", progress);
+                            update_progress("Segmenting point cloud", progress as usize)?;
                             old_progress = progress;
                         }
                     }
                 }
             } else {
-                while !stack.is_empty() {
-                    let i = stack.pop().unwrap();
+                while let Some(i) = stack.pop() {
+                    
                     let p = input.get_point_info(i);
                     let ret = kdtree.nearest(&[ p.x, p.y, p.z ], num_neighbouring_points, &squared_euclidean).unwrap();
                     let z = p.z;
@@ -342,24 +249,20 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                                 clust_data.n += 1.0;
                                 let delta = normal_vectors[index_n] - clust_data.mean;
                                 clust_data.mean += delta / clust_data.n;
-                                clust_data.variance += delta*(normal_vectors[index_n] - clust_data.mean);
+                                clust_data.variance += delta.component_mul(&(normal_vectors[index_n] - clust_data.mean));
                             }
                         }
                     }
-                    if verbose {
+                    if progress_enabled(verbose) {
                         progress = (100.0_f64 * num_solved_points / num_points) as i32;
                         if progress != old_progress {
-                            println!("Segmenting point cloud: {}%", progress);
+                            update_progress("Segmenting point cloud", progress as usize)?;
                             old_progress = progress;
                         }
                     }
                 }
             }
 
-            if clust_data.n < 2.0 {
-                clust_data.variance / (clust_data.n - 1.0);
-            }
-
             segment_mean_norm.push(clust_data);
 
             // if clust_data.mean.z == get_max_component(clust_data.mean) {
@@ -391,13 +294,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
     let mut cur_seed_loc: usize = 0;
     while num_solved_points < num_points {
         let mut seed_found = false;
-        for i in cur_seed_loc..n_points {
-            if !is_assigned[i] {
-                seed_id = i;
-                cur_seed_loc = i;
-                seed_found = true;
-                break;
-            }
+        if let Some(i) = (cur_seed_loc..n_points).find(|&i| !is_assigned[i]) {
+            seed_id = i;
+            cur_seed_loc = i;
+            seed_found = true;
         }
 
         if seed_found {
@@ -412,8 +312,8 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
             num_solved_points += 1.0;
 
             if !variable_dist {
-                while !stack.is_empty() {
-                    let i = stack.pop().unwrap();
+                while let Some(i) = stack.pop() {
+                    
                     let p = input.get_point_info(i);
                     let ret = kdtree.within(&[ p.x, p.y, p.z ], search_dist_sqrd, &squared_euclidean).unwrap();
                     for j in 0..ret.len() {
@@ -426,17 +326,17 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                             segment_histo[current_seg_id] += 1;
                         }
                     }
-                    if verbose {
+                    if progress_enabled(verbose) {
                         progress = (100.0_f64 * num_solved_points / num_points) as i32;
                         if progress != old_progress {
-                            println!("Segmenting point cloud: {}%", progress);
+                            update_progress("Segmenting point cloud", progress as usize)?;
                             old_progress = progress;
                         }
                     }
                 }
             } else {
-                while !stack.is_empty() {
-                    let i = stack.pop().unwrap();
+                while let Some(i) = stack.pop() {
+                    
                     let p = input.get_point_info(i);
                     let ret = kdtree.nearest(&[ p.x, p.y, p.z ], num_neighbouring_points, &squared_euclidean).unwrap();
                     for j in 0..ret.len() {
@@ -449,10 +349,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                             segment_histo[current_seg_id] += 1;
                         }
                     }
-                    if verbose {
+                    if progress_enabled(verbose) {
                         progress = (100.0_f64 * num_solved_points / num_points) as i32;
                         if progress != old_progress {
-                            println!("Segmenting point cloud: {}%", progress);
+                            update_progress("Segmenting point cloud", progress as usize)?;
                             old_progress = progress;
                         }
                     }
@@ -479,12 +379,12 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
         let p: PointData = input[i]; //.get_point_info(i);
         if p.z > minz {
             let coords: [f64; 2] = [ p.x, p.y ];
-            kdtree.add(coords.clone(), i).unwrap();
+            kdtree.add(coords, i).unwrap();
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * i as f64 / num_points) as i32;
             if progress != old_progress {
-                println!("Creating 2D-tree: {}%", progress);
+                update_progress("Creating 2D-tree", progress as usize)?;
                 old_progress = progress;
             }
         }
@@ -518,10 +418,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                     num_higher[seg_val] += p.z - lowest_neighbour_z;
                 }
             //}
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Estimating segment prominance: {}%", progress);
+                    update_progress("Estimating segment prominance", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -549,10 +449,10 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
                     num_higher[seg_val] += p.z - lowest_neighbour_z;
                 }
             //}
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Estimating segment prominance: {}%", progress);
+                    update_progress("Estimating segment prominance", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -603,13 +503,13 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
     if filter {
         for i in 0..input.header.number_of_points as usize {
             if !is_off_terrain[i] {
-                output.add_point_record(input.get_record(i));
+                output.add_point_record_with_extra_bytes(input.get_record(i), input.get_extra_bytes(i));
                 num_points_filtered += 1;
             }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Saving data: {}%", progress);
+                    update_progress("Saving data", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -623,32 +523,67 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
             }
             //if is_off_terrain[i] { class_val = oto_class_value; } // off terrain point
             let pr = input.get_record(i);
-            let pr2: las::LidarPointRecord;
-            match pr {
+            let pr2: las::LidarPointRecord = match pr {
                 las::LidarPointRecord::PointRecord0 { mut point_data }  => {
                     point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord0 { point_data: point_data };
+                    las::LidarPointRecord::PointRecord0 { point_data }
 
                 },
                 las::LidarPointRecord::PointRecord1 { mut point_data, gps_data } => {
                     point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord1 { point_data: point_data, gps_data: gps_data };
+                    las::LidarPointRecord::PointRecord1 { point_data, gps_data }
                 },
                 las::LidarPointRecord::PointRecord2 { mut point_data, rgb_data } => {
                     point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord2 { point_data: point_data, rgb_data: rgb_data };
+                    las::LidarPointRecord::PointRecord2 { point_data, rgb_data }
                 },
                 las::LidarPointRecord::PointRecord3 { mut point_data, gps_data, rgb_data } => {
                     point_data.set_classification(class_val);
-                    pr2 = las::LidarPointRecord::PointRecord3 { point_data: point_data,
-                        gps_data: gps_data, rgb_data: rgb_data};
+                    las::LidarPointRecord::PointRecord3 { point_data,
+                        gps_data, rgb_data}
                 },
-            }
-            output.add_point_record(pr2);
-            if verbose {
+                las::LidarPointRecord::PointRecord4 { mut point_data, gps_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord4 { point_data,
+                        gps_data, wave_packet}
+                },
+                las::LidarPointRecord::PointRecord5 { mut point_data, gps_data, rgb_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord5 { point_data,
+                        gps_data, rgb_data, wave_packet}
+                },
+                las::LidarPointRecord::PointRecord6 { mut point_data, gps_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord6 { point_data,
+                        gps_data}
+                },
+                las::LidarPointRecord::PointRecord7 { mut point_data, gps_data, rgb_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord7 { point_data,
+                        gps_data, rgb_data}
+                },
+                las::LidarPointRecord::PointRecord8 { mut point_data, gps_data, rgb_data, nir_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord8 { point_data,
+                        gps_data, rgb_data, nir_data}
+                },
+                las::LidarPointRecord::PointRecord9 { mut point_data, gps_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord9 { point_data,
+                        gps_data, wave_packet}
+                },
+                las::LidarPointRecord::PointRecord10 { mut point_data, gps_data, rgb_data, nir_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord10 { point_data,
+                        gps_data, rgb_data,
+                        nir_data, wave_packet}
+                },
+            };
+            output.add_point_record_with_extra_bytes(pr2, input.get_extra_bytes(i));
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * i as f64 / num_points) as i32;
                 if progress != old_progress {
-                    println!("Saving data: {}%", progress);
+                    update_progress("Saving data", progress as usize)?;
                     old_progress = progress;
                 }
             }
@@ -658,13 +593,13 @@ fn lidar_segmentation(input_file: String, output_file: String, search_dist: f64,
 
     if num_points_filtered > 0 {
         if verbose { println!("Writing output LAS file..."); }
-        let _ = match output.write() {
-            Ok(_) => println!("Complete!"),
-            Err(e) => println!("error while writing: {:?}", e),
-        };
+        output.write()?;
+        println!("Complete!");
     } else {
         println!("No points were contained in the elevation slice.");
     }
+
+    Ok(())
 }
 
 #[inline]
@@ -684,9 +619,9 @@ fn plane_from_points(points: &Vec<Vector3<f64>>) -> Vector3<f64> {
     let n = points.len();
     // assert!(n >= 3, "At least three points required");
 
-    let mut sum = Vector3{ x: 0.0, y: 0.0, z: 0.0 };
+    let mut sum = Vector3::new(0.0, 0.0, 0.0);
     for p in points {
-        sum = sum + *p;
+        sum += *p;
     }
     let centroid = sum * (1.0 / (n as f64));
 
@@ -695,7 +630,7 @@ fn plane_from_points(points: &Vec<Vector3<f64>>) -> Vector3<f64> {
     let mut yy = 0.0; let mut yz = 0.0; let mut zz = 0.0;
 
     for p in points {
-        let r = p - &centroid;
+        let r = p - centroid;
         xx += r.x * r.x;
         xy += r.x * r.y;
         xz += r.x * r.z;
@@ -715,15 +650,15 @@ fn plane_from_points(points: &Vec<Vector3<f64>>) -> Vector3<f64> {
         if det_max == det_x {
             let a = (xz*yz - xy*zz) / det_x;
             let b = (xy*yz - xz*yy) / det_x;
-            Vector3{ x: 1.0, y: a, z: b }
+            Vector3::new(1.0, a, b)
         } else if det_max == det_y {
             let a = (yz*xz - xy*zz) / det_y;
             let b = (xy*xz - yz*xx) / det_y;
-            Vector3{ x: a, y: 1.0, z: b }
+            Vector3::new(a, 1.0, b)
         } else {
             let a = (yz*xy - xz*yy) / det_z;
             let b = (xz*xy - yz*xx) / det_z;
-            Vector3{ x: a, y: b, z: 1.0 }
+            Vector3::new(a, b, 1.0)
         };
 
     //plane_from_point_and_normal(centroid, normalize(dir))
@@ -733,7 +668,7 @@ fn plane_from_points(points: &Vec<Vector3<f64>>) -> Vector3<f64> {
 #[inline]
 fn normalize(v: Vector3<f64>) -> Vector3<f64> {
     let norm = (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
-    Vector3 { x: v.x/norm, y: v.y/norm, z: v.z/norm }
+    Vector3::new(v.x/norm, v.y/norm, v.z/norm)
 }
 
 #[inline]
@@ -759,6 +694,6 @@ pub struct ClusterData {
 
 impl Default for ClusterData {
     fn default() -> ClusterData {
-        ClusterData { mean: Vector3{ x: 0.0, y: 0.0, z: 0.0 }, variance: Vector3{ x: 0.0, y: 0.0, z: 0.0 }, n: 0.0 }
+        ClusterData { mean: Vector3::new(0.0, 0.0, 0.0), variance: Vector3::new(0.0, 0.0, 0.0), n: 0.0 }
     }
 }
@@ -0,0 +1,301 @@
+use std::io::{Error, ErrorKind};
+use crate::lidar::las;
+use crate::lidar::point_data::*;
+use kdtree::KdTree;
+use kdtree::distance::squared_euclidean;
+use std::f64;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+pub struct LidarSlopeBasedFilter;
+
+impl WhiteboxTool for LidarSlopeBasedFilter {
+    fn get_tool_name(&self) -> String {
+        "lidar_slope_based_filter".to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Filters a LAS file for ground points based on the slope between each point and its neighbours. Points that
+are elevated above a neighbouring point by a slope steeper than the threshold are classed as off-terrain points.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Search Distance", &["--dist"], ParameterType::Float, None, "Search distance in xy units; if specified, a constant search distance is used in place of --num_points.").with_min_value(0.0),
+            ToolParameter::optional("Number of Points", &["--num_points"], ParameterType::Integer, Some("25"), "Number of nearest-neighbour points used in place of a constant search distance.").with_min_value(1.0),
+            ToolParameter::optional("Slope Threshold", &["--slope"], ParameterType::Float, Some("60.0"), "Slope threshold, in degrees.").with_range(0.0, 90.0),
+            ToolParameter::optional("Minimum Elevation", &["--minz"], ParameterType::Float, None, "Minimum elevation used in the analysis."),
+            ToolParameter::optional("Minimum Elevation Difference", &["--minzdiff"], ParameterType::Float, Some("0.15"), "Minimum elevation difference between points to be considered an off-terrain point.").with_min_value(0.0),
+            ToolParameter::optional("Last Returns Only", &["--last_only"], ParameterType::Boolean, None, "Only consider last-return points."),
+            ToolParameter::optional("Classify Points", &["--class"], ParameterType::Boolean, None, "Output all of the points of the input, classified as ground or OTO points, rather than only the ground points."),
+            ToolParameter::optional("Ground Class", &["--groundclass"], ParameterType::Integer, Some("2"), "Class value (integer between 0-31) to be assigned to ground points; implies --class.").with_range(0.0, 31.0),
+            ToolParameter::optional("OTO Class", &["--otoclass"], ParameterType::Integer, Some("1"), "Class value (integer between 0-31) to be assigned to off-terrain objects (OTOs); implies --class.").with_range(0.0, 31.0),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_slope_based_filter -v --wd=\"/path/to/data/\" --args=\"-i=input.las -o=output.las --dist=5.0 --slope=45.0 --minz=0.0 --class\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let variable_dist = !args.is_given("--dist");
+    let mut search_dist = args.get_float("--dist").unwrap_or(2.0);
+    let num_neighbouring_points = args.get_integer("--num_points")? as usize;
+    let mut slope_threshold = args.get_float("--slope")?;
+    let minz = args.get_float("--minz").unwrap_or(f64::NEG_INFINITY);
+    let min_elev_diff = args.get_float("--minzdiff")?;
+    let filter = !(args.get_bool("--class") || args.is_given("--groundclass") || args.is_given("--otoclass"));
+    let ground_class_value = args.get_integer("--groundclass")? as u8;
+    let oto_class_value = args.get_integer("--otoclass")? as u8;
+    let last_only = args.get_bool("--last_only");
+
+    println!("***************************************");
+    println!("* Welcome to lidar_slope_based_filter *");
+    println!("***************************************");
+
+    slope_threshold = slope_threshold.to_radians().tan();
+
+    search_dist = search_dist * search_dist;
+
+    let dimensions = 2;
+    let capacity_per_node = 128;
+    let mut kdtree = KdTree::new_with_capacity(dimensions, capacity_per_node);
+
+    let input: las::LasFile = las::LasFile::new(&input_file, "r")?;
+
+    let n_points = input.header.number_of_points as usize;
+    if input.header.number_of_points == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("The input file {} contains no points.", input_file)));
+    }
+    let num_points: f64 = (input.header.number_of_points as f64 - 1.0).max(1.0); // used for progress calculation only
+    let mut is_off_terrain = vec![false; n_points];
+
+    let mut progress: i32;
+    let mut old_progress: i32 = -1;
+    for i in 0..n_points {
+        let p: PointData = input.get_point_info(i);
+        if last_only {
+            is_off_terrain[i] = !p.is_late_return();
+        }
+        if p.z > minz {
+            let coords: [f64; 2] = [ p.x, p.y ];
+            kdtree.add(coords, i).unwrap();
+            if progress_enabled(verbose) {
+                progress = (100.0_f64 * i as f64 / num_points) as i32;
+                if progress != old_progress {
+                    update_progress("Creating tree", progress as usize)?;
+                    old_progress = progress;
+                }
+            }
+        }
+    }
+
+    let mut elev_diff: f64;
+    let mut z: f64;
+    let mut zn: f64;
+    let (mut higher_z, mut lower_z, mut higher_index): (f64, f64, usize);
+    let mut index_n: usize;
+    let mut dist: f64;
+    let mut slope: f64;
+
+    if !variable_dist {
+        for i in 0..n_points {
+            if !is_off_terrain[i] {
+                let p: PointData = input.get_point_info(i);
+                if p.is_late_return() {
+                    let ret = kdtree.within(&[ p.x, p.y ], search_dist, &squared_euclidean).unwrap();
+                    z = p.z;
+                    for j in 0..ret.len() {
+                        dist = (ret[j].0).sqrt();
+                        if dist > 0.0 {
+                            index_n = *ret[j].1;
+                            let pn: PointData = input.get_point_info(index_n);
+                            zn = pn.z;
+                            if zn < z {
+                                higher_z = z;
+                                lower_z = zn;
+                                higher_index = i;
+                            } else {
+                                higher_z = zn;
+                                lower_z = z;
+                                higher_index = index_n;
+                            }
+                            elev_diff = higher_z - lower_z;
+                            slope = elev_diff / dist;
+                            if slope > slope_threshold && elev_diff > min_elev_diff {
+                                is_off_terrain[higher_index] = true;
+                            }
+                        }
+                    }
+                } else {
+                    is_off_terrain[i] = true;
+                }
+            }
+            if progress_enabled(verbose) {
+                progress = (100.0_f64 * i as f64 / num_points) as i32;
+                if progress != old_progress {
+                    update_progress("Performing Analysis", progress as usize)?;
+                    old_progress = progress;
+                }
+            }
+        }
+    } else {
+        for i in 0..n_points {
+            if !is_off_terrain[i] {
+                let p: PointData = input.get_point_info(i);
+                if p.is_late_return() {
+                    let ret = kdtree.nearest(&[ p.x, p.y ], num_neighbouring_points, &squared_euclidean).unwrap();
+                    z = p.z;
+                    for j in 0..ret.len() {
+                        dist = (ret[j].0).sqrt();
+                        if dist > 0.0 {
+                            index_n = *ret[j].1;
+                            let pn: PointData = input.get_point_info(index_n);
+                            zn = pn.z;
+                            if zn < z {
+                                higher_z = z;
+                                lower_z = zn;
+                                higher_index = i;
+                            } else {
+                                higher_z = zn;
+                                lower_z = z;
+                                higher_index = index_n;
+                            }
+                            elev_diff = higher_z - lower_z;
+                            slope = elev_diff / dist;
+                            if slope > slope_threshold && elev_diff > min_elev_diff {
+                                is_off_terrain[higher_index] = true;
+                            }
+                        }
+                    }
+                } else {
+                    is_off_terrain[i] = true;
+                }
+            }
+            if progress_enabled(verbose) {
+                progress = (100.0_f64 * i as f64 / num_points) as i32;
+                if progress != old_progress {
+                    update_progress("Performing Analysis", progress as usize)?;
+                    old_progress = progress;
+                }
+            }
+        }
+    }
+
+    // now output the data
+    let mut output = las::LasFile::initialize_using_file(&output_file, &input);
+    output.header.system_id = "EXTRACTION".to_string();
+
+    let mut num_points_filtered: i64 = 0;
+    if filter {
+        for i in 0..input.header.number_of_points as usize {
+            if !is_off_terrain[i] {
+                output.add_point_record_with_extra_bytes(input.get_record(i), input.get_extra_bytes(i));
+                num_points_filtered += 1;
+            }
+            if progress_enabled(verbose) {
+                progress = (100.0_f64 * i as f64 / num_points) as i32;
+                if progress != old_progress {
+                    update_progress("Saving data", progress as usize)?;
+                    old_progress = progress;
+                }
+            }
+        }
+    } else { // classify
+
+        for i in 0..input.header.number_of_points as usize {
+            let mut class_val = ground_class_value; // ground point
+            if is_off_terrain[i] { class_val = oto_class_value; } // off terrain point
+            let pr = input.get_record(i);
+            let pr2: las::LidarPointRecord = match pr {
+                las::LidarPointRecord::PointRecord0 { mut point_data }  => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord0 { point_data }
+
+                },
+                las::LidarPointRecord::PointRecord1 { mut point_data, gps_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord1 { point_data, gps_data }
+                },
+                las::LidarPointRecord::PointRecord2 { mut point_data, rgb_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord2 { point_data, rgb_data }
+                },
+                las::LidarPointRecord::PointRecord3 { mut point_data, gps_data, rgb_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord3 { point_data,
+                        gps_data, rgb_data}
+                },
+                las::LidarPointRecord::PointRecord4 { mut point_data, gps_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord4 { point_data,
+                        gps_data, wave_packet}
+                },
+                las::LidarPointRecord::PointRecord5 { mut point_data, gps_data, rgb_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord5 { point_data,
+                        gps_data, rgb_data, wave_packet}
+                },
+                las::LidarPointRecord::PointRecord6 { mut point_data, gps_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord6 { point_data,
+                        gps_data}
+                },
+                las::LidarPointRecord::PointRecord7 { mut point_data, gps_data, rgb_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord7 { point_data,
+                        gps_data, rgb_data}
+                },
+                las::LidarPointRecord::PointRecord8 { mut point_data, gps_data, rgb_data, nir_data } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord8 { point_data,
+                        gps_data, rgb_data, nir_data}
+                },
+                las::LidarPointRecord::PointRecord9 { mut point_data, gps_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord9 { point_data,
+                        gps_data, wave_packet}
+                },
+                las::LidarPointRecord::PointRecord10 { mut point_data, gps_data, rgb_data, nir_data, wave_packet } => {
+                    point_data.set_classification(class_val);
+                    las::LidarPointRecord::PointRecord10 { point_data,
+                        gps_data, rgb_data,
+                        nir_data, wave_packet}
+                },
+            };
+            output.add_point_record_with_extra_bytes(pr2, input.get_extra_bytes(i));
+            if progress_enabled(verbose) {
+                progress = (100.0_f64 * i as f64 / num_points) as i32;
+                if progress != old_progress {
+                    update_progress("Saving data", progress as usize)?;
+                    old_progress = progress;
+                }
+            }
+        }
+        num_points_filtered = 1; // so it passes the saving
+    }
+
+    if num_points_filtered > 0 {
+        if verbose { println!("Writing output LAS file..."); }
+        output.write()?;
+        println!("Complete!");
+    } else {
+        println!("No points were contained in the elevation slice.");
+    }
+
+    Ok(())
+}
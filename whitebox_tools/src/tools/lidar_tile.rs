@@ -0,0 +1,250 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::f64;
+use std::fs::DirBuilder;
+use std::io::Error;
+use std::io::ErrorKind;
+use std::path;
+use std::path::Path;
+use crate::lidar::las_reader::LasReader;
+use crate::lidar::las_writer::LasWriter;
+use crate::lidar::point_data::*;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+pub struct LidarTile;
+
+impl WhiteboxTool for LidarTile {
+    fn get_tool_name(&self) -> String {
+        "lidar_tile".to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Breaks a LAS or LAZ file into tiles of a regular grid. The tiles are written to a directory named after the
+input file, in the same format as the input.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS or LAZ file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Tile Width X", &["--width_x"], ParameterType::Float, Some("1000.0"), "Width of tiles in the x dimension.").with_min_value_exclusive(0.0),
+            ToolParameter::optional("Tile Width Y", &["--width_y"], ParameterType::Float, Some("1000.0"), "Width of tiles in the y dimension.").with_min_value_exclusive(0.0),
+            ToolParameter::optional("Origin X", &["--origin_x"], ParameterType::Float, Some("0.0"), "Origin point for the tile grid, x dimension."),
+            ToolParameter::optional("Origin Y", &["--origin_y"], ParameterType::Float, Some("0.0"), "Origin point for the tile grid, y dimension."),
+            ToolParameter::optional("Minimum Number of Points", &["--min_points"], ParameterType::Integer, Some("0"), "Minimum number of points contained in a tile for it to be output.").with_min_value(0.0),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_tile -v --wd=\"/path/to/data/\" --args=\"-i=input.las --width_x=100.0 --width_y=250.0 --min_points=100\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let width_x = args.get_float("--width_x")?;
+    let width_y = args.get_float("--width_y")?;
+    let origin_x = args.get_float("--origin_x")?;
+    let origin_y = args.get_float("--origin_y")?;
+    let min_points = args.get_integer("--min_points")? as usize;
+
+    lidar_tile(input_file, width_x, width_y, origin_x, origin_y, min_points, verbose)
+}
+
+fn lidar_tile(input_file: String, width_x: f64, width_y: f64, origin_x: f64, origin_y: f64, min_points: usize, verbose: bool) -> Result<(), Error> {
+    if verbose {
+        println!("*************************");
+        println!("* Welcome to lidar_tile *");
+        println!("*************************");
+    }
+
+    // The input file is streamed rather than read into memory, first to count the points
+    // in each tile and then to write the tiles. Each writing pass keeps a limited number of
+    // tiles open, so very large inputs with many tiles may be read several times.
+    let max_open_tiles = 256usize;
+
+    if verbose { println!("Reading input LAS file..."); }
+    let input = LasReader::new(&input_file)?;
+
+    if verbose { println!("Performing analysis..."); }
+
+    let min_x = input.header.min_x;
+    let max_x = input.header.max_x;
+    let min_y = input.header.min_y;
+    let max_y = input.header.max_y;
+    // let min_z = input.header.min_z;
+    // let max_z = input.header.max_z;
+
+    if input.header.number_of_points == 0 {
+
+        return Err(Error::new(ErrorKind::InvalidInput, format!("The input file {} contains no points.", input_file)));
+
+    }
+
+    let num_points: f64 = (input.header.number_of_points as f64 - 1.0).max(1.0); // used for progress calculation only
+
+    let start_x_grid = ((min_x - origin_x) / width_x).floor();
+	let end_x_grid = ((max_x - origin_x) / width_x).ceil();
+	let start_y_grid = ((min_y - origin_y) / width_y).floor();
+	let end_y_grid = ((max_y - origin_y) / width_y).ceil();
+	let cols = cmp::max((end_x_grid - start_x_grid).abs() as usize, 1);
+	let rows = cmp::max((end_y_grid - start_y_grid).abs() as usize, 1);
+	let num_tiles = rows * cols;
+
+    if num_tiles > 32767usize {
+        return Err(Error::new(ErrorKind::InvalidInput, "There are too many output tiles. Choose a larger grid width."));
+    }
+
+    let tile_of = |p: &PointData| -> usize {
+        let col = cmp::min((((p.x - origin_x) / width_x) - start_x_grid).floor() as usize, cols - 1); // relative to the grid edge
+        let row = cmp::min((((p.y - origin_y) / width_y) - start_y_grid).floor() as usize, rows - 1); // relative to the grid edge
+        row * cols + col
+    };
+
+    let mut num_points_in_tile = vec![0usize; num_tiles];
+    let mut progress: i32;
+    let mut old_progress: i32 = -1;
+    let mut i = 0usize;
+    for chunk in input {
+        let chunk = chunk?;
+        for pr in chunk {
+            num_points_in_tile[tile_of(&pr.point_data())] += 1;
+            if progress_enabled(verbose) {
+                progress = (100.0_f64 * i as f64 / num_points) as i32;
+                if progress != old_progress {
+                    update_progress("Binning points", progress as usize)?;
+                    old_progress = progress;
+                }
+            }
+            i += 1;
+        }
+    }
+
+    let mut output_tile = vec![false; num_tiles];
+    for tile_num in 0..num_tiles {
+        if num_points_in_tile[tile_num] > min_points {
+            output_tile[tile_num] = true;
+        }
+    }
+
+    let (mut col, mut row): (usize, usize);
+    let mut min_row = 999999;
+    let mut min_col = 999999;
+    for tile_num in 0..num_tiles {
+        if output_tile[tile_num] {
+            row = (tile_num as f64 / cols as f64).floor() as usize;
+            col = tile_num % cols;
+            if row < min_row { min_row = row; }
+            if col < min_col { min_col = col; }
+        }
+    }
+
+    let name: String = match Path::new(&input_file).file_stem().unwrap().to_str() {
+        Some(n) => n.to_string(),
+        None => "".to_string(),
+    };
+    let dir: String = match Path::new(&input_file).parent().unwrap().to_str() {
+        Some(n) => n.to_string(),
+        None => "".to_string(),
+    };
+    // Tiles are written in the same format (LAS or LAZ) as the input file.
+    let extension: String = match Path::new(&input_file).extension() {
+        Some(e) => e.to_str().unwrap_or("las").to_lowercase(),
+        None => "las".to_string(),
+    };
+    let sep = path::MAIN_SEPARATOR;
+    let output_dir: String = format!("{}{}{}{}", dir, sep, name, sep);
+    DirBuilder::new().recursive(true).create(output_dir.clone())?;
+
+    let tiles: Vec<usize> = (0..num_tiles).filter(|&t| output_tile[t]).collect();
+    let mut num_tiles_created = 0;
+    for batch in tiles.chunks(max_open_tiles) {
+        let mut input = LasReader::new(&input_file)?;
+        let mut writers: HashMap<usize, LasWriter> = HashMap::new();
+        for &tile_num in batch {
+            row = (tile_num as f64 / cols as f64).floor() as usize;
+            col = tile_num % cols;
+            let output_file = format!("{}{}_row{}_col{}.{}", output_dir, name, row - min_row + 1, col - min_col + 1, extension);
+            let mut output = LasWriter::new(&output_file, &input.header, &input.vlr_data, &input.evlr_data)?;
+            output.header.system_id = "EXTRACTION".to_string();
+            writers.insert(tile_num, output);
+        }
+
+        loop {
+            let chunk = input.read_chunk()?;
+            if chunk.is_empty() { break; }
+            for (j, pr) in chunk.into_iter().enumerate() {
+                if let Some(output) = writers.get_mut(&tile_of(&pr.point_data())) {
+                    output.add_point_record_with_extra_bytes(pr, input.get_extra_bytes(j))?;
+                }
+            }
+        }
+
+        for (_, mut output) in writers {
+            output.close()?;
+            num_tiles_created += 1;
+        }
+
+        if progress_enabled(verbose) {
+            progress = (100.0_f64 * num_tiles_created as f64 / tiles.len() as f64) as i32;
+            if progress != old_progress {
+                update_progress("Progress", progress as usize)?;
+                old_progress = progress;
+            }
+        }
+    }
+
+    if num_tiles_created > 0 {
+        if verbose {
+            println!("Successfully created {} tiles.", num_tiles_created);
+        }
+    } else if num_tiles_created == 0 {
+        return Err(Error::other("No tiles were created."));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::ErrorKind;
+    use crate::io_utils::temp_file_name;
+    use crate::lidar::header::LasHeader;
+    use crate::lidar::las::LasFile;
+    use crate::tools::WhiteboxTool;
+    use super::LidarTile;
+
+    #[test]
+    fn zero_width_and_empty_files_are_errors() {
+        let input_file = temp_file_name("lidar_tile_empty.las");
+        let mut header: LasHeader = Default::default();
+        header.project_id_used = true;
+        header.version_major = 1;
+        header.version_minor = 2;
+        header.x_scale_factor = 0.01;
+        header.y_scale_factor = 0.01;
+        header.z_scale_factor = 0.01;
+        let mut input = LasFile::new(&input_file, "w").unwrap();
+        input.add_header(header);
+        input.write().unwrap();
+
+        let zero_width = LidarTile.run(vec![format!("-i={}", input_file), "--width_x=0".to_string()], "", false);
+        let empty = LidarTile.run(vec![format!("-i={}", input_file)], "", false);
+        let _ = fs::remove_file(&input_file);
+
+        assert_eq!(zero_width.unwrap_err().kind(), ErrorKind::InvalidInput);
+        let err = empty.unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(err.to_string().contains("contains no points"));
+    }
+}
@@ -0,0 +1,281 @@
+//////////////////////////////////////////////////////////////////////////////////////////////
+// This tool performs a filter operation on a LAS file. The basis of the filter is
+// a mathematical morphology operator known as an 'Opening', which is an erosion (min filter)
+// followed by a dialation (max filter).
+//////////////////////////////////////////////////////////////////////////////////////////////
+
+use std::f64;
+use std::io::Error;
+use std::io::ErrorKind;
+use crate::lidar::las;
+use crate::lidar::point_data::*;
+use crate::structures::fixed_radius_search::FixedRadiusSearch;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+pub struct LidarTophatTransform;
+
+impl WhiteboxTool for LidarTophatTransform {
+    fn get_tool_name(&self) -> String {
+        "lidar_tophat_transform".to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Performs a white top-hat transform on a LAS file, i.e. replaces the elevation of each point by its height above
+a morphological opening of the surface. This approximates the height of each point above the ground.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output LAS file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Search Distance", &["--dist"], ParameterType::Float, Some("2.0"), "Search distance in xy units.").with_min_value(0.0),
+            ToolParameter::optional("Minimum Elevation", &["--minz"], ParameterType::Float, None, "Minimum elevation used in the analysis."),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_tophat_transform -v --wd=\"/path/to/data/\" --args=\"-i=input.las -o=output.las --dist=10.0\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let search_dist = args.get_float("--dist")?;
+    let minz = args.get_float("--minz").unwrap_or(f64::NEG_INFINITY);
+
+    lidar_tophat_transform(
+        input_file,
+        output_file,
+        search_dist,
+        minz,
+        verbose,
+    )?;
+    println!("Complete!");
+
+    Ok(())
+}
+
+fn lidar_tophat_transform(input_file: String, output_file: String,
+    search_dist: f64, minz: f64, verbose: bool)
+    -> Result<(), Error> {
+
+    println!("**************************************");
+    println!("* Welcome to lidar_elev_above_ground *");
+    println!("**************************************");
+
+    let input: las::LasFile = las::LasFile::new(&input_file, "r")?;
+
+    let n_points = input.header.number_of_points as usize;
+    if input.header.number_of_points == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput, format!("The input file {} contains no points.", input_file)));
+    }
+    let num_points: f64 = (input.header.number_of_points as f64 - 1.0).max(1.0); // used for progress calculation only
+    let mut progress: i32;
+    let mut old_progress: i32 = -1;
+    let mut zn: f64;
+    let mut index_n: usize;
+    let mut neighbourhood_min = vec![f64::INFINITY; n_points];
+    let mut neighbourhood_max_min = vec![f64::NEG_INFINITY; n_points];
+    let mut residuals = vec![0.0f64; n_points];
+
+    let mut frs: FixedRadiusSearch<usize> = FixedRadiusSearch::new(search_dist);
+    for i in 0..n_points {
+        let p: PointData = input.get_point_info(i);
+        if p.z > minz && p.classification() != 7u8 {
+            frs.insert(p.x, p.y, i);
+            if progress_enabled(verbose) {
+                progress = (100.0_f64 * i as f64 / num_points) as i32;
+                if progress != old_progress {
+                    update_progress("Binning points", progress as usize)?;
+                    old_progress = progress;
+                }
+            }
+        }
+    }
+
+    for i in 0..n_points {
+        let p: PointData = input.get_point_info(i);
+        let ret = frs.search(p.x, p.y);
+        for j in 0..ret.len() {
+            index_n = ret[j].0;
+            let pn: PointData = input.get_point_info(index_n);
+            zn = pn.z;
+            if zn < neighbourhood_min[i] {
+                neighbourhood_min[i] = zn;
+            }
+        }
+        if progress_enabled(verbose) {
+            progress = (100.0_f64 * i as f64 / num_points) as i32;
+            if progress != old_progress {
+                update_progress("Performing erosion", progress as usize)?;
+                old_progress = progress;
+            }
+        }
+    }
+
+    for i in 0..n_points {
+        let p: PointData = input.get_point_info(i);
+        let ret = frs.search(p.x, p.y);
+        for j in 0..ret.len() {
+            index_n = ret[j].0;
+            if neighbourhood_min[index_n] > neighbourhood_max_min[i] {
+                neighbourhood_max_min[i] = neighbourhood_min[index_n];
+            }
+        }
+        residuals[i] = p.z - neighbourhood_max_min[i];
+        if progress_enabled(verbose) {
+            progress = (100.0_f64 * i as f64 / num_points) as i32;
+            if progress != old_progress {
+                update_progress("Performing dilation", progress as usize)?;
+                old_progress = progress;
+            }
+        }
+    }
+
+    // now output the data
+    let mut output = las::LasFile::initialize_using_file(&output_file, &input);
+    output.header.system_id = "EXTRACTION".to_string();
+
+    let mut num_points_filtered: i64 = 0;
+    for i in 0..input.header.number_of_points as usize {
+        let pr = input.get_record(i);
+        let pr2: las::LidarPointRecord;
+        match pr {
+            las::LidarPointRecord::PointRecord0 { mut point_data }  => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord0 { point_data };
+
+            },
+            las::LidarPointRecord::PointRecord1 { mut point_data, gps_data } => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord1 { point_data, gps_data };
+            },
+            las::LidarPointRecord::PointRecord2 { mut point_data, rgb_data } => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord2 { point_data, rgb_data };
+            },
+            las::LidarPointRecord::PointRecord3 { mut point_data, gps_data, rgb_data } => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord3 { point_data,
+                    gps_data, rgb_data};
+            },
+            las::LidarPointRecord::PointRecord4 { mut point_data, gps_data, wave_packet } => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord4 { point_data,
+                    gps_data, wave_packet};
+            },
+            las::LidarPointRecord::PointRecord5 { mut point_data, gps_data, rgb_data, wave_packet } => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord5 { point_data,
+                    gps_data, rgb_data, wave_packet};
+            },
+            las::LidarPointRecord::PointRecord6 { mut point_data, gps_data } => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord6 { point_data,
+                    gps_data};
+            },
+            las::LidarPointRecord::PointRecord7 { mut point_data, gps_data, rgb_data } => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord7 { point_data,
+                    gps_data, rgb_data};
+            },
+            las::LidarPointRecord::PointRecord8 { mut point_data, gps_data, rgb_data, nir_data } => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord8 { point_data,
+                    gps_data, rgb_data, nir_data};
+            },
+            las::LidarPointRecord::PointRecord9 { mut point_data, gps_data, wave_packet } => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord9 { point_data,
+                    gps_data, wave_packet};
+            },
+            las::LidarPointRecord::PointRecord10 { mut point_data, gps_data, rgb_data, nir_data, wave_packet } => {
+                point_data.z = residuals[i];
+                pr2 = las::LidarPointRecord::PointRecord10 { point_data,
+                    gps_data, rgb_data,
+                    nir_data, wave_packet};
+            },
+        }
+        output.add_point_record_with_extra_bytes(pr2, input.get_extra_bytes(i));
+        num_points_filtered += 1;
+        if progress_enabled(verbose) {
+            progress = (100.0_f64 * i as f64 / num_points) as i32;
+            if progress != old_progress {
+                update_progress("Saving data", progress as usize)?;
+                old_progress = progress;
+            }
+        }
+    }
+
+    if num_points_filtered > 0 {
+        if verbose { println!("Writing output LAS file..."); }
+        output.write()?;
+    } else {
+        return Err(Error::new(ErrorKind::InvalidData, "No points were contained in the output file."));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64;
+    use std::fs;
+    use crate::io_utils::temp_file_name;
+    use crate::lidar::extra_bytes::{ ExtraBytesDataType, ExtraBytesDescriptor };
+    use crate::lidar::header::LasHeader;
+    use crate::lidar::las::{ LasFile, LidarPointRecord };
+    use crate::lidar::point_data::PointData;
+    use super::lidar_tophat_transform;
+
+    #[test]
+    fn heights_above_ground_and_extra_bytes() {
+        let input_file = temp_file_name("input.las");
+        let output_file = temp_file_name("output.las");
+        let mut header: LasHeader = Default::default();
+        header.project_id_used = true;
+        header.version_major = 1;
+        header.version_minor = 2;
+        header.x_scale_factor = 0.01;
+        header.y_scale_factor = 0.01;
+        header.z_scale_factor = 0.01;
+        let mut input = LasFile::new(&input_file, "w").unwrap();
+        input.add_header(header);
+        input.add_extra_bytes_descriptor(ExtraBytesDescriptor::new("Segment", ExtraBytesDataType::U16, "segment identifier")).unwrap();
+        // flat ground at 100 m, with a 5 m object in the middle of the grid
+        for i in 0..400 {
+            let mut p: PointData = Default::default();
+            p.x = (i % 20) as f64;
+            p.y = (i / 20) as f64;
+            p.z = if i == 210 { 105.0 } else { 100.0 };
+            p.bit_field.value = 0b0000_1001;
+            input.add_point_record(LidarPointRecord::PointRecord0 { point_data: p });
+            input.set_extra_f64(i, "Segment", (i % 7) as f64).unwrap();
+        }
+        input.write().unwrap();
+
+        lidar_tophat_transform(input_file.clone(), output_file.clone(), 2.0, f64::NEG_INFINITY, false).unwrap();
+        let output = LasFile::new(&output_file, "r").unwrap();
+        let _ = fs::remove_file(&input_file);
+        let _ = fs::remove_file(&output_file);
+
+        assert_eq!(output.header.number_of_points, 400);
+        assert_eq!(output.get_extra_bytes_descriptors().len(), 1);
+        for i in 0..400 {
+            let height = output.get_point_info(i).z;
+            assert!((height - if i == 210 { 5.0 } else { 0.0 }).abs() < 1e-6, "point {} is {} m above ground", i, height);
+            assert_eq!(output.get_extra_f64(i, "Segment").unwrap(), (i % 7) as f64);
+        }
+    }
+}
@@ -0,0 +1,598 @@
+use std::cmp;
+use std::f64;
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{Error, ErrorKind, SeekFrom};
+use std::time::{SystemTime, UNIX_EPOCH};
+use byteorder::{ByteOrder, LittleEndian};
+use crate::lidar::las::{ standard_header_size, GpsTimeType };
+use crate::lidar::las_reader::LasReader;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+
+const TOOL_NAME: &str = "lidar_validate";
+
+pub struct LidarValidate;
+
+impl WhiteboxTool for LidarValidate {
+    fn get_tool_name(&self) -> String {
+        TOOL_NAME.to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Checks that a LiDAR (LAS) file conforms to the LAS specification and that its header is consistent
+with its point records, reporting the problems found as JSON. The tool fails if any errors are found.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input LAS or LAZ file.").with_file_type(ParameterFileType::Lidar),
+            ToolParameter::optional("Output File", &["-o", "--output"], ParameterType::NewFile, None, "Output JSON report file; by default the report is printed.").with_file_type(ParameterFileType::Json),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=lidar_validate --wd=\"/dir/to/data\" --args=\"-i=file.las\"
+./whitebox-tools -r=lidar_validate --wd=\"/dir/to/data\" --args=\"-i=file.laz -o=report.json\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+/// The length of the standard fields of each point data record format (PDRF).
+const RECORD_LENGTHS: [u16; 11] = [20, 28, 26, 34, 57, 63, 30, 36, 38, 59, 67];
+
+/// The GPS epoch, 1980-01-06, in seconds since the Unix epoch.
+const GPS_EPOCH: f64 = 315_964_800f64;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Clone, Debug)]
+struct Issue {
+    severity: Severity,
+    check: &'static str,
+    message: String,
+}
+
+#[derive(Default)]
+struct Report {
+    issues: Vec<Issue>,
+}
+
+impl Report {
+    fn error(&mut self, check: &'static str, message: String) {
+        self.issues.push(Issue { severity: Severity::Error, check, message });
+    }
+
+    fn warning(&mut self, check: &'static str, message: String) {
+        self.issues.push(Issue { severity: Severity::Warning, check, message });
+    }
+
+    fn has_error(&self, check: &str) -> bool {
+        self.issues.iter().any(|i| i.severity == Severity::Error && i.check == check)
+    }
+
+    fn num_errors(&self) -> usize {
+        self.issues.iter().filter(|i| i.severity == Severity::Error).count()
+    }
+
+    fn num_warnings(&self) -> usize {
+        self.issues.iter().filter(|i| i.severity == Severity::Warning).count()
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output").unwrap_or_default();
+
+    if verbose {
+        println!("***************{}", "*".repeat(TOOL_NAME.len()));
+        println!("* Welcome to {} *", TOOL_NAME);
+        println!("***************{}", "*".repeat(TOOL_NAME.len()));
+    }
+
+    let mut report = Report::default();
+    let summary = validate_file_structure(&input_file, &mut report)?;
+    if summary.readable {
+        validate_points(&input_file, &summary, &mut report);
+    }
+
+    let json = report_to_json(&input_file, &summary, &report);
+    if output_file.is_empty() {
+        println!("{}", json);
+    } else {
+        let mut f = File::create(&output_file)?;
+        f.write_all(json.as_bytes())?;
+        if verbose { println!("Report written to {}", output_file); }
+    }
+
+    let num_errors = report.num_errors();
+    if num_errors > 0 {
+        return Err(Error::new(ErrorKind::InvalidData, format!("{} failed validation with {} error(s).", input_file, num_errors)));
+    }
+    Ok(())
+}
+
+/// The header values needed by the point checks, as stored in the file.
+#[derive(Default)]
+struct FileSummary {
+    version_major: u8,
+    version_minor: u8,
+    point_format: u8,
+    compressed: bool,
+    number_of_points: u64,
+    number_of_points_by_return: [u64; 15],
+    /// `false` if the file is too broken for its point records to be read.
+    readable: bool,
+}
+
+/// Checks the header, VLRs and EVLRs, which are read directly from the file rather than
+/// through `LasFile`, since it tolerates, and so hides, several of these problems.
+fn validate_file_structure(input_file: &str, report: &mut Report) -> Result<FileSummary, Error> {
+    let mut summary = FileSummary::default();
+    let mut f = File::open(input_file)?;
+    let file_size = f.metadata()?.len();
+
+    let mut buffer = vec![0u8; cmp::min(file_size, 375) as usize];
+    f.read_exact(&mut buffer)?;
+    if buffer.len() < 227 || &buffer[0..4] != b"LASF" {
+        report.error("file_signature", "The file does not begin with a LAS header (file signature 'LASF').".to_string());
+        return Ok(summary);
+    }
+
+    summary.version_major = buffer[24];
+    summary.version_minor = buffer[25];
+    let (major, minor) = (summary.version_major, summary.version_minor);
+    if major != 1 || minor > 4 {
+        report.error("version", format!("LAS version {}.{} is not supported.", major, minor));
+        return Ok(summary);
+    }
+
+    let header_size = LittleEndian::read_u16(&buffer[94..96]);
+    let offset_to_points = LittleEndian::read_u32(&buffer[96..100]) as u64;
+    let number_of_vlrs = LittleEndian::read_u32(&buffer[100..104]);
+    summary.compressed = (buffer[104] & 0b1100_0000u8) != 0;
+    summary.point_format = buffer[104] & 0b0011_1111u8;
+    let point_record_length = LittleEndian::read_u16(&buffer[105..107]);
+    let legacy_number_of_points = LittleEndian::read_u32(&buffer[107..111]) as u64;
+    let mut legacy_number_by_return = [0u64; 5];
+    for i in 0..5 {
+        legacy_number_by_return[i] = LittleEndian::read_u32(&buffer[111 + i * 4..115 + i * 4]) as u64;
+    }
+    let global_encoding = LittleEndian::read_u16(&buffer[6..8]);
+
+    let expected_header_size = standard_header_size(minor);
+    if header_size < expected_header_size || header_size as u64 > file_size {
+        report.error("header_size", format!("The header size is {} bytes but a LAS {}.{} header is {} bytes.", header_size, major, minor, expected_header_size));
+        return Ok(summary);
+    }
+    if (buffer.len() as u64) < expected_header_size as u64 {
+        report.error("header_size", "The file is shorter than its header.".to_string());
+        return Ok(summary);
+    }
+
+    let (mut waveform_data_start, mut start_of_first_evlr, mut number_of_evlrs) = (0u64, 0u64, 0u32);
+    if minor >= 3 {
+        waveform_data_start = LittleEndian::read_u64(&buffer[227..235]);
+    }
+    summary.number_of_points = legacy_number_of_points;
+    summary.number_of_points_by_return[0..5].copy_from_slice(&legacy_number_by_return);
+    if minor >= 4 {
+        start_of_first_evlr = LittleEndian::read_u64(&buffer[235..243]);
+        number_of_evlrs = LittleEndian::read_u32(&buffer[243..247]);
+        summary.number_of_points = LittleEndian::read_u64(&buffer[247..255]);
+        for i in 0..15 {
+            summary.number_of_points_by_return[i] = LittleEndian::read_u64(&buffer[255 + i * 8..263 + i * 8]);
+        }
+
+        // The legacy counts must match the 64-bit counts, where they can represent them,
+        // for the point formats of earlier versions, and must be zero for the others.
+        let legacy_expected = if summary.point_format < 6 && summary.number_of_points <= u32::MAX as u64 {
+            summary.number_of_points
+        } else {
+            0u64
+        };
+        if legacy_number_of_points != legacy_expected {
+            report.error("legacy_point_count", format!("The legacy number of point records is {} but should be {}.", legacy_number_of_points, legacy_expected));
+        }
+        for i in 0..5 {
+            let expected = if summary.point_format < 6 && legacy_expected > 0 { summary.number_of_points_by_return[i] } else { 0u64 };
+            if legacy_number_by_return[i] != expected {
+                report.error("legacy_point_count", format!("The legacy number of points by return {} is {} but should be {}.", i + 1, legacy_number_by_return[i], expected));
+            }
+        }
+    }
+
+    // point data record format
+    let max_format = match minor {
+        0 | 1 => 1u8,
+        2 => 3u8,
+        3 => 5u8,
+        _ => 10u8,
+    };
+    if summary.point_format > 10 {
+        report.error("point_format", format!("Point data record format {} is not supported.", summary.point_format));
+        return Ok(summary);
+    } else if summary.point_format > max_format {
+        report.error("point_format", format!("Point data record format {} is not allowed in LAS {}.{} files.", summary.point_format, major, minor));
+    }
+    let standard_length = RECORD_LENGTHS[summary.point_format as usize];
+    if point_record_length < standard_length {
+        report.error("point_record_length", format!("The point record length is {} bytes but format {} records are at least {} bytes.", point_record_length, summary.point_format, standard_length));
+        return Ok(summary);
+    }
+
+    if offset_to_points > file_size || offset_to_points < header_size as u64 {
+        report.error("offset_to_points", format!("The offset to the point records ({}) lies outside of the file.", offset_to_points));
+        return Ok(summary);
+    }
+
+    // VLRs
+    let mut head = vec![0u8; offset_to_points as usize];
+    f.seek(SeekFrom::Start(0))?;
+    f.read_exact(&mut head)?;
+    let mut offset = header_size as usize;
+    let mut has_extra_bytes_vlr = false;
+    for i in 0..number_of_vlrs {
+        if offset + 54 > head.len() {
+            report.error("vlr_length", format!("VLR {} of {} begins after the start of the point records.", i + 1, number_of_vlrs));
+            break;
+        }
+        let user_id = String::from_utf8_lossy(&head[offset + 2..offset + 18]).trim_matches(char::from(0)).trim().to_string();
+        let record_id = LittleEndian::read_u16(&head[offset + 18..offset + 20]);
+        let record_length = LittleEndian::read_u16(&head[offset + 20..offset + 22]) as usize;
+        offset += 54;
+        if offset + record_length > head.len() {
+            report.error("vlr_length", format!("VLR {} ({}, {}) is {} bytes long and extends beyond the start of the point records.", i + 1, user_id, record_id, record_length));
+            break;
+        }
+        if let Some(message) = check_record_length(&user_id, record_id, &head[offset..offset + record_length]) {
+            report.error("vlr_length", format!("VLR {} ({}, {}): {}", i + 1, user_id, record_id, message));
+        }
+        if user_id == "LASF_Spec" && record_id == 4 {
+            has_extra_bytes_vlr = true;
+        }
+        offset += record_length;
+    }
+    if offset < head.len() && !report.has_error("vlr_length") {
+        report.warning("vlr_length", format!("There are {} bytes between the last VLR and the point records.", head.len() - offset));
+    }
+    if point_record_length > standard_length && !has_extra_bytes_vlr {
+        report.warning("point_record_length", format!("The point records include {} extra bytes but the file does not describe them (extra bytes VLR).", point_record_length - standard_length));
+    }
+
+    // point records
+    let mut truncated = false;
+    let waveform_data_internal = (global_encoding & 0b0010u16) != 0;
+    let end_of_points = if minor >= 4 && number_of_evlrs > 0 && start_of_first_evlr > 0 {
+        start_of_first_evlr
+    } else if minor >= 3 && waveform_data_internal && waveform_data_start > 0 {
+        waveform_data_start
+    } else {
+        file_size
+    };
+    if end_of_points < offset_to_points || end_of_points > file_size {
+        report.error("evlr_length", "The EVLRs begin outside of the point records' section of the file.".to_string());
+    } else if !summary.compressed {
+        let bytes = end_of_points - offset_to_points;
+        let available = bytes / point_record_length as u64;
+        if available != summary.number_of_points {
+            report.error("point_count", format!("The header states that there are {} point records but the file contains {}.", summary.number_of_points, available));
+            truncated = available < summary.number_of_points;
+        } else if !bytes.is_multiple_of(point_record_length as u64) {
+            report.warning("point_count", format!("There are {} bytes following the last point record.", bytes % point_record_length as u64));
+        }
+    }
+
+    // EVLRs
+    let mut offset = start_of_first_evlr;
+    for i in 0..number_of_evlrs {
+        if offset + 60 > file_size {
+            report.error("evlr_length", format!("EVLR {} of {} begins beyond the end of the file.", i + 1, number_of_evlrs));
+            break;
+        }
+        let mut evlr_header = [0u8; 60];
+        f.seek(SeekFrom::Start(offset))?;
+        f.read_exact(&mut evlr_header)?;
+        let record_length = LittleEndian::read_u64(&evlr_header[20..28]);
+        if offset + 60 + record_length > file_size {
+            report.error("evlr_length", format!("EVLR {} of {} is {} bytes long and extends beyond the end of the file.", i + 1, number_of_evlrs, record_length));
+            break;
+        }
+        offset += 60 + record_length;
+    }
+
+    summary.readable = !truncated && !report.has_error("vlr_length") && !report.has_error("evlr_length");
+    Ok(summary)
+}
+
+/// Returns a description of the problem if the length of a VLR of a known type does not
+/// agree with its definition.
+fn check_record_length(user_id: &str, record_id: u16, data: &[u8]) -> Option<String> {
+    let len = data.len();
+    match (user_id, record_id) {
+        ("LASF_Projection", 34_735) => {
+            if len < 8 || !len.is_multiple_of(8) {
+                return Some(format!("a GeoKey directory must be a multiple of 8 bytes long, not {}.", len));
+            }
+            let number_of_keys = LittleEndian::read_u16(&data[6..8]) as usize;
+            if len != 8 * (number_of_keys + 1) {
+                return Some(format!("the GeoKey directory contains {} keys and should be {} bytes long, not {}.", number_of_keys, 8 * (number_of_keys + 1), len));
+            }
+        },
+        ("LASF_Projection", 34_736) if !len.is_multiple_of(8) => {
+            return Some(format!("the GeoTIFF double parameters must be a multiple of 8 bytes long, not {}.", len));
+        },
+        ("LASF_Spec", 0) if len != 256 * 16 => {
+            return Some(format!("a classification lookup is {} bytes long, not {}.", 256 * 16, len));
+        },
+        ("LASF_Spec", 4) if !len.is_multiple_of(192) => {
+            return Some(format!("an extra bytes description must be a multiple of 192 bytes long, not {}.", len));
+        },
+        ("LASF_Spec", 100..=354) if len != 26 => {
+            return Some(format!("a waveform packet descriptor is 26 bytes long, not {}.", len));
+        },
+        _ => {},
+    }
+    None
+}
+
+/// Checks the point records against the header: the number of points and points by return,
+/// the bounding box, the return numbers, the GPS times and duplicate points.
+fn validate_points(input_file: &str, summary: &FileSummary, report: &mut Report) {
+    let input = match LasReader::new(input_file) {
+        Ok(r) => r,
+        Err(e) => {
+            report.error("point_records", format!("The point records cannot be read: {}", e));
+            return;
+        },
+    };
+    let header = input.header.clone();
+    let max_returns = if summary.point_format >= 6 { 15u8 } else { 5u8 };
+    let gps_week_time = match header.global_encoding.gps_time() {
+        GpsTimeType::GpsWeekTime => true,
+        GpsTimeType::SatelliteGpsTime => false,
+    };
+    // Adjusted standard GPS time is the GPS time minus 1e9 seconds. Times before the GPS
+    // epoch, or in the future, are invalid.
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as f64).unwrap_or(f64::INFINITY);
+    let (min_gps_time, max_gps_time) = if gps_week_time {
+        (0f64, 604_800f64)
+    } else {
+        (-1_000_000_000f64, now - GPS_EPOCH + 18f64 - 1_000_000_000f64)
+    };
+
+    let mut num_points = 0u64;
+    let mut number_by_return = [0u64; 15];
+    let (mut min_x, mut min_y, mut min_z) = (f64::INFINITY, f64::INFINITY, f64::INFINITY);
+    let (mut max_x, mut max_y, mut max_z) = (f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+    let (mut num_zero_returns, mut num_bad_returns, mut num_return_gt_returns) = (0u64, 0u64, 0u64);
+    let mut num_bad_gps_times = 0u64;
+    let (mut min_bad_gps_time, mut max_bad_gps_time) = (f64::INFINITY, f64::NEG_INFINITY);
+    for chunk in input {
+        let chunk = match chunk {
+            Ok(c) => c,
+            Err(e) => {
+                report.error("point_records", format!("The point records cannot be read after record {}: {}", num_points, e));
+                return;
+            },
+        };
+        for pr in chunk {
+            let p = pr.point_data();
+
+            // The PointData accessors report a return number of zero as one, so the bit
+            // field is read directly.
+            let (return_number, number_of_returns) = if p.is_extended() {
+                (p.bit_field.value & 0b0000_1111u8, p.bit_field.value >> 4)
+            } else {
+                (p.bit_field.value & 0b0000_0111u8, (p.bit_field.value >> 3) & 0b0000_0111u8)
+            };
+            if return_number == 0 || number_of_returns == 0 {
+                num_zero_returns += 1;
+            } else if return_number > max_returns || number_of_returns > max_returns {
+                num_bad_returns += 1;
+            } else if return_number > number_of_returns {
+                num_return_gt_returns += 1;
+            }
+            if return_number >= 1 && return_number <= max_returns {
+                number_by_return[return_number as usize - 1] += 1;
+            }
+
+            if let Some(t) = pr.gps_time() {
+                if !(t >= min_gps_time && t <= max_gps_time) {
+                    num_bad_gps_times += 1;
+                    if t < min_bad_gps_time { min_bad_gps_time = t; }
+                    if t > max_bad_gps_time { max_bad_gps_time = t; }
+                }
+            }
+
+            if p.x < min_x { min_x = p.x; }
+            if p.x > max_x { max_x = p.x; }
+            if p.y < min_y { min_y = p.y; }
+            if p.y > max_y { max_y = p.y; }
+            if p.z < min_z { min_z = p.z; }
+            if p.z > max_z { max_z = p.z; }
+
+            num_points += 1;
+        }
+    }
+
+    if num_points != summary.number_of_points {
+        report.error("point_count", format!("The header states that there are {} point records but {} were read.", summary.number_of_points, num_points));
+    }
+
+    let num_by_return_fields = if summary.version_minor >= 4 { 15 } else { 5 };
+    for i in 0..num_by_return_fields {
+        if number_by_return[i] != summary.number_of_points_by_return[i] {
+            report.error("points_by_return", format!("The header states that there are {} points with return number {} but there are {}.",
+                summary.number_of_points_by_return[i], i + 1, number_by_return[i]));
+        }
+    }
+
+    if num_zero_returns > 0 {
+        report.error("return_number", format!("{} points have a return number or number of returns of zero.", num_zero_returns));
+    }
+    if num_bad_returns > 0 {
+        report.error("return_number", format!("{} points have a return number or number of returns greater than {}.", num_bad_returns, max_returns));
+    }
+    if num_return_gt_returns > 0 {
+        report.error("return_number", format!("{} points have a return number greater than their number of returns.", num_return_gt_returns));
+    }
+
+    if num_bad_gps_times > 0 {
+        let expected = if gps_week_time { "GPS week time, 0 to 604800 s" } else { "adjusted standard GPS time, between the GPS epoch and now" };
+        report.error("gps_time", format!("{} points have GPS times ({} to {}) outside of the range of {}.", num_bad_gps_times, min_bad_gps_time, max_bad_gps_time, expected));
+    }
+
+    let num_passes = cmp::max(1, num_points.div_ceil(MAX_DUPLICATE_CHECK_POINTS));
+    match count_duplicate_points(input_file, num_passes) {
+        Ok(0) => {},
+        Ok(num_duplicates) => {
+            report.warning("duplicate_points", format!("{} points have the same coordinates as an earlier point.", num_duplicates));
+        },
+        Err(e) => {
+            report.error("point_records", format!("The point records cannot be read while looking for duplicate points: {}", e));
+        },
+    }
+
+    // The header bounds must contain the points, to within the precision of the stored
+    // coordinates, and should not be larger than the extent of the points.
+    if num_points > 0 {
+        let axes = [("x", header.min_x, header.max_x, min_x, max_x, header.x_scale_factor),
+                    ("y", header.min_y, header.max_y, min_y, max_y, header.y_scale_factor),
+                    ("z", header.min_z, header.max_z, min_z, max_z, header.z_scale_factor)];
+        for &(axis, header_min, header_max, min, max, scale_factor) in &axes {
+            let tolerance = 0.5 * scale_factor.abs();
+            if min < header_min - tolerance || max > header_max + tolerance {
+                report.error("bounds", format!("The points extend from {} to {} in {} but the header bounds are {} to {}.", min, max, axis, header_min, header_max));
+            } else if min > header_min + tolerance || max < header_max - tolerance {
+                report.warning("bounds", format!("The header bounds in {} ({} to {}) are larger than the extent of the points ({} to {}).", axis, header_min, header_max, min, max));
+            }
+        }
+    }
+}
+
+/// The largest number of point coordinates that are held in memory at once while looking
+/// for duplicate points. The points of larger files are divided into groups by a hash of
+/// their coordinates, so that duplicates fall into the same group, and each group is
+/// checked in a separate pass through the file.
+const MAX_DUPLICATE_CHECK_POINTS: u64 = 10_000_000;
+
+/// Returns the number of points that have the same stored coordinates as an earlier point,
+/// reading the file once for each group of points.
+fn count_duplicate_points(input_file: &str, num_passes: u64) -> Result<u64, Error> {
+    let mut num_duplicates = 0u64;
+    for pass in 0..num_passes {
+        let input = LasReader::new(input_file)?;
+        let header = input.header.clone();
+        let mut coordinates: Vec<(i64, i64, i64)> = vec![];
+        for chunk in input {
+            for pr in chunk? {
+                let p = pr.point_data();
+                let key = (((p.x - header.x_offset) / header.x_scale_factor).round() as i64,
+                           ((p.y - header.y_offset) / header.y_scale_factor).round() as i64,
+                           ((p.z - header.z_offset) / header.z_scale_factor).round() as i64);
+                if num_passes == 1 || coordinate_hash(key) % num_passes == pass {
+                    coordinates.push(key);
+                }
+            }
+        }
+        coordinates.sort_unstable();
+        num_duplicates += coordinates.windows(2).filter(|w| w[0] == w[1]).count() as u64;
+    }
+    Ok(num_duplicates)
+}
+
+fn coordinate_hash(key: (i64, i64, i64)) -> u64 {
+    let h = (key.0 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (key.1 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ (key.2 as u64).wrapping_mul(0x1656_67B1_9E37_79F9);
+    h ^ (h >> 29)
+}
+
+fn report_to_json(input_file: &str, summary: &FileSummary, report: &Report) -> String {
+    let mut s = "{\n".to_string();
+    s.push_str(&format!("  \"file\": {},\n", json_string(input_file)));
+    s.push_str(&format!("  \"version\": \"{}.{}\",\n", summary.version_major, summary.version_minor));
+    s.push_str(&format!("  \"point_format\": {},\n", summary.point_format));
+    s.push_str(&format!("  \"compressed\": {},\n", summary.compressed));
+    s.push_str(&format!("  \"number_of_points\": {},\n", summary.number_of_points));
+    s.push_str(&format!("  \"points_checked\": {},\n", summary.readable));
+    s.push_str(&format!("  \"valid\": {},\n", report.num_errors() == 0));
+    s.push_str(&format!("  \"errors\": {},\n", report.num_errors()));
+    s.push_str(&format!("  \"warnings\": {},\n", report.num_warnings()));
+    s.push_str("  \"issues\": [");
+    for (i, issue) in report.issues.iter().enumerate() {
+        let severity = match issue.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        s.push_str(if i == 0 { "\n" } else { ",\n" });
+        s.push_str(&format!("    {{ \"severity\": \"{}\", \"check\": \"{}\", \"message\": {} }}", severity, issue.check, json_string(&issue.message)));
+    }
+    s.push_str(if report.issues.is_empty() { "]\n" } else { "\n  ]\n" });
+    s.push('}');
+    s
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use crate::io_utils::temp_file_name;
+    use crate::lidar::header::LasHeader;
+    use crate::lidar::las::LidarPointRecord;
+    use crate::lidar::las_writer::LasWriter;
+    use crate::lidar::point_data::PointData;
+    use super::*;
+
+    /// Writes 200 points, of which every fifth repeats the coordinates of the point before it.
+    fn write_points_with_duplicates(file_name: &str) {
+        let mut header: LasHeader = Default::default();
+        header.project_id_used = true;
+        header.version_major = 1;
+        header.version_minor = 2;
+        header.point_format = 0;
+        header.x_scale_factor = 0.01;
+        header.y_scale_factor = 0.01;
+        header.z_scale_factor = 0.01;
+        let mut writer = LasWriter::new(file_name, &header, &[], &[]).unwrap();
+        for i in 0..200 {
+            let j = if i % 5 == 4 { i - 1 } else { i };
+            let mut p: PointData = Default::default();
+            p.x = 500.0 + j as f64 * 0.5;
+            p.y = 800.0 + (j % 13) as f64;
+            p.z = 10.0 + (j % 3) as f64 * 0.01;
+            p.bit_field.value = 0b0000_1001;
+            writer.add_point_record(LidarPointRecord::PointRecord0 { point_data: p }).unwrap();
+        }
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn duplicates_are_found_in_one_or_several_passes() {
+        let file_name = temp_file_name("duplicates.las");
+        write_points_with_duplicates(&file_name);
+        for num_passes in 1..5 {
+            assert_eq!(count_duplicate_points(&file_name, num_passes).unwrap(), 40);
+        }
+
+        let mut report = Report::default();
+        let summary = validate_file_structure(&file_name, &mut report).unwrap();
+        validate_points(&file_name, &summary, &mut report);
+        let _ = fs::remove_file(&file_name);
+        let warnings: Vec<&Issue> = report.issues.iter().filter(|i| i.check == "duplicate_points").collect();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].message.starts_with("40 points"));
+        assert_eq!(report.num_errors(), 0, "{:?}", report.issues);
+    }
+}
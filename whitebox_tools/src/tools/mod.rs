@@ -1,12 +1,78 @@
+pub mod anova;
+pub mod cumulative_distribution;
+pub mod dev_from_mean_elev;
+pub mod elev_percentile;
+pub mod fill_missing_data;
 pub mod lidar_elevation_slice;
 pub mod lidar_flightline_overlap;
+pub mod lidar_ground_point_separation;
 pub mod lidar_info;
 pub mod lidar_join;
+pub mod lidar_kappa;
+pub mod lidar_normal_vec;
+pub mod lidar_reclass;
+pub mod lidar_remove_outliers;
+pub mod lidar_reproject;
+pub mod lidar_segmentation;
+pub mod lidar_segmentation_based_filter;
+pub mod lidar_slope_based_filter;
+pub mod lidar_tile;
+pub mod lidar_tophat_transform;
+pub mod lidar_validate;
+pub mod parameters;
+pub mod percent_elev_range;
+pub mod progress;
+pub mod raster_reproject;
+pub mod relative_topographic_position;
 pub mod remove_off_terrain_objects;
 
-use tools;
+use crate::tools;
+use crate::tools::parameters::*;
 use std::io::{Error, ErrorKind};
 
+/// A tool, which is run by the `ToolManager` with the arguments given on the command line.
+pub trait WhiteboxTool {
+    fn get_tool_name(&self) -> String;
+    fn get_tool_description(&self) -> String;
+    fn get_tool_parameters(&self) -> Vec<ToolParameter>;
+    fn get_example_usage(&self) -> Option<String>;
+    /// Runs the tool. File names in the arguments that are not paths are relative to the
+    /// working directory.
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error>;
+}
+
+/// Returns all of the tools, in alphabetical order of their names. A tool is added by
+/// implementing `WhiteboxTool` in a module of its own and listing it here.
+pub fn get_tools() -> Vec<Box<dyn WhiteboxTool>> {
+    vec![
+        Box::new(tools::anova::Anova),
+        Box::new(tools::cumulative_distribution::CumulativeDistribution),
+        Box::new(tools::dev_from_mean_elev::DevFromMeanElev),
+        Box::new(tools::elev_percentile::ElevPercentile),
+        Box::new(tools::fill_missing_data::FillMissingData),
+        Box::new(tools::lidar_elevation_slice::LidarElevationSlice),
+        Box::new(tools::lidar_flightline_overlap::LidarFlightlineOverlap),
+        Box::new(tools::lidar_ground_point_separation::LidarGroundPointSeparation),
+        Box::new(tools::lidar_info::LidarInfo),
+        Box::new(tools::lidar_join::LidarJoin),
+        Box::new(tools::lidar_kappa::LidarKappa),
+        Box::new(tools::lidar_normal_vec::LidarNormalVec),
+        Box::new(tools::lidar_reclass::LidarReclass),
+        Box::new(tools::lidar_remove_outliers::LidarRemoveOutliers),
+        Box::new(tools::lidar_reproject::LidarReproject),
+        Box::new(tools::lidar_segmentation::LidarSegmentation),
+        Box::new(tools::lidar_segmentation_based_filter::LidarSegmentationBasedFilter),
+        Box::new(tools::lidar_slope_based_filter::LidarSlopeBasedFilter),
+        Box::new(tools::lidar_tile::LidarTile),
+        Box::new(tools::lidar_tophat_transform::LidarTophatTransform),
+        Box::new(tools::lidar_validate::LidarValidate),
+        Box::new(tools::percent_elev_range::PercentElevRange),
+        Box::new(tools::raster_reproject::RasterReproject),
+        Box::new(tools::relative_topographic_position::RelativeTopographicPosition),
+        Box::new(tools::remove_off_terrain_objects::RemoveOffTerrainObjects),
+    ]
+}
+
 #[derive(Default)]
 pub struct ToolManager {
     pub working_dir: String,
@@ -19,119 +85,266 @@ impl ToolManager {
         Ok(tm)
     }
 
-    pub fn run_tool(&self, tool_name: String, args: Vec<String>) -> Result<(), Error> {
-        // if !working_dir.is_empty() {
-        //     tool_args_vec.insert(0, format!("--wd={}", working_dir));
-        // }
-        match tool_name.to_lowercase().as_ref() {
-            "lidar_elevation_slice" => {
-                return tools::lidar_elevation_slice::run(args, &self.working_dir, self.verbose);
-            }
-            "lidar_flightline_overlap" => {
-                return tools::lidar_flightline_overlap::run(args, &self.working_dir, self.verbose);
-            },
-            "lidar_info" => {
-            return tools::lidar_info::run(args, &self.working_dir);
-            },
-            "lidar_join" => {
-                return tools::lidar_join::run(args, &self.working_dir, self.verbose);
-            },
-            "remove_off_terrain_objects" => {
-                return tools::remove_off_terrain_objects::run(args, &self.working_dir, self.verbose);
-            },
-            _ => Err(Error::new(ErrorKind::NotFound, format!("Unrecognized tool name {}.", tool_name))),
+    /// Returns the tool of a name, which is not case sensitive.
+    pub fn get_tool(&self, tool_name: &str) -> Result<Box<dyn WhiteboxTool>, Error> {
+        let name = tool_name.to_lowercase();
+        match get_tools().into_iter().find(|t| t.get_tool_name() == name) {
+            Some(tool) => Ok(tool),
+            None => Err(Error::new(ErrorKind::NotFound, format!("Unrecognized tool name {}.", tool_name))),
         }
     }
 
-    pub fn tool_help(&self, tool_name: String) -> Result<(), Error> {
-        let mut description = "".to_string();
-        let mut parameters = "".to_string();
-        let mut example = "".to_string();
-        let ret: Result<(), Error> = match tool_name.to_lowercase().as_ref() {
-        "lidar_elevation_slice" => {
-            description = tools::lidar_elevation_slice::get_tool_description();
-            parameters = tools::lidar_elevation_slice::get_tool_parameters();
-            if tools::lidar_elevation_slice::get_example_usage().is_some() {
-                example = tools::lidar_elevation_slice::get_example_usage().unwrap();
-            }
-            Ok(())
-        },
-        "lidar_flightline_overlap" => {
-            description = tools::lidar_flightline_overlap::get_tool_description();
-            parameters = tools::lidar_flightline_overlap::get_tool_parameters();
-            if tools::lidar_flightline_overlap::get_example_usage().is_some() {
-                example = tools::lidar_flightline_overlap::get_example_usage().unwrap();
-            }
-            Ok(())
-        },
-        "lidar_info" => {
-            description = tools::lidar_info::get_tool_description();
-            parameters = tools::lidar_info::get_tool_parameters();
-            if tools::lidar_info::get_example_usage().is_some() {
-                example = tools::lidar_info::get_example_usage().unwrap();
-            }
-            Ok(())
-        },
-        "lidar_join" => {
-            description = tools::lidar_join::get_tool_description();
-            parameters = tools::lidar_join::get_tool_parameters();
-            if tools::lidar_join::get_example_usage().is_some() {
-                example = tools::lidar_join::get_example_usage().unwrap();
-            }
-            Ok(())
-        },
-        "remove_off_terrain_objects" => {
-            description = tools::remove_off_terrain_objects::get_tool_description();
-            parameters = tools::remove_off_terrain_objects::get_tool_parameters();
-            if tools::remove_off_terrain_objects::get_example_usage().is_some() {
-                example = tools::remove_off_terrain_objects::get_example_usage().unwrap();
-            }
-            Ok(())
-        },
-        _ => Err(Error::new(ErrorKind::NotFound, format!("Unrecognized tool name {}.", tool_name))),
-        };
-        if example.len() <= 1 {
-            let s = format!("{} Help
-Description: {}
+    pub fn run_tool(&self, tool_name: String, args: Vec<String>) -> Result<(), Error> {
+        let tool = self.get_tool(&tool_name)?;
+        tool.run(args, &self.working_dir, self.verbose)
+    }
 
-Input parameters:
-{} \n\nNo example provided", tool_name, description, parameters);
+    pub fn tool_help(&self, tool_name: String) -> Result<(), Error> {
+        println!("{}", self.get_tool_help(&tool_name)?);
+        Ok(())
+    }
 
-            println!("{}", s);
-        } else {
-            let s = format!("{} Help
+    /// Returns the help of a tool, i.e. its description, parameters and example usage.
+    pub fn get_tool_help(&self, tool_name: &str) -> Result<String, Error> {
+        let tool = self.get_tool(tool_name)?;
+        let description = tool.get_tool_description();
+        let parameters = get_parameters_help(&tool.get_tool_parameters());
+        match tool.get_example_usage() {
+            Some(ref example) if example.len() > 1 => {
+                Ok(format!("{} Help
 Description: {}
 
 Input parameters:
 {}
 
 Example usage:
-{}", tool_name, description, parameters, example);
+{}", tool_name, description, parameters, example))
+            },
+            _ => {
+                Ok(format!("{} Help
+Description: {}
 
-            println!("{}", s);
+Input parameters:
+{} \n\nNo example provided", tool_name, description, parameters))
+            },
         }
-        ret
+    }
+
+    /// Prints a JSON description of a tool and its parameters.
+    pub fn tool_parameters(&self, tool_name: String) -> Result<(), Error> {
+        println!("{}", self.get_tool_parameters_json(&tool_name)?);
+        Ok(())
+    }
+
+    /// Returns a JSON description of a tool and its parameters.
+    pub fn get_tool_parameters_json(&self, tool_name: &str) -> Result<String, Error> {
+        let tool = self.get_tool(tool_name)?;
+        Ok(get_tool_json(&*tool))
     }
 
     pub fn list_tools(&self) {
-        let mut tool_names = Vec::new();
-        let mut tool_descriptions = Vec::new();
-        tool_names.push(tools::lidar_elevation_slice::get_tool_name());
-        tool_descriptions.push(tools::lidar_elevation_slice::get_tool_description());
-        tool_names.push(tools::lidar_flightline_overlap::get_tool_name());
-        tool_descriptions.push(tools::lidar_flightline_overlap::get_tool_description());
-        tool_names.push(tools::lidar_info::get_tool_name());
-        tool_descriptions.push(tools::lidar_info::get_tool_description());
-        tool_names.push(tools::lidar_join::get_tool_name());
-        tool_descriptions.push(tools::lidar_join::get_tool_description());
-        tool_names.push(tools::remove_off_terrain_objects::get_tool_name());
-        tool_descriptions.push(tools::remove_off_terrain_objects::get_tool_description());
-
-        let mut ret = format!("All {} Available Tools:\n", tool_names.len());
-        for i in 0..tool_names.len() {
-            ret.push_str(&format!("{}: {}\n\n", tool_names[i], tool_descriptions[i]));
+        let tools = get_tools();
+        let mut ret = format!("All {} Available Tools:\n", tools.len());
+        for tool in &tools {
+            ret.push_str(&format!("{}: {}\n\n", tool.get_tool_name(), tool.get_tool_description()));
         }
 
         println!("{}", ret);
     }
+
+    /// Prints a JSON description of all of the tools and their parameters.
+    pub fn list_tools_json(&self) {
+        println!("{}", self.get_tools_json());
+    }
+
+    /// Returns a JSON description of all of the tools and their parameters.
+    pub fn get_tools_json(&self) -> String {
+        let tools: Vec<String> = get_tools().iter().map(|t| get_tool_json(&**t)).collect();
+        format!("{{\n\"tools\": [\n{}\n]\n}}", tools.join(",\n"))
+    }
+}
+
+/// Returns a JSON object describing a tool, which is generated from its definition.
+fn get_tool_json(tool: &dyn WhiteboxTool) -> String {
+    let example = match tool.get_example_usage() {
+        Some(ref example) => json_string(example),
+        None => "null".to_string(),
+    };
+    format!("{{
+  \"name\": {},
+  \"description\": {},
+  \"example_usage\": {},
+  \"parameters\": {}
+}}", json_string(&tool.get_tool_name()), json_string(&tool.get_tool_description()), example, get_parameters_json(&tool.get_tool_parameters()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::iter::Peekable;
+    use std::str::Chars;
+    use super::*;
+
+    /// Checks that a string is a single JSON value, as the crate has no JSON parser.
+    fn is_valid_json(s: &str) -> bool {
+        let mut chars = s.chars().peekable();
+        let valid = parse_value(&mut chars);
+        skip_whitespace(&mut chars);
+        valid && chars.next().is_none()
+    }
+
+    fn skip_whitespace(chars: &mut Peekable<Chars>) {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+    }
+
+    fn parse_value(chars: &mut Peekable<Chars>) -> bool {
+        skip_whitespace(chars);
+        match chars.peek().cloned() {
+            Some('{') => parse_sequence(chars, '}', true),
+            Some('[') => parse_sequence(chars, ']', false),
+            Some('"') => parse_string(chars),
+            Some(c) if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while chars.peek().is_some_and(|c| "+-.eE".contains(*c) || c.is_ascii_digit()) {
+                    number.push(chars.next().unwrap());
+                }
+                number.parse::<f64>().is_ok()
+            },
+            Some(_) => {
+                let mut word = String::new();
+                while chars.peek().is_some_and(|c| c.is_alphabetic()) {
+                    word.push(chars.next().unwrap());
+                }
+                word == "true" || word == "false" || word == "null"
+            },
+            None => false,
+        }
+    }
+
+    fn parse_string(chars: &mut Peekable<Chars>) -> bool {
+        chars.next();
+        loop {
+            match chars.next() {
+                Some('"') => return true,
+                Some('\\') => match chars.next() {
+                    Some('u') => for _ in 0..4 {
+                        if !chars.next().is_some_and(|c| c.is_ascii_hexdigit()) {
+                            return false;
+                        }
+                    },
+                    Some(c) if "\"\\/bfnrt".contains(c) => {},
+                    _ => return false,
+                },
+                Some(c) if (c as u32) < 0x20 => return false,
+                Some(_) => {},
+                None => return false,
+            }
+        }
+    }
+
+    /// Parses an object, whose members are strings followed by a colon and a value, or an array.
+    fn parse_sequence(chars: &mut Peekable<Chars>, end: char, is_object: bool) -> bool {
+        chars.next();
+        skip_whitespace(chars);
+        if chars.peek() == Some(&end) {
+            chars.next();
+            return true;
+        }
+        loop {
+            if is_object {
+                skip_whitespace(chars);
+                if chars.peek() != Some(&'"') || !parse_string(chars) {
+                    return false;
+                }
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return false;
+                }
+            }
+            if !parse_value(chars) {
+                return false;
+            }
+            skip_whitespace(chars);
+            match chars.next() {
+                Some(',') => {},
+                Some(c) if c == end => return true,
+                _ => return false,
+            }
+        }
+    }
+
+    #[test]
+    fn json_checker() {
+        assert!(is_valid_json("{\"a\": [1, -2.5e3, \"x\\n\\u00e9\"], \"b\": {}, \"c\": [true, null]}"));
+        assert!(!is_valid_json("{\"a\": [1, 2}"));
+        assert!(!is_valid_json("{\"a\" 1}"));
+        assert!(!is_valid_json("[1, 2,]"));
+        assert!(!is_valid_json("\"a\nb\""));
+        assert!(!is_valid_json("{} {}"));
+    }
+
+    #[test]
+    fn tool_metadata_is_valid_json() {
+        let tools: Vec<String> = get_tools().iter().map(|t| get_tool_json(&**t)).collect();
+        assert!(is_valid_json(&format!("{{\n\"tools\": [\n{}\n]\n}}", tools.join(",\n"))));
+        for tool in get_tools() {
+            let name = tool.get_tool_name();
+            let json = get_tool_json(&*tool);
+            assert!(is_valid_json(&json), "{}", json);
+            assert!(json.starts_with(&format!("{{\n  \"name\": \"{}\",", name)));
+            assert_eq!(json.matches("\"flags\": ").count(), tool.get_tool_parameters().len(), "{}", name);
+        }
+        let tm = ToolManager::default();
+        assert_eq!(tm.tool_parameters("no_such_tool".to_string()).unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn tools_are_registered_once_in_order() {
+        let names: Vec<String> = get_tools().iter().map(|t| t.get_tool_name()).collect();
+        let mut sorted = names.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(names, sorted);
+        for name in &names {
+            assert!(name.chars().all(|c| c.is_lowercase() || c.is_ascii_digit() || c == '_'), "{}", name);
+        }
+    }
+
+    #[test]
+    fn tools_are_found_by_name() {
+        let tm = ToolManager::default();
+        assert_eq!(tm.get_tool("elev_percentile").unwrap().get_tool_name(), "elev_percentile");
+        assert_eq!(tm.get_tool("Elev_Percentile").unwrap().get_tool_name(), "elev_percentile");
+        assert_eq!(tm.get_tool("no_such_tool").err().unwrap().kind(), ErrorKind::NotFound);
+        assert_eq!(tm.run_tool("no_such_tool".to_string(), vec![]).unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn parameters_and_examples_use_the_flags_of_the_tool() {
+        for tool in get_tools() {
+            let name = tool.get_tool_name();
+            let parameters = tool.get_tool_parameters();
+            let mut flags: Vec<String> = parameters.iter().flat_map(|p| p.flags.clone()).collect();
+            assert!(flags.iter().all(|f| f.starts_with('-')), "{}", name);
+            let num_flags = flags.len();
+            flags.sort();
+            flags.dedup();
+            assert_eq!(flags.len(), num_flags, "{} has a flag that is used twice", name);
+
+            let example = tool.get_example_usage().unwrap_or_default();
+            for line in example.lines() {
+                assert!(line.contains(&format!("-r={}", name)), "{}: {}", name, line);
+                if let Some(start) = line.find("--args=\"") {
+                    let args = &line[start + 8..];
+                    let args = &args[..args.find('"').unwrap_or(args.len())];
+                    // the values of some arguments are lists, which may contain spaces
+                    for arg in args.split_whitespace().filter(|a| a.starts_with('-')) {
+                        let flag = arg.split('=').next().unwrap();
+                        assert!(flags.iter().any(|f| f == flag), "{}: the example uses {}", name, flag);
+                    }
+                }
+            }
+        }
+    }
 }
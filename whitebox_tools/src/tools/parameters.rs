@@ -0,0 +1,615 @@
+use std::io::{Error, ErrorKind};
+use std::path;
+use std::path::Path;
+
+/// The type of the value of a tool parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterType {
+    /// A flag, which is either given or not, and takes no value.
+    Boolean,
+    String,
+    Integer,
+    Float,
+    /// A file that is read, e.g. an input raster.
+    ExistingFile,
+    /// A file that is created, e.g. an output raster.
+    NewFile,
+    /// Several files that are read, separated by commas or semicolons.
+    FileList,
+    /// One of a fixed set of values, e.g. the name of a resampling method.
+    OptionList(Vec<String>),
+}
+
+impl ParameterType {
+    /// Returns the name of the type, as it is given in the JSON description of a parameter.
+    pub fn get_name(&self) -> &'static str {
+        match *self {
+            ParameterType::Boolean => "boolean",
+            ParameterType::String => "string",
+            ParameterType::Integer => "integer",
+            ParameterType::Float => "float",
+            ParameterType::ExistingFile => "existing_file",
+            ParameterType::NewFile => "new_file",
+            ParameterType::FileList => "file_list",
+            ParameterType::OptionList(_) => "option_list",
+        }
+    }
+}
+
+/// The kind of file given for a file parameter, which determines the file extensions that a
+/// front-end offers when a file is chosen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterFileType {
+    Any,
+    Lidar,
+    Raster,
+    Csv,
+    Html,
+    Json,
+}
+
+impl ParameterFileType {
+    pub fn get_name(&self) -> &'static str {
+        match *self {
+            ParameterFileType::Any => "any",
+            ParameterFileType::Lidar => "lidar",
+            ParameterFileType::Raster => "raster",
+            ParameterFileType::Csv => "csv",
+            ParameterFileType::Html => "html",
+            ParameterFileType::Json => "json",
+        }
+    }
+
+    /// Returns the extensions of the files of the type; any file is accepted if there are none.
+    pub fn get_extensions(&self) -> &'static [&'static str] {
+        match *self {
+            ParameterFileType::Any => &[],
+            ParameterFileType::Lidar => &["las", "laz"],
+            ParameterFileType::Raster => &["dep", "tif", "tiff", "flt", "img", "bil", "bip", "bsq", "rst", "sdat", "grd", "asc"],
+            ParameterFileType::Csv => &["csv"],
+            ParameterFileType::Html => &["html"],
+            ParameterFileType::Json => &["json"],
+        }
+    }
+}
+
+/// A parameter of a tool, i.e. an argument that may be given when it is run.
+#[derive(Debug, Clone)]
+pub struct ToolParameter {
+    /// A short name, e.g. "Input File".
+    pub name: String,
+    /// The flags by which the argument is given, e.g. "-i" and "--input".
+    pub flags: Vec<String>,
+    pub description: String,
+    pub parameter_type: ParameterType,
+    /// The value taken when the argument is not given, if any.
+    pub default_value: Option<String>,
+    pub optional: bool,
+    /// The smallest value allowed for a numeric parameter.
+    pub min_value: Option<f64>,
+    /// Whether values must be greater than the minimum, rather than at least the minimum.
+    pub min_exclusive: bool,
+    /// The largest value allowed for a numeric parameter.
+    pub max_value: Option<f64>,
+    /// The kind of file given for a file parameter.
+    pub file_type: ParameterFileType,
+}
+
+impl ToolParameter {
+    /// Returns a parameter that must be given when the tool is run.
+    pub fn required(name: &str, flags: &[&str], parameter_type: ParameterType, description: &str) -> ToolParameter {
+        ToolParameter {
+            name: name.to_string(),
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+            description: description.to_string(),
+            parameter_type,
+            default_value: None,
+            optional: false,
+            min_value: None,
+            min_exclusive: false,
+            max_value: None,
+            file_type: ParameterFileType::Any,
+        }
+    }
+
+    /// Returns a parameter that may be omitted, in which case it takes its default value.
+    pub fn optional(name: &str, flags: &[&str], parameter_type: ParameterType, default_value: Option<&str>, description: &str) -> ToolParameter {
+        ToolParameter {
+            name: name.to_string(),
+            flags: flags.iter().map(|f| f.to_string()).collect(),
+            description: description.to_string(),
+            parameter_type,
+            default_value: default_value.map(|d| d.to_string()),
+            optional: true,
+            min_value: None,
+            min_exclusive: false,
+            max_value: None,
+            file_type: ParameterFileType::Any,
+        }
+    }
+
+    /// Returns the parameter, with the values of a numeric parameter limited to a range.
+    pub fn with_range(mut self, min_value: f64, max_value: f64) -> ToolParameter {
+        self.min_value = Some(min_value);
+        self.min_exclusive = false;
+        self.max_value = Some(max_value);
+        self
+    }
+
+    /// Returns the parameter, with the values of a numeric parameter limited to a minimum.
+    pub fn with_min_value(mut self, min_value: f64) -> ToolParameter {
+        self.min_value = Some(min_value);
+        self.min_exclusive = false;
+        self
+    }
+
+    /// Returns the parameter, with the values of a numeric parameter limited to those greater
+    /// than a minimum, e.g. 0 for a width.
+    pub fn with_min_value_exclusive(mut self, min_value: f64) -> ToolParameter {
+        self.min_value = Some(min_value);
+        self.min_exclusive = true;
+        self
+    }
+
+    /// Returns the parameter, with the kind of file that is given for it.
+    pub fn with_file_type(mut self, file_type: ParameterFileType) -> ToolParameter {
+        self.file_type = file_type;
+        self
+    }
+
+    /// Returns true if the parameter is given by a flag, which may be written with one or two
+    /// leading dashes, e.g. "-input" and "--input" are the same flag.
+    pub fn has_flag(&self, flag: &str) -> bool {
+        let flag = flag.trim_matches('-').to_lowercase();
+        self.flags.iter().any(|f| f.trim_matches('-').to_lowercase() == flag)
+    }
+
+    /// Returns the flag by which the parameter is referred to in messages, i.e. its long form.
+    pub fn get_flag(&self) -> &str {
+        match self.flags.last() {
+            Some(f) => f,
+            None => &self.name,
+        }
+    }
+
+    /// Checks a value given for the parameter and returns it as it is stored, i.e. with file
+    /// names that are not paths placed in the working directory.
+    fn validate(&self, value: &str, working_directory: &str) -> Result<String, Error> {
+        let flag = self.get_flag();
+        match self.parameter_type {
+            ParameterType::Boolean => {
+                match value.to_lowercase().as_ref() {
+                    "true" | "t" | "yes" | "1" => Ok("true".to_string()),
+                    "false" | "f" | "no" | "0" => Ok("false".to_string()),
+                    _ => Err(Error::new(ErrorKind::InvalidInput,
+                        format!("The value '{}' given for {} is not true or false.", value, flag))),
+                }
+            },
+            ParameterType::String => Ok(value.to_string()),
+            ParameterType::Integer | ParameterType::Float => {
+                let number = if self.parameter_type == ParameterType::Integer {
+                    match value.parse::<i64>() {
+                        Ok(v) => v as f64,
+                        Err(_) => return Err(Error::new(ErrorKind::InvalidInput,
+                            format!("The value '{}' given for {} is not an integer.", value, flag))),
+                    }
+                } else {
+                    match value.parse::<f64>() {
+                        Ok(v) => v,
+                        Err(_) => return Err(Error::new(ErrorKind::InvalidInput,
+                            format!("The value '{}' given for {} is not a number.", value, flag))),
+                    }
+                };
+                if let Some(min) = self.min_value {
+                    if self.min_exclusive && number <= min {
+                        return Err(Error::new(ErrorKind::InvalidInput,
+                            format!("The value {} given for {} is not greater than {}.", value, flag, min)));
+                    }
+                    if number < min {
+                        return Err(Error::new(ErrorKind::InvalidInput,
+                            format!("The value {} given for {} is less than the minimum of {}.", value, flag, min)));
+                    }
+                }
+                if let Some(max) = self.max_value {
+                    if number > max {
+                        return Err(Error::new(ErrorKind::InvalidInput,
+                            format!("The value {} given for {} is greater than the maximum of {}.", value, flag, max)));
+                    }
+                }
+                Ok(value.to_string())
+            },
+            ParameterType::ExistingFile => {
+                let file_name = get_file_path(value, working_directory);
+                if !Path::new(&file_name).is_file() {
+                    return Err(Error::new(ErrorKind::NotFound,
+                        format!("The file {} given for {} does not exist.", file_name, flag)));
+                }
+                Ok(file_name)
+            },
+            ParameterType::NewFile => Ok(get_file_path(value, working_directory)),
+            ParameterType::FileList => {
+                let mut file_names = vec![];
+                for f in value.split([',', ';']).map(|f| f.trim()).filter(|f| !f.is_empty()) {
+                    let file_name = get_file_path(f, working_directory);
+                    if !Path::new(&file_name).is_file() {
+                        return Err(Error::new(ErrorKind::NotFound,
+                            format!("The file {} given for {} does not exist.", file_name, flag)));
+                    }
+                    file_names.push(file_name);
+                }
+                if file_names.is_empty() {
+                    return Err(Error::new(ErrorKind::InvalidInput, format!("No files were given for {}.", flag)));
+                }
+                Ok(file_names.join(","))
+            },
+            ParameterType::OptionList(ref options) => {
+                match options.iter().find(|o| o.to_lowercase() == value.to_lowercase()) {
+                    Some(o) => Ok(o.clone()),
+                    None => Err(Error::new(ErrorKind::InvalidInput,
+                        format!("The value '{}' given for {} is not one of {}.", value, flag, options.join(", ")))),
+                }
+            },
+        }
+    }
+}
+
+/// Returns the description of a tool's parameters printed by its help, one line per
+/// parameter.
+pub fn get_parameters_help(parameters: &[ToolParameter]) -> String {
+    let mut s = String::new();
+    for p in parameters {
+        let mut line = format!("{:<18} {}", p.flags.join(", "), p.description);
+        if let ParameterType::OptionList(ref options) = p.parameter_type {
+            line.push_str(&format!(" One of {}.", options.join(", ")));
+        }
+        match (p.min_value, p.max_value) {
+            (Some(min), None) if p.min_exclusive => line.push_str(&format!(" Must be greater than {}.", min)),
+            (Some(min), Some(max)) => line.push_str(&format!(" Ranges from {} to {}.", min, max)),
+            (Some(min), None) => line.push_str(&format!(" Minimum is {}.", min)),
+            (None, Some(max)) => line.push_str(&format!(" Maximum is {}.", max)),
+            (None, None) => {},
+        }
+        if let Some(ref default_value) = p.default_value {
+            line.push_str(&format!(" Default is {}.", default_value));
+        } else if p.optional {
+            line.push_str(" Optional.");
+        }
+        s.push_str(line.trim_end());
+        s.push('\n');
+    }
+    s.trim_end().to_string()
+}
+
+/// Returns a JSON array describing the parameters, for front-ends that build their own
+/// interfaces to the tools.
+pub fn get_parameters_json(parameters: &[ToolParameter]) -> String {
+    let mut s = "[".to_string();
+    for (i, p) in parameters.iter().enumerate() {
+        s.push_str(if i == 0 { "\n" } else { ",\n" });
+        s.push_str("    {\n");
+        s.push_str(&format!("      \"name\": {},\n", json_string(&p.name)));
+        let flags: Vec<String> = p.flags.iter().map(|f| json_string(f)).collect();
+        s.push_str(&format!("      \"flags\": [{}],\n", flags.join(", ")));
+        s.push_str(&format!("      \"description\": {},\n", json_string(&p.description)));
+        s.push_str(&format!("      \"type\": \"{}\",\n", p.parameter_type.get_name()));
+        if let ParameterType::OptionList(ref options) = p.parameter_type {
+            let options: Vec<String> = options.iter().map(|o| json_string(o)).collect();
+            s.push_str(&format!("      \"options\": [{}],\n", options.join(", ")));
+        }
+        match p.parameter_type {
+            ParameterType::ExistingFile | ParameterType::NewFile | ParameterType::FileList => {
+                let extensions: Vec<String> = p.file_type.get_extensions().iter().map(|e| json_string(e)).collect();
+                s.push_str(&format!("      \"file_type\": \"{}\",\n", p.file_type.get_name()));
+                s.push_str(&format!("      \"file_extensions\": [{}],\n", extensions.join(", ")));
+            },
+            _ => {},
+        }
+        if let Some(min) = p.min_value {
+            s.push_str(&format!("      \"min_value\": {},\n", min));
+            if p.min_exclusive {
+                s.push_str("      \"min_exclusive\": true,\n");
+            }
+        }
+        if let Some(max) = p.max_value {
+            s.push_str(&format!("      \"max_value\": {},\n", max));
+        }
+        s.push_str(&format!("      \"optional\": {},\n", p.optional));
+        match p.default_value {
+            Some(ref d) => s.push_str(&format!("      \"default_value\": {}\n", json_string(d))),
+            None => s.push_str("      \"default_value\": null\n"),
+        }
+        s.push_str("    }");
+    }
+    s.push_str(if parameters.is_empty() { "]" } else { "\n  ]" });
+    s
+}
+
+/// Quotes a string for JSON, escaping quotes, backslashes and control characters.
+pub fn json_string(value: &str) -> String {
+    let mut s = String::with_capacity(value.len() + 2);
+    s.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => s.push_str("\\\""),
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\r' => s.push_str("\\r"),
+            '\t' => s.push_str("\\t"),
+            c if (c as u32) < 0x20 => s.push_str(&format!("\\u{:04x}", c as u32)),
+            c => s.push(c),
+        }
+    }
+    s.push('"');
+    s
+}
+
+/// The arguments that a tool was run with, checked against its parameters.
+#[derive(Debug, Clone)]
+pub struct ToolArgs {
+    parameters: Vec<ToolParameter>,
+    /// The value of each parameter, in the order of the parameters; a parameter that was not
+    /// given takes its default value, if it has one.
+    values: Vec<Option<String>>,
+    given: Vec<bool>,
+}
+
+/// Parses the arguments that a tool is run with, e.g. `-i=input.las`, `--input input.las`,
+/// `--dist=-2.5` or `--vlr`. An error is returned for an unrecognized flag, a flag that is
+/// missing its value, a value that is not of the parameter's type or is out of its range,
+/// an input file that does not exist, and a required parameter that is not given. File names
+/// that are not paths are placed in the working directory.
+pub fn parse_tool_args(parameters: &[ToolParameter], args: &[String], working_directory: &str) -> Result<ToolArgs, Error> {
+    let mut values: Vec<Option<String>> = vec![None; parameters.len()];
+    let mut given = vec![false; parameters.len()];
+    let args: Vec<String> = args.iter()
+                                .map(|a| strip_quotes(a).to_string())
+                                .filter(|a| !a.is_empty())
+                                .collect();
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if !arg.starts_with('-') || arg.parse::<f64>().is_ok() {
+            return Err(Error::new(ErrorKind::InvalidInput,
+                format!("Unexpected argument '{}'. Please see help (-h) for parameter descriptions.", arg)));
+        }
+        // the value follows an equals sign, a space or the next argument
+        let (flag, mut value) = match arg.find(|c: char| c == '=' || c.is_whitespace()) {
+            Some(n) => (arg[..n].to_string(), Some(strip_quotes(&arg[n+1..]).to_string())),
+            None => (arg.clone(), None),
+        };
+        let index = match parameters.iter().position(|p| p.has_flag(&flag)) {
+            Some(n) => n,
+            None => return Err(Error::new(ErrorKind::InvalidInput,
+                format!("Unrecognized parameter {}. Please see help (-h) for parameter descriptions.", flag))),
+        };
+        let p = &parameters[index];
+        if value.is_none() {
+            if p.parameter_type == ParameterType::Boolean {
+                value = Some("true".to_string());
+            } else if i + 1 < args.len() && (!args[i+1].starts_with('-') || args[i+1].parse::<f64>().is_ok()) {
+                i += 1;
+                value = Some(args[i].clone());
+            } else {
+                return Err(Error::new(ErrorKind::InvalidInput,
+                    format!("No value was given for {} ({}).", p.get_flag(), p.name)));
+            }
+        }
+        values[index] = Some(p.validate(&value.unwrap(), working_directory)?);
+        given[index] = true;
+        i += 1;
+    }
+
+    for (index, p) in parameters.iter().enumerate() {
+        if !given[index] {
+            if !p.optional {
+                if args.is_empty() {
+                    return Err(Error::new(ErrorKind::InvalidInput,
+                        "Tool run with no parameters. Please see help (-h) for parameter descriptions."));
+                }
+                return Err(Error::new(ErrorKind::InvalidInput,
+                    format!("The {} parameter ({}) is required.", p.get_flag(), p.name)));
+            }
+            if let Some(ref default_value) = p.default_value {
+                values[index] = Some(p.validate(default_value, working_directory)?);
+            }
+        }
+    }
+
+    Ok(ToolArgs { parameters: parameters.to_vec(), values, given })
+}
+
+impl ToolArgs {
+    fn get_index(&self, flag: &str) -> Result<usize, Error> {
+        match self.parameters.iter().position(|p| p.has_flag(flag)) {
+            Some(n) => Ok(n),
+            None => Err(Error::new(ErrorKind::InvalidInput, format!("Unrecognized parameter {}.", flag))),
+        }
+    }
+
+    fn get_value(&self, flag: &str) -> Result<String, Error> {
+        let index = self.get_index(flag)?;
+        match self.values[index] {
+            Some(ref v) => Ok(v.clone()),
+            None => Err(Error::new(ErrorKind::InvalidInput,
+                format!("No value was given for {}.", self.parameters[index].get_flag()))),
+        }
+    }
+
+    /// Returns true if the argument was given, rather than taking its default value.
+    pub fn is_given(&self, flag: &str) -> bool {
+        match self.get_index(flag) {
+            Ok(index) => self.given[index],
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the value of a parameter as it was given, or, for a file, its path.
+    pub fn get_string(&self, flag: &str) -> Result<String, Error> {
+        self.get_value(flag)
+    }
+
+    pub fn get_integer(&self, flag: &str) -> Result<i64, Error> {
+        match self.get_value(flag)?.parse::<i64>() {
+            Ok(v) => Ok(v),
+            Err(_) => Err(Error::new(ErrorKind::InvalidInput, format!("The value of {} is not an integer.", flag))),
+        }
+    }
+
+    pub fn get_float(&self, flag: &str) -> Result<f64, Error> {
+        match self.get_value(flag)?.parse::<f64>() {
+            Ok(v) => Ok(v),
+            Err(_) => Err(Error::new(ErrorKind::InvalidInput, format!("The value of {} is not a number.", flag))),
+        }
+    }
+
+    /// Returns the value of a flag, which is false if it was not given.
+    pub fn get_bool(&self, flag: &str) -> bool {
+        match self.get_value(flag) {
+            Ok(v) => v == "true",
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the files of a file list.
+    pub fn get_list(&self, flag: &str) -> Result<Vec<String>, Error> {
+        Ok(self.get_value(flag)?.split(',').map(|s| s.to_string()).collect())
+    }
+}
+
+/// Returns the path of a file, which is in the working directory if the file name is not a path.
+/// Removes the whitespace around an argument and one pair of matching quotes that encloses
+/// it. Quotes within the argument, e.g. the apostrophe of a file name, are kept.
+fn strip_quotes(arg: &str) -> &str {
+    let arg = arg.trim();
+    let quoted = arg.len() > 1 && (arg.starts_with('"') && arg.ends_with('"') || arg.starts_with('\'') && arg.ends_with('\''));
+    if quoted { arg[1..arg.len()-1].trim() } else { arg }
+}
+
+fn get_file_path(file_name: &str, working_directory: &str) -> String {
+    let sep = path::MAIN_SEPARATOR;
+    if file_name.contains(sep) || working_directory.is_empty() {
+        return file_name.to_string();
+    }
+    if working_directory.ends_with(sep) {
+        format!("{}{}", working_directory, file_name)
+    } else {
+        format!("{}{}{}", working_directory, sep, file_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::path;
+    use super::*;
+
+    fn parameters() -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input file."),
+            ToolParameter::optional("Output File", &["-o", "--output"], ParameterType::NewFile, None, "Output file."),
+            ToolParameter::optional("Distance", &["--dist"], ParameterType::Float, Some("2.0"), "Distance.").with_min_value(-10.0),
+            ToolParameter::optional("Width", &["--width"], ParameterType::Float, Some("1.0"), "Width.").with_min_value_exclusive(0.0),
+            ToolParameter::optional("Class", &["--class"], ParameterType::Integer, Some("2"), "Class.").with_range(0.0, 255.0),
+            ToolParameter::optional("Method", &["--method"], ParameterType::OptionList(vec!["Nearest".to_string(), "Bilinear".to_string()]), Some("Nearest"), "Method."),
+            ToolParameter::optional("Verbose Header", &["--vlr"], ParameterType::Boolean, None, "Flag."),
+        ]
+    }
+
+    /// Parses arguments given as one string, with the crate's manifest as the input file.
+    fn parse(args: &str) -> Result<ToolArgs, Error> {
+        let working_directory = env!("CARGO_MANIFEST_DIR");
+        let mut all_args = vec!["-i=Cargo.toml".to_string()];
+        all_args.extend(args.split_whitespace().map(|a| a.to_string()));
+        parse_tool_args(&parameters(), &all_args, working_directory)
+    }
+
+    fn parse_error(args: &str) -> Error {
+        match parse(args) {
+            Ok(_) => panic!("'{}' was parsed without error", args),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn values_and_defaults() {
+        let args = parse("--dist -2.5 --class=7 --method=bilinear --vlr -o out.las").unwrap();
+        assert_eq!(args.get_float("--dist").unwrap(), -2.5);
+        assert_eq!(args.get_integer("--class").unwrap(), 7);
+        assert_eq!(args.get_string("--method").unwrap(), "Bilinear");
+        assert!(args.get_bool("--vlr"));
+        assert!(args.get_string("--output").unwrap().ends_with(&format!("{}out.las", path::MAIN_SEPARATOR)));
+        assert!(args.is_given("--class"));
+
+        let args = parse("").unwrap();
+        assert_eq!(args.get_float("--width").unwrap(), 1.0);
+        assert!(!args.is_given("--width"));
+        assert!(!args.get_bool("--vlr"));
+        assert!(args.get_string("--output").is_err());
+    }
+
+    #[test]
+    fn invalid_arguments_are_errors() {
+        assert_eq!(parse_error("--unknown=1").kind(), ErrorKind::InvalidInput);
+        assert_eq!(parse_error("--dist").kind(), ErrorKind::InvalidInput);
+        assert_eq!(parse_error("--dist=far").kind(), ErrorKind::InvalidInput);
+        assert_eq!(parse_error("--dist=-11").kind(), ErrorKind::InvalidInput);
+        assert_eq!(parse_error("--class=2.5").kind(), ErrorKind::InvalidInput);
+        assert_eq!(parse_error("--class=256").kind(), ErrorKind::InvalidInput);
+        assert_eq!(parse_error("--method=cubic").kind(), ErrorKind::InvalidInput);
+        assert_eq!(parse_error("stray").kind(), ErrorKind::InvalidInput);
+
+        let missing = parse_tool_args(&parameters(), &["--dist=1".to_string()], "").unwrap_err();
+        assert_eq!(missing.kind(), ErrorKind::InvalidInput);
+        let not_found = parse_tool_args(&parameters(), &["-i=whitebox_parameters_missing.las".to_string()], "").unwrap_err();
+        assert_eq!(not_found.kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn surrounding_quotes_are_removed() {
+        let file_name = format!("{0}data{0}O'Brien{0}dem.tif", path::MAIN_SEPARATOR);
+        for arg in &[format!("-o={}", file_name), format!("--output=\"{}\"", file_name),
+                     format!("'-o={}'", file_name), format!("-o='{}'", file_name)] {
+            assert_eq!(parse(arg).unwrap().get_string("-o").unwrap(), file_name, "{}", arg);
+        }
+        let args = vec!["-i=Cargo.toml".to_string(), "-o".to_string(), format!("\"{}\"", file_name)];
+        let args = parse_tool_args(&parameters(), &args, env!("CARGO_MANIFEST_DIR")).unwrap();
+        assert_eq!(args.get_string("--output").unwrap(), file_name);
+        assert_eq!(parse("--method='bilinear'").unwrap().get_string("--method").unwrap(), "Bilinear");
+    }
+
+    #[test]
+    fn exclusive_minimum() {
+        assert_eq!(parse("--width=0.5").unwrap().get_float("--width").unwrap(), 0.5);
+        assert_eq!(parse_error("--width=0").kind(), ErrorKind::InvalidInput);
+        assert_eq!(parse_error("--width=-1").kind(), ErrorKind::InvalidInput);
+        assert!(parse("--class=0").is_ok());
+
+        let help = get_parameters_help(&parameters());
+        assert!(help.contains("Width. Must be greater than 0. Default is 1.0."));
+        assert!(help.contains("Distance. Minimum is -10. Default is 2.0."));
+        let json = get_parameters_json(&parameters());
+        assert_eq!(json.matches("\"min_exclusive\": true").count(), 1);
+    }
+
+    #[test]
+    fn json_strings_are_escaped() {
+        assert_eq!(json_string("plain"), "\"plain\"");
+        assert_eq!(json_string("a \"b\" \\ c\nd\te\u{1}"), "\"a \\\"b\\\" \\\\ c\\nd\\te\\u0001\"");
+        assert_eq!(json_string("élévation"), "\"élévation\"");
+    }
+
+    #[test]
+    fn json_describes_each_parameter() {
+        let mut parameters = parameters();
+        parameters[0] = parameters[0].clone().with_file_type(ParameterFileType::Lidar);
+        let json = get_parameters_json(&parameters);
+        assert_eq!(json.matches("\"name\": ").count(), parameters.len());
+        assert!(json.contains("\"flags\": [\"-i\", \"--input\"]"));
+        assert!(json.contains("\"file_type\": \"lidar\",\n      \"file_extensions\": [\"las\", \"laz\"]"));
+        assert!(json.contains("\"type\": \"option_list\",\n      \"options\": [\"Nearest\", \"Bilinear\"]"));
+        assert!(json.contains("\"min_value\": 0,\n      \"max_value\": 255,"));
+        assert!(json.contains("\"optional\": false,\n      \"default_value\": null"));
+        assert!(json.contains("\"default_value\": \"2.0\""));
+        assert_eq!(get_parameters_json(&[]), "[]");
+    }
+}
@@ -0,0 +1,38 @@
+use std::cell::RefCell;
+use std::io::{Error, ErrorKind};
+
+thread_local! {
+    static PROGRESS_HANDLER: RefCell<Option<Box<dyn FnMut(&str, usize) -> bool>>> = RefCell::new(None);
+}
+
+/// Sets the function that receives the progress of the tools that are run on the current
+/// thread, in place of it being printed, or removes it if `None`. The function is given a
+/// label for the step of the tool, e.g. "Performing analysis", and the percentage of the
+/// step that is complete, and returns false to cancel the tool.
+pub fn set_progress_handler(handler: Option<Box<dyn FnMut(&str, usize) -> bool>>) {
+    PROGRESS_HANDLER.with(|h| *h.borrow_mut() = handler);
+}
+
+/// Returns whether a tool reports its progress, i.e. if it is verbose or a handler is set on
+/// the current thread.
+pub fn progress_enabled(verbose: bool) -> bool {
+    verbose || PROGRESS_HANDLER.with(|h| h.borrow().is_some())
+}
+
+/// Reports the progress of a tool, which is printed unless a handler is set. An error of the
+/// kind `Interrupted` is returned if the handler cancels the tool.
+pub fn update_progress(label: &str, progress: usize) -> Result<(), Error> {
+    let cancelled = PROGRESS_HANDLER.with(|h| {
+        match *h.borrow_mut() {
+            Some(ref mut handler) => !handler(label, progress),
+            None => {
+                println!("{}: {}%", label, progress);
+                false
+            },
+        }
+    });
+    if cancelled {
+        return Err(Error::new(ErrorKind::Interrupted, "The tool was cancelled."));
+    }
+    Ok(())
+}
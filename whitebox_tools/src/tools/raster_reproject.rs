@@ -0,0 +1,267 @@
+extern crate time;
+
+use std::io::{Error, ErrorKind};
+use std::f64;
+use crate::raster::*;
+use crate::spatial_ref_system::SpatialReference;
+use crate::spatial_ref_system::transform::CoordinateTransform;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+const TOOL_NAME: &str = "raster_reproject";
+
+pub struct RasterReproject;
+
+impl WhiteboxTool for RasterReproject {
+    fn get_tool_name(&self) -> String {
+        TOOL_NAME.to_string()
+    }
+
+    fn get_tool_description(&self) -> String {
+        let s = "Resamples a raster into another coordinate reference system.";
+
+        s.to_string()
+    }
+
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::required("EPSG Code", &["--epsg"], ParameterType::Integer, "EPSG code of the output coordinate reference system.").with_range(1.0, 32767.0),
+            ToolParameter::optional("Source EPSG Code", &["--source_epsg"], ParameterType::Integer, None, "EPSG code of the input coordinate reference system, if the file does not specify it or specifies it incorrectly.").with_range(1.0, 32767.0),
+            ToolParameter::optional("Cell Size", &["--cell_size"], ParameterType::Float, None, "Output grid resolution, in the units of the output system; by default the number of cells is preserved.").with_min_value(0.0),
+            ToolParameter::optional("Resampling Method", &["--resample"], ParameterType::OptionList(vec!["nn".to_string(), "bilinear".to_string()]), Some("nn"), "Resampling method used to interpolate the output grid; 'nn' is nearest neighbour."),
+        ]
+    }
+
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=raster_reproject -v --wd=\"/dir/to/data\" --args=\"-i=DEM.dep -o=DEM_utm.dep --epsg=32617 --resample=bilinear\"
+./whitebox-tools -r=raster_reproject -v --wd=\"/dir/to/data\" --args=\"-i=DEM.dep -o=DEM_geo.dep --epsg=4326 --source_epsg=26917 --cell_size=0.0001\"";
+        Some(s.to_string())
+    }
+
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
+}
+
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let epsg_code = args.get_integer("--epsg")? as u16;
+    let source_epsg_code = args.get_integer("--source_epsg").unwrap_or(0) as u16;
+    let mut cell_size = args.get_float("--cell_size").unwrap_or(0f64);
+    let bilinear = args.get_string("--resample")? == "bilinear";
+    if verbose {
+        println!("***************{}", "*".repeat(TOOL_NAME.len()));
+        println!("* Welcome to {} *", TOOL_NAME);
+        println!("***************{}", "*".repeat(TOOL_NAME.len()));
+    }
+
+    if verbose { println!("Reading data...") };
+    let input = Raster::new(&input_file, "r")?;
+
+    let start = time::now();
+
+    let source = if source_epsg_code != 0 {
+        SpatialReference::from_epsg(source_epsg_code)
+    } else {
+        input.configs.get_spatial_reference()
+    };
+    if source.epsg_code == 0 {
+        return Err(Error::new(ErrorKind::InvalidInput,
+            "The coordinate reference system of the input file is unknown. Please specify its EPSG code (--source_epsg)."));
+    }
+    let target = SpatialReference::from_epsg(epsg_code);
+    let forward = CoordinateTransform::new(&source, &target)?;
+    let inverse = CoordinateTransform::new(&target, &source)?;
+    if verbose { println!("Transforming from {} to {}", source, target); }
+
+    // The output grid covers the transformed extent of the input. Unless a cell size is
+    // specified, it has about as many cells as the input.
+    let (west, south, east, north) = forward.transform_extent(input.configs.west, input.configs.south, input.configs.east, input.configs.north);
+    if cell_size <= 0f64 {
+        cell_size = ((east - west) * (north - south) / (input.configs.rows * input.configs.columns) as f64).sqrt();
+    }
+    // (the small tolerance keeps round-off from adding a row or column)
+    let rows = ((north - south) / cell_size - 0.000_001).ceil().max(1f64) as isize;
+    let columns = ((east - west) / cell_size - 0.000_001).ceil().max(1f64) as isize;
+
+    let mut configs = input.configs.clone();
+    configs.rows = rows as usize;
+    configs.columns = columns as usize;
+    configs.north = north;
+    configs.south = north - rows as f64 * cell_size;
+    configs.west = west;
+    configs.east = west + columns as f64 * cell_size;
+    configs.resolution_x = cell_size;
+    configs.resolution_y = cell_size;
+    configs.minimum = f64::INFINITY;
+    configs.maximum = f64::NEG_INFINITY;
+    configs.display_min = f64::INFINITY;
+    configs.display_max = f64::NEG_INFINITY;
+    configs.metadata = vec![];
+    configs.set_spatial_reference(&target);
+    configs.projection = target.name();
+    configs.xy_units = if target.is_geographic() { "degrees".to_string() } else { "metres".to_string() };
+    let mut output = Raster::initialize_using_config(&output_file, &configs);
+
+    let nodata = input.configs.nodata;
+    let mut progress: usize;
+    let mut old_progress: usize = 1;
+    for row in 0..rows {
+        let y = north - (row as f64 + 0.5) * cell_size;
+        for col in 0..columns {
+            let x = west + (col as f64 + 0.5) * cell_size;
+            let (src_x, src_y) = inverse.transform_xy(x, y);
+            let z = resample(&input, src_x, src_y, bilinear);
+            if z != nodata {
+                output.set_value(row, col, z);
+            }
+        }
+        if progress_enabled(verbose) {
+            progress = (100.0_f64 * (row + 1) as f64 / rows as f64) as usize;
+            if progress != old_progress {
+                update_progress("Progress", progress)?;
+                old_progress = progress;
+            }
+        }
+    }
+
+    let end = time::now();
+    let elapsed_time = end - start;
+    output.add_metadata_entry(format!("Created by whitebox_tools\' {} tool", TOOL_NAME));
+    output.add_metadata_entry(format!("Input file: {}", input_file));
+    output.add_metadata_entry(format!("Source coordinate reference system: EPSG:{}", source.epsg_code));
+    output.add_metadata_entry(format!("Resampling method: {}", if bilinear { "bilinear" } else { "nearest neighbour" }));
+    output.add_metadata_entry(format!("Elapsed Time (excluding I/O): {}", elapsed_time).replace("PT", ""));
+
+    if verbose { println!("Saving data...") };
+    match output.write() {
+        Ok(_) => if verbose { println!("Output file written") },
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(())
+}
+
+/// Returns the value of the input at a point in its coordinate system, or nodata if the
+/// point is outside of the input or could not be transformed, e.g. because it is beyond
+/// the area of use of a projection.
+fn resample(input: &Raster, x: f64, y: f64, bilinear: bool) -> f64 {
+    if !x.is_finite() || !y.is_finite() {
+        return input.configs.nodata;
+    }
+    // fractional grid position in the input, relative to the cell centres
+    let c = (x - input.configs.west) / input.configs.resolution_x - 0.5;
+    let r = (input.configs.north - y) / input.configs.resolution_y - 0.5;
+    if bilinear {
+        interpolate_bilinear(input, r, c)
+    } else {
+        input.get_value(r.round() as isize, c.round() as isize)
+    }
+}
+
+/// Interpolates the value at a fractional grid position from the four surrounding cells.
+/// Cells that are nodata or outside the grid are excluded and the weights of the others
+/// are renormalized.
+fn interpolate_bilinear(input: &Raster, r: f64, c: f64) -> f64 {
+    let nodata = input.configs.nodata;
+    let (r0, c0) = (r.floor(), c.floor());
+    let (dr, dc) = (r - r0, c - c0);
+    let (r0, c0) = (r0 as isize, c0 as isize);
+    let mut sum = 0f64;
+    let mut sum_weights = 0f64;
+    for &(row, col, w) in &[(r0, c0, (1f64 - dr) * (1f64 - dc)),
+                             (r0, c0 + 1, (1f64 - dr) * dc),
+                             (r0 + 1, c0, dr * (1f64 - dc)),
+                             (r0 + 1, c0 + 1, dr * dc)] {
+        let z = input.get_value(row, col);
+        if z != nodata && w > 0f64 {
+            sum += z * w;
+            sum_weights += w;
+        }
+    }
+    if sum_weights > 0f64 { sum / sum_weights } else { nodata }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64;
+    use std::fs;
+    use crate::io_utils::temp_file_name;
+    use crate::raster::*;
+    use crate::spatial_ref_system::SpatialReference;
+    use crate::spatial_ref_system::transform::CoordinateTransform;
+    use crate::tools::WhiteboxTool;
+    use super::{RasterReproject, resample};
+
+    /// A geographic raster of 0.01 degree cells near Toronto, whose cells are numbered.
+    fn geographic_raster(file_name: &str) -> Raster {
+        let mut configs = RasterConfigs::default();
+        configs.rows = 20;
+        configs.columns = 30;
+        configs.west = -79.5;
+        configs.east = -79.2;
+        configs.north = 43.8;
+        configs.south = 43.6;
+        configs.resolution_x = 0.01;
+        configs.resolution_y = 0.01;
+        configs.nodata = -9999.0;
+        configs.data_type = DataType::F64;
+        configs.photometric_interp = PhotometricInterpretation::Continuous;
+        configs.set_spatial_reference(&SpatialReference::from_epsg(4326));
+        let mut r = Raster::initialize_using_config(file_name, &configs);
+        for row in 0..20 {
+            for col in 0..30 {
+                r.set_value(row, col, (row * 30 + col) as f64);
+            }
+        }
+        r
+    }
+
+    #[test]
+    fn points_that_cannot_be_transformed_are_nodata() {
+        let input = geographic_raster("unused.tif");
+        assert_eq!(resample(&input, -79.455, 43.795, false), 4.0);
+        for &bilinear in &[false, true] {
+            assert_eq!(resample(&input, f64::NAN, 43.7, bilinear), -9999.0);
+            assert_eq!(resample(&input, -79.3, f64::INFINITY, bilinear), -9999.0);
+            assert_eq!(resample(&input, -78.0, 43.7, bilinear), -9999.0);
+        }
+    }
+
+    #[test]
+    fn nearest_neighbour_to_utm() {
+        let input_file = temp_file_name("geographic.tif");
+        let output_file = temp_file_name("utm.tif");
+        let input = geographic_raster(&input_file);
+        input.clone().write().unwrap();
+        let result = RasterReproject.run(vec![format!("-i={}", input_file), format!("-o={}", output_file), "--epsg=32617".to_string()], "", false);
+        let output = Raster::new(&output_file, "r");
+        let _ = fs::remove_file(&input_file);
+        let _ = fs::remove_file(&output_file);
+        result.unwrap();
+        let output = output.unwrap();
+
+        assert_eq!(output.configs.epsg_code, 32617);
+        let inverse = CoordinateTransform::new(&SpatialReference::from_epsg(32617), &SpatialReference::from_epsg(4326)).unwrap();
+        let res = output.configs.resolution_x;
+        let mut num_valid = 0;
+        for row in 0..output.configs.rows as isize {
+            for col in 0..output.configs.columns as isize {
+                let x = output.configs.west + (col as f64 + 0.5) * res;
+                let y = output.configs.north - (row as f64 + 0.5) * res;
+                let (lon, lat) = inverse.transform_xy(x, y);
+                let expected = resample(&input, lon, lat, false);
+                assert_eq!(output.get_value(row, col), expected, "cell ({}, {})", row, col);
+                if expected != -9999.0 {
+                    num_valid += 1;
+                }
+            }
+        }
+        assert!(num_valid > output.configs.rows * output.configs.columns / 2);
+    }
+}
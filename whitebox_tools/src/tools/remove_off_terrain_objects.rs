@@ -1,85 +1,59 @@
 extern crate time;
 
-use std::io::{Error, ErrorKind};
-use std::path;
+use std::io::Error;
 use std::f64;
 use std::collections::VecDeque;
-use raster::*;
-use structures::fixed_radius_search::FixedRadiusSearch;
-use structures::array2d::Array2D;
+use crate::raster::*;
+use crate::structures::fixed_radius_search::FixedRadiusSearch;
+use crate::structures::array2d::Array2D;
+use crate::tools::WhiteboxTool;
+use crate::tools::parameters::*;
+use crate::tools::progress::{progress_enabled, update_progress};
+
+pub struct RemoveOffTerrainObjects;
+
+impl WhiteboxTool for RemoveOffTerrainObjects {
+    fn get_tool_name(&self) -> String {
+        "remove_off_terrain_objects".to_string()
+    }
 
-pub fn get_tool_name() -> String {
-    return "remove_off_terrain_objects".to_string();
-}
+    fn get_tool_description(&self) -> String {
+        let s = "Removes off-terrain objects from a raster digital elevation model (DEM).";
 
-pub fn get_tool_description() -> String {
-    let s = "Removes off-terrain objects from a raster digital elevation model (DEM).";
+        s.to_string()
+    }
 
-    return s.to_string();
-}
+    fn get_tool_parameters(&self) -> Vec<ToolParameter> {
+        vec![
+            ToolParameter::required("Input File", &["-i", "--input"], ParameterType::ExistingFile, "Input raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::required("Output File", &["-o", "--output"], ParameterType::NewFile, "Output raster file.").with_file_type(ParameterFileType::Raster),
+            ToolParameter::optional("Filter Size", &["--filter"], ParameterType::Integer, Some("11"), "Filter size (cells).").with_min_value(1.0),
+            ToolParameter::optional("Slope Threshold", &["--slope"], ParameterType::Float, Some("15.0"), "Slope threshold, in degrees.").with_range(0.0, 90.0),
+        ]
+    }
 
-pub fn get_tool_parameters() -> String {
-    let s = "-i, --input        Input raster file.
--o, --output       Output raster file.
---filter           Filter size (cells); default is 11.
---slope            Slope threshold; default is 15.0.";
-    return s.to_string();
-}
+    fn get_example_usage(&self) -> Option<String> {
+        let s = "./whitebox-tools -r=remove_off_terrain_objects --wd=\"/dir/to/data\" --args=\"-i=DEM.dep -o=bare_earth_DEM.dep --filter=25 --slope=10.0\"";
+        Some(s.to_string())
+    }
 
-pub fn get_example_usage() -> Option<String> {
-    let s = "./whitebox-tools -r=remove_off_terrain_objects --wd=\"/dir/to/data\" --args=\"-i=DEM.dep -o=bare_earth_DEM.dep --filter=25 --slope=10.0\"";
-    return Some(s.to_string());
+    fn run(&self, args: Vec<String>, working_directory: &str, verbose: bool) -> Result<(), Error> {
+        let args = parse_tool_args(&self.get_tool_parameters(), &args, working_directory)?;
+        run(&args, verbose)
+    }
 }
 
-pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) -> Result<(), Error> {
-    let mut input_file = String::new();
-    let mut output_file = String::new();
-    let mut filter_size = 11usize;
-    let mut slope_threshold = 15f64;
-    let mut keyval: bool;
-    if args.len() == 0 {
-        return Err(Error::new(ErrorKind::InvalidInput, "Tool run with no paramters. Please see help (-h) for parameter descriptions."));
-    }
-    for i in 0..args.len() {
-        let mut arg = args[i].replace("\"", "");
-        arg = arg.replace("\'", "");
-        let cmd = arg.split("="); // in case an equals sign was used
-        let vec = cmd.collect::<Vec<&str>>();
-        keyval = false;
-        if vec.len() > 1 { keyval = true; }
-        if vec[0].to_lowercase() == "-i" || vec[0].to_lowercase() == "--input" {
-            if keyval {
-                input_file = vec[1].to_string();
-            } else {
-                input_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-o" || vec[0].to_lowercase() == "--output" {
-            if keyval {
-                output_file = vec[1].to_string();
-            } else {
-                output_file = args[i+1].to_string();
-            }
-        } else if vec[0].to_lowercase() == "-filter" || vec[0].to_lowercase() == "--filter" {
-            if keyval {
-                filter_size = vec[1].to_string().parse::<usize>().unwrap();
-            } else {
-                filter_size = args[i+1].to_string().parse::<usize>().unwrap();
-            }
-        } else if vec[0].to_lowercase() == "-slope" || vec[0].to_lowercase() == "--slope" {
-            if keyval {
-                slope_threshold = vec[1].to_string().parse::<f64>().unwrap();
-            } else {
-                slope_threshold = args[i+1].to_string().parse::<f64>().unwrap();
-            }
-        }
-    }
+fn run(args: &ToolArgs, verbose: bool) -> Result<(), Error> {
+    let input_file = args.get_string("--input")?;
+    let output_file = args.get_string("--output")?;
+    let mut filter_size = args.get_integer("--filter")? as usize;
+    let slope_threshold = args.get_float("--slope")?;
     if verbose {
         println!("*****************************************");
         println!("* Welcome to remove_off_terrain_objects *");
         println!("*****************************************");
     }
 
-    let sep: String = path::MAIN_SEPARATOR.to_string();
 
     // The filter dimensions must be odd numbers such that there is a middle pixel
     if (filter_size as f64 / 2f64).floor() == (filter_size as f64 / 2f64) {
@@ -93,13 +67,6 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     let mut progress: usize;
     let mut old_progress: usize = 1;
 
-    if !input_file.contains(&sep) {
-        input_file = format!("{}{}", working_directory, input_file);
-    }
-    if !output_file.contains(&sep) {
-        output_file = format!("{}{}", working_directory, output_file);
-    }
-
     if verbose { println!("Reading data...") };
     let input = Raster::new(&input_file, "r")?;
 
@@ -161,21 +128,17 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
                     for v in filter_vals.iter() {
                         if *v < min_val { min_val = *v; }
                     }
-                    if min_val < f64::INFINITY {
-                        erosion[(row, col)] = min_val;
-                    } else {
-                        erosion[(row, col)] = min_val;
-                    }
+                    erosion[(row, col)] = min_val;
                 } else {
                     erosion[(row, col)] = nodata;
                     opening[(row, col)] = nodata;
                     tophat[(row, col)] = nodata;
                 }
             }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
                 if progress != old_progress {
-                    println!("Performing erosion: {}%", progress);
+                    update_progress("Performing erosion", progress)?;
                     old_progress = progress;
                 }
             }
@@ -219,10 +182,10 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
                     }
                 }
             }
-            if verbose {
+            if progress_enabled(verbose) {
                 progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
                 if progress != old_progress {
-                    println!("Performing dilation: {}%", progress);
+                    update_progress("Performing dilation", progress)?;
                     old_progress = progress;
                 }
             }
@@ -241,24 +204,24 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
             out[(row, col)] = initial_value;
             if tophat[(row, col)] != nodata {
                 if tophat[(row, col)] <= height_diff_threshold[1] { // == 0f64 {
-                    stack.push(GridCell { row: row, column: col });
+                    stack.push(GridCell { row, column: col });
                     out[(row, col)] = tophat[(row, col)];
                 }
             } else {
                 out[(row, col)] = nodata;
             }
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Finding seed cells: {}%", progress);
+                update_progress("Finding seed cells", progress)?;
                 old_progress = progress;
             }
         }
     }
 
-    while stack.len() > 0 {
-        let gc = stack.pop().unwrap();
+    while let Some(gc) = stack.pop() {
+        
         row = gc.row;
         col = gc.column;
         z = tophat[(row, col)];
@@ -266,12 +229,11 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
             row_n = row + d_y[i];
             col_n = col + d_x[i];
             z_n = tophat[(row_n, col_n)];
-            if z_n != nodata && out[(row_n, col_n)] == initial_value {
-                if z_n - z < height_diff_threshold[i] {
+            if z_n != nodata && out[(row_n, col_n)] == initial_value
+                && z_n - z < height_diff_threshold[i] {
                     out[(row_n, col_n)] = z_n;
                     stack.push(GridCell { row: row_n, column: col_n });
                 }
-            }
         }
     }
 
@@ -291,10 +253,10 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
                 }
             }
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Finding OTO edge cells: {}%", progress);
+                update_progress("Finding OTO edge cells", progress)?;
                 old_progress = progress;
             }
         }
@@ -325,10 +287,10 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
                 out[(row, col)] = opening[(row, col)] + tophat[(row, col)];
             }
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Interpolating data holes: {}%", progress);
+                update_progress("Interpolating data holes", progress)?;
                 old_progress = progress;
             }
         }
@@ -347,10 +309,10 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
                 output.set_value(row, col, nodata);
             }
         }
-        if verbose {
+        if progress_enabled(verbose) {
             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
             if progress != old_progress {
-                println!("Outputing data: {}%", progress);
+                update_progress("Outputing data", progress)?;
                 old_progress = progress;
             }
         }
@@ -363,9 +325,9 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     output.add_metadata_entry(format!("Elapsed Time (excluding I/O): {}", elapsed_time).replace("PT", ""));
 
     if verbose { println!("Saving data...") };
-    let _ = match output.write() {
+    match output.write() {
         Ok(_) => if verbose { println!("Output file written") },
-        Err(e) => return Err(e),
+        Err(e) => return Err(e.into()),
     };
 
 
@@ -418,7 +380,7 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     //     if verbose {
     //         progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
     //         if progress != old_progress {
-    //             println!("Performing Erosion: {}%", progress);
+    //             update_progress("Performing Erosion", progress)?;
     //             old_progress = progress;
     //         }
     //     }
@@ -443,7 +405,7 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     //     if verbose {
     //         progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
     //         if progress != old_progress {
-    //             println!("Performing Dilation: {}%", progress);
+    //             update_progress("Performing Dilation", progress)?;
     //             old_progress = progress;
     //         }
     //     }
@@ -488,7 +450,7 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     //     if verbose {
     //         progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
     //         if progress != old_progress {
-    //             println!("Progress: {}%", progress);
+    //             update_progress("Progress", progress)?;
     //             old_progress = progress;
     //         }
     //     }
@@ -524,7 +486,7 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     //     if verbose {
     //         progress = (100.0_f64 * num_solved_cells as f64 / (num_cells - 1) as f64) as usize;
     //         if progress != old_progress {
-    //             println!("Progress: {}%", progress);
+    //             update_progress("Progress", progress)?;
     //             old_progress = progress;
     //         }
     //     }
@@ -558,7 +520,7 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     //     if verbose {
     //         progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
     //         if progress != old_progress {
-    //             println!("Progress: {}%", progress);
+    //             update_progress("Progress", progress)?;
     //             old_progress = progress;
     //         }
     //     }
@@ -582,7 +544,7 @@ pub fn run<'a>(args: Vec<String>, working_directory: &'a str, verbose: bool) ->
     //         if verbose {
     //             progress = (100.0_f64 * row as f64 / (rows - 1) as f64) as usize;
     //             if progress != old_progress {
-    //                 println!("Progress: {}%", progress);
+    //                 update_progress("Progress", progress)?;
     //                 old_progress = progress;
     //             }
     //         }
@@ -0,0 +1,49 @@
+#!/usr/bin/env python
+# A smoke test of the Python extension module. Build it and run the test with:
+#
+#   cargo build --lib --features python
+#   cp target/debug/libwhitebox_tools.so target/debug/whitebox.so
+#   python test_python.py target/debug
+#
+# The argument is the directory that contains whitebox.so (whitebox.pyd on Windows).
+import json
+import os
+import shutil
+import sys
+import tempfile
+
+def main():
+    sys.path.insert(0, os.path.abspath(sys.argv[1] if len(sys.argv) > 1 else "target/debug"))
+    import whitebox
+
+    tools = whitebox.list_tools()
+    assert "dev_from_mean_elev" in tools, "the tools are not listed"
+    assert "--filter" in whitebox.tool_help("dev_from_mean_elev")
+    params = json.loads(whitebox.tool_parameters("dev_from_mean_elev"))
+    assert "--input" in params["parameters"][0]["flags"]
+
+    test_data = os.path.join(os.path.dirname(os.path.abspath(__file__)), "testdata")
+    out_dir = tempfile.mkdtemp()
+    try:
+        whitebox.run_tool("dev_from_mean_elev", ["-i=" + os.path.join(test_data, "DEM.dep"), "-o=dev.dep", "--filter=3"], out_dir)
+        dem = whitebox.Raster(os.path.join(test_data, "DEM.dep"))
+        dev = whitebox.Raster(os.path.join(out_dir, "dev.dep"))
+        assert (dev.rows, dev.columns) == (dem.rows, dem.columns)
+
+        copy = whitebox.Raster.initialize_using_file(os.path.join(out_dir, "copy.dep"), dem)
+        copy.set_value(1, 2, dem.get_value(1, 2))
+        copy.write()
+        copy = whitebox.Raster(os.path.join(out_dir, "copy.dep"))
+        assert copy.get_value(1, 2) == dem.get_value(1, 2)
+        assert copy.get_value(0, 0) == copy.nodata
+
+        try:
+            whitebox.run_tool("dev_from_mean_elev", ["--filter=3"], out_dir)
+            raise AssertionError("a missing input file was not an error")
+        except ValueError:
+            pass
+    finally:
+        shutil.rmtree(out_dir)
+    print("The Python module passed the smoke test.")
+
+main()